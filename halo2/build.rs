@@ -0,0 +1,64 @@
+//! Embeds what `build_info` reports: the git commit the crate was built
+//! from, its enabled features and a digest of the circuit sources.
+
+use std::{env, fs, path::Path, process::Command};
+
+/// Sources whose contents define the constraints, hashed in this order.
+const CIRCUIT_SOURCES: &[&str] = &["src/circuit.rs", "src/compat.rs", "src/gadgets"];
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR");
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(&manifest_dir)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let commit = git(&["rev-parse", "HEAD"]).unwrap_or_default();
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        for watched in ["HEAD", "index", "refs"] {
+            println!("cargo:rerun-if-changed={git_dir}/{watched}");
+        }
+    }
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_ascii_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    let mut hasher = blake3::Hasher::new();
+    for source in CIRCUIT_SOURCES {
+        println!("cargo:rerun-if-changed={source}");
+        let path = Path::new(&manifest_dir).join(source);
+        let mut files = if path.is_dir() {
+            fs::read_dir(&path)
+                .expect("reading circuit sources")
+                .map(|entry| entry.expect("reading circuit sources").path())
+                .collect()
+        } else {
+            vec![path]
+        };
+        files.sort();
+        for file in files {
+            let contents = fs::read(&file).expect("reading circuit sources");
+            hasher.update(&(contents.len() as u64).to_le_bytes());
+            hasher.update(&contents);
+        }
+    }
+
+    println!("cargo:rustc-env=YYSFOLD_GIT_COMMIT={commit}");
+    println!("cargo:rustc-env=YYSFOLD_GIT_DIRTY={dirty}");
+    println!("cargo:rustc-env=YYSFOLD_FEATURES={}", features.join(","));
+    println!(
+        "cargo:rustc-env=YYSFOLD_CIRCUIT_SOURCE_DIGEST={}",
+        hasher.finalize().to_hex()
+    );
+}
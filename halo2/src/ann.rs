@@ -0,0 +1,535 @@
+//! Distance-threshold attestations for approximate-nearest-neighbour results.
+//!
+//! [`AnnCircuit`] proves that every returned pq-reconstructed vector lies within
+//! squared distance `threshold^2` of a query, exposing only Poseidon
+//! commitments to the query and the results plus the threshold:
+//!
+//! ```text
+//! slot 0   queryCommitment     hash(QUERY_DOMAIN, q)
+//! slot 1   resultsCommitment   hash(RESULTS_DOMAIN, leaf(r_0), .., leaf(r_k-1))
+//! slot 2   threshold^2         fixed-point, FIXED_POINT_SCALE^2 units
+//! slot 3   candidatesCommitment  hash(CANDIDATES_DOMAIN, leaf(c_0), ..)   top-k only
+//! ```
+//!
+//! With `candidates > 0` the circuit also proves a top-k claim over a
+//! disclosed candidate set: results are sorted by distance, every result is a
+//! candidate, and no remaining candidate is closer than the last result (see
+//! `gadgets::ordering`).
+//!
+//! Coordinates use the integer fixed-point encoding from
+//! [`crate::prove::float_to_fixed`], so squared distances are exact integers and the
+//! `distance <= threshold^2` check is a plain range check. Clients recompute
+//! both commitments from the query they sent and the vectors they received,
+//! which also pins every coordinate to an `i64`, so squared distances cannot
+//! wrap the field.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use halo2_proofs::{
+    circuit::{Cell, Layouter, Region, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    fixed_point::FixedPoint,
+    gadgets::{
+        distance::{DistanceChip, DistanceConfig},
+        ordering::{OrderingChip, OrderingConfig},
+        poseidon::{PoseidonChip, PoseidonConfig},
+        range::{RangeCheckChip, RangeCheckConfig},
+    },
+    keys::KeyedCircuit,
+    platform::from_json_slice,
+    poseidon::{
+        domain_capacity, hash_leaf, hash_with_capacity, CANDIDATES_DOMAIN, CHALLENGE_DOMAIN,
+        QUERY_DOMAIN, RESULTS_DOMAIN,
+    },
+    prove::{float_to_fixed, FIXED_POINT_SCALE},
+    storage::{path_key, LocalStorage, Storage},
+};
+
+pub const QUERY_COMMITMENT_SLOT: usize = 0;
+pub const RESULTS_COMMITMENT_SLOT: usize = 1;
+pub const THRESHOLD_SLOT: usize = 2;
+pub const CANDIDATES_COMMITMENT_SLOT: usize = 3;
+
+/// Bits allowed for `threshold^2 - distance`; thresholds must satisfy
+/// `threshold * FIXED_POINT_SCALE < 2^64`.
+pub const DISTANCE_BITS: usize = 128;
+
+/// Query dimension and result count fixed at keygen time.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnnParams {
+    pub dim: usize,
+    pub results: usize,
+    /// Size of the disclosed candidate set; zero for threshold-only proofs.
+    pub candidates: usize,
+}
+
+impl AnnParams {
+    pub fn top_k(&self) -> bool {
+        self.candidates > 0
+    }
+
+    pub fn instance_len(&self) -> usize {
+        3 + usize::from(self.top_k())
+    }
+}
+
+/// Private inputs of an ANN attestation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnWitness {
+    pub query: Vec<f64>,
+    /// Returned pq-reconstructed vectors, in the order served to the client.
+    pub results: Vec<Vec<f64>>,
+    /// Euclidean distance bound, in the same units as the vectors.
+    pub threshold: f64,
+    /// Disclosed candidate set for top-k proofs; results must be its closest
+    /// members, in ascending distance order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<Vec<Vec<f64>>>,
+}
+
+/// Public side of an ANN attestation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnPublicInputs {
+    pub query_commitment: Hash256,
+    pub results_commitment: Hash256,
+    pub threshold: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates_commitment: Option<Hash256>,
+}
+
+impl AnnWitness {
+    pub fn params(&self) -> AnnParams {
+        AnnParams {
+            dim: self.query.len(),
+            results: self.results.len(),
+            candidates: self.candidates.as_ref().map(Vec::len).unwrap_or(0),
+        }
+    }
+
+    pub fn public_inputs(&self) -> AnnPublicInputs {
+        AnnPublicInputs {
+            query_commitment: Hash256::from_field(&query_commitment(&self.query)),
+            results_commitment: Hash256::from_field(&results_commitment(&self.results)),
+            threshold: self.threshold,
+            candidates_commitment: self
+                .candidates
+                .as_deref()
+                .map(|candidates| Hash256::from_field(&candidates_commitment(candidates))),
+        }
+    }
+
+    /// Checks shape and that every result is within the threshold, so a bad
+    /// witness fails here rather than as an unsatisfied proof.
+    pub fn validate(&self) -> Result<()> {
+        if self.query.is_empty() || self.results.is_empty() {
+            anyhow::bail!("ANN witness needs a query and at least one result");
+        }
+        let bound = squared_threshold(self.threshold)?;
+        for (idx, result) in self.results.iter().enumerate() {
+            if result.len() != self.query.len() {
+                anyhow::bail!(
+                    "result {idx} has {} components, query has {}",
+                    result.len(),
+                    self.query.len()
+                );
+            }
+            let distance = fixed_squared_distance(&self.query, result);
+            if distance > bound {
+                anyhow::bail!(
+                    "result {idx} is {:.6} from the query, above threshold {}",
+                    (distance as f64).sqrt() / FIXED_POINT_SCALE,
+                    self.threshold
+                );
+            }
+        }
+        if self.candidates.is_some() {
+            self.top_k_remainder()?;
+        }
+        Ok(())
+    }
+
+    /// Candidate indices not returned as results, after checking the top-k
+    /// claim: results are distinct candidates, sorted by distance, and no
+    /// remaining candidate is closer than the last result.
+    pub fn top_k_remainder(&self) -> Result<Vec<usize>> {
+        let candidates = self
+            .candidates
+            .as_deref()
+            .context("top-k witness missing candidates")?;
+        let fixed: Vec<Vec<Fr>> = candidates.iter().map(|row| to_fixed_vector(row)).collect();
+        let mut used = vec![false; candidates.len()];
+        let mut previous = 0u128;
+        for (idx, result) in self.results.iter().enumerate() {
+            let result_fixed = to_fixed_vector(result);
+            let position = fixed
+                .iter()
+                .enumerate()
+                .position(|(candidate, row)| !used[candidate] && *row == result_fixed)
+                .with_context(|| format!("result {idx} is not in the candidate set"))?;
+            used[position] = true;
+            let distance = fixed_squared_distance(&self.query, result);
+            if distance < previous {
+                anyhow::bail!(
+                    "result {idx} is closer than result {}; results must be sorted",
+                    idx - 1
+                );
+            }
+            previous = distance;
+        }
+        let remainder: Vec<usize> = (0..candidates.len()).filter(|idx| !used[*idx]).collect();
+        for idx in &remainder {
+            if candidates[*idx].len() != self.query.len() {
+                anyhow::bail!("candidate {idx} has {} components", candidates[*idx].len());
+            }
+            if fixed_squared_distance(&self.query, &candidates[*idx]) < previous {
+                anyhow::bail!("candidate {idx} is closer than the last returned result");
+            }
+        }
+        Ok(remainder)
+    }
+}
+
+impl AnnPublicInputs {
+    /// Instance column values for a circuit keyed with `params`.
+    pub fn to_instances(&self, params: &AnnParams) -> Result<Vec<Fr>> {
+        let bound = squared_threshold(self.threshold)?;
+        let mut instances = vec![
+            self.query_commitment.to_canonical_field()?,
+            self.results_commitment.to_canonical_field()?,
+            Fr::from_u128(bound),
+        ];
+        if params.top_k() {
+            let commitment = self
+                .candidates_commitment
+                .context("ANN public inputs missing candidatesCommitment")?;
+            instances.push(commitment.to_canonical_field()?);
+        }
+        debug_assert_eq!(instances.len(), params.instance_len());
+        Ok(instances)
+    }
+
+    /// Recomputes both commitments from what the client actually sent and received.
+    pub fn check_against(&self, query: &[f64], results: &[Vec<f64>]) -> Result<()> {
+        if self.query_commitment.to_canonical_field()? != query_commitment(query) {
+            anyhow::bail!("queryCommitment does not match the query");
+        }
+        if self.results_commitment.to_canonical_field()? != results_commitment(results) {
+            anyhow::bail!("resultsCommitment does not match the returned vectors");
+        }
+        Ok(())
+    }
+
+    pub fn check_candidates(&self, candidates: &[Vec<f64>]) -> Result<()> {
+        let commitment = self
+            .candidates_commitment
+            .context("ANN public inputs missing candidatesCommitment")?;
+        if commitment.to_canonical_field()? != candidates_commitment(candidates) {
+            anyhow::bail!("candidatesCommitment does not match the disclosed candidates");
+        }
+        Ok(())
+    }
+}
+
+pub fn load_ann_witness(path: &Path) -> Result<AnnWitness> {
+    load_ann_witness_from(&LocalStorage::default(), &path_key(path))
+}
+
+pub fn load_ann_public_inputs(path: &Path) -> Result<AnnPublicInputs> {
+    load_ann_public_inputs_from(&LocalStorage::default(), &path_key(path))
+}
+
+pub fn load_ann_witness_from(storage: &dyn Storage, key: &str) -> Result<AnnWitness> {
+    let bytes = storage.read(key)?;
+    from_json_slice(&bytes).with_context(|| format!("parsing ANN witness {key}"))
+}
+
+pub fn load_ann_public_inputs_from(storage: &dyn Storage, key: &str) -> Result<AnnPublicInputs> {
+    let bytes = storage.read(key)?;
+    from_json_slice(&bytes).with_context(|| format!("parsing ANN public inputs {key}"))
+}
+
+pub fn to_fixed_vector(values: &[f64]) -> Vec<Fr> {
+    values.iter().map(|value| float_to_fixed(*value)).collect()
+}
+
+pub fn query_commitment(query: &[f64]) -> Fr {
+    let fields = to_fixed_vector(query);
+    hash_with_capacity(domain_capacity(QUERY_DOMAIN, fields.len()), &fields)
+}
+
+pub fn results_commitment(results: &[Vec<f64>]) -> Fr {
+    let leaves: Vec<Fr> = results
+        .iter()
+        .map(|row| hash_leaf(&to_fixed_vector(row)))
+        .collect();
+    hash_with_capacity(domain_capacity(RESULTS_DOMAIN, leaves.len()), &leaves)
+}
+
+pub fn candidates_commitment(candidates: &[Vec<f64>]) -> Fr {
+    let leaves: Vec<Fr> = candidates
+        .iter()
+        .map(|row| hash_leaf(&to_fixed_vector(row)))
+        .collect();
+    hash_with_capacity(domain_capacity(CANDIDATES_DOMAIN, leaves.len()), &leaves)
+}
+
+/// `floor(threshold * FIXED_POINT_SCALE)^2`, the bound the circuit checks against.
+pub fn squared_threshold(threshold: f64) -> Result<u128> {
+    FixedPoint::default().squared_threshold(threshold)
+}
+
+/// Squared distance in fixed-point units, as the circuit computes it.
+pub fn fixed_squared_distance(a: &[f64], b: &[f64]) -> u128 {
+    FixedPoint::default().squared_distance(a, b)
+}
+
+#[derive(Clone, Debug)]
+pub struct AnnConfig {
+    instance: Column<Instance>,
+    public: Column<Advice>,
+    distance: DistanceConfig,
+    range: RangeCheckConfig,
+    poseidon: PoseidonConfig,
+    ordering: Option<OrderingConfig>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AnnCircuit {
+    pub params: AnnParams,
+    pub query: Vec<Fr>,
+    pub results: Vec<Vec<Fr>>,
+    /// Top-k only: the disclosed candidate set.
+    pub candidates: Vec<Vec<Fr>>,
+    /// Top-k only: candidates that were not returned, in any order.
+    pub remainder: Vec<Vec<Fr>>,
+    pub instances: Vec<Fr>,
+}
+
+impl AnnCircuit {
+    pub fn blank(params: &AnnParams) -> Self {
+        Self {
+            params: params.clone(),
+            query: vec![Fr::zero(); params.dim],
+            results: vec![vec![Fr::zero(); params.dim]; params.results],
+            candidates: vec![vec![Fr::zero(); params.dim]; params.candidates],
+            remainder: vec![
+                vec![Fr::zero(); params.dim];
+                params.candidates.saturating_sub(params.results)
+            ],
+            instances: vec![Fr::zero(); params.instance_len()],
+        }
+    }
+
+    pub fn from_witness(witness: &AnnWitness) -> Result<Self> {
+        witness.validate()?;
+        let params = witness.params();
+        let candidates = witness.candidates.as_deref().unwrap_or_default();
+        let remainder = if params.top_k() {
+            witness
+                .top_k_remainder()?
+                .into_iter()
+                .map(|idx| to_fixed_vector(&candidates[idx]))
+                .collect()
+        } else {
+            vec![]
+        };
+        Ok(Self {
+            query: to_fixed_vector(&witness.query),
+            results: witness
+                .results
+                .iter()
+                .map(|row| to_fixed_vector(row))
+                .collect(),
+            candidates: candidates.iter().map(|row| to_fixed_vector(row)).collect(),
+            remainder,
+            instances: witness.public_inputs().to_instances(&params)?,
+            params,
+        })
+    }
+}
+
+impl KeyedCircuit for AnnCircuit {
+    type Shape = AnnParams;
+
+    fn shape(&self) -> &AnnParams {
+        &self.params
+    }
+}
+
+impl Circuit<Fr> for AnnCircuit {
+    type Config = AnnConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = AnnParams;
+
+    fn without_witnesses(&self) -> Self {
+        Self::blank(&self.params)
+    }
+
+    fn params(&self) -> Self::Params {
+        self.params.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        Self::configure_with_params(meta, AnnParams::default())
+    }
+
+    fn configure_with_params(meta: &mut ConstraintSystem<Fr>, params: AnnParams) -> Self::Config {
+        let instance = meta.instance_column();
+        let public = meta.advice_column();
+        meta.enable_equality(instance);
+        meta.enable_equality(public);
+        let range = RangeCheckChip::configure(meta);
+        let ordering = params
+            .top_k()
+            .then(|| OrderingChip::configure(meta, range.clone()));
+        AnnConfig {
+            instance,
+            public,
+            distance: DistanceChip::configure(meta),
+            range,
+            poseidon: PoseidonChip::configure(meta),
+            ordering,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        if self.query.len() != self.params.dim
+            || self.results.len() != self.params.results
+            || self.instances.len() != self.params.instance_len()
+        {
+            return Err(Error::Synthesis);
+        }
+        let distance = DistanceChip::construct(config.distance.clone());
+        let range = RangeCheckChip::construct(config.range.clone());
+        let poseidon = PoseidonChip::construct(config.poseidon.clone());
+
+        let threshold = layouter.assign_region(
+            || "ann_threshold",
+            |mut region: Region<'_, Fr>| {
+                let cell = region.assign_advice_from_instance(
+                    || "threshold",
+                    config.instance,
+                    THRESHOLD_SLOT,
+                    config.public,
+                    0,
+                )?;
+                Ok((cell.cell(), self.instances[THRESHOLD_SLOT]))
+            },
+        )?;
+
+        let query = distance.assign_vector(&mut layouter, "ann_query", &self.query)?;
+        let mut leaves = Vec::with_capacity(self.results.len());
+        let mut distances = Vec::with_capacity(self.results.len());
+        for result in &self.results {
+            let result = distance.assign_vector(&mut layouter, "ann_result", result)?;
+            let squared = distance.squared_distance(&mut layouter, &query, &result)?;
+            range.assert_less_or_equal(&mut layouter, squared, threshold, DISTANCE_BITS)?;
+            leaves.push(poseidon.hash_leaf(&mut layouter, &result)?);
+            distances.push(squared);
+        }
+
+        let capacity = domain_capacity(QUERY_DOMAIN, query.len());
+        let (query_commitment, _) = poseidon.hash(&mut layouter, capacity, &query)?;
+        constrain_to_instance(
+            &mut layouter,
+            &config,
+            query_commitment,
+            QUERY_COMMITMENT_SLOT,
+        )?;
+
+        let capacity = domain_capacity(RESULTS_DOMAIN, leaves.len());
+        let results_commitment = poseidon.hash(&mut layouter, capacity, &leaves)?;
+        constrain_to_instance(
+            &mut layouter,
+            &config,
+            results_commitment.0,
+            RESULTS_COMMITMENT_SLOT,
+        )?;
+
+        let Some(ordering) = &config.ordering else {
+            return Ok(());
+        };
+        if self.candidates.len() != self.params.candidates
+            || self.remainder.len() + self.results.len() != self.candidates.len()
+        {
+            return Err(Error::Synthesis);
+        }
+        let ordering = OrderingChip::construct(ordering.clone());
+        ordering.assert_sorted(&mut layouter, &distances, DISTANCE_BITS)?;
+
+        let mut candidate_leaves = Vec::with_capacity(self.candidates.len());
+        for candidate in &self.candidates {
+            let candidate = distance.assign_vector(&mut layouter, "ann_candidate", candidate)?;
+            candidate_leaves.push(poseidon.hash_leaf(&mut layouter, &candidate)?);
+        }
+        let capacity = domain_capacity(CANDIDATES_DOMAIN, candidate_leaves.len());
+        let candidates_commitment = poseidon.hash(&mut layouter, capacity, &candidate_leaves)?;
+        constrain_to_instance(
+            &mut layouter,
+            &config,
+            candidates_commitment.0,
+            CANDIDATES_COMMITMENT_SLOT,
+        )?;
+
+        let mut remainder_leaves = Vec::with_capacity(self.remainder.len());
+        let mut remainder_distances = Vec::with_capacity(self.remainder.len());
+        for other in &self.remainder {
+            let other = distance.assign_vector(&mut layouter, "ann_remainder", other)?;
+            remainder_distances.push(distance.squared_distance(&mut layouter, &query, &other)?);
+            remainder_leaves.push(poseidon.hash_leaf(&mut layouter, &other)?);
+        }
+        if let Some(last) = distances.last() {
+            ordering.assert_all_at_least(
+                &mut layouter,
+                &remainder_distances,
+                *last,
+                DISTANCE_BITS,
+            )?;
+        }
+
+        // Fiat-Shamir challenge bound to both sides of the permutation.
+        let mut transcript = vec![candidates_commitment, results_commitment];
+        transcript.extend_from_slice(&remainder_leaves);
+        let capacity = domain_capacity(CHALLENGE_DOMAIN, transcript.len());
+        let gamma = poseidon.hash(&mut layouter, capacity, &transcript)?;
+        let mut returned = leaves;
+        returned.extend(remainder_leaves);
+        ordering.assert_permutation(&mut layouter, &candidate_leaves, &returned, gamma)
+    }
+}
+
+fn constrain_to_instance(
+    layouter: &mut impl Layouter<Fr>,
+    config: &AnnConfig,
+    cell: Cell,
+    slot: usize,
+) -> Result<(), Error> {
+    layouter.assign_region(
+        || format!("ann_instance_{slot}"),
+        |mut region: Region<'_, Fr>| {
+            let public = region.assign_advice_from_instance(
+                || "instance_public",
+                config.instance,
+                slot,
+                config.public,
+                0,
+            )?;
+            region.constrain_equal(cell, public.cell());
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,320 @@
+//! Append-only, hash-chained audit log of prove, verify and keygen operations.
+//!
+//! Each operation appends one JSON line:
+//!
+//! ```json
+//! {"seq":3,"timestamp":1760000000,"operation":"prove","inputs":{"witness":".."},
+//!  "keyFingerprint":"..","durationMs":812,"outcome":"ok","prevHash":"..","hash":".."}
+//! ```
+//!
+//! `hash` is blake3 over `prevHash` and the compact JSON of the entry without
+//! `hash`; the first entry chains from [`GENESIS_HASH`]. Editing, dropping or
+//! reordering lines breaks the chain, which [`verify_chain`] reports. Only the
+//! tail is checked when a log is reopened for appending.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Operation {
+    Keygen,
+    Prove,
+    Verify,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Keygen => "keygen",
+            Operation::Prove => "prove",
+            Operation::Verify => "verify",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Ok,
+    Error,
+}
+
+/// What an operation ran on: blake3 digests of its inputs by name, and the
+/// fingerprint of the key config it used.
+#[derive(Debug, Clone, Default)]
+pub struct Subject {
+    pub inputs: BTreeMap<String, String>,
+    pub key_fingerprint: Option<String>,
+}
+
+impl Subject {
+    pub fn input(mut self, name: &str, bytes: &[u8]) -> Self {
+        self.inputs.insert(name.to_string(), digest(bytes));
+        self
+    }
+
+    /// Digests a file's contents, recording the read error instead if it cannot be opened.
+    pub fn input_file(mut self, name: &str, path: &Path) -> Self {
+        let value = match std::fs::read(path) {
+            Ok(bytes) => digest(&bytes),
+            Err(err) => format!("unreadable: {err}"),
+        };
+        self.inputs.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn key(mut self, fingerprint: Option<String>) -> Self {
+        self.key_fingerprint = fingerprint;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryBody {
+    seq: u64,
+    timestamp: u64,
+    operation: Operation,
+    inputs: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_fingerprint: Option<String>,
+    duration_ms: u64,
+    outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    prev_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    body: EntryBody,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    pub fn seq(&self) -> u64 {
+        self.body.seq
+    }
+
+    pub fn operation(&self) -> Operation {
+        self.body.operation
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.body.outcome
+    }
+
+    fn expected_hash(&self) -> Result<String> {
+        chain_hash(&self.body)
+    }
+}
+
+struct Chain {
+    file: File,
+    next_seq: u64,
+    last_hash: String,
+}
+
+/// An open audit log; appends are serialized so one log can be shared by threads.
+pub struct AuditLog {
+    path: PathBuf,
+    chain: Mutex<Chain>,
+}
+
+impl AuditLog {
+    /// Opens `path` for appending, creating it if needed. Fails if the last
+    /// entry does not hash to its recorded value.
+    pub fn open(path: &Path) -> Result<Self> {
+        let (next_seq, last_hash) = match read_last_entry(path)? {
+            Some(entry) => {
+                if entry.expected_hash()? != entry.hash {
+                    anyhow::bail!(
+                        "audit log {:?} entry {} does not match its hash",
+                        path,
+                        entry.seq()
+                    );
+                }
+                (entry.seq() + 1, entry.hash)
+            }
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening {:?}", path))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            chain: Mutex::new(Chain {
+                file,
+                next_seq,
+                last_hash,
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends one entry and syncs it to disk.
+    pub fn append(
+        &self,
+        operation: Operation,
+        subject: Subject,
+        duration_ms: u64,
+        error: Option<String>,
+    ) -> Result<AuditEntry> {
+        let mut chain = self
+            .chain
+            .lock()
+            .map_err(|_| anyhow::anyhow!("audit log lock poisoned"))?;
+        let body = EntryBody {
+            seq: chain.next_seq,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            operation,
+            inputs: subject.inputs,
+            key_fingerprint: subject.key_fingerprint,
+            duration_ms,
+            outcome: if error.is_some() {
+                Outcome::Error
+            } else {
+                Outcome::Ok
+            },
+            error,
+            prev_hash: chain.last_hash.clone(),
+        };
+        let entry = AuditEntry {
+            hash: chain_hash(&body)?,
+            body,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        chain
+            .file
+            .write_all(&line)
+            .and_then(|_| chain.file.sync_data())
+            .with_context(|| format!("appending to {:?}", self.path))?;
+        chain.next_seq += 1;
+        chain.last_hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    /// Runs `op`, timing it and appending its outcome. `subject` is built
+    /// after `op` returns, so keygen can fingerprint the key it just created.
+    /// A failure to write the entry fails the call, so nothing runs unrecorded.
+    pub fn observe<T>(
+        &self,
+        operation: Operation,
+        subject: impl FnOnce() -> Subject,
+        op: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let result = op();
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let error = result.as_ref().err().map(|err| format!("{err:#}"));
+        self.append(operation, subject(), duration_ms, error)?;
+        result
+    }
+}
+
+/// [`AuditLog::observe`] when auditing is configured, otherwise just `op`.
+pub fn audited<T>(
+    log: Option<&AuditLog>,
+    operation: Operation,
+    subject: impl FnOnce() -> Subject,
+    op: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    match log {
+        Some(log) => log.observe(operation, subject, op),
+        None => op(),
+    }
+}
+
+pub fn open_optional(path: Option<&Path>) -> Result<Option<AuditLog>> {
+    path.map(AuditLog::open).transpose()
+}
+
+/// Checks every entry's hash, sequence number and link to its predecessor.
+/// Returns the number of entries.
+pub fn verify_chain(path: &Path) -> Result<u64> {
+    let file = File::open(path).with_context(|| format!("opening {:?}", path))?;
+    let mut expected_prev = GENESIS_HASH.to_string();
+    let mut count = 0u64;
+    for (line_no, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("reading {:?}", path))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: AuditEntry = serde_json::from_str(&line)
+            .with_context(|| format!("parsing audit entry on line {}", line_no + 1))?;
+        if entry.seq() != count {
+            anyhow::bail!(
+                "line {}: expected seq {count}, found {}",
+                line_no + 1,
+                entry.seq()
+            );
+        }
+        if entry.body.prev_hash != expected_prev {
+            anyhow::bail!(
+                "line {}: chain broken, prevHash {} does not match {}",
+                line_no + 1,
+                entry.body.prev_hash,
+                expected_prev
+            );
+        }
+        if entry.expected_hash()? != entry.hash {
+            anyhow::bail!("line {}: entry does not match its hash", line_no + 1);
+        }
+        expected_prev = entry.hash;
+        count += 1;
+    }
+    Ok(count)
+}
+
+pub fn digest(bytes: &[u8]) -> String {
+    digest_hex(bytes)
+}
+
+fn chain_hash(body: &EntryBody) -> Result<String> {
+    let mut hasher = Blake3Hasher::new();
+    hasher.update(body.prev_hash.as_bytes());
+    hasher.update(&serde_json::to_vec(body)?);
+    Ok(hex::encode(hasher.finalize().as_bytes()))
+}
+
+fn read_last_entry(path: &Path) -> Result<Option<AuditEntry>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = File::open(path).with_context(|| format!("opening {:?}", path))?;
+    let mut last = None;
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("reading {:?}", path))?;
+        if !line.trim().is_empty() {
+            last = Some(line);
+        }
+    }
+    last.map(|line| {
+        serde_json::from_str(&line).with_context(|| format!("parsing last entry of {:?}", path))
+    })
+    .transpose()
+}
@@ -0,0 +1,246 @@
+//! Proving a range of historical blocks for a new prover deployment.
+//!
+//! Blocks are keyed by height: the witness is read from
+//! `<height>.witness.json` in the artifact store, and the proof is written
+//! to `<height>.proof` with its `.meta.json` sidecar and the public inputs it
+//! was proven against as `<height>.public.json`. Heights that already have a
+//! proof are skipped. Public inputs come from a [`PublicInputsSource`],
+//! normally the node's JSON-RPC endpoint via [`RpcSource`], or one file
+//! holding an array of blocks via [`FileSource`]. Heights that cannot be
+//! proven are reported as gaps instead of stopping the run.
+
+use std::{collections::BTreeMap, ops::RangeInclusive, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cancel::CancellationToken,
+    errors::{Coded, ErrorCode},
+    http::{post_json, Limits},
+    io::load_witness_from,
+    metadata::ProofMetadata,
+    platform::from_json_slice,
+    prover::Prover,
+    public_inputs::{load_public_inputs_batch, ParsedPublicInputs},
+    storage::Storage,
+};
+
+/// JSON-RPC method [`RpcSource`] calls unless told otherwise.
+pub const DEFAULT_RPC_METHOD: &str = "yysfold_getPublicInputs";
+
+/// Where backfill gets each block's public inputs.
+pub trait PublicInputsSource {
+    /// The public inputs of block `height`, or `None` if the source has no
+    /// such block yet.
+    fn public_inputs(&self, height: u64) -> Result<Option<ParsedPublicInputs>>;
+}
+
+/// Calls `method` with `[height]` on a JSON-RPC 2.0 endpoint; a `null`
+/// result means the block is unknown.
+#[derive(Debug, Clone)]
+pub struct RpcSource {
+    pub url: String,
+    pub method: String,
+    pub limits: Limits,
+}
+
+impl RpcSource {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            method: DEFAULT_RPC_METHOD.to_string(),
+            limits: Limits::default(),
+        }
+    }
+
+    pub fn with_method(self, method: &str) -> Self {
+        Self {
+            method: method.to_string(),
+            ..self
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    result: Option<ParsedPublicInputs>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl PublicInputsSource for RpcSource {
+    fn public_inputs(&self, height: u64) -> Result<Option<ParsedPublicInputs>> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": height,
+            "method": self.method,
+            "params": [height],
+        });
+        let body = post_json(&self.url, &serde_json::to_vec(&request)?, &self.limits)?;
+        let response: RpcResponse = from_json_slice(&body)
+            .with_context(|| format!("parsing {} response for block {height}", self.method))?;
+        if let Some(error) = response.error {
+            anyhow::bail!(
+                "{} failed for block {height}: {} ({})",
+                self.method,
+                error.message,
+                error.code
+            );
+        }
+        Ok(response.result)
+    }
+}
+
+/// Public inputs of many blocks from one file, as a JSON array of blocks
+/// (or a single block), keyed by `blockHeight`.
+#[derive(Debug, Clone, Default)]
+pub struct FileSource {
+    blocks: BTreeMap<u64, ParsedPublicInputs>,
+}
+
+impl FileSource {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::new(load_public_inputs_batch(path)?)
+    }
+
+    /// Fails when two entries claim the same height.
+    pub fn new(blocks: Vec<ParsedPublicInputs>) -> Result<Self> {
+        let mut by_height = BTreeMap::new();
+        for block in blocks {
+            let height = block.block_height;
+            if by_height.insert(height, block).is_some() {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::InvalidInput,
+                    format!("public inputs list block {height} more than once")
+                ));
+            }
+        }
+        Ok(Self { blocks: by_height })
+    }
+
+    /// Lowest to highest height listed, if any.
+    pub fn heights(&self) -> Option<RangeInclusive<u64>> {
+        let first = *self.blocks.keys().next()?;
+        let last = *self.blocks.keys().next_back()?;
+        Some(first..=last)
+    }
+}
+
+impl PublicInputsSource for FileSource {
+    fn public_inputs(&self, height: u64) -> Result<Option<ParsedPublicInputs>> {
+        Ok(self.blocks.get(&height).cloned())
+    }
+}
+
+/// Artifact keys of block `height` under `prefix`.
+pub fn block_key(prefix: &str, height: u64, suffix: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        format!("{height}{suffix}")
+    } else {
+        format!("{prefix}/{height}{suffix}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gap {
+    pub height: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillReport {
+    /// Heights proven by this run.
+    pub proved: Vec<u64>,
+    /// Heights that already had a proof.
+    pub existing: Vec<u64>,
+    /// Heights still without a proof, and why.
+    pub gaps: Vec<Gap>,
+}
+
+pub struct Backfill<'a> {
+    pub witnesses: &'a dyn Storage,
+    pub witness_prefix: String,
+    pub proofs: &'a dyn Storage,
+    pub proof_prefix: String,
+    pub source: &'a dyn PublicInputsSource,
+}
+
+impl Backfill<'_> {
+    /// Proves every height in `heights` without a proof, in order. With no
+    /// `prover`, nothing is proven and missing heights are reported as gaps.
+    pub fn run(
+        &self,
+        heights: RangeInclusive<u64>,
+        prover: Option<&Prover>,
+        cancel: &CancellationToken,
+    ) -> Result<BackfillReport> {
+        let mut report = BackfillReport::default();
+        for height in heights {
+            cancel.check("backfill")?;
+            if self
+                .proofs
+                .exists(&block_key(&self.proof_prefix, height, ".proof"))?
+            {
+                report.existing.push(height);
+                continue;
+            }
+            match self.prove_height(height, prover, cancel) {
+                Ok(()) => report.proved.push(height),
+                Err(err) if cancel.is_cancelled() => return Err(err),
+                Err(err) => report.gaps.push(Gap {
+                    height,
+                    reason: format!("{err:#}"),
+                }),
+            }
+        }
+        Ok(report)
+    }
+
+    fn prove_height(
+        &self,
+        height: u64,
+        prover: Option<&Prover>,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        let witness_key = block_key(&self.witness_prefix, height, ".witness.json");
+        if !self.witnesses.exists(&witness_key)? {
+            anyhow::bail!("no witness at {witness_key:?}");
+        }
+        let public_inputs = self
+            .source
+            .public_inputs(height)?
+            .context("no public inputs for this height")?;
+        if public_inputs.block_height != height {
+            anyhow::bail!(
+                "source returned public inputs for block {}",
+                public_inputs.block_height
+            );
+        }
+        let Some(prover) = prover else {
+            anyhow::bail!("not proven (dry run)");
+        };
+        let witness = load_witness_from(self.witnesses, &witness_key)?;
+        let output = prover.prove_with(&witness, &public_inputs, cancel)?;
+        let proof_key = block_key(&self.proof_prefix, height, ".proof");
+        self.proofs.write(
+            &block_key(&self.proof_prefix, height, ".public.json"),
+            &serde_json::to_vec_pretty(&public_inputs)?,
+        )?;
+        self.proofs.write(
+            &format!("{proof_key}.meta.json"),
+            &serde_json::to_vec_pretty(&ProofMetadata::from(output.metadata))?,
+        )?;
+        self.proofs.write(&proof_key, &output.proof)
+    }
+}
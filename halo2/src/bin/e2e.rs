@@ -0,0 +1,152 @@
+use std::{fs, path::PathBuf, time::Instant};
+
+use anyhow::Result;
+use clap::Parser;
+
+use folding_halo2::{
+    circuit::FoldedCircuit,
+    codebook::CommitMode,
+    keys::{load_or_init_keys, load_params_and_vk},
+    prove::{build_circuit, circuit_params, create_folded_proof, CircuitModes},
+    quantization::EmbeddingPreset,
+    synthetic::{generate, SyntheticConfig},
+    verify::verify_with_keys,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Generate a synthetic block, then keygen, prove and verify it end to end",
+    long_about = "Generate a synthetic block, then keygen, prove and verify it end to end. \
+                  There is no aggregation step: this crate has no aggregation circuit, \
+                  so each block proof is verified on its own."
+)]
+struct Args {
+    #[arg(long = "out-dir", default_value = "e2e-output")]
+    out_dir: PathBuf,
+    #[arg(long, default_value_t = 8)]
+    vectors: usize,
+    #[arg(long, default_value_t = 16)]
+    dim: usize,
+    #[arg(long, default_value_t = 4)]
+    subvectors: usize,
+    #[arg(long, default_value_t = 4)]
+    centroids: usize,
+    /// Embedding preset (minilm-384, mpnet-768, openai-small-1536, openai-large-3072);
+    /// overrides --dim and --subvectors, keeps --centroids
+    #[arg(long)]
+    preset: Option<EmbeddingPreset>,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    #[arg(long = "circuit-k", default_value_t = 12)]
+    circuit_k: u32,
+    /// Also constrain the Poseidon Merkle root over the folded vectors
+    #[arg(long = "vector-root")]
+    vector_root: bool,
+    /// Also commit to the PQ codes and look them up in the codebook
+    #[arg(long = "pq-codes")]
+    pq_codes: bool,
+    /// Recompute codebookRoot in-circuit (merkle or poseidon); needs --pq-codes
+    #[arg(long = "codebook-commitment", requires = "pq_codes")]
+    codebook_commitment: Option<CommitMode>,
+    /// Expose sha256(public values) mod p as the single instance
+    #[arg(long = "instance-hash")]
+    instance_hash: bool,
+    /// Also expose the previous block's proof digest as an instance
+    #[arg(long)]
+    lineage: bool,
+    /// Also expose a randomness beacon value as an instance
+    #[arg(long)]
+    beacon: bool,
+    /// Also expose the block's PQ compression stats; needs --pq-codes
+    #[arg(long = "compression-stats", requires = "pq_codes")]
+    compression_stats: bool,
+    /// Also bound each PQ subvector's residual by a committed epsilon; needs --pq-codes
+    #[arg(long = "subvector-epsilons", requires = "pq_codes")]
+    subvector_epsilons: bool,
+    /// Also bound every vector's residual by a committed SLA bound
+    #[arg(long = "sla-bound")]
+    sla_bound: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    fs::create_dir_all(&args.out_dir)?;
+
+    let (dim, subvectors) = match args.preset {
+        Some(preset) => (preset.dim(), preset.shape().subvectors),
+        None => (args.dim, args.subvectors),
+    };
+
+    let started = Instant::now();
+    let block = generate(&SyntheticConfig {
+        vectors: args.vectors,
+        dim,
+        subvectors,
+        centroids: args.centroids,
+        seed: args.seed,
+        codebook_mode: args.codebook_commitment.unwrap_or(CommitMode::Merkle),
+        ..SyntheticConfig::default()
+    })?;
+    let witness_path = args.out_dir.join("witness.json");
+    let public_inputs_path = args.out_dir.join("public_inputs.json");
+    fs::write(&witness_path, serde_json::to_vec_pretty(&block.witness)?)?;
+    fs::write(
+        &public_inputs_path,
+        serde_json::to_vec_pretty(&block.public_inputs)?,
+    )?;
+    println!(
+        "generated {}x{} block in {:?} -> {:?}",
+        args.vectors,
+        dim,
+        started.elapsed(),
+        args.out_dir
+    );
+
+    let params = circuit_params(
+        &block.witness,
+        CircuitModes {
+            vector_root: args.vector_root,
+            pq_codes: args.pq_codes,
+            codebook_commitment: args.codebook_commitment,
+            instance_hash: args.instance_hash,
+            lineage: args.lineage,
+            beacon: args.beacon,
+            sparse: false,
+            compression_stats: args.compression_stats,
+            subvector_epsilons: args.subvector_epsilons,
+            delta: None,
+            witness_commitment: false,
+            rotation: false,
+            residual_stage: false,
+            scalar: false,
+            row_permutation: false,
+            hiding: false,
+            vector_commitments: false,
+            sla_bound: args.sla_bound,
+            scale: None,
+        },
+    )?;
+    let circuit = build_circuit(&block.witness, &block.public_inputs, &params)?;
+    let blank = FoldedCircuit::blank_with(&params);
+    let proving_key = args.out_dir.join("proving_key.json");
+    let verification_key = args.out_dir.join("verification_key.json");
+
+    let started = Instant::now();
+    let (params, pk) = load_or_init_keys(&proving_key, &verification_key, args.circuit_k, &blank)?;
+    println!("keygen (k={}) in {:?}", args.circuit_k, started.elapsed());
+
+    let started = Instant::now();
+    let proof = create_folded_proof(&params, &pk, &circuit)?;
+    let proof_path = args.out_dir.join("proof.bin");
+    fs::write(&proof_path, &proof)?;
+    println!("proved in {:?} ({} bytes)", started.elapsed(), proof.len());
+
+    let started = Instant::now();
+    let (params, vk) = load_params_and_vk(&verification_key, &blank)?;
+    verify_with_keys(&params, &vk, &circuit.public_inputs, &proof)?;
+    println!("verified in {:?}", started.elapsed());
+
+    println!("e2e pipeline succeeded");
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+
+use folding_halo2::{
+    build_info::exit_if_version_json,
+    circuit::{FoldedCircuit, FoldedParams},
+    errors::exit_on_error,
+    keygen::{set_keygen_progress, set_keygen_threads, stderr_progress},
+    keys::generate_keys_in,
+    platform::from_json_slice,
+    srs::load_srs,
+    storage::{path_key, LocalStorage},
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Generate a key pair once and serialize it, so provers and verifiers skip keygen"
+)]
+struct Args {
+    /// Key config to create; the proving key is written next to it as `.pk`
+    #[arg(long = "proving-key")]
+    proving_key: PathBuf,
+    /// Key config to create; the verifying key is written next to it as `.vk`
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    #[arg(long = "circuit-k", default_value_t = 12)]
+    circuit_k: u32,
+    /// Circuit layout as JSON (the `circuit` of a key config), keyed to a
+    /// witness shape
+    #[arg(long)]
+    layout: PathBuf,
+    /// Trusted setup (halo2 params or a `.ptau` file) to build the keys on
+    /// instead of a fresh config seed
+    #[arg(long)]
+    params: Option<PathBuf>,
+    /// Worker threads for key generation (defaults to all cores)
+    #[arg(long = "keygen-threads")]
+    keygen_threads: Option<usize>,
+    /// Print the build (crate version, git commit, features, circuit hash)
+    /// as JSON and exit
+    #[arg(long = "version-json")]
+    _version_json: bool,
+}
+
+fn main() {
+    exit_if_version_json();
+    exit_on_error(run());
+}
+
+fn run() -> Result<()> {
+    let args = Args::parse();
+    set_keygen_threads(args.keygen_threads);
+    set_keygen_progress(Some(stderr_progress()), Duration::from_secs(10));
+
+    let bytes = fs::read(&args.layout).with_context(|| format!("opening {:?}", args.layout))?;
+    let layout: FoldedParams =
+        from_json_slice(&bytes).with_context(|| format!("parsing layout {:?}", args.layout))?;
+    let setup = args
+        .params
+        .as_deref()
+        .map(|path| load_srs(path, args.circuit_k))
+        .transpose()?;
+    let storage = LocalStorage::default();
+    let keys = generate_keys_in(
+        &storage,
+        &path_key(&args.proving_key),
+        &path_key(&args.verification_key),
+        args.circuit_k,
+        &FoldedCircuit::blank_with(&layout),
+        setup.as_ref(),
+    )?;
+    let artifacts = serde_json::json!({
+        "provingKey": keys.proving_artifacts,
+        "verificationKey": keys.verifying_artifacts,
+    });
+    keys.commit(&storage)?;
+    eprintln!(
+        "wrote k={} keys for {:?} and {:?}",
+        args.circuit_k, args.proving_key, args.verification_key
+    );
+    println!("{}", serde_json::to_string_pretty(&artifacts)?);
+    Ok(())
+}
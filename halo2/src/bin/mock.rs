@@ -1,11 +1,16 @@
 use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 use halo2_proofs::dev::MockProver;
-use halo2curves::bn256::Fr;
 
-use folding_halo2::{circuit::FoldedCircuit, io::load_witness, load_public_inputs};
+use folding_halo2::{
+    circuit::FoldedCircuit,
+    io::load_witness,
+    load_public_inputs,
+    prove::{circuit_params, claimed_residuals, CircuitModes},
+    shape::{ensure_rows, WitnessShape},
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about = "Mock prover for folded circuit")]
@@ -16,18 +21,34 @@ struct Args {
     public_inputs: PathBuf,
     #[arg(long = "circuit-k", default_value_t = 12)]
     circuit_k: u32,
+    /// Fixed-point scale of the witness values; defaults to the witness's
+    /// `scale`, or 1e6
+    #[arg(long)]
+    scale: Option<u64>,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
     let witness = load_witness(&args.witness)?;
     let public_inputs = load_public_inputs(&args.public_inputs)?;
-    let instances = public_inputs.to_field_elements()?;
+    let params = circuit_params(
+        &witness,
+        CircuitModes {
+            scale: args.scale,
+            ..CircuitModes::default()
+        },
+    )?;
+    let instances = public_inputs.public_values(&params)?;
     let commitments = public_inputs.commitment_fields()?;
 
-    let folded = to_field_matrix(&witness.folded_vectors);
-    let pq = to_field_matrix(&witness.pq_vectors);
-    let epsilon = compute_field_residuals(&folded, &pq);
+    let fixed_point = params.fixed_point()?;
+    let folded = fixed_point
+        .to_field_matrix(&witness.folded_vectors)
+        .context("foldedVectors")?;
+    let pq = fixed_point
+        .to_field_matrix(&witness.pq_vectors)
+        .context("pqVectors")?;
+    let epsilon = claimed_residuals(&witness, folded.len(), fixed_point)?;
 
     let circuit = FoldedCircuit {
         public_inputs: instances.clone(),
@@ -35,58 +56,12 @@ fn main() -> Result<()> {
         pq_vectors: pq,
         epsilon_squared: epsilon,
         commitments,
+        ..FoldedCircuit::blank_with(&params)
     };
 
+    ensure_rows(&circuit.params, WitnessShape::of(&witness)?, args.circuit_k)?;
     let prover = MockProver::run(args.circuit_k, &circuit, vec![instances])?;
     prover.assert_satisfied();
     println!("Mock prover satisfied");
     Ok(())
 }
-
-fn to_field_matrix(input: &[Vec<f64>]) -> Vec<Vec<Fr>> {
-    input
-        .iter()
-        .map(|row| row.iter().map(|value| float_to_field(*value)).collect())
-        .collect()
-}
-
-fn float_to_field(value: f64) -> Fr {
-    const SCALE: f64 = 1_000_000.0;
-    let scaled = (value * SCALE).floor() as i64;
-    from_i64(scaled) * scale_inv()
-}
-
-fn from_i64(value: i64) -> Fr {
-    if value >= 0 {
-        Fr::from(value as u64)
-    } else {
-        -Fr::from((-value) as u64)
-    }
-}
-
-fn scale_inv() -> Fr {
-    use std::sync::OnceLock;
-    static INV: OnceLock<Fr> = OnceLock::new();
-    *INV.get_or_init(|| {
-        Fr::from(1_000_000u64)
-            .invert()
-            .expect("scale must have inverse in field")
-    })
-}
-
-fn compute_field_residuals(folded: &[Vec<Fr>], pq: &[Vec<Fr>]) -> Vec<Fr> {
-    folded
-        .iter()
-        .zip(pq.iter())
-        .map(|(f_row, pq_row)| {
-            f_row
-                .iter()
-                .zip(pq_row.iter())
-                .fold(Fr::zero(), |acc, (a, b)| {
-                    let diff = *a - *b;
-                    acc + diff.square()
-                })
-        })
-        .collect()
-}
-
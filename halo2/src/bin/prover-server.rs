@@ -0,0 +1,745 @@
+use std::{
+    fs,
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+use zeroize::{Zeroize, Zeroizing};
+
+#[cfg(feature = "chaos")]
+use folding_halo2::chaos::{FaultInjector, FaultPoint};
+use folding_halo2::{
+    audit::{audited, AuditLog, Operation, Subject},
+    build_info::exit_if_version_json,
+    cancel::{is_cancelled, parse_timeout, CancellationToken},
+    capabilities::capabilities,
+    errors::{exit_on_error, ErrorCode, ErrorReport},
+    hashing::digest_hex,
+    http::{post_json, read_request, write_response, Limits, Request, Response},
+    io::decode_witness,
+    jobs::{JobOutput, JobRegistry, JobStatus, RegistryLimits, Submission, SubmitError},
+    keygen::{set_keygen_progress, set_keygen_threads, stderr_progress},
+    keys::{key_fingerprint, read_circuit_k, read_circuit_params},
+    policy::Policy,
+    prover::ProveTimings,
+    quote::{quote, Pricing, QuoteRequest},
+    shape::WitnessShape,
+    signing::{Signers, WitnessManifest},
+    slo::{LatencyTracker, SloAlert, SloConfig, Stage},
+    storage::LocalStorage,
+    transcript::TranscriptKind,
+    upload::{UploadDeclaration, UploadError, UploadStore},
+    ParsedPublicInputs, Prover, WitnessData,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Proving daemon: queues folded-block proving jobs and serves their results"
+)]
+struct Args {
+    #[arg(long = "proving-key")]
+    proving_key: PathBuf,
+    /// Circuit modes are taken from an existing key config; new keys use the default circuit
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    /// Used when the key config does not exist yet; otherwise its recorded `k` wins
+    #[arg(long = "circuit-k", default_value_t = 12)]
+    circuit_k: u32,
+    /// JSON settings (`jobTimeout`, `slo`, `pricing`)
+    /// re-read on every reload
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Bearer token enabling `POST /admin/reload`; admin endpoints are off without it
+    #[arg(long = "admin-token")]
+    admin_token: Option<String>,
+    /// Skip the canary prove/verify run at startup and on reload
+    #[arg(long = "skip-self-test")]
+    skip_self_test: bool,
+    /// Worker threads for key generation (defaults to all cores)
+    #[arg(long = "keygen-threads")]
+    keygen_threads: Option<usize>,
+    #[arg(long, default_value = "127.0.0.1:8091")]
+    listen: String,
+    #[arg(long, default_value_t = 1)]
+    workers: usize,
+    #[arg(long = "max-queue", default_value_t = 16)]
+    max_queue: usize,
+    /// Attempts allowed per idempotency key before a failed job is no longer retried
+    #[arg(long = "max-attempts", default_value_t = 3)]
+    max_attempts: u32,
+    /// Finished jobs kept for status queries and idempotent replays
+    #[arg(long = "retain-jobs", default_value_t = 1024)]
+    retain_jobs: usize,
+    /// Per-job limit (`90`, `500ms`, `5m`, `1h`); overrunning jobs are reported as cancelled
+    #[arg(long = "job-timeout", value_parser = parse_timeout)]
+    job_timeout: Option<Duration>,
+    #[arg(long = "max-body-bytes", default_value_t = 16 * 1024 * 1024)]
+    max_body_bytes: usize,
+    #[arg(long = "max-connections", default_value_t = 32)]
+    max_connections: usize,
+    #[arg(long = "read-timeout-secs", default_value_t = 30)]
+    read_timeout_secs: u64,
+    /// Deadline for receiving a whole request; slower clients get a 408
+    #[arg(long = "request-timeout-secs", default_value_t = 120)]
+    request_timeout_secs: u64,
+    /// Append a record of every proving job to this hash-chained audit log
+    #[arg(long = "audit-log")]
+    audit_log: Option<PathBuf>,
+    /// Key usage policy (`maxK`, `allowedShapes`, `allowedEncodings`), re-read
+    /// on every reload; keys outside it fail to load and requests outside it
+    /// are rejected at submission
+    #[arg(long)]
+    policy: Option<PathBuf>,
+    /// Answer jobs with simulated proofs, skipping `create_proof`; results are
+    /// marked `simulated` and rejected by every verifier
+    #[arg(long)]
+    simulate: bool,
+    /// Fiat-Shamir transcript for every job (blake2b, keccak256 or poseidon)
+    #[arg(long, default_value = "blake2b")]
+    transcript: TranscriptKind,
+    /// POST every SLO breach and recovery as JSON to this `http://` URL
+    #[arg(long = "slo-alert-url")]
+    slo_alert_url: Option<String>,
+    /// Accept resumable witness uploads (`/uploads`) into this directory
+    #[arg(long = "upload-dir")]
+    upload_dir: Option<PathBuf>,
+    #[arg(long = "max-upload-bytes", default_value_t = 8 << 30)]
+    max_upload_bytes: u64,
+    /// Generator keys (`signers`: name and publicKey) allowed to sign
+    /// witnesses; submissions then need a `witnessManifest` from one of them
+    #[arg(long = "witness-signers")]
+    witness_signers: Option<PathBuf>,
+    /// Inject the faults of this plan into jobs, see `folding_halo2::chaos`
+    #[cfg(feature = "chaos")]
+    #[arg(long)]
+    chaos: Option<PathBuf>,
+    /// Print the build (crate version, git commit, features, circuit hash)
+    /// as JSON and exit
+    #[arg(long = "version-json")]
+    _version_json: bool,
+}
+
+struct ProveRequest {
+    witness: WitnessData,
+    public_inputs: ParsedPublicInputs,
+}
+
+/// Clears the witness once its job is proved, failed or replaced.
+impl Drop for ProveRequest {
+    fn drop(&mut self) {
+        self.witness.zeroize();
+    }
+}
+
+/// `POST /jobs` body: the witness inline, or the id of a complete upload.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SubmitBody {
+    #[serde(default)]
+    witness: Option<WitnessData>,
+    #[serde(default)]
+    witness_upload: Option<String>,
+    /// Signed manifest of the witness, see `folding_halo2::signing`.
+    #[serde(default)]
+    witness_manifest: Option<WitnessManifest>,
+    public_inputs: ParsedPublicInputs,
+}
+
+struct Job {
+    request: ProveRequest,
+    digest: String,
+    submitted: Instant,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SettingsFile {
+    job_timeout: Option<String>,
+    /// Latency thresholds, see [`folding_halo2::slo`].
+    #[serde(default)]
+    slo: SloConfig,
+    /// Quoted by `POST /quote`; quotes carry no price without it.
+    pricing: Option<Pricing>,
+}
+
+/// Key material and settings swapped as a unit by a reload. Workers hold the
+/// `Arc` for the whole job, so a reload never changes keys under a running proof.
+struct Hot {
+    prover: Prover,
+    key_fingerprint: Option<String>,
+    job_timeout: Option<Duration>,
+    policy: Policy,
+    slo: SloConfig,
+    pricing: Option<Pricing>,
+}
+
+struct State {
+    args: Args,
+    hot: RwLock<Arc<Hot>>,
+    reload_lock: Mutex<()>,
+    jobs: JobRegistry<Job>,
+    audit: Option<AuditLog>,
+    limits: Limits,
+    active: AtomicUsize,
+    max_connections: usize,
+    latency: LatencyTracker,
+    uploads: Option<UploadStore>,
+    signers: Option<Signers>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<FaultInjector>,
+}
+
+fn main() {
+    exit_if_version_json();
+    exit_on_error(run());
+}
+
+fn run() -> Result<()> {
+    let args = Args::parse();
+    set_keygen_threads(args.keygen_threads);
+    set_keygen_progress(Some(stderr_progress()), Duration::from_secs(10));
+
+    let hot = load_hot(&args)?;
+    let latency = LatencyTracker::new(&hot.slo)?;
+    let state = Arc::new(State {
+        hot: RwLock::new(Arc::new(hot)),
+        reload_lock: Mutex::new(()),
+        jobs: JobRegistry::new(RegistryLimits {
+            max_queue: args.max_queue,
+            max_attempts: args.max_attempts,
+            retain_finished: args.retain_jobs,
+        }),
+        audit: args.audit_log.as_deref().map(AuditLog::open).transpose()?,
+        limits: Limits {
+            max_body_bytes: args.max_body_bytes,
+            read_timeout: Duration::from_secs(args.read_timeout_secs),
+            request_timeout: Duration::from_secs(args.request_timeout_secs),
+            ..Limits::default()
+        },
+        active: AtomicUsize::new(0),
+        max_connections: args.max_connections,
+        latency,
+        uploads: args
+            .upload_dir
+            .as_deref()
+            .map(|dir| UploadStore::open(Box::new(LocalStorage::new(dir)), args.max_upload_bytes)),
+        signers: args
+            .witness_signers
+            .as_deref()
+            .map(Signers::load)
+            .transpose()?,
+        #[cfg(feature = "chaos")]
+        chaos: args.chaos.as_deref().map(FaultInjector::load).transpose()?,
+        args,
+    });
+
+    for _ in 0..state.args.workers.max(1) {
+        let state = Arc::clone(&state);
+        thread::spawn(move || worker(&state));
+    }
+
+    let listener = TcpListener::bind(&state.args.listen)?;
+    eprintln!("prover-server listening on {}", state.args.listen);
+    if state.args.simulate {
+        eprintln!("prover-server is SIMULATING: proofs are placeholders no verifier accepts");
+    }
+    #[cfg(feature = "chaos")]
+    if state.chaos.is_some() {
+        eprintln!("prover-server is INJECTING FAULTS into jobs");
+    }
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("accept failed: {err}");
+                continue;
+            }
+        };
+        if state.active.fetch_add(1, Ordering::SeqCst) >= state.max_connections {
+            state.active.fetch_sub(1, Ordering::SeqCst);
+            let _ = write_response(&stream, &Response::error(503, "too many connections"));
+            continue;
+        }
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(&state, &stream) {
+                eprintln!("connection error: {err}");
+            }
+            state.active.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+/// Reads the key config, settings and policy files, derives the proving key
+/// and runs the canary self-test against it.
+fn load_hot(args: &Args) -> Result<Hot> {
+    let settings = match &args.config {
+        Some(path) => {
+            let bytes = fs::read(path).with_context(|| format!("opening {:?}", path))?;
+            serde_json::from_slice(&bytes).with_context(|| format!("parsing {:?}", path))?
+        }
+        None => SettingsFile::default(),
+    };
+    let job_timeout = match settings.job_timeout.as_deref() {
+        Some(raw) => Some(parse_timeout(raw)?),
+        None => args.job_timeout,
+    };
+
+    let policy = Policy::load_optional(args.policy.as_deref())?;
+    if args.verification_key.exists() {
+        policy.check(
+            &read_circuit_params(&args.verification_key)?,
+            read_circuit_k(&args.verification_key)?,
+            None,
+        )?;
+    }
+
+    let prover = Prover::load(&args.proving_key, &args.verification_key, args.circuit_k)?
+        .with_simulation(args.simulate)
+        .with_transcript(args.transcript);
+    if !args.skip_self_test {
+        let report = prover.self_test()?;
+        eprintln!(
+            "self-test passed: {}-byte canary proof in {} ms, verified in {} ms",
+            report.proof_bytes, report.prove_ms, report.verify_ms
+        );
+    }
+    Ok(Hot {
+        prover,
+        key_fingerprint: Some(key_fingerprint(&args.proving_key)?),
+        job_timeout,
+        policy,
+        slo: settings.slo,
+        pricing: settings.pricing,
+    })
+}
+
+impl State {
+    fn hot(&self) -> Arc<Hot> {
+        Arc::clone(
+            &self
+                .hot
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+
+    /// Rebuilds the keys off the lock and swaps them in; queued jobs stay queued
+    /// and pick up the new keys when they start. On error the old keys stay live.
+    fn reload(&self) -> Result<Arc<Hot>> {
+        let _serialized = self
+            .reload_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let hot = Arc::new(load_hot(&self.args)?);
+        self.latency.configure(&hot.slo)?;
+        *self
+            .hot
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::clone(&hot);
+        Ok(hot)
+    }
+}
+
+fn worker(state: &State) {
+    loop {
+        let (id, job) = state.jobs.next();
+        let queued = job.submitted.elapsed();
+        let hot = state.hot();
+        let cancel = CancellationToken::with_optional_timeout(hot.job_timeout);
+        let result = audited(
+            state.audit.as_ref(),
+            Operation::Prove,
+            || {
+                let mut subject = Subject::default().key(hot.key_fingerprint.clone());
+                subject
+                    .inputs
+                    .insert("request".to_string(), job.digest.clone());
+                subject
+            },
+            || {
+                inject_faults(state, &cancel)?;
+                prove(&hot, &job.request, &cancel)
+            },
+        );
+        let mut samples = vec![(Stage::Queue, queued)];
+        if let Ok((_, timings)) = &result {
+            samples.extend([
+                (Stage::Witness, timings.witness),
+                (Stage::Prove, timings.prove),
+                (Stage::Total, job.submitted.elapsed()),
+            ]);
+        }
+        for alert in state.latency.record(&samples) {
+            raise(state, alert);
+        }
+        let outcome = result.map(|(output, _)| output).map_err(|err| {
+            let status = if is_cancelled(&err) {
+                JobStatus::Cancelled
+            } else {
+                JobStatus::Failed
+            };
+            let report = ErrorReport::new(&err);
+            eprintln!("job {id} {status:?}: {report}");
+            (status, report)
+        });
+        state.jobs.finish(&id, outcome);
+    }
+}
+
+#[cfg(feature = "chaos")]
+fn inject_faults(state: &State, cancel: &CancellationToken) -> Result<()> {
+    if let Some(chaos) = &state.chaos {
+        chaos.inject(FaultPoint::WitnessRead, cancel)?;
+        chaos.inject(FaultPoint::Prove, cancel)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "chaos"))]
+fn inject_faults(_state: &State, _cancel: &CancellationToken) -> Result<()> {
+    Ok(())
+}
+
+fn prove(
+    hot: &Hot,
+    request: &ProveRequest,
+    cancel: &CancellationToken,
+) -> Result<(JobOutput, ProveTimings)> {
+    let output = hot
+        .prover
+        .prove_with(&request.witness, &request.public_inputs, cancel)?;
+    Ok((
+        JobOutput {
+            proof: hex::encode(output.proof),
+            metadata: output.metadata,
+        },
+        output.timings,
+    ))
+}
+
+/// Logs an SLO breach or recovery and forwards it to `--slo-alert-url`.
+fn raise(state: &State, alert: SloAlert) {
+    if alert.resolved {
+        eprintln!("SLO recovered: {}", alert.breach);
+    } else {
+        eprintln!("SLO breached: {}", alert.breach);
+    }
+    let Some(url) = state.args.slo_alert_url.clone() else {
+        return;
+    };
+    let limits = state.limits;
+    thread::spawn(move || {
+        let sent = serde_json::to_vec(&alert)
+            .map_err(anyhow::Error::from)
+            .and_then(|body| post_json(&url, &body, &limits));
+        if let Err(err) = sent {
+            eprintln!("SLO alert to {url} failed: {err:#}");
+        }
+    });
+}
+
+fn handle_connection(state: &State, stream: &TcpStream) -> Result<()> {
+    let response = match read_request(stream, &state.limits) {
+        Ok(mut request) => {
+            let response = route(state, &request);
+            // Submissions and uploads carry witnesses in the body.
+            request.body.zeroize();
+            response
+        }
+        Err(err) => Response::error(err.status, err.message),
+    };
+    write_response(stream, &response)
+}
+
+fn route(state: &State, request: &Request) -> Response {
+    if request.path == "/uploads" || request.path.starts_with("/uploads/") {
+        return route_upload(state, request);
+    }
+    let job_id = request.path.strip_prefix("/jobs/");
+    match (request.method.as_str(), request.path.as_str(), job_id) {
+        ("POST", "/jobs", _) => handle_submit(state, request),
+        ("POST", "/quote", _) => handle_quote(state, request),
+        ("GET", _, Some(id)) => match state.jobs.get(id) {
+            Some(job) => Response::json(200, &job),
+            None => Response::error(404, format!("unknown job {id}")),
+        },
+        ("GET", "/healthz", _) => Response::json(
+            200,
+            &serde_json::json!({
+                "status": "ok",
+                "queued": state.jobs.queued(),
+                "keyFingerprint": state.hot().key_fingerprint,
+                "simulate": state.args.simulate,
+                "ready": state.latency.ready(),
+            }),
+        ),
+        ("GET", "/readyz", _) => {
+            let report = state.latency.report();
+            let status = if report.ready { 200 } else { 503 };
+            Response::json(
+                status,
+                &serde_json::json!({
+                    "ready": report.ready,
+                    "breaches": report.breaches,
+                }),
+            )
+        }
+        ("GET", "/metrics/latency", _) => Response::json(200, &state.latency.report()),
+        ("GET", "/capabilities", _) => Response::json(200, &capabilities()),
+        ("POST", "/admin/reload", _) => handle_reload(state, request),
+        (_, "/jobs", _)
+        | (_, "/quote", _)
+        | (_, "/healthz", _)
+        | (_, "/readyz", _)
+        | (_, "/metrics/latency", _)
+        | (_, "/capabilities", _)
+        | (_, "/admin/reload", _)
+        | (_, _, Some(_)) => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+/// Estimates a job from its witness shape, without the witness.
+fn handle_quote(state: &State, request: &Request) -> Response {
+    let payload: QuoteRequest = match serde_json::from_slice(&request.body) {
+        Ok(payload) => payload,
+        Err(err) => {
+            return Response::report(
+                400,
+                &ErrorReport::with_code(
+                    ErrorCode::InvalidInput,
+                    format!("invalid request body: {err}"),
+                ),
+            )
+        }
+    };
+    let hot = state.hot();
+    let shape = WitnessShape {
+        vectors: payload.vectors,
+        dim: payload.dim,
+    };
+    let quoted = hot
+        .policy
+        .check(hot.prover.layout(), hot.prover.circuit_k(), Some(shape))
+        .and_then(|()| {
+            quote(
+                &hot.prover,
+                payload,
+                Some(&state.latency.report()),
+                hot.pricing.as_ref(),
+            )
+        });
+    match quoted {
+        Ok(quote) => Response::json(200, &quote),
+        Err(err) => Response::report(422, &ErrorReport::new(&err)),
+    }
+}
+
+fn route_upload(state: &State, request: &Request) -> Response {
+    let Some(uploads) = &state.uploads else {
+        return Response::error(404, "uploads are disabled; start with --upload-dir");
+    };
+    let upload_id = request.path.strip_prefix("/uploads/");
+    let result = match (request.method.as_str(), upload_id) {
+        ("POST", None) => {
+            let declaration: UploadDeclaration = match serde_json::from_slice(&request.body) {
+                Ok(declaration) => declaration,
+                Err(err) => {
+                    return Response::report(
+                        400,
+                        &ErrorReport::with_code(
+                            ErrorCode::InvalidInput,
+                            format!("invalid upload declaration: {err}"),
+                        ),
+                    )
+                }
+            };
+            return match uploads.create(declaration) {
+                Ok(status) => Response::json(201, &status),
+                Err(err) => upload_error(&err),
+            };
+        }
+        ("GET", Some(id)) => uploads.status(id),
+        ("PATCH", Some(id)) => {
+            let Some(offset) = request
+                .header("Upload-Offset")
+                .and_then(|offset| offset.trim().parse::<u64>().ok())
+            else {
+                return Response::error(400, "PATCH needs a numeric Upload-Offset header");
+            };
+            uploads.append(id, offset, &request.body)
+        }
+        _ => return Response::error(405, "method not allowed"),
+    };
+    match result {
+        Ok(status) => {
+            let offset = status.offset.to_string();
+            Response::json(200, &status).with_header("Upload-Offset", offset)
+        }
+        Err(err) => upload_error(&err),
+    }
+}
+
+fn upload_error(err: &anyhow::Error) -> Response {
+    let status = match err.downcast_ref::<UploadError>() {
+        Some(UploadError::Unknown { .. }) => 404,
+        Some(UploadError::OffsetMismatch { .. } | UploadError::Incomplete { .. }) => 409,
+        None => 422,
+    };
+    let response = Response::report(status, &ErrorReport::new(err));
+    match err.downcast_ref::<UploadError>() {
+        Some(UploadError::OffsetMismatch { stored, .. }) => {
+            response.with_header("Upload-Offset", stored.to_string())
+        }
+        _ => response,
+    }
+}
+
+/// The witness of a submission, read from its upload when it names one.
+fn submitted_witness(state: &State, body: SubmitBody) -> Result<ProveRequest, Response> {
+    let witness = match (body.witness, &body.witness_upload) {
+        (Some(witness), None) => witness,
+        (None, Some(id)) => {
+            let Some(uploads) = &state.uploads else {
+                return Err(Response::error(
+                    404,
+                    "uploads are disabled; start with --upload-dir",
+                ));
+            };
+            let witness = uploads
+                .read(id)
+                .map(Zeroizing::new)
+                .and_then(|bytes| decode_witness(&bytes, None, &format!("upload {id}")));
+            match witness {
+                Ok(witness) => witness,
+                Err(err) => return Err(upload_error(&err)),
+            }
+        }
+        _ => {
+            return Err(Response::report(
+                400,
+                &ErrorReport::with_code(
+                    ErrorCode::InvalidInput,
+                    "pass exactly one of witness and witnessUpload",
+                ),
+            ))
+        }
+    };
+    Ok(ProveRequest {
+        witness,
+        public_inputs: body.public_inputs,
+    })
+}
+
+fn handle_submit(state: &State, request: &Request) -> Response {
+    let key = request
+        .header("Idempotency-Key")
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+    if key.is_some_and(|key| key.len() > 255) {
+        return Response::error(400, "Idempotency-Key longer than 255 bytes");
+    }
+    let body: SubmitBody = match serde_json::from_slice(&request.body) {
+        Ok(body) => body,
+        Err(err) => {
+            return Response::report(
+                400,
+                &ErrorReport::with_code(
+                    ErrorCode::InvalidInput,
+                    format!("invalid request body: {err}"),
+                ),
+            )
+        }
+    };
+    let upload = body.witness_upload.clone();
+    let manifest = body.witness_manifest.clone();
+    let payload = match submitted_witness(state, body) {
+        Ok(payload) => payload,
+        Err(response) => return response,
+    };
+    if let Some(signers) = &state.signers {
+        if let Err(err) = signers.check(&payload.witness, manifest.as_ref()) {
+            return Response::report(403, &ErrorReport::new(&err));
+        }
+    }
+    let hot = state.hot();
+    let admitted = WitnessShape::of(&payload.witness).and_then(|shape| {
+        hot.policy
+            .check(hot.prover.layout(), hot.prover.circuit_k(), Some(shape))
+    });
+    if let Err(err) = admitted {
+        return Response::report(422, &ErrorReport::new(&err));
+    }
+    let digest = digest_hex(&request.body);
+    let job = Job {
+        request: payload,
+        digest: digest.clone(),
+        submitted: Instant::now(),
+    };
+    let submitted = state.jobs.submit(key, digest, job);
+    if let (Ok(_), Some(id), Some(uploads)) = (&submitted, &upload, &state.uploads) {
+        uploads.discard(id);
+    }
+    match submitted {
+        Ok(Submission::Enqueued(job)) => Response::json(202, &job),
+        Ok(Submission::Existing(job)) => {
+            Response::json(200, &job).with_header("Idempotent-Replayed", "true")
+        }
+        Err(err @ SubmitError::QueueFull) => {
+            Response::error(503, err.to_string()).with_header("Retry-After", "5")
+        }
+        Err(err @ SubmitError::KeyConflict { .. }) => Response::error(422, err.to_string()),
+        Err(err @ SubmitError::AttemptsExhausted { .. }) => Response::error(409, err.to_string()),
+    }
+}
+
+fn handle_reload(state: &State, request: &Request) -> Response {
+    let Some(expected) = state.args.admin_token.as_deref() else {
+        return Response::error(404, "not found");
+    };
+    let presented = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !constant_time_eq(presented.trim().as_bytes(), expected.as_bytes()) {
+        return Response::error(401, "invalid admin token");
+    }
+    match state.reload() {
+        Ok(hot) => {
+            eprintln!(
+                "reloaded keys (k={}, fingerprint {})",
+                hot.prover.circuit_k(),
+                hot.key_fingerprint.as_deref().unwrap_or("-")
+            );
+            Response::json(
+                200,
+                &serde_json::json!({
+                    "reloaded": true,
+                    "circuitK": hot.prover.circuit_k(),
+                    "keyFingerprint": hot.key_fingerprint,
+                    "queued": state.jobs.queued(),
+                }),
+            )
+        }
+        Err(err) => {
+            eprintln!("reload failed, keeping current keys: {err:#}");
+            Response::error(500, format!("reload failed, keeping current keys: {err:#}"))
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
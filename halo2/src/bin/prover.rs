@@ -1,18 +1,39 @@
-use std::{fs::File, io::Write, path::PathBuf, sync::OnceLock};
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process, thread,
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
-use halo2_proofs::{
-    plonk::create_proof,
-    poly::kzg::{commitment::KZGCommitmentScheme, multiopen::ProverGWC},
-    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
-};
-use halo2curves::bn256::{Bn256, G1Affine, Fr};
-use rand::SeedableRng;
-use rand_chacha::ChaCha20Rng;
 
-use folding_halo2::{circuit::FoldedCircuit, io::load_witness, keys::load_or_init_keys, load_public_inputs};
-use std::env;
+use folding_halo2::{
+    audit::{audited, open_optional, Operation, Subject},
+    build_info::exit_if_version_json,
+    cancel::{parse_timeout, CancellationToken},
+    circuit::FoldedCircuit,
+    codebook::CommitMode,
+    container::ProofContainer,
+    errors::{exit_on_error, Coded, ErrorCode},
+    hashing::HashAlgorithm,
+    io::{load_field_witness, load_witness, FieldWitness, WitnessData},
+    keygen::{set_keygen_progress, set_keygen_threads, stderr_progress},
+    keys::{key_fingerprint, load_or_init_keys_with_setup, read_circuit_k, read_circuit_params},
+    l1::{ChainHeadTracker, EthRpc, L1Reference},
+    load_public_inputs,
+    metadata::write_sidecar,
+    policy::Policy,
+    prove::{circuit_params, field_circuit_params, CircuitModes},
+    prover::Prover,
+    replay::Replay,
+    shape::{negotiate_shape, WitnessShape},
+    signing::{Signers, WitnessManifest},
+    srs::load_srs,
+    storage::AtomicFile,
+    transcript::TranscriptKind,
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about = "Halo2 prover for folded blocks")]
@@ -29,119 +50,354 @@ struct Args {
     output: PathBuf,
     #[arg(long = "circuit-k", default_value_t = 12)]
     circuit_k: u32,
+    /// Trusted setup (halo2 params or a `.ptau` file) to build the keys on
+    /// instead of the config seed; recorded in new configs and required by
+    /// keys built on it
+    #[arg(long)]
+    params: Option<PathBuf>,
+    /// Constrain a Poseidon Merkle root over the folded vectors (foldedVectorRoot)
+    #[arg(long = "vector-root")]
+    vector_root: bool,
+    /// Commit to the PQ codes and check them against the codebook (pqCodesCommitment)
+    #[arg(long = "pq-codes")]
+    pq_codes: bool,
+    /// Recompute codebookRoot in-circuit from the codebook (merkle or poseidon); needs --pq-codes
+    #[arg(long = "codebook-commitment", requires = "pq_codes")]
+    codebook_commitment: Option<CommitMode>,
+    /// Expose sha256(public values) mod p as the single instance, for EVM verifiers
+    #[arg(long = "instance-hash")]
+    instance_hash: bool,
+    /// Expose the previous block's proof digest (previousProofDigest) as an instance
+    #[arg(long)]
+    lineage: bool,
+    /// Previous block's proof; its digest is recorded in the metadata and
+    /// fills previousProofDigest when the public inputs omit it
+    #[arg(long = "previous-proof")]
+    previous_proof: Option<PathBuf>,
+    /// Expose the randomness beacon value (beaconValue) as an instance
+    #[arg(long)]
+    beacon: bool,
+    /// Lay out only the witness's sparseVectors entries (sparsityCommitment)
+    #[arg(long)]
+    sparse: bool,
+    /// Expose the block's PQ compression stats (compressionStats); needs --pq-codes
+    #[arg(long = "compression-stats", requires = "pq_codes")]
+    compression_stats: bool,
+    /// Bound each PQ subvector's residual by its entry in subvectorEpsilons; needs --pq-codes
+    #[arg(long = "subvector-epsilons", requires = "pq_codes")]
+    subvector_epsilons: bool,
+    /// Prove delta witnesses from `yysfold delta` with up to this many changed
+    /// vectors, updating previousVectorRoot into foldedVectorRoot; needs --vector-root
+    #[arg(long, requires = "vector_root")]
+    delta: Option<usize>,
+    /// Expose a salted Merkle root over the full witness rows (witnessCommitment)
+    /// so single rows can be opened to auditors later; needs auditSalt in the witness
+    #[arg(long = "witness-commitment")]
+    witness_commitment: bool,
+    /// Commit to the witness's OPQ rotation (rotationCommitment); needs --pq-codes
+    #[arg(long, requires = "pq_codes")]
+    rotation: bool,
+    /// Check the witness's residualStage against a residual codebook root
+    /// (residualCodebookRoot); needs --codebook-commitment
+    #[arg(long = "residual-stage", requires = "codebook_commitment")]
+    residual_stage: bool,
+    /// Dequantize the witness's int8 scalarQuantization instead of looking up
+    /// PQ codes, committing to it as scalarCommitment
+    #[arg(long, conflicts_with = "pq_codes")]
+    scalar: bool,
+    /// Commit rows in the order the witness's secret rowPermutation salt
+    /// picks, committing to it as permutationCommitment; needs --vector-root
+    /// or --witness-commitment
+    #[arg(long = "row-permutation")]
+    row_permutation: bool,
+    /// Recompute foldedCommitment and pqCommitment in-circuit as hiding
+    /// commitments under the witness's blinding factors (see `yysfold blind`)
+    #[arg(long)]
+    hiding: bool,
+    /// Recompute foldedCommitment and pqCommitment in-circuit as Poseidon
+    /// hashes of the vectors (see `yysfold gen-public-inputs
+    /// --vector-commitments`)
+    #[arg(long = "vector-commitments", conflicts_with = "hiding")]
+    vector_commitments: bool,
+    /// Bound every vector's residual by the public slaBound, the committed
+    /// maximum reconstruction error
+    #[arg(long = "sla-bound")]
+    sla_bound: bool,
+    /// Fixed-point scale of the witness values; defaults to the witness's
+    /// `scale`, or 1e6. Keys are made for one scale
+    #[arg(long)]
+    scale: Option<u64>,
+    /// Append keygen and prove records to this hash-chained audit log
+    #[arg(long = "audit-log")]
+    audit_log: Option<PathBuf>,
+    /// Give up after this long (`90`, `500ms`, `5m`, `1h`); a proof still
+    /// running at the deadline is abandoned and the process exits with 124
+    #[arg(long, value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+    /// Worker threads for key generation (defaults to all cores)
+    #[arg(long = "keygen-threads")]
+    keygen_threads: Option<usize>,
+    /// Capture the witness, public inputs, key configs and versions of this
+    /// run into a tar archive that `yysfold replay` re-runs
+    #[arg(long)]
+    record: Option<PathBuf>,
+    /// Key usage policy (`maxK`, `allowedShapes`, `allowedEncodings`); runs
+    /// outside it are rejected before keygen
+    #[arg(long)]
+    policy: Option<PathBuf>,
+    /// Generator keys (`signers`: name and publicKey) allowed to sign
+    /// witnesses; unsigned or foreign witnesses are refused before proving
+    #[arg(long = "witness-signers")]
+    witness_signers: Option<PathBuf>,
+    /// Signed manifest of the witness; defaults to `<witness>.sig.json`
+    #[arg(long = "witness-manifest", requires = "witness_signers")]
+    witness_manifest: Option<PathBuf>,
+    /// Skip `create_proof` and write a simulated proof, marked `simulated` in
+    /// the metadata and rejected by every verifier; for integration testing
+    #[arg(long)]
+    simulate: bool,
+    /// Fiat-Shamir transcript (blake2b, keccak256 or poseidon), recorded in
+    /// the metadata; verifiers must use the same one
+    #[arg(long, default_value = "blake2b")]
+    transcript: TranscriptKind,
+    /// L1 JSON-RPC endpoint; binds the proof to a finalized L1 block, recorded
+    /// as l1Reference in the metadata, and refuses to write it if that block
+    /// is reorged away while proving
+    #[arg(long = "l1-rpc-url")]
+    l1_rpc_url: Option<String>,
+    /// L1 block (`<number>:<hash>`) the public inputs were read at; defaults
+    /// to the finalized head; needs --l1-rpc-url
+    #[arg(long = "l1-reference", requires = "l1_rpc_url")]
+    l1_reference: Option<L1Reference>,
+    /// Hash for proofDigest and previousProofDigest (blake3 or sha256); use
+    /// sha256 when a contract recomputes the lineage
+    #[arg(long = "proof-digest", default_value = "blake3")]
+    proof_digest: HashAlgorithm,
+    /// Write a self-describing proof embedding the instance layout, versions,
+    /// transcript and verifying key digest; the sidecar still describes the
+    /// raw proof
+    #[arg(long)]
+    container: bool,
+    /// Print the build (crate version, git commit, features, circuit hash)
+    /// as JSON and exit
+    #[arg(long = "version-json")]
+    _version_json: bool,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// Grace period for the cooperative checks to report a timeout before the
+/// watchdog terminates a prover stuck inside `create_proof`.
+const WATCHDOG_GRACE: Duration = Duration::from_secs(2);
+const KEYGEN_HEARTBEAT: Duration = Duration::from_secs(10);
 
-    let witness = load_witness(&args.witness)?;
-    let public_inputs = load_public_inputs(&args.public_inputs)?;
-    let instances = public_inputs.to_field_elements()?;
-    let commitment_fields = public_inputs.commitment_fields()?;
+fn main() {
+    exit_if_version_json();
+    exit_on_error(run());
+}
 
-    // Ensure witness parsed (even though circuit only checks commitments)
-    if witness.folded_vectors.is_empty() || witness.pq_vectors.is_empty() {
-        anyhow::bail!("witness must contain foldedVectors");
+fn run() -> Result<()> {
+    let args = Args::parse();
+    set_keygen_threads(args.keygen_threads);
+    set_keygen_progress(Some(stderr_progress()), KEYGEN_HEARTBEAT);
+    let audit = open_optional(args.audit_log.as_deref())?;
+    let cancel = CancellationToken::with_optional_timeout(args.timeout);
+    if let Some(deadline) = cancel.deadline() {
+        spawn_watchdog(cancel.clone(), deadline);
     }
 
-    let blank = FoldedCircuit::blank(instances.len());
+    let witness = if args.pq_codes || args.scalar || args.witness_signers.is_some() {
+        LoadedWitness::Whole(load_witness(&args.witness)?)
+    } else {
+        LoadedWitness::Field(load_field_witness(&args.witness, args.scale)?)
+    };
+    if let Some(path) = &args.witness_signers {
+        let manifest = WitnessManifest::load_for(&args.witness, args.witness_manifest.as_deref())?;
+        let signer = Signers::load(path)?.check(witness.data(), manifest.as_ref())?;
+        eprintln!("witness signed by {}", signer.name);
+    }
+    let mut public_inputs = load_public_inputs(&args.public_inputs)?;
+    let previous_proof = match &args.previous_proof {
+        Some(path) => Some(fs::read(path).with_context(|| format!("opening {:?}", path))?),
+        None => None,
+    };
+    if let Some(previous_proof) = &previous_proof {
+        let digest = args.proof_digest.digest(previous_proof);
+        match &public_inputs.previous_proof_digest {
+            Some(declared) if *declared != digest => {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "previousProofDigest {declared} does not match --previous-proof ({digest})"
+                    )
+                ))
+            }
+            Some(_) => {}
+            None => public_inputs.previous_proof_digest = Some(digest),
+        }
+    }
 
-    let (params, pk) =
-        load_or_init_keys(&args.proving_key, &args.verification_key, args.circuit_k, &blank)?;
+    let l1 = args.l1_rpc_url.as_deref().map(EthRpc::new);
+    let tracker = l1.as_ref().map(|client| ChainHeadTracker::new(client));
+    let l1_reference = tracker
+        .as_ref()
+        .map(|tracker| tracker.observe(args.l1_reference))
+        .transpose()?;
 
-    let folded_matrix = to_field_matrix(&witness.folded_vectors);
-    let pq_matrix = to_field_matrix(&witness.pq_vectors);
-    let epsilon_squared = compute_field_residuals(&folded_matrix, &pq_matrix);
-    let circuit = FoldedCircuit {
-        public_inputs: instances.clone(),
-        folded_vectors: folded_matrix,
-        pq_vectors: pq_matrix,
-        epsilon_squared,
-        commitments: commitment_fields,
+    let modes = CircuitModes {
+        vector_root: args.vector_root,
+        pq_codes: args.pq_codes,
+        codebook_commitment: args.codebook_commitment,
+        instance_hash: args.instance_hash,
+        lineage: args.lineage,
+        beacon: args.beacon,
+        sparse: args.sparse,
+        compression_stats: args.compression_stats,
+        subvector_epsilons: args.subvector_epsilons,
+        delta: args.delta,
+        witness_commitment: args.witness_commitment,
+        rotation: args.rotation,
+        residual_stage: args.residual_stage,
+        scalar: args.scalar,
+        row_permutation: args.row_permutation,
+        hiding: args.hiding,
+        vector_commitments: args.vector_commitments,
+        sla_bound: args.sla_bound,
+        scale: args.scale,
+    };
+    let layout = match &witness {
+        LoadedWitness::Whole(witness) => circuit_params(witness, modes)?,
+        LoadedWitness::Field(witness) => field_circuit_params(witness, modes)?,
     };
+    let shape = witness.shape()?;
+    let policy = Policy::load_optional(args.policy.as_deref())?;
+    if args.verification_key.exists() {
+        let keyed = read_circuit_params(&args.verification_key)?;
+        let keyed_k = read_circuit_k(&args.verification_key)?;
+        negotiate_shape(witness.data(), shape, &keyed, Some(keyed_k))?;
+        policy.check(&keyed, keyed_k, Some(shape))?;
+    } else {
+        negotiate_shape(witness.data(), shape, &layout, Some(args.circuit_k))?;
+        policy.check(&layout, args.circuit_k, Some(shape))?;
+    }
+    let blank = FoldedCircuit::blank_with(&layout);
+    let setup = args
+        .params
+        .as_deref()
+        .map(|path| load_srs(path, args.circuit_k))
+        .transpose()?;
+    cancel.check("keygen")?;
 
-    let instance_container = vec![instances.clone()];
-    let instance_refs: Vec<&[halo2curves::bn256::Fr]> =
-        instance_container.iter().map(|v| v.as_slice()).collect();
-    let circuit_instances: Vec<&[&[halo2curves::bn256::Fr]]> = vec![&instance_refs[..]];
-    let circuits = vec![circuit.clone()];
-
-    let mut transcript =
-        Blake2bWrite::<Vec<u8>, halo2curves::bn256::G1Affine, Challenge255<_>>::init(vec![]);
-
-    let rng = ChaCha20Rng::from_entropy();
-
-    create_proof::<
-        KZGCommitmentScheme<Bn256>,
-        ProverGWC<'_, Bn256>,
-        Challenge255<G1Affine>,
-        ChaCha20Rng,
-        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
-        FoldedCircuit,
-    >(
-        &params,
-        &pk,
-        &circuits,
-        &circuit_instances,
-        rng,
-        &mut transcript,
+    let fingerprint = || key_fingerprint(&args.proving_key).ok();
+    let (params, pk) = audited(
+        audit.as_ref(),
+        Operation::Keygen,
+        || Subject::default().key(fingerprint()),
+        || {
+            load_or_init_keys_with_setup(
+                &args.proving_key,
+                &args.verification_key,
+                args.circuit_k,
+                &blank,
+                setup.as_ref(),
+            )
+        },
     )?;
 
-    let proof = transcript.finalize();
-    let mut file = File::create(&args.output)?;
-    file.write_all(&proof)?;
-    Ok(())
-}
+    if let Some(record) = &args.record {
+        Replay::capture(
+            &args.witness,
+            &public_inputs,
+            &args.proving_key,
+            &args.verification_key,
+            args.circuit_k,
+            &layout,
+        )?
+        .write(record)?;
+    }
+    let prover = Prover::new(pk, params, layout)
+        .with_simulation(args.simulate)
+        .with_transcript(args.transcript);
 
-fn to_field_matrix(input: &[Vec<f64>]) -> Vec<Vec<Fr>> {
-    input
-        .iter()
-        .map(|row| row.iter().map(|value| float_to_field(*value)).collect())
-        .collect()
-}
+    let output = audited(
+        audit.as_ref(),
+        Operation::Prove,
+        || {
+            Subject::default()
+                .input_file("witness", &args.witness)
+                .input_file("publicInputs", &args.public_inputs)
+                .key(fingerprint())
+        },
+        || match witness {
+            LoadedWitness::Whole(witness) => prover.prove_with(&witness, &public_inputs, &cancel),
+            LoadedWitness::Field(witness) => {
+                prover.prove_field_with(witness, &public_inputs, &cancel)
+            }
+        },
+    )?;
+    let proof = output.proof;
+    if let (Some(tracker), Some(reference)) = (&tracker, &l1_reference) {
+        tracker.ensure_canonical(reference)?;
+    }
+    let written = if args.container {
+        ProofContainer::seal(
+            proof.clone(),
+            prover.layout(),
+            prover.circuit_k(),
+            prover.proving_key().get_vk(),
+            args.transcript,
+        )?
+        .to_bytes()?
+    } else {
+        proof.clone()
+    };
+    let mut file = AtomicFile::create(&args.output)?;
+    file.write_all(&written)
+        .with_context(|| format!("writing {:?}", args.output))?;
+    file.commit()?;
+    if args.simulate {
+        eprintln!(
+            "wrote a SIMULATED proof to {:?}; no verifier accepts it",
+            args.output
+        );
+    }
 
-fn compute_field_residuals(folded: &[Vec<Fr>], pq: &[Vec<Fr>]) -> Vec<Fr> {
-    let multiplier = env::var("HALO2_EPSILON_MULTIPLIER")
-        .ok()
-        .and_then(|raw| raw.parse::<f64>().ok())
-        .unwrap_or(1.0);
-    let multiplier_fr = float_to_field(multiplier);
-    folded
-        .iter()
-        .zip(pq.iter())
-        .map(|(f_row, pq_row)| {
-            f_row
-                .iter()
-                .zip(pq_row.iter())
-                .fold(Fr::zero(), |acc, (a, b)| {
-                    let diff = *a - *b;
-                    acc + diff.square()
-                })
-                * multiplier_fr
-        })
-        .collect()
+    let metadata = output
+        .metadata
+        .with_l1_reference(l1_reference)
+        .with_digest_algorithm(args.proof_digest, &proof);
+    write_sidecar(&args.output, &metadata.into())?;
+    Ok(())
 }
 
-fn float_to_field(value: f64) -> Fr {
-    const SCALE: f64 = 1_000_000.0;
-    let scaled = (value * SCALE).floor() as i64;
-    from_i64(scaled) * scale_inv()
+/// The witness as loaded: whole when a mode or the signer check needs its
+/// `f64` vectors, otherwise streamed into the field.
+enum LoadedWitness {
+    Whole(WitnessData),
+    Field(FieldWitness),
 }
 
-fn from_i64(value: i64) -> Fr {
-    if value >= 0 {
-        Fr::from(value as u64)
-    } else {
-        -Fr::from((-value) as u64)
+impl LoadedWitness {
+    fn data(&self) -> &WitnessData {
+        match self {
+            Self::Whole(witness) => witness,
+            Self::Field(witness) => &witness.witness,
+        }
     }
-}
 
-fn scale_inv() -> Fr {
-    static INV: OnceLock<Fr> = OnceLock::new();
-    *INV.get_or_init(|| {
-        Fr::from(1_000_000u64)
-            .invert()
-            .expect("scale must have inverse in field")
-    })
+    fn shape(&self) -> Result<WitnessShape> {
+        match self {
+            Self::Whole(witness) => WitnessShape::of(witness),
+            Self::Field(witness) => witness.shape(),
+        }
+    }
 }
 
+fn spawn_watchdog(cancel: CancellationToken, deadline: Instant) {
+    thread::spawn(move || {
+        thread::sleep(deadline.saturating_duration_since(Instant::now()) + WATCHDOG_GRACE);
+        eprintln!(
+            "prover timed out during {}",
+            cancel.stage().unwrap_or_else(|| "startup".to_string())
+        );
+        process::exit(124);
+    });
+}
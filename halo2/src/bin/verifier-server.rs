@@ -0,0 +1,254 @@
+use std::{
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+
+use folding_halo2::{
+    audit::{digest, AuditLog, Operation, Subject},
+    build_info::exit_if_version_json,
+    capabilities::capabilities,
+    errors::{classify, exit_on_error, ErrorCode, ErrorReport},
+    http::{read_request, write_response, Limits, Request, Response},
+    transcript::TranscriptKind,
+    verify::VerifierKeys,
+    ParsedPublicInputs, ProofMetadata,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Verification-only daemon for folded block proofs (no proving key material)"
+)]
+struct Args {
+    /// Serve from a bundle written by `yysfold keys export-verifier`. It
+    /// holds only the verifying key and verifier params, so no setup runs
+    /// and the daemon never holds a key config's setup secrets
+    #[arg(long = "verifier-bundle")]
+    verifier_bundle: PathBuf,
+    #[arg(long, default_value = "127.0.0.1:8090")]
+    listen: String,
+    #[arg(long = "max-body-bytes", default_value_t = 256 * 1024)]
+    max_body_bytes: usize,
+    #[arg(long = "max-connections", default_value_t = 32)]
+    max_connections: usize,
+    #[arg(long = "read-timeout-secs", default_value_t = 10)]
+    read_timeout_secs: u64,
+    /// Deadline for receiving a whole request; slower clients get a 408
+    #[arg(long = "request-timeout-secs", default_value_t = 30)]
+    request_timeout_secs: u64,
+    /// Append a record of every verification to this hash-chained audit log
+    #[arg(long = "audit-log")]
+    audit_log: Option<PathBuf>,
+    /// Print the build (crate version, git commit, features, circuit hash)
+    /// as JSON and exit
+    #[arg(long = "version-json")]
+    _version_json: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyRequest {
+    proof: String,
+    #[serde(rename = "publicInputs")]
+    public_inputs: ParsedPublicInputs,
+    /// Proof metadata; when present its versions are checked before verifying
+    /// and its transcript is used.
+    #[serde(default)]
+    metadata: Option<ProofMetadata>,
+}
+
+struct State {
+    keys: VerifierKeys,
+    limits: Limits,
+    active: AtomicUsize,
+    max_connections: usize,
+    audit: Option<AuditLog>,
+    key_fingerprint: Option<String>,
+}
+
+fn main() {
+    exit_if_version_json();
+    exit_on_error(run());
+}
+
+fn run() -> Result<()> {
+    let args = Args::parse();
+
+    let keys = VerifierKeys::from_bundle(&args.verifier_bundle)?;
+    let fingerprint = digest(
+        &std::fs::read(&args.verifier_bundle)
+            .with_context(|| format!("opening {:?}", args.verifier_bundle))?,
+    );
+    let state = Arc::new(State {
+        keys,
+        limits: Limits {
+            max_body_bytes: args.max_body_bytes,
+            read_timeout: Duration::from_secs(args.read_timeout_secs),
+            request_timeout: Duration::from_secs(args.request_timeout_secs),
+            ..Limits::default()
+        },
+        active: AtomicUsize::new(0),
+        max_connections: args.max_connections,
+        audit: args.audit_log.as_deref().map(AuditLog::open).transpose()?,
+        key_fingerprint: Some(fingerprint),
+    });
+
+    let listener = TcpListener::bind(&args.listen)?;
+    eprintln!("verifier-server listening on {}", args.listen);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("accept failed: {err}");
+                continue;
+            }
+        };
+        if state.active.fetch_add(1, Ordering::SeqCst) >= state.max_connections {
+            state.active.fetch_sub(1, Ordering::SeqCst);
+            let _ = write_response(&stream, &Response::error(503, "too many connections"));
+            continue;
+        }
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(&state, &stream) {
+                eprintln!("connection error: {err}");
+            }
+            state.active.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(state: &State, stream: &TcpStream) -> Result<()> {
+    let response = match read_request(stream, &state.limits) {
+        Ok(request) => route(state, &request),
+        Err(err) => Response::error(err.status, err.message),
+    };
+    write_response(stream, &response)
+}
+
+fn route(state: &State, request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/verify") => handle_verify(state, request),
+        ("GET", "/healthz") => Response::json(200, &serde_json::json!({ "status": "ok" })),
+        ("GET", "/capabilities") => Response::json(200, &capabilities()),
+        (_, "/verify") | (_, "/healthz") | (_, "/capabilities") => {
+            Response::error(405, "method not allowed")
+        }
+        _ => Response::error(404, "not found"),
+    }
+}
+
+fn handle_verify(state: &State, request: &Request) -> Response {
+    let payload: VerifyRequest = match serde_json::from_slice(&request.body) {
+        Ok(payload) => payload,
+        Err(err) => {
+            return Response::report(
+                400,
+                &ErrorReport::with_code(
+                    ErrorCode::InvalidInput,
+                    format!("invalid request body: {err}"),
+                ),
+            )
+        }
+    };
+    let proof_hex = payload
+        .proof
+        .trim_start_matches("0x")
+        .trim_start_matches("0X");
+    if proof_hex.len() != 2 * state.keys.proof_bytes() {
+        return rejected(
+            422,
+            ErrorReport::with_code(
+                ErrorCode::MalformedProof,
+                format!(
+                    "proof is {} hex digits, these keys produce {}-byte proofs",
+                    proof_hex.len(),
+                    state.keys.proof_bytes()
+                ),
+            ),
+        );
+    }
+    let proof = match hex::decode(proof_hex) {
+        Ok(proof) => proof,
+        Err(err) => {
+            return Response::report(
+                400,
+                &ErrorReport::with_code(
+                    ErrorCode::InvalidInput,
+                    format!("invalid proof hex: {err}"),
+                ),
+            )
+        }
+    };
+    let mut transcript = TranscriptKind::default();
+    if let Some(metadata) = payload.metadata {
+        let metadata = metadata.into_latest();
+        if let Err(err) = metadata.check_compatibility() {
+            return rejected(
+                409,
+                ErrorReport::with_code(ErrorCode::Incompatible, err.to_string()),
+            );
+        }
+        if let Err(err) = metadata.check_scale(state.keys.circuit.scale()) {
+            return rejected(409, ErrorReport::new(&err));
+        }
+        transcript = metadata.transcript;
+    }
+    let instances = match payload.public_inputs.to_instances(&state.keys.circuit) {
+        Ok(instances) => instances,
+        Err(err) => {
+            return Response::report(
+                400,
+                &ErrorReport::with_code(classify(&err), format!("invalid public inputs: {err}")),
+            )
+        }
+    };
+
+    let started = Instant::now();
+    let result = state.keys.verify_in(&instances, &proof, transcript);
+    if let Some(audit) = &state.audit {
+        let subject = Subject::default()
+            .input("proof", &proof)
+            .input("request", &request.body)
+            .key(state.key_fingerprint.clone());
+        let error = result.as_ref().err().map(|err| format!("{err:#}"));
+        let elapsed = started.elapsed().as_millis() as u64;
+        if let Err(err) = audit.append(Operation::Verify, subject, elapsed, error) {
+            eprintln!("audit log write failed: {err:#}");
+            return Response::error(500, "audit log unavailable");
+        }
+    }
+
+    match result {
+        Ok(()) if state.keys.circuit.sla_bound => Response::json(
+            200,
+            &serde_json::json!({ "valid": true, "slaBound": payload.public_inputs.sla_bound }),
+        ),
+        Ok(()) => Response::json(200, &serde_json::json!({ "valid": true })),
+        Err(err) => rejected(422, ErrorReport::new(&err)),
+    }
+}
+
+/// A `valid: false` answer carrying the catalogue code of the rejection.
+fn rejected(status: u16, report: ErrorReport) -> Response {
+    Response::json(
+        status,
+        &serde_json::json!({
+            "valid": false,
+            "error": report.message,
+            "code": report.code,
+            "hint": report.hint,
+            "docs": report.docs,
+        }),
+    )
+}
@@ -1,18 +1,23 @@
-use std::{fs::File, io::Read, path::PathBuf};
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
-use halo2_proofs::{
-    plonk::verify_proof,
-    poly::commitment::ParamsProver,
-    poly::kzg::{
-        commitment::KZGCommitmentScheme, multiopen::VerifierGWC, strategy::SingleStrategy,
-    },
-    transcript::{Blake2bRead, Challenge255, TranscriptReadBuffer},
-};
-use halo2curves::bn256::{Bn256, Fr, G1Affine};
 
-use folding_halo2::{circuit::FoldedCircuit, keys::load_params_and_vk, load_public_inputs};
+use folding_halo2::{
+    audit::{audited, digest, open_optional, Operation, Subject},
+    build_info::exit_if_version_json,
+    container::{is_container, ProofContainer, CONTAINER_MAGIC},
+    errors::{exit_on_error, Coded, ErrorCode},
+    keys::key_fingerprint,
+    load_public_inputs,
+    metadata::{read_sidecar, sidecar_path},
+    transcript::TranscriptKind,
+    verify::VerifierKeys,
+};
 
 #[derive(Parser, Debug)]
 #[command(version, about = "Halo2 verifier for folded blocks")]
@@ -21,42 +26,166 @@ struct Args {
     proof: PathBuf,
     #[arg(long = "public-inputs")]
     public_inputs: PathBuf,
-    #[arg(long = "verification-key")]
-    verification_key: PathBuf,
+    #[arg(
+        long = "verification-key",
+        required_unless_present_any = ["verifier_bundle", "vk"],
+        conflicts_with_all = ["verifier_bundle", "vk"]
+    )]
+    verification_key: Option<PathBuf>,
+    /// Verify with a bundle from `yysfold keys export-verifier` instead of a key config
+    #[arg(long = "verifier-bundle", conflicts_with = "vk")]
+    verifier_bundle: Option<PathBuf>,
+    /// Serialized verifying key (`.vk` from `yysfold keys migrate`), read
+    /// with the layout embedded in a `prover --container` proof
+    #[arg(long, requires = "params")]
+    vk: Option<PathBuf>,
+    /// Serialized params (`.params`, or the verifier params from
+    /// `yysfold keys export-verifier --params-output`) to go with --vk, or
+    /// the trusted setup (halo2 params or `.ptau`) a --verification-key
+    /// config was built on
+    #[arg(long, conflicts_with = "verifier_bundle")]
+    params: Option<PathBuf>,
+    /// Skip the proof format / circuit version check against `<proof>.meta.json`
+    #[arg(long = "ignore-metadata")]
+    ignore_metadata: bool,
+    /// Fiat-Shamir transcript the proof was made under; defaults to the one
+    /// recorded in `<proof>.meta.json`, or blake2b
+    #[arg(long)]
+    transcript: Option<TranscriptKind>,
+    /// Append a verify record to this hash-chained audit log
+    #[arg(long = "audit-log")]
+    audit_log: Option<PathBuf>,
+    /// Print the build (crate version, git commit, features, circuit hash)
+    /// as JSON and exit
+    #[arg(long = "version-json")]
+    _version_json: bool,
+}
+
+fn main() {
+    exit_if_version_json();
+    exit_on_error(run());
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     let args = Args::parse();
+    let audit = open_optional(args.audit_log.as_deref())?;
 
-    let public_inputs = load_public_inputs(&args.public_inputs)?;
-    let instances = public_inputs.to_field_elements()?;
-    let blank = FoldedCircuit::blank(instances.len());
-    let instance_container = vec![instances];
-    let instance_refs: Vec<&[Fr]> = instance_container.iter().map(|v| v.as_slice()).collect();
-    let circuit_instances: Vec<&[&[Fr]]> = vec![&instance_refs[..]];
+    audited(
+        audit.as_ref(),
+        Operation::Verify,
+        || {
+            Subject::default()
+                .input_file("proof", &args.proof)
+                .input_file("publicInputs", &args.public_inputs)
+                .key(keys_fingerprint(&args))
+        },
+        || verify(&args),
+    )
+}
 
-    let (params, vk) = load_params_and_vk(&args.verification_key, &blank)?;
-    let params_verifier = params.verifier_params();
-    let strategy = SingleStrategy::new(params_verifier);
+fn verify(args: &Args) -> Result<()> {
+    if args.vk.is_some() || is_container_file(&args.proof)? {
+        return verify_container(args);
+    }
+    let has_metadata = !args.ignore_metadata && sidecar_path(&args.proof).exists();
+    let mut transcript = TranscriptKind::default();
+    let mut metadata = None;
+    if has_metadata {
+        let latest = read_sidecar(&args.proof)?.into_latest();
+        latest.check_compatibility()?;
+        transcript = latest.transcript;
+        metadata = Some(latest);
+    }
+    let transcript = args.transcript.unwrap_or(transcript);
+
+    let public_inputs = load_public_inputs(&args.public_inputs)?;
+    let keys = match (&args.verifier_bundle, &args.verification_key) {
+        (Some(bundle), _) => VerifierKeys::from_bundle(bundle)?,
+        (None, Some(config)) => {
+            VerifierKeys::from_config_with_setup(config, args.params.as_deref())?
+        }
+        (None, None) => anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "pass --verification-key or --verifier-bundle"
+        )),
+    };
+    if let Some(metadata) = &metadata {
+        metadata.check_scale(keys.circuit.scale())?;
+    }
 
+    // Read at most one byte past a valid proof; the length check rejects it.
     let mut proof_bytes = Vec::new();
-    File::open(&args.proof)?.read_to_end(&mut proof_bytes)?;
-
-    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof_bytes[..]);
-
-    verify_proof::<
-        KZGCommitmentScheme<Bn256>,
-        VerifierGWC<'_, Bn256>,
-        Challenge255<G1Affine>,
-        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
-        SingleStrategy<'_, Bn256>,
-    >(
-        params_verifier,
-        &vk,
-        strategy,
-        &circuit_instances,
-        &mut transcript,
-    )?;
+    File::open(&args.proof)
+        .with_context(|| format!("opening {:?}", args.proof))?
+        .take(keys.proof_bytes() as u64 + 1)
+        .read_to_end(&mut proof_bytes)
+        .with_context(|| format!("reading {:?}", args.proof))?;
+
+    let report = keys
+        .verify_bytes(&proof_bytes, &public_inputs, Some(transcript))
+        .with_context(|| {
+            if has_metadata {
+                "proof rejected".to_string()
+            } else {
+                format!(
+                "proof rejected (no {:?}; a prover/verifier version mismatch also fails this way)",
+                sidecar_path(&args.proof)
+            )
+            }
+        })?;
+    if let Some(sla) = report.sla_bound {
+        eprintln!("every vector is within the SLA bound {sla}");
+    }
 
     Ok(())
 }
+
+fn is_container_file(proof: &Path) -> Result<bool> {
+    let mut magic = Vec::new();
+    File::open(proof)
+        .with_context(|| format!("opening {:?}", proof))?
+        .take(CONTAINER_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    Ok(is_container(&magic))
+}
+
+/// Verifies a self-describing proof: the layout, versions and transcript
+/// come from its header, checked against the keys' verifying key.
+fn verify_container(args: &Args) -> Result<()> {
+    let bytes = fs::read(&args.proof).with_context(|| format!("opening {:?}", args.proof))?;
+    let container =
+        ProofContainer::from_bytes(&bytes).with_context(|| format!("reading {:?}", args.proof))?;
+    let keys = match (
+        &args.vk,
+        &args.params,
+        &args.verifier_bundle,
+        &args.verification_key,
+    ) {
+        (Some(vk), Some(params), _, _) => {
+            let vk = fs::read(vk).with_context(|| format!("opening {:?}", vk))?;
+            let params = fs::read(params).with_context(|| format!("opening {:?}", params))?;
+            VerifierKeys::from_raw(&params, &vk, container.header.circuit.clone())?
+        }
+        (_, _, Some(bundle), _) => VerifierKeys::from_bundle(bundle)?,
+        (_, params, None, Some(config)) => {
+            VerifierKeys::from_config_with_setup(config, params.as_deref())?
+        }
+        _ => anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "pass --verification-key, --verifier-bundle or --vk with --params"
+        )),
+    };
+    let public_inputs = load_public_inputs(&args.public_inputs)?;
+    container
+        .verify(&public_inputs, &keys)
+        .context("proof rejected")
+}
+
+fn keys_fingerprint(args: &Args) -> Option<String> {
+    match (&args.verifier_bundle, &args.verification_key, &args.vk) {
+        (Some(bundle), _, _) => std::fs::read(bundle).ok().map(|bytes| digest(&bytes)),
+        (None, Some(config), _) => key_fingerprint(config).ok(),
+        (None, None, Some(vk)) => std::fs::read(vk).ok().map(|bytes| digest(&bytes)),
+        (None, None, None) => None,
+    }
+}
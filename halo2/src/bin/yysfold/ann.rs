@@ -0,0 +1,142 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    ann::{load_ann_public_inputs, load_ann_witness, AnnCircuit, AnnParams},
+    audit::{audited, open_optional, Operation, Subject},
+    keys::{key_fingerprint, load_or_init_keys, load_params_and_vk, read_circuit_shape},
+    prove::create_circuit_proof,
+    storage::write_atomic,
+    verify::verify_with_keys,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct ProveArgs {
+    /// ANN witness: query, returned vectors, distance threshold and, for top-k
+    /// proofs, the candidate set
+    #[arg(long)]
+    witness: PathBuf,
+    #[arg(long = "proving-key")]
+    proving_key: PathBuf,
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    #[arg(long = "circuit-k", default_value_t = 14)]
+    circuit_k: u32,
+    #[arg(long)]
+    output: PathBuf,
+    /// Where to write the public inputs; defaults to `<output>.public.json`
+    #[arg(long = "public-inputs")]
+    public_inputs: Option<PathBuf>,
+    /// Append keygen and prove records to this hash-chained audit log
+    #[arg(long = "audit-log")]
+    audit_log: Option<PathBuf>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct VerifyArgs {
+    #[arg(long)]
+    proof: PathBuf,
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    /// Witness-shaped file with the query and returned vectors to check the
+    /// commitments against
+    #[arg(long)]
+    results: Option<PathBuf>,
+    /// Append a verify record to this hash-chained audit log
+    #[arg(long = "audit-log")]
+    audit_log: Option<PathBuf>,
+}
+
+pub fn run_prove(args: ProveArgs) -> Result<()> {
+    let audit = open_optional(args.audit_log.as_deref())?;
+    let witness = load_ann_witness(&args.witness)?;
+    let circuit = AnnCircuit::from_witness(&witness)?;
+    let blank = AnnCircuit::blank(&circuit.params);
+    let fingerprint = || key_fingerprint(&args.proving_key).ok();
+    let (params, pk) = audited(
+        audit.as_ref(),
+        Operation::Keygen,
+        || Subject::default().key(fingerprint()),
+        || {
+            load_or_init_keys(
+                &args.proving_key,
+                &args.verification_key,
+                args.circuit_k,
+                &blank,
+            )
+        },
+    )?;
+    let proof = audited(
+        audit.as_ref(),
+        Operation::Prove,
+        || {
+            Subject::default()
+                .input_file("witness", &args.witness)
+                .key(fingerprint())
+        },
+        || create_circuit_proof(&params, &pk, &circuit, &circuit.instances),
+    )?;
+    write_atomic(&args.output, &proof)?;
+
+    let public_path = args.public_inputs.unwrap_or_else(|| {
+        let mut path = args.output.clone().into_os_string();
+        path.push(".public.json");
+        path.into()
+    });
+    fs::write(
+        &public_path,
+        serde_json::to_vec_pretty(&witness.public_inputs())?,
+    )?;
+    println!(
+        "ANN proof for {} results written to {:?} ({:?})",
+        circuit.params.results, args.output, public_path
+    );
+    Ok(())
+}
+
+pub fn run_verify(args: VerifyArgs) -> Result<()> {
+    let audit = open_optional(args.audit_log.as_deref())?;
+    audited(
+        audit.as_ref(),
+        Operation::Verify,
+        || {
+            Subject::default()
+                .input_file("proof", &args.proof)
+                .input_file("publicInputs", &args.public_inputs)
+                .key(key_fingerprint(&args.verification_key).ok())
+        },
+        || verify(&args),
+    )
+}
+
+fn verify(args: &VerifyArgs) -> Result<()> {
+    let public_inputs = load_ann_public_inputs(&args.public_inputs)?;
+    if let Some(path) = &args.results {
+        let served = load_ann_witness(path)?;
+        public_inputs.check_against(&served.query, &served.results)?;
+        if let Some(candidates) = &served.candidates {
+            public_inputs.check_candidates(candidates)?;
+        }
+    }
+    let shape: AnnParams = read_circuit_shape(&args.verification_key)?;
+    let blank = AnnCircuit::blank(&shape);
+    let (params, vk) = load_params_and_vk(&args.verification_key, &blank)?;
+    let proof = fs::read(&args.proof).with_context(|| format!("opening {:?}", args.proof))?;
+    verify_with_keys(&params, &vk, &public_inputs.to_instances(&shape)?, &proof)?;
+    if shape.top_k() {
+        println!(
+            "top {} of {} candidates, all within {} of the committed query",
+            shape.results, shape.candidates, public_inputs.threshold
+        );
+    } else {
+        println!(
+            "{} results within {} of the committed query",
+            shape.results, public_inputs.threshold
+        );
+    }
+    Ok(())
+}
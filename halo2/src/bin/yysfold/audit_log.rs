@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::audit::verify_chain;
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long)]
+    path: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let entries = verify_chain(&args.path)?;
+    println!("{:?}: {entries} entries, chain intact", args.path);
+    Ok(())
+}
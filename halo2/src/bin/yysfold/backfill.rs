@@ -0,0 +1,133 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    backfill::{Backfill, FileSource, PublicInputsSource, RpcSource, DEFAULT_RPC_METHOD},
+    cancel::{parse_timeout, CancellationToken},
+    errors::{Coded, ErrorCode},
+    prover::Prover,
+    storage::{path_key, LocalStorage},
+    transcript::TranscriptKind,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// First height to backfill; defaults to the lowest in --public-inputs
+    #[arg(long, required_unless_present = "public_inputs")]
+    from: Option<u64>,
+    /// Last height to backfill, inclusive; defaults to the highest in
+    /// --public-inputs
+    #[arg(long, required_unless_present = "public_inputs")]
+    to: Option<u64>,
+    /// JSON-RPC endpoint serving public inputs by height (`http://` only)
+    #[arg(
+        long = "rpc-url",
+        required_unless_present = "public_inputs",
+        conflicts_with = "public_inputs"
+    )]
+    rpc_url: Option<String>,
+    /// File holding a JSON array of blocks' public inputs, each matched to
+    /// `<height>.witness.json` by its blockHeight
+    #[arg(long = "public-inputs")]
+    public_inputs: Option<PathBuf>,
+    #[arg(long = "rpc-method", default_value = DEFAULT_RPC_METHOD)]
+    rpc_method: String,
+    /// Directory holding `<height>.witness.json`
+    #[arg(long = "witness-dir")]
+    witness_dir: PathBuf,
+    /// Directory for `<height>.proof`, its sidecar and `<height>.public.json`
+    #[arg(long = "output-dir")]
+    output_dir: PathBuf,
+    #[arg(long = "proving-key", required_unless_present = "dry_run")]
+    proving_key: Option<PathBuf>,
+    /// Circuit shape and `k` are taken from this config when it exists
+    #[arg(long = "verification-key", required_unless_present = "dry_run")]
+    verification_key: Option<PathBuf>,
+    #[arg(long = "circuit-k", default_value_t = 12)]
+    circuit_k: u32,
+    /// Report the heights that are missing without proving them
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+    /// Give up after this long (`90`, `500ms`, `5m`, `1h`)
+    #[arg(long, value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+    /// Write simulated proofs, rejected by every verifier; for integration
+    /// testing
+    #[arg(long)]
+    simulate: bool,
+    #[arg(long, default_value = "blake2b")]
+    transcript: TranscriptKind,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let (source, listed): (Box<dyn PublicInputsSource>, _) =
+        match (&args.public_inputs, &args.rpc_url) {
+            (Some(path), _) => {
+                let source = FileSource::load(path)?;
+                let heights = source.heights();
+                (Box::new(source), heights)
+            }
+            (None, Some(url)) => (
+                Box::new(RpcSource::new(url).with_method(&args.rpc_method)),
+                None,
+            ),
+            (None, None) => anyhow::bail!(Coded::new(
+                ErrorCode::Usage,
+                "pass --rpc-url or --public-inputs"
+            )),
+        };
+    let (Some(from), Some(to)) = (
+        args.from
+            .or(listed.as_ref().map(|heights| *heights.start())),
+        args.to.or(listed.as_ref().map(|heights| *heights.end())),
+    ) else {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "--public-inputs lists no blocks; pass --from and --to"
+        ));
+    };
+    if from > to {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            format!("--from {from} is above --to {to}")
+        ));
+    }
+    let prover = match (&args.proving_key, &args.verification_key, args.dry_run) {
+        (Some(proving_key), Some(verification_key), false) => Some(
+            Prover::load(proving_key, verification_key, args.circuit_k)?
+                .with_simulation(args.simulate)
+                .with_transcript(args.transcript),
+        ),
+        _ => None,
+    };
+    let storage = LocalStorage::default();
+    let backfill = Backfill {
+        witnesses: &storage,
+        witness_prefix: path_key(&args.witness_dir),
+        proofs: &storage,
+        proof_prefix: path_key(&args.output_dir),
+        source: source.as_ref(),
+    };
+    let cancel = CancellationToken::with_optional_timeout(args.timeout);
+    let report = backfill.run(from..=to, prover.as_ref(), &cancel)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    eprintln!(
+        "{} proved, {} already proven, {} gaps",
+        report.proved.len(),
+        report.existing.len(),
+        report.gaps.len()
+    );
+    if !report.gaps.is_empty() && !args.dry_run {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Io,
+            format!(
+                "{} of {} heights could not be proven",
+                report.gaps.len(),
+                to - from + 1
+            )
+        ));
+    }
+    Ok(())
+}
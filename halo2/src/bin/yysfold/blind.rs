@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    hiding::{hiding_commitments, Blinding},
+    io::load_witness,
+    load_public_inputs,
+    storage::write_atomic,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long)]
+    witness: PathBuf,
+    /// Blinded witness to write, for `prover --hiding`
+    #[arg(long)]
+    output: PathBuf,
+    /// Keep the witness's blinding factors instead of drawing fresh ones
+    #[arg(long)]
+    keep: bool,
+    /// Public inputs; written back to --public-output with foldedCommitment
+    /// and pqCommitment replaced by the hiding commitments
+    #[arg(long = "public-inputs", requires = "public_output")]
+    public_inputs: Option<PathBuf>,
+    #[arg(long = "public-output")]
+    public_output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let mut witness = load_witness(&args.witness)?;
+    if !args.keep || witness.blinding.is_none() {
+        witness.blinding = Some(Blinding::random());
+    }
+    let [folded, pq] = hiding_commitments(&witness)?;
+    write_atomic(&args.output, &serde_json::to_vec(&witness)?)?;
+
+    if let (Some(input), Some(output)) = (&args.public_inputs, &args.public_output) {
+        let mut public_inputs = load_public_inputs(input)?;
+        public_inputs.folded_commitment = folded;
+        public_inputs.pq_commitment = pq;
+        write_atomic(output, &serde_json::to_vec_pretty(&public_inputs)?)?;
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({
+            "foldedCommitment": folded,
+            "pqCommitment": pq,
+        }))?
+    );
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::capabilities::capabilities;
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Print the full capabilities document as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let capabilities = capabilities();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&capabilities)?);
+        return Ok(());
+    }
+    let names = |values: Vec<String>| values.join(", ");
+    println!(
+        "curve: {} ({}, {})",
+        capabilities.curve,
+        capabilities.commitment_scheme,
+        names(
+            capabilities
+                .opening_schemes
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        )
+    );
+    println!(
+        "transcripts: {}",
+        names(
+            capabilities
+                .transcripts
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        )
+    );
+    println!(
+        "witness encodings: {}",
+        names(
+            capabilities
+                .witness_encodings
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        )
+    );
+    println!(
+        "proves: circuit {}, proof format {}",
+        capabilities.circuit_version, capabilities.proof_format_version
+    );
+    for range in &capabilities.verifies {
+        println!(
+            "verifies: proof format {}, circuits {}..={}",
+            range.proof_format, range.oldest_circuit, range.newest_circuit
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    codebook::{load_committed_codebook, CommitMode, CommittedCodebook},
+    errors::{Coded, ErrorCode},
+    io::load_witness,
+    load_public_inputs,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Witness carrying the codebook to commit
+    #[arg(long, conflicts_with = "verify", required_unless_present = "verify")]
+    witness: Option<PathBuf>,
+    /// merkle (per-centroid leaves) or poseidon (single sponge)
+    #[arg(long, default_value = "merkle")]
+    mode: CommitMode,
+    /// Write the codebook with its root and mode here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Recompute the root of a previously committed codebook instead
+    #[arg(long)]
+    verify: Option<PathBuf>,
+    /// Also require codebookRoot in these public inputs to match
+    #[arg(long = "public-inputs")]
+    public_inputs: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let committed = match (&args.witness, &args.verify) {
+        (_, Some(path)) => {
+            let committed = load_committed_codebook(path)?;
+            committed.verify()?;
+            committed
+        }
+        (Some(path), None) => {
+            let codebook = load_witness(path)?
+                .codebook
+                .context("witness has no codebook")?;
+            CommittedCodebook::new(codebook, args.mode)
+        }
+        (None, None) => anyhow::bail!(Coded::new(ErrorCode::Usage, "pass --witness or --verify")),
+    };
+
+    if let Some(path) = &args.public_inputs {
+        let expected = load_public_inputs(path)?.codebook_root;
+        if expected != committed.root {
+            anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!(
+                    "public inputs codebookRoot {expected} does not match {} ({})",
+                    committed.root, committed.mode
+                )
+            ));
+        }
+    }
+
+    if args.verify.is_some() {
+        println!(
+            "codebook root {} ({}) verified",
+            committed.root, committed.mode
+        );
+        return Ok(());
+    }
+    let json = serde_json::to_string_pretty(&committed)?;
+    match args.output {
+        Some(path) => {
+            fs::write(&path, json)?;
+            println!("codebook root {} ({})", committed.root, committed.mode);
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use std::{io, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Args as ClapArgs, Command};
+use clap_complete::Shell;
+
+#[derive(ClapArgs, Debug)]
+pub struct CompletionsArgs {
+    /// bash, elvish, fish, powershell or zsh
+    shell: Shell,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct ManArgs {
+    /// Write yysfold.1 and one page per subcommand here instead of printing
+    /// the top-level page
+    #[arg(long = "output-dir")]
+    output_dir: Option<PathBuf>,
+}
+
+pub fn run_completions(args: CompletionsArgs, mut command: Command) -> Result<()> {
+    let name = command.get_name().to_string();
+    clap_complete::generate(args.shell, &mut command, name, &mut io::stdout());
+    Ok(())
+}
+
+pub fn run_man(args: ManArgs, command: Command) -> Result<()> {
+    match &args.output_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {:?}", dir))?;
+            clap_mangen::generate_to(command, dir)
+                .with_context(|| format!("writing man pages to {:?}", dir))?;
+            eprintln!("wrote man pages to {:?}", dir);
+        }
+        None => clap_mangen::Man::new(command).render(&mut io::stdout())?,
+    }
+    Ok(())
+}
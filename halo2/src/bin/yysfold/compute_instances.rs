@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    io::load_witness,
+    keys::read_circuit_params,
+    load_public_inputs,
+    prove::{build_circuit, circuit_params, CircuitModes},
+    public_inputs::field_to_hex,
+    storage::write_atomic,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long)]
+    witness: PathBuf,
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+    /// Key config whose circuit shape to compute for; defaults to the
+    /// commitment-only circuit
+    #[arg(long = "verification-key")]
+    verification_key: Option<PathBuf>,
+    /// Write JSON here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+/// Converts the witness and public inputs exactly as the prover would,
+/// checking every commitment the keyed circuit binds, but stops short of
+/// proving.
+pub fn run(args: Args) -> Result<()> {
+    let witness = load_witness(&args.witness)?;
+    let public_inputs = load_public_inputs(&args.public_inputs)?;
+    let params = match &args.verification_key {
+        Some(path) => read_circuit_params(path)?,
+        None => circuit_params(&witness, CircuitModes::default())?,
+    };
+    let circuit = build_circuit(&witness, &public_inputs, &params)?;
+    let hex = |values: &[_]| values.iter().map(field_to_hex).collect::<Vec<_>>();
+    let mut report = serde_json::json!({
+        "params": &params,
+        "instances": hex(&circuit.public_inputs),
+        "residuals": hex(&circuit.epsilon_squared),
+    });
+    if params.instance_hash {
+        report["publicValues"] = hex(&circuit.hashed_inputs).into();
+    }
+    let json = serde_json::to_string_pretty(&report)?;
+    match &args.output {
+        Some(path) => write_atomic(path, json.as_bytes())?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
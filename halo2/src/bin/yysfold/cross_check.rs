@@ -0,0 +1,73 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    crosscheck::cross_check,
+    errors::{Coded, ErrorCode},
+    io::load_witness,
+    load_public_inputs,
+    prover::Prover,
+    selftest::canary_block,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long = "proving-key")]
+    proving_key: PathBuf,
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    #[arg(long = "circuit-k", default_value_t = 12)]
+    circuit_k: u32,
+    /// Directory of `<name>.witness.json` and `<name>.public.json` pairs;
+    /// the canary block for the keyed layout always runs
+    #[arg(long)]
+    corpus: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let prover = Prover::load(&args.proving_key, &args.verification_key, args.circuit_k)?;
+
+    let canary = canary_block(prover.layout())?;
+    let mut cases = cross_check(&prover, "canary", &canary.witness, &canary.public_inputs)?;
+
+    if let Some(corpus) = &args.corpus {
+        let mut names: Vec<String> = fs::read_dir(corpus)
+            .with_context(|| format!("opening {:?}", corpus))?
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                name.strip_suffix(".witness.json").map(str::to_string)
+            })
+            .collect();
+        names.sort();
+        for name in names {
+            let witness = load_witness(corpus.join(format!("{name}.witness.json")))?;
+            let public_inputs = load_public_inputs(corpus.join(format!("{name}.public.json")))?;
+            cases.extend(
+                cross_check(&prover, &name, &witness, &public_inputs)
+                    .with_context(|| format!("building {name}"))?,
+            );
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&cases)?);
+    let divergent: Vec<_> = cases.iter().filter(|case| !case.agrees()).collect();
+    if !divergent.is_empty() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::VerificationFailed,
+            format!(
+                "{} of {} cases diverge between MockProver and the real prover: {}",
+                divergent.len(),
+                cases.len(),
+                divergent
+                    .iter()
+                    .map(|case| format!("{} ({:?})", case.name, case.mutation))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        ));
+    }
+    eprintln!("{} cases agree", cases.len());
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    bytes::Hash256, delta::diff, io::load_witness, load_public_inputs, merkle,
+    storage::write_atomic,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Full witness of the previous block
+    #[arg(long)]
+    previous: PathBuf,
+    /// Full witness of this block
+    #[arg(long)]
+    current: PathBuf,
+    /// Delta witness to write, for `prover --delta`
+    #[arg(long)]
+    output: PathBuf,
+    /// This block's public inputs; written back to --public-output with
+    /// previousVectorRoot and foldedVectorRoot filled in
+    #[arg(long = "public-inputs", requires = "public_output")]
+    public_inputs: Option<PathBuf>,
+    #[arg(long = "public-output")]
+    public_output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let current = load_witness(&args.current)?;
+    let delta = diff(&load_witness(&args.previous)?, &current)?;
+    write_atomic(&args.output, &serde_json::to_vec(&delta)?)?;
+
+    let section = delta.delta.as_ref().expect("diff writes a delta section");
+    let folded = current
+        .fixed_point()?
+        .to_field_matrix(&current.folded_vectors)
+        .context("foldedVectors")?;
+    let root = Hash256::from_field(&merkle::vector_root(&folded));
+    if let (Some(input), Some(output)) = (&args.public_inputs, &args.public_output) {
+        let mut public_inputs = load_public_inputs(input)?;
+        public_inputs.previous_vector_root = Some(section.previous_vector_root);
+        public_inputs.folded_vector_root = Some(root);
+        write_atomic(output, &serde_json::to_vec_pretty(&public_inputs)?)?;
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({
+            "changedVectors": section.indices.len(),
+            "vectorCount": section.vector_count,
+            "previousVectorRoot": section.previous_vector_root,
+            "foldedVectorRoot": root,
+        }))?
+    );
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    errors::{Coded, ErrorCode},
+    fixed_point::resolve_scale,
+    io::load_witness,
+    reference::{compare_rows, differential, DifferentialReport},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Compare on this witness instead of random ones
+    #[arg(long)]
+    witness: Option<PathBuf>,
+    #[arg(long, default_value_t = 1000)]
+    rounds: usize,
+    #[arg(long, default_value_t = 4)]
+    vectors: usize,
+    #[arg(long, default_value_t = 16)]
+    dim: usize,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Fixed-point scale to compare at; defaults to the witness's `scale`,
+    /// or 1e6
+    #[arg(long)]
+    scale: Option<u64>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let report = match &args.witness {
+        Some(path) => {
+            let witness = load_witness(path)?;
+            let fixed_point = resolve_scale(args.scale, witness.scale)?;
+            DifferentialReport {
+                seed: args.seed,
+                rounds: 1,
+                values_checked: 2 * witness.folded_vectors.iter().map(Vec::len).sum::<usize>(),
+                discrepancies: compare_rows(
+                    &witness.folded_vectors,
+                    &witness.pq_vectors,
+                    fixed_point,
+                )?,
+            }
+        }
+        None => differential(
+            args.seed,
+            args.rounds,
+            args.vectors,
+            args.dim,
+            resolve_scale(args.scale, None)?,
+        )?,
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.discrepancies.is_empty() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Internal,
+            format!(
+                "{} discrepancies between the field and reference residuals",
+                report.discrepancies.len()
+            )
+        ));
+    }
+    Ok(())
+}
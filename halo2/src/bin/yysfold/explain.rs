@@ -0,0 +1,27 @@
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::errors::{Coded, ErrorCode};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Error code to describe (e.g. YF003); lists the whole catalogue when omitted
+    code: Option<String>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let codes = match &args.code {
+        Some(code) => match ErrorCode::from_code(code) {
+            Some(code) => vec![code],
+            None => anyhow::bail!(Coded::new(
+                ErrorCode::Usage,
+                format!("unknown error code {code:?}")
+            )),
+        },
+        None => ErrorCode::ALL.to_vec(),
+    };
+    for code in codes {
+        println!("{code}  {}\n  hint: {}", code.docs_slug(), code.hint());
+    }
+    Ok(())
+}
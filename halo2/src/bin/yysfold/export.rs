@@ -0,0 +1,35 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    export::{export_instances, ExportEncoding, ExportTarget},
+    load_public_inputs,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+    /// Target field: bn254, bls12-381 or goldilocks
+    #[arg(long, default_value = "bls12-381")]
+    target: ExportTarget,
+    /// Encoding: hashed (per-slot hash-to-field) or limbs (lossless split of the BN254 instances)
+    #[arg(long, default_value = "hashed")]
+    encoding: ExportEncoding,
+    /// Write JSON here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let public_inputs = load_public_inputs(&args.public_inputs)?;
+    let exported = export_instances(&public_inputs, args.target, args.encoding)?;
+    let json = serde_json::to_string_pretty(&exported)?;
+    match args.output {
+        Some(path) => fs::write(path, json)?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    errors::{Coded, ErrorCode},
+    load_public_inputs,
+    metadata::{read_sidecar, sidecar_path},
+    shard::ShardManifest,
+    storage::write_atomic,
+    summary::{export, summarize},
+    verify::VerifierKeys,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Proof files; a .meta.json sidecar next to one supplies its transcript
+    /// and proving time
+    #[arg(long = "proof", required = true, num_args = 1..)]
+    proofs: Vec<PathBuf>,
+    /// Public inputs of each proof, in the same order as --proof
+    #[arg(long = "public-inputs", required = true, num_args = 1..)]
+    public_inputs: Vec<PathBuf>,
+    #[arg(
+        long = "verification-key",
+        required_unless_present = "verifier_bundle",
+        conflicts_with = "verifier_bundle"
+    )]
+    verification_key: Option<PathBuf>,
+    #[arg(long = "verifier-bundle")]
+    verifier_bundle: Option<PathBuf>,
+    /// Shard manifests from `shard combine`; proofs found in one are marked as
+    /// its members
+    #[arg(long = "manifest")]
+    manifests: Vec<PathBuf>,
+    /// Write the summary here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    if args.proofs.len() != args.public_inputs.len() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            format!(
+                "{} proofs but {} public inputs; pass one --public-inputs per --proof",
+                args.proofs.len(),
+                args.public_inputs.len()
+            )
+        ));
+    }
+    let keys = match (&args.verifier_bundle, &args.verification_key) {
+        (Some(bundle), _) => VerifierKeys::from_bundle(bundle)?,
+        (None, Some(config)) => VerifierKeys::from_config(config)?,
+        (None, None) => anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "pass --verification-key or --verifier-bundle"
+        )),
+    };
+    let manifests = args
+        .manifests
+        .iter()
+        .map(|path| {
+            let bytes = fs::read(path).with_context(|| format!("opening {:?}", path))?;
+            serde_json::from_slice::<ShardManifest>(&bytes)
+                .with_context(|| format!("parsing shard manifest {:?}", path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut blocks = Vec::with_capacity(args.proofs.len());
+    for (proof_path, public_inputs_path) in args.proofs.iter().zip(&args.public_inputs) {
+        let public_inputs = load_public_inputs(public_inputs_path)?;
+        let proof = fs::read(proof_path).with_context(|| format!("opening {:?}", proof_path))?;
+        let metadata = if sidecar_path(proof_path).exists() {
+            Some(read_sidecar(proof_path)?.into_latest())
+        } else {
+            None
+        };
+        let summary = summarize(&public_inputs, &proof, metadata.as_ref(), &keys, &manifests)
+            .with_context(|| format!("{:?}", proof_path))?;
+        blocks.push(summary);
+    }
+    let json = serde_json::to_string_pretty(&export(blocks))?;
+    match args.output {
+        Some(path) => write_atomic(&path, json.as_bytes())?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
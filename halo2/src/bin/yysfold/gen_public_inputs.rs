@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    codebook::CommitMode,
+    io::load_witness,
+    storage::write_atomic,
+    synthetic::{template_public_inputs, TemplateOptions},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long)]
+    witness: PathBuf,
+    #[arg(long = "block-height", default_value_t = 1)]
+    block_height: u64,
+    /// How codebookRoot is derived from the witness codebook
+    #[arg(long = "codebook-mode", default_value = "merkle")]
+    codebook_mode: CommitMode,
+    /// Nonzeros the sparse circuit is keyed for (defaults to the widest row)
+    #[arg(long)]
+    nonzeros: Option<usize>,
+    /// Poseidon foldedCommitment and pqCommitment, for `prover --vector-commitments`
+    #[arg(long = "vector-commitments")]
+    vector_commitments: bool,
+    /// slaBound, the maximum reconstruction error; defaults to the smallest
+    /// the witness satisfies
+    #[arg(long = "sla-bound")]
+    sla_bound: Option<f64>,
+    /// Changed vectors per delta witness the circuit is keyed for
+    #[arg(long)]
+    delta: Option<usize>,
+    /// Write here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let witness = load_witness(&args.witness)?;
+    let public_inputs = template_public_inputs(
+        &witness,
+        &TemplateOptions {
+            block_height: args.block_height,
+            codebook_mode: args.codebook_mode,
+            nonzeros: args.nonzeros,
+            vector_commitments: args.vector_commitments,
+            sla_bound: args.sla_bound,
+            delta: args.delta,
+        },
+    )?;
+    let json = serde_json::to_string_pretty(&public_inputs)?;
+    match &args.output {
+        Some(path) => write_atomic(path, json.as_bytes())?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
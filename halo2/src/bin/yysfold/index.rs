@@ -0,0 +1,118 @@
+use std::{ops::RangeInclusive, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Args as ClapArgs, Subcommand};
+
+use folding_halo2::{
+    errors::{Coded, ErrorCode},
+    index::{IndexEntry, ProofIndex},
+    keys::key_fingerprint,
+    storage::{path_key, LocalStorage},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Index database; created on first use
+    #[arg(long, default_value = "yysfold-index.sqlite")]
+    db: PathBuf,
+    #[command(subcommand)]
+    command: IndexCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum IndexCommand {
+    /// Record a proof, its public inputs and its sidecar
+    Add(AddArgs),
+    /// List indexed proofs by block height
+    Query(QueryArgs),
+    /// Check every entry against the artifacts it points at
+    Check,
+    /// List where a proof's artifacts were replicated
+    Replicas(ReplicasArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct AddArgs {
+    #[arg(long)]
+    proof: PathBuf,
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+    /// Key config the proof verifies under, recorded by fingerprint
+    #[arg(long = "verification-key")]
+    verification_key: Option<PathBuf>,
+}
+
+#[derive(ClapArgs, Debug)]
+struct ReplicasArgs {
+    #[arg(long)]
+    proof: PathBuf,
+}
+
+#[derive(ClapArgs, Debug)]
+struct QueryArgs {
+    /// Inclusive heights, `a..b`; either end may be left open
+    #[arg(long = "height-range", value_parser = parse_height_range, default_value = "..")]
+    height_range: RangeInclusive<u64>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let index = ProofIndex::open(&args.db)?;
+    let storage = LocalStorage::default();
+    match args.command {
+        IndexCommand::Add(add) => {
+            let fingerprint = add
+                .verification_key
+                .as_deref()
+                .map(key_fingerprint)
+                .transpose()?;
+            let entry = IndexEntry::from_artifacts(
+                &storage,
+                &path_key(&add.proof),
+                &path_key(&add.public_inputs),
+                fingerprint,
+            )?;
+            index.record(&entry)?;
+            eprintln!("indexed block {} at {:?}", entry.height, add.proof);
+        }
+        IndexCommand::Query(query) => {
+            let entries = index.query(query.height_range)?;
+            println!("{}", serde_json::to_string_pretty(&entries)?);
+        }
+        IndexCommand::Check => {
+            let problems = index.check(&storage)?;
+            for problem in &problems {
+                eprintln!("{}: {}", problem.proof_key, problem.problem);
+            }
+            if !problems.is_empty() {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "{} index entries disagree with the artifact store",
+                        problems.len()
+                    )
+                ));
+            }
+            eprintln!("index consistent with the artifact store");
+        }
+        IndexCommand::Replicas(replicas) => {
+            let replicas = index.replicas(&path_key(&replicas.proof))?;
+            println!("{}", serde_json::to_string_pretty(&replicas)?);
+        }
+    }
+    Ok(())
+}
+
+fn parse_height_range(raw: &str) -> Result<RangeInclusive<u64>> {
+    let (start, end) = raw
+        .split_once("..")
+        .with_context(|| format!("height range {raw:?} is not of the form a..b"))?;
+    let bound = |value: &str, open: u64| -> Result<u64> {
+        if value.is_empty() {
+            return Ok(open);
+        }
+        value
+            .parse()
+            .with_context(|| format!("height {value:?} in range {raw:?}"))
+    };
+    Ok(bound(start, 0)?..=bound(end.trim_start_matches('='), u64::MAX)?)
+}
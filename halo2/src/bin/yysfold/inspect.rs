@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    inspect::inspect_instances, keys::read_circuit_params, load_public_inputs, FoldedParams,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+    /// Key config whose circuit shape fixes the slot layout; without it only
+    /// the three commitment slots are shown
+    #[arg(long = "verification-key")]
+    verification_key: Option<PathBuf>,
+    /// Print JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let params = match &args.verification_key {
+        Some(path) => read_circuit_params(path)?,
+        None => FoldedParams::default(),
+    };
+    let report = inspect_instances(&load_public_inputs(&args.public_inputs)?, &params)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    println!("circuit version {}", report.circuit_version);
+    for slot in &report.slots {
+        println!(
+            "[{}] {}  ({})\n    source {}\n    value  {}",
+            slot.slot, slot.name, slot.encoding, slot.source, slot.value
+        );
+    }
+    if let Some(hash) = &report.instance_hash {
+        println!("instance (sha256 of the values above, mod p)\n    value  {hash}");
+    }
+    Ok(())
+}
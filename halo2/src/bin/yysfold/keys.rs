@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Args as ClapArgs, Subcommand};
+
+use folding_halo2::{
+    circuit::FoldedCircuit,
+    keys::{export_verifier_bundle_in, plan_key_migration_in, read_circuit_k, read_circuit_params},
+    selftest::run_self_test_with,
+    srs::{load_srs, setup_fingerprint, write_srs},
+    storage::{path_key, write_atomic, LocalStorage},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: KeysCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum KeysCommand {
+    /// Serialize the keys of a seed-only config pair so loads stop regenerating them
+    Migrate(MigrateArgs),
+    /// Write a verifier bundle (verifier params + verifying key) so verifiers skip setup
+    ExportVerifier(ExportVerifierArgs),
+    /// Convert a trusted setup (`.ptau` or halo2 params) to halo2 params at one `k`
+    ConvertParams(ConvertParamsArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct MigrateArgs {
+    #[arg(long = "proving-key")]
+    proving_key: PathBuf,
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    /// Trusted setup the keys were built on, for configs that record one
+    #[arg(long)]
+    params: Option<PathBuf>,
+    /// Regenerate and round-trip the keys without writing anything
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+struct ExportVerifierArgs {
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    #[arg(long)]
+    output: PathBuf,
+    /// Also write the verifier params alone, for `verifier --params`
+    #[arg(long = "params-output")]
+    params_output: Option<PathBuf>,
+    /// Trusted setup the keys were built on, for configs that record one
+    #[arg(long)]
+    params: Option<PathBuf>,
+}
+
+#[derive(ClapArgs, Debug)]
+struct ConvertParamsArgs {
+    #[arg(long)]
+    input: PathBuf,
+    #[arg(long)]
+    output: PathBuf,
+    /// Largest `k` the converted params support
+    #[arg(long)]
+    k: u32,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        KeysCommand::Migrate(args) => migrate(args),
+        KeysCommand::ExportVerifier(args) => export_verifier(args),
+        KeysCommand::ConvertParams(args) => convert_params(args),
+    }
+}
+
+fn migrate(args: MigrateArgs) -> Result<()> {
+    let storage = LocalStorage::default();
+    let circuit_params = read_circuit_params(&args.verification_key)?;
+    let circuit_k = read_circuit_k(&args.verification_key)?;
+    let blank = FoldedCircuit::blank_with(&circuit_params);
+    let setup = args
+        .params
+        .as_deref()
+        .map(|path| load_srs(path, circuit_k))
+        .transpose()?;
+    let migration = plan_key_migration_in(
+        &storage,
+        &path_key(&args.proving_key),
+        &path_key(&args.verification_key),
+        &blank,
+        setup.as_ref(),
+    )?;
+    let report = run_self_test_with(
+        &migration.params,
+        &migration.pk,
+        &migration.vk,
+        circuit_k,
+        &circuit_params,
+    )?;
+    eprintln!(
+        "round-trip proof with the serialized keys verified ({} bytes)",
+        report.proof_bytes
+    );
+
+    let fingerprints = serde_json::json!({
+        "provingKey": migration.proving_artifacts,
+        "verificationKey": migration.verifying_artifacts,
+    });
+    if args.dry_run {
+        eprintln!("dry run: nothing written");
+    } else {
+        migration.commit(&storage)?;
+    }
+    println!("{}", serde_json::to_string_pretty(&fingerprints)?);
+    Ok(())
+}
+
+fn export_verifier(args: ExportVerifierArgs) -> Result<()> {
+    let circuit_params = read_circuit_params(&args.verification_key)?;
+    let blank = FoldedCircuit::blank_with(&circuit_params);
+    let setup = match &args.params {
+        Some(path) => Some(load_srs(path, read_circuit_k(&args.verification_key)?)?),
+        None => None,
+    };
+    let bundle = export_verifier_bundle_in(
+        &LocalStorage::default(),
+        &path_key(&args.verification_key),
+        &blank,
+        setup.as_ref(),
+    )?;
+    write_atomic(&args.output, &serde_json::to_vec_pretty(&bundle)?)?;
+    if let (Some(path), Some(verifier_params)) = (&args.params_output, &bundle.verifier_params) {
+        write_atomic(path, &hex::decode(verifier_params)?)?;
+    }
+    eprintln!(
+        "wrote verifier bundle for k={} to {:?}",
+        bundle.circuit_k, args.output
+    );
+    Ok(())
+}
+
+fn convert_params(args: ConvertParamsArgs) -> Result<()> {
+    let params = load_srs(&args.input, args.k)?;
+    write_atomic(&args.output, &write_srs(&params)?)?;
+    eprintln!("wrote k={} params to {:?}", args.k, args.output);
+    println!("{}", setup_fingerprint(&params));
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+use halo2curves::bn256::Fr;
+
+use folding_halo2::{
+    errors::{Coded, ErrorCode},
+    io::{load_witness, WitnessData},
+    layout::{compare, LayoutVariant, Objective},
+    prove::to_field_matrix,
+    synthetic::{generate, SyntheticConfig},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Witness to lay out; a synthetic block is generated when omitted
+    #[arg(long)]
+    witness: Option<PathBuf>,
+    #[arg(long, default_value_t = 8)]
+    vectors: usize,
+    #[arg(long, default_value_t = 16)]
+    dim: usize,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Advice column counts to try
+    #[arg(long, value_delimiter = ',', default_value = "1,2,4,8")]
+    columns: Vec<usize>,
+    /// Gate styles to try: fused, simple
+    #[arg(long, value_delimiter = ',', default_value = "fused,simple")]
+    gates: Vec<String>,
+    /// prove-time or proof-size
+    #[arg(long, default_value = "prove-time")]
+    objective: Objective,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let witness = match &args.witness {
+        Some(path) => load_witness(path)?,
+        None => {
+            let config = SyntheticConfig {
+                vectors: args.vectors,
+                dim: args.dim,
+                seed: args.seed,
+                ..SyntheticConfig::default()
+            };
+            generate(&config)?.witness
+        }
+    };
+    let (folded, pq) = flatten(&witness)?;
+
+    let mut variants = Vec::new();
+    for columns in &args.columns {
+        if *columns == 0 {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Usage,
+                "--columns entries must be at least 1"
+            ));
+        }
+        for gates in &args.gates {
+            let fused = match gates.as_str() {
+                "fused" => true,
+                "simple" => false,
+                other => anyhow::bail!(Coded::new(
+                    ErrorCode::Usage,
+                    format!("unknown gate style {other:?} (fused or simple)")
+                )),
+            };
+            variants.push(LayoutVariant {
+                columns: *columns,
+                fused,
+            });
+        }
+    }
+
+    let report = compare(&variants, &folded, &pq, args.objective, |measurement| {
+        eprintln!(
+            "{}: k={} proof={} bytes prove={} ms",
+            measurement.variant,
+            measurement.circuit_k,
+            measurement.proof_bytes,
+            measurement.prove_ms
+        );
+    })?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+fn flatten(witness: &WitnessData) -> Result<(Vec<Fr>, Vec<Fr>)> {
+    if witness.folded_vectors.len() != witness.pq_vectors.len() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "witness has {} folded vectors but {} pq vectors",
+                witness.folded_vectors.len(),
+                witness.pq_vectors.len()
+            )
+        ));
+    }
+    let folded: Vec<Fr> = to_field_matrix(&witness.folded_vectors)
+        .into_iter()
+        .flatten()
+        .collect();
+    let pq: Vec<Fr> = to_field_matrix(&witness.pq_vectors)
+        .into_iter()
+        .flatten()
+        .collect();
+    if folded.len() != pq.len() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            "folded and pq vectors differ in dimension"
+        ));
+    }
+    Ok((folded, pq))
+}
@@ -0,0 +1,33 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::metadata::{check_lineage, read_sidecar};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Proof files oldest first, each with its .meta.json sidecar
+    #[arg(long = "proof", required = true, num_args = 1..)]
+    proofs: Vec<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let mut links = 0;
+    for pair in args.proofs.windows(2).rev() {
+        let (previous_path, current_path) = (&pair[0], &pair[1]);
+        let previous_proof =
+            fs::read(previous_path).with_context(|| format!("opening {:?}", previous_path))?;
+        let previous = read_sidecar(previous_path)?.into_latest();
+        let current = read_sidecar(current_path)?.into_latest();
+        check_lineage(&previous_proof, &previous, &current)
+            .with_context(|| format!("{:?} -> {:?}", previous_path, current_path))?;
+        links += 1;
+    }
+    println!(
+        "{:?}: {links} links back to {:?}, lineage intact",
+        args.proofs[args.proofs.len() - 1],
+        args.proofs[0]
+    );
+    Ok(())
+}
@@ -0,0 +1,192 @@
+mod ann;
+mod audit_log;
+mod backfill;
+mod blind;
+mod capabilities;
+mod commit_codebook;
+mod completions;
+mod compute_instances;
+mod cross_check;
+mod delta;
+mod diff_residuals;
+mod explain;
+mod export;
+mod export_summary;
+mod gen_public_inputs;
+#[cfg(feature = "index")]
+mod index;
+mod inspect;
+mod keys;
+mod layout_bench;
+mod lineage;
+mod open_vector;
+mod pq_plan;
+mod proof_size;
+mod prove;
+mod replay;
+#[cfg(feature = "schema")]
+mod schema;
+mod seal;
+mod search;
+mod self_test;
+mod settle;
+mod shard;
+mod sign;
+mod witness;
+mod witness_opening;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::{CommandFactory, Parser, Subcommand};
+use folding_halo2::{
+    build_info::exit_if_version_json,
+    errors::exit_on_error,
+    keygen::{set_keygen_progress, stderr_progress},
+};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Folded block proving toolkit")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Print the build (crate version, git commit, features, circuit hash)
+    /// as JSON and exit
+    #[arg(long = "version-json", global = true)]
+    _version_json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Re-encode public inputs into another chain's scalar field
+    ExportInstances(export::Args),
+    /// Produce a Merkle opening for one folded vector of a block
+    OpenVector(open_vector::OpenArgs),
+    /// Check a vector opening against a block's foldedVectorRoot
+    VerifyOpening(open_vector::VerifyArgs),
+    /// Prove that returned ANN results lie within a distance of a committed query,
+    /// optionally as the ordered top-k of a candidate set
+    ProveAnn(ann::ProveArgs),
+    /// Verify a distance-threshold ANN proof
+    VerifyAnn(ann::VerifyArgs),
+    /// Answer a nearest-neighbour query over a block's vectors
+    Search(search::Args),
+    /// Choose a PQ subvector partition for an embedding size
+    PqPlan(pq_plan::Args),
+    /// Break down proof bytes into commitments, evaluations and opening proof
+    ProofSize(proof_size::Args),
+    /// Compute or check a codebook's codebookRoot
+    CommitCodebook(commit_codebook::Args),
+    /// Encrypt a witness for storage, or decrypt it again
+    SealWitness(seal::Args),
+    /// Convert witnesses between JSON and CBOR
+    Witness(witness::Args),
+    /// Sign a witness's digest as its generator, for `prover --witness-signers`
+    SignWitness(sign::Args),
+    /// Check the hash chain of an audit log
+    VerifyAuditLog(audit_log::Args),
+    /// Walk a chain of proofs back through their previousProofDigest links
+    VerifyLineage(lineage::Args),
+    /// Prove and verify a built-in canary block against the configured keys
+    SelfTest(self_test::Args),
+    /// List the transcripts, encodings and versions this build supports
+    Capabilities(capabilities::Args),
+    /// Check that MockProver and real proving accept exactly the same cases
+    CrossCheck(cross_check::Args),
+    /// Compare proof size and proving time across advice column layouts
+    LayoutBench(layout_bench::Args),
+    /// Manage key configs
+    Keys(keys::Args),
+    /// Describe an error code, or list them all
+    Explain(explain::Args),
+    /// Compare the field residual code against a slow exact reference
+    DiffResiduals(diff_residuals::Args),
+    /// Re-run a prover run captured with `prover --record`
+    Replay(replay::Args),
+    /// Prove a block too large for one circuit as equal shards and bind them
+    Shard(shard::Args),
+    /// Build a delta witness of the vectors changed since the previous block
+    Delta(delta::Args),
+    /// Fabricate public inputs consistent with a witness, for devnets
+    GenPublicInputs(gen_public_inputs::Args),
+    /// Open witness rows against a block's witnessCommitment for an auditor
+    OpenRows(witness_opening::OpenArgs),
+    /// Check witness row openings against a block's witnessCommitment
+    VerifyRowOpenings(witness_opening::VerifyArgs),
+    /// Show each instance value with its slot, source public input and encoding
+    Inspect(inspect::Args),
+    /// Compute the instances and residuals of a witness without proving
+    ComputeInstances(compute_instances::Args),
+    /// Verify proofs and summarize their blocks as explorer-friendly JSON
+    ExportSummary(export_summary::Args),
+    /// Track proven blocks and their artifacts in a SQLite index
+    #[cfg(feature = "index")]
+    Index(index::Args),
+    /// Watch a directory and prove witness/public-input pairs as they arrive
+    Prove(prove::Args),
+    /// Prove the missing blocks in a height range and report the gaps
+    Backfill(backfill::Args),
+    /// Print the JSON Schema of witnesses or public inputs
+    #[cfg(feature = "schema")]
+    Schema(schema::Args),
+    /// Add blinding factors to a witness and compute its hiding commitments
+    Blind(blind::Args),
+    /// Compute the aggregate instance commitment and calldata for settling a batch of blocks
+    Settle(settle::Args),
+    /// Print a shell completion script for yysfold
+    Completions(completions::CompletionsArgs),
+    /// Render the yysfold man pages
+    Man(completions::ManArgs),
+}
+
+fn main() {
+    exit_if_version_json();
+    exit_on_error(run());
+}
+
+fn run() -> Result<()> {
+    let cli = Cli::parse();
+    set_keygen_progress(Some(stderr_progress()), Duration::from_secs(10));
+    match cli.command {
+        Command::ExportInstances(args) => export::run(args),
+        Command::OpenVector(args) => open_vector::run_open(args),
+        Command::VerifyOpening(args) => open_vector::run_verify(args),
+        Command::ProveAnn(args) => ann::run_prove(args),
+        Command::VerifyAnn(args) => ann::run_verify(args),
+        Command::Search(args) => search::run(args),
+        Command::PqPlan(args) => pq_plan::run(args),
+        Command::ProofSize(args) => proof_size::run(args),
+        Command::CommitCodebook(args) => commit_codebook::run(args),
+        Command::SealWitness(args) => seal::run(args),
+        Command::Witness(args) => witness::run(args),
+        Command::SignWitness(args) => sign::run(args),
+        Command::VerifyAuditLog(args) => audit_log::run(args),
+        Command::VerifyLineage(args) => lineage::run(args),
+        Command::SelfTest(args) => self_test::run(args),
+        Command::Capabilities(args) => capabilities::run(args),
+        Command::CrossCheck(args) => cross_check::run(args),
+        Command::LayoutBench(args) => layout_bench::run(args),
+        Command::Keys(args) => keys::run(args),
+        Command::Explain(args) => explain::run(args),
+        Command::DiffResiduals(args) => diff_residuals::run(args),
+        Command::Replay(args) => replay::run(args),
+        Command::Shard(args) => shard::run(args),
+        Command::Delta(args) => delta::run(args),
+        Command::GenPublicInputs(args) => gen_public_inputs::run(args),
+        Command::OpenRows(args) => witness_opening::run_open(args),
+        Command::VerifyRowOpenings(args) => witness_opening::run_verify(args),
+        Command::Inspect(args) => inspect::run(args),
+        Command::ComputeInstances(args) => compute_instances::run(args),
+        Command::ExportSummary(args) => export_summary::run(args),
+        #[cfg(feature = "index")]
+        Command::Index(args) => index::run(args),
+        Command::Prove(args) => prove::run(args),
+        Command::Backfill(args) => backfill::run(args),
+        #[cfg(feature = "schema")]
+        Command::Schema(args) => schema::run(args),
+        Command::Blind(args) => blind::run(args),
+        Command::Settle(args) => settle::run(args),
+        Command::Completions(args) => completions::run_completions(args, Cli::command()),
+        Command::Man(args) => completions::run_man(args, Cli::command()),
+    }
+}
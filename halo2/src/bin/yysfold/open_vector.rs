@@ -0,0 +1,68 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    bytes::Hash256,
+    io::load_witness,
+    load_public_inputs,
+    opening::{open_vector, verify_opening, VectorOpening},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct OpenArgs {
+    #[arg(long)]
+    witness: PathBuf,
+    #[arg(long)]
+    index: usize,
+    /// Check the opening against foldedVectorRoot from these public inputs
+    #[arg(long = "public-inputs")]
+    public_inputs: Option<PathBuf>,
+    /// Write the opening here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct VerifyArgs {
+    #[arg(long)]
+    opening: PathBuf,
+    /// Public inputs of the proven block; the opening must lead to their
+    /// foldedVectorRoot
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+}
+
+pub fn run_open(args: OpenArgs) -> Result<()> {
+    let witness = load_witness(&args.witness)?;
+    let opening = open_vector(&witness, args.index)?;
+    if let Some(path) = &args.public_inputs {
+        let root = expected_root(path)?;
+        verify_opening(&opening, root)?;
+    }
+    let json = serde_json::to_string_pretty(&opening)?;
+    match args.output {
+        Some(path) => fs::write(path, json)?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+pub fn run_verify(args: VerifyArgs) -> Result<()> {
+    let bytes = fs::read(&args.opening).with_context(|| format!("opening {:?}", args.opening))?;
+    let opening: VectorOpening = serde_json::from_slice(&bytes)?;
+    let root = expected_root(&args.public_inputs)?;
+    verify_opening(&opening, root)?;
+    println!(
+        "leaf {} of {} opens to foldedVectorRoot {root}",
+        opening.index, opening.vector_count
+    );
+    Ok(())
+}
+
+fn expected_root(path: &std::path::Path) -> Result<Hash256> {
+    load_public_inputs(path)?
+        .folded_vector_root
+        .context("public inputs missing foldedVectorRoot")
+}
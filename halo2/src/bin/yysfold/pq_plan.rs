@@ -0,0 +1,36 @@
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    errors::{Coded, ErrorCode},
+    quantization::{plan_partition, EmbeddingPreset, DEFAULT_CENTROIDS, DEFAULT_SUB_DIM},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Embedding dimensionality to partition
+    #[arg(long, conflicts_with = "preset", required_unless_present = "preset")]
+    dim: Option<usize>,
+    /// Embedding preset: minilm-384, mpnet-768, openai-small-1536 or openai-large-3072
+    #[arg(long)]
+    preset: Option<EmbeddingPreset>,
+    /// Preferred components per subvector
+    #[arg(long = "sub-dim", default_value_t = DEFAULT_SUB_DIM)]
+    sub_dim: usize,
+    #[arg(long, default_value_t = DEFAULT_CENTROIDS)]
+    centroids: usize,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let dim = match (args.dim, args.preset) {
+        (Some(dim), _) => dim,
+        (None, Some(preset)) => preset.dim(),
+        (None, None) => anyhow::bail!(Coded::new(ErrorCode::Usage, "pass --dim or --preset")),
+    };
+    let plan = plan_partition(dim, args.sub_dim, args.centroids)?;
+    for warning in &plan.warnings {
+        eprintln!("warning: {warning}");
+    }
+    println!("{}", serde_json::to_string_pretty(&plan)?);
+    Ok(())
+}
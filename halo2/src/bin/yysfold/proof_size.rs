@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+use halo2curves::bn256::Fr;
+use serde::Serialize;
+
+use folding_halo2::{
+    circuit::FoldedCircuit,
+    errors::{Coded, ErrorCode},
+    keys::{load_params_and_vk, read_circuit_params},
+    layout::{layout_vk, measure, LayoutVariant},
+    proof_size::{breakdown, OpeningScheme, ProofSizeBreakdown},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Key configs to size; repeat for several
+    #[arg(long = "verification-key")]
+    verification_keys: Vec<PathBuf>,
+    /// Also size the layout-bench circuit at these advice column counts
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<usize>,
+    /// Use the fused residual gate for --columns layouts
+    #[arg(long)]
+    fused: bool,
+    /// Residual components for --columns layouts
+    #[arg(long, default_value_t = 128)]
+    components: usize,
+    #[arg(long, value_delimiter = ',', default_value = "gwc,shplonk")]
+    schemes: Vec<OpeningScheme>,
+    /// Prove each --columns layout and report the real GWC proof length
+    #[arg(long)]
+    measure: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Row {
+    configuration: String,
+    #[serde(flatten)]
+    breakdown: ProofSizeBreakdown,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    measured_bytes: Option<usize>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    if args.verification_keys.is_empty() && args.columns.is_empty() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "pass --verification-key or --columns"
+        ));
+    }
+    let mut rows = Vec::new();
+
+    for path in &args.verification_keys {
+        let circuit = read_circuit_params(path)?;
+        let (_, vk) = load_params_and_vk(path, &FoldedCircuit::blank_with(&circuit))?;
+        for scheme in &args.schemes {
+            rows.push(Row {
+                configuration: path.display().to_string(),
+                breakdown: breakdown(vk.cs(), *scheme),
+                measured_bytes: None,
+            });
+        }
+    }
+
+    for columns in &args.columns {
+        if *columns == 0 {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Usage,
+                "--columns entries must be at least 1"
+            ));
+        }
+        let variant = LayoutVariant {
+            columns: *columns,
+            fused: args.fused,
+        };
+        let vk = layout_vk(variant, args.components)?;
+        let measured = if args.measure {
+            let zeros = vec![Fr::zero(); args.components];
+            Some(measure(variant, &zeros, &zeros)?.proof_bytes)
+        } else {
+            None
+        };
+        for scheme in &args.schemes {
+            rows.push(Row {
+                configuration: variant.to_string(),
+                breakdown: breakdown(vk.cs(), *scheme),
+                measured_bytes: measured.filter(|_| *scheme == OpeningScheme::Gwc),
+            });
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&rows)?);
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    cancel::{parse_timeout, CancellationToken},
+    prover::Prover,
+    replication::{Replica, ReplicationConfig, Replicator},
+    storage::{path_key, LocalStorage},
+    transcript::TranscriptKind,
+    watch::{WatchEvent, Watcher},
+};
+#[cfg(feature = "index")]
+use folding_halo2::{
+    index::{IndexEntry, ProofIndex},
+    keys::key_fingerprint,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Directory to watch for `<name>.witness.json` + `<name>.public.json`
+    /// pairs; each is proven to `<name>.proof`
+    #[arg(long)]
+    watch: PathBuf,
+    #[arg(long = "proving-key")]
+    proving_key: PathBuf,
+    /// Circuit shape and `k` are taken from this config when it exists
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    #[arg(long = "circuit-k", default_value_t = 12)]
+    circuit_k: u32,
+    /// How long both files of a pair must stay unchanged before proving
+    #[arg(long, value_parser = parse_timeout, default_value = "2s")]
+    debounce: Duration,
+    #[arg(long = "poll-interval", value_parser = parse_timeout, default_value = "1s")]
+    poll_interval: Duration,
+    /// Stop watching after this long (`90`, `500ms`, `5m`, `1h`)
+    #[arg(long, value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+    /// Write simulated proofs, rejected by every verifier; for integration
+    /// testing
+    #[arg(long)]
+    simulate: bool,
+    #[arg(long, default_value = "blake2b")]
+    transcript: TranscriptKind,
+    /// Replication config; each proof, its sidecar and public inputs are
+    /// copied to every target listed there
+    #[arg(long)]
+    replicate: Option<PathBuf>,
+    /// Record proven blocks, and where they were replicated, in this index
+    #[cfg(feature = "index")]
+    #[arg(long)]
+    index: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let prover = Prover::load(&args.proving_key, &args.verification_key, args.circuit_k)?
+        .with_simulation(args.simulate)
+        .with_transcript(args.transcript);
+    let replicator = args
+        .replicate
+        .as_deref()
+        .map(ReplicationConfig::load)
+        .transpose()?
+        .map(Replicator::new);
+    #[cfg(feature = "index")]
+    let index = args.index.as_deref().map(ProofIndex::open).transpose()?;
+    #[cfg(feature = "index")]
+    let fingerprint = match index {
+        Some(_) => Some(key_fingerprint(&args.verification_key)?),
+        None => None,
+    };
+    let storage = LocalStorage::default();
+    let mut watcher = Watcher::new(&storage, &path_key(&args.watch), args.debounce);
+    let cancel = CancellationToken::with_optional_timeout(args.timeout);
+    eprintln!("watching {:?}", args.watch);
+    let watched = watcher.run(&prover, args.poll_interval, &cancel, |event| match event {
+        WatchEvent::Proved {
+            height,
+            proof_key,
+            public_inputs_key,
+            ..
+        } => {
+            eprintln!("proved block {height} to {proof_key}");
+            let mut replicas: Vec<Replica> = Vec::new();
+            if let Some(replicator) = &replicator {
+                let keys = [
+                    proof_key.clone(),
+                    format!("{proof_key}.meta.json"),
+                    public_inputs_key.clone(),
+                ];
+                match replicator.replicate(&storage, *height, &keys) {
+                    Ok(report) => {
+                        for failure in &report.failures {
+                            eprintln!(
+                                "replicating {} to {} failed: {}",
+                                failure.artifact_key, failure.target, failure.error
+                            );
+                        }
+                        replicas = report.replicas;
+                    }
+                    Err(err) => eprintln!("replicating block {height} failed: {err:#}"),
+                }
+            }
+            #[cfg(feature = "index")]
+            if let Some(index) = &index {
+                let indexed = IndexEntry::from_artifacts(
+                    &storage,
+                    proof_key,
+                    public_inputs_key,
+                    fingerprint.clone(),
+                )
+                .and_then(|entry| index.record(&entry))
+                .and_then(|()| index.record_replicas(proof_key, &replicas));
+                if let Err(err) = indexed {
+                    eprintln!("indexing block {height} failed: {err:#}");
+                }
+            }
+            #[cfg(not(feature = "index"))]
+            let _ = replicas;
+        }
+        WatchEvent::Quarantined { name, error } => {
+            eprintln!("quarantined {name}: {error}")
+        }
+    });
+    match watched {
+        Err(_) if cancel.is_cancelled() => Ok(()),
+        watched => watched,
+    }
+}
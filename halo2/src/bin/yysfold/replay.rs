@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    errors::{Coded, ErrorCode},
+    replay::Replay,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Archive written by `prover --record`
+    archive: PathBuf,
+    /// Print the recorded manifest without proving
+    #[arg(long)]
+    inspect: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let replay = Replay::read(&args.archive)?;
+    if args.inspect {
+        println!("{}", serde_json::to_string_pretty(&replay.manifest)?);
+        return Ok(());
+    }
+    for drift in replay.version_drift() {
+        eprintln!("recorded with a different build: {drift}");
+    }
+    let report = replay.run()?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.verified {
+        anyhow::bail!(Coded::new(
+            ErrorCode::VerificationFailed,
+            "replayed proof does not verify"
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::schema::SchemaKind;
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// `witness` or `public-inputs`
+    kind: SchemaKind,
+    /// Write the schema here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let json = serde_json::to_string_pretty(&args.kind.schema())?;
+    match args.output {
+        Some(path) => fs::write(&path, json).with_context(|| format!("writing {:?}", path))?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    encryption::{is_envelope, key_provider_from_env, open, seal, KEY_ENV, KEY_FILE_ENV},
+    errors::{Coded, ErrorCode},
+    io::WitnessFormat,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Witness (or envelope, with --decrypt) to read
+    #[arg(long)]
+    input: PathBuf,
+    #[arg(long)]
+    output: PathBuf,
+    /// Decrypt an envelope instead of sealing a plain witness
+    #[arg(long)]
+    decrypt: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let provider = key_provider_from_env()?
+        .with_context(|| format!("set {KEY_ENV} or {KEY_FILE_ENV} to a 32-byte hex key"))?;
+    let bytes = fs::read(&args.input).with_context(|| format!("opening {:?}", args.input))?;
+    let output = if args.decrypt {
+        open(&bytes, provider.as_ref())?
+    } else {
+        if is_envelope(&bytes) {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Usage,
+                format!("{:?} is already encrypted", args.input)
+            ));
+        }
+        if WitnessFormat::detect(&bytes) == WitnessFormat::Json {
+            serde_json::from_slice::<serde_json::Value>(&bytes)
+                .with_context(|| format!("{:?} is not a JSON or CBOR witness", args.input))?;
+        }
+        seal(&bytes, provider.as_ref())?
+    };
+    fs::write(&args.output, output)?;
+    println!(
+        "{} {:?} -> {:?} (key {})",
+        if args.decrypt { "decrypted" } else { "sealed" },
+        args.input,
+        args.output,
+        provider.key_id()
+    );
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+use serde::Serialize;
+
+use folding_halo2::{
+    io::load_witness,
+    search::{Hit, IndexSource, SearchIndex},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long)]
+    witness: PathBuf,
+    /// JSON array with the query vector
+    #[arg(long)]
+    query: PathBuf,
+    #[arg(long, default_value_t = 5)]
+    k: usize,
+    /// Vectors to search: folded, pq or codes (asymmetric distances over the codebook)
+    #[arg(long, default_value = "codes")]
+    source: IndexSource,
+    /// Also write an ANN witness for `yysfold prove-ann`
+    #[arg(long = "ann-witness")]
+    ann_witness: Option<PathBuf>,
+    /// Distance bound for the ANN witness; defaults to the furthest result
+    #[arg(long, requires = "ann_witness")]
+    threshold: Option<f64>,
+    /// Disclose this many nearest vectors as a top-k candidate set
+    #[arg(long, requires = "ann_witness")]
+    candidates: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchOutput {
+    source: IndexSource,
+    k: usize,
+    hits: Vec<Hit>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let witness = load_witness(&args.witness)?;
+    let bytes = fs::read(&args.query).with_context(|| format!("opening {:?}", args.query))?;
+    let query: Vec<f64> = serde_json::from_slice(&bytes).context("query must be a JSON array")?;
+    let index = SearchIndex::from_witness(&witness, args.source)?;
+    let hits = index.search(&query, args.k)?;
+
+    if let Some(path) = &args.ann_witness {
+        let attestation = index.attest(&query, args.k, args.threshold, args.candidates)?;
+        fs::write(path, serde_json::to_vec_pretty(&attestation)?)?;
+    }
+    let output = SearchOutput {
+        source: index.source(),
+        k: args.k,
+        hits,
+    };
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    circuit::FoldedCircuit,
+    keys::{load_or_init_keys, read_circuit_k, read_circuit_params},
+    selftest::{canary_params, run_self_test},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long = "proving-key")]
+    proving_key: PathBuf,
+    /// Circuit shape and `k` are taken from this config when it exists;
+    /// otherwise keys are generated for the default canary block
+    #[arg(long = "verification-key")]
+    verification_key: PathBuf,
+    #[arg(long = "circuit-k", default_value_t = 12)]
+    circuit_k: u32,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let (circuit_params, circuit_k) = if args.verification_key.exists() {
+        (
+            read_circuit_params(&args.verification_key)?,
+            read_circuit_k(&args.verification_key)?,
+        )
+    } else {
+        (canary_params(), args.circuit_k)
+    };
+    let blank = FoldedCircuit::blank_with(&circuit_params);
+    let (params, pk) =
+        load_or_init_keys(&args.proving_key, &args.verification_key, circuit_k, &blank)?;
+    let report = run_self_test(&params, &pk, circuit_k, &circuit_params)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    circuit::FoldedParams,
+    keys::{read_circuit_params, read_verifier_bundle},
+    load_public_inputs,
+    settlement::settlement_batch,
+    storage::write_atomic,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    /// Public inputs of every block in the batch; heights must be consecutive
+    #[arg(long = "public-inputs", required = true, num_args = 1..)]
+    public_inputs: Vec<PathBuf>,
+    /// Key config the blocks were proved under; defaults to the
+    /// commitment-only circuit
+    #[arg(long = "verification-key", conflicts_with = "verifier_bundle")]
+    verification_key: Option<PathBuf>,
+    #[arg(long = "verifier-bundle")]
+    verifier_bundle: Option<PathBuf>,
+    /// Write JSON here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let params = match (&args.verifier_bundle, &args.verification_key) {
+        (Some(bundle), _) => read_verifier_bundle::<FoldedParams>(bundle)?.circuit,
+        (None, Some(config)) => read_circuit_params(config)?,
+        (None, None) => FoldedParams::default(),
+    };
+    let blocks = args
+        .public_inputs
+        .iter()
+        .map(load_public_inputs)
+        .collect::<Result<Vec<_>>>()?;
+    let batch = settlement_batch(&blocks, &params)?;
+    let json = serde_json::to_string_pretty(&batch)?;
+    match &args.output {
+        Some(path) => write_atomic(path, json.as_bytes())?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::{Args as ClapArgs, Subcommand};
+
+use folding_halo2::{
+    errors::{Coded, ErrorCode},
+    io::load_witness,
+    load_public_inputs,
+    shard::{check_manifest, combine, shard_public_inputs, split, ShardManifest, ShardProof},
+    storage::write_atomic,
+    verify::VerifierKeys,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: ShardCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum ShardCommand {
+    /// Split a block into shard witnesses and public inputs to prove one by one
+    Split(SplitArgs),
+    /// Verify every shard proof of a block and write the manifest binding them
+    Combine(CombineArgs),
+    /// Check a shard manifest against the block's public inputs
+    Check(CheckArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct SplitArgs {
+    #[arg(long)]
+    witness: PathBuf,
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+    /// Number of shards; must divide the block's vector count
+    #[arg(long)]
+    shards: usize,
+    /// Directory for `shard-NNN.witness.json` and `shard-NNN.public.json`
+    #[arg(long)]
+    out: PathBuf,
+}
+
+#[derive(ClapArgs, Debug)]
+struct CombineArgs {
+    /// The block's public inputs, as given to `split`
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+    /// Directory holding `shard-NNN.public.json` and the proofs `shard-NNN.proof`
+    #[arg(long)]
+    dir: PathBuf,
+    #[arg(
+        long = "verification-key",
+        required_unless_present = "verifier_bundle",
+        conflicts_with = "verifier_bundle"
+    )]
+    verification_key: Option<PathBuf>,
+    #[arg(long = "verifier-bundle")]
+    verifier_bundle: Option<PathBuf>,
+    #[arg(long)]
+    output: PathBuf,
+}
+
+#[derive(ClapArgs, Debug)]
+struct CheckArgs {
+    #[arg(long)]
+    manifest: PathBuf,
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        ShardCommand::Split(args) => run_split(args),
+        ShardCommand::Combine(args) => run_combine(args),
+        ShardCommand::Check(args) => run_check(args),
+    }
+}
+
+fn shard_path(dir: &Path, index: usize, suffix: &str) -> PathBuf {
+    dir.join(format!("shard-{index:03}.{suffix}"))
+}
+
+fn run_split(args: SplitArgs) -> Result<()> {
+    let witness = load_witness(&args.witness)?;
+    let block = load_public_inputs(&args.public_inputs)?;
+    let shards = split(&witness, args.shards)?;
+    fs::create_dir_all(&args.out).with_context(|| format!("creating {:?}", args.out))?;
+    for (index, shard) in shards.iter().enumerate() {
+        let public_inputs = shard_public_inputs(&block, shard, index, shards.len())?;
+        write_atomic(
+            &shard_path(&args.out, index, "witness.json"),
+            &serde_json::to_vec(shard)?,
+        )?;
+        write_atomic(
+            &shard_path(&args.out, index, "public.json"),
+            &serde_json::to_vec_pretty(&public_inputs)?,
+        )?;
+    }
+    eprintln!(
+        "wrote {} shards of {} vectors to {:?}; prove each with the same keys as shard-NNN.proof",
+        shards.len(),
+        witness.folded_vectors.len() / shards.len(),
+        args.out
+    );
+    Ok(())
+}
+
+fn run_combine(args: CombineArgs) -> Result<()> {
+    let block = load_public_inputs(&args.public_inputs)?;
+    let keys = match (&args.verifier_bundle, &args.verification_key) {
+        (Some(bundle), _) => VerifierKeys::from_bundle(bundle)?,
+        (None, Some(config)) => VerifierKeys::from_config(config)?,
+        (None, None) => anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "pass --verification-key or --verifier-bundle"
+        )),
+    };
+    let mut shards = Vec::new();
+    while shard_path(&args.dir, shards.len(), "public.json").exists() {
+        let index = shards.len();
+        let proof_path = shard_path(&args.dir, index, "proof");
+        shards.push(ShardProof {
+            public_inputs: load_public_inputs(shard_path(&args.dir, index, "public.json"))?,
+            proof: fs::read(&proof_path).with_context(|| format!("opening {:?}", proof_path))?,
+        });
+    }
+    let manifest = combine(&block, &keys, &shards)?;
+    write_atomic(&args.output, &serde_json::to_vec_pretty(&manifest)?)?;
+    println!("{}", manifest.root);
+    Ok(())
+}
+
+fn run_check(args: CheckArgs) -> Result<()> {
+    let bytes = fs::read(&args.manifest).with_context(|| format!("opening {:?}", args.manifest))?;
+    let manifest: ShardManifest = serde_json::from_slice(&bytes)
+        .with_context(|| format!("parsing shard manifest {:?}", args.manifest))?;
+    check_manifest(&manifest, &load_public_inputs(&args.public_inputs)?)?;
+    println!(
+        "manifest {} binds {} shards to block {}",
+        manifest.root,
+        manifest.shards.len(),
+        manifest.block_height
+    );
+    Ok(())
+}
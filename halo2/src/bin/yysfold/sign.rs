@@ -0,0 +1,57 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+use zeroize::Zeroizing;
+
+use folding_halo2::{
+    io::load_witness,
+    signing::{generate_secret_key, manifest_path, parse_secret_key, public_key, sign_witness},
+    storage::write_atomic,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[arg(long)]
+    witness: PathBuf,
+    /// Hex ed25519 secret key of the witness generator
+    #[arg(long)]
+    key: PathBuf,
+    /// Write a fresh secret key to --key first; fails if it exists
+    #[arg(long = "generate-key")]
+    generate_key: bool,
+    /// Defaults to `<witness>.sig.json`
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    if args.generate_key {
+        if args.key.exists() {
+            anyhow::bail!(
+                "{:?} already exists; not overwriting a signing key",
+                args.key
+            );
+        }
+        let secret = generate_secret_key();
+        write_atomic(&args.key, hex::encode(secret.as_ref()).as_bytes())?;
+        eprintln!(
+            "wrote signing key {:?} (public key {})",
+            args.key,
+            public_key(&secret)?
+        );
+    }
+    let hex_key = Zeroizing::new(
+        fs::read_to_string(&args.key).with_context(|| format!("opening {:?}", args.key))?,
+    );
+    let secret = parse_secret_key(&hex_key)?;
+    let witness = load_witness(&args.witness)?;
+    let manifest = sign_witness(&witness, &secret)?;
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| manifest_path(&args.witness));
+    write_atomic(&output, &serde_json::to_vec_pretty(&manifest)?)?;
+    println!("{}", serde_json::to_string_pretty(&manifest)?);
+    Ok(())
+}
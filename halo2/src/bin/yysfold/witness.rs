@@ -0,0 +1,67 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Args as ClapArgs, Subcommand};
+
+use folding_halo2::{
+    encryption::is_envelope,
+    errors::{Coded, ErrorCode},
+    io::{decode_witness, encode_witness, WitnessFormat},
+    storage::write_atomic,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: WitnessCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum WitnessCommand {
+    /// Re-encode a witness as JSON or CBOR
+    Convert(ConvertArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct ConvertArgs {
+    /// Plain JSON or CBOR witness; decrypt envelopes with `seal-witness --decrypt` first
+    #[arg(long)]
+    input: PathBuf,
+    #[arg(long)]
+    output: PathBuf,
+    /// `json` or `cbor`; loaders detect either
+    #[arg(long, default_value = "cbor")]
+    format: WitnessFormat,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    match args.command {
+        WitnessCommand::Convert(args) => convert(args),
+    }
+}
+
+fn convert(args: ConvertArgs) -> Result<()> {
+    let bytes = fs::read(&args.input).with_context(|| format!("opening {:?}", args.input))?;
+    if is_envelope(&bytes) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            format!(
+                "{:?} is encrypted; decrypt it with `yysfold seal-witness --decrypt` first",
+                args.input
+            )
+        ));
+    }
+    let from = WitnessFormat::detect(&bytes);
+    let witness = decode_witness(&bytes, None, &format!("{:?}", args.input))?;
+    let output = encode_witness(&witness, args.format)?;
+    write_atomic(&args.output, &output)?;
+    eprintln!(
+        "{from} {:?} ({} bytes) -> {} {:?} ({} bytes)",
+        args.input,
+        bytes.len(),
+        args.format,
+        args.output,
+        output.len()
+    );
+    Ok(())
+}
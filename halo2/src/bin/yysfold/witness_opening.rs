@@ -0,0 +1,88 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::Args as ClapArgs;
+
+use folding_halo2::{
+    bytes::Hash256,
+    io::load_witness,
+    load_public_inputs,
+    storage::write_atomic,
+    witness_commitment::{open_row, verify_row_opening, RowOpening},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct OpenArgs {
+    /// Witness with the auditSalt the block was proven with
+    #[arg(long)]
+    witness: PathBuf,
+    /// Rows to open; each gets its own opening
+    #[arg(long = "index", required = true)]
+    indices: Vec<usize>,
+    /// Check the openings against witnessCommitment from these public inputs
+    #[arg(long = "public-inputs")]
+    public_inputs: Option<PathBuf>,
+    /// Write the openings here instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct VerifyArgs {
+    /// Openings from `open-rows`
+    #[arg(long)]
+    openings: PathBuf,
+    /// Public inputs of the proven block; witnessCommitment must match
+    #[arg(long = "public-inputs")]
+    public_inputs: PathBuf,
+}
+
+pub fn run_open(args: OpenArgs) -> Result<()> {
+    let witness = load_witness(&args.witness)?;
+    let expected = args
+        .public_inputs
+        .as_deref()
+        .map(expected_commitment)
+        .transpose()?;
+    let openings = args
+        .indices
+        .iter()
+        .map(|&index| {
+            let opening = open_row(&witness, index)?;
+            if let Some(expected) = expected {
+                verify_row_opening(&opening, expected)?;
+            }
+            Ok(opening)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let json = serde_json::to_string_pretty(&openings)?;
+    match args.output {
+        Some(path) => write_atomic(&path, json.as_bytes())?,
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+pub fn run_verify(args: VerifyArgs) -> Result<()> {
+    let bytes = fs::read(&args.openings).with_context(|| format!("opening {:?}", args.openings))?;
+    let openings: Vec<RowOpening> = serde_json::from_slice(&bytes)
+        .with_context(|| format!("parsing row openings {:?}", args.openings))?;
+    let expected = expected_commitment(&args.public_inputs)?;
+    for opening in &openings {
+        verify_row_opening(opening, expected)?;
+    }
+    println!(
+        "{} rows open against witnessCommitment {expected}",
+        openings.len()
+    );
+    Ok(())
+}
+
+fn expected_commitment(path: &Path) -> Result<Hash256> {
+    load_public_inputs(path)?
+        .witness_commitment
+        .context("public inputs missing witnessCommitment")
+}
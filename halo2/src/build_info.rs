@@ -0,0 +1,65 @@
+//! What this build of the prover is, recorded in every proof's metadata.
+//!
+//! An auditor holding a proof can check its [`BuildInfo`] against a
+//! checkout: the crate version and git commit pin the code, the features
+//! pin what was compiled in, and `circuitHash` (blake3 over the circuit,
+//! gadget and version sources, computed by `build.rs`) changes with any
+//! edit to the constraints, even one that forgot to bump `circuitVersion`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::compat::{CIRCUIT_VERSION, PROOF_FORMAT_VERSION};
+
+/// Flag every executable answers with [`BuildInfo::current`] as JSON.
+pub const VERSION_JSON_FLAG: &str = "--version-json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    pub crate_version: String,
+    /// Absent when built outside a git checkout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    /// Tracked files differed from `gitCommit` at build time.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub git_dirty: bool,
+    #[serde(default)]
+    pub features: Vec<String>,
+    pub circuit_version: u32,
+    pub proof_format_version: u32,
+    pub circuit_hash: String,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        let commit = env!("YYSFOLD_GIT_COMMIT");
+        let features = env!("YYSFOLD_FEATURES");
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: (!commit.is_empty()).then(|| commit.to_string()),
+            git_dirty: env!("YYSFOLD_GIT_DIRTY") == "true",
+            features: features
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .map(str::to_string)
+                .collect(),
+            circuit_version: CIRCUIT_VERSION,
+            proof_format_version: PROOF_FORMAT_VERSION,
+            circuit_hash: env!("YYSFOLD_CIRCUIT_SOURCE_DIGEST").to_string(),
+        }
+    }
+}
+
+/// Prints [`BuildInfo::current`] and exits when the process was started
+/// with [`VERSION_JSON_FLAG`]. Called before argument parsing, so the flag
+/// works without the arguments a command otherwise requires.
+pub fn exit_if_version_json() {
+    if std::env::args_os()
+        .skip(1)
+        .any(|arg| arg == VERSION_JSON_FLAG)
+    {
+        let json = serde_json::to_string_pretty(&BuildInfo::current()).unwrap_or_default();
+        println!("{json}");
+        std::process::exit(0);
+    }
+}
@@ -0,0 +1,215 @@
+//! Big-endian byte and limb helpers, and [`Hash256`], the typed 32-byte
+//! value behind every root, commitment and digest in the public inputs.
+//!
+//! Everything user-facing is big-endian, 0x-prefixed lowercase hex (EVM
+//! `uint256` order); field elements are little-endian in memory, so the
+//! conversions here are the one place the byte order flips.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result};
+use halo2curves::{
+    bn256::Fr,
+    ff::{FromUniformBytes, PrimeField},
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A 32-byte hash, root or field element, big-endian.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hash256(pub [u8; 32]);
+
+impl Hash256 {
+    pub const ZERO: Hash256 = Hash256([0; 32]);
+
+    /// Parses 64 hex digits, with or without a `0x` prefix.
+    pub fn from_hex(raw: &str) -> Result<Self> {
+        let bytes = decode_hex(raw)?;
+        let bytes: [u8; 32] = bytes.as_slice().try_into().with_context(|| {
+            format!(
+                "{raw} is {} bytes, expected 32 (64 hex digits)",
+                bytes.len()
+            )
+        })?;
+        Ok(Self(bytes))
+    }
+
+    /// 0x-prefixed lowercase hex.
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The canonical encoding of `value`.
+    pub fn from_field(value: &Fr) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(value.to_repr().as_ref());
+        bytes.reverse();
+        Self(bytes)
+    }
+
+    /// The field element this encodes, rejecting values `>= p`.
+    pub fn to_canonical_field(&self) -> Result<Fr> {
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.as_mut().copy_from_slice(&self.0);
+        repr.as_mut().reverse();
+        Option::<Fr>::from(Fr::from_repr(repr))
+            .with_context(|| format!("{self} is not a canonical field element"))
+    }
+
+    /// The value as a big-endian integer reduced mod p; for digests, which
+    /// need not be below the modulus.
+    pub fn to_field_reduced(&self) -> Fr {
+        let mut wide = [0u8; 64];
+        wide[..32].copy_from_slice(&self.0);
+        wide[..32].reverse();
+        Fr::from_uniform_bytes(&wide)
+    }
+
+    /// Splits into big-endian limbs of `bits` bits (a multiple of 8 dividing
+    /// 256), each zero-padded back to 32 bytes.
+    pub fn limbs(&self, bits: usize) -> Vec<Hash256> {
+        self.0
+            .chunks(bits / 8)
+            .map(|chunk| {
+                let mut limb = [0u8; 32];
+                limb[32 - chunk.len()..].copy_from_slice(chunk);
+                Hash256(limb)
+            })
+            .collect()
+    }
+
+    /// From little-endian 64-bit limbs.
+    pub fn from_u64_limbs(limbs: &[u64; 4]) -> Self {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.chunks_mut(8).zip(limbs.iter().rev()) {
+            chunk.copy_from_slice(&limb.to_be_bytes());
+        }
+        Self(bytes)
+    }
+}
+
+impl From<[u8; 32]> for Hash256 {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<blake3::Hash> for Hash256 {
+    fn from(hash: blake3::Hash) -> Self {
+        Self(*hash.as_bytes())
+    }
+}
+
+impl fmt::Display for Hash256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for Hash256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hash256({self})")
+    }
+}
+
+impl FromStr for Hash256 {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        Self::from_hex(raw)
+    }
+}
+
+/// A `0x`-optional, 64-digit hex string.
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Hash256 {
+    fn schema_name() -> String {
+        "Hash256".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{InstanceType, SchemaObject, StringValidation};
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^(0[xX])?[0-9a-fA-F]{64}$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl Serialize for Hash256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        Self::from_hex(&raw).map_err(|err| de::Error::custom(format!("{err:#}")))
+    }
+}
+
+/// `raw` without a `0x` or `0X` prefix.
+pub fn strip_hex_prefix(raw: &str) -> &str {
+    raw.strip_prefix("0x")
+        .or_else(|| raw.strip_prefix("0X"))
+        .unwrap_or(raw)
+}
+
+/// Hex digits, optionally 0x-prefixed, as bytes.
+pub fn decode_hex(raw: &str) -> Result<Vec<u8>> {
+    hex::decode(strip_hex_prefix(raw.trim())).with_context(|| format!("{raw} is not valid hex"))
+}
+
+/// Reduces a big-endian byte string modulo `modulus` (little-endian 64-bit
+/// limbs, below 2^255).
+pub fn reduce_be(bytes: &[u8], modulus: &[u64; 4]) -> [u64; 4] {
+    let mut acc = [0u64; 4];
+    for byte in bytes {
+        for shift in (0..8).rev() {
+            let bit = (byte >> shift) & 1;
+            shl1(&mut acc);
+            acc[0] |= bit as u64;
+            if !less_than(&acc, modulus) {
+                sub_assign(&mut acc, modulus);
+            }
+        }
+    }
+    acc
+}
+
+fn shl1(value: &mut [u64; 4]) {
+    let mut carry = 0;
+    for limb in value.iter_mut() {
+        let next = *limb >> 63;
+        *limb = (*limb << 1) | carry;
+        carry = next;
+    }
+}
+
+fn less_than(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    for idx in (0..4).rev() {
+        if a[idx] != b[idx] {
+            return a[idx] < b[idx];
+        }
+    }
+    false
+}
+
+fn sub_assign(a: &mut [u64; 4], b: &[u64; 4]) {
+    let mut borrow = false;
+    for idx in 0..4 {
+        let (diff, under1) = a[idx].overflowing_sub(b[idx]);
+        let (diff, under2) = diff.overflowing_sub(borrow as u64);
+        a[idx] = diff;
+        borrow = under1 || under2;
+    }
+}
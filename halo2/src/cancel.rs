@@ -0,0 +1,154 @@
+//! Cooperative cancellation for proving jobs.
+//!
+//! A [`CancellationToken`] is shared between whoever owns a job and the code
+//! running it. Long-running steps call [`CancellationToken::check`] between
+//! phases; once the token is cancelled or its deadline has passed, the next
+//! check fails with [`Cancelled`]. halo2's `create_proof` has no cancellation
+//! hook, so a proof already underway runs to completion and is discarded.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    Cancelled,
+    TimedOut,
+}
+
+/// Returned by [`CancellationToken::check`]; recover it with
+/// `err.downcast_ref::<Cancelled>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cancelled {
+    pub reason: CancelReason,
+    pub stage: String,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            CancelReason::Cancelled => write!(f, "cancelled before {}", self.stage),
+            CancelReason::TimedOut => write!(f, "timed out before {}", self.stage),
+        }
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    deadline: Option<Instant>,
+    stage: Mutex<Option<String>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    /// A token that is only cancelled explicitly.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                deadline: Some(deadline),
+                ..Inner::default()
+            }),
+        }
+    }
+
+    /// [`Self::with_timeout`] when a timeout is given, otherwise [`Self::new`].
+    pub fn with_optional_timeout(timeout: Option<Duration>) -> Self {
+        timeout.map(Self::with_timeout).unwrap_or_default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.inner.deadline
+    }
+
+    pub fn reason(&self) -> Option<CancelReason> {
+        if self.inner.cancelled.load(Ordering::SeqCst) {
+            Some(CancelReason::Cancelled)
+        } else if self
+            .inner
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Some(CancelReason::TimedOut)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// The stage named by the most recent [`Self::check`].
+    pub fn stage(&self) -> Option<String> {
+        self.inner.stage.lock().ok().and_then(|stage| stage.clone())
+    }
+
+    /// Records that `stage` is about to start, failing if the job should stop.
+    pub fn check(&self, stage: &str) -> Result<()> {
+        if let Ok(mut current) = self.inner.stage.lock() {
+            *current = Some(stage.to_string());
+        }
+        match self.reason() {
+            Some(reason) => Err(Cancelled {
+                reason,
+                stage: stage.to_string(),
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<Cancelled>().is_some()
+}
+
+/// Parses a `--timeout` value: plain seconds, or a number suffixed with
+/// `ms`, `s`, `m` or `h`.
+pub fn parse_timeout(raw: &str) -> Result<Duration> {
+    let raw = raw.trim();
+    let split = raw
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(raw.len());
+    let (number, unit) = raw.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid timeout {raw:?}"))?;
+    let seconds = match unit {
+        "" | "s" => value,
+        "ms" => value / 1000.0,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        other => anyhow::bail!("unknown timeout unit {other:?} in {raw:?}"),
+    };
+    if !seconds.is_finite() || seconds <= 0.0 {
+        anyhow::bail!("timeout must be positive, got {raw:?}");
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
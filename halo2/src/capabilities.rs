@@ -0,0 +1,76 @@
+//! What this build can prove, verify and read, for format negotiation.
+//!
+//! [`capabilities`] is served by `yysfold capabilities` and both servers'
+//! `GET /capabilities`, so a client can pick a transcript, witness encoding
+//! and circuit version the other side supports before sending work.
+
+use serde::Serialize;
+
+use crate::{
+    build_info::BuildInfo,
+    compat::{CIRCUIT_VERSION, COMPATIBILITY, PROOF_FORMAT_VERSION},
+    fixed_point::MAX_SCALE,
+    hashing::HashAlgorithm,
+    io::WitnessFormat,
+    proof_size::OpeningScheme,
+    prove::FIXED_POINT_SCALE,
+    transcript::TranscriptKind,
+};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub build: BuildInfo,
+    pub curve: &'static str,
+    pub commitment_scheme: &'static str,
+    /// Multiopen arguments proofs are made and verified with.
+    pub opening_schemes: Vec<OpeningScheme>,
+    pub transcripts: Vec<TranscriptKind>,
+    pub digest_algorithms: Vec<HashAlgorithm>,
+    pub witness_encodings: Vec<WitnessFormat>,
+    /// Scale used unless a witness or keys set another one.
+    pub fixed_point_scale: f64,
+    /// Largest scale keys can be made for.
+    pub max_fixed_point_scale: u64,
+    /// Circuit version new proofs are made with.
+    pub circuit_version: u32,
+    pub proof_format_version: u32,
+    /// Versions this build verifies, per proof format.
+    pub verifies: Vec<VersionRange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionRange {
+    pub proof_format: u32,
+    pub oldest_circuit: u32,
+    pub newest_circuit: u32,
+}
+
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        build: BuildInfo::current(),
+        curve: "bn256",
+        commitment_scheme: "kzg",
+        opening_schemes: vec![OpeningScheme::Gwc],
+        transcripts: vec![
+            TranscriptKind::Blake2b,
+            TranscriptKind::Keccak256,
+            TranscriptKind::Poseidon,
+        ],
+        digest_algorithms: vec![HashAlgorithm::Blake3, HashAlgorithm::Sha256],
+        witness_encodings: vec![WitnessFormat::Json, WitnessFormat::Cbor],
+        fixed_point_scale: FIXED_POINT_SCALE,
+        max_fixed_point_scale: MAX_SCALE,
+        circuit_version: CIRCUIT_VERSION,
+        proof_format_version: PROOF_FORMAT_VERSION,
+        verifies: COMPATIBILITY
+            .iter()
+            .map(|entry| VersionRange {
+                proof_format: entry.proof_format,
+                oldest_circuit: *entry.circuits.start(),
+                newest_circuit: *entry.circuits.end(),
+            })
+            .collect(),
+    }
+}
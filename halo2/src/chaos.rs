@@ -0,0 +1,166 @@
+//! Deterministic fault injection for testing the proving service.
+//!
+//! A [`FaultPlan`] lists rules, each injecting one [`Fault`] at one
+//! [`FaultPoint`] of a job. Rules select the hits of their point by count
+//! (`after`, `every`, `times`) rather than at random, so the same plan and
+//! job order fail the same way on every run, and a test can assert exactly
+//! which job was delayed, failed or cancelled and how the queue and retries
+//! answered. For example, failing the second job's allocation once and
+//! cancelling every third job 200ms into its proof:
+//!
+//! ```json
+//! {"faults": [
+//!   {"point": "prove", "fault": {"kind": "allocationFailure"}, "after": 1, "times": 1},
+//!   {"point": "prove", "fault": {"kind": "cancel", "afterMillis": 200}, "every": 3}
+//! ]}
+//! ```
+//!
+//! Only built with the `chaos` feature, which production builds leave off.
+
+use std::{
+    fmt,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    cancel::CancellationToken,
+    errors::{Coded, ErrorCode},
+    platform::{from_json_slice, normalize},
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FaultPlan {
+    pub faults: Vec<FaultRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FaultRule {
+    pub point: FaultPoint,
+    pub fault: Fault,
+    /// Hits of `point` to let through before the first fault.
+    #[serde(default)]
+    pub after: u64,
+    /// Fault every this many hits from then on.
+    #[serde(default = "every_hit")]
+    pub every: u64,
+    /// Stop after this many faults; unlimited when absent.
+    #[serde(default)]
+    pub times: Option<u64>,
+}
+
+fn every_hit() -> u64 {
+    1
+}
+
+/// Where in a job a fault fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FaultPoint {
+    /// Before the job's witness is read into the circuit.
+    WitnessRead,
+    /// Before the proof is created.
+    Prove,
+}
+
+impl fmt::Display for FaultPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultPoint::WitnessRead => write!(f, "witness read"),
+            FaultPoint::Prove => write!(f, "prove"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", deny_unknown_fields)]
+pub enum Fault {
+    /// Stalls the job, as a slow disk or network read would.
+    #[serde(rename_all = "camelCase")]
+    Delay { millis: u64 },
+    /// Fails the job as if an allocation had failed.
+    AllocationFailure,
+    /// Cancels the job's token this long after the point, so the job is
+    /// cancelled mid-proof at its next check.
+    #[serde(rename_all = "camelCase")]
+    Cancel { after_millis: u64 },
+}
+
+struct Armed {
+    rule: FaultRule,
+    hits: AtomicU64,
+    fired: AtomicU64,
+}
+
+pub struct FaultInjector {
+    rules: Vec<Armed>,
+}
+
+impl FaultInjector {
+    pub fn new(plan: FaultPlan) -> Result<Self> {
+        if plan.faults.iter().any(|rule| rule.every == 0) {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                "fault rules need every ≥ 1"
+            ));
+        }
+        Ok(Self {
+            rules: plan
+                .faults
+                .into_iter()
+                .map(|rule| Armed {
+                    rule,
+                    hits: AtomicU64::new(0),
+                    fired: AtomicU64::new(0),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = normalize(path);
+        let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+        let plan =
+            from_json_slice(&bytes).with_context(|| format!("parsing fault plan {:?}", path))?;
+        Self::new(plan)
+    }
+
+    /// Applies every rule selecting this hit of `point` to the job owning
+    /// `cancel`. Fails when an allocation failure is injected.
+    pub fn inject(&self, point: FaultPoint, cancel: &CancellationToken) -> Result<()> {
+        for armed in self.rules.iter().filter(|armed| armed.rule.point == point) {
+            let rule = &armed.rule;
+            let hit = armed.hits.fetch_add(1, Ordering::SeqCst);
+            if hit < rule.after || (hit - rule.after) % rule.every != 0 {
+                continue;
+            }
+            let fired = armed.fired.fetch_add(1, Ordering::SeqCst);
+            if rule.times.is_some_and(|times| fired >= times) {
+                continue;
+            }
+            eprintln!("chaos: injecting {:?} at {point} (hit {hit})", rule.fault);
+            match rule.fault {
+                Fault::Delay { millis } => thread::sleep(Duration::from_millis(millis)),
+                Fault::AllocationFailure => anyhow::bail!(Coded::new(
+                    ErrorCode::Internal,
+                    format!("memory allocation failed at {point} (injected)")
+                )),
+                Fault::Cancel { after_millis } => {
+                    let cancel = cancel.clone();
+                    thread::spawn(move || {
+                        thread::sleep(Duration::from_millis(after_millis));
+                        cancel.cancel();
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
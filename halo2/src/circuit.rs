@@ -1,9 +1,390 @@
 use halo2_proofs::{
-    circuit::{Layouter, Region, SimpleFloorPlanner, Value},
-    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance, Selector},
+    circuit::{Cell, Layouter, Region, SimpleFloorPlanner, Value},
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, Selector,
+    },
     poly::Rotation,
 };
 use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ann::DISTANCE_BITS,
+    codebook::{CommitMode, CODEBOOK_ROOT_SLOT},
+    fixed_point::{FixedPoint, DEFAULT_SCALE},
+    gadgets::{
+        component_range::{ComponentRangeChip, ComponentRangeConfig},
+        distance::{DistanceChip, DistanceConfig},
+        merkle::{MerkleUpdate, MerkleUpdateChip, MerkleUpdateConfig},
+        poseidon::{AssignedValue, PoseidonChip, PoseidonConfig},
+        pq::{PqLookupChip, PqLookupConfig},
+        range::{RangeCheckChip, RangeCheckConfig},
+        scalar::{DequantizeChip, DequantizeConfig},
+        sha256::{Sha256Chip, Sha256Config},
+        shuffle::{ShuffleChip, ShuffleConfig},
+    },
+    hiding::{FOLDED_COMMITMENT_SLOT, PQ_COMMITMENT_SLOT},
+    poseidon::{
+        domain_capacity, CODEBOOK_DOMAIN, CODES_DOMAIN, EPSILONS_DOMAIN, HIDING_DOMAIN,
+        PERMUTATION_DOMAIN, RESIDUALS_DOMAIN, ROTATION_DOMAIN, SCALAR_DOMAIN, SPARSITY_DOMAIN,
+        VECTOR_COMMITMENT_DOMAIN,
+    },
+    public_inputs::instance_hash,
+    quantization::{CompressionStats, PqShape},
+};
+
+/// Instance row holding the Poseidon Merkle root of the folded vectors.
+pub const VECTOR_ROOT_SLOT: usize = 3;
+
+/// Shape and feature switches fixed at keygen time.
+///
+/// The default (all zero / disabled) reproduces the original commitment-only
+/// circuit, so key configs written before these params existed keep working.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoldedParams {
+    #[serde(default)]
+    pub vectors: usize,
+    #[serde(default)]
+    pub dim: usize,
+    /// Constrain a Poseidon Merkle root over the folded vector rows to instance
+    /// slot [`VECTOR_ROOT_SLOT`].
+    #[serde(default)]
+    pub vector_root: bool,
+    /// Commit to the PQ code list and look every pq component up in the codebook.
+    #[serde(default)]
+    pub pq_codes: bool,
+    #[serde(default)]
+    pub subvectors: usize,
+    #[serde(default)]
+    pub centroids: usize,
+    /// With `pq_codes`, recompute `codebookRoot` from the codebook table in
+    /// this mode instead of treating it as an opaque digest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codebook_commitment: Option<CommitMode>,
+    /// Expose `sha256(public values) mod p` as the only instance instead of
+    /// the values themselves, so an EVM verifier can derive it with the
+    /// SHA-256 precompile from the full public inputs. The hash is
+    /// recomputed in-circuit (about 18k rows per 64-byte block; three values
+    /// take two blocks, five take three), so keys need `k >= 16`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub instance_hash: bool,
+    /// Expose the previous block's proof digest (`previousProofDigest`) as
+    /// public value [`FoldedParams::lineage_slot`]. No gate reads it; the
+    /// instance is bound by the transcript, or by the hash in
+    /// `instance_hash` mode, so the proof only verifies for that predecessor.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lineage: bool,
+    /// Expose a randomness beacon value (`beaconValue`, e.g. a drand round's
+    /// randomness) as public value [`FoldedParams::beacon_slot`], bound the
+    /// same way as the lineage digest.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub beacon: bool,
+    /// Sparse mode: lay out only this many (index, folded, pq) entries per
+    /// vector from the witness's `sparseVectors`, and commit to the padded
+    /// index pattern at public value [`FoldedParams::sparsity_slot`].
+    /// Excludes `vector_root` and `pq_codes`, which hash the dense rows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonzeros: Option<usize>,
+    /// With `pq_codes`, expose the block's [`CompressionStats`] as public
+    /// values [`FoldedParams::compression_slot`] and the one after it. The
+    /// keyed shape fixes them, so the circuit pins both to fixed constants.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compression_stats: bool,
+    /// With `pq_codes`, bound the residual of every subvector segment by
+    /// that subspace's epsilon and commit to the epsilons at public value
+    /// [`FoldedParams::epsilon_slot`]. See [`crate::epsilon`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub subvector_epsilons: bool,
+    /// Delta mode: lay out at most this many changed vectors and apply them
+    /// as Merkle leaf updates to `previousVectorRoot` at public value
+    /// [`FoldedParams::delta_slot`]; the updated root is `foldedVectorRoot`.
+    /// `vectors` is the whole block, a power of two. Needs `vector_root`,
+    /// excludes `pq_codes` and `nonzeros`. See [`crate::delta`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<usize>,
+    /// Expose a salted Poseidon Merkle root over the full folded and pq rows
+    /// as public value [`FoldedParams::witness_commitment_slot`], so rows can
+    /// be opened to auditors later. Excludes `nonzeros` and `delta`. See
+    /// [`crate::witness_commitment`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub witness_commitment: bool,
+    /// With `pq_codes`, commit to the witness's `dim x dim` OPQ rotation at
+    /// public value [`FoldedParams::rotation_slot`]. See [`crate::opq`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rotation: bool,
+    /// With `codebook_commitment`, two-stage residual quantization with this
+    /// many residual centroids per subspace: every pq component is split into
+    /// a primary and a residual centroid component, each looked up in its
+    /// codebook, and the residual codebook's root is public value
+    /// [`FoldedParams::residual_codebook_slot`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub residual_centroids: Option<usize>,
+    /// Int8 scalar quantization: every pq component dequantizes from the
+    /// witness's code, per-dimension scale and zero point, all committed at
+    /// public value [`FoldedParams::scalar_slot`]. Excludes `pq_codes`,
+    /// `nonzeros` and `delta`. See [`crate::scalar`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub scalar: bool,
+    /// With `vector_root` or `witness_commitment`, commit the rows in the
+    /// order the witness's secret `rowPermutation` picks, proven to be a
+    /// permutation of the laid-out rows, and commit to that order at public
+    /// value [`FoldedParams::permutation_slot`]. Excludes `delta`. See
+    /// [`crate::permutation`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub row_permutation: bool,
+    /// Recompute public values `foldedCommitment` and `pqCommitment` from the
+    /// laid-out rows as commitments blinded by the witness's secret factors,
+    /// instead of copying opaque digests through. Excludes `nonzeros` and
+    /// `delta`. See [`crate::hiding`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hiding: bool,
+    /// Recompute public values `foldedCommitment` and `pqCommitment` as
+    /// Poseidon hashes of the laid-out rows. Excludes `hiding`, `nonzeros`
+    /// and `delta`. See [`crate::vector_commitment`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub vector_commitments: bool,
+    /// Bound the residual of every vector by the squared fixed-point SLA
+    /// bound at public value [`FoldedParams::sla_slot`], the maximum
+    /// reconstruction error the block was committed to.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sla_bound: bool,
+    /// Fixed-point scale the witness values were converted at; absent means
+    /// [`DEFAULT_SCALE`]. It fixes the component range and residual scaling
+    /// gates, so keys only prove witnesses at their own scale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u64>,
+}
+
+impl FoldedParams {
+    /// Number of public values: the three commitments plus the optional roots,
+    /// the lineage digest, the beacon value, the sparsity commitment, the
+    /// compression stats, the previous vector root, the witness commitment, the
+    /// rotation commitment, the residual codebook root, the scalar commitment,
+    /// the permutation commitment, the epsilon commitment and the SLA bound,
+    /// and last the residual commitment.
+    pub fn public_len(&self) -> usize {
+        4 + usize::from(self.vector_root)
+            + usize::from(self.pq_codes)
+            + usize::from(self.lineage)
+            + usize::from(self.beacon)
+            + usize::from(self.nonzeros.is_some())
+            + 2 * usize::from(self.compression_stats)
+            + usize::from(self.delta.is_some())
+            + usize::from(self.witness_commitment)
+            + usize::from(self.rotation)
+            + usize::from(self.residual_centroids.is_some())
+            + usize::from(self.scalar)
+            + usize::from(self.row_permutation)
+            + usize::from(self.subvector_epsilons)
+            + usize::from(self.sla_bound)
+    }
+
+    /// Rows of the instance column.
+    pub fn instance_len(&self) -> usize {
+        if self.instance_hash {
+            1
+        } else {
+            self.public_len()
+        }
+    }
+
+    /// Instance column values for the public `values`.
+    pub fn instances(&self, values: Vec<Fr>) -> Vec<Fr> {
+        if self.instance_hash {
+            vec![instance_hash(&values)]
+        } else {
+            values
+        }
+    }
+
+    /// Fixed-point scale of the laid-out values.
+    pub fn scale(&self) -> u64 {
+        self.scale.unwrap_or(DEFAULT_SCALE)
+    }
+
+    /// The conversion at [`Self::scale`], rejecting an out of range scale.
+    pub fn fixed_point(&self) -> anyhow::Result<FixedPoint> {
+        FixedPoint::new(self.scale())
+    }
+
+    /// Residual entries laid out per vector.
+    pub fn row_len(&self) -> usize {
+        self.nonzeros.unwrap_or(self.dim)
+    }
+
+    /// Vectors laid out: the changed-row capacity in delta mode, otherwise
+    /// the whole block.
+    pub fn laid_out_vectors(&self) -> usize {
+        self.delta.unwrap_or(self.vectors)
+    }
+
+    /// Levels of the vector tree in delta mode.
+    pub fn tree_depth(&self) -> usize {
+        self.vectors.trailing_zeros() as usize
+    }
+
+    pub fn pq_codes_slot(&self) -> Option<usize> {
+        self.pq_codes
+            .then(|| VECTOR_ROOT_SLOT + usize::from(self.vector_root))
+    }
+
+    pub fn lineage_slot(&self) -> Option<usize> {
+        self.lineage
+            .then(|| VECTOR_ROOT_SLOT + usize::from(self.vector_root) + usize::from(self.pq_codes))
+    }
+
+    pub fn beacon_slot(&self) -> Option<usize> {
+        self.beacon.then(|| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+        })
+    }
+
+    pub fn sparsity_slot(&self) -> Option<usize> {
+        self.nonzeros.map(|_| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+                + usize::from(self.beacon)
+        })
+    }
+
+    /// First of the two compression stats slots: original then pq bytes.
+    pub fn compression_slot(&self) -> Option<usize> {
+        self.compression_stats.then(|| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+                + usize::from(self.beacon)
+                + usize::from(self.nonzeros.is_some())
+        })
+    }
+
+    pub fn delta_slot(&self) -> Option<usize> {
+        self.delta.map(|_| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+                + usize::from(self.beacon)
+                + usize::from(self.nonzeros.is_some())
+                + 2 * usize::from(self.compression_stats)
+        })
+    }
+
+    pub fn witness_commitment_slot(&self) -> Option<usize> {
+        self.witness_commitment.then(|| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+                + usize::from(self.beacon)
+                + usize::from(self.nonzeros.is_some())
+                + 2 * usize::from(self.compression_stats)
+                + usize::from(self.delta.is_some())
+        })
+    }
+
+    pub fn rotation_slot(&self) -> Option<usize> {
+        self.rotation.then(|| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+                + usize::from(self.beacon)
+                + usize::from(self.nonzeros.is_some())
+                + 2 * usize::from(self.compression_stats)
+                + usize::from(self.delta.is_some())
+                + usize::from(self.witness_commitment)
+        })
+    }
+
+    pub fn residual_codebook_slot(&self) -> Option<usize> {
+        self.residual_centroids.map(|_| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+                + usize::from(self.beacon)
+                + usize::from(self.nonzeros.is_some())
+                + 2 * usize::from(self.compression_stats)
+                + usize::from(self.delta.is_some())
+                + usize::from(self.witness_commitment)
+                + usize::from(self.rotation)
+        })
+    }
+
+    pub fn scalar_slot(&self) -> Option<usize> {
+        self.scalar.then(|| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+                + usize::from(self.beacon)
+                + usize::from(self.nonzeros.is_some())
+                + 2 * usize::from(self.compression_stats)
+                + usize::from(self.delta.is_some())
+                + usize::from(self.witness_commitment)
+                + usize::from(self.rotation)
+                + usize::from(self.residual_centroids.is_some())
+        })
+    }
+
+    pub fn permutation_slot(&self) -> Option<usize> {
+        self.row_permutation.then(|| {
+            VECTOR_ROOT_SLOT
+                + usize::from(self.vector_root)
+                + usize::from(self.pq_codes)
+                + usize::from(self.lineage)
+                + usize::from(self.beacon)
+                + usize::from(self.nonzeros.is_some())
+                + 2 * usize::from(self.compression_stats)
+                + usize::from(self.delta.is_some())
+                + usize::from(self.witness_commitment)
+                + usize::from(self.rotation)
+                + usize::from(self.residual_centroids.is_some())
+                + usize::from(self.scalar)
+        })
+    }
+
+    pub fn epsilon_slot(&self) -> Option<usize> {
+        self.subvector_epsilons
+            .then(|| self.public_len() - 2 - usize::from(self.sla_bound))
+    }
+
+    pub fn sla_slot(&self) -> Option<usize> {
+        self.sla_bound.then(|| self.public_len() - 2)
+    }
+
+    /// Slot of `residualCommitment`, the last public value of every circuit.
+    pub fn residual_slot(&self) -> usize {
+        self.public_len() - 1
+    }
+
+    pub fn compression(&self) -> CompressionStats {
+        CompressionStats::new(
+            self.vectors,
+            PqShape {
+                dim: self.dim,
+                subvectors: self.subvectors,
+                centroids: self.centroids,
+            },
+        )
+    }
+
+    pub fn sub_dim(&self) -> usize {
+        if self.subvectors == 0 {
+            0
+        } else {
+            self.dim / self.subvectors
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct FoldedConfig {
@@ -11,7 +392,37 @@ pub struct FoldedConfig {
     commit_advice: Column<Advice>,
     instance: Column<Instance>,
     diff_selector: Selector,
+    residual_first: Selector,
+    residual_next: Selector,
     sum_selector: Selector,
+    poseidon: PoseidonConfig,
+    components: ComponentRangeConfig,
+    pq_lookup: Option<PqLookupConfig>,
+    dequantize: Option<DequantizeConfig>,
+    sha256: Option<Sha256Config>,
+    stats: Option<StatsConfig>,
+    epsilons: Option<EpsilonConfig>,
+    delta: Option<MerkleUpdateConfig>,
+    shuffle: Option<ShuffleConfig>,
+}
+
+/// Pins advice cells to fixed constants.
+#[derive(Clone, Debug)]
+struct StatsConfig {
+    constant: Column<Fixed>,
+    selector: Selector,
+}
+
+/// Segment residuals are taken over field values `fixed / S`, so the gate
+/// rescales them by `S^2` before the range check against the fixed-point
+/// bound.
+#[derive(Clone, Debug)]
+struct EpsilonConfig {
+    distance: DistanceConfig,
+    range: RangeCheckConfig,
+    scale: Selector,
+    /// `S^2` at the keyed scale.
+    scale_squared: Fr,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -19,18 +430,153 @@ pub struct FoldedCircuit {
     pub public_inputs: Vec<Fr>,
     pub folded_vectors: Vec<Vec<Fr>>,
     pub pq_vectors: Vec<Vec<Fr>>,
+    /// Residual of each vector as the witness generator reported it (see
+    /// [`crate::prove::claimed_residuals`]); the `epsilon_check` gate
+    /// confirms it against the laid-out rows, and the cells are hashed into
+    /// `residualCommitment` (see [`crate::epsilon::residual_commitment`]).
     pub epsilon_squared: Vec<Fr>,
     pub commitments: [Fr; 3],
+    pub params: FoldedParams,
+    /// `pq_codes[vector][subspace]`, only used with `params.pq_codes`.
+    pub pq_codes: Vec<Vec<Fr>>,
+    /// `codebook[subspace][centroid][component]`, only used with `params.pq_codes`.
+    pub codebook: Vec<Vec<Vec<Fr>>>,
+    /// Public values hashed into the single instance, only used with
+    /// `params.instance_hash`.
+    pub hashed_inputs: Vec<Fr>,
+    /// Padded `indices[vector][entry]`, only used with `params.nonzeros`;
+    /// `folded_vectors` and `pq_vectors` then hold the values at these
+    /// indices.
+    pub sparse_indices: Vec<Vec<Fr>>,
+    /// Squared fixed-point bound per subspace, only used with
+    /// `params.subvector_epsilons`.
+    pub subvector_bounds: Vec<Fr>,
+    /// Root the leaf updates start from, only used with `params.delta`.
+    pub previous_vector_root: Fr,
+    /// One update per laid-out vector, only used with `params.delta`.
+    pub delta_updates: Vec<MerkleUpdate>,
+    /// Salt per vector, only used with `params.witness_commitment`.
+    pub row_salts: Vec<Fr>,
+    /// Row-major OPQ rotation, only used with `params.rotation`.
+    pub rotation: Vec<Fr>,
+    /// `residual_codes[vector][subspace]`, only used with
+    /// `params.residual_centroids`.
+    pub residual_codes: Vec<Vec<Fr>>,
+    /// Residual codebook, only used with `params.residual_centroids`.
+    pub residual_codebook: Vec<Vec<Vec<Fr>>>,
+    /// Primary-stage reconstruction of each vector, only used with
+    /// `params.residual_centroids`; the residual stage is `pq` minus it.
+    pub primary_vectors: Vec<Vec<Fr>>,
+    /// `scalar_codes[vector][dimension]`, only used with `params.scalar`.
+    pub scalar_codes: Vec<Vec<Fr>>,
+    /// Per-dimension scales, only used with `params.scalar`.
+    pub scales: Vec<Fr>,
+    /// Per-dimension zero points, only used with `params.scalar`.
+    pub zero_points: Vec<Fr>,
+    /// Committed row order, `row_order[position] = row`, only used with
+    /// `params.row_permutation`.
+    pub row_order: Vec<usize>,
+    /// Salt of the permutation commitment, only used with
+    /// `params.row_permutation`.
+    pub permutation_salt: Fr,
+    /// Folded then pq blinding factor, only used with `params.hiding`.
+    pub blinding: Vec<Fr>,
+    /// Squared fixed-point SLA bound, only used with `params.sla_bound`.
+    pub sla_bound: Fr,
 }
 
 impl FoldedCircuit {
-    pub fn blank(len: usize) -> Self {
+    /// Keygen circuit for `params`: zero-filled vectors of the keyed shape so
+    /// that every region, selector and fixed column matches a real proof.
+    pub fn blank_with(params: &FoldedParams) -> Self {
+        let zeros = vec![vec![Fr::zero(); params.row_len()]; params.laid_out_vectors()];
+        let (pq_codes, codebook) = if params.pq_codes {
+            (
+                vec![vec![Fr::zero(); params.subvectors]; params.vectors],
+                vec![vec![vec![Fr::zero(); params.sub_dim()]; params.centroids]; params.subvectors],
+            )
+        } else {
+            (vec![], vec![])
+        };
         Self {
-            public_inputs: vec![Fr::from(0); len],
-            folded_vectors: vec![],
-            pq_vectors: vec![],
-            epsilon_squared: vec![],
+            public_inputs: vec![Fr::zero(); params.instance_len()],
+            folded_vectors: zeros.clone(),
+            pq_vectors: zeros,
+            epsilon_squared: vec![Fr::zero(); params.laid_out_vectors()],
             commitments: [Fr::zero(); 3],
+            params: params.clone(),
+            pq_codes,
+            codebook,
+            hashed_inputs: if params.instance_hash {
+                vec![Fr::zero(); params.public_len()]
+            } else {
+                vec![]
+            },
+            sparse_indices: match params.nonzeros {
+                Some(nonzeros) => vec![vec![Fr::zero(); nonzeros]; params.vectors],
+                None => vec![],
+            },
+            subvector_bounds: if params.subvector_epsilons {
+                vec![Fr::zero(); params.subvectors]
+            } else {
+                vec![]
+            },
+            previous_vector_root: Fr::zero(),
+            delta_updates: match params.delta {
+                Some(capacity) => vec![MerkleUpdate::disabled(params.tree_depth()); capacity],
+                None => vec![],
+            },
+            row_salts: if params.witness_commitment {
+                vec![Fr::zero(); params.vectors]
+            } else {
+                vec![]
+            },
+            rotation: if params.rotation {
+                vec![Fr::zero(); params.dim * params.dim]
+            } else {
+                vec![]
+            },
+            residual_codes: match params.residual_centroids {
+                Some(_) => vec![vec![Fr::zero(); params.subvectors]; params.vectors],
+                None => vec![],
+            },
+            residual_codebook: match params.residual_centroids {
+                Some(centroids) => {
+                    vec![vec![vec![Fr::zero(); params.sub_dim()]; centroids]; params.subvectors]
+                }
+                None => vec![],
+            },
+            primary_vectors: match params.residual_centroids {
+                Some(_) => vec![vec![Fr::zero(); params.dim]; params.vectors],
+                None => vec![],
+            },
+            scalar_codes: if params.scalar {
+                vec![vec![Fr::zero(); params.dim]; params.vectors]
+            } else {
+                vec![]
+            },
+            scales: if params.scalar {
+                vec![Fr::zero(); params.dim]
+            } else {
+                vec![]
+            },
+            zero_points: if params.scalar {
+                vec![Fr::zero(); params.dim]
+            } else {
+                vec![]
+            },
+            row_order: if params.row_permutation {
+                (0..params.vectors).collect()
+            } else {
+                vec![]
+            },
+            permutation_salt: Fr::zero(),
+            blinding: if params.hiding {
+                vec![Fr::zero(); 2]
+            } else {
+                vec![]
+            },
+            sla_bound: Fr::zero(),
         }
     }
 }
@@ -38,21 +584,30 @@ impl FoldedCircuit {
 impl Circuit<Fr> for FoldedCircuit {
     type Config = FoldedConfig;
     type FloorPlanner = SimpleFloorPlanner;
-    type Params = ();
+    type Params = FoldedParams;
 
     fn without_witnesses(&self) -> Self {
-        Self::blank(self.public_inputs.len())
+        Self::blank_with(&self.params)
     }
 
     fn params(&self) -> Self::Params {
-        ()
+        self.params.clone()
     }
 
     fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        Self::configure_with_params(meta, FoldedParams::default())
+    }
+
+    fn configure_with_params(
+        meta: &mut ConstraintSystem<Fr>,
+        params: Self::Params,
+    ) -> Self::Config {
         let advice = meta.advice_column();
         let commit_advice = meta.advice_column();
         let instance = meta.instance_column();
         let diff_selector = meta.selector();
+        let residual_first = meta.selector();
+        let residual_next = meta.selector();
         let sum_selector = meta.selector();
         meta.enable_equality(advice);
         meta.enable_equality(commit_advice);
@@ -64,17 +619,79 @@ impl Circuit<Fr> for FoldedCircuit {
             let diff = meta.query_advice(advice, Rotation(2));
             vec![s * (folded - pq - diff)]
         });
+        // Running sum of squared differences next to each diff cell.
+        meta.create_gate("residual_first", |meta| {
+            let s = meta.query_selector(residual_first);
+            let diff = meta.query_advice(advice, Rotation(2));
+            let sum = meta.query_advice(commit_advice, Rotation(2));
+            vec![s * (sum - diff.clone() * diff)]
+        });
+        meta.create_gate("residual_next", |meta| {
+            let s = meta.query_selector(residual_next);
+            let diff = meta.query_advice(advice, Rotation(2));
+            let previous = meta.query_advice(commit_advice, Rotation::prev());
+            let sum = meta.query_advice(commit_advice, Rotation(2));
+            vec![s * (sum - previous - diff.clone() * diff)]
+        });
         meta.create_gate("epsilon_check", |meta| {
             let s = meta.query_selector(sum_selector);
-            let value = meta.query_advice(advice, Rotation::cur());
-            vec![s * value]
+            let sum = meta.query_advice(commit_advice, Rotation::prev());
+            let epsilon_squared = meta.query_advice(advice, Rotation::cur());
+            vec![s * (sum - epsilon_squared)]
+        });
+        let poseidon = PoseidonChip::configure(meta);
+        let components = ComponentRangeChip::configure(meta, params.scale());
+        let pq_lookup = params.pq_codes.then(|| PqLookupChip::configure(meta));
+        let dequantize = params.scalar.then(|| DequantizeChip::configure(meta));
+        let sha256 = params.instance_hash.then(|| Sha256Chip::configure(meta));
+        let stats = params.compression_stats.then(|| {
+            let constant = meta.fixed_column();
+            let selector = meta.selector();
+            meta.create_gate("compression_stats", |meta| {
+                let s = meta.query_selector(selector);
+                let value = meta.query_advice(advice, Rotation::cur());
+                vec![s * (value - meta.query_fixed(constant, Rotation::cur()))]
+            });
+            StatsConfig { constant, selector }
+        });
+        let epsilons = (params.subvector_epsilons || params.sla_bound).then(|| {
+            let scale = meta.selector();
+            let scale_squared = Fr::from(params.scale()) * Fr::from(params.scale());
+            meta.create_gate("epsilon_scale", |meta| {
+                let s = meta.query_selector(scale);
+                let residual = meta.query_advice(advice, Rotation::cur());
+                let scaled = meta.query_advice(advice, Rotation::next());
+                vec![s * (scaled - residual * Expression::Constant(scale_squared))]
+            });
+            EpsilonConfig {
+                distance: DistanceChip::configure(meta),
+                range: RangeCheckChip::configure(meta),
+                scale,
+                scale_squared,
+            }
+        });
+        let delta = params.delta.map(|_| MerkleUpdateChip::configure(meta));
+        let shuffle = params.row_permutation.then(|| {
+            let width = usize::from(params.vector_root) + usize::from(params.witness_commitment);
+            ShuffleChip::configure(meta, width)
         });
         FoldedConfig {
             advice,
             commit_advice,
             instance,
             diff_selector,
+            residual_first,
+            residual_next,
             sum_selector,
+            poseidon,
+            components,
+            pq_lookup,
+            dequantize,
+            sha256,
+            stats,
+            epsilons,
+            delta,
+            shuffle,
         }
     }
 
@@ -85,12 +702,26 @@ impl Circuit<Fr> for FoldedCircuit {
     ) -> Result<(), Error> {
         let commit_advice = config.commit_advice;
         let instance = config.instance;
+        let hashed = match &config.sha256 {
+            Some(_) => Some(assign_hashed_inputs(
+                &mut layouter,
+                &config,
+                &self.hashed_inputs,
+                &self.params,
+            )?),
+            None => None,
+        };
+        let hashed = hashed.as_deref();
         layouter.assign_region(
             || "commitment equality",
             |mut region| {
                 for (idx, commitment) in self.commitments.iter().enumerate() {
                     let private =
                         region.assign_advice(commit_advice, idx * 2, Value::known(*commitment));
+                    if let Some(hashed) = hashed {
+                        region.constrain_equal(private.cell(), hashed[idx].0);
+                        continue;
+                    }
                     let public = region.assign_advice_from_instance(
                         || "commitment_public",
                         instance,
@@ -104,6 +735,9 @@ impl Circuit<Fr> for FoldedCircuit {
             },
         )?;
 
+        let mut folded_rows = Vec::with_capacity(self.folded_vectors.len());
+        let mut pq_rows = Vec::with_capacity(self.pq_vectors.len());
+        let mut residuals = Vec::with_capacity(self.epsilon_squared.len());
         if !self.folded_vectors.is_empty()
             && self.folded_vectors.len() == self.pq_vectors.len()
             && self.folded_vectors.len() == self.epsilon_squared.len()
@@ -114,7 +748,7 @@ impl Circuit<Fr> for FoldedCircuit {
                 .zip(self.pq_vectors.iter())
                 .zip(self.epsilon_squared.iter());
             for (batch_idx, ((folded, pq), epsilon)) in batches.enumerate() {
-                enforce_component_difference(
+                let (folded_cells, pq_cells, residual) = enforce_component_difference(
                     &mut layouter,
                     &config,
                     folded,
@@ -122,13 +756,505 @@ impl Circuit<Fr> for FoldedCircuit {
                     *epsilon,
                     batch_idx,
                 )?;
+                folded_rows.push(folded_cells);
+                pq_rows.push(pq_cells);
+                residuals.push(residual);
+            }
+        }
+
+        let components = ComponentRangeChip::construct(config.components.clone());
+        components.assign_table(&mut layouter)?;
+        for (row_idx, (folded, pq)) in folded_rows.iter().zip(&pq_rows).enumerate() {
+            let row: Vec<AssignedValue> = folded.iter().chain(pq).copied().collect();
+            components.check_row(&mut layouter, row_idx, &row)?;
+        }
+
+        if folded_rows.len() != self.params.laid_out_vectors() {
+            return Err(Error::Synthesis);
+        }
+        let chip = PoseidonChip::construct(config.poseidon.clone());
+
+        let capacity = domain_capacity(RESIDUALS_DOMAIN, residuals.len());
+        let (commitment, _) = chip.hash(&mut layouter, capacity, &residuals)?;
+        bind_public(
+            &mut layouter,
+            &config,
+            hashed,
+            commitment,
+            self.params.residual_slot(),
+        )?;
+
+        if self.params.hiding {
+            let blinding =
+                assign_values(&mut layouter, &config, "blinding factors", &self.blinding)?;
+            if blinding.len() != 2 {
+                return Err(Error::Synthesis);
+            }
+            let matrices = [
+                (FOLDED_COMMITMENT_SLOT, &folded_rows),
+                (PQ_COMMITMENT_SLOT, &pq_rows),
+            ];
+            for (factor, (slot, rows)) in blinding.into_iter().zip(matrices) {
+                let mut inputs = vec![factor];
+                inputs.extend(rows.iter().flatten().copied());
+                let capacity = domain_capacity(HIDING_DOMAIN, inputs.len());
+                let (commitment, _) = chip.hash(&mut layouter, capacity, &inputs)?;
+                bind_public(&mut layouter, &config, hashed, commitment, slot)?;
             }
         }
 
+        if self.params.vector_commitments {
+            let matrices = [
+                (FOLDED_COMMITMENT_SLOT, &folded_rows),
+                (PQ_COMMITMENT_SLOT, &pq_rows),
+            ];
+            for (slot, rows) in matrices {
+                let inputs: Vec<AssignedValue> = rows.iter().flatten().copied().collect();
+                let capacity = domain_capacity(VECTOR_COMMITMENT_DOMAIN, inputs.len());
+                let (commitment, _) = chip.hash(&mut layouter, capacity, &inputs)?;
+                bind_public(&mut layouter, &config, hashed, commitment, slot)?;
+            }
+        }
+
+        let mut vector_leaves = Vec::with_capacity(folded_rows.len());
+        if self.params.vector_root {
+            for row in &folded_rows {
+                vector_leaves.push(chip.hash_leaf(&mut layouter, row)?);
+            }
+        }
+        let mut commitment_leaves = Vec::with_capacity(folded_rows.len());
+        if self.params.witness_commitment {
+            let salts = assign_values(&mut layouter, &config, "row salts", &self.row_salts)?;
+            if salts.len() != folded_rows.len() {
+                return Err(Error::Synthesis);
+            }
+            for ((salt, folded), pq) in salts.into_iter().zip(&folded_rows).zip(&pq_rows) {
+                let mut row = Vec::with_capacity(1 + folded.len() + pq.len());
+                row.push(salt);
+                row.extend_from_slice(folded);
+                row.extend_from_slice(pq);
+                commitment_leaves.push(chip.hash_leaf(&mut layouter, &row)?);
+            }
+        }
+
+        if let (Some(shuffle), Some(slot)) = (&config.shuffle, self.params.permutation_slot()) {
+            // Both trees take their leaves in the one shuffled order.
+            let rows: Vec<Vec<AssignedValue>> = (0..folded_rows.len())
+                .map(|row| {
+                    vector_leaves
+                        .get(row)
+                        .into_iter()
+                        .chain(commitment_leaves.get(row))
+                        .copied()
+                        .collect()
+                })
+                .collect();
+            let shuffler = ShuffleChip::construct(shuffle.clone());
+            let (indices, shuffled) = shuffler.shuffle(&mut layouter, &rows, &self.row_order)?;
+            if self.params.vector_root {
+                vector_leaves = shuffled.iter().map(|row| row[0]).collect();
+            }
+            if self.params.witness_commitment {
+                commitment_leaves = shuffled.iter().map(|row| row[row.len() - 1]).collect();
+            }
+            let mut inputs = assign_values(
+                &mut layouter,
+                &config,
+                "permutation salt",
+                &[self.permutation_salt],
+            )?;
+            inputs.extend(indices);
+            let capacity = domain_capacity(PERMUTATION_DOMAIN, inputs.len());
+            let (commitment, _) = chip.hash(&mut layouter, capacity, &inputs)?;
+            bind_public(&mut layouter, &config, hashed, commitment, slot)?;
+        }
+
+        if self.params.vector_root {
+            let leaves = vector_leaves;
+            let root = match (&config.delta, self.params.delta_slot()) {
+                (Some(delta), Some(slot)) => {
+                    if self.delta_updates.len() != leaves.len() {
+                        return Err(Error::Synthesis);
+                    }
+                    let updater = MerkleUpdateChip::construct(delta.clone());
+                    let previous = assign_values(
+                        &mut layouter,
+                        &config,
+                        "previous vector root",
+                        &[self.previous_vector_root],
+                    )?[0];
+                    bind_public(&mut layouter, &config, hashed, previous.0, slot)?;
+                    let mut root = previous;
+                    for (update, leaf) in self.delta_updates.iter().zip(leaves) {
+                        root = updater.update(&mut layouter, &chip, root, leaf, update)?;
+                    }
+                    root
+                }
+                _ => chip.merkle_root(&mut layouter, leaves)?,
+            };
+            bind_public(&mut layouter, &config, hashed, root.0, VECTOR_ROOT_SLOT)?;
+        }
+
+        if let Some(slot) = self.params.witness_commitment_slot() {
+            let root = chip.merkle_root(&mut layouter, commitment_leaves)?;
+            bind_public(&mut layouter, &config, hashed, root.0, slot)?;
+        }
+
+        if let (Some(pq_lookup), Some(slot)) = (&config.pq_lookup, self.params.pq_codes_slot()) {
+            let lookup = PqLookupChip::construct(pq_lookup.clone());
+            // The residual codebook follows the primary one in the table,
+            // as subspaces `subvectors..2 * subvectors`.
+            let table: Vec<Vec<Vec<Fr>>> = self
+                .codebook
+                .iter()
+                .chain(&self.residual_codebook)
+                .cloned()
+                .collect();
+            let mut codebook = lookup.assign_codebook(&mut layouter, &table)?;
+            let residual_codebook = codebook.split_off(self.codebook.len());
+            if let Some(mode) = self.params.codebook_commitment {
+                let root = commit_codebook(&mut layouter, &chip, mode, &codebook)?;
+                bind_public(&mut layouter, &config, hashed, root.0, CODEBOOK_ROOT_SLOT)?;
+                if let Some(slot) = self.params.residual_codebook_slot() {
+                    let root = commit_codebook(&mut layouter, &chip, mode, &residual_codebook)?;
+                    bind_public(&mut layouter, &config, hashed, root.0, slot)?;
+                }
+            }
+            let codes = lookup.assign_codes(&mut layouter, &self.pq_codes)?;
+            if codes.len() != pq_rows.len() {
+                return Err(Error::Synthesis);
+            }
+            let residual_codes = if self.params.residual_centroids.is_some() {
+                let residual_codes = lookup.assign_codes(&mut layouter, &self.residual_codes)?;
+                if residual_codes.len() != pq_rows.len()
+                    || self.primary_vectors.len() != pq_rows.len()
+                {
+                    return Err(Error::Synthesis);
+                }
+                residual_codes
+            } else {
+                vec![]
+            };
+            for (row_idx, (row_codes, pq_row)) in codes.iter().zip(pq_rows.iter()).enumerate() {
+                let Some(residual_row) = residual_codes.get(row_idx) else {
+                    lookup.constrain_row(
+                        &mut layouter,
+                        row_idx,
+                        row_codes,
+                        pq_row,
+                        self.params.sub_dim(),
+                        0,
+                    )?;
+                    continue;
+                };
+                let (primary, residual) = split_stages(
+                    &mut layouter,
+                    &config,
+                    pq_row,
+                    &self.primary_vectors[row_idx],
+                    row_idx,
+                )?;
+                lookup.constrain_row(
+                    &mut layouter,
+                    row_idx,
+                    row_codes,
+                    &primary,
+                    self.params.sub_dim(),
+                    0,
+                )?;
+                lookup.constrain_row(
+                    &mut layouter,
+                    row_idx,
+                    residual_row,
+                    &residual,
+                    self.params.sub_dim(),
+                    self.params.subvectors,
+                )?;
+            }
+            let flat: Vec<AssignedValue> = codes
+                .concat()
+                .into_iter()
+                .chain(residual_codes.concat())
+                .collect();
+            let capacity = domain_capacity(CODES_DOMAIN, flat.len());
+            let (commitment, _) = chip.hash(&mut layouter, capacity, &flat)?;
+            bind_public(&mut layouter, &config, hashed, commitment, slot)?;
+        }
+
+        if let (Some(dequantize), Some(slot)) = (&config.dequantize, self.params.scalar_slot()) {
+            let dequantizer = DequantizeChip::construct(dequantize.clone());
+            dequantizer.assign_table(&mut layouter)?;
+            let scales = assign_values(&mut layouter, &config, "scales", &self.scales)?;
+            let zeros = assign_values(&mut layouter, &config, "zero points", &self.zero_points)?;
+            if self.scalar_codes.len() != pq_rows.len() {
+                return Err(Error::Synthesis);
+            }
+            let mut inputs = scales.clone();
+            inputs.extend_from_slice(&zeros);
+            for (row_idx, (codes, pq_row)) in
+                self.scalar_codes.iter().zip(pq_rows.iter()).enumerate()
+            {
+                let code_cells = dequantizer.dequantize_row(
+                    &mut layouter,
+                    row_idx,
+                    codes,
+                    &scales,
+                    &zeros,
+                    pq_row,
+                )?;
+                inputs.extend(code_cells);
+            }
+            let capacity = domain_capacity(SCALAR_DOMAIN, inputs.len());
+            let (commitment, _) = chip.hash(&mut layouter, capacity, &inputs)?;
+            bind_public(&mut layouter, &config, hashed, commitment, slot)?;
+        }
+
+        if let Some(slot) = self.params.rotation_slot() {
+            let entries = assign_values(&mut layouter, &config, "rotation", &self.rotation)?;
+            let capacity = domain_capacity(ROTATION_DOMAIN, entries.len());
+            let (commitment, _) = chip.hash(&mut layouter, capacity, &entries)?;
+            bind_public(&mut layouter, &config, hashed, commitment, slot)?;
+        }
+
+        if let Some(slot) = self.params.sparsity_slot() {
+            let flat: Vec<Fr> = self.sparse_indices.concat();
+            let indices = assign_values(&mut layouter, &config, "sparsity pattern", &flat)?;
+            let capacity = domain_capacity(SPARSITY_DOMAIN, indices.len());
+            let (commitment, _) = chip.hash(&mut layouter, capacity, &indices)?;
+            bind_public(&mut layouter, &config, hashed, commitment, slot)?;
+        }
+
+        if let (Some(epsilons), Some(slot)) = (&config.epsilons, self.params.epsilon_slot()) {
+            let distance = DistanceChip::construct(epsilons.distance.clone());
+            let range = RangeCheckChip::construct(epsilons.range.clone());
+            let bounds = distance.assign_vector(
+                &mut layouter,
+                "subvector_bounds",
+                &self.subvector_bounds,
+            )?;
+            let capacity = domain_capacity(EPSILONS_DOMAIN, bounds.len());
+            let (commitment, _) = chip.hash(&mut layouter, capacity, &bounds)?;
+            bind_public(&mut layouter, &config, hashed, commitment, slot)?;
+            let sub_dim = self.params.sub_dim();
+            if sub_dim == 0 || bounds.len() != self.params.subvectors {
+                return Err(Error::Synthesis);
+            }
+            for (folded, pq) in folded_rows.iter().zip(pq_rows.iter()) {
+                let segments = folded.chunks(sub_dim).zip(pq.chunks(sub_dim));
+                for ((folded, pq), bound) in segments.zip(bounds.iter()) {
+                    let residual = distance.squared_distance(&mut layouter, folded, pq)?;
+                    let scaled = scale_residual(&mut layouter, &config, epsilons, residual)?;
+                    range.assert_less_or_equal(&mut layouter, scaled, *bound, DISTANCE_BITS)?;
+                }
+            }
+        }
+
+        if let (Some(epsilons), Some(slot)) = (&config.epsilons, self.params.sla_slot()) {
+            let range = RangeCheckChip::construct(epsilons.range.clone());
+            let bound = assign_values(&mut layouter, &config, "sla bound", &[self.sla_bound])?[0];
+            bind_public(&mut layouter, &config, hashed, bound.0, slot)?;
+            for residual in &residuals {
+                let scaled = scale_residual(&mut layouter, &config, epsilons, *residual)?;
+                range.assert_less_or_equal(&mut layouter, scaled, bound, DISTANCE_BITS)?;
+            }
+        }
+
+        if let (Some(stats), Some(slot)) = (&config.stats, self.params.compression_slot()) {
+            let compression = self.params.compression();
+            let values = [compression.original_bytes, compression.pq_bytes].map(Fr::from);
+            let cells = layouter.assign_region(
+                || "compression stats",
+                |mut region: Region<'_, Fr>| {
+                    let mut cells = Vec::with_capacity(values.len());
+                    for (row, value) in values.iter().enumerate() {
+                        stats.selector.enable(&mut region, row)?;
+                        region.assign_fixed(stats.constant, row, *value);
+                        let cell = region.assign_advice(config.advice, row, Value::known(*value));
+                        cells.push(cell.cell());
+                    }
+                    Ok(cells)
+                },
+            )?;
+            for (offset, cell) in cells.into_iter().enumerate() {
+                bind_public(&mut layouter, &config, hashed, cell, slot + offset)?;
+            }
+        }
+
+        if let (Some(sha256), Some(hashed)) = (&config.sha256, hashed) {
+            let chip = Sha256Chip::construct(sha256.clone());
+            let (digest, _) = chip.hash_to_field(&mut layouter, hashed)?;
+            constrain_to_instance(&mut layouter, &config, digest, 0)?;
+        }
+
         Ok(())
     }
 }
 
+/// Advice cells for the public values hashed in `instance_hash` mode.
+fn assign_hashed_inputs(
+    layouter: &mut impl Layouter<Fr>,
+    config: &FoldedConfig,
+    values: &[Fr],
+    params: &FoldedParams,
+) -> Result<Vec<AssignedValue>, Error> {
+    if values.len() != params.public_len() {
+        return Err(Error::Synthesis);
+    }
+    assign_values(layouter, config, "hashed public values", values)
+}
+
+/// Copies `residual` and returns it multiplied by `S^2`.
+fn scale_residual(
+    layouter: &mut impl Layouter<Fr>,
+    config: &FoldedConfig,
+    epsilons: &EpsilonConfig,
+    residual: AssignedValue,
+) -> Result<AssignedValue, Error> {
+    layouter.assign_region(
+        || "epsilon_scale",
+        |mut region: Region<'_, Fr>| {
+            epsilons.scale.enable(&mut region, 0)?;
+            let copied = region.assign_advice(config.advice, 0, Value::known(residual.1));
+            region.constrain_equal(copied.cell(), residual.0);
+            let scaled = residual.1 * epsilons.scale_squared;
+            let cell = region.assign_advice(config.advice, 1, Value::known(scaled));
+            Ok((cell.cell(), scaled))
+        },
+    )
+}
+
+/// Free advice cells for `values`, to be bound by a hash.
+fn assign_values(
+    layouter: &mut impl Layouter<Fr>,
+    config: &FoldedConfig,
+    name: &'static str,
+    values: &[Fr],
+) -> Result<Vec<AssignedValue>, Error> {
+    layouter.assign_region(
+        || name,
+        |mut region: Region<'_, Fr>| {
+            Ok(values
+                .iter()
+                .enumerate()
+                .map(|(row, value)| {
+                    let cell =
+                        region.assign_advice(config.commit_advice, row, Value::known(*value));
+                    (cell.cell(), *value)
+                })
+                .collect())
+        },
+    )
+}
+
+/// Binds `cell` to public value `slot`: the instance row itself, or the
+/// hashed value cell in `instance_hash` mode.
+fn bind_public(
+    layouter: &mut impl Layouter<Fr>,
+    config: &FoldedConfig,
+    hashed: Option<&[AssignedValue]>,
+    cell: Cell,
+    slot: usize,
+) -> Result<(), Error> {
+    let Some(hashed) = hashed else {
+        return constrain_to_instance(layouter, config, cell, slot);
+    };
+    layouter.assign_region(
+        || format!("public_{slot}"),
+        |mut region: Region<'_, Fr>| {
+            region.constrain_equal(cell, hashed[slot].0);
+            Ok(())
+        },
+    )
+}
+
+fn constrain_to_instance(
+    layouter: &mut impl Layouter<Fr>,
+    config: &FoldedConfig,
+    cell: Cell,
+    slot: usize,
+) -> Result<(), Error> {
+    layouter.assign_region(
+        || format!("instance_{slot}"),
+        |mut region: Region<'_, Fr>| {
+            let public = region.assign_advice_from_instance(
+                || "instance_public",
+                config.instance,
+                slot,
+                config.commit_advice,
+                0,
+            )?;
+            region.constrain_equal(cell, public.cell());
+            Ok(())
+        },
+    )
+}
+
+/// Root of an assigned codebook under `mode`, as in [`crate::codebook::commit`].
+fn commit_codebook(
+    layouter: &mut impl Layouter<Fr>,
+    chip: &PoseidonChip,
+    mode: CommitMode,
+    codebook: &[Vec<Vec<AssignedValue>>],
+) -> Result<AssignedValue, Error> {
+    match mode {
+        CommitMode::Merkle => {
+            let mut leaves = Vec::new();
+            for centroid in codebook.iter().flatten() {
+                leaves.push(chip.hash_leaf(layouter, centroid)?);
+            }
+            chip.merkle_root(layouter, leaves)
+        }
+        CommitMode::Poseidon => {
+            let flat: Vec<AssignedValue> = codebook.iter().flatten().flatten().copied().collect();
+            let capacity = domain_capacity(CODEBOOK_DOMAIN, flat.len());
+            chip.hash(layouter, capacity, &flat)
+        }
+    }
+}
+
+/// Lays out `pq[i], primary[i], residual[i]` triples under the difference
+/// gate, so `pq = primary + residual`, and returns the primary and residual
+/// cells. The pq cells are copies of `pq_row`.
+fn split_stages(
+    layouter: &mut impl Layouter<Fr>,
+    config: &FoldedConfig,
+    pq_row: &[AssignedValue],
+    primary: &[Fr],
+    row_idx: usize,
+) -> Result<(Vec<AssignedValue>, Vec<AssignedValue>), Error> {
+    if pq_row.len() != primary.len() {
+        return Err(Error::Synthesis);
+    }
+    layouter.assign_region(
+        || format!("residual_stage_{row_idx}"),
+        |mut region: Region<'_, Fr>| {
+            let mut primary_cells = Vec::with_capacity(primary.len());
+            let mut residual_cells = Vec::with_capacity(primary.len());
+            for (idx, (pq, first)) in pq_row.iter().zip(primary).enumerate() {
+                let offset = 3 * idx;
+                let second = pq.1 - *first;
+                let pq_cell = region.assign_advice(config.advice, offset, Value::known(pq.1));
+                region.constrain_equal(pq_cell.cell(), pq.0);
+                let first_cell =
+                    region.assign_advice(config.advice, offset + 1, Value::known(*first));
+                primary_cells.push((first_cell.cell(), *first));
+                let second_cell =
+                    region.assign_advice(config.advice, offset + 2, Value::known(second));
+                residual_cells.push((second_cell.cell(), second));
+                config.diff_selector.enable(&mut region, offset)?;
+            }
+            Ok((primary_cells, residual_cells))
+        },
+    )
+}
+
+/// Lays out `folded[i], pq[i], diff[i]` triples, with the running sum of
+/// `diff^2` beside each diff, and constrains the final sum to equal
+/// `epsilon_squared`. Returns the folded and pq cells and the
+/// `epsilon_squared` cell.
+///
+/// A mismatch fails synthesis here; the `epsilon_check` gate makes the
+/// same mismatch unsatisfiable for a prover that skips this check.
 fn enforce_component_difference(
     layouter: &mut impl Layouter<Fr>,
     config: &FoldedConfig,
@@ -136,36 +1262,43 @@ fn enforce_component_difference(
     pq: &[Fr],
     epsilon_squared: Fr,
     batch_idx: usize,
-) -> Result<(), Error> {
-    if folded.len() != pq.len() {
+) -> Result<(Vec<AssignedValue>, Vec<AssignedValue>, AssignedValue), Error> {
+    if folded.len() != pq.len() || folded.is_empty() {
         return Err(Error::Synthesis);
     }
     let pairs: Vec<_> = folded.iter().zip(pq.iter()).collect();
+    let sum: Fr = pairs.iter().map(|(a, b)| (**a - **b).square()).sum();
+    if sum != epsilon_squared {
+        return Err(Error::Synthesis);
+    }
     layouter.assign_region(
         || format!("diff_batch_{batch_idx}"),
         |mut region: Region<'_, Fr>| {
             let mut offset = 0;
             let mut sum = Fr::zero();
-            for (_idx, (a, b)) in pairs.iter().enumerate() {
+            let mut folded_cells = Vec::with_capacity(pairs.len());
+            let mut pq_cells = Vec::with_capacity(pairs.len());
+            for (idx, (a, b)) in pairs.iter().enumerate() {
                 let diff = **a - **b;
                 sum += diff.square();
-                region.assign_advice(config.advice, offset, Value::known(**a));
-                region.assign_advice(config.advice, offset + 1, Value::known(**b));
+                let folded_cell = region.assign_advice(config.advice, offset, Value::known(**a));
+                folded_cells.push((folded_cell.cell(), **a));
+                let pq_cell = region.assign_advice(config.advice, offset + 1, Value::known(**b));
+                pq_cells.push((pq_cell.cell(), **b));
                 region.assign_advice(config.advice, offset + 2, Value::known(diff));
+                region.assign_advice(config.commit_advice, offset + 2, Value::known(sum));
                 config.diff_selector.enable(&mut region, offset)?;
+                if idx == 0 {
+                    config.residual_first.enable(&mut region, offset)?;
+                } else {
+                    config.residual_next.enable(&mut region, offset)?;
+                }
                 offset += 3;
             }
-            let diff_val = sum - epsilon_squared;
-            if diff_val != Fr::zero() {
-                println!(
-                    "epsilon mismatch batch {}: sum {:?} != epsilon {:?}",
-                    batch_idx, sum, epsilon_squared
-                );
-            }
-            region.assign_advice(config.advice, offset, Value::known(diff_val));
+            let residual =
+                region.assign_advice(config.advice, offset, Value::known(epsilon_squared));
             config.sum_selector.enable(&mut region, offset)?;
-            Ok(())
+            Ok((folded_cells, pq_cells, (residual.cell(), epsilon_squared)))
         },
     )
 }
-
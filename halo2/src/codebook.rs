@@ -0,0 +1,150 @@
+//! Reproducible `codebookRoot` commitments.
+//!
+//! Centroid components are encoded with `prove::float_to_field`, exactly as
+//! the circuit assigns the codebook table, and committed in one of two modes:
+//!
+//! * `merkle`: one `poseidon::hash_leaf` per centroid, ordered subspace-major,
+//!   combined with `merkle::root`. Individual centroids can be opened later.
+//! * `poseidon`: a single sponge over every component under
+//!   [`CODEBOOK_DOMAIN`]. Fewer hashes, no openings.
+//!
+//! When a circuit is keyed with a commit mode, `codebookRoot` must be the
+//! canonical field element produced here; the circuit recomputes it from the
+//! codebook table and constrains it to instance slot [`CODEBOOK_ROOT_SLOT`].
+
+use std::{fmt, path::Path, str::FromStr};
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    merkle,
+    platform::from_json_slice,
+    poseidon::{domain_capacity, hash_with_capacity, CODEBOOK_DOMAIN},
+    prove::to_field_matrix,
+    storage::{path_key, LocalStorage, Storage},
+};
+
+/// Instance row holding `codebookRoot`.
+pub const CODEBOOK_ROOT_SLOT: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitMode {
+    Merkle,
+    Poseidon,
+}
+
+impl fmt::Display for CommitMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CommitMode::Merkle => "merkle",
+            CommitMode::Poseidon => "poseidon",
+        })
+    }
+}
+
+impl FromStr for CommitMode {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "merkle" => Ok(CommitMode::Merkle),
+            "poseidon" | "poseidon-hash" => Ok(CommitMode::Poseidon),
+            other => anyhow::bail!("unknown codebook commit mode {other}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Root {
+    pub mode: CommitMode,
+    pub value: Fr,
+}
+
+impl Root {
+    pub fn hash(&self) -> Hash256 {
+        Hash256::from_field(&self.value)
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.hash().to_hex()
+    }
+}
+
+/// Commits to `codebook[subspace][centroid][component]`.
+pub fn commit(codebook: &[Vec<Vec<f64>>], mode: CommitMode) -> Root {
+    let fields: Vec<Vec<Vec<Fr>>> = codebook
+        .iter()
+        .map(|subspace| to_field_matrix(subspace))
+        .collect();
+    Root {
+        mode,
+        value: commit_fields(&fields, mode),
+    }
+}
+
+/// [`commit`] over already-encoded field values.
+pub fn commit_fields(codebook: &[Vec<Vec<Fr>>], mode: CommitMode) -> Fr {
+    match mode {
+        CommitMode::Merkle => merkle::root(
+            codebook
+                .iter()
+                .flat_map(|subspace| merkle::leaves(subspace))
+                .collect(),
+        ),
+        CommitMode::Poseidon => {
+            let flat: Vec<Fr> = codebook.iter().flatten().flatten().copied().collect();
+            hash_with_capacity(domain_capacity(CODEBOOK_DOMAIN, flat.len()), &flat)
+        }
+    }
+}
+
+/// A codebook serialized together with its committed root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommittedCodebook {
+    pub mode: CommitMode,
+    pub root: Hash256,
+    pub subvectors: usize,
+    pub centroids: usize,
+    pub codebook: Vec<Vec<Vec<f64>>>,
+}
+
+impl CommittedCodebook {
+    pub fn new(codebook: Vec<Vec<Vec<f64>>>, mode: CommitMode) -> Self {
+        let root = commit(&codebook, mode).hash();
+        Self {
+            mode,
+            root,
+            subvectors: codebook.len(),
+            centroids: codebook.first().map(Vec::len).unwrap_or(0),
+            codebook,
+        }
+    }
+
+    /// Recomputes the root and checks it against the recorded one.
+    pub fn verify(&self) -> Result<Root> {
+        let root = commit(&self.codebook, self.mode);
+        if self.root.to_canonical_field()? != root.value {
+            anyhow::bail!(
+                "codebook root {} does not match contents ({} under {})",
+                self.root,
+                root.to_hex(),
+                self.mode
+            );
+        }
+        Ok(root)
+    }
+}
+
+pub fn load_committed_codebook(path: &Path) -> Result<CommittedCodebook> {
+    load_committed_codebook_from(&LocalStorage::default(), &path_key(path))
+}
+
+pub fn load_committed_codebook_from(storage: &dyn Storage, key: &str) -> Result<CommittedCodebook> {
+    let bytes = storage.read(key)?;
+    from_json_slice(&bytes).with_context(|| format!("parsing codebook {key}"))
+}
@@ -0,0 +1,138 @@
+//! Proof format and circuit versions, and which combinations this build verifies.
+//!
+//! * The proof format covers the commitment scheme, multiopen and transcript:
+//!   a change there makes every existing proof unreadable. The transcript
+//!   hash is chosen per proof and recorded next to the versions, see
+//!   [`crate::transcript`]; without a record it is blake2b.
+//! * The circuit version changes whenever constraints or the instance layout
+//!   change. Up to version 16 circuits keyed with default params kept their
+//!   constraints and later versions only added opt-in modes; version 17
+//!   changed the residual gates of every circuit, version 20 range checked
+//!   laid-out vector components and version 22 keyed every circuit's vector
+//!   shape and component ranges, each retiring the older ones.
+//!
+//! Both are recorded in proof metadata. Checking them before verification
+//! turns an opaque transcript failure into an explicit version error.
+//!
+//! Circuit versions:
+//!
+//! 1. folded/pq residual checks over committed instances
+//! 2. optional `foldedVectorRoot` Merkle root
+//! 3. optional `pqCodesCommitment` and in-circuit `codebookRoot`
+//! 4. optional `instanceHash`: a single SHA-256 instance over the public values
+//! 5. optional `lineage`: the previous block's proof digest as a public value
+//! 6. optional `beacon`: a randomness beacon value as a public value
+//! 7. optional sparse layout (`nonzeros`) with a committed sparsity pattern
+//! 8. optional `compressionStats` pinned to the keyed PQ shape
+//! 9. optional `subvectorEpsilons`: per-subspace residual bounds
+//! 10. optional `delta`: changed vectors applied as Merkle leaf updates to
+//!     `previousVectorRoot`
+//! 11. optional `witnessCommitment`: a salted Merkle root over the full
+//!     witness rows
+//! 12. optional `rotation`: a commitment to the OPQ rotation matrix
+//! 13. optional two-stage residual quantization (`residualCentroids`) with a
+//!     `residualCodebookRoot`
+//! 14. optional `scalar`: int8 dequantization with a `scalarCommitment`
+//! 15. optional `rowPermutation`: rows committed in a shuffled order with a
+//!     `permutationCommitment`
+//! 16. optional `hiding`: `foldedCommitment` and `pqCommitment` as blinded
+//!     Poseidon commitments recomputed in-circuit
+//! 17. residuals accumulated in-circuit from the squared differences; every
+//!     earlier version's keys accepted any claimed residual
+//! 18. optional `vectorCommitments`: `foldedCommitment` and `pqCommitment`
+//!     as Poseidon hashes of the rows recomputed in-circuit
+//! 19. optional `slaBound`: every vector's residual bounded by a public
+//!     maximum reconstruction error
+//! 20. every laid-out vector component range checked to 48-bit fixed point
+//!     by limb lookups; earlier keys accepted any field element
+//! 21. optional fixed-point `scale` other than 1e6, recorded in proof
+//!     metadata
+//! 22. every circuit keys its vector shape, range checks its components and
+//!     commits the residual cells at public value `residualCommitment`;
+//!     earlier default-mode keys laid out no residuals and checked no
+//!     component ranges
+//! 23. `instanceHash` inputs checked below the modulus before hashing; only
+//!     keys of that mode changed, so version 22 proofs still verify
+
+use std::{fmt, ops::RangeInclusive};
+
+pub const PROOF_FORMAT_VERSION: u32 = 1;
+pub const CIRCUIT_VERSION: u32 = 23;
+
+/// Metadata written before versions were recorded is read as this version.
+pub const LEGACY_VERSION: u32 = 1;
+
+/// Circuit versions a proof format can be verified for by this build.
+pub struct Compatibility {
+    pub proof_format: u32,
+    pub circuits: RangeInclusive<u32>,
+}
+
+pub const COMPATIBILITY: &[Compatibility] = &[Compatibility {
+    proof_format: 1,
+    circuits: 22..=CIRCUIT_VERSION,
+}];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incompatibility {
+    NewerProofFormat { found: u32, supported: u32 },
+    UnknownProofFormat { found: u32 },
+    NewerCircuit { found: u32, supported: u32 },
+    RetiredCircuit { found: u32, oldest: u32 },
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Incompatibility::NewerProofFormat { found, supported } => write!(
+                f,
+                "proof produced with newer proof format {found}; this verifier reads format {supported} and must be upgraded"
+            ),
+            Incompatibility::UnknownProofFormat { found } => {
+                write!(f, "proof format {found} is not supported by this verifier")
+            }
+            Incompatibility::NewerCircuit { found, supported } => write!(
+                f,
+                "proof produced by newer circuit version {found}; this verifier supports up to {supported} and must be upgraded"
+            ),
+            Incompatibility::RetiredCircuit { found, oldest } => write!(
+                f,
+                "proof produced by circuit version {found}, which is no longer supported (oldest is {oldest}); re-prove with a current prover"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Incompatibility {}
+
+/// Checks a proof's recorded versions against [`COMPATIBILITY`].
+pub fn check(proof_format: u32, circuit_version: u32) -> Result<(), Incompatibility> {
+    let Some(entry) = COMPATIBILITY
+        .iter()
+        .find(|entry| entry.proof_format == proof_format)
+    else {
+        return Err(if proof_format > PROOF_FORMAT_VERSION {
+            Incompatibility::NewerProofFormat {
+                found: proof_format,
+                supported: PROOF_FORMAT_VERSION,
+            }
+        } else {
+            Incompatibility::UnknownProofFormat {
+                found: proof_format,
+            }
+        });
+    };
+    if circuit_version > *entry.circuits.end() {
+        return Err(Incompatibility::NewerCircuit {
+            found: circuit_version,
+            supported: *entry.circuits.end(),
+        });
+    }
+    if circuit_version < *entry.circuits.start() {
+        return Err(Incompatibility::RetiredCircuit {
+            found: circuit_version,
+            oldest: *entry.circuits.start(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! Self-describing proofs.
+//!
+//! A container is the proof bytes behind a small header naming the instance
+//! layout ([`FoldedParams`]), the circuit and proof format versions, the
+//! transcript and a digest of the verifying key. A verifier holding only a
+//! serialized params/vk pair can read the vk with the embedded layout and
+//! encode the public inputs the same way, so one verifier build serves
+//! deployments keyed with different layouts. The header never replaces the
+//! vk: the digest must match the vk the proof is checked against.
+//!
+//! Layout: `YYSPROOF`, the header length as a little-endian `u32`, the JSON
+//! header, then the raw proof.
+
+use anyhow::{Context, Result};
+use halo2_proofs::plonk::VerifyingKey;
+use halo2curves::bn256::G1Affine;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    circuit::FoldedParams,
+    compat::{self, CIRCUIT_VERSION, PROOF_FORMAT_VERSION},
+    errors::{Coded, ErrorCode},
+    hashing::digest,
+    keys::KEY_FORMAT,
+    platform::from_json_slice,
+    public_inputs::ParsedPublicInputs,
+    transcript::TranscriptKind,
+    verify::VerifierKeys,
+};
+
+pub const CONTAINER_MAGIC: &[u8; 8] = b"YYSPROOF";
+pub const CONTAINER_VERSION: u32 = 1;
+/// Headers are a few hundred bytes; anything past this is not a container.
+pub const MAX_HEADER_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofHeader {
+    pub version: u32,
+    pub proof_format_version: u32,
+    pub circuit_version: u32,
+    pub circuit_k: u32,
+    /// Instance layout the proof was made for.
+    pub circuit: FoldedParams,
+    #[serde(default, skip_serializing_if = "TranscriptKind::is_default")]
+    pub transcript: TranscriptKind,
+    /// [`vk_digest`] of the verifying key the proof verifies under.
+    pub vk_digest: Hash256,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofContainer {
+    pub header: ProofHeader,
+    pub proof: Vec<u8>,
+}
+
+/// blake3 of the serialized verifying key.
+pub fn vk_digest(vk: &VerifyingKey<G1Affine>) -> Result<Hash256> {
+    let mut bytes = Vec::new();
+    vk.write(&mut bytes, KEY_FORMAT)?;
+    Ok(digest(&bytes))
+}
+
+/// Whether `bytes` start like a container rather than a raw proof.
+pub fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(CONTAINER_MAGIC)
+}
+
+impl ProofContainer {
+    /// Wraps a proof made for `circuit` under the keys `vk` belongs to.
+    pub fn seal(
+        proof: Vec<u8>,
+        circuit: &FoldedParams,
+        circuit_k: u32,
+        vk: &VerifyingKey<G1Affine>,
+        transcript: TranscriptKind,
+    ) -> Result<Self> {
+        Ok(Self {
+            header: ProofHeader {
+                version: CONTAINER_VERSION,
+                proof_format_version: PROOF_FORMAT_VERSION,
+                circuit_version: CIRCUIT_VERSION,
+                circuit_k,
+                circuit: circuit.clone(),
+                transcript,
+                vk_digest: vk_digest(vk)?,
+            },
+            proof,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let header = serde_json::to_vec(&self.header)?;
+        let mut bytes =
+            Vec::with_capacity(CONTAINER_MAGIC.len() + 4 + header.len() + self.proof.len());
+        bytes.extend_from_slice(CONTAINER_MAGIC);
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(&self.proof);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let malformed = |message: String| Coded::new(ErrorCode::MalformedProof, message);
+        let rest = bytes
+            .strip_prefix(CONTAINER_MAGIC.as_slice())
+            .ok_or_else(|| malformed("not a proof container".to_string()))?;
+        let (len, rest) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(|| malformed("truncated proof container".to_string()))?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if len > MAX_HEADER_BYTES || len > rest.len() {
+            anyhow::bail!(malformed(format!(
+                "proof container header of {len} bytes does not fit"
+            )));
+        }
+        let (header, proof) = rest.split_at(len);
+        let header: ProofHeader =
+            from_json_slice(header).context("parsing the proof container header")?;
+        if header.version != CONTAINER_VERSION {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Incompatible,
+                format!("unsupported proof container version {}", header.version)
+            ));
+        }
+        Ok(Self {
+            header,
+            proof: proof.to_vec(),
+        })
+    }
+
+    /// Checks that the header describes `keys`: same verifying key and
+    /// layout, and versions this build reads.
+    pub fn check_keys(&self, keys: &VerifierKeys) -> Result<()> {
+        compat::check(
+            self.header.proof_format_version,
+            self.header.circuit_version,
+        )?;
+        let digest = vk_digest(&keys.vk)?;
+        if digest != self.header.vk_digest {
+            anyhow::bail!(Coded::new(
+                ErrorCode::KeyMismatch,
+                format!(
+                    "proof was made for verifying key {}, these keys are {digest}",
+                    self.header.vk_digest
+                )
+            ));
+        }
+        if keys.circuit != self.header.circuit {
+            anyhow::bail!(Coded::new(
+                ErrorCode::KeyMismatch,
+                format!(
+                    "proof embeds layout {:?}, keys are for {:?}",
+                    self.header.circuit, keys.circuit
+                )
+            ));
+        }
+        Ok(())
+    }
+
+    /// Verifies the proof for `public_inputs`, encoded with the embedded
+    /// layout, under the embedded transcript.
+    pub fn verify(&self, public_inputs: &ParsedPublicInputs, keys: &VerifierKeys) -> Result<()> {
+        self.check_keys(keys)?;
+        let instances = public_inputs.to_instances(&self.header.circuit)?;
+        keys.verify_in(&instances, &self.proof, self.header.transcript)
+    }
+}
@@ -0,0 +1,202 @@
+//! Cross-validation of `MockProver` against real proving.
+//!
+//! For every case, `MockProver` must be satisfied exactly when a real proof
+//! of the same circuit and instances verifies. A case where the two
+//! disagree points at a gate the mock checks but the verifier never
+//! enforces, a cell left unconstrained, or instances that reach them
+//! differently. [`cross_check`] runs a block as given and with a mutated
+//! instance and witness value; the mutated cases must be rejected by both.
+//! The witness mutation keeps every check made while building the circuit
+//! passing, so only the constraints can reject it.
+//!
+//! `yysfold cross-check` runs this over a corpus of blocks and fails on any
+//! divergence, for a nightly job over the golden vectors.
+
+use anyhow::Result;
+use halo2_proofs::dev::MockProver;
+use halo2curves::bn256::Fr;
+use serde::Serialize;
+
+use crate::{
+    circuit::FoldedCircuit,
+    io::WitnessData,
+    prove::{build_circuit, create_circuit_proof},
+    prover::Prover,
+    public_inputs::ParsedPublicInputs,
+    shape::{ensure_rows, WitnessShape},
+    verify::verify_with_keys,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Mutation {
+    /// The block as given.
+    None,
+    /// The first instance plus one.
+    Instance,
+    /// The first folded component that differs from its pq counterpart
+    /// moved onto it, with the row's claimed residual updated to match.
+    Witness,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaseOutcome {
+    pub name: String,
+    pub mutation: Mutation,
+    pub mock_satisfied: bool,
+    /// First failure `MockProver` reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mock_error: Option<String>,
+    pub proof_verified: bool,
+    /// Why proving or verifying failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prover_error: Option<String>,
+}
+
+impl CaseOutcome {
+    pub fn agrees(&self) -> bool {
+        self.mock_satisfied == self.proof_verified
+    }
+}
+
+/// Runs `name` unchanged and mutated through both provers with `prover`'s
+/// keys.
+pub fn cross_check(
+    prover: &Prover,
+    name: &str,
+    witness: &WitnessData,
+    public_inputs: &ParsedPublicInputs,
+) -> Result<Vec<CaseOutcome>> {
+    let layout = prover.layout();
+    ensure_rows(layout, WitnessShape::of(witness)?, prover.circuit_k())?;
+    let circuit = build_circuit(witness, public_inputs, layout)?;
+    let instances = circuit.public_inputs.clone();
+
+    let mut cases = vec![check_case(
+        prover,
+        name,
+        Mutation::None,
+        &circuit,
+        &instances,
+    )];
+
+    let mut altered = instances.clone();
+    if let Some(first) = altered.first_mut() {
+        *first += Fr::one();
+        cases.push(check_case(
+            prover,
+            name,
+            Mutation::Instance,
+            &circuit,
+            &altered,
+        ));
+    }
+
+    let mut mutated = circuit.clone();
+    if mutate_witness(&mut mutated) {
+        cases.push(check_case(
+            prover,
+            name,
+            Mutation::Witness,
+            &mutated,
+            &instances,
+        ));
+    }
+    Ok(cases)
+}
+
+/// Applies [`Mutation::Witness`] to `circuit`; false when every row
+/// already equals its reconstruction.
+fn mutate_witness(circuit: &mut FoldedCircuit) -> bool {
+    let rows = circuit.folded_vectors.iter_mut().zip(&circuit.pq_vectors);
+    for (row, (folded, pq)) in rows.enumerate() {
+        let Some(component) = (0..folded.len()).find(|&idx| folded[idx] != pq[idx]) else {
+            continue;
+        };
+        folded[component] = pq[component];
+        circuit.epsilon_squared[row] = folded
+            .iter()
+            .zip(pq)
+            .fold(Fr::zero(), |acc, (a, b)| acc + (*a - *b).square());
+        return true;
+    }
+    false
+}
+
+fn check_case(
+    prover: &Prover,
+    name: &str,
+    mutation: Mutation,
+    circuit: &FoldedCircuit,
+    instances: &[Fr],
+) -> CaseOutcome {
+    let mock = MockProver::run(prover.circuit_k(), circuit, vec![instances.to_vec()])
+        .map_err(|err| err.to_string())
+        .and_then(|mock| {
+            mock.verify().map_err(|failures| {
+                failures
+                    .first()
+                    .map(|failure| failure.to_string())
+                    .unwrap_or_default()
+            })
+        });
+    let real = create_circuit_proof(prover.params(), prover.proving_key(), circuit, instances)
+        .and_then(|proof| {
+            verify_with_keys(
+                prover.params(),
+                prover.proving_key().get_vk(),
+                instances,
+                &proof,
+            )
+        })
+        .map_err(|err| format!("{err:#}"));
+    CaseOutcome {
+        name: name.to_string(),
+        mutation,
+        mock_satisfied: mock.is_ok(),
+        mock_error: mock.err(),
+        proof_verified: real.is_ok(),
+        prover_error: real.err(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        plonk::{keygen_pk, keygen_vk},
+        poly::kzg::commitment::ParamsKZG,
+    };
+    use halo2curves::bn256::Bn256;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+    use crate::{
+        selftest::{canary_block, canary_params},
+        shape::required_k,
+    };
+
+    #[test]
+    fn mock_and_real_prover_agree_on_a_synthetic_block() {
+        let layout = canary_params();
+        let block = canary_block(&layout).unwrap();
+        let k = required_k(&layout, WitnessShape::of(&block.witness).unwrap());
+        let params = ParamsKZG::<Bn256>::setup(k, ChaCha20Rng::seed_from_u64(0));
+        let blank = FoldedCircuit::blank_with(&layout);
+        let vk = keygen_vk(&params, &blank).unwrap();
+        let pk = keygen_pk(&params, vk, &blank).unwrap();
+        let prover = Prover::new(pk, params, layout);
+
+        let cases = cross_check(&prover, "canary", &block.witness, &block.public_inputs).unwrap();
+        assert_eq!(cases.len(), 3);
+        for case in &cases {
+            assert!(case.agrees(), "{case:?}");
+            assert_eq!(
+                case.mock_satisfied,
+                case.mutation == Mutation::None,
+                "{case:?}"
+            );
+        }
+    }
+}
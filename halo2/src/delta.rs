@@ -0,0 +1,221 @@
+//! Delta witnesses: prove only the vectors that changed since the previous
+//! block.
+//!
+//! A delta witness is an ordinary witness whose `foldedVectors` and
+//! `pqVectors` hold just the changed rows, plus a `delta` section naming
+//! their row indices and the previous block's leaves. A circuit keyed with
+//! `delta` (see [`crate::circuit::FoldedParams::delta`]) checks the residuals
+//! of those rows and applies them as Merkle leaf updates to
+//! `previousVectorRoot`, exposing the resulting root as `foldedVectorRoot`.
+//! Proving cost follows the number of changed rows, not the block size.
+//!
+//! The tree must be perfect, so delta blocks have a power-of-two number of
+//! vectors.
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    errors::{Coded, ErrorCode},
+    fixed_point::resolve_scale,
+    gadgets::merkle::MerkleUpdate,
+    io::WitnessData,
+    merkle,
+    poseidon::{hash_leaf, hash_node},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaSection {
+    /// `foldedVectorRoot` of the previous block.
+    pub previous_vector_root: Hash256,
+    /// Vectors in the block, changed or not.
+    pub vector_count: usize,
+    /// Row index of each changed vector, strictly increasing.
+    pub indices: Vec<usize>,
+    /// Leaf hashes of the previous block's folded vectors.
+    pub previous_leaves: Vec<Hash256>,
+}
+
+/// The delta witness taking `previous` to `current`: the rows whose folded or
+/// pq vector changed, and the previous block's leaves.
+pub fn diff(previous: &WitnessData, current: &WitnessData) -> Result<WitnessData> {
+    let count = current.folded_vectors.len();
+    if previous.folded_vectors.len() != count {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "previous block has {} vectors, current has {count}; a delta keeps the vector count",
+                previous.folded_vectors.len()
+            )
+        ));
+    }
+    if !count.is_power_of_two() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!("delta blocks need a power-of-two vector count, block has {count}")
+        ));
+    }
+    let indices: Vec<usize> = (0..count)
+        .filter(|&row| {
+            previous.folded_vectors[row] != current.folded_vectors[row]
+                || previous.pq_vectors.get(row) != current.pq_vectors.get(row)
+        })
+        .collect();
+    if previous.scale != current.scale {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Incompatible,
+            "previous and current blocks are at different fixed-point scales"
+        ));
+    }
+    let fixed_point = resolve_scale(None, current.scale)?;
+    let leaves = merkle::leaves(
+        &fixed_point
+            .to_field_matrix(&previous.folded_vectors)
+            .context("previous foldedVectors")?,
+    );
+    let previous_root = merkle::root(leaves.clone());
+    let residuals = current
+        .residuals
+        .as_ref()
+        .map(|residuals| {
+            indices
+                .iter()
+                .map(|&row| residuals.get(row).copied())
+                .collect::<Option<Vec<_>>>()
+                .context("current witness has fewer residuals than vectors")
+        })
+        .transpose()?;
+    Ok(WitnessData {
+        folded_vectors: indices
+            .iter()
+            .map(|&row| current.folded_vectors[row].clone())
+            .collect(),
+        pq_vectors: indices
+            .iter()
+            .map(|&row| current.pq_vectors[row].clone())
+            .collect(),
+        header_rlp: current.header_rlp.clone(),
+        pq_codes: None,
+        codebook: None,
+        sparse_vectors: None,
+        provenance: current.provenance.clone(),
+        delta: Some(DeltaSection {
+            previous_vector_root: Hash256::from_field(&previous_root),
+            vector_count: count,
+            indices,
+            previous_leaves: leaves.iter().map(Hash256::from_field).collect(),
+        }),
+        audit_salt: None,
+        residuals,
+        scale: current.scale,
+        rotation: current.rotation.clone(),
+        residual_stage: None,
+        scalar_quantization: None,
+        row_permutation: None,
+        blinding: None,
+    })
+}
+
+/// Leaf updates of `section` writing `rows` (the changed folded rows as field
+/// elements), padded with disabled updates to `capacity`, and the root after
+/// all of them.
+pub fn updates(
+    section: &DeltaSection,
+    rows: &[Vec<Fr>],
+    capacity: usize,
+) -> Result<(Vec<MerkleUpdate>, Fr)> {
+    let count = section.vector_count;
+    if !count.is_power_of_two() || section.previous_leaves.len() != count {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "delta has {} previous leaves for {count} vectors; expected a power of two of each",
+                section.previous_leaves.len()
+            )
+        ));
+    }
+    if section.indices.len() != rows.len() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "delta lists {} changed indices for {} changed rows",
+                section.indices.len(),
+                rows.len()
+            )
+        ));
+    }
+    if rows.len() > capacity {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "{} changed vectors, keys allow {capacity} per block",
+                rows.len()
+            )
+        ));
+    }
+    if section.indices.windows(2).any(|pair| pair[0] >= pair[1])
+        || section.indices.last().is_some_and(|&last| last >= count)
+    {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            format!("delta indices must be strictly increasing and below {count}")
+        ));
+    }
+
+    let leaves = section
+        .previous_leaves
+        .iter()
+        .map(Hash256::to_canonical_field)
+        .collect::<Result<Vec<_>>>()
+        .context("previousLeaves")?;
+    let mut levels = merkle::levels(leaves);
+    let root = |levels: &[Vec<Fr>]| levels.last().expect("tree has a root level")[0];
+    let previous_root = section
+        .previous_vector_root
+        .to_canonical_field()
+        .context("previousVectorRoot")?;
+    if root(&levels) != previous_root {
+        anyhow::bail!(Coded::new(
+            ErrorCode::CommitmentMismatch,
+            format!(
+                "previousLeaves hash to {}, not previousVectorRoot {}",
+                Hash256::from_field(&root(&levels)),
+                section.previous_vector_root
+            )
+        ));
+    }
+
+    let depth = count.trailing_zeros() as usize;
+    let mut updates = Vec::with_capacity(capacity);
+    for (&index, row) in section.indices.iter().zip(rows) {
+        let path = merkle::path(&levels, index)
+            .into_iter()
+            .map(|(sibling, is_right)| {
+                (sibling.expect("perfect trees have every sibling"), is_right)
+            })
+            .collect();
+        updates.push(MerkleUpdate {
+            enabled: true,
+            old_leaf: levels[0][index],
+            path,
+        });
+        let mut node = hash_leaf(row);
+        let mut position = index;
+        for level in levels.iter_mut() {
+            level[position] = node;
+            let sibling = level.get(position ^ 1).copied();
+            node = match sibling {
+                Some(sibling) if position % 2 == 1 => hash_node(sibling, node),
+                Some(sibling) => hash_node(node, sibling),
+                None => node,
+            };
+            position /= 2;
+        }
+    }
+    updates.resize(capacity, MerkleUpdate::disabled(depth));
+    Ok((updates, root(&levels)))
+}
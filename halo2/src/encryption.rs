@@ -0,0 +1,271 @@
+//! Envelope encryption for witnesses at rest.
+//!
+//! A witness is sealed with a fresh AES-256-GCM data key; the data key is
+//! wrapped by a [`KeyProvider`] (a local key-encryption key, or a KMS behind
+//! the same trait) and stored next to the ciphertext:
+//!
+//! ```json
+//! {"yysfoldEnvelope":1,"algorithm":"AES-256-GCM","keyId":"..","wrappedKey":"..",
+//!  "keyNonce":"..","nonce":"..","ciphertext":".."}
+//! ```
+//!
+//! Envelopes are written as compact JSON with `yysfoldEnvelope` first, which
+//! is how `io::load_witness` tells them apart from plain witnesses. The
+//! cipher itself needs the `encryption` feature; without it envelopes are
+//! still recognised, and opening one fails with a clear error.
+//!
+//! Key-encryption keys, data keys and the hex they are parsed from are
+//! zeroized once dropped.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::platform::from_json_slice;
+
+pub const ENVELOPE_VERSION: u32 = 1;
+pub const ALGORITHM: &str = "AES-256-GCM";
+const ENVELOPE_PREFIX: &[u8] = b"{\"yysfoldEnvelope\"";
+
+/// Hex-encoded 32-byte key-encryption key for the default [`LocalKek`].
+pub const KEY_ENV: &str = "YYSFOLD_WITNESS_KEY";
+/// File holding the hex key, used when [`KEY_ENV`] is unset.
+pub const KEY_FILE_ENV: &str = "YYSFOLD_WITNESS_KEY_FILE";
+pub const KEY_ID_ENV: &str = "YYSFOLD_WITNESS_KEY_ID";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedEnvelope {
+    pub yysfold_envelope: u32,
+    pub algorithm: String,
+    pub key_id: String,
+    pub wrapped_key: String,
+    pub key_nonce: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// A data key encrypted under a provider's key-encryption key.
+#[derive(Debug, Clone)]
+pub struct WrappedKey {
+    pub key_id: String,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Wraps and unwraps data keys; implement this to back envelopes with a KMS.
+pub trait KeyProvider: Send + Sync {
+    fn key_id(&self) -> &str;
+    fn wrap_key(&self, data_key: &[u8; 32]) -> Result<WrappedKey>;
+    fn unwrap_key(&self, wrapped: &WrappedKey) -> Result<Zeroizing<[u8; 32]>>;
+}
+
+/// Key-encryption key held in process memory.
+pub struct LocalKek {
+    key_id: String,
+    key: [u8; 32],
+}
+
+impl LocalKek {
+    pub fn new(key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        Self {
+            key_id: key_id.into(),
+            key,
+        }
+    }
+
+    pub fn from_hex(key_id: impl Into<String>, hex_key: &str) -> Result<Self> {
+        let bytes = Zeroizing::new(
+            hex::decode(hex_key.trim().trim_start_matches("0x"))
+                .context("witness key must be hex")?,
+        );
+        let key = Zeroizing::new(
+            <[u8; 32]>::try_from(bytes.as_slice())
+                .map_err(|_| anyhow::anyhow!("witness key must be 32 bytes"))?,
+        );
+        Ok(Self::new(key_id, *key))
+    }
+}
+
+impl Drop for LocalKek {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl KeyProvider for LocalKek {
+    fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    fn wrap_key(&self, data_key: &[u8; 32]) -> Result<WrappedKey> {
+        let nonce = aead::random_nonce();
+        let ciphertext = aead::encrypt(&self.key, &nonce, data_key, self.key_id.as_bytes())?;
+        Ok(WrappedKey {
+            key_id: self.key_id.clone(),
+            nonce: nonce.to_vec(),
+            ciphertext,
+        })
+    }
+
+    fn unwrap_key(&self, wrapped: &WrappedKey) -> Result<Zeroizing<[u8; 32]>> {
+        if wrapped.key_id != self.key_id {
+            anyhow::bail!(
+                "witness was sealed with key {:?}, have {:?}",
+                wrapped.key_id,
+                self.key_id
+            );
+        }
+        let plain = Zeroizing::new(
+            aead::decrypt(
+                &self.key,
+                &wrapped.nonce,
+                &wrapped.ciphertext,
+                self.key_id.as_bytes(),
+            )
+            .context("unwrapping witness data key")?,
+        );
+        <[u8; 32]>::try_from(plain.as_slice())
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow::anyhow!("unwrapped data key has wrong length"))
+    }
+}
+
+/// The configured key provider, if any: [`KEY_ENV`] or [`KEY_FILE_ENV`], with
+/// the key id from [`KEY_ID_ENV`] (default `local`).
+pub fn key_provider_from_env() -> Result<Option<Box<dyn KeyProvider>>> {
+    let key_id = std::env::var(KEY_ID_ENV).unwrap_or_else(|_| "local".to_string());
+    let hex_key = Zeroizing::new(match std::env::var(KEY_ENV) {
+        Ok(value) => value,
+        Err(_) => match std::env::var(KEY_FILE_ENV) {
+            Ok(path) => {
+                std::fs::read_to_string(&path).with_context(|| format!("opening {path:?}"))?
+            }
+            Err(_) => return Ok(None),
+        },
+    });
+    Ok(Some(Box::new(LocalKek::from_hex(key_id, &hex_key)?)))
+}
+
+pub fn is_envelope(bytes: &[u8]) -> bool {
+    let start = bytes
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    bytes[start..].starts_with(ENVELOPE_PREFIX)
+}
+
+/// Encrypts `plaintext` under a fresh data key wrapped by `provider`.
+pub fn seal(plaintext: &[u8], provider: &dyn KeyProvider) -> Result<Vec<u8>> {
+    let data_key = Zeroizing::new(aead::random_key());
+    let wrapped = provider.wrap_key(&data_key)?;
+    let nonce = aead::random_nonce();
+    let ciphertext = aead::encrypt(
+        &data_key,
+        &nonce,
+        plaintext,
+        &associated_data(&wrapped.key_id),
+    )?;
+    let envelope = EncryptedEnvelope {
+        yysfold_envelope: ENVELOPE_VERSION,
+        algorithm: ALGORITHM.to_string(),
+        key_id: wrapped.key_id,
+        wrapped_key: hex::encode(wrapped.ciphertext),
+        key_nonce: hex::encode(wrapped.nonce),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    };
+    Ok(serde_json::to_vec(&envelope)?)
+}
+
+/// Decrypts an envelope produced by [`seal`].
+pub fn open(bytes: &[u8], provider: &dyn KeyProvider) -> Result<Vec<u8>> {
+    let envelope: EncryptedEnvelope = from_json_slice(bytes).context("parsing witness envelope")?;
+    if envelope.yysfold_envelope != ENVELOPE_VERSION || envelope.algorithm != ALGORITHM {
+        anyhow::bail!(
+            "unsupported witness envelope v{} ({})",
+            envelope.yysfold_envelope,
+            envelope.algorithm
+        );
+    }
+    let wrapped = WrappedKey {
+        key_id: envelope.key_id.clone(),
+        nonce: hex::decode(&envelope.key_nonce).context("envelope keyNonce")?,
+        ciphertext: hex::decode(&envelope.wrapped_key).context("envelope wrappedKey")?,
+    };
+    let data_key = provider.unwrap_key(&wrapped)?;
+    aead::decrypt(
+        &data_key,
+        &hex::decode(&envelope.nonce).context("envelope nonce")?,
+        &hex::decode(&envelope.ciphertext).context("envelope ciphertext")?,
+        &associated_data(&envelope.key_id),
+    )
+    .context("decrypting witness (wrong key or tampered envelope)")
+}
+
+fn associated_data(key_id: &str) -> Vec<u8> {
+    format!("yysfold-witness-v{ENVELOPE_VERSION}:{ALGORITHM}:{key_id}").into_bytes()
+}
+
+#[cfg(feature = "encryption")]
+mod aead {
+    use aes_gcm::{
+        aead::{Aead, KeyInit, Payload},
+        Aes256Gcm, Key, Nonce,
+    };
+    use anyhow::Result;
+    use rand::{rngs::OsRng, RngCore};
+
+    pub fn random_key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        key
+    }
+
+    pub fn random_nonce() -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        nonce
+    }
+
+    pub fn encrypt(key: &[u8; 32], nonce: &[u8], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if nonce.len() != 12 {
+            anyhow::bail!("AES-GCM nonce must be 12 bytes");
+        }
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+            .encrypt(Nonce::from_slice(nonce), Payload { msg, aad })
+            .map_err(|_| anyhow::anyhow!("AES-GCM encryption failed"))
+    }
+
+    pub fn decrypt(key: &[u8; 32], nonce: &[u8], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if nonce.len() != 12 {
+            anyhow::bail!("AES-GCM nonce must be 12 bytes");
+        }
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+            .decrypt(Nonce::from_slice(nonce), Payload { msg, aad })
+            .map_err(|_| anyhow::anyhow!("AES-GCM authentication failed"))
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+mod aead {
+    use anyhow::Result;
+
+    const DISABLED: &str = "witness encryption needs the `encryption` feature";
+
+    pub fn random_key() -> [u8; 32] {
+        [0u8; 32]
+    }
+
+    pub fn random_nonce() -> [u8; 12] {
+        [0u8; 12]
+    }
+
+    pub fn encrypt(_key: &[u8; 32], _nonce: &[u8], _msg: &[u8], _aad: &[u8]) -> Result<Vec<u8>> {
+        anyhow::bail!(DISABLED)
+    }
+
+    pub fn decrypt(_key: &[u8; 32], _nonce: &[u8], _msg: &[u8], _aad: &[u8]) -> Result<Vec<u8>> {
+        anyhow::bail!(DISABLED)
+    }
+}
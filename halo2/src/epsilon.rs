@@ -0,0 +1,138 @@
+//! Per-subvector residual bounds.
+//!
+//! Product quantization encodes each subspace separately, so a single
+//! residual per vector hides where the error sits. With `subvector_epsilons`
+//! the circuit bounds the residual of every (vector, subspace) segment by
+//! that subspace's epsilon:
+//!
+//! ```text
+//! sum_{j in segment s} (floor(folded_j * S) - floor(pq_j * S))^2 <= floor(eps_s * S)^2
+//! ```
+//!
+//! with `S = FIXED_POINT_SCALE`, as `subvector_epsilons` needs `pq_codes`
+//! and so the default scale. The squared bounds are committed with
+//! Poseidon under [`EPSILONS_DOMAIN`] and exposed as one public value; the
+//! epsilons themselves travel in the public inputs as `subvectorEpsilons`.
+//!
+//! With `sla_bound` the circuit bounds the residual of every whole vector by
+//! `floor(sla * S)^2` at the keyed scale, exposed as a public value on its
+//! own, so a block can be checked against a contractual maximum
+//! reconstruction error `slaBound`.
+//!
+//! Every circuit also hashes the residual cell of each laid-out vector,
+//! `residual / S^2` and zero for padding rows, with Poseidon under
+//! [`RESIDUALS_DOMAIN`] into the public value `residualCommitment`, so a
+//! proof is bound to the residuals the witness generator reported.
+
+use anyhow::Result;
+use halo2curves::bn256::Fr;
+
+use crate::{
+    ann::{fixed_squared_distance, squared_threshold},
+    fixed_point::FixedPoint,
+    poseidon::{domain_capacity, hash_with_capacity, EPSILONS_DOMAIN, RESIDUALS_DOMAIN},
+    prove::FIXED_POINT_SCALE,
+};
+
+/// Squared fixed-point bounds, one per subspace.
+pub fn subvector_bounds(epsilons: &[f64]) -> Result<Vec<u128>> {
+    epsilons
+        .iter()
+        .map(|epsilon| squared_threshold(*epsilon))
+        .collect()
+}
+
+pub fn bounds_to_fields(bounds: &[u128]) -> Vec<Fr> {
+    bounds.iter().map(|bound| Fr::from_u128(*bound)).collect()
+}
+
+/// The public commitment to `bounds`, matching the in-circuit hash.
+pub fn epsilon_commitment(bounds: &[u128]) -> Fr {
+    let fields = bounds_to_fields(bounds);
+    hash_with_capacity(domain_capacity(EPSILONS_DOMAIN, fields.len()), &fields)
+}
+
+/// The residual cells of `rows` laid-out vectors with squared `residuals`
+/// in `fixed_point` units, as the circuit assigns them.
+pub fn residual_cells(residuals: &[u128], fixed_point: FixedPoint, rows: usize) -> Vec<Fr> {
+    let scale = fixed_point.inverse().square();
+    let mut cells: Vec<Fr> = residuals
+        .iter()
+        .map(|residual| Fr::from_u128(*residual) * scale)
+        .collect();
+    cells.resize(rows, Fr::zero());
+    cells
+}
+
+/// The public `residualCommitment` to `cells`, matching the in-circuit hash.
+pub fn residual_commitment(cells: &[Fr]) -> Fr {
+    hash_with_capacity(domain_capacity(RESIDUALS_DOMAIN, cells.len()), cells)
+}
+
+/// Checks every segment of every vector against its subspace bound, naming
+/// the first one that exceeds it.
+pub fn check_segments(
+    folded: &[Vec<f64>],
+    pq: &[Vec<f64>],
+    sub_dim: usize,
+    bounds: &[u128],
+) -> Result<()> {
+    for (row, (folded_row, pq_row)) in folded.iter().zip(pq).enumerate() {
+        let segments = folded_row.chunks(sub_dim).zip(pq_row.chunks(sub_dim));
+        for (subspace, ((a, b), bound)) in segments.zip(bounds).enumerate() {
+            let residual = fixed_squared_distance(a, b);
+            if residual > *bound {
+                anyhow::bail!(
+                    "vector {row} subspace {subspace}: residual {} exceeds epsilon {}",
+                    (residual as f64).sqrt() / FIXED_POINT_SCALE,
+                    (*bound as f64).sqrt() / FIXED_POINT_SCALE
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks every vector's residual against the squared SLA `bound`, both in
+/// `fixed_point` units, naming the first one that exceeds it.
+pub fn check_sla_bound(fixed_point: FixedPoint, residuals: &[u128], bound: u128) -> Result<()> {
+    if let Some(row) = residuals.iter().position(|residual| *residual > bound) {
+        let scale = fixed_point.scale() as f64;
+        anyhow::bail!(
+            "vector {row}: reconstruction error {} exceeds the SLA bound {}",
+            (residuals[row] as f64).sqrt() / scale,
+            (bound as f64).sqrt() / scale
+        );
+    }
+    Ok(())
+}
+
+/// Smallest per-subspace epsilons that every segment satisfies, rounded up
+/// by a few fixed-point units.
+pub fn covering_epsilons(folded: &[Vec<f64>], pq: &[Vec<f64>], subvectors: usize) -> Vec<f64> {
+    let sub_dim = folded.first().map(Vec::len).unwrap_or(0) / subvectors.max(1);
+    let mut worst = vec![0u128; subvectors];
+    for (folded_row, pq_row) in folded.iter().zip(pq) {
+        let segments = folded_row
+            .chunks(sub_dim.max(1))
+            .zip(pq_row.chunks(sub_dim.max(1)));
+        for (slot, (a, b)) in worst.iter_mut().zip(segments) {
+            *slot = (*slot).max(fixed_squared_distance(a, b));
+        }
+    }
+    worst
+        .iter()
+        .map(|residual| ((*residual as f64).sqrt().ceil() + 2.0) / FIXED_POINT_SCALE)
+        .collect()
+}
+
+/// Smallest SLA bound every vector satisfies at `fixed_point`, rounded up
+/// like [`covering_epsilons`].
+pub fn covering_sla_bound(fixed_point: FixedPoint, folded: &[Vec<f64>], pq: &[Vec<f64>]) -> f64 {
+    let worst = fixed_point
+        .residuals(folded, pq)
+        .into_iter()
+        .max()
+        .unwrap_or(0);
+    ((worst as f64).sqrt().ceil() + 2.0) / fixed_point.scale() as f64
+}
@@ -0,0 +1,259 @@
+//! Error catalogue shared by the CLI tools, the HTTP services and the
+//! protobuf API.
+//!
+//! Every failure surfaces with a stable code (`YF…`), a one-line hint and a
+//! docs slug. Messages stay free-form; the code is what scripts, dashboards
+//! and translated front ends key on. Errors are tagged where they are raised
+//! with [`Coded`], or recognised by type in [`classify`]; anything else is
+//! [`ErrorCode::Internal`].
+
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+use crate::{
+    cancel::{CancelReason, Cancelled},
+    compat::Incompatibility,
+    shape::ShapeMismatch,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// Missing or conflicting command line arguments.
+    Usage,
+    /// A witness, public inputs, proof or key file that does not parse.
+    InvalidInput,
+    /// A witness that does not fit the keyed circuit.
+    ShapeMismatch,
+    /// Public inputs that disagree with the witness they were built from.
+    CommitmentMismatch,
+    /// Key configs, bundles or artifacts that disagree with each other.
+    KeyMismatch,
+    /// A proof from a prover version this verifier does not read.
+    Incompatible,
+    /// A well-formed proof that does not verify.
+    VerificationFailed,
+    Cancelled,
+    TimedOut,
+    /// A file that cannot be read or written.
+    Io,
+    /// A circuit, `k` or instance encoding the key usage policy does not allow.
+    PolicyViolation,
+    /// Proof bytes of the wrong length for the keys, or with bytes left over
+    /// after the transcript.
+    MalformedProof,
+    /// A witness without a valid manifest from an authorized generator.
+    UnauthorizedWitness,
+    Internal,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 14] = [
+        ErrorCode::Usage,
+        ErrorCode::InvalidInput,
+        ErrorCode::ShapeMismatch,
+        ErrorCode::CommitmentMismatch,
+        ErrorCode::KeyMismatch,
+        ErrorCode::Incompatible,
+        ErrorCode::VerificationFailed,
+        ErrorCode::Cancelled,
+        ErrorCode::TimedOut,
+        ErrorCode::Io,
+        ErrorCode::PolicyViolation,
+        ErrorCode::MalformedProof,
+        ErrorCode::UnauthorizedWitness,
+        ErrorCode::Internal,
+    ];
+
+    /// Parses a code such as `YF003`, ignoring case.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|candidate| candidate.code().eq_ignore_ascii_case(code.trim()))
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorCode::Usage => "YF001",
+            ErrorCode::InvalidInput => "YF002",
+            ErrorCode::ShapeMismatch => "YF003",
+            ErrorCode::CommitmentMismatch => "YF004",
+            ErrorCode::KeyMismatch => "YF005",
+            ErrorCode::Incompatible => "YF006",
+            ErrorCode::VerificationFailed => "YF007",
+            ErrorCode::Cancelled => "YF008",
+            ErrorCode::TimedOut => "YF009",
+            ErrorCode::Io => "YF010",
+            ErrorCode::PolicyViolation => "YF011",
+            ErrorCode::MalformedProof => "YF012",
+            ErrorCode::UnauthorizedWitness => "YF013",
+            ErrorCode::Internal => "YF999",
+        }
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            ErrorCode::Usage => "see --help for the required and conflicting flags",
+            ErrorCode::InvalidInput => {
+                "check the file is the JSON or hex the tool expects and was not truncated"
+            }
+            ErrorCode::ShapeMismatch => {
+                "prove with keys generated for this witness shape, or re-keygen from it"
+            }
+            ErrorCode::CommitmentMismatch => {
+                "regenerate the public inputs from the same witness, or drop the stale field"
+            }
+            ErrorCode::KeyMismatch => {
+                "use the proving and verification keys from one keygen, or regenerate both"
+            }
+            ErrorCode::Incompatible => {
+                "upgrade the verifier, or re-prove the block with a current prover"
+            }
+            ErrorCode::VerificationFailed => {
+                "check the public inputs and keys are the ones the proof was made with"
+            }
+            ErrorCode::Cancelled => "the job was cancelled; resubmit it to prove again",
+            ErrorCode::TimedOut => "raise --timeout, or prove on a less loaded machine",
+            ErrorCode::Io => "check the path exists and is readable and writable",
+            ErrorCode::PolicyViolation => {
+                "prove with a circuit the policy allows, or update the policy with the verifier"
+            }
+            ErrorCode::MalformedProof => {
+                "check the proof file was copied whole and belongs to these verification keys"
+            }
+            ErrorCode::UnauthorizedWitness => {
+                "have an authorized generator sign the witness, or add its key to the signers"
+            }
+            ErrorCode::Internal => "re-run with RUST_BACKTRACE=1 and report the output",
+        }
+    }
+
+    /// Anchor of the code's entry in the error reference.
+    pub fn docs_slug(self) -> &'static str {
+        match self {
+            ErrorCode::Usage => "errors/usage",
+            ErrorCode::InvalidInput => "errors/invalid-input",
+            ErrorCode::ShapeMismatch => "errors/shape-mismatch",
+            ErrorCode::CommitmentMismatch => "errors/commitment-mismatch",
+            ErrorCode::KeyMismatch => "errors/key-mismatch",
+            ErrorCode::Incompatible => "errors/incompatible",
+            ErrorCode::VerificationFailed => "errors/verification-failed",
+            ErrorCode::Cancelled => "errors/cancelled",
+            ErrorCode::TimedOut => "errors/timed-out",
+            ErrorCode::Io => "errors/io",
+            ErrorCode::PolicyViolation => "errors/policy-violation",
+            ErrorCode::MalformedProof => "errors/malformed-proof",
+            ErrorCode::UnauthorizedWitness => "errors/unauthorized-witness",
+            ErrorCode::Internal => "errors/internal",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+/// An error tagged with its catalogue code:
+/// `anyhow::bail!(Coded::new(ErrorCode::Usage, "pass --dim or --preset"))`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coded {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl Coded {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Coded {}
+
+/// The catalogue code for `err`, from the first recognised error in its chain.
+pub fn classify(err: &anyhow::Error) -> ErrorCode {
+    for cause in err.chain() {
+        if let Some(coded) = cause.downcast_ref::<Coded>() {
+            return coded.code;
+        }
+        if cause.is::<ShapeMismatch>() {
+            return ErrorCode::ShapeMismatch;
+        }
+        if cause.is::<Incompatibility>() {
+            return ErrorCode::Incompatible;
+        }
+        if let Some(cancelled) = cause.downcast_ref::<Cancelled>() {
+            return match cancelled.reason {
+                CancelReason::Cancelled => ErrorCode::Cancelled,
+                CancelReason::TimedOut => ErrorCode::TimedOut,
+            };
+        }
+        if cause.is::<serde_json::Error>() || cause.is::<hex::FromHexError>() {
+            return ErrorCode::InvalidInput;
+        }
+        if cause.is::<std::io::Error>() {
+            return ErrorCode::Io;
+        }
+    }
+    ErrorCode::Internal
+}
+
+/// An error as reported to users: code, full message, hint and docs slug.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorReport {
+    pub code: ErrorCode,
+    pub message: String,
+    pub hint: &'static str,
+    pub docs: &'static str,
+}
+
+impl ErrorReport {
+    pub fn new(err: &anyhow::Error) -> Self {
+        Self::with_code(classify(err), format!("{err:#}"))
+    }
+
+    pub fn with_code(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            hint: code.hint(),
+            docs: code.docs_slug(),
+        }
+    }
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error[{}]: {}\n  hint: {}\n  docs: {}",
+            self.code, self.message, self.hint, self.docs
+        )
+    }
+}
+
+/// Entry point wrapper for the binaries: prints the report for a failed
+/// `run` to stderr and exits with status 1.
+pub fn exit_on_error(result: anyhow::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("{}", ErrorReport::new(&err));
+        std::process::exit(1);
+    }
+}
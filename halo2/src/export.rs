@@ -0,0 +1,199 @@
+//! Re-encoding of block public inputs for verifiers on other fields.
+//!
+//! Two encodings are supported:
+//!
+//! * `hashed`: every public-input slot is mapped to one target-field element as
+//!   `blake3_xof("yysfold-export-v1" || target || slot || source)[..64] mod p`,
+//!   interpreting the 64 XOF bytes big-endian. `source` is the hex-decoded
+//!   public-input string (or the 8-byte big-endian block height), so any chain
+//!   can recompute the value from the same public-inputs document.
+//! * `limbs`: the BN254 instance values are split losslessly into big-endian
+//!   limbs that fit the target field (2 x 128-bit for BLS12-381 Fr, 8 x 32-bit
+//!   for Goldilocks, 1 x 256-bit for BN254 itself).
+//!
+//! All values are emitted as 0x-prefixed, 32-byte big-endian hex.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    bytes::{reduce_be, Hash256},
+    public_inputs::ParsedPublicInputs,
+};
+
+const DOMAIN: &str = "yysfold-export-v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportTarget {
+    Bn254,
+    Bls12_381,
+    Goldilocks,
+}
+
+impl ExportTarget {
+    /// Field modulus as little-endian 64-bit limbs.
+    fn modulus(self) -> [u64; 4] {
+        match self {
+            ExportTarget::Bn254 => [
+                0x43e1f593f0000001,
+                0x2833e84879b97091,
+                0xb85045b68181585d,
+                0x30644e72e131a029,
+            ],
+            ExportTarget::Bls12_381 => [
+                0xffffffff00000001,
+                0x53bda402fffe5bfe,
+                0x3339d80809a1d805,
+                0x73eda753299d7d48,
+            ],
+            ExportTarget::Goldilocks => [0xffffffff00000001, 0, 0, 0],
+        }
+    }
+
+    fn limb_bits(self) -> usize {
+        match self {
+            ExportTarget::Bn254 => 256,
+            ExportTarget::Bls12_381 => 128,
+            ExportTarget::Goldilocks => 32,
+        }
+    }
+}
+
+impl fmt::Display for ExportTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportTarget::Bn254 => "bn254",
+            ExportTarget::Bls12_381 => "bls12-381",
+            ExportTarget::Goldilocks => "goldilocks",
+        })
+    }
+}
+
+impl FromStr for ExportTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "bn254" | "bn256" => Ok(ExportTarget::Bn254),
+            "bls12-381" | "bls12_381" => Ok(ExportTarget::Bls12_381),
+            "goldilocks" => Ok(ExportTarget::Goldilocks),
+            other => anyhow::bail!("unknown export target {other}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportEncoding {
+    Hashed,
+    Limbs,
+}
+
+impl FromStr for ExportEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "hashed" => Ok(ExportEncoding::Hashed),
+            "limbs" => Ok(ExportEncoding::Limbs),
+            other => anyhow::bail!("unknown export encoding {other}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedSlot {
+    pub name: String,
+    pub source: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedInstances {
+    pub target: ExportTarget,
+    pub encoding: ExportEncoding,
+    pub domain: &'static str,
+    pub slots: Vec<ExportedSlot>,
+}
+
+pub fn export_instances(
+    public_inputs: &ParsedPublicInputs,
+    target: ExportTarget,
+    encoding: ExportEncoding,
+) -> Result<ExportedInstances> {
+    let slots = match encoding {
+        ExportEncoding::Hashed => hashed_slots(public_inputs, target)?,
+        ExportEncoding::Limbs => limb_slots(public_inputs, target)?,
+    };
+    Ok(ExportedInstances {
+        target,
+        encoding,
+        domain: DOMAIN,
+        slots,
+    })
+}
+
+fn hashed_slots(
+    public_inputs: &ParsedPublicInputs,
+    target: ExportTarget,
+) -> Result<Vec<ExportedSlot>> {
+    let hash = |value: &Hash256| (value.to_hex(), value.as_bytes().to_vec());
+    let height = public_inputs.block_height.to_be_bytes();
+    let sources = [
+        ("prevStateRoot", hash(&public_inputs.prev_state_root)),
+        ("newStateRoot", hash(&public_inputs.new_state_root)),
+        (
+            "blockHeight",
+            (format!("0x{}", hex::encode(height)), height.to_vec()),
+        ),
+        ("txMerkleRoot", hash(&public_inputs.tx_merkle_root)),
+        ("foldedCommitment", hash(&public_inputs.folded_commitment)),
+        ("pqCommitment", hash(&public_inputs.pq_commitment)),
+        ("codebookRoot", hash(&public_inputs.codebook_root)),
+    ];
+    Ok(sources
+        .into_iter()
+        .map(|(name, (source, bytes))| {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(DOMAIN.as_bytes());
+            hasher.update(target.to_string().as_bytes());
+            hasher.update(name.as_bytes());
+            hasher.update(&bytes);
+            let mut wide = [0u8; 64];
+            hasher.finalize_xof().fill(&mut wide);
+            let value = reduce_be(&wide, &target.modulus());
+            ExportedSlot {
+                name: name.to_string(),
+                source,
+                values: vec![Hash256::from_u64_limbs(&value).to_hex()],
+            }
+        })
+        .collect())
+}
+
+fn limb_slots(
+    public_inputs: &ParsedPublicInputs,
+    target: ExportTarget,
+) -> Result<Vec<ExportedSlot>> {
+    let names = ["foldedCommitment", "pqCommitment", "codebookRoot"];
+    let instances = public_inputs.to_field_elements()?;
+    Ok(names
+        .iter()
+        .zip(instances.iter())
+        .map(|(name, value)| {
+            let value = Hash256::from_field(value);
+            ExportedSlot {
+                name: name.to_string(),
+                source: value.to_hex(),
+                values: value
+                    .limbs(target.limb_bits())
+                    .iter()
+                    .map(Hash256::to_hex)
+                    .collect(),
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,293 @@
+//! Fixed-point encoding of `f64` witness values.
+//!
+//! A value `x` enters the circuit as the field element `q / S`, with the
+//! integer `q = floor(x * S)` and scale `S`. `x` is the shortest decimal the
+//! `f64` prints as, so `0.000249` at scale `10^6` is `249` even though the
+//! nearest `f64` is slightly smaller. [`FixedPoint::to_fixed`] rejects
+//! non-finite values and any `q` outside the signed [`COMPONENT_BITS`]-bit
+//! range instead of saturating, and the circuit checks the same range for
+//! every laid-out vector component with 8-bit limb lookups (see
+//! [`crate::gadgets::component_range`]):
+//!
+//! ```text
+//! q * S^-1 * S + 2^(COMPONENT_BITS - 1) = sum_i limb_i * 2^(8 i),  limb_i in [0, 256)
+//! ```
+//!
+//! Components in range keep every difference below `2^COMPONENT_BITS`, so
+//! a vector of up to `2^32` components has a squared residual below
+//! `2^128` that the field holds exactly (see [`crate::ann::DISTANCE_BITS`]).
+
+use std::{cmp::Ordering, sync::OnceLock};
+
+use anyhow::{Context, Result};
+use halo2curves::{
+    bn256::Fr,
+    ff::{Field, PrimeField},
+};
+
+use crate::{
+    ann::DISTANCE_BITS,
+    errors::{Coded, ErrorCode},
+};
+
+/// Scale of every conversion unless configured otherwise: six decimal digits.
+pub const DEFAULT_SCALE: u64 = 1_000_000;
+
+/// Largest scale whose square still leaves room for residuals in the
+/// [`crate::ann::DISTANCE_BITS`]-bit checks.
+pub const MAX_SCALE: u64 = 1 << 32;
+
+/// Width of a fixed-point component, sign included.
+pub const COMPONENT_BITS: usize = 48;
+
+/// Width of a limb looked up in the component range table.
+pub const LIMB_BITS: usize = 8;
+
+/// Limbs per component.
+pub const COMPONENT_LIMBS: usize = COMPONENT_BITS / LIMB_BITS;
+
+/// Rows of the component range table.
+pub const TABLE_ROWS: usize = 1 << LIMB_BITS;
+
+/// Products from here on are integers in `f64` and need no correction.
+const EXACT_LIMIT: f64 = (1u64 << 53) as f64;
+
+/// `f64` to field conversion at one scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedPoint {
+    scale: u64,
+    inverse: Fr,
+}
+
+impl Default for FixedPoint {
+    fn default() -> Self {
+        static DEFAULT: OnceLock<FixedPoint> = OnceLock::new();
+        *DEFAULT.get_or_init(|| Self::new(DEFAULT_SCALE).expect("default scale is valid"))
+    }
+}
+
+impl FixedPoint {
+    pub fn new(scale: u64) -> Result<Self> {
+        if scale == 0 || scale > MAX_SCALE {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!("fixed-point scale must be between 1 and {MAX_SCALE}, got {scale}")
+            ));
+        }
+        let inverse = Fr::from(scale)
+            .invert()
+            .expect("nonzero scale must have an inverse");
+        Ok(Self { scale, inverse })
+    }
+
+    pub fn scale(&self) -> u64 {
+        self.scale
+    }
+
+    /// `S^-1` in the field.
+    pub fn inverse(&self) -> Fr {
+        self.inverse
+    }
+
+    /// Largest magnitude a component can encode.
+    pub fn max_value(&self) -> f64 {
+        (1u64 << (COMPONENT_BITS - 1)) as f64 / self.scale as f64
+    }
+
+    /// `floor(value * S)`, rejecting non-finite values and overflow.
+    pub fn to_fixed(&self, value: f64) -> Result<i64> {
+        if !value.is_finite() {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!("{value} is not a finite number")
+            ));
+        }
+        let fixed = self.floor_scaled(value);
+        let limit = (1u64 << (COMPONENT_BITS - 1)) as f64;
+        if fixed < -limit || fixed >= limit {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!(
+                    "{value} overflows the {COMPONENT_BITS}-bit fixed-point range at scale {}; \
+                     components must stay below {} in magnitude",
+                    self.scale,
+                    self.max_value()
+                )
+            ));
+        }
+        Ok(fixed as i64)
+    }
+
+    /// `floor(value * S) / S` in the field.
+    pub fn to_field(&self, value: f64) -> Result<Fr> {
+        Ok(from_i64(self.to_fixed(value)?) * self.inverse)
+    }
+
+    /// [`Self::to_field`] over every row, naming the first value that fails.
+    pub fn to_field_matrix(&self, rows: &[Vec<f64>]) -> Result<Vec<Vec<Fr>>> {
+        rows.iter()
+            .enumerate()
+            .map(|(row, values)| {
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(column, value)| {
+                        self.to_field(*value)
+                            .with_context(|| format!("vector {row} component {column}"))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// `floor(value * S)` saturated to `i64`, as an `as` cast does. Out of
+    /// range values pass unnoticed; [`Self::to_fixed`] rejects them.
+    pub fn saturating_fixed(&self, value: f64) -> i64 {
+        self.floor_scaled(value) as i64
+    }
+
+    /// `floor(value * S)` of the decimal `value` prints as. The `f64`
+    /// product is within a few ulps of it, so only a product that close to
+    /// an integer can floor the other way; those are settled exactly.
+    fn floor_scaled(&self, value: f64) -> f64 {
+        let scaled = value * self.scale as f64;
+        let nearest = scaled.round();
+        if !(scaled.abs() < EXACT_LIMIT)
+            || (scaled - nearest).abs() > scaled.abs() * 4.0 * f64::EPSILON
+        {
+            return scaled.floor();
+        }
+        let order = cmp_decimal_scaled(value.abs(), self.scale, nearest.abs() as u64);
+        let below = if value.is_sign_negative() {
+            order == Ordering::Greater
+        } else {
+            order == Ordering::Less
+        };
+        if below {
+            nearest - 1.0
+        } else {
+            nearest
+        }
+    }
+
+    /// [`Self::saturating_fixed`] divided by `S` in the field.
+    pub fn saturating_field(&self, value: f64) -> Fr {
+        from_i64(self.saturating_fixed(value)) * self.inverse
+    }
+
+    /// `floor(threshold * S)^2`, the bound the circuit checks against.
+    pub fn squared_threshold(&self, threshold: f64) -> Result<u128> {
+        if !threshold.is_finite() || threshold < 0.0 {
+            anyhow::bail!("threshold must be a non-negative number, got {threshold}");
+        }
+        let scaled = self.floor_scaled(threshold);
+        if scaled >= u64::MAX as f64 {
+            anyhow::bail!(
+                "threshold {threshold} does not fit the {DISTANCE_BITS}-bit distance check"
+            );
+        }
+        let scaled = scaled as u128;
+        Ok(scaled * scaled)
+    }
+
+    /// Squared distance in fixed-point units, as the circuit computes it.
+    pub fn squared_distance(&self, a: &[f64], b: &[f64]) -> u128 {
+        a.iter()
+            .zip(b.iter())
+            .map(|(x, y)| {
+                let x = self.saturating_fixed(*x) as i128;
+                let y = self.saturating_fixed(*y) as i128;
+                let diff = x.abs_diff(y);
+                diff.saturating_mul(diff)
+            })
+            .fold(0u128, u128::saturating_add)
+    }
+
+    /// Squared residual of every row, as a witness generator reports them
+    /// in [`crate::io::WitnessData::residuals`].
+    pub fn residuals(&self, folded: &[Vec<f64>], pq: &[Vec<f64>]) -> Vec<u128> {
+        folded
+            .iter()
+            .zip(pq)
+            .map(|(a, b)| self.squared_distance(a, b))
+            .collect()
+    }
+
+    /// [`Self::squared_distance`] of two rows already converted with
+    /// [`Self::to_field`]. Their components are in range, so the sum is
+    /// below `2^128` and read back exactly.
+    pub fn field_squared_distance(&self, a: &[Fr], b: &[Fr]) -> u128 {
+        let scale = Fr::from(self.scale);
+        let sum: Fr = a
+            .iter()
+            .zip(b)
+            .map(|(x, y)| {
+                let diff = (*x - *y) * scale;
+                diff * diff
+            })
+            .sum();
+        let repr = sum.to_repr();
+        if repr[16..].iter().any(|byte| *byte != 0) {
+            return u128::MAX;
+        }
+        u128::from_le_bytes(repr[..16].try_into().expect("16 bytes"))
+    }
+
+    /// [`Self::residuals`] of rows already converted with [`Self::to_field`].
+    pub fn field_residuals(&self, folded: &[Vec<Fr>], pq: &[Vec<Fr>]) -> Vec<u128> {
+        folded
+            .iter()
+            .zip(pq)
+            .map(|(a, b)| self.field_squared_distance(a, b))
+            .collect()
+    }
+}
+
+/// The conversion to prove a witness with: `requested` (a `--scale` flag),
+/// else the witness's own `scale`, else [`DEFAULT_SCALE`]. A requested scale
+/// the witness contradicts is rejected, since its residuals are in the
+/// witness's units.
+pub fn resolve_scale(requested: Option<u64>, witness: Option<u64>) -> Result<FixedPoint> {
+    match (requested, witness) {
+        (Some(requested), Some(witness)) if requested != witness => anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            format!("--scale {requested} contradicts the witness's scale {witness}")
+        )),
+        (requested, witness) => FixedPoint::new(requested.or(witness).unwrap_or(DEFAULT_SCALE)),
+    }
+}
+
+/// Compares the decimal `magnitude` prints as, times `scale`, with `target`
+/// in integers: the shortest decimal has at most 17 digits, so the product
+/// stays below `2^89`.
+fn cmp_decimal_scaled(magnitude: f64, scale: u64, target: u64) -> Ordering {
+    let printed = format!("{magnitude:e}");
+    let (mantissa, exponent) = printed.split_once('e').expect("exponent notation");
+    let exponent: i32 = exponent.parse().expect("integer exponent");
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits: u128 = format!("{whole}{fraction}")
+        .parse()
+        .expect("at most 17 digits");
+    let scaled = digits * u128::from(scale);
+    // magnitude = digits * 10^shift
+    let shift = exponent - fraction.len() as i32;
+    let power = 10u128.checked_pow(shift.unsigned_abs());
+    if shift >= 0 {
+        power
+            .and_then(|power| scaled.checked_mul(power))
+            .map_or(Ordering::Greater, |lhs| lhs.cmp(&u128::from(target)))
+    } else {
+        power
+            .and_then(|power| u128::from(target).checked_mul(power))
+            .map_or(Ordering::Less, |rhs| scaled.cmp(&rhs))
+    }
+}
+
+/// A signed integer in the field, negatives as `p - |value|`.
+pub fn from_i64(value: i64) -> Fr {
+    if value >= 0 {
+        Fr::from(value as u64)
+    } else {
+        -Fr::from(value.unsigned_abs())
+    }
+}
@@ -0,0 +1,135 @@
+//! Range checks on fixed-point vector components by limb lookups.
+//!
+//! ```text
+//! row j       value | limb_0 | .. | limb_5      s_range: value * S + 2^47 = sum_i limb_i * 2^(8 i)
+//! ```
+//!
+//! Every limb is looked up in a fixed table of `0..256`, so a component
+//! costs one row and the table 256. See [`crate::fixed_point`].
+
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+use halo2curves::{bn256::Fr, ff::PrimeField};
+
+use crate::{
+    fixed_point::{COMPONENT_BITS, COMPONENT_LIMBS, LIMB_BITS, TABLE_ROWS},
+    gadgets::poseidon::AssignedValue,
+};
+
+#[derive(Clone, Debug)]
+pub struct ComponentRangeConfig {
+    value: Column<Advice>,
+    limbs: [Column<Advice>; COMPONENT_LIMBS],
+    table: Column<Fixed>,
+    s_range: Selector,
+    s_table: Selector,
+    scale: Fr,
+}
+
+#[derive(Clone, Debug)]
+pub struct ComponentRangeChip {
+    config: ComponentRangeConfig,
+}
+
+impl ComponentRangeChip {
+    pub fn construct(config: ComponentRangeConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configures the check for components encoded at `scale`.
+    pub fn configure(meta: &mut ConstraintSystem<Fr>, scale: u64) -> ComponentRangeConfig {
+        let value = meta.advice_column();
+        meta.enable_equality(value);
+        let limbs = [(); COMPONENT_LIMBS].map(|_| meta.advice_column());
+        let table = meta.fixed_column();
+        let s_range = meta.complex_selector();
+        let s_table = meta.complex_selector();
+        let scale = Fr::from(scale);
+
+        meta.create_gate("component_range", |meta| {
+            let s = meta.query_selector(s_range);
+            let value = meta.query_advice(value, Rotation::cur());
+            let recomposed =
+                limbs
+                    .iter()
+                    .rev()
+                    .fold(Expression::Constant(Fr::zero()), |acc, limb| {
+                        acc * Expression::Constant(Fr::from(1u64 << LIMB_BITS))
+                            + meta.query_advice(*limb, Rotation::cur())
+                    });
+            vec![
+                s * (value * Expression::Constant(scale) + Expression::Constant(shift())
+                    - recomposed),
+            ]
+        });
+
+        for limb in limbs {
+            meta.lookup_any("component_limb", |meta| {
+                let s_in = meta.query_selector(s_range);
+                let s_tab = meta.query_selector(s_table);
+                let input = meta.query_advice(limb, Rotation::cur());
+                let entry = meta.query_fixed(table, Rotation::cur());
+                vec![(s_in * input, s_tab * entry)]
+            });
+        }
+
+        ComponentRangeConfig {
+            value,
+            limbs,
+            table,
+            s_range,
+            s_table,
+            scale,
+        }
+    }
+
+    /// Assigns the table of limb values.
+    pub fn assign_table(&self, layouter: &mut impl Layouter<Fr>) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "component_range_table",
+            |mut region: Region<'_, Fr>| {
+                for row in 0..TABLE_ROWS {
+                    config.s_table.enable(&mut region, row)?;
+                    region.assign_fixed(config.table, row, Fr::from(row as u64));
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Constrains every cell of `components` to encode a fixed-point value
+    /// in the signed `COMPONENT_BITS`-bit range.
+    pub fn check_row(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        row_idx: usize,
+        components: &[AssignedValue],
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || format!("component_range_{row_idx}"),
+            |mut region: Region<'_, Fr>| {
+                for (offset, (cell, value)) in components.iter().enumerate() {
+                    config.s_range.enable(&mut region, offset)?;
+                    let copied = region.assign_advice(config.value, offset, Value::known(*value));
+                    region.constrain_equal(copied.cell(), *cell);
+                    let shifted = *value * config.scale + shift();
+                    let repr = shifted.to_repr();
+                    for (limb, byte) in config.limbs.iter().zip(repr.as_ref()) {
+                        region.assign_advice(*limb, offset, Value::known(Fr::from(*byte as u64)));
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// `2^(COMPONENT_BITS - 1)`, shifting signed components to unsigned.
+fn shift() -> Fr {
+    Fr::from(1u64 << (COMPONENT_BITS - 1))
+}
@@ -0,0 +1,129 @@
+//! Squared Euclidean distance between two assigned vectors.
+//!
+//! Layout per distance region:
+//!
+//! ```text
+//! row j       a_j | b_j | acc_j      s_step: acc_{j+1} = acc_j + (a_j - b_j)^2
+//! row n       -   | -   | acc_n      distance
+//! ```
+//!
+//! `acc_0` is pinned to zero, and `a`/`b` are copy-constrained to the caller's
+//! cells, so the returned cell is exactly the squared distance in the field.
+
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+    poly::Rotation,
+};
+use halo2curves::bn256::Fr;
+
+use crate::gadgets::poseidon::AssignedValue;
+
+#[derive(Clone, Debug)]
+pub struct DistanceConfig {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    acc: Column<Advice>,
+    s_zero: Selector,
+    s_step: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct DistanceChip {
+    config: DistanceConfig,
+}
+
+impl DistanceChip {
+    pub fn construct(config: DistanceConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> DistanceConfig {
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let acc = meta.advice_column();
+        for column in [a, b, acc] {
+            meta.enable_equality(column);
+        }
+        let s_zero = meta.selector();
+        let s_step = meta.selector();
+
+        meta.create_gate("distance_zero", |meta| {
+            let s = meta.query_selector(s_zero);
+            vec![s * meta.query_advice(acc, Rotation::cur())]
+        });
+
+        meta.create_gate("distance_step", |meta| {
+            let s = meta.query_selector(s_step);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let cur = meta.query_advice(acc, Rotation::cur());
+            let next = meta.query_advice(acc, Rotation::next());
+            let diff = a - b;
+            vec![s * (next - cur - diff.clone() * diff)]
+        });
+
+        DistanceConfig {
+            a,
+            b,
+            acc,
+            s_zero,
+            s_step,
+        }
+    }
+
+    /// Assigns a free vector so later regions can copy it.
+    pub fn assign_vector(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        name: &str,
+        values: &[Fr],
+    ) -> Result<Vec<AssignedValue>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || name.to_string(),
+            |mut region: Region<'_, Fr>| {
+                let mut cells = Vec::with_capacity(values.len());
+                for (row, value) in values.iter().enumerate() {
+                    let cell = region.assign_advice(config.a, row, Value::known(*value));
+                    cells.push((cell.cell(), *value));
+                }
+                Ok(cells)
+            },
+        )
+    }
+
+    /// Returns `sum_j (a_j - b_j)^2` as an assigned cell.
+    pub fn squared_distance(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        a: &[AssignedValue],
+        b: &[AssignedValue],
+    ) -> Result<AssignedValue, Error> {
+        if a.len() != b.len() || a.is_empty() {
+            return Err(Error::Synthesis);
+        }
+        let config = &self.config;
+        layouter.assign_region(
+            || "squared_distance",
+            |mut region: Region<'_, Fr>| {
+                let mut acc = Fr::zero();
+                config.s_zero.enable(&mut region, 0)?;
+                let mut last = region.assign_advice(config.acc, 0, Value::known(acc));
+                for (row, ((a_cell, a_value), (b_cell, b_value))) in
+                    a.iter().zip(b.iter()).enumerate()
+                {
+                    config.s_step.enable(&mut region, row)?;
+                    let assigned = region.assign_advice(config.a, row, Value::known(*a_value));
+                    region.constrain_equal(assigned.cell(), *a_cell);
+                    let assigned = region.assign_advice(config.b, row, Value::known(*b_value));
+                    region.constrain_equal(assigned.cell(), *b_cell);
+                    let diff = *a_value - *b_value;
+                    acc += diff.square();
+                    last = region.assign_advice(config.acc, row + 1, Value::known(acc));
+                }
+                Ok((last.cell(), acc))
+            },
+        )
+    }
+}
@@ -0,0 +1,201 @@
+//! In-circuit Merkle leaf updates over a perfect Poseidon tree.
+//!
+//! An update replaces one leaf: the old leaf and its path must hash to the
+//! current root, and the new leaf with the same path gives the next root.
+//! Trees are perfect (power-of-two leaves), so every path has one sibling per
+//! level and the direction bits alone choose the leaf. Layout, five advice
+//! columns:
+//!
+//! ```text
+//! swap     bit     | node     | sibling | left     | right     bit boolean, (left, right) = bit ? (sibling, node) : (node, sibling)
+//! select   enabled | old_root | root    | new_root | next      enabled boolean, enabled => old_root = root,
+//!                                                              next = enabled ? new_root : root
+//! ```
+//!
+//! A disabled update leaves the root unchanged, so circuits keyed for up to
+//! `n` updates pad with disabled ones.
+
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+use halo2curves::bn256::Fr;
+
+use crate::gadgets::poseidon::{AssignedValue, PoseidonChip};
+
+/// Witness for one leaf update: the replaced leaf and its path, leaf level
+/// first, as `(sibling, node_is_right)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MerkleUpdate {
+    pub enabled: bool,
+    pub old_leaf: Fr,
+    pub path: Vec<(Fr, bool)>,
+}
+
+impl MerkleUpdate {
+    /// A disabled update with a zero path of `depth` levels.
+    pub fn disabled(depth: usize) -> Self {
+        Self {
+            enabled: false,
+            old_leaf: Fr::zero(),
+            path: vec![(Fr::zero(), false); depth],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleUpdateConfig {
+    columns: [Column<Advice>; 5],
+    s_swap: Selector,
+    s_select: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleUpdateChip {
+    config: MerkleUpdateConfig,
+}
+
+impl MerkleUpdateChip {
+    pub fn construct(config: MerkleUpdateConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> MerkleUpdateConfig {
+        let columns = [(); 5].map(|_| meta.advice_column());
+        for column in columns {
+            meta.enable_equality(column);
+        }
+        let s_swap = meta.selector();
+        let s_select = meta.selector();
+        let one = || Expression::Constant(Fr::one());
+
+        meta.create_gate("merkle_swap", |meta| {
+            let s = meta.query_selector(s_swap);
+            let [bit, node, sibling, left, right] =
+                columns.map(|column| meta.query_advice(column, Rotation::cur()));
+            vec![
+                s.clone() * bit.clone() * (one() - bit.clone()),
+                s.clone() * (left - node.clone() - bit.clone() * (sibling.clone() - node.clone())),
+                s * (right - sibling.clone() - bit * (node - sibling)),
+            ]
+        });
+
+        meta.create_gate("merkle_select", |meta| {
+            let s = meta.query_selector(s_select);
+            let [enabled, old_root, root, new_root, next] =
+                columns.map(|column| meta.query_advice(column, Rotation::cur()));
+            vec![
+                s.clone() * enabled.clone() * (one() - enabled.clone()),
+                s.clone() * enabled.clone() * (old_root - root.clone()),
+                s * (next - root.clone() - enabled * (new_root - root)),
+            ]
+        });
+
+        MerkleUpdateConfig {
+            columns,
+            s_swap,
+            s_select,
+        }
+    }
+
+    /// Applies `update` to the tree with `root`, writing `new_leaf`, and
+    /// returns the next root.
+    pub fn update(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        poseidon: &PoseidonChip,
+        root: AssignedValue,
+        new_leaf: AssignedValue,
+        update: &MerkleUpdate,
+    ) -> Result<AssignedValue, Error> {
+        let columns = self.config.columns;
+        let (old_leaf, path) = layouter.assign_region(
+            || "merkle_path",
+            |mut region: Region<'_, Fr>| {
+                let old_leaf = assign(&mut region, columns[0], 0, update.old_leaf);
+                let path = update
+                    .path
+                    .iter()
+                    .enumerate()
+                    .map(|(row, (sibling, is_right))| {
+                        let bit = assign(&mut region, columns[1], row, Fr::from(*is_right as u64));
+                        let sibling = assign(&mut region, columns[2], row, *sibling);
+                        (bit, sibling)
+                    })
+                    .collect::<Vec<_>>();
+                Ok((old_leaf, path))
+            },
+        )?;
+        let old_root = self.climb(layouter, poseidon, old_leaf, &path)?;
+        let new_root = self.climb(layouter, poseidon, new_leaf, &path)?;
+        layouter.assign_region(
+            || "merkle_select",
+            |mut region: Region<'_, Fr>| {
+                self.config.s_select.enable(&mut region, 0)?;
+                let enabled = Fr::from(update.enabled as u64);
+                assign(&mut region, columns[0], 0, enabled);
+                copy(&mut region, columns[1], old_root)?;
+                copy(&mut region, columns[2], root)?;
+                copy(&mut region, columns[3], new_root)?;
+                let next = if update.enabled { new_root.1 } else { root.1 };
+                Ok(assign(&mut region, columns[4], 0, next))
+            },
+        )
+    }
+
+    /// Hashes `leaf` up `path` of `(bit, sibling)` cells to its root.
+    fn climb(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        poseidon: &PoseidonChip,
+        leaf: AssignedValue,
+        path: &[(AssignedValue, AssignedValue)],
+    ) -> Result<AssignedValue, Error> {
+        let columns = self.config.columns;
+        let mut node = leaf;
+        for (bit, sibling) in path {
+            let (left, right) = layouter.assign_region(
+                || "merkle_swap",
+                |mut region: Region<'_, Fr>| {
+                    self.config.s_swap.enable(&mut region, 0)?;
+                    copy(&mut region, columns[0], *bit)?;
+                    copy(&mut region, columns[1], node)?;
+                    copy(&mut region, columns[2], *sibling)?;
+                    let (left, right) = if bit.1 == Fr::one() {
+                        (sibling.1, node.1)
+                    } else {
+                        (node.1, sibling.1)
+                    };
+                    Ok((
+                        assign(&mut region, columns[3], 0, left),
+                        assign(&mut region, columns[4], 0, right),
+                    ))
+                },
+            )?;
+            node = poseidon.hash_node(layouter, left, right)?;
+        }
+        Ok(node)
+    }
+}
+
+fn assign(
+    region: &mut Region<'_, Fr>,
+    column: Column<Advice>,
+    row: usize,
+    value: Fr,
+) -> AssignedValue {
+    let cell = region.assign_advice(column, row, Value::known(value));
+    (cell.cell(), value)
+}
+
+/// Copies `value` into row 0 of `column`.
+fn copy(
+    region: &mut Region<'_, Fr>,
+    column: Column<Advice>,
+    value: AssignedValue,
+) -> Result<(), Error> {
+    let (cell, _) = assign(region, column, 0, value.1);
+    region.constrain_equal(cell, value.0);
+    Ok(())
+}
@@ -0,0 +1,10 @@
+pub mod component_range;
+pub mod distance;
+pub mod merkle;
+pub mod ordering;
+pub mod poseidon;
+pub mod pq;
+pub mod range;
+pub mod scalar;
+pub mod sha256;
+pub mod shuffle;
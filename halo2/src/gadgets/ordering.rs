@@ -0,0 +1,167 @@
+//! Ordering and permutation gadgets for top-k attestations.
+//!
+//! * [`OrderingChip::assert_sorted`] chains `value <= bound` range checks over
+//!   consecutive cells.
+//! * [`OrderingChip::assert_all_at_least`] bounds a set of cells from below by
+//!   one shared cell.
+//! * [`OrderingChip::assert_permutation`] proves two lists are the same
+//!   multiset with a grand product `prod (gamma - x_i)` on each side. `gamma`
+//!   must be derived from commitments to both lists (Fiat-Shamir), otherwise
+//!   the prover could pick it to make unequal products collide.
+//!
+//! Grand product layout:
+//!
+//! ```text
+//! row 0       -   | -     | 1           s_init
+//! row i       x_i | gamma | acc_i       s_step: acc_{i+1} = acc_i * (gamma - x_i)
+//! row n       -   | -     | acc_n       product
+//! ```
+
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+use halo2curves::bn256::Fr;
+
+use crate::gadgets::{
+    poseidon::AssignedValue,
+    range::{RangeCheckChip, RangeCheckConfig},
+};
+
+#[derive(Clone, Debug)]
+pub struct OrderingConfig {
+    value: Column<Advice>,
+    challenge: Column<Advice>,
+    acc: Column<Advice>,
+    s_init: Selector,
+    s_step: Selector,
+    range: RangeCheckConfig,
+}
+
+#[derive(Clone, Debug)]
+pub struct OrderingChip {
+    config: OrderingConfig,
+}
+
+impl OrderingChip {
+    pub fn construct(config: OrderingConfig) -> Self {
+        Self { config }
+    }
+
+    /// Reuses the caller's range-check columns for comparisons.
+    pub fn configure(meta: &mut ConstraintSystem<Fr>, range: RangeCheckConfig) -> OrderingConfig {
+        let value = meta.advice_column();
+        let challenge = meta.advice_column();
+        let acc = meta.advice_column();
+        for column in [value, challenge, acc] {
+            meta.enable_equality(column);
+        }
+        let s_init = meta.selector();
+        let s_step = meta.selector();
+
+        meta.create_gate("grand_product_init", |meta| {
+            let s = meta.query_selector(s_init);
+            let acc = meta.query_advice(acc, Rotation::cur());
+            vec![s * (acc - Expression::Constant(Fr::one()))]
+        });
+
+        meta.create_gate("grand_product_step", |meta| {
+            let s = meta.query_selector(s_step);
+            let value = meta.query_advice(value, Rotation::cur());
+            let gamma = meta.query_advice(challenge, Rotation::cur());
+            let cur = meta.query_advice(acc, Rotation::cur());
+            let next = meta.query_advice(acc, Rotation::next());
+            vec![s * (next - cur * (gamma - value))]
+        });
+
+        OrderingConfig {
+            value,
+            challenge,
+            acc,
+            s_init,
+            s_step,
+            range,
+        }
+    }
+
+    /// Constrains `values[0] <= values[1] <= .. <= values[n-1]`.
+    pub fn assert_sorted(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        values: &[AssignedValue],
+        bits: usize,
+    ) -> Result<(), Error> {
+        let range = RangeCheckChip::construct(self.config.range.clone());
+        for pair in values.windows(2) {
+            range.assert_less_or_equal(layouter, pair[0], pair[1], bits)?;
+        }
+        Ok(())
+    }
+
+    /// Constrains `bound <= value` for every value.
+    pub fn assert_all_at_least(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        values: &[AssignedValue],
+        bound: AssignedValue,
+        bits: usize,
+    ) -> Result<(), Error> {
+        let range = RangeCheckChip::construct(self.config.range.clone());
+        for value in values {
+            range.assert_less_or_equal(layouter, bound, *value, bits)?;
+        }
+        Ok(())
+    }
+
+    /// Constrains `left` and `right` to be permutations of each other.
+    pub fn assert_permutation(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        left: &[AssignedValue],
+        right: &[AssignedValue],
+        gamma: AssignedValue,
+    ) -> Result<(), Error> {
+        if left.len() != right.len() {
+            return Err(Error::Synthesis);
+        }
+        let left = self.grand_product(layouter, left, gamma)?;
+        let right = self.grand_product(layouter, right, gamma)?;
+        layouter.assign_region(
+            || "permutation_products",
+            |mut region: Region<'_, Fr>| {
+                region.constrain_equal(left.0, right.0);
+                Ok(())
+            },
+        )
+    }
+
+    /// `prod_i (gamma - values[i])`.
+    pub fn grand_product(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        values: &[AssignedValue],
+        gamma: AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "grand_product",
+            |mut region: Region<'_, Fr>| {
+                let mut acc = Fr::one();
+                config.s_init.enable(&mut region, 0)?;
+                let mut last = region.assign_advice(config.acc, 0, Value::known(acc));
+                for (row, (cell, value)) in values.iter().enumerate() {
+                    config.s_step.enable(&mut region, row)?;
+                    let assigned = region.assign_advice(config.value, row, Value::known(*value));
+                    region.constrain_equal(assigned.cell(), *cell);
+                    let assigned =
+                        region.assign_advice(config.challenge, row, Value::known(gamma.1));
+                    region.constrain_equal(assigned.cell(), gamma.0);
+                    acc *= gamma.1 - *value;
+                    last = region.assign_advice(config.acc, row + 1, Value::known(acc));
+                }
+                Ok((last.cell(), acc))
+            },
+        )
+    }
+}
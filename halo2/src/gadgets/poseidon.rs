@@ -0,0 +1,275 @@
+//! In-circuit Poseidon sponge and Merkle root over `poseidon::spec()`.
+//!
+//! Layout per hash region (three state columns, one row per round):
+//!
+//! ```text
+//! row 0       capacity | 0  | 0      s_init (capacity read from rc[0])
+//! row 1       -        | x0 | x1     inputs, copy-constrained to the caller's cells
+//! row 2       absorbed state         s_full / s_partial for each round
+//! ...
+//! row 2+65    permuted state         next absorb starts here
+//! ```
+//!
+//! Cells are passed around as `(Cell, Fr)` pairs so callers can chain hashes
+//! across regions with copy constraints.
+
+use halo2_proofs::{
+    circuit::{Cell, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+use halo2curves::bn256::Fr;
+
+use crate::poseidon::{
+    apply_round, is_full_round, leaf_capacity, spec, NODE_CAPACITY, RATE, TOTAL_ROUNDS, WIDTH,
+};
+
+pub type AssignedValue = (Cell, Fr);
+
+#[derive(Clone, Debug)]
+pub struct PoseidonConfig {
+    state: [Column<Advice>; WIDTH],
+    round_constants: [Column<Fixed>; WIDTH],
+    s_init: Selector,
+    s_absorb: Selector,
+    s_pad: Selector,
+    s_full: Selector,
+    s_partial: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct PoseidonChip {
+    config: PoseidonConfig,
+}
+
+impl PoseidonChip {
+    pub fn construct(config: PoseidonConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> PoseidonConfig {
+        let state = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let round_constants = [
+            meta.fixed_column(),
+            meta.fixed_column(),
+            meta.fixed_column(),
+        ];
+        for column in state {
+            meta.enable_equality(column);
+        }
+        let s_init = meta.selector();
+        let s_absorb = meta.selector();
+        let s_pad = meta.selector();
+        let s_full = meta.selector();
+        let s_partial = meta.selector();
+        let mds = spec().mds;
+
+        meta.create_gate("poseidon_init", |meta| {
+            let s = meta.query_selector(s_init);
+            let capacity = meta.query_fixed(round_constants[0], Rotation::cur());
+            let cur: Vec<_> = state
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect();
+            vec![
+                s.clone() * (cur[0].clone() - capacity),
+                s.clone() * cur[1].clone(),
+                s * cur[2].clone(),
+            ]
+        });
+
+        meta.create_gate("poseidon_absorb", |meta| {
+            let s = meta.query_selector(s_absorb);
+            (0..WIDTH)
+                .map(|idx| {
+                    let cur = meta.query_advice(state[idx], Rotation::cur());
+                    let next = meta.query_advice(state[idx], Rotation(2));
+                    if idx == 0 {
+                        s.clone() * (next - cur)
+                    } else {
+                        let input = meta.query_advice(state[idx], Rotation::next());
+                        s.clone() * (next - cur - input)
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+
+        meta.create_gate("poseidon_pad", |meta| {
+            let s = meta.query_selector(s_pad);
+            vec![s * meta.query_advice(state[2], Rotation::cur())]
+        });
+
+        for (name, selector, full) in [
+            ("poseidon_full_round", s_full, true),
+            ("poseidon_partial_round", s_partial, false),
+        ] {
+            meta.create_gate(name, |meta| {
+                let s = meta.query_selector(selector);
+                let sboxed: Vec<Expression<Fr>> = (0..WIDTH)
+                    .map(|idx| {
+                        let value = meta.query_advice(state[idx], Rotation::cur())
+                            + meta.query_fixed(round_constants[idx], Rotation::cur());
+                        if idx == 0 || full {
+                            pow5(value)
+                        } else {
+                            value
+                        }
+                    })
+                    .collect();
+                (0..WIDTH)
+                    .map(|row| {
+                        let next = meta.query_advice(state[row], Rotation::next());
+                        let mixed = sboxed.iter().enumerate().fold(
+                            Expression::Constant(Fr::zero()),
+                            |acc, (col, value)| {
+                                acc + value.clone() * Expression::Constant(mds[row][col])
+                            },
+                        );
+                        s.clone() * (next - mixed)
+                    })
+                    .collect::<Vec<_>>()
+            });
+        }
+
+        PoseidonConfig {
+            state,
+            round_constants,
+            s_init,
+            s_absorb,
+            s_pad,
+            s_full,
+            s_partial,
+        }
+    }
+
+    /// Sponge hash of `inputs` with the given capacity element, matching
+    /// `poseidon::hash_with_capacity`.
+    pub fn hash(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        capacity: Fr,
+        inputs: &[AssignedValue],
+    ) -> Result<AssignedValue, Error> {
+        if inputs.is_empty() {
+            return Err(Error::Synthesis);
+        }
+        let config = &self.config;
+        layouter.assign_region(
+            || "poseidon_hash",
+            |mut region: Region<'_, Fr>| {
+                let mut state = [capacity, Fr::zero(), Fr::zero()];
+                let mut row = 0;
+                config.s_init.enable(&mut region, row)?;
+                region.assign_fixed(config.round_constants[0], row, capacity);
+                let mut cells = self.assign_state(&mut region, row, &state);
+
+                for chunk in inputs.chunks(RATE) {
+                    config.s_absorb.enable(&mut region, row)?;
+                    region.assign_advice(config.state[0], row + 1, Value::known(Fr::zero()));
+                    for (idx, input) in chunk.iter().enumerate() {
+                        let (cell, value) = *input;
+                        let assigned = region.assign_advice(
+                            config.state[idx + 1],
+                            row + 1,
+                            Value::known(value),
+                        );
+                        region.constrain_equal(assigned.cell(), cell);
+                        state[idx + 1] += value;
+                    }
+                    if chunk.len() < RATE {
+                        region.assign_advice(config.state[2], row + 1, Value::known(Fr::zero()));
+                        config.s_pad.enable(&mut region, row + 1)?;
+                    }
+                    row += 2;
+                    self.assign_state(&mut region, row, &state);
+
+                    for round in 0..TOTAL_ROUNDS {
+                        if is_full_round(round) {
+                            config.s_full.enable(&mut region, row)?;
+                        } else {
+                            config.s_partial.enable(&mut region, row)?;
+                        }
+                        for (column, constant) in config
+                            .round_constants
+                            .iter()
+                            .zip(spec().round_constants[round].iter())
+                        {
+                            region.assign_fixed(*column, row, *constant);
+                        }
+                        apply_round(&mut state, round);
+                        row += 1;
+                        cells = self.assign_state(&mut region, row, &state);
+                    }
+                }
+                Ok((cells[1], state[1]))
+            },
+        )
+    }
+
+    pub fn hash_leaf(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        values: &[AssignedValue],
+    ) -> Result<AssignedValue, Error> {
+        self.hash(layouter, leaf_capacity(values.len()), values)
+    }
+
+    pub fn hash_node(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        left: AssignedValue,
+        right: AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        self.hash(layouter, Fr::from(NODE_CAPACITY), &[left, right])
+    }
+
+    /// Merkle root over already-hashed leaves, matching `merkle::root`.
+    pub fn merkle_root(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        leaves: Vec<AssignedValue>,
+    ) -> Result<AssignedValue, Error> {
+        let mut level = leaves;
+        if level.is_empty() {
+            return Err(Error::Synthesis);
+        }
+        while level.len() > 1 {
+            let mut next = Vec::with_capacity((level.len() + 1) / 2);
+            for pair in level.chunks(2) {
+                match pair {
+                    [left, right] => next.push(self.hash_node(layouter, *left, *right)?),
+                    [single] => next.push(*single),
+                    _ => unreachable!("chunks(2) yields one or two nodes"),
+                }
+            }
+            level = next;
+        }
+        Ok(level[0])
+    }
+
+    fn assign_state(
+        &self,
+        region: &mut Region<'_, Fr>,
+        row: usize,
+        state: &[Fr; WIDTH],
+    ) -> [Cell; WIDTH] {
+        let mut cells = Vec::with_capacity(WIDTH);
+        for (column, value) in self.config.state.iter().zip(state.iter()) {
+            cells.push(
+                region
+                    .assign_advice(*column, row, Value::known(*value))
+                    .cell(),
+            );
+        }
+        [cells[0], cells[1], cells[2]]
+    }
+}
+
+fn pow5(value: Expression<Fr>) -> Expression<Fr> {
+    let squared = value.clone() * value.clone();
+    squared.clone() * squared * value
+}
@@ -0,0 +1,175 @@
+//! Lookup binding PQ codes and reconstructed vectors to the codebook.
+//!
+//! Codebook rows `(subspace, centroid, component, value)` form a dynamic
+//! table; every pq-vector component is looked up as
+//! `(subspace, code, component, pq_value)`. Subspace and component indices
+//! are fixed columns, so the prover can only choose the code and values.
+//! A residual stage's codebook is appended to the table as further
+//! subspaces, and its rows are looked up from that offset.
+
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector},
+    poly::Rotation,
+};
+use halo2curves::bn256::Fr;
+
+use crate::gadgets::poseidon::AssignedValue;
+
+#[derive(Clone, Debug)]
+pub struct PqLookupConfig {
+    subspace: Column<Fixed>,
+    component: Column<Fixed>,
+    centroid: Column<Fixed>,
+    code: Column<Advice>,
+    value: Column<Advice>,
+    s_table: Selector,
+    s_input: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct PqLookupChip {
+    config: PqLookupConfig,
+}
+
+impl PqLookupChip {
+    pub fn construct(config: PqLookupConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> PqLookupConfig {
+        let subspace = meta.fixed_column();
+        let component = meta.fixed_column();
+        let centroid = meta.fixed_column();
+        let code = meta.advice_column();
+        let value = meta.advice_column();
+        meta.enable_equality(code);
+        meta.enable_equality(value);
+        let s_table = meta.complex_selector();
+        let s_input = meta.complex_selector();
+
+        meta.lookup_any("pq_codebook", |meta| {
+            let s_in = meta.query_selector(s_input);
+            let s_tab = meta.query_selector(s_table);
+            let subspace = meta.query_fixed(subspace, Rotation::cur());
+            let component = meta.query_fixed(component, Rotation::cur());
+            let centroid = meta.query_fixed(centroid, Rotation::cur());
+            let code = meta.query_advice(code, Rotation::cur());
+            let value = meta.query_advice(value, Rotation::cur());
+            vec![
+                (s_in.clone() * subspace.clone(), s_tab.clone() * subspace),
+                (s_in.clone() * code, s_tab.clone() * centroid),
+                (s_in.clone() * component.clone(), s_tab.clone() * component),
+                (s_in * value.clone(), s_tab * value),
+            ]
+        });
+
+        PqLookupConfig {
+            subspace,
+            component,
+            centroid,
+            code,
+            value,
+            s_table,
+            s_input,
+        }
+    }
+
+    /// Assigns the codebook table, returning the value cells as
+    /// `cells[subspace][centroid][component]`.
+    pub fn assign_codebook(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        codebook: &[Vec<Vec<Fr>>],
+    ) -> Result<Vec<Vec<Vec<AssignedValue>>>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "pq_codebook_table",
+            |mut region: Region<'_, Fr>| {
+                let mut row = 0;
+                let mut cells = Vec::with_capacity(codebook.len());
+                for (subspace, centroids) in codebook.iter().enumerate() {
+                    let mut subspace_cells = Vec::with_capacity(centroids.len());
+                    for (centroid, components) in centroids.iter().enumerate() {
+                        let mut centroid_cells = Vec::with_capacity(components.len());
+                        for (component, value) in components.iter().enumerate() {
+                            config.s_table.enable(&mut region, row)?;
+                            region.assign_fixed(config.subspace, row, Fr::from(subspace as u64));
+                            region.assign_fixed(config.centroid, row, Fr::from(centroid as u64));
+                            region.assign_fixed(config.component, row, Fr::from(component as u64));
+                            let cell =
+                                region.assign_advice(config.value, row, Value::known(*value));
+                            centroid_cells.push((cell.cell(), *value));
+                            row += 1;
+                        }
+                        subspace_cells.push(centroid_cells);
+                    }
+                    cells.push(subspace_cells);
+                }
+                Ok(cells)
+            },
+        )
+    }
+
+    /// Assigns one cell per code, returned as `cells[vector][subspace]`.
+    pub fn assign_codes(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        codes: &[Vec<Fr>],
+    ) -> Result<Vec<Vec<AssignedValue>>, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "pq_codes",
+            |mut region: Region<'_, Fr>| {
+                let mut row = 0;
+                let mut cells = Vec::with_capacity(codes.len());
+                for row_codes in codes {
+                    let mut row_cells = Vec::with_capacity(row_codes.len());
+                    for code in row_codes {
+                        let cell = region.assign_advice(config.code, row, Value::known(*code));
+                        row_cells.push((cell.cell(), *code));
+                        row += 1;
+                    }
+                    cells.push(row_cells);
+                }
+                Ok(cells)
+            },
+        )
+    }
+
+    /// Looks up every pq component of `pq_row` under the codes of its vector,
+    /// in the table subspaces starting at `first_subspace`.
+    pub fn constrain_row(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        row_idx: usize,
+        codes: &[AssignedValue],
+        pq_row: &[AssignedValue],
+        sub_dim: usize,
+        first_subspace: usize,
+    ) -> Result<(), Error> {
+        if codes.len() * sub_dim != pq_row.len() {
+            return Err(Error::Synthesis);
+        }
+        let config = &self.config;
+        layouter.assign_region(
+            || format!("pq_lookup_{row_idx}_{first_subspace}"),
+            |mut region: Region<'_, Fr>| {
+                for (offset, (pq_cell, pq_value)) in pq_row.iter().enumerate() {
+                    let subspace = offset / sub_dim;
+                    let component = offset % sub_dim;
+                    let (code_cell, code_value) = codes[subspace];
+                    config.s_input.enable(&mut region, offset)?;
+                    let table_subspace = (first_subspace + subspace) as u64;
+                    region.assign_fixed(config.subspace, offset, Fr::from(table_subspace));
+                    region.assign_fixed(config.component, offset, Fr::from(component as u64));
+                    let code = region.assign_advice(config.code, offset, Value::known(code_value));
+                    region.constrain_equal(code.cell(), code_cell);
+                    let value = region.assign_advice(config.value, offset, Value::known(*pq_value));
+                    region.constrain_equal(value.cell(), *pq_cell);
+                }
+                Ok(())
+            },
+        )
+    }
+}
@@ -0,0 +1,143 @@
+//! Bit-decomposition range checks and `value <= bound` comparisons.
+//!
+//! A range check decomposes the value MSB-first into a running sum:
+//!
+//! ```text
+//! row i       acc_i | bit_i      s_bit: bit_i boolean, acc_{i+1} = 2 * acc_i + bit_i
+//! row n       acc_n | -          copy-constrained to the checked cell
+//! ```
+//!
+//! Comparisons lay out `bound, value, gap` in consecutive `acc` rows with
+//! `bound = value + gap`, then range check the gap.
+
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+use halo2curves::{bn256::Fr, ff::PrimeField};
+
+use crate::gadgets::poseidon::AssignedValue;
+
+#[derive(Clone, Debug)]
+pub struct RangeCheckConfig {
+    acc: Column<Advice>,
+    bit: Column<Advice>,
+    s_zero: Selector,
+    s_bit: Selector,
+    s_gap: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct RangeCheckChip {
+    config: RangeCheckConfig,
+}
+
+impl RangeCheckChip {
+    pub fn construct(config: RangeCheckConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> RangeCheckConfig {
+        let acc = meta.advice_column();
+        let bit = meta.advice_column();
+        meta.enable_equality(acc);
+        let s_zero = meta.selector();
+        let s_bit = meta.selector();
+        let s_gap = meta.selector();
+
+        meta.create_gate("range_zero", |meta| {
+            let s = meta.query_selector(s_zero);
+            vec![s * meta.query_advice(acc, Rotation::cur())]
+        });
+
+        meta.create_gate("range_bit", |meta| {
+            let s = meta.query_selector(s_bit);
+            let bit = meta.query_advice(bit, Rotation::cur());
+            let cur = meta.query_advice(acc, Rotation::cur());
+            let next = meta.query_advice(acc, Rotation::next());
+            let two = Expression::Constant(Fr::from(2));
+            vec![
+                s.clone() * bit.clone() * (Expression::Constant(Fr::one()) - bit.clone()),
+                s * (next - cur * two - bit),
+            ]
+        });
+
+        meta.create_gate("range_gap", |meta| {
+            let s = meta.query_selector(s_gap);
+            let bound = meta.query_advice(acc, Rotation::cur());
+            let value = meta.query_advice(acc, Rotation::next());
+            let gap = meta.query_advice(acc, Rotation(2));
+            vec![s * (bound - value - gap)]
+        });
+
+        RangeCheckConfig {
+            acc,
+            bit,
+            s_zero,
+            s_bit,
+            s_gap,
+        }
+    }
+
+    /// Constrains `value < 2^bits`.
+    pub fn check(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        value: AssignedValue,
+        bits: usize,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        let (value_cell, value) = value;
+        let repr = value.to_repr();
+        let bytes = repr.as_ref();
+        layouter.assign_region(
+            || "range_check",
+            |mut region: Region<'_, Fr>| {
+                let mut acc = Fr::zero();
+                config.s_zero.enable(&mut region, 0)?;
+                let mut last = region.assign_advice(config.acc, 0, Value::known(acc));
+                for row in 0..bits {
+                    let position = bits - 1 - row;
+                    let bit = bytes
+                        .get(position / 8)
+                        .map(|byte| (byte >> (position % 8)) & 1)
+                        .unwrap_or(0);
+                    let bit = Fr::from(bit as u64);
+                    config.s_bit.enable(&mut region, row)?;
+                    region.assign_advice(config.bit, row, Value::known(bit));
+                    acc = acc + acc + bit;
+                    last = region.assign_advice(config.acc, row + 1, Value::known(acc));
+                }
+                region.constrain_equal(last.cell(), value_cell);
+                Ok(())
+            },
+        )
+    }
+
+    /// Constrains `value <= bound`, given both are below `2^bits` and far from
+    /// the field modulus.
+    pub fn assert_less_or_equal(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        value: AssignedValue,
+        bound: AssignedValue,
+        bits: usize,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        let gap = layouter.assign_region(
+            || "range_gap",
+            |mut region: Region<'_, Fr>| {
+                config.s_gap.enable(&mut region, 0)?;
+                let assigned = region.assign_advice(config.acc, 0, Value::known(bound.1));
+                region.constrain_equal(assigned.cell(), bound.0);
+                let assigned = region.assign_advice(config.acc, 1, Value::known(value.1));
+                region.constrain_equal(assigned.cell(), value.0);
+                let gap = bound.1 - value.1;
+                let assigned = region.assign_advice(config.acc, 2, Value::known(gap));
+                Ok((assigned.cell(), gap))
+            },
+        )?;
+        self.check(layouter, gap, bits)
+    }
+}
@@ -0,0 +1,148 @@
+//! Int8 scalar dequantization.
+//!
+//! ```text
+//! row j       code | scale | zero | value      s_dequant: value = scale * (code - zero)
+//! ```
+//!
+//! `code` and `zero` are looked up in a fixed table of the 256 int8 values,
+//! so each component costs one row and there is no codebook to lay out.
+
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector},
+    poly::Rotation,
+};
+use halo2curves::bn256::Fr;
+
+use crate::gadgets::poseidon::AssignedValue;
+
+#[derive(Clone, Debug)]
+pub struct DequantizeConfig {
+    code: Column<Advice>,
+    scale: Column<Advice>,
+    zero: Column<Advice>,
+    value: Column<Advice>,
+    int8: Column<Fixed>,
+    s_dequant: Selector,
+    s_table: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct DequantizeChip {
+    config: DequantizeConfig,
+}
+
+impl DequantizeChip {
+    pub fn construct(config: DequantizeConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> DequantizeConfig {
+        let code = meta.advice_column();
+        let scale = meta.advice_column();
+        let zero = meta.advice_column();
+        let value = meta.advice_column();
+        for column in [code, scale, zero, value] {
+            meta.enable_equality(column);
+        }
+        let int8 = meta.fixed_column();
+        let s_dequant = meta.complex_selector();
+        let s_table = meta.complex_selector();
+
+        meta.create_gate("dequantize", |meta| {
+            let s = meta.query_selector(s_dequant);
+            let code = meta.query_advice(code, Rotation::cur());
+            let scale = meta.query_advice(scale, Rotation::cur());
+            let zero = meta.query_advice(zero, Rotation::cur());
+            let value = meta.query_advice(value, Rotation::cur());
+            vec![s * (value - scale * (code - zero))]
+        });
+
+        for (name, column) in [("int8_code", code), ("int8_zero_point", zero)] {
+            meta.lookup_any(name, |meta| {
+                let s_in = meta.query_selector(s_dequant);
+                let s_tab = meta.query_selector(s_table);
+                let input = meta.query_advice(column, Rotation::cur());
+                let entry = meta.query_fixed(int8, Rotation::cur());
+                vec![(s_in * input, s_tab * entry)]
+            });
+        }
+
+        DequantizeConfig {
+            code,
+            scale,
+            zero,
+            value,
+            int8,
+            s_dequant,
+            s_table,
+        }
+    }
+
+    /// Assigns the table of int8 values.
+    pub fn assign_table(&self, layouter: &mut impl Layouter<Fr>) -> Result<(), Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "int8_table",
+            |mut region: Region<'_, Fr>| {
+                for (row, value) in (i8::MIN..=i8::MAX).enumerate() {
+                    config.s_table.enable(&mut region, row)?;
+                    region.assign_fixed(config.int8, row, int8_to_field(value));
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Constrains every component of `pq_row` to dequantize from its code in
+    /// `codes` under the per-dimension `scales` and `zeros`, returning the
+    /// code cells.
+    pub fn dequantize_row(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        row_idx: usize,
+        codes: &[Fr],
+        scales: &[AssignedValue],
+        zeros: &[AssignedValue],
+        pq_row: &[AssignedValue],
+    ) -> Result<Vec<AssignedValue>, Error> {
+        if codes.len() != pq_row.len()
+            || scales.len() != pq_row.len()
+            || zeros.len() != pq_row.len()
+        {
+            return Err(Error::Synthesis);
+        }
+        let config = &self.config;
+        layouter.assign_region(
+            || format!("dequantize_{row_idx}"),
+            |mut region: Region<'_, Fr>| {
+                let mut code_cells = Vec::with_capacity(codes.len());
+                for (offset, (((code, scale), zero), value)) in
+                    codes.iter().zip(scales).zip(zeros).zip(pq_row).enumerate()
+                {
+                    config.s_dequant.enable(&mut region, offset)?;
+                    let cell = region.assign_advice(config.code, offset, Value::known(*code));
+                    code_cells.push((cell.cell(), *code));
+                    for (column, (source, assigned)) in [
+                        (config.scale, scale),
+                        (config.zero, zero),
+                        (config.value, value),
+                    ] {
+                        let cell = region.assign_advice(column, offset, Value::known(*assigned));
+                        region.constrain_equal(cell.cell(), *source);
+                    }
+                }
+                Ok(code_cells)
+            },
+        )
+    }
+}
+
+/// An int8 as a field element, negative values as `p - |value|`.
+pub fn int8_to_field(value: i8) -> Fr {
+    if value < 0 {
+        -Fr::from(value.unsigned_abs() as u64)
+    } else {
+        Fr::from(value as u64)
+    }
+}
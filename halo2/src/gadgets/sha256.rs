@@ -0,0 +1,771 @@
+//! In-circuit SHA-256 for the `instanceHash` mode.
+//!
+//! Every word is held both as 32 boolean cells and as its packed value.
+//! Bitwise functions run one bit per row, MSB first, packing the output:
+//!
+//! ```text
+//! row i       x_i | y_i | z_i | out_i | acc_i    s_xor3 / s_ch / s_maj (s_bit for fresh words)
+//! row 32      -                       | acc_32   packed word
+//! ```
+//!
+//! with `acc_0 = 0` and `acc_{i+1} = 2 * acc_i + out_i`. Rotations and shifts
+//! cost nothing: they only pick which input bit (or the zero cell) is copied
+//! into `x`, `y` and `z`. Additions mod 2^32 sum up to eight packed words and
+//! split the total into a low word and a three-bit carry:
+//!
+//! ```text
+//! row 0       t0  | t1 | t2 | t3 | -
+//! row 1       t4  | t5 | t6 | t7 | -
+//! row 2       low | c0 | c1 | c2 | -         t0 + .. + t7 = low + 2^32 * (c0 + 2 c1 + 4 c2)
+//! row 3..35   bit decomposition of low
+//! ```
+//!
+//! Field elements enter and leave as eight big-endian words, composed with
+//! `acc_{k+1} = 2^32 * acc_k + x_k`; the digest therefore comes out reduced
+//! mod p. Inputs must decompose canonically, so their 256 bits are also
+//! compared with p - 1, MSB first, tracking whether the prefix so far equals
+//! it (`eq`) or is already below it (`lt`):
+//!
+//! ```text
+//! row i       bit_i | eq_i | lt_i    constant: bit i of p - 1
+//! row 256     -     | eq   | lt      eq + lt = 1
+//! ```
+//!
+//! starting from `eq_0 = 1, lt_0 = 0`. About 18k rows per 64-byte block.
+
+use halo2_proofs::{
+    circuit::{Cell, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+use halo2curves::{bn256::Fr, ff::PrimeField};
+
+use crate::gadgets::poseidon::AssignedValue;
+
+const WORD_BITS: usize = 32;
+
+/// Rows of a decomposed word: one per bit and the packed value.
+const WORD_ROWS: usize = WORD_BITS + 1;
+
+/// Rows of an addition: three of terms and carries, then the low word.
+const ADD_ROWS: usize = 3 + WORD_ROWS;
+
+/// Rows of one compression: the message schedule and 64 rounds.
+const BLOCK_ROWS: usize =
+    48 * (2 * WORD_ROWS + ADD_ROWS) + 64 * (4 * WORD_ROWS + 2 * ADD_ROWS) + 8 * ADD_ROWS;
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+#[derive(Clone, Debug)]
+pub struct Sha256Config {
+    x: Column<Advice>,
+    y: Column<Advice>,
+    z: Column<Advice>,
+    out: Column<Advice>,
+    acc: Column<Advice>,
+    constant: Column<Fixed>,
+    s_zero: Selector,
+    s_pack: Selector,
+    s_bit: Selector,
+    s_xor3: Selector,
+    s_ch: Selector,
+    s_maj: Selector,
+    s_add: Selector,
+    s_word: Selector,
+    s_const: Selector,
+    s_below: Selector,
+    s_below_first: Selector,
+    s_below_last: Selector,
+}
+
+/// A 32-bit word: `bits[i]` holds bit `i` (LSB first).
+#[derive(Clone, Debug)]
+pub struct Word {
+    bits: Vec<AssignedValue>,
+    packed: Cell,
+    value: u32,
+}
+
+impl Word {
+    fn packed(&self) -> AssignedValue {
+        (self.packed, Fr::from(u64::from(self.value)))
+    }
+
+    fn rotr(&self, n: usize) -> Vec<AssignedValue> {
+        (0..WORD_BITS)
+            .map(|i| self.bits[(i + n) % WORD_BITS])
+            .collect()
+    }
+
+    fn shr(&self, n: usize, zero: AssignedValue) -> Vec<AssignedValue> {
+        (0..WORD_BITS)
+            .map(|i| self.bits.get(i + n).copied().unwrap_or(zero))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BitOp {
+    Xor3,
+    Ch,
+    Maj,
+}
+
+impl BitOp {
+    fn apply(self, x: bool, y: bool, z: bool) -> bool {
+        match self {
+            BitOp::Xor3 => x ^ y ^ z,
+            BitOp::Ch => (x && y) || (!x && z),
+            BitOp::Maj => (x && y) || (x && z) || (y && z),
+        }
+    }
+}
+
+struct Constants {
+    zero: AssignedValue,
+    rounds: Vec<AssignedValue>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha256Chip {
+    config: Sha256Config,
+}
+
+impl Sha256Chip {
+    pub fn construct(config: Sha256Config) -> Self {
+        Self { config }
+    }
+
+    /// Rows [`Sha256Chip::hash_to_field`] lays out for `inputs` field
+    /// elements, at most; regions on disjoint columns may share rows.
+    pub fn rows(inputs: usize) -> usize {
+        let words = 8 * inputs;
+        let blocks = (words + 3).div_ceil(16);
+        let padding = 16 * blocks - words;
+        let compose = 8 + 1;
+        let below_modulus = 256 + 1;
+        1 + ROUND_CONSTANTS.len()
+            + inputs * (8 * WORD_ROWS + compose + below_modulus)
+            + (padding + INITIAL_STATE.len()) * (1 + WORD_ROWS)
+            + blocks * BLOCK_ROWS
+            + compose
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> Sha256Config {
+        let x = meta.advice_column();
+        let y = meta.advice_column();
+        let z = meta.advice_column();
+        let out = meta.advice_column();
+        let acc = meta.advice_column();
+        let constant = meta.fixed_column();
+        for column in [x, y, z, out, acc] {
+            meta.enable_equality(column);
+        }
+        let s_zero = meta.selector();
+        let s_pack = meta.selector();
+        let s_bit = meta.selector();
+        let s_xor3 = meta.selector();
+        let s_ch = meta.selector();
+        let s_maj = meta.selector();
+        let s_add = meta.selector();
+        let s_word = meta.selector();
+        let s_const = meta.selector();
+        let s_below = meta.selector();
+        let s_below_first = meta.selector();
+        let s_below_last = meta.selector();
+        let one = || Expression::Constant(Fr::one());
+        let two = || Expression::Constant(Fr::from(2));
+
+        meta.create_gate("sha256_zero", |meta| {
+            let s = meta.query_selector(s_zero);
+            vec![s * meta.query_advice(acc, Rotation::cur())]
+        });
+
+        meta.create_gate("sha256_pack", |meta| {
+            let s = meta.query_selector(s_pack);
+            let bit = meta.query_advice(out, Rotation::cur());
+            let cur = meta.query_advice(acc, Rotation::cur());
+            let next = meta.query_advice(acc, Rotation::next());
+            vec![s * (next - cur * two() - bit)]
+        });
+
+        meta.create_gate("sha256_bit", |meta| {
+            let s = meta.query_selector(s_bit);
+            let bit = meta.query_advice(out, Rotation::cur());
+            vec![s * bit.clone() * (one() - bit)]
+        });
+
+        for (name, selector, op) in [
+            ("sha256_xor3", s_xor3, BitOp::Xor3),
+            ("sha256_ch", s_ch, BitOp::Ch),
+            ("sha256_maj", s_maj, BitOp::Maj),
+        ] {
+            meta.create_gate(name, |meta| {
+                let s = meta.query_selector(selector);
+                let a = meta.query_advice(x, Rotation::cur());
+                let b = meta.query_advice(y, Rotation::cur());
+                let c = meta.query_advice(z, Rotation::cur());
+                let result = meta.query_advice(out, Rotation::cur());
+                let expected = match op {
+                    BitOp::Xor3 => {
+                        a.clone() + b.clone() + c.clone()
+                            - (a.clone() * b.clone()
+                                + a.clone() * c.clone()
+                                + b.clone() * c.clone())
+                                * two()
+                            + a * b * c * Expression::Constant(Fr::from(4))
+                    }
+                    BitOp::Ch => a.clone() * b + (one() - a) * c,
+                    BitOp::Maj => {
+                        a.clone() * b.clone() + a.clone() * c.clone() + b.clone() * c.clone()
+                            - a * b * c * two()
+                    }
+                };
+                vec![s * (result - expected)]
+            });
+        }
+
+        meta.create_gate("sha256_add", |meta| {
+            let s = meta.query_selector(s_add);
+            let mut sum = Expression::Constant(Fr::zero());
+            for rotation in [Rotation::cur(), Rotation::next()] {
+                for column in [x, y, z, out] {
+                    sum = sum + meta.query_advice(column, rotation);
+                }
+            }
+            let low = meta.query_advice(x, Rotation(2));
+            let carry: Vec<_> = [y, z, out]
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation(2)))
+                .collect();
+            let carry_value = carry[0].clone()
+                + carry[1].clone() * two()
+                + carry[2].clone() * Expression::Constant(Fr::from(4));
+            let mut constraints = vec![
+                s.clone() * (sum - low - carry_value * Expression::Constant(Fr::from(1u64 << 32))),
+            ];
+            for bit in carry {
+                constraints.push(s.clone() * bit.clone() * (one() - bit));
+            }
+            constraints
+        });
+
+        meta.create_gate("sha256_word", |meta| {
+            let s = meta.query_selector(s_word);
+            let word = meta.query_advice(x, Rotation::cur());
+            let cur = meta.query_advice(acc, Rotation::cur());
+            let next = meta.query_advice(acc, Rotation::next());
+            vec![s * (next - cur * Expression::Constant(Fr::from(1u64 << 32)) - word)]
+        });
+
+        meta.create_gate("sha256_const", |meta| {
+            let s = meta.query_selector(s_const);
+            let value = meta.query_advice(x, Rotation::cur());
+            vec![s * (value - meta.query_fixed(constant, Rotation::cur()))]
+        });
+
+        meta.create_gate("sha256_below_modulus", |meta| {
+            let s = meta.query_selector(s_below);
+            let bit = meta.query_advice(x, Rotation::cur());
+            let bound = meta.query_fixed(constant, Rotation::cur());
+            let eq = meta.query_advice(y, Rotation::cur());
+            let lt = meta.query_advice(z, Rotation::cur());
+            let eq_next = meta.query_advice(y, Rotation::next());
+            let lt_next = meta.query_advice(z, Rotation::next());
+            let same =
+                bound.clone() * bit.clone() + (one() - bound.clone()) * (one() - bit.clone());
+            vec![
+                s.clone() * (eq_next - eq.clone() * same),
+                s * (lt_next - lt - bound * eq * (one() - bit)),
+            ]
+        });
+
+        meta.create_gate("sha256_below_modulus_ends", |meta| {
+            let first = meta.query_selector(s_below_first);
+            let last = meta.query_selector(s_below_last);
+            let eq = meta.query_advice(y, Rotation::cur());
+            let lt = meta.query_advice(z, Rotation::cur());
+            vec![
+                first.clone() * (eq.clone() - one()),
+                first * lt.clone(),
+                last * (eq + lt - one()),
+            ]
+        });
+
+        Sha256Config {
+            x,
+            y,
+            z,
+            out,
+            acc,
+            constant,
+            s_zero,
+            s_pack,
+            s_bit,
+            s_xor3,
+            s_ch,
+            s_maj,
+            s_add,
+            s_word,
+            s_const,
+            s_below,
+            s_below_first,
+            s_below_last,
+        }
+    }
+
+    /// `sha256(be32(inputs[0]) || be32(inputs[1]) || ..) mod p`, matching
+    /// `public_inputs::instance_hash`.
+    pub fn hash_to_field(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        inputs: &[AssignedValue],
+    ) -> Result<AssignedValue, Error> {
+        if inputs.is_empty() {
+            return Err(Error::Synthesis);
+        }
+        let constants = self.constants(layouter)?;
+
+        let mut message = Vec::with_capacity(inputs.len() * 8 + 16);
+        for input in inputs {
+            let repr = input.1.to_repr();
+            let mut bytes = repr.as_ref().to_vec();
+            bytes.reverse();
+            let mut words = Vec::with_capacity(8);
+            for chunk in bytes.chunks(4) {
+                let value = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                words.push(self.free_word(layouter, value)?);
+            }
+            self.compose(layouter, &words, Some(input.0))?;
+            self.below_modulus(layouter, &words)?;
+            message.extend(words);
+        }
+        let length_bits = (inputs.len() * 256) as u64;
+        let mut padding = vec![0x8000_0000u32];
+        while (message.len() + padding.len() + 2) % 16 != 0 {
+            padding.push(0);
+        }
+        padding.push((length_bits >> 32) as u32);
+        padding.push(length_bits as u32);
+        for value in padding {
+            message.push(self.constant_word(layouter, value)?);
+        }
+
+        let mut state = Vec::with_capacity(8);
+        for value in INITIAL_STATE {
+            state.push(self.constant_word(layouter, value)?);
+        }
+        for block in message.chunks(16) {
+            state = self.compress(layouter, &constants, &state, block)?;
+        }
+        self.compose(layouter, &state, None)
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        constants: &Constants,
+        state: &[Word],
+        block: &[Word],
+    ) -> Result<Vec<Word>, Error> {
+        let zero = constants.zero;
+        let mut schedule: Vec<Word> = block.to_vec();
+        for t in 16..64 {
+            let w15 = &schedule[t - 15];
+            let sigma0 = self.bitwise(
+                layouter,
+                BitOp::Xor3,
+                [w15.rotr(7), w15.rotr(18), w15.shr(3, zero)],
+            )?;
+            let w2 = &schedule[t - 2];
+            let sigma1 = self.bitwise(
+                layouter,
+                BitOp::Xor3,
+                [w2.rotr(17), w2.rotr(19), w2.shr(10, zero)],
+            )?;
+            let word = self.add(
+                layouter,
+                zero,
+                &[
+                    sigma1.packed(),
+                    schedule[t - 7].packed(),
+                    sigma0.packed(),
+                    schedule[t - 16].packed(),
+                ],
+            )?;
+            schedule.push(word);
+        }
+
+        let mut working = state.to_vec();
+        for t in 0..64 {
+            let [a, b, c, d, e, f, g, h] = [0, 1, 2, 3, 4, 5, 6, 7].map(|idx| &working[idx]);
+            let big_sigma1 =
+                self.bitwise(layouter, BitOp::Xor3, [e.rotr(6), e.rotr(11), e.rotr(25)])?;
+            let ch = self.bitwise(layouter, BitOp::Ch, [e.rotr(0), f.rotr(0), g.rotr(0)])?;
+            let big_sigma0 =
+                self.bitwise(layouter, BitOp::Xor3, [a.rotr(2), a.rotr(13), a.rotr(22)])?;
+            let maj = self.bitwise(layouter, BitOp::Maj, [a.rotr(0), b.rotr(0), c.rotr(0)])?;
+            let temp1 = [
+                h.packed(),
+                big_sigma1.packed(),
+                ch.packed(),
+                constants.rounds[t],
+                schedule[t].packed(),
+            ];
+            let mut new_e = temp1.to_vec();
+            new_e.push(d.packed());
+            let mut new_a = temp1.to_vec();
+            new_a.extend([big_sigma0.packed(), maj.packed()]);
+            let new_e = self.add(layouter, zero, &new_e)?;
+            let new_a = self.add(layouter, zero, &new_a)?;
+            working.pop();
+            working.insert(0, new_a);
+            working[4] = new_e;
+        }
+
+        state
+            .iter()
+            .zip(working.iter())
+            .map(|(initial, last)| self.add(layouter, zero, &[initial.packed(), last.packed()]))
+            .collect()
+    }
+
+    fn constants(&self, layouter: &mut impl Layouter<Fr>) -> Result<Constants, Error> {
+        layouter.assign_region(
+            || "sha256_constants",
+            |mut region: Region<'_, Fr>| {
+                let zero = self.assign_constant(&mut region, 0, 0)?;
+                let mut rounds = Vec::with_capacity(ROUND_CONSTANTS.len());
+                for (idx, value) in ROUND_CONSTANTS.iter().enumerate() {
+                    rounds.push(self.assign_constant(&mut region, idx + 1, *value)?);
+                }
+                Ok(Constants { zero, rounds })
+            },
+        )
+    }
+
+    fn assign_constant(
+        &self,
+        region: &mut Region<'_, Fr>,
+        row: usize,
+        value: u32,
+    ) -> Result<AssignedValue, Error> {
+        let value = Fr::from(u64::from(value));
+        self.config.s_const.enable(region, row)?;
+        region.assign_fixed(self.config.constant, row, value);
+        let cell = region.assign_advice(self.config.x, row, Value::known(value));
+        Ok((cell.cell(), value))
+    }
+
+    /// Decomposes `value` into boolean `out` cells starting at `row`.
+    fn assign_bits(
+        &self,
+        region: &mut Region<'_, Fr>,
+        row: usize,
+        value: u32,
+    ) -> Result<Word, Error> {
+        let bits: Vec<bool> = (0..WORD_BITS)
+            .rev()
+            .map(|position| (value >> position) & 1 == 1)
+            .collect();
+        for offset in 0..WORD_BITS {
+            self.config.s_bit.enable(region, row + offset)?;
+        }
+        self.assign_packed(region, row, &bits)
+    }
+
+    /// Lays out MSB-first `bits` in `out` with their running sum in `acc`.
+    fn assign_packed(
+        &self,
+        region: &mut Region<'_, Fr>,
+        row: usize,
+        bits: &[bool],
+    ) -> Result<Word, Error> {
+        let config = &self.config;
+        let mut acc = 0u64;
+        let mut cells = vec![None; WORD_BITS];
+        config.s_zero.enable(region, row)?;
+        let mut packed = region
+            .assign_advice(config.acc, row, Value::known(Fr::zero()))
+            .cell();
+        for (offset, bit) in bits.iter().enumerate() {
+            config.s_pack.enable(region, row + offset)?;
+            let value = Fr::from(u64::from(*bit));
+            let cell = region.assign_advice(config.out, row + offset, Value::known(value));
+            cells[WORD_BITS - 1 - offset] = Some((cell.cell(), value));
+            acc = acc * 2 + u64::from(*bit);
+            packed = region
+                .assign_advice(config.acc, row + offset + 1, Value::known(Fr::from(acc)))
+                .cell();
+        }
+        Ok(Word {
+            bits: cells.into_iter().flatten().collect(),
+            packed,
+            value: acc as u32,
+        })
+    }
+
+    /// A word with no constraint beyond its bits being boolean.
+    fn free_word(&self, layouter: &mut impl Layouter<Fr>, value: u32) -> Result<Word, Error> {
+        layouter.assign_region(
+            || "sha256_word",
+            |mut region: Region<'_, Fr>| self.assign_bits(&mut region, 0, value),
+        )
+    }
+
+    fn constant_word(&self, layouter: &mut impl Layouter<Fr>, value: u32) -> Result<Word, Error> {
+        layouter.assign_region(
+            || "sha256_constant_word",
+            |mut region: Region<'_, Fr>| {
+                let (cell, _) = self.assign_constant(&mut region, 0, value)?;
+                let word = self.assign_bits(&mut region, 1, value)?;
+                region.constrain_equal(cell, word.packed);
+                Ok(word)
+            },
+        )
+    }
+
+    fn bitwise(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        op: BitOp,
+        inputs: [Vec<AssignedValue>; 3],
+    ) -> Result<Word, Error> {
+        let config = &self.config;
+        let selector = match op {
+            BitOp::Xor3 => config.s_xor3,
+            BitOp::Ch => config.s_ch,
+            BitOp::Maj => config.s_maj,
+        };
+        layouter.assign_region(
+            || "sha256_bitwise",
+            |mut region: Region<'_, Fr>| {
+                let mut bits = Vec::with_capacity(WORD_BITS);
+                for offset in 0..WORD_BITS {
+                    let position = WORD_BITS - 1 - offset;
+                    selector.enable(&mut region, offset)?;
+                    let mut values = [false; 3];
+                    for (idx, column) in [config.x, config.y, config.z].iter().enumerate() {
+                        let (cell, value) = inputs[idx][position];
+                        let assigned = region.assign_advice(*column, offset, Value::known(value));
+                        region.constrain_equal(assigned.cell(), cell);
+                        values[idx] = value == Fr::one();
+                    }
+                    bits.push(op.apply(values[0], values[1], values[2]));
+                }
+                self.assign_packed(&mut region, 0, &bits)
+            },
+        )
+    }
+
+    /// Sum of up to eight words mod 2^32.
+    fn add(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        zero: AssignedValue,
+        terms: &[AssignedValue],
+    ) -> Result<Word, Error> {
+        if terms.len() > 8 {
+            return Err(Error::Synthesis);
+        }
+        let config = &self.config;
+        layouter.assign_region(
+            || "sha256_add",
+            |mut region: Region<'_, Fr>| {
+                config.s_add.enable(&mut region, 0)?;
+                let columns = [config.x, config.y, config.z, config.out];
+                let mut total = 0u64;
+                for idx in 0..8 {
+                    let (cell, value) = terms.get(idx).copied().unwrap_or(zero);
+                    total += u64::from(word_value(&value));
+                    let assigned =
+                        region.assign_advice(columns[idx % 4], idx / 4, Value::known(value));
+                    region.constrain_equal(assigned.cell(), cell);
+                }
+                let low = total as u32;
+                let carry = total >> 32;
+                let low_cell =
+                    region.assign_advice(config.x, 2, Value::known(Fr::from(u64::from(low))));
+                for (idx, column) in [config.y, config.z, config.out].iter().enumerate() {
+                    region.assign_advice(*column, 2, Value::known(Fr::from((carry >> idx) & 1)));
+                }
+                let word = self.assign_bits(&mut region, 3, low)?;
+                region.constrain_equal(low_cell.cell(), word.packed);
+                Ok(word)
+            },
+        )
+    }
+
+    /// `sum(words[k] * 2^(32 * (len - 1 - k)))` in the field, optionally
+    /// constrained to `target`.
+    fn compose(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        words: &[Word],
+        target: Option<Cell>,
+    ) -> Result<AssignedValue, Error> {
+        let config = &self.config;
+        layouter.assign_region(
+            || "sha256_compose",
+            |mut region: Region<'_, Fr>| {
+                let shift = Fr::from(1u64 << 32);
+                let mut acc = Fr::zero();
+                config.s_zero.enable(&mut region, 0)?;
+                let mut result = region
+                    .assign_advice(config.acc, 0, Value::known(acc))
+                    .cell();
+                for (row, word) in words.iter().enumerate() {
+                    config.s_word.enable(&mut region, row)?;
+                    let (cell, value) = word.packed();
+                    let assigned = region.assign_advice(config.x, row, Value::known(value));
+                    region.constrain_equal(assigned.cell(), cell);
+                    acc = acc * shift + value;
+                    result = region
+                        .assign_advice(config.acc, row + 1, Value::known(acc))
+                        .cell();
+                }
+                if let Some(target) = target {
+                    region.constrain_equal(result, target);
+                }
+                Ok((result, acc))
+            },
+        )
+    }
+
+    /// Constrains the big-endian `words` to a value below p, so no input has
+    /// a second decomposition `value + p` that would hash differently.
+    fn below_modulus(&self, layouter: &mut impl Layouter<Fr>, words: &[Word]) -> Result<(), Error> {
+        let max = (-Fr::one()).to_repr();
+        let bound: Vec<bool> = max
+            .as_ref()
+            .iter()
+            .rev()
+            .flat_map(|byte| {
+                (0..8)
+                    .rev()
+                    .map(move |position| (byte >> position) & 1 == 1)
+            })
+            .collect();
+        let bits: Vec<AssignedValue> = words
+            .iter()
+            .flat_map(|word| word.bits.iter().rev().copied())
+            .collect();
+        if bits.len() != bound.len() {
+            return Err(Error::Synthesis);
+        }
+        let config = &self.config;
+        layouter.assign_region(
+            || "sha256_below_modulus",
+            |mut region: Region<'_, Fr>| {
+                let (mut eq, mut lt) = (true, false);
+                config.s_below_first.enable(&mut region, 0)?;
+                for (row, ((cell, value), bound)) in bits.iter().zip(&bound).enumerate() {
+                    config.s_below.enable(&mut region, row)?;
+                    let assigned = region.assign_advice(config.x, row, Value::known(*value));
+                    region.constrain_equal(assigned.cell(), *cell);
+                    region.assign_fixed(config.constant, row, Fr::from(u64::from(*bound)));
+                    region.assign_advice(config.y, row, Value::known(Fr::from(u64::from(eq))));
+                    region.assign_advice(config.z, row, Value::known(Fr::from(u64::from(lt))));
+                    let bit = *value == Fr::one();
+                    lt |= eq && *bound && !bit;
+                    eq &= bit == *bound;
+                }
+                let last = bits.len();
+                config.s_below_last.enable(&mut region, last)?;
+                region.assign_advice(config.y, last, Value::known(Fr::from(u64::from(eq))));
+                region.assign_advice(config.z, last, Value::known(Fr::from(u64::from(lt))));
+                Ok(())
+            },
+        )
+    }
+}
+
+fn word_value(value: &Fr) -> u32 {
+    let repr = value.to_repr();
+    let bytes = repr.as_ref();
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, plonk::Circuit};
+
+    use super::*;
+
+    /// Decomposes `words` and checks them against the modulus.
+    #[derive(Clone)]
+    struct BelowModulus {
+        words: [u32; 8],
+    }
+
+    impl Circuit<Fr> for BelowModulus {
+        type Config = Sha256Config;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Sha256Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = Sha256Chip::construct(config);
+            let words = self
+                .words
+                .iter()
+                .map(|value| chip.free_word(&mut layouter, *value))
+                .collect::<Result<Vec<_>, _>>()?;
+            chip.below_modulus(&mut layouter, &words)
+        }
+    }
+
+    /// Whether the little-endian 256-bit integer passes the modulus check.
+    fn satisfied(le_bytes: [u8; 32]) -> bool {
+        let mut bytes = le_bytes;
+        bytes.reverse();
+        let words = std::array::from_fn(|idx| {
+            u32::from_be_bytes(bytes[4 * idx..4 * idx + 4].try_into().unwrap())
+        });
+        MockProver::run(10, &BelowModulus { words }, vec![])
+            .unwrap()
+            .verify()
+            .is_ok()
+    }
+
+    #[test]
+    fn only_canonical_decompositions_pass() {
+        let max: [u8; 32] = (-Fr::one()).to_repr().as_ref().try_into().unwrap();
+        assert_eq!(max[0], 0, "p - 1 ends in a zero byte");
+        assert!(satisfied(max));
+        assert!(satisfied(
+            Fr::from(7).to_repr().as_ref().try_into().unwrap()
+        ));
+        // p and p + 1 are the second decompositions of 0 and 1.
+        for extra in [1, 2] {
+            let mut beyond = max;
+            beyond[0] += extra;
+            assert!(!satisfied(beyond), "p - 1 + {extra}");
+        }
+        assert!(!satisfied([0xff; 32]));
+    }
+}
@@ -0,0 +1,135 @@
+//! Row shuffle argument.
+//!
+//! ```text
+//! source row i   position = i (fixed) | v_1 .. v_w     s_source
+//! target row j   index = order[j]     | v_1 .. v_w     s_target
+//! ```
+//!
+//! Every target tuple is looked up among the source tuples and every source
+//! tuple among the target tuples. Source positions are distinct, so with as
+//! many target rows as source rows the target is a permutation of the source
+//! and `index` names the source row each target row came from.
+
+use halo2_proofs::{
+    circuit::{Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector, VirtualCells},
+    poly::Rotation,
+};
+use halo2curves::bn256::Fr;
+
+use crate::gadgets::poseidon::AssignedValue;
+
+#[derive(Clone, Debug)]
+pub struct ShuffleConfig {
+    position: Column<Fixed>,
+    index: Column<Advice>,
+    values: Vec<Column<Advice>>,
+    s_source: Selector,
+    s_target: Selector,
+}
+
+#[derive(Clone, Debug)]
+pub struct ShuffleChip {
+    config: ShuffleConfig,
+}
+
+/// Shuffled rows: the source index of each target row, then its values.
+pub type ShuffledRows = (Vec<AssignedValue>, Vec<Vec<AssignedValue>>);
+
+impl ShuffleChip {
+    pub fn construct(config: ShuffleConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configures a shuffle of rows of `width` values.
+    pub fn configure(meta: &mut ConstraintSystem<Fr>, width: usize) -> ShuffleConfig {
+        let position = meta.fixed_column();
+        let index = meta.advice_column();
+        meta.enable_equality(index);
+        let values: Vec<_> = (0..width).map(|_| meta.advice_column()).collect();
+        for column in &values {
+            meta.enable_equality(*column);
+        }
+        let s_source = meta.complex_selector();
+        let s_target = meta.complex_selector();
+
+        let tuple = |meta: &mut VirtualCells<'_, Fr>, selector: Selector, index: Expression<Fr>| {
+            let s = meta.query_selector(selector);
+            let mut tuple = vec![s.clone() * index];
+            for column in &values {
+                tuple.push(s.clone() * meta.query_advice(*column, Rotation::cur()));
+            }
+            tuple
+        };
+        meta.lookup_any("shuffle_target_in_source", |meta| {
+            let target_index = meta.query_advice(index, Rotation::cur());
+            let source_position = meta.query_fixed(position, Rotation::cur());
+            let input = tuple(meta, s_target, target_index);
+            let table = tuple(meta, s_source, source_position);
+            input.into_iter().zip(table).collect()
+        });
+        meta.lookup_any("shuffle_source_in_target", |meta| {
+            let source_position = meta.query_fixed(position, Rotation::cur());
+            let target_index = meta.query_advice(index, Rotation::cur());
+            let input = tuple(meta, s_source, source_position);
+            let table = tuple(meta, s_target, target_index);
+            input.into_iter().zip(table).collect()
+        });
+
+        ShuffleConfig {
+            position,
+            index,
+            values,
+            s_source,
+            s_target,
+        }
+    }
+
+    /// Lays out `rows` and their reordering by `order`, where `order[j]` is
+    /// the row placed at position `j`.
+    pub fn shuffle(
+        &self,
+        layouter: &mut impl Layouter<Fr>,
+        rows: &[Vec<AssignedValue>],
+        order: &[usize],
+    ) -> Result<ShuffledRows, Error> {
+        let config = &self.config;
+        if order.len() != rows.len() || rows.iter().any(|row| row.len() != config.values.len()) {
+            return Err(Error::Synthesis);
+        }
+        let sources = order
+            .iter()
+            .map(|position| rows.get(*position))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::Synthesis)?;
+        layouter.assign_region(
+            || "row_shuffle",
+            |mut region: Region<'_, Fr>| {
+                for (offset, row) in rows.iter().enumerate() {
+                    config.s_source.enable(&mut region, offset)?;
+                    region.assign_fixed(config.position, offset, Fr::from(offset as u64));
+                    for (column, (source, value)) in config.values.iter().zip(row) {
+                        let cell = region.assign_advice(*column, offset, Value::known(*value));
+                        region.constrain_equal(cell.cell(), *source);
+                    }
+                }
+                let mut indices = Vec::with_capacity(order.len());
+                let mut shuffled = Vec::with_capacity(order.len());
+                for (target, (index, row)) in order.iter().zip(&sources).enumerate() {
+                    let offset = rows.len() + target;
+                    config.s_target.enable(&mut region, offset)?;
+                    let index = Fr::from(*index as u64);
+                    let cell = region.assign_advice(config.index, offset, Value::known(index));
+                    indices.push((cell.cell(), index));
+                    let mut values = Vec::with_capacity(row.len());
+                    for (column, (_, value)) in config.values.iter().zip(row.iter()) {
+                        let cell = region.assign_advice(*column, offset, Value::known(*value));
+                        values.push((cell.cell(), *value));
+                    }
+                    shuffled.push(values);
+                }
+                Ok((indices, shuffled))
+            },
+        )
+    }
+}
@@ -0,0 +1,129 @@
+//! Byte hashing behind one interface.
+//!
+//! * `blake3`: the default for every digest the toolkit computes (proof,
+//!   request and config digests, shard bindings, audit chains). Inputs of at
+//!   least [`PARALLEL_THRESHOLD`] bytes are hashed across the rayon pool.
+//! * `sha256`: for digests a contract has to recompute, where the EVM's
+//!   precompile makes it the cheap choice.
+//!
+//! Both backends pick their SIMD (and, for sha256, SHA-NI) code paths at
+//! runtime, so one build runs at full speed on every CPU it supports. The
+//! result of a digest never depends on which path computed it.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::bytes::Hash256;
+
+/// Inputs this large are worth splitting across threads; below it the
+/// thread handoff costs more than it saves.
+pub const PARALLEL_THRESHOLD: usize = 128 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Blake3,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn is_default(&self) -> bool {
+        *self == HashAlgorithm::Blake3
+    }
+
+    pub fn hasher(self) -> Box<dyn Hasher> {
+        match self {
+            HashAlgorithm::Blake3 => Box::new(Blake3Hasher::new()),
+            HashAlgorithm::Sha256 => Box::new(Sha256Hasher::default()),
+        }
+    }
+
+    pub fn digest(self, bytes: &[u8]) -> Hash256 {
+        let mut hasher = self.hasher();
+        hasher.update(bytes);
+        hasher.finalize()
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Sha256 => "sha256",
+        })
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            other => anyhow::bail!("unknown hash {other:?} (blake3 or sha256)"),
+        }
+    }
+}
+
+/// An incremental hash with a 32-byte output.
+pub trait Hasher: Send {
+    fn update(&mut self, bytes: &[u8]);
+    fn finalize(&self) -> Hash256;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Blake3Hasher(blake3::Hasher);
+
+impl Blake3Hasher {
+    pub fn new() -> Self {
+        Self(blake3::Hasher::new())
+    }
+
+    /// blake3's key derivation mode, separating digests by `context`.
+    pub fn derive_key(context: &str) -> Self {
+        Self(blake3::Hasher::new_derive_key(context))
+    }
+}
+
+impl Hasher for Blake3Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        if bytes.len() >= PARALLEL_THRESHOLD {
+            self.0.update_rayon(bytes);
+        } else {
+            self.0.update(bytes);
+        }
+    }
+
+    fn finalize(&self) -> Hash256 {
+        self.0.finalize().into()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        Digest::update(&mut self.0, bytes);
+    }
+
+    fn finalize(&self) -> Hash256 {
+        Hash256(self.0.clone().finalize().into())
+    }
+}
+
+/// blake3 of `bytes`.
+pub fn digest(bytes: &[u8]) -> Hash256 {
+    HashAlgorithm::Blake3.digest(bytes)
+}
+
+/// blake3 of `bytes` as unprefixed lowercase hex, the form proof and config
+/// digests have always been recorded in.
+pub fn digest_hex(bytes: &[u8]) -> String {
+    hex::encode(digest(bytes).as_bytes())
+}
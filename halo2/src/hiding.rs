@@ -0,0 +1,126 @@
+//! Hiding commitments to the vector matrices.
+//!
+//! By default `foldedCommitment` and `pqCommitment` are opaque digests the
+//! circuit only copies through, and a digest of the rows can be confirmed by
+//! anyone who guesses the rows. A circuit keyed with `hiding` instead
+//! recomputes both in-circuit as Pedersen-style commitments
+//! `Poseidon(blinding, rows..)` under [`HIDING_DOMAIN`], each with its own
+//! secret blinding factor from the witness's `blinding`. With a uniformly
+//! random blinding factor the commitment reveals nothing about the
+//! embeddings, and the proof still binds them. Unlike Pedersen over an
+//! elliptic curve the commitments are not additively homomorphic.
+//!
+//! Both values are then canonical field elements rather than the
+//! blake3/ChaCha20 mapped roots of the default encoding.
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    errors::{Coded, ErrorCode},
+    io::WitnessData,
+    poseidon::{domain_capacity, hash_with_capacity, HIDING_DOMAIN},
+};
+
+/// Public value slot of `foldedCommitment`.
+pub const FOLDED_COMMITMENT_SLOT: usize = 0;
+/// Public value slot of `pqCommitment`.
+pub const PQ_COMMITMENT_SLOT: usize = 1;
+
+/// Secret blinding factors; never published.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Blinding {
+    pub folded: Hash256,
+    pub pq: Hash256,
+}
+
+impl Blinding {
+    /// Fresh factors from the OS RNG.
+    pub fn random() -> Self {
+        let mut draw = || {
+            let mut bytes = [0u8; 32];
+            OsRng.fill_bytes(&mut bytes);
+            Hash256(bytes)
+        };
+        Self {
+            folded: draw(),
+            pq: draw(),
+        }
+    }
+
+    /// Folded then pq factor as circuit values.
+    pub fn fields(&self) -> [Fr; 2] {
+        [self.folded.to_field_reduced(), self.pq.to_field_reduced()]
+    }
+}
+
+/// The witness's `blinding`, which hiding circuits need.
+pub fn blinding(witness: &WitnessData) -> Result<&Blinding> {
+    witness.blinding.as_ref().ok_or_else(|| {
+        Coded::new(
+            ErrorCode::InvalidInput,
+            "hiding commitments need secret blinding factors in the witness",
+        )
+        .into()
+    })
+}
+
+/// Commitment to `rows` under `blinding`, matching the in-circuit hash over
+/// the factor and then the rows flattened row by row.
+pub fn hiding_commitment(blinding: Fr, rows: &[Vec<Fr>]) -> Fr {
+    let mut inputs = Vec::with_capacity(1 + rows.iter().map(Vec::len).sum::<usize>());
+    inputs.push(blinding);
+    inputs.extend(rows.iter().flatten().copied());
+    hash_with_capacity(domain_capacity(HIDING_DOMAIN, inputs.len()), &inputs)
+}
+
+/// `foldedCommitment` and `pqCommitment` of a blinded witness.
+pub fn hiding_commitments(witness: &WitnessData) -> Result<[Hash256; 2]> {
+    let [folded, pq] = blinding(witness)?.fields();
+    let (folded_rows, pq_rows) = witness.field_vectors()?;
+    Ok([
+        Hash256::from_field(&hiding_commitment(folded, &folded_rows)),
+        Hash256::from_field(&hiding_commitment(pq, &pq_rows)),
+    ])
+}
+
+/// Checks that `values` hold the hiding commitments of `folded` and `pq`.
+pub fn check_hiding_commitments(
+    blinding: &Blinding,
+    folded: &[Vec<Fr>],
+    pq: &[Vec<Fr>],
+    values: &[Fr],
+) -> Result<()> {
+    let [folded_blinding, pq_blinding] = blinding.fields();
+    for (name, slot, commitment) in [
+        (
+            "foldedCommitment",
+            FOLDED_COMMITMENT_SLOT,
+            hiding_commitment(folded_blinding, folded),
+        ),
+        (
+            "pqCommitment",
+            PQ_COMMITMENT_SLOT,
+            hiding_commitment(pq_blinding, pq),
+        ),
+    ] {
+        let value = values
+            .get(slot)
+            .with_context(|| format!("public values missing {name}"))?;
+        if *value != commitment {
+            anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!(
+                    "{name} does not match the blinded witness (expected {})",
+                    Hash256::from_field(&commitment)
+                )
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,368 @@
+//! Minimal blocking HTTP/1.1 plumbing for the daemons.
+//!
+//! Only what the verifier and prover services need: one request per
+//! connection, `Content-Length` bodies, and hard limits on every input. The
+//! client side, [`post_json`], is just as small and speaks plain `http://`.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::errors::ErrorReport;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_header_bytes: usize,
+    pub max_body_bytes: usize,
+    /// Longest wait for any single read.
+    pub read_timeout: Duration,
+    /// Deadline for a whole request, so a client trickling bytes cannot hold
+    /// a connection open read by read.
+    pub request_timeout: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_header_bytes: 8 * 1024,
+            max_body_bytes: 1024 * 1024,
+            read_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        let body = serde_json::to_vec(value).unwrap_or_else(|_| b"{}".to_vec());
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message.into() }))
+    }
+
+    /// [`Response::error`] with the catalogue code, hint and docs slug.
+    pub fn report(status: u16, report: &ErrorReport) -> Self {
+        Self::json(
+            status,
+            &serde_json::json!({
+                "error": report.message,
+                "code": report.code,
+                "hint": report.hint,
+                "docs": report.docs,
+            }),
+        )
+    }
+}
+
+/// Errors raised while reading a request, mapped to a status code.
+#[derive(Debug)]
+pub struct RequestError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl RequestError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Reads from a connection for at most `request_timeout` after `started`,
+/// shortening each read's socket timeout to the time left and failing with
+/// `TimedOut` once it is used up.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    started: Instant,
+    limits: &'a Limits,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self
+            .limits
+            .request_timeout
+            .saturating_sub(self.started.elapsed());
+        if left.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request deadline passed",
+            ));
+        }
+        self.stream
+            .set_read_timeout(Some(left.min(self.limits.read_timeout)))?;
+        self.stream.read(buf)
+    }
+}
+
+/// 408 for reads that timed out, 400 for anything else.
+fn read_error(err: io::Error, limits: &Limits) -> RequestError {
+    match err.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => RequestError::new(
+            408,
+            format!(
+                "request not received within {}s",
+                limits.request_timeout.as_secs()
+            ),
+        ),
+        _ => RequestError::new(400, err.to_string()),
+    }
+}
+
+pub fn read_request(stream: &TcpStream, limits: &Limits) -> Result<Request, RequestError> {
+    let deadline = DeadlineReader {
+        stream,
+        started: Instant::now(),
+        limits,
+    };
+    let mut reader = BufReader::new(deadline.take(limits.max_header_bytes as u64));
+
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|err| read_error(err, limits))?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(path), Some(version)) if version.starts_with("HTTP/1.") => {
+            (method.to_string(), path.to_string())
+        }
+        _ => return Err(RequestError::new(400, "malformed request line")),
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|err| read_error(err, limits))?;
+        if read == 0 {
+            return Err(RequestError::new(431, "request headers too large"));
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| RequestError::new(400, "malformed header"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.parse::<usize>())
+        .transpose()
+        .map_err(|_| RequestError::new(400, "invalid content-length"))?
+        .unwrap_or(0);
+    if content_length > limits.max_body_bytes {
+        return Err(RequestError::new(
+            413,
+            format!(
+                "body of {content_length} bytes exceeds limit of {}",
+                limits.max_body_bytes
+            ),
+        ));
+    }
+
+    // Headers may have buffered part of the body; reuse it before reading the rest.
+    let mut body = reader.buffer().to_vec();
+    let mut inner = reader.into_inner().into_inner();
+    if body.len() > content_length {
+        return Err(RequestError::new(400, "body longer than content-length"));
+    }
+    let remaining = content_length - body.len();
+    (&mut inner)
+        .take(remaining as u64)
+        .read_to_end(&mut body)
+        .map_err(|err| read_error(err, limits))?;
+    if body.len() != content_length {
+        return Err(RequestError::new(400, "truncated body"));
+    }
+
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+pub fn write_response(mut stream: &TcpStream, response: &Response) -> Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// POSTs `body` as JSON to an `http://` URL and returns the response body,
+/// failing on a non-2xx status. The response is held to `limits`.
+pub fn post_json(url: &str, body: &[u8], limits: &Limits) -> Result<Vec<u8>> {
+    let rest = url
+        .strip_prefix("http://")
+        .with_context(|| format!("{url:?} is not an http:// URL"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{authority}:80")
+    };
+    let mut stream =
+        TcpStream::connect(&address).with_context(|| format!("connecting to {address}"))?;
+    stream.set_read_timeout(Some(limits.read_timeout))?;
+    stream.set_write_timeout(Some(limits.read_timeout))?;
+    let head = format!(
+        "POST {path} HTTP/1.1\r\nHost: {authority}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+
+    let max = limits.max_header_bytes + limits.max_body_bytes;
+    let mut response = Vec::new();
+    (&mut stream)
+        .take(max as u64 + 1)
+        .read_to_end(&mut response)
+        .with_context(|| format!("reading the response from {url}"))?;
+    if response.len() > max {
+        anyhow::bail!("response from {url} exceeds {max} bytes");
+    }
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .with_context(|| format!("malformed response from {url}"))?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .with_context(|| format!("malformed status line from {url}"))?;
+    if head.lines().any(|line| {
+        line.to_ascii_lowercase()
+            .starts_with("transfer-encoding: chunked")
+    }) {
+        anyhow::bail!("{url} sent a chunked response, which is not supported");
+    }
+    let body = response[split + 4..].to_vec();
+    if !(200..300).contains(&status) {
+        anyhow::bail!(
+            "{url} answered {status}: {}",
+            String::from_utf8_lossy(&body).trim()
+        );
+    }
+    Ok(body)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+
+    #[test]
+    fn trickling_client_gets_408_at_the_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            // Each byte arrives well within the read timeout.
+            for byte in b"POST /verify HTTP/1.1\r\nContent-Length: 2\r\n" {
+                if stream.write_all(&[*byte]).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let limits = Limits {
+            read_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_millis(300),
+            ..Limits::default()
+        };
+        let started = Instant::now();
+        let err = read_request(&stream, &limits).unwrap_err();
+        assert_eq!(err.status, 408, "{}", err.message);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        drop(stream);
+        client.join().unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+//! SQLite index of proven blocks and where their artifacts live.
+//!
+//! The index records, per proof, the block height, the storage keys of the
+//! proof, its public inputs and metadata sidecar, the proof digest and the
+//! circuit version and key fingerprint it was made with. It is a cache over
+//! the artifact store, never the source of truth: [`ProofIndex::check`]
+//! reports entries whose artifacts have gone missing or changed.
+//!
+//! Copies made by [`crate::replication`] hooks are recorded per proof as
+//! [`Replica`]s, so a lost region can be restored from the locations here.
+
+use std::{
+    ops::RangeInclusive,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::{
+    bytes::Hash256, compat::LEGACY_VERSION, hashing::digest, metadata::ProofMetadata,
+    platform::from_json_slice, public_inputs::load_public_inputs_from, replication::Replica,
+    storage::Storage,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS proofs (
+    proof_key TEXT PRIMARY KEY,
+    height INTEGER NOT NULL,
+    public_inputs_key TEXT NOT NULL,
+    metadata_key TEXT,
+    proof_digest TEXT NOT NULL,
+    circuit_version INTEGER NOT NULL,
+    key_fingerprint TEXT,
+    indexed_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS proofs_height ON proofs (height);
+CREATE TABLE IF NOT EXISTS replicas (
+    proof_key TEXT NOT NULL,
+    target TEXT NOT NULL,
+    artifact_key TEXT NOT NULL,
+    location TEXT NOT NULL,
+    replicated_at INTEGER NOT NULL,
+    PRIMARY KEY (proof_key, target, artifact_key)
+);
+";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexEntry {
+    pub height: u64,
+    pub proof_key: String,
+    pub public_inputs_key: String,
+    /// Key of the `.meta.json` sidecar, when the proof had one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_key: Option<String>,
+    /// blake3 of the proof bytes.
+    pub proof_digest: Hash256,
+    /// From the sidecar; [`LEGACY_VERSION`] without one.
+    pub circuit_version: u32,
+    /// Fingerprint of the key config the proof verifies under.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_fingerprint: Option<String>,
+    /// Unix seconds the entry was recorded.
+    pub indexed_at: u64,
+}
+
+impl IndexEntry {
+    /// Reads the artifacts at `proof_key` and `public_inputs_key` (and the
+    /// proof's sidecar, if present) into an entry.
+    pub fn from_artifacts(
+        storage: &dyn Storage,
+        proof_key: &str,
+        public_inputs_key: &str,
+        key_fingerprint: Option<String>,
+    ) -> Result<Self> {
+        let proof = storage.read(proof_key)?;
+        let public_inputs = load_public_inputs_from(storage, public_inputs_key)?;
+        let metadata_key = format!("{proof_key}.meta.json");
+        let (metadata_key, circuit_version) = if storage.exists(&metadata_key)? {
+            let bytes = storage.read(&metadata_key)?;
+            let metadata: ProofMetadata =
+                from_json_slice(&bytes).with_context(|| format!("parsing {metadata_key:?}"))?;
+            (Some(metadata_key), metadata.into_latest().circuit_version)
+        } else {
+            (None, LEGACY_VERSION)
+        };
+        Ok(IndexEntry {
+            height: public_inputs.block_height,
+            proof_key: proof_key.to_string(),
+            public_inputs_key: public_inputs_key.to_string(),
+            metadata_key,
+            proof_digest: digest(&proof),
+            circuit_version,
+            key_fingerprint,
+            indexed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// An index entry that no longer matches the artifact store.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Inconsistency {
+    pub proof_key: String,
+    pub problem: String,
+}
+
+pub struct ProofIndex {
+    conn: Connection,
+}
+
+impl ProofIndex {
+    /// Opens the index at `path`, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).with_context(|| format!("opening {:?}", path))?;
+        conn.execute_batch(SCHEMA)
+            .context("creating the proof index schema")?;
+        Ok(ProofIndex { conn })
+    }
+
+    /// Records `entry`, replacing any entry for the same proof key.
+    pub fn record(&self, entry: &IndexEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO proofs (proof_key, height, public_inputs_key, metadata_key, \
+             proof_digest, circuit_version, key_fingerprint, indexed_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                entry.proof_key,
+                entry.height as i64,
+                entry.public_inputs_key,
+                entry.metadata_key,
+                entry.proof_digest.to_hex(),
+                entry.circuit_version,
+                entry.key_fingerprint,
+                entry.indexed_at as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove(&self, proof_key: &str) -> Result<bool> {
+        self.conn.execute(
+            "DELETE FROM replicas WHERE proof_key = ?1",
+            params![proof_key],
+        )?;
+        let removed = self.conn.execute(
+            "DELETE FROM proofs WHERE proof_key = ?1",
+            params![proof_key],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Records where artifacts of the proof at `proof_key` were replicated,
+    /// replacing earlier copies of the same artifact at the same target.
+    pub fn record_replicas(&self, proof_key: &str, replicas: &[Replica]) -> Result<()> {
+        for replica in replicas {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO replicas (proof_key, target, artifact_key, location, \
+                 replicated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    proof_key,
+                    replica.target,
+                    replica.artifact_key,
+                    replica.location,
+                    replica.replicated_at as i64,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Replicas of the proof at `proof_key`, by target then artifact key.
+    pub fn replicas(&self, proof_key: &str) -> Result<Vec<Replica>> {
+        let mut statement = self.conn.prepare(
+            "SELECT target, artifact_key, location, replicated_at FROM replicas \
+             WHERE proof_key = ?1 ORDER BY target, artifact_key",
+        )?;
+        let rows = statement.query_map(params![proof_key], |row| {
+            let replicated_at: i64 = row.get(3)?;
+            Ok(Replica {
+                target: row.get(0)?,
+                artifact_key: row.get(1)?,
+                location: row.get(2)?,
+                replicated_at: replicated_at as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn get(&self, proof_key: &str) -> Result<Option<IndexEntry>> {
+        self.conn
+            .query_row(
+                &format!("{SELECT} WHERE proof_key = ?1"),
+                params![proof_key],
+                read_row,
+            )
+            .optional()?
+            .transpose()
+    }
+
+    /// Entries with heights in `heights`, by height then proof key.
+    pub fn query(&self, heights: RangeInclusive<u64>) -> Result<Vec<IndexEntry>> {
+        let mut statement = self.conn.prepare(&format!(
+            "{SELECT} WHERE height BETWEEN ?1 AND ?2 ORDER BY height, proof_key"
+        ))?;
+        let start = i64::try_from(*heights.start()).unwrap_or(i64::MAX);
+        let end = i64::try_from(*heights.end()).unwrap_or(i64::MAX);
+        let rows = statement.query_map(params![start, end], read_row)?;
+        rows.map(|row| row?).collect()
+    }
+
+    /// Checks every entry against `storage`: the proof, public inputs and
+    /// sidecar must still exist, the proof must still hash to the recorded
+    /// digest and the public inputs must still be for the recorded height.
+    pub fn check(&self, storage: &dyn Storage) -> Result<Vec<Inconsistency>> {
+        let mut problems = Vec::new();
+        for entry in self.query(0..=u64::MAX)? {
+            let problem = |problem: String| Inconsistency {
+                proof_key: entry.proof_key.clone(),
+                problem,
+            };
+            if !storage.exists(&entry.proof_key)? {
+                problems.push(problem("proof is missing".to_string()));
+                continue;
+            }
+            let digest = digest(&storage.read(&entry.proof_key)?);
+            if digest != entry.proof_digest {
+                problems.push(problem(format!(
+                    "proof digest is {digest}, index recorded {}",
+                    entry.proof_digest
+                )));
+            }
+            match load_public_inputs_from(storage, &entry.public_inputs_key) {
+                Ok(public_inputs) if public_inputs.block_height != entry.height => {
+                    problems.push(problem(format!(
+                        "public inputs are for block {}, index recorded {}",
+                        public_inputs.block_height, entry.height
+                    )))
+                }
+                Ok(_) => {}
+                Err(err) => problems.push(problem(format!(
+                    "public inputs {:?} unreadable: {err:#}",
+                    entry.public_inputs_key
+                ))),
+            }
+            if let Some(metadata_key) = &entry.metadata_key {
+                if !storage.exists(metadata_key)? {
+                    problems.push(problem(format!("metadata {metadata_key:?} is missing")));
+                }
+            }
+        }
+        Ok(problems)
+    }
+}
+
+const SELECT: &str = "SELECT proof_key, height, public_inputs_key, metadata_key, proof_digest, \
+                      circuit_version, key_fingerprint, indexed_at FROM proofs";
+
+/// A row of [`SELECT`]; the digest parse error surfaces as the inner result.
+fn read_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Result<IndexEntry>> {
+    let digest: String = row.get(4)?;
+    let height: i64 = row.get(1)?;
+    let indexed_at: i64 = row.get(7)?;
+    let entry = IndexEntry {
+        proof_key: row.get(0)?,
+        height: height as u64,
+        public_inputs_key: row.get(2)?,
+        metadata_key: row.get(3)?,
+        proof_digest: Hash256::ZERO,
+        circuit_version: row.get(5)?,
+        key_fingerprint: row.get(6)?,
+        indexed_at: indexed_at as u64,
+    };
+    Ok(Hash256::from_hex(&digest).map(|proof_digest| IndexEntry {
+        proof_digest,
+        ..entry
+    }))
+}
@@ -0,0 +1,250 @@
+//! Labelled view of a block's instance values, for integrators checking which
+//! public input lands in which instance slot and how it was mapped to `Fr`.
+
+use std::fmt;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    bytes::Hash256,
+    circuit::FoldedParams,
+    compat::CIRCUIT_VERSION,
+    public_inputs::{field_to_hex, ParsedPublicInputs},
+};
+
+/// How a public input becomes its field element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SlotEncoding {
+    /// A ChaCha20 draw seeded with `blake3(bytes)`.
+    Blake3Chacha20,
+    /// The bytes read big-endian as a field element below the modulus.
+    Canonical,
+    /// The bytes read big-endian and reduced mod p.
+    Reduced,
+    /// An unsigned integer.
+    Integer,
+    /// Poseidon commitment to the fixed-point epsilon bounds.
+    EpsilonCommitment,
+    /// The squared fixed-point bound, `floor(value * S)^2`.
+    SquaredBound,
+}
+
+impl fmt::Display for SlotEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SlotEncoding::Blake3Chacha20 => "blake3-chacha20",
+            SlotEncoding::Canonical => "canonical",
+            SlotEncoding::Reduced => "reduced",
+            SlotEncoding::Integer => "integer",
+            SlotEncoding::EpsilonCommitment => "epsilon-commitment",
+            SlotEncoding::SquaredBound => "squared-bound",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedSlot {
+    pub slot: usize,
+    /// Public-inputs field the value comes from.
+    pub name: String,
+    pub source: String,
+    pub encoding: SlotEncoding,
+    /// The field element, as 32-byte big-endian hex.
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceReport {
+    /// Circuit version whose slot layout and encodings these are.
+    pub circuit_version: u32,
+    pub slots: Vec<InspectedSlot>,
+    /// The only instance in `instanceHash` mode: `sha256` over the slot
+    /// values above.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_hash: Option<String>,
+}
+
+/// Labels every public value of `public_inputs` under `params`.
+pub fn inspect_instances(
+    public_inputs: &ParsedPublicInputs,
+    params: &FoldedParams,
+) -> Result<InstanceReport> {
+    let values = public_inputs.public_values(params)?;
+    let labels = slot_labels(public_inputs, params);
+    debug_assert_eq!(labels.len(), values.len());
+    let slots = labels
+        .into_iter()
+        .zip(&values)
+        .enumerate()
+        .map(|(slot, ((name, source, encoding), value))| InspectedSlot {
+            slot,
+            name: name.to_string(),
+            source,
+            encoding,
+            value: field_to_hex(value),
+        })
+        .collect();
+    let instance_hash = params
+        .instance_hash
+        .then(|| field_to_hex(&params.instances(values)[0]));
+    Ok(InstanceReport {
+        circuit_version: CIRCUIT_VERSION,
+        slots,
+        instance_hash,
+    })
+}
+
+/// Name, source and encoding of each public value, in the slot order of
+/// [`ParsedPublicInputs::public_values`].
+fn slot_labels(
+    public_inputs: &ParsedPublicInputs,
+    params: &FoldedParams,
+) -> Vec<(&'static str, String, SlotEncoding)> {
+    let hex = |value: Option<Hash256>| value.map(|v| v.to_hex()).unwrap_or_default();
+    let commitment_encoding = if params.hiding || params.vector_commitments {
+        SlotEncoding::Canonical
+    } else {
+        SlotEncoding::Blake3Chacha20
+    };
+    let mut labels = vec![
+        (
+            "foldedCommitment",
+            public_inputs.folded_commitment.to_hex(),
+            commitment_encoding,
+        ),
+        (
+            "pqCommitment",
+            public_inputs.pq_commitment.to_hex(),
+            commitment_encoding,
+        ),
+        (
+            "codebookRoot",
+            public_inputs.codebook_root.to_hex(),
+            if params.codebook_commitment.is_some() {
+                SlotEncoding::Canonical
+            } else {
+                SlotEncoding::Blake3Chacha20
+            },
+        ),
+    ];
+    if params.vector_root {
+        labels.push((
+            "foldedVectorRoot",
+            hex(public_inputs.folded_vector_root),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.pq_codes {
+        labels.push((
+            "pqCodesCommitment",
+            hex(public_inputs.pq_codes_commitment),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.lineage {
+        labels.push((
+            "previousProofDigest",
+            hex(public_inputs.previous_proof_digest),
+            SlotEncoding::Reduced,
+        ));
+    }
+    if params.beacon {
+        labels.push((
+            "beaconValue",
+            hex(public_inputs.beacon_value),
+            SlotEncoding::Reduced,
+        ));
+    }
+    if params.nonzeros.is_some() {
+        labels.push((
+            "sparsityCommitment",
+            hex(public_inputs.sparsity_commitment),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.compression_stats {
+        let stats = public_inputs.compression_stats;
+        labels.push((
+            "compressionStats.originalBytes",
+            stats
+                .map(|s| s.original_bytes.to_string())
+                .unwrap_or_default(),
+            SlotEncoding::Integer,
+        ));
+        labels.push((
+            "compressionStats.pqBytes",
+            stats.map(|s| s.pq_bytes.to_string()).unwrap_or_default(),
+            SlotEncoding::Integer,
+        ));
+    }
+    if params.delta.is_some() {
+        labels.push((
+            "previousVectorRoot",
+            hex(public_inputs.previous_vector_root),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.witness_commitment {
+        labels.push((
+            "witnessCommitment",
+            hex(public_inputs.witness_commitment),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.rotation {
+        labels.push((
+            "rotationCommitment",
+            hex(public_inputs.rotation_commitment),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.residual_centroids.is_some() {
+        labels.push((
+            "residualCodebookRoot",
+            hex(public_inputs.residual_codebook_root),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.scalar {
+        labels.push((
+            "scalarCommitment",
+            hex(public_inputs.scalar_commitment),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.row_permutation {
+        labels.push((
+            "permutationCommitment",
+            hex(public_inputs.permutation_commitment),
+            SlotEncoding::Canonical,
+        ));
+    }
+    if params.subvector_epsilons {
+        let epsilons = public_inputs.subvector_epsilons.as_deref().unwrap_or(&[]);
+        labels.push((
+            "subvectorEpsilons",
+            serde_json::to_string(epsilons).unwrap_or_default(),
+            SlotEncoding::EpsilonCommitment,
+        ));
+    }
+    if params.sla_bound {
+        labels.push((
+            "slaBound",
+            public_inputs
+                .sla_bound
+                .map(|sla| sla.to_string())
+                .unwrap_or_default(),
+            SlotEncoding::SquaredBound,
+        ));
+    }
+    labels.push((
+        "residualCommitment",
+        hex(public_inputs.residual_commitment),
+        SlotEncoding::Canonical,
+    ));
+    labels
+}
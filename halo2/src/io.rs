@@ -1,21 +1,609 @@
-use std::{fs::File, io::BufReader, path::Path};
+use std::{fmt, fs, io, path::Path, str::FromStr};
 
-use anyhow::Result;
-use serde::Deserialize;
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use serde::{
+    de::{DeserializeSeed, Error as _, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use zeroize::Zeroize;
 
-#[derive(Debug, Deserialize)]
+use crate::{
+    bytes::Hash256,
+    delta::DeltaSection,
+    encryption::{is_envelope, key_provider_from_env, open, KeyProvider, KEY_ENV, KEY_FILE_ENV},
+    errors::{Coded, ErrorCode},
+    fixed_point::{resolve_scale, FixedPoint},
+    hiding::Blinding,
+    permutation::RowPermutation,
+    platform::{from_json_slice, normalize, strip_bom},
+    quantization::ResidualStage,
+    scalar::ScalarQuantization,
+    shape::WitnessShape,
+    sparse::SparseVectors,
+    storage::Storage,
+};
+
+/// A block's private witness. `Debug` prints only its shape, and
+/// [`Zeroize`] clears the vectors, codes and secrets once a job is done
+/// with them.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct WitnessData {
-    #[serde(rename = "foldedVectors")]
+    #[serde(rename = "foldedVectors", default)]
     pub folded_vectors: Vec<Vec<f64>>,
-    #[serde(rename = "pqVectors")]
+    #[serde(rename = "pqVectors", default)]
     pub pq_vectors: Vec<Vec<f64>>,
     #[serde(rename = "headerRlp")]
     pub header_rlp: Option<String>,
+    /// Per-vector centroid index for each PQ subspace.
+    #[serde(rename = "pqCodes", default, skip_serializing_if = "Option::is_none")]
+    pub pq_codes: Option<Vec<Vec<u32>>>,
+    /// PQ codebook as `codebook[subspace][centroid][component]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codebook: Option<Vec<Vec<Vec<f64>>>>,
+    /// Sparse encoding of the folded and pq vectors; when the dense fields
+    /// are omitted they are expanded from it on load.
+    #[serde(
+        rename = "sparseVectors",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sparse_vectors: Option<SparseVectors>,
+    /// Where the witness came from; copied into the proof metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Set on delta witnesses, whose vectors are only the rows changed since
+    /// the previous block; see [`crate::delta`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<DeltaSection>,
+    /// Secret the row salts of `witnessCommitment` derive from; see
+    /// [`crate::witness_commitment`]. Never published.
+    #[serde(rename = "auditSalt", default, skip_serializing_if = "Option::is_none")]
+    pub audit_salt: Option<Hash256>,
+    /// Squared fixed-point residual of each vector as the witness generator
+    /// computed it, `sum_j (floor(folded_j * S) - floor(pq_j * S))^2` with
+    /// `S` the witness's `scale`. The prover takes these as given and the
+    /// circuit confirms them; it no longer derives them itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub residuals: Option<Vec<u128>>,
+    /// Fixed-point scale `S` the vectors are converted at; absent means
+    /// [`crate::fixed_point::DEFAULT_SCALE`]. Keys prove only witnesses at
+    /// their own scale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u64>,
+    /// OPQ rotation applied before quantization, `rotation[row][column]`;
+    /// `foldedVectors` are then already rotated. See [`crate::opq`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<Vec<Vec<f64>>>,
+    /// Second quantization stage; `pqVectors` then hold the sum of both
+    /// stages' centroids. See [`crate::quantization::ResidualStage`].
+    #[serde(
+        rename = "residualStage",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub residual_stage: Option<ResidualStage>,
+    /// Int8 codes that `pqVectors` dequantize from, instead of PQ codes.
+    /// See [`crate::scalar`].
+    #[serde(
+        rename = "scalarQuantization",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scalar_quantization: Option<ScalarQuantization>,
+    /// Secret salt the committed row order derives from; roots then commit
+    /// the rows shuffled. See [`crate::permutation`]. Never published.
+    #[serde(
+        rename = "rowPermutation",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub row_permutation: Option<RowPermutation>,
+    /// Secret blinding factors of the hiding `foldedCommitment` and
+    /// `pqCommitment`; see [`crate::hiding`]. Never published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blinding: Option<Blinding>,
+}
+
+impl fmt::Debug for WitnessData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WitnessData")
+            .field("vectors", &self.folded_vectors.len())
+            .field("dim", &self.folded_vectors.first().map_or(0, Vec::len))
+            .field("provenance", &self.provenance)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Zeroize for WitnessData {
+    fn zeroize(&mut self) {
+        self.folded_vectors.zeroize();
+        self.pq_vectors.zeroize();
+        self.pq_codes.zeroize();
+        self.codebook.zeroize();
+        if let Some(sparse) = &mut self.sparse_vectors {
+            for row in &mut sparse.rows {
+                row.indices.zeroize();
+                row.folded.zeroize();
+                row.pq.zeroize();
+            }
+        }
+        if let Some(salt) = &mut self.audit_salt {
+            salt.0.zeroize();
+        }
+        self.residuals.zeroize();
+        self.rotation.zeroize();
+        if let Some(stage) = &mut self.residual_stage {
+            stage.codes.zeroize();
+            stage.codebook.zeroize();
+        }
+        if let Some(scalar) = &mut self.scalar_quantization {
+            scalar.codes.zeroize();
+        }
+        if let Some(permutation) = &mut self.row_permutation {
+            permutation.salt.0.zeroize();
+        }
+        if let Some(blinding) = &mut self.blinding {
+            blinding.folded.0.zeroize();
+            blinding.pq.0.zeroize();
+        }
+    }
 }
 
+/// Which generator, embedding model and codebook produced a witness, so a
+/// proven block can be traced back to them. Every field is free-form.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codebook_id: Option<String>,
+}
+
+impl WitnessData {
+    /// Fills the dense vectors from `sparse_vectors` when only the sparse
+    /// encoding was supplied.
+    pub fn expand_sparse(&mut self) -> Result<()> {
+        let Some(sparse) = &self.sparse_vectors else {
+            return Ok(());
+        };
+        sparse.validate()?;
+        if self.folded_vectors.is_empty() && self.pq_vectors.is_empty() {
+            let (folded, pq) = sparse.to_dense();
+            self.folded_vectors = folded;
+            self.pq_vectors = pq;
+        }
+        Ok(())
+    }
+
+    /// The conversion at the witness's `scale`.
+    pub fn fixed_point(&self) -> Result<FixedPoint> {
+        resolve_scale(None, self.scale)
+    }
+
+    /// `foldedVectors` and `pqVectors` as field elements at the witness's
+    /// `scale`, for commitments computed outside the circuit.
+    pub fn field_vectors(&self) -> Result<(Vec<Vec<Fr>>, Vec<Vec<Fr>>)> {
+        let fixed_point = self.fixed_point()?;
+        Ok((
+            fixed_point
+                .to_field_matrix(&self.folded_vectors)
+                .context("foldedVectors")?,
+            fixed_point
+                .to_field_matrix(&self.pq_vectors)
+                .context("pqVectors")?,
+        ))
+    }
+}
+
+/// CBOR self-describe tag (55799), written ahead of CBOR witnesses.
+const CBOR_MAGIC: &[u8] = b"\xD9\xD9\xF7";
+
+/// Encoding of a witness file. JSON is the interchange format; CBOR stores
+/// each `f64` in at most 9 bytes and parses several times faster, for large
+/// blocks. Loaders detect the encoding, see [`WitnessFormat::detect`].
+///
+/// bincode is not offered: it is not self-describing, so it cannot carry the
+/// optional fields a witness omits when they are unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WitnessFormat {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl WitnessFormat {
+    /// CBOR when `bytes` start with the self-describe tag or a CBOR map
+    /// header, which no JSON document does; JSON otherwise.
+    pub fn detect(bytes: &[u8]) -> Self {
+        match bytes.first() {
+            _ if bytes.starts_with(CBOR_MAGIC) => WitnessFormat::Cbor,
+            Some(0xA0..=0xBB | 0xBF) => WitnessFormat::Cbor,
+            _ => WitnessFormat::Json,
+        }
+    }
+}
+
+impl fmt::Display for WitnessFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessFormat::Json => f.write_str("json"),
+            WitnessFormat::Cbor => f.write_str("cbor"),
+        }
+    }
+}
+
+impl FromStr for WitnessFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "json" => Ok(WitnessFormat::Json),
+            "cbor" => Ok(WitnessFormat::Cbor),
+            other => anyhow::bail!("unknown witness format {other:?} (json or cbor)"),
+        }
+    }
+}
+
+/// Serializes `witness` in `format`; JSON is compact.
+pub fn encode_witness(witness: &WitnessData, format: WitnessFormat) -> Result<Vec<u8>> {
+    match format {
+        WitnessFormat::Json => Ok(serde_json::to_vec(witness)?),
+        WitnessFormat::Cbor => {
+            let mut out = CBOR_MAGIC.to_vec();
+            ciborium::into_writer(witness, &mut out).context("encoding witness as CBOR")?;
+            Ok(out)
+        }
+    }
+}
+
+/// Loads a witness, decrypting it with the key provider from the environment
+/// when the file is an encryption envelope.
 pub fn load_witness<P: AsRef<Path>>(path: P) -> Result<WitnessData> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let witness = serde_json::from_reader(reader)?;
+    let path = normalize(path.as_ref());
+    let bytes = fs::read(&path).with_context(|| format!("opening {path:?}"))?;
+    decode_witness(&bytes, None, &format!("{path:?}"))
+}
+
+pub fn load_witness_from(storage: &dyn Storage, key: &str) -> Result<WitnessData> {
+    load_witness_with(storage, key, None)
+}
+
+/// [`load_witness_from`] with an explicit key provider for encrypted witnesses.
+pub fn load_witness_with(
+    storage: &dyn Storage,
+    key: &str,
+    provider: Option<&dyn KeyProvider>,
+) -> Result<WitnessData> {
+    let bytes = storage.read(key)?;
+    decode_witness(&bytes, provider, key)
+}
+
+/// Parses witness bytes already fetched from `source`, decrypting envelopes.
+/// The plain witness may be JSON or CBOR.
+pub fn decode_witness(
+    bytes: &[u8],
+    provider: Option<&dyn KeyProvider>,
+    source: &str,
+) -> Result<WitnessData> {
+    let bytes = strip_bom(bytes);
+    if !is_envelope(bytes) {
+        return parse_witness(bytes).with_context(|| format!("parsing witness {source}"));
+    }
+    let plain = match provider {
+        Some(provider) => open(bytes, provider)?,
+        None => {
+            let provider = key_provider_from_env()?.with_context(|| {
+                format!("witness {source} is encrypted; set {KEY_ENV} or {KEY_FILE_ENV}")
+            })?;
+            open(bytes, provider.as_ref())?
+        }
+    };
+    parse_witness(&plain).with_context(|| format!("parsing decrypted witness {source}"))
+}
+
+fn parse_witness(bytes: &[u8]) -> Result<WitnessData> {
+    let mut witness: WitnessData = match WitnessFormat::detect(bytes) {
+        WitnessFormat::Json => from_json_slice(bytes)?,
+        WitnessFormat::Cbor => {
+            ciborium::from_reader(bytes.strip_prefix(CBOR_MAGIC).unwrap_or(bytes))?
+        }
+    };
+    witness.expand_sparse()?;
     Ok(witness)
 }
+
+/// Which of a witness's dense vector sets a streamed row belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorSet {
+    Folded,
+    Pq,
+}
+
+/// A witness whose dense vectors were converted to field elements while
+/// parsing, see [`load_field_witness`].
+pub struct FieldWitness {
+    /// Everything but the dense vectors, which are left empty.
+    pub witness: WitnessData,
+    /// The conversion the rows were made with.
+    pub fixed_point: FixedPoint,
+    pub folded: Vec<Vec<Fr>>,
+    pub pq: Vec<Vec<Fr>>,
+}
+
+impl FieldWitness {
+    /// Converts the dense vectors of a witness loaded whole, dropping the
+    /// `f64` rows.
+    pub fn from_witness(mut witness: WitnessData, fixed_point: FixedPoint) -> Result<Self> {
+        check_scale(&witness, fixed_point)?;
+        let folded = fixed_point
+            .to_field_matrix(&std::mem::take(&mut witness.folded_vectors))
+            .context("foldedVectors")?;
+        let pq = fixed_point
+            .to_field_matrix(&std::mem::take(&mut witness.pq_vectors))
+            .context("pqVectors")?;
+        Ok(Self {
+            witness,
+            fixed_point,
+            folded,
+            pq,
+        })
+    }
+
+    pub fn shape(&self) -> Result<WitnessShape> {
+        WitnessShape::of_rows(&self.folded, &self.pq)
+    }
+}
+
+/// Loads a witness as field elements at `resolve_scale(requested, scale)`,
+/// streaming plain JSON with [`stream_witness`]: peak memory is the field
+/// matrices plus one row, where [`load_witness`] also holds the file and
+/// its `f64` matrices. A witness declaring a scale other than the one first
+/// assumed is read a second time at its own scale. Encrypted and CBOR
+/// witnesses are loaded whole and converted.
+pub fn load_field_witness<P: AsRef<Path>>(path: P, requested: Option<u64>) -> Result<FieldWitness> {
+    let path = normalize(path.as_ref());
+    let reader_at_start = || -> Result<io::BufReader<fs::File>> {
+        let file = fs::File::open(&path).with_context(|| format!("opening {path:?}"))?;
+        Ok(io::BufReader::new(file))
+    };
+    let mut reader = reader_at_start()?;
+    if !is_streamable(strip_bom(reader.fill_buf()?)) {
+        let witness = load_witness(&path)?;
+        let fixed_point = resolve_scale(requested, witness.scale)?;
+        return FieldWitness::from_witness(witness, fixed_point);
+    }
+    let fixed_point = resolve_scale(requested, None)?;
+    let streamed =
+        collect_rows(reader, fixed_point).with_context(|| format!("parsing witness {path:?}"))?;
+    let wanted = resolve_scale(requested, streamed.witness.scale)?;
+    if wanted == fixed_point {
+        return Ok(streamed);
+    }
+    drop(streamed);
+    collect_rows(reader_at_start()?, wanted).with_context(|| format!("parsing witness {path:?}"))
+}
+
+/// Streams `reader` at `fixed_point`, collecting the converted rows; the
+/// caller checks the witness's `scale`.
+fn collect_rows<R: io::BufRead>(reader: R, fixed_point: FixedPoint) -> Result<FieldWitness> {
+    let (mut folded, mut pq) = (Vec::new(), Vec::new());
+    let witness = stream_rows(reader, fixed_point, &mut |set, row| {
+        match set {
+            VectorSet::Folded => folded.push(row),
+            VectorSet::Pq => pq.push(row),
+        }
+        Ok(())
+    })?;
+    Ok(FieldWitness {
+        witness,
+        fixed_point,
+        folded,
+        pq,
+    })
+}
+
+/// Parses a plain JSON witness from `reader`, handing every row of
+/// `foldedVectors` and `pqVectors` to `on_row` as field elements (see
+/// [`FixedPoint::to_field`], which rejects values out of range) as soon as
+/// it is read; only one row of `f64`s is held at a time. Returns the rest
+/// of the witness with the dense vectors empty.
+/// Rows are converted at `fixed_point` before the witness's `scale` may be
+/// read, so a witness declaring another scale is rejected once parsed.
+/// A witness given only as `sparseVectors` is expanded and its rows handed
+/// over the same way.
+///
+/// Encrypted and CBOR witnesses are rejected; load those with
+/// [`load_witness`].
+pub fn stream_witness<R, F>(
+    reader: R,
+    fixed_point: FixedPoint,
+    mut on_row: F,
+) -> Result<WitnessData>
+where
+    R: io::BufRead,
+    F: FnMut(VectorSet, Vec<Fr>) -> Result<()>,
+{
+    let witness = stream_rows(reader, fixed_point, &mut on_row)?;
+    check_scale(&witness, fixed_point)?;
+    Ok(witness)
+}
+
+/// Rejects a witness declaring a scale other than `fixed_point`'s.
+fn check_scale(witness: &WitnessData, fixed_point: FixedPoint) -> Result<()> {
+    if let Some(scale) = witness.scale.filter(|scale| *scale != fixed_point.scale()) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Incompatible,
+            format!(
+                "witness is at fixed-point scale {scale}, it was converted at {}",
+                fixed_point.scale()
+            )
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a witness starting with `head` is plain JSON.
+fn is_streamable(head: &[u8]) -> bool {
+    !is_envelope(head) && WitnessFormat::detect(head) == WitnessFormat::Json
+}
+
+/// [`stream_witness`] without checking the witness's `scale`.
+fn stream_rows<R: io::BufRead>(
+    mut reader: R,
+    fixed_point: FixedPoint,
+    mut on_row: &mut dyn FnMut(VectorSet, Vec<Fr>) -> Result<()>,
+) -> Result<WitnessData> {
+    let head = reader.fill_buf()?;
+    let bom = head.len() - strip_bom(head).len();
+    if !is_streamable(&head[bom..]) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            "only plain JSON witnesses can be streamed; load encrypted or CBOR witnesses whole"
+        ));
+    }
+    reader.consume(bom);
+
+    let mut failure = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let parsed = StreamSeed {
+        fixed_point,
+        on_row: &mut on_row,
+        failure: &mut failure,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|witness| deserializer.end().map(|()| witness));
+    let mut witness = match (parsed, failure) {
+        (_, Some(err)) => return Err(err),
+        (parsed, None) => parsed?,
+    };
+    if witness.sparse_vectors.is_some() {
+        witness.expand_sparse()?;
+        for (set, rows) in [
+            (
+                VectorSet::Folded,
+                std::mem::take(&mut witness.folded_vectors),
+            ),
+            (VectorSet::Pq, std::mem::take(&mut witness.pq_vectors)),
+        ] {
+            for row in rows {
+                on_row(set, to_field_row(fixed_point, &row)?)?;
+            }
+        }
+    }
+    Ok(witness)
+}
+
+/// Deserializes a witness object, routing the dense vectors to `on_row`.
+/// An error from `on_row` is kept in `failure` so its code survives serde.
+struct StreamSeed<'a, F> {
+    fixed_point: FixedPoint,
+    on_row: &'a mut F,
+    failure: &'a mut Option<anyhow::Error>,
+}
+
+impl<'de, F> DeserializeSeed<'de> for StreamSeed<'_, F>
+where
+    F: FnMut(VectorSet, Vec<Fr>) -> Result<()>,
+{
+    type Value = WitnessData;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<WitnessData, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F> Visitor<'de> for StreamSeed<'_, F>
+where
+    F: FnMut(VectorSet, Vec<Fr>) -> Result<()>,
+{
+    type Value = WitnessData;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a witness object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<WitnessData, A::Error> {
+        let mut rest = serde_json::Map::new();
+        // Kept out of `rest`: a JSON value would round residuals above u64.
+        let mut residuals = None;
+        while let Some(key) = map.next_key::<String>()? {
+            let set = match key.as_str() {
+                "foldedVectors" => VectorSet::Folded,
+                "pqVectors" => VectorSet::Pq,
+                "residuals" => {
+                    residuals = map.next_value::<Option<Vec<u128>>>()?;
+                    continue;
+                }
+                _ => {
+                    rest.insert(key, map.next_value()?);
+                    continue;
+                }
+            };
+            map.next_value_seed(RowsSeed {
+                set,
+                fixed_point: self.fixed_point,
+                on_row: &mut *self.on_row,
+                failure: &mut *self.failure,
+            })?;
+        }
+        let mut witness: WitnessData =
+            serde_json::from_value(serde_json::Value::Object(rest)).map_err(A::Error::custom)?;
+        witness.residuals = residuals;
+        Ok(witness)
+    }
+}
+
+struct RowsSeed<'a, F> {
+    set: VectorSet,
+    fixed_point: FixedPoint,
+    on_row: &'a mut F,
+    failure: &'a mut Option<anyhow::Error>,
+}
+
+impl<'de, F> DeserializeSeed<'de> for RowsSeed<'_, F>
+where
+    F: FnMut(VectorSet, Vec<Fr>) -> Result<()>,
+{
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F> Visitor<'de> for RowsSeed<'_, F>
+where
+    F: FnMut(VectorSet, Vec<Fr>) -> Result<()>,
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an array of vectors")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(row) = seq.next_element::<Vec<f64>>()? {
+            let handled = to_field_row(self.fixed_point, &row)
+                .and_then(|fields| (self.on_row)(self.set, fields));
+            if let Err(err) = handled {
+                let message = format!("{err:#}");
+                *self.failure = Some(err);
+                return Err(A::Error::custom(message));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_field_row(fixed_point: FixedPoint, row: &[f64]) -> Result<Vec<Fr>> {
+    row.iter()
+        .map(|value| fixed_point.to_field(*value))
+        .collect()
+}
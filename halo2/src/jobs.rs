@@ -0,0 +1,298 @@
+//! In-memory job registry for the proving service.
+//!
+//! Submissions may carry an idempotency key. A retried submission with the
+//! same key and the same request body gets the existing job back instead of
+//! enqueueing new work: queued, running and succeeded jobs are returned as
+//! they are. Failed and cancelled jobs are retried under the same id until
+//! `max_attempts` is reached. Reusing a key for a different body is rejected.
+//!
+//! Finished jobs are kept for `retain_finished` completions, after which the
+//! oldest are forgotten together with their keys.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Condvar, Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{
+    errors::{ErrorCode, ErrorReport},
+    metadata::ProofMetadataV1,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+
+    fn is_retryable(self) -> bool {
+        matches!(self, JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobOutput {
+    /// Hex-encoded proof transcript.
+    pub proof: String,
+    pub metadata: ProofMetadataV1,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRecord {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    pub request_digest: String,
+    pub status: JobStatus,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Catalogue code of `error`, see [`crate::errors`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<JobOutput>,
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+}
+
+/// Outcome of [`JobRegistry::submit`].
+#[derive(Debug, Clone)]
+pub enum Submission {
+    /// New work was enqueued (a fresh job, or a retry of a failed one).
+    Enqueued(JobRecord),
+    /// The key already maps to a job that needs no new work.
+    Existing(JobRecord),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitError {
+    /// The idempotency key was first used with a different request body.
+    KeyConflict {
+        id: String,
+    },
+    /// The job for this key has failed `attempts` times.
+    AttemptsExhausted {
+        id: String,
+        attempts: u32,
+    },
+    QueueFull,
+}
+
+impl std::fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmitError::KeyConflict { id } => write!(
+                f,
+                "idempotency key already used for a different request (job {id})"
+            ),
+            SubmitError::AttemptsExhausted { id, attempts } => {
+                write!(f, "job {id} failed {attempts} times, not retrying")
+            }
+            SubmitError::QueueFull => f.write_str("job queue is full"),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RegistryLimits {
+    pub max_queue: usize,
+    pub max_attempts: u32,
+    pub retain_finished: usize,
+}
+
+impl Default for RegistryLimits {
+    fn default() -> Self {
+        Self {
+            max_queue: 16,
+            max_attempts: 3,
+            retain_finished: 1024,
+        }
+    }
+}
+
+struct State<P> {
+    next_id: u64,
+    jobs: HashMap<String, JobRecord>,
+    by_key: HashMap<String, String>,
+    queue: VecDeque<(String, P)>,
+    finished: VecDeque<String>,
+}
+
+/// Jobs shared between the HTTP handlers and the worker threads.
+pub struct JobRegistry<P> {
+    limits: RegistryLimits,
+    state: Mutex<State<P>>,
+    ready: Condvar,
+}
+
+impl<P> JobRegistry<P> {
+    pub fn new(limits: RegistryLimits) -> Self {
+        Self {
+            limits,
+            state: Mutex::new(State {
+                next_id: 0,
+                jobs: HashMap::new(),
+                by_key: HashMap::new(),
+                queue: VecDeque::new(),
+                finished: VecDeque::new(),
+            }),
+            ready: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<P>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Enqueues `payload`, or resolves it against the job already recorded for `key`.
+    /// `request_digest` identifies the request body, e.g. its blake3 hash.
+    pub fn submit(
+        &self,
+        key: Option<&str>,
+        request_digest: String,
+        payload: P,
+    ) -> Result<Submission, SubmitError> {
+        let mut state = self.lock();
+        let existing = key.and_then(|key| state.by_key.get(key).cloned());
+        if let Some(id) = existing {
+            let job = state.jobs.get(&id).cloned().expect("keyed job is tracked");
+            if job.request_digest != request_digest {
+                return Err(SubmitError::KeyConflict { id });
+            }
+            if !job.status.is_retryable() {
+                return Ok(Submission::Existing(job));
+            }
+            if job.attempts >= self.limits.max_attempts {
+                return Err(SubmitError::AttemptsExhausted {
+                    id,
+                    attempts: job.attempts,
+                });
+            }
+            if state.queue.len() >= self.limits.max_queue {
+                return Err(SubmitError::QueueFull);
+            }
+            state.finished.retain(|finished| finished != &id);
+            let job = state.jobs.get_mut(&id).expect("keyed job is tracked");
+            job.status = JobStatus::Queued;
+            job.error = None;
+            job.error_code = None;
+            job.finished_at = None;
+            let job = job.clone();
+            state.queue.push_back((id, payload));
+            self.ready.notify_one();
+            return Ok(Submission::Enqueued(job));
+        }
+
+        if state.queue.len() >= self.limits.max_queue {
+            return Err(SubmitError::QueueFull);
+        }
+        state.next_id += 1;
+        let id = format!("job-{}-{}", now(), state.next_id);
+        let job = JobRecord {
+            id: id.clone(),
+            idempotency_key: key.map(str::to_string),
+            request_digest,
+            status: JobStatus::Queued,
+            attempts: 0,
+            error: None,
+            error_code: None,
+            result: None,
+            created_at: now(),
+            finished_at: None,
+        };
+        if let Some(key) = key {
+            state.by_key.insert(key.to_string(), id.clone());
+        }
+        state.jobs.insert(id.clone(), job.clone());
+        state.queue.push_back((id, payload));
+        self.ready.notify_one();
+        Ok(Submission::Enqueued(job))
+    }
+
+    pub fn get(&self, id: &str) -> Option<JobRecord> {
+        self.lock().jobs.get(id).cloned()
+    }
+
+    pub fn queued(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    /// Blocks until a job is queued, marks it running and hands it to the caller.
+    pub fn next(&self) -> (String, P) {
+        let mut state = self.lock();
+        loop {
+            if let Some((id, payload)) = state.queue.pop_front() {
+                if let Some(job) = state.jobs.get_mut(&id) {
+                    job.status = JobStatus::Running;
+                    job.attempts += 1;
+                }
+                return (id, payload);
+            }
+            state = self
+                .ready
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Records the outcome of a job taken with [`Self::next`].
+    pub fn finish(&self, id: &str, outcome: Result<JobOutput, (JobStatus, ErrorReport)>) {
+        let mut state = self.lock();
+        let Some(job) = state.jobs.get_mut(id) else {
+            return;
+        };
+        match outcome {
+            Ok(output) => {
+                job.status = JobStatus::Succeeded;
+                job.result = Some(output);
+            }
+            Err((status, report)) => {
+                job.status = status;
+                job.error = Some(report.message);
+                job.error_code = Some(report.code);
+            }
+        }
+        job.finished_at = Some(now());
+        state.finished.push_back(id.to_string());
+        while state.finished.len() > self.limits.retain_finished {
+            let Some(evicted) = state.finished.pop_front() else {
+                break;
+            };
+            if let Some(job) = state.jobs.remove(&evicted) {
+                if let Some(key) = job.idempotency_key {
+                    state.by_key.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,172 @@
+//! Thread count and progress reporting for key generation.
+//!
+//! halo2 already spreads the SRS power loop and the keygen FFTs over rayon
+//! when built with `multicore`; [`set_keygen_threads`] bounds that pool.
+//! halo2 exposes no hooks inside a phase, so progress is reported per phase
+//! (SRS setup, verifying key, proving key) with percentages weighted by their
+//! typical share of the work, plus a heartbeat while a phase runs.
+
+use std::{
+    sync::{mpsc, Arc, Mutex, OnceLock, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeygenPhase {
+    Setup,
+    VerifyingKey,
+    ProvingKey,
+    Done,
+}
+
+impl KeygenPhase {
+    /// Share of keygen finished when this phase starts.
+    fn start_percent(self) -> u8 {
+        match self {
+            KeygenPhase::Setup => 0,
+            KeygenPhase::VerifyingKey => 25,
+            KeygenPhase::ProvingKey => 45,
+            KeygenPhase::Done => 100,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeygenPhase::Setup => "srs setup",
+            KeygenPhase::VerifyingKey => "verifying key",
+            KeygenPhase::ProvingKey => "proving key",
+            KeygenPhase::Done => "done",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeygenProgress {
+    pub circuit_k: u32,
+    pub phase: KeygenPhase,
+    pub percent: u8,
+    pub elapsed: Duration,
+}
+
+pub type ProgressHook = Arc<dyn Fn(&KeygenProgress) + Send + Sync>;
+
+#[derive(Default)]
+struct Settings {
+    threads: Option<usize>,
+    progress: Option<ProgressHook>,
+    heartbeat: Option<Duration>,
+}
+
+fn settings() -> &'static RwLock<Settings> {
+    static SETTINGS: OnceLock<RwLock<Settings>> = OnceLock::new();
+    SETTINGS.get_or_init(Default::default)
+}
+
+/// Limits keygen to `threads` worker threads; `None` uses rayon's default.
+pub fn set_keygen_threads(threads: Option<usize>) {
+    if let Ok(mut settings) = settings().write() {
+        settings.threads = threads;
+    }
+}
+
+/// Receives a report at every phase boundary and every `heartbeat` within one.
+pub fn set_keygen_progress(hook: Option<ProgressHook>, heartbeat: Duration) {
+    if let Ok(mut settings) = settings().write() {
+        settings.progress = hook;
+        settings.heartbeat = Some(heartbeat);
+    }
+}
+
+/// A hook printing progress lines to stderr, for the CLIs.
+pub fn stderr_progress() -> ProgressHook {
+    Arc::new(|progress: &KeygenProgress| {
+        eprintln!(
+            "keygen k={}: {:>3}% {} ({:.1}s)",
+            progress.circuit_k,
+            progress.percent,
+            progress.phase.label(),
+            progress.elapsed.as_secs_f64()
+        );
+    })
+}
+
+/// Handed to the keygen body to announce phases.
+pub struct Tracker {
+    circuit_k: u32,
+    started: Instant,
+    phase: Arc<Mutex<KeygenPhase>>,
+    hook: Option<ProgressHook>,
+}
+
+impl Tracker {
+    pub fn phase(&self, phase: KeygenPhase) {
+        if let Ok(mut current) = self.phase.lock() {
+            *current = phase;
+        }
+        if let Some(hook) = &self.hook {
+            hook(&report(self.circuit_k, phase, self.started));
+        }
+    }
+}
+
+fn report(circuit_k: u32, phase: KeygenPhase, started: Instant) -> KeygenProgress {
+    KeygenProgress {
+        circuit_k,
+        phase,
+        percent: phase.start_percent(),
+        elapsed: started.elapsed(),
+    }
+}
+
+/// Runs a keygen body on the configured thread pool, reporting its phases.
+pub fn run_keygen<T: Send>(
+    circuit_k: u32,
+    body: impl FnOnce(&Tracker) -> Result<T> + Send,
+) -> Result<T> {
+    let (threads, hook, heartbeat) = match settings().read() {
+        Ok(settings) => (
+            settings.threads,
+            settings.progress.clone(),
+            settings.heartbeat,
+        ),
+        Err(_) => (None, None, None),
+    };
+    let tracker = Tracker {
+        circuit_k,
+        started: Instant::now(),
+        phase: Arc::new(Mutex::new(KeygenPhase::Setup)),
+        hook: hook.clone(),
+    };
+    tracker.phase(KeygenPhase::Setup);
+
+    let (stop, stopped) = mpsc::channel::<()>();
+    if let (Some(hook), Some(interval)) = (hook, heartbeat) {
+        let phase = Arc::clone(&tracker.phase);
+        let started = tracker.started;
+        thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let current = phase
+                    .lock()
+                    .map(|phase| *phase)
+                    .unwrap_or(KeygenPhase::Setup);
+                hook(&report(circuit_k, current, started));
+            }
+        });
+    }
+
+    let result = match threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?
+            .install(|| body(&tracker)),
+        None => body(&tracker),
+    };
+    drop(stop);
+    if result.is_ok() {
+        tracker.phase(KeygenPhase::Done);
+    }
+    result
+}
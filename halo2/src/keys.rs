@@ -1,106 +1,931 @@
-use std::{fs::File, path::Path};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    io::Read,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
 use halo2_proofs::{
-    plonk::{keygen_pk, keygen_vk, ProvingKey, VerifyingKey},
-    poly::kzg::commitment::ParamsKZG,
+    plonk::{keygen_pk, keygen_vk, Circuit, ProvingKey, VerifyingKey},
+    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    SerdeFormat,
+};
+use halo2curves::{
+    bn256::{Bn256, Fr, G1Affine, G2Affine},
+    group::GroupEncoding,
 };
-use halo2curves::bn256::{Bn256, G1Affine};
 use rand::{rngs::OsRng, RngCore, SeedableRng};
 use rand_chacha::ChaCha20Rng;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    circuit::FoldedParams,
+    compat::CIRCUIT_VERSION,
+    errors::{Coded, ErrorCode},
+    hashing::digest_hex,
+    keygen::{run_keygen, KeygenPhase},
+    platform::from_json_slice,
+    srs::setup_fingerprint,
+    storage::{path_key, LocalStorage, Storage},
+    FoldedCircuit,
+};
+
+/// A circuit whose shape is recorded in the key config at keygen, so verifiers
+/// can rebuild the matching blank circuit.
+pub trait KeyedCircuit: Circuit<Fr> + Sync {
+    type Shape: Clone + Debug + Default + PartialEq + Serialize + DeserializeOwned;
 
-use crate::FoldedCircuit;
+    fn shape(&self) -> &Self::Shape;
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct KeyConfig {
+impl KeyedCircuit for FoldedCircuit {
+    type Shape = FoldedParams;
+
+    fn shape(&self) -> &FoldedParams {
+        &self.params
+    }
+}
+
+/// A key config. Its `seed` is the toxic waste of a seed-derived setup, so
+/// it is left out of `Debug` and zeroized on drop.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "S: Serialize",
+    deserialize = "S: DeserializeOwned + Default"
+))]
+struct KeyConfig<S = FoldedParams> {
     circuit_k: u32,
     seed: [u8; 32],
+    #[serde(default)]
+    circuit: S,
+    /// [Fingerprint](setup_fingerprint) of the trusted setup the keys are
+    /// built on; without one they are built on a setup derived from `seed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    setup: Option<String>,
+    /// Serialized keys written by [`plan_key_migration_in`]; without them the
+    /// keys are regenerated from `seed` on every load.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    artifacts: Option<KeyArtifacts>,
+}
+
+impl<S: Debug> Debug for KeyConfig<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyConfig")
+            .field("circuit_k", &self.circuit_k)
+            .field("seed", &"<redacted>")
+            .field("circuit", &self.circuit)
+            .field("setup", &self.setup)
+            .field("artifacts", &self.artifacts)
+            .finish()
+    }
+}
+
+impl<S> Drop for KeyConfig<S> {
+    fn drop(&mut self) {
+        self.seed.zeroize();
+    }
 }
 
-pub fn load_or_init_keys(
+/// Storage keys of a config's serialized params and keys, with their blake3 fingerprints.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyArtifacts {
+    pub params: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proving_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verifying_key: Option<String>,
+    pub fingerprints: BTreeMap<String, String>,
+}
+
+pub(crate) const KEY_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+/// How long a prover waits for another process to finish creating the
+/// configs of the same key pair before giving up.
+const INIT_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+const INIT_LOCK_POLL: Duration = Duration::from_millis(100);
+
+pub fn load_or_init_keys<C: KeyedCircuit>(
     proving_path: &Path,
     verifying_path: &Path,
     requested_k: u32,
-    blank_circuit: &FoldedCircuit,
+    blank_circuit: &C,
 ) -> Result<(ParamsKZG<Bn256>, ProvingKey<G1Affine>)> {
-    let config = load_or_create_config(proving_path, requested_k)?;
-    ensure_config(verifying_path, &config)?;
-    build_params_and_pk(&config, blank_circuit)
+    load_or_init_keys_with_setup(
+        proving_path,
+        verifying_path,
+        requested_k,
+        blank_circuit,
+        None,
+    )
 }
 
-pub fn load_params_and_vk(
+/// [`load_or_init_keys`] on a trusted setup (see [`crate::srs`]). New
+/// configs record the setup's fingerprint, and keys recorded with one can
+/// only be loaded with it.
+pub fn load_or_init_keys_with_setup<C: KeyedCircuit>(
+    proving_path: &Path,
     verifying_path: &Path,
-    blank_circuit: &FoldedCircuit,
+    requested_k: u32,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<(ParamsKZG<Bn256>, ProvingKey<G1Affine>)> {
+    load_or_init_keys_in(
+        &LocalStorage::default(),
+        &path_key(proving_path),
+        &path_key(verifying_path),
+        requested_k,
+        blank_circuit,
+        setup,
+    )
+}
+
+pub fn load_params_and_vk<C: KeyedCircuit>(
+    verifying_path: &Path,
+    blank_circuit: &C,
 ) -> Result<(ParamsKZG<Bn256>, VerifyingKey<G1Affine>)> {
-    let config = read_config(verifying_path)?;
-    build_params_and_vk(&config, blank_circuit)
+    load_params_and_vk_with_setup(verifying_path, blank_circuit, None)
 }
 
-fn build_params_and_pk(
-    config: &KeyConfig,
-    blank_circuit: &FoldedCircuit,
+pub fn load_params_and_vk_with_setup<C: KeyedCircuit>(
+    verifying_path: &Path,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<(ParamsKZG<Bn256>, VerifyingKey<G1Affine>)> {
+    load_params_and_vk_in(
+        &LocalStorage::default(),
+        &path_key(verifying_path),
+        blank_circuit,
+        setup,
+    )
+}
+
+pub fn load_or_init_keys_in<C: KeyedCircuit>(
+    storage: &dyn Storage,
+    proving_key: &str,
+    verifying_key: &str,
+    requested_k: u32,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
 ) -> Result<(ParamsKZG<Bn256>, ProvingKey<G1Affine>)> {
-    let mut rng = ChaCha20Rng::from_seed(config.seed);
-    let params = ParamsKZG::<Bn256>::setup(config.circuit_k, &mut rng);
-    let vk = keygen_vk(&params, blank_circuit)?;
-    let pk = keygen_pk(&params, vk, blank_circuit)?;
+    let config = {
+        let _lock = if storage.exists(proving_key)? && storage.exists(verifying_key)? {
+            None
+        } else {
+            Some(InitLock::acquire(storage, proving_key)?)
+        };
+        let config = load_or_create_config(
+            storage,
+            proving_key,
+            requested_k,
+            blank_circuit.shape(),
+            setup.map(setup_fingerprint),
+        )?;
+        ensure_config(storage, verifying_key, &config)?;
+        config
+    };
+    match &config.artifacts {
+        Some(artifacts) if artifacts.proving_key.is_some() => {
+            read_params_and_pk(storage, artifacts, blank_circuit)
+        }
+        _ => build_params_and_pk(
+            Some((storage, verifying_key)),
+            &config,
+            blank_circuit,
+            setup,
+        ),
+    }
+}
+
+pub fn load_params_and_vk_in<C: KeyedCircuit>(
+    storage: &dyn Storage,
+    verifying_key: &str,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<(ParamsKZG<Bn256>, VerifyingKey<G1Affine>)> {
+    let config = read_config::<C::Shape>(storage, verifying_key)?;
+    ensure_circuit_params(&config, blank_circuit.shape())?;
+    match &config.artifacts {
+        Some(artifacts) if artifacts.verifying_key.is_some() => {
+            read_params_and_vk(storage, artifacts, blank_circuit)
+        }
+        _ => build_params_and_vk(
+            Some((storage, verifying_key)),
+            &config,
+            blank_circuit,
+            setup,
+        ),
+    }
+}
+
+/// Serialized keys derived from a seed-only config, not yet written.
+/// Inspect or exercise the keys, then [`KeyMigration::commit`] them.
+pub struct KeyMigration<S> {
+    pub params: ParamsKZG<Bn256>,
+    pub pk: ProvingKey<G1Affine>,
+    pub vk: VerifyingKey<G1Affine>,
+    pub proving_artifacts: KeyArtifacts,
+    pub verifying_artifacts: KeyArtifacts,
+    configs: Vec<(String, KeyConfig<S>)>,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl<S: Serialize> KeyMigration<S> {
+    /// Writes the artifacts, then the updated configs, so an interrupted
+    /// commit leaves the old configs in charge.
+    pub fn commit(self, storage: &dyn Storage) -> Result<()> {
+        for (key, bytes) in &self.files {
+            storage.write(key, bytes)?;
+        }
+        for (key, config) in &self.configs {
+            write_config(storage, key, config)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes `pk` in [`KEY_FORMAT`]; read back with [`read_pk`].
+pub fn write_pk(pk: &ProvingKey<G1Affine>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    pk.write(&mut bytes, KEY_FORMAT)?;
+    Ok(bytes)
+}
+
+/// Reads a [`write_pk`] blob for `blank_circuit`'s shape.
+pub fn read_pk<C: Circuit<Fr>>(bytes: &[u8], blank_circuit: &C) -> Result<ProvingKey<G1Affine>> {
+    Ok(ProvingKey::read::<_, C>(
+        &mut &bytes[..],
+        KEY_FORMAT,
+        blank_circuit.params(),
+    )?)
+}
+
+/// Serializes `vk` in [`KEY_FORMAT`]; read back with [`read_vk`].
+pub fn write_vk(vk: &VerifyingKey<G1Affine>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    vk.write(&mut bytes, KEY_FORMAT)?;
+    Ok(bytes)
+}
+
+/// Reads a [`write_vk`] blob for `blank_circuit`'s shape.
+pub fn read_vk<C: Circuit<Fr>>(bytes: &[u8], blank_circuit: &C) -> Result<VerifyingKey<G1Affine>> {
+    Ok(VerifyingKey::read::<_, C>(
+        &mut &bytes[..],
+        KEY_FORMAT,
+        blank_circuit.params(),
+    )?)
+}
+
+/// Creates a config pair for `blank_circuit` at `circuit_k` and serializes
+/// its keys next to it, running keygen once. Later loads of the pair read
+/// the key blobs instead of running keygen.
+pub fn generate_keys_in<C: KeyedCircuit>(
+    storage: &dyn Storage,
+    proving_key: &str,
+    verifying_key: &str,
+    circuit_k: u32,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<KeyMigration<C::Shape>> {
+    {
+        let _lock = InitLock::acquire(storage, proving_key)?;
+        let config = load_or_create_config(
+            storage,
+            proving_key,
+            circuit_k,
+            blank_circuit.shape(),
+            setup.map(setup_fingerprint),
+        )?;
+        ensure_config(storage, verifying_key, &config)?;
+    }
+    plan_key_migration_in(storage, proving_key, verifying_key, blank_circuit, setup)
+}
+
+/// Regenerates the keys of an existing `{circuit_k, seed}` config pair once
+/// and serializes them next to the configs. The returned keys are read back
+/// from the serialized bytes, so a proof made with them exercises the new format.
+pub fn plan_key_migration_in<C: KeyedCircuit>(
+    storage: &dyn Storage,
+    proving_key: &str,
+    verifying_key: &str,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<KeyMigration<C::Shape>> {
+    let mut proving = read_config::<C::Shape>(storage, proving_key)?;
+    let mut verifying = read_config::<C::Shape>(storage, verifying_key)?;
+    if proving.circuit_k != verifying.circuit_k
+        || proving.seed != verifying.seed
+        || proving.circuit != verifying.circuit
+        || proving.setup != verifying.setup
+    {
+        anyhow::bail!(Coded::new(
+            ErrorCode::KeyMismatch,
+            "Verifier key config mismatch"
+        ));
+    }
+    ensure_circuit_params(&proving, blank_circuit.shape())?;
+    if proving.artifacts.is_some() || verifying.artifacts.is_some() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            format!("{proving_key} already references serialized keys")
+        ));
+    }
+
+    // Computed afresh so a migration never serializes a cached key.
+    let (params, pk) = build_params_and_pk(None, &proving, blank_circuit, setup)?;
+    let mut params_bytes = Vec::new();
+    params.write(&mut params_bytes)?;
+    let pk_bytes = write_pk(&pk)?;
+    let vk_bytes = write_vk(pk.get_vk())?;
+
+    let mut files = Vec::new();
+    let mut artifacts = |config_key: &str, key_bytes: (&str, &[u8])| {
+        let params_key = format!("{config_key}.params");
+        let key_key = format!("{config_key}.{}", key_bytes.0);
+        let fingerprints = BTreeMap::from([
+            (params_key.clone(), fingerprint(&params_bytes)),
+            (key_key.clone(), fingerprint(key_bytes.1)),
+        ]);
+        files.push((params_key.clone(), params_bytes.clone()));
+        files.push((key_key.clone(), key_bytes.1.to_vec()));
+        (params_key, key_key, fingerprints)
+    };
+    let (params_key, pk_key, fingerprints) = artifacts(proving_key, ("pk", &pk_bytes));
+    let proving_artifacts = KeyArtifacts {
+        params: params_key,
+        proving_key: Some(pk_key),
+        verifying_key: None,
+        fingerprints,
+    };
+    let (params_key, vk_key, fingerprints) = artifacts(verifying_key, ("vk", &vk_bytes));
+    let verifying_artifacts = KeyArtifacts {
+        params: params_key,
+        proving_key: None,
+        verifying_key: Some(vk_key),
+        fingerprints,
+    };
+
+    let params = ParamsKZG::<Bn256>::read(&mut params_bytes.as_slice())?;
+    let pk = read_pk(&pk_bytes, blank_circuit)?;
+    let vk = read_vk(&vk_bytes, blank_circuit)?;
+
+    proving.artifacts = Some(proving_artifacts.clone());
+    verifying.artifacts = Some(verifying_artifacts.clone());
+    Ok(KeyMigration {
+        params,
+        pk,
+        vk,
+        proving_artifacts,
+        verifying_artifacts,
+        configs: vec![
+            (proving_key.to_string(), proving),
+            (verifying_key.to_string(), verifying),
+        ],
+        files,
+    })
+}
+
+/// Circuit shape recorded alongside a key config; verifiers build their blank circuit from it.
+pub fn read_circuit_params(verifying_path: &Path) -> Result<FoldedParams> {
+    read_circuit_params_in(&LocalStorage::default(), &path_key(verifying_path))
+}
+
+pub fn read_circuit_params_in(storage: &dyn Storage, verifying_key: &str) -> Result<FoldedParams> {
+    read_circuit_shape_in(storage, verifying_key)
+}
+
+/// `k` recorded in a key config.
+pub fn read_circuit_k(config_path: &Path) -> Result<u32> {
+    read_circuit_k_in(&LocalStorage::default(), &path_key(config_path))
+}
+
+pub fn read_circuit_k_in(storage: &dyn Storage, key: &str) -> Result<u32> {
+    Ok(read_config::<serde::de::IgnoredAny>(storage, key)?.circuit_k)
+}
+
+/// [`read_circuit_params`] for circuits other than [`FoldedCircuit`].
+pub fn read_circuit_shape<S: DeserializeOwned + Default>(verifying_path: &Path) -> Result<S> {
+    read_circuit_shape_in(&LocalStorage::default(), &path_key(verifying_path))
+}
+
+pub fn read_circuit_shape_in<S: DeserializeOwned + Default>(
+    storage: &dyn Storage,
+    verifying_key: &str,
+) -> Result<S> {
+    Ok(read_config::<S>(storage, verifying_key)?.circuit)
+}
+
+/// blake3 over the canonical JSON of a key config, identifying the keys it
+/// derives without exposing the seed.
+pub fn key_fingerprint(config_path: &Path) -> Result<String> {
+    key_fingerprint_in(&LocalStorage::default(), &path_key(config_path))
+}
+
+pub fn key_fingerprint_in(storage: &dyn Storage, key: &str) -> Result<String> {
+    let bytes = Zeroizing::new(storage.read(key)?);
+    let config: serde_json::Value =
+        from_json_slice(&bytes).with_context(|| format!("parsing key config {key}"))?;
+    Ok(digest_hex(&serde_json::to_vec(&config)?))
+}
+
+/// Version 1 bundles carry the full params; version 2 only the
+/// [verifier params](write_verifier_params).
+pub const VERIFIER_BUNDLE_VERSION: u32 = 2;
+
+/// Everything a verifier needs without the seed: the circuit shape, the
+/// serialized verifier params and verifying key, hex-encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    bound(
+        serialize = "S: Serialize",
+        deserialize = "S: DeserializeOwned + Default"
+    )
+)]
+pub struct VerifierBundle<S = FoldedParams> {
+    pub version: u32,
+    pub circuit_k: u32,
+    #[serde(default)]
+    pub circuit: S,
+    /// Full params, in version 1 bundles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verifier_params: Option<String>,
+    pub verifying_key: String,
+}
+
+impl<S: Debug + PartialEq> VerifierBundle<S> {
+    /// Decodes the params and verifying key for `blank_circuit`'s shape.
+    pub fn load<C: KeyedCircuit<Shape = S>>(
+        &self,
+        blank_circuit: &C,
+    ) -> Result<(ParamsKZG<Bn256>, VerifyingKey<G1Affine>)> {
+        if !(1..=VERIFIER_BUNDLE_VERSION).contains(&self.version) {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Incompatible,
+                format!("unsupported verifier bundle version {}", self.version)
+            ));
+        }
+        if &self.circuit != blank_circuit.shape() {
+            anyhow::bail!(Coded::new(
+                ErrorCode::KeyMismatch,
+                format!(
+                    "Verifier bundle was created for circuit {:?}, requested {:?}",
+                    self.circuit,
+                    blank_circuit.shape()
+                )
+            ));
+        }
+        let params = match (&self.verifier_params, &self.params) {
+            (Some(verifier_params), _) => {
+                let bytes =
+                    hex::decode(verifier_params).context("verifier bundle verifierParams")?;
+                read_verifier_params(&bytes).context("reading verifier bundle verifierParams")?
+            }
+            (None, Some(params)) => {
+                let bytes = hex::decode(params).context("verifier bundle params")?;
+                ParamsKZG::<Bn256>::read(&mut bytes.as_slice())
+                    .context("reading verifier bundle params")?
+            }
+            (None, None) => anyhow::bail!("verifier bundle has neither verifierParams nor params"),
+        };
+        let vk_bytes = hex::decode(&self.verifying_key).context("verifier bundle verifyingKey")?;
+        let vk = VerifyingKey::read::<_, C>(
+            &mut vk_bytes.as_slice(),
+            KEY_FORMAT,
+            blank_circuit.params(),
+        )
+        .context("reading verifier bundle verifying key")?;
+        Ok((params, vk))
+    }
+}
+
+/// Builds a [`VerifierBundle`] from a verifying key config, regenerating the
+/// keys once if the config has no serialized artifacts.
+pub fn export_verifier_bundle_in<C: KeyedCircuit>(
+    storage: &dyn Storage,
+    verifying_key: &str,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<VerifierBundle<C::Shape>> {
+    let config = read_config::<C::Shape>(storage, verifying_key)?;
+    let (params, vk) = load_params_and_vk_in(storage, verifying_key, blank_circuit, setup)?;
+    let mut vk_bytes = Vec::new();
+    vk.write(&mut vk_bytes, KEY_FORMAT)?;
+    Ok(VerifierBundle {
+        version: VERIFIER_BUNDLE_VERSION,
+        circuit_k: config.circuit_k,
+        circuit: config.circuit.clone(),
+        params: None,
+        verifier_params: Some(hex::encode(write_verifier_params(&params))),
+        verifying_key: hex::encode(vk_bytes),
+    })
+}
+
+/// Leading bytes of a verifier-params file. A full params file starts with
+/// its little-endian `k` instead, which is far below this.
+pub const VERIFIER_PARAMS_MAGIC: &[u8; 4] = b"YVP1";
+
+/// Serializes the part of `params` a verifier reads: `k`, `g[0]`, `g2` and
+/// `s_g2`, 164 bytes at any `k`. Instances are evaluated rather than
+/// committed by the GWC verifier, so the rest of the SRS is prover-only.
+pub fn write_verifier_params(params: &ParamsKZG<Bn256>) -> Vec<u8> {
+    let mut bytes = VERIFIER_PARAMS_MAGIC.to_vec();
+    bytes.extend_from_slice(&params.k().to_le_bytes());
+    bytes.extend_from_slice(params.get_g()[0].to_bytes().as_ref());
+    bytes.extend_from_slice(params.g2().to_bytes().as_ref());
+    bytes.extend_from_slice(params.s_g2().to_bytes().as_ref());
+    bytes
+}
+
+/// Reads [`write_verifier_params`] output. The result verifies proofs of
+/// its `k` but cannot commit to polynomials, so it must never reach a prover.
+pub fn read_verifier_params(bytes: &[u8]) -> Result<ParamsKZG<Bn256>> {
+    let mut rest = bytes
+        .strip_prefix(VERIFIER_PARAMS_MAGIC.as_slice())
+        .context("not a verifier params file")?;
+    let mut k = [0u8; 4];
+    let mut g = <G1Affine as GroupEncoding>::Repr::default();
+    let mut g2 = <G2Affine as GroupEncoding>::Repr::default();
+    let mut s_g2 = <G2Affine as GroupEncoding>::Repr::default();
+    for field in [k.as_mut_slice(), g.as_mut(), g2.as_mut(), s_g2.as_mut()] {
+        rest.read_exact(field)
+            .context("verifier params are truncated")?;
+    }
+    if !rest.is_empty() {
+        anyhow::bail!("{} trailing bytes after verifier params", rest.len());
+    }
+    let k = u32::from_le_bytes(k);
+    let g = Option::<G1Affine>::from(G1Affine::from_bytes(&g)).context("invalid g[0]")?;
+    let g2 = Option::<G2Affine>::from(G2Affine::from_bytes(&g2)).context("invalid g2")?;
+    let s_g2 = Option::<G2Affine>::from(G2Affine::from_bytes(&s_g2)).context("invalid s_g2")?;
+    // `from_parts` only borrows an instance for its type; a k=1 setup is
+    // the cheapest one at hand.
+    let template = ParamsKZG::<Bn256>::setup(1, ChaCha20Rng::from_seed([0; 32]));
+    Ok(template.from_parts(k, vec![g], Some(vec![]), g2, s_g2))
+}
+
+/// Reads either a full params file or a verifier-params file.
+pub fn read_any_params(bytes: &[u8]) -> Result<ParamsKZG<Bn256>> {
+    if bytes.starts_with(VERIFIER_PARAMS_MAGIC) {
+        read_verifier_params(bytes)
+    } else {
+        Ok(ParamsKZG::<Bn256>::read(&mut &bytes[..])?)
+    }
+}
+
+pub fn read_verifier_bundle<S: DeserializeOwned + Default>(
+    path: &Path,
+) -> Result<VerifierBundle<S>> {
+    read_verifier_bundle_in(&LocalStorage::default(), &path_key(path))
+}
+
+pub fn read_verifier_bundle_in<S: DeserializeOwned + Default>(
+    storage: &dyn Storage,
+    key: &str,
+) -> Result<VerifierBundle<S>> {
+    let bytes = storage.read(key)?;
+    from_json_slice(&bytes).with_context(|| format!("parsing verifier bundle {key}"))
+}
+
+fn ensure_circuit_params<S: Debug + PartialEq>(config: &KeyConfig<S>, params: &S) -> Result<()> {
+    if &config.circuit != params {
+        anyhow::bail!(Coded::new(
+            ErrorCode::KeyMismatch,
+            format!(
+                "Key config was created for circuit {:?}, requested {:?}",
+                config.circuit, params
+            )
+        ));
+    }
+    Ok(())
+}
+
+fn build_params_and_pk<S: Serialize, C: Circuit<Fr> + Sync>(
+    cache: Option<(&dyn Storage, &str)>,
+    config: &KeyConfig<S>,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<(ParamsKZG<Bn256>, ProvingKey<G1Affine>)> {
+    run_keygen(config.circuit_k, |tracker| {
+        let params = config_params(config, setup)?;
+        tracker.phase(KeygenPhase::VerifyingKey);
+        let vk = memoized_vk(cache, config, &params, blank_circuit)?;
+        tracker.phase(KeygenPhase::ProvingKey);
+        let pk = keygen_pk(&params, vk, blank_circuit)?;
+        Ok((params, pk))
+    })
+}
+
+fn build_params_and_vk<S: Serialize, C: Circuit<Fr> + Sync>(
+    cache: Option<(&dyn Storage, &str)>,
+    config: &KeyConfig<S>,
+    blank_circuit: &C,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<(ParamsKZG<Bn256>, VerifyingKey<G1Affine>)> {
+    run_keygen(config.circuit_k, |tracker| {
+        let params = config_params(config, setup)?;
+        tracker.phase(KeygenPhase::VerifyingKey);
+        let vk = memoized_vk(cache, config, &params, blank_circuit)?;
+        Ok((params, vk))
+    })
+}
+
+/// The params `config`'s keys are built on: the trusted setup it records,
+/// reduced to its `k`, or the setup derived from its seed.
+fn config_params<S>(
+    config: &KeyConfig<S>,
+    setup: Option<&ParamsKZG<Bn256>>,
+) -> Result<ParamsKZG<Bn256>> {
+    match (&config.setup, setup) {
+        (None, None) => {
+            let mut rng = ChaCha20Rng::from_seed(config.seed);
+            Ok(ParamsKZG::<Bn256>::setup(config.circuit_k, &mut rng))
+        }
+        (Some(expected), Some(setup)) => {
+            let actual = setup_fingerprint(setup);
+            if actual != *expected {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::KeyMismatch,
+                    format!("keys were built on trusted setup {expected}, given {actual}")
+                ));
+            }
+            if setup.k() < config.circuit_k {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::KeyMismatch,
+                    format!(
+                        "trusted setup only supports k≤{}, keys need k={}",
+                        setup.k(),
+                        config.circuit_k
+                    )
+                ));
+            }
+            let mut params = setup.clone();
+            if params.k() > config.circuit_k {
+                params.downsize(config.circuit_k);
+            }
+            Ok(params)
+        }
+        (Some(expected), None) => anyhow::bail!(Coded::new(
+            ErrorCode::KeyMismatch,
+            format!("keys were built on trusted setup {expected}; pass its params")
+        )),
+        (None, Some(_)) => anyhow::bail!(Coded::new(
+            ErrorCode::KeyMismatch,
+            "keys were built on their config seed, not a trusted setup"
+        )),
+    }
+}
+
+/// The fixed-column and permutation commitments of a seed-only config,
+/// memoized next to the config as a serialized verifying key.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitmentCache {
+    /// Digest of the circuit version, `circuit_k`, seed (or trusted setup)
+    /// and shape the commitments were computed for.
+    source: String,
+    verifying_key: String,
+}
+
+fn commitment_cache_key(config_key: &str) -> String {
+    format!("{config_key}.commitments")
+}
+
+fn commitment_source<S: Serialize>(config: &KeyConfig<S>) -> Result<String> {
+    let bytes = Zeroizing::new(match &config.setup {
+        None => serde_json::to_vec(&(
+            CIRCUIT_VERSION,
+            config.circuit_k,
+            config.seed,
+            &config.circuit,
+        ))?,
+        Some(setup) => {
+            serde_json::to_vec(&(CIRCUIT_VERSION, config.circuit_k, setup, &config.circuit))?
+        }
+    });
+    Ok(digest_hex(&bytes))
+}
+
+/// Reads the verifying key from the commitment cache of `config_key`, or
+/// runs `keygen_vk` and caches it. A stale or unreadable cache is recomputed
+/// and a failed cache write is ignored, so read-only storage only loses the
+/// speedup.
+fn memoized_vk<S: Serialize, C: Circuit<Fr>>(
+    cache: Option<(&dyn Storage, &str)>,
+    config: &KeyConfig<S>,
+    params: &ParamsKZG<Bn256>,
+    blank_circuit: &C,
+) -> Result<VerifyingKey<G1Affine>> {
+    let Some((storage, config_key)) = cache else {
+        return Ok(keygen_vk(params, blank_circuit)?);
+    };
+    let key = commitment_cache_key(config_key);
+    let source = commitment_source(config)?;
+    if let Some(vk) = read_cached_vk(storage, &key, &source, blank_circuit) {
+        return Ok(vk);
+    }
+    let vk = keygen_vk(params, blank_circuit)?;
+    let mut vk_bytes = Vec::new();
+    vk.write(&mut vk_bytes, KEY_FORMAT)?;
+    let cache = CommitmentCache {
+        source,
+        verifying_key: hex::encode(vk_bytes),
+    };
+    let _ = storage.write(&key, &serde_json::to_vec(&cache)?);
+    Ok(vk)
+}
+
+fn read_cached_vk<C: Circuit<Fr>>(
+    storage: &dyn Storage,
+    key: &str,
+    source: &str,
+    blank_circuit: &C,
+) -> Option<VerifyingKey<G1Affine>> {
+    if !storage.exists(key).ok()? {
+        return None;
+    }
+    let cache: CommitmentCache = from_json_slice(&storage.read(key).ok()?).ok()?;
+    if cache.source != source {
+        return None;
+    }
+    let bytes = hex::decode(&cache.verifying_key).ok()?;
+    VerifyingKey::read::<_, C>(&mut bytes.as_slice(), KEY_FORMAT, blank_circuit.params()).ok()
+}
+
+fn read_params_and_pk<C: Circuit<Fr>>(
+    storage: &dyn Storage,
+    artifacts: &KeyArtifacts,
+    blank_circuit: &C,
+) -> Result<(ParamsKZG<Bn256>, ProvingKey<G1Affine>)> {
+    let params = read_params(storage, artifacts)?;
+    let key = artifacts.proving_key.as_deref().unwrap_or_default();
+    let bytes = read_artifact(storage, artifacts, key)?;
+    let pk =
+        read_pk(&bytes, blank_circuit).with_context(|| format!("reading proving key {key}"))?;
     Ok((params, pk))
 }
 
-fn build_params_and_vk(
-    config: &KeyConfig,
-    blank_circuit: &FoldedCircuit,
+fn read_params_and_vk<C: Circuit<Fr>>(
+    storage: &dyn Storage,
+    artifacts: &KeyArtifacts,
+    blank_circuit: &C,
 ) -> Result<(ParamsKZG<Bn256>, VerifyingKey<G1Affine>)> {
-    let mut rng = ChaCha20Rng::from_seed(config.seed);
-    let params = ParamsKZG::<Bn256>::setup(config.circuit_k, &mut rng);
-    let vk = keygen_vk(&params, blank_circuit)?;
+    let params = read_params(storage, artifacts)?;
+    let key = artifacts.verifying_key.as_deref().unwrap_or_default();
+    let bytes = read_artifact(storage, artifacts, key)?;
+    let vk =
+        read_vk(&bytes, blank_circuit).with_context(|| format!("reading verifying key {key}"))?;
     Ok((params, vk))
 }
 
-fn load_or_create_config(path: &Path, requested_k: u32) -> Result<KeyConfig> {
-    if path.exists() {
-        let config = read_config(path)?;
-        if config.circuit_k != requested_k {
-            anyhow::bail!(
-                "Existing proving key config uses k={}, requested {}",
-                config.circuit_k,
-                requested_k
-            );
+fn read_params(storage: &dyn Storage, artifacts: &KeyArtifacts) -> Result<ParamsKZG<Bn256>> {
+    let bytes = read_artifact(storage, artifacts, &artifacts.params)?;
+    ParamsKZG::<Bn256>::read(&mut bytes.as_slice())
+        .with_context(|| format!("reading params {}", artifacts.params))
+}
+
+/// Reads an artifact and checks it against the fingerprint recorded at migration.
+fn read_artifact(storage: &dyn Storage, artifacts: &KeyArtifacts, key: &str) -> Result<Vec<u8>> {
+    let bytes = storage.read(key)?;
+    match artifacts.fingerprints.get(key) {
+        Some(expected) if *expected != fingerprint(&bytes) => {
+            anyhow::bail!(Coded::new(
+                ErrorCode::KeyMismatch,
+                format!("{key} does not match its recorded fingerprint")
+            ))
         }
-        Ok(config)
-    } else {
+        _ => Ok(bytes),
+    }
+}
+
+fn fingerprint(bytes: &[u8]) -> String {
+    digest_hex(bytes)
+}
+
+/// Advisory lock held while a key pair's configs are created, so provers
+/// started together agree on one seed. Taken by creating `{key}.lock`
+/// exclusively and released by deleting it; a lock left behind by a killed
+/// process has to be removed by hand.
+struct InitLock<'a> {
+    storage: &'a dyn Storage,
+    key: String,
+}
+
+impl<'a> InitLock<'a> {
+    fn acquire(storage: &'a dyn Storage, config_key: &str) -> Result<Self> {
+        let key = format!("{config_key}.lock");
+        let started = Instant::now();
+        let owner = format!("{}\n", std::process::id());
+        while !storage.write_new(&key, owner.as_bytes())? {
+            if started.elapsed() > INIT_LOCK_TIMEOUT {
+                anyhow::bail!(
+                    "timed out after {:?} waiting for {key}; remove it if no other prover \
+                     is initializing these keys",
+                    INIT_LOCK_TIMEOUT
+                );
+            }
+            thread::sleep(INIT_LOCK_POLL);
+        }
+        Ok(Self { storage, key })
+    }
+}
+
+impl Drop for InitLock<'_> {
+    fn drop(&mut self) {
+        let _ = self.storage.delete(&self.key);
+    }
+}
+
+fn load_or_create_config<S>(
+    storage: &dyn Storage,
+    key: &str,
+    requested_k: u32,
+    params: &S,
+    setup: Option<String>,
+) -> Result<KeyConfig<S>>
+where
+    S: Clone + Debug + Default + PartialEq + Serialize + DeserializeOwned,
+{
+    if !storage.exists(key)? {
         let mut seed = [0u8; 32];
         OsRng.fill_bytes(&mut seed);
         let config = KeyConfig {
             circuit_k: requested_k,
             seed,
+            circuit: params.clone(),
+            setup,
+            artifacts: None,
         };
-        write_config(path, &config)?;
-        Ok(config)
+        seed.zeroize();
+        if write_new_config(storage, key, &config)? {
+            return Ok(config);
+        }
     }
+    let config = read_config::<S>(storage, key)?;
+    if config.circuit_k != requested_k {
+        anyhow::bail!(Coded::new(
+            ErrorCode::KeyMismatch,
+            format!(
+                "Existing proving key config uses k={}, requested {}",
+                config.circuit_k, requested_k
+            )
+        ));
+    }
+    ensure_circuit_params(&config, params)?;
+    Ok(config)
 }
 
-fn ensure_config(path: &Path, config: &KeyConfig) -> Result<()> {
-    if path.exists() {
-        let existing = read_config(path)?;
-        if existing.circuit_k != config.circuit_k || existing.seed != config.seed {
-            anyhow::bail!("Verifier key config mismatch");
-        }
-        Ok(())
-    } else {
-        write_config(path, config)
+fn ensure_config<S>(storage: &dyn Storage, key: &str, config: &KeyConfig<S>) -> Result<()>
+where
+    S: Default + PartialEq + Serialize + DeserializeOwned,
+{
+    if write_new_config(storage, key, config)? {
+        return Ok(());
     }
+    let existing = read_config::<S>(storage, key)?;
+    if existing.circuit_k != config.circuit_k
+        || existing.seed != config.seed
+        || existing.circuit != config.circuit
+        || existing.setup != config.setup
+    {
+        anyhow::bail!(Coded::new(
+            ErrorCode::KeyMismatch,
+            "Verifier key config mismatch"
+        ));
+    }
+    Ok(())
 }
 
-fn read_config(path: &Path) -> Result<KeyConfig> {
-    let file = File::open(path).with_context(|| format!("opening {:?}", path))?;
-    Ok(serde_json::from_reader(file)?)
+fn read_config<S: DeserializeOwned + Default>(
+    storage: &dyn Storage,
+    key: &str,
+) -> Result<KeyConfig<S>> {
+    let bytes = Zeroizing::new(storage.read(key)?);
+    from_json_slice(&bytes).with_context(|| format!("parsing key config {key}"))
 }
 
-fn write_config(path: &Path, config: &KeyConfig) -> Result<()> {
-    let file = File::create(path)?;
-    serde_json::to_writer_pretty(file, config)?;
-    Ok(())
+fn write_config<S: Serialize>(
+    storage: &dyn Storage,
+    key: &str,
+    config: &KeyConfig<S>,
+) -> Result<()> {
+    let bytes = Zeroizing::new(serde_json::to_vec_pretty(config)?);
+    storage.write(key, &bytes)
+}
+
+/// Writes `config` unless `key` already exists; see [`Storage::write_new`].
+fn write_new_config<S: Serialize>(
+    storage: &dyn Storage,
+    key: &str,
+    config: &KeyConfig<S>,
+) -> Result<bool> {
+    let bytes = Zeroizing::new(serde_json::to_vec_pretty(config)?);
+    storage.write_new(key, &bytes)
 }
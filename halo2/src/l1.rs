@@ -0,0 +1,173 @@
+//! Binding proofs to the finalized L1 block their public inputs came from.
+//!
+//! A proof can record, in its metadata, the L1 block at which its public
+//! inputs were observed. [`ChainHeadTracker`] picks that block (by default
+//! the current finalized head), refuses blocks that are not finalized yet,
+//! and checks that the block is still canonical before and after proving, so
+//! a proof is never written against data an L1 reorg has replaced.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::{strip_hex_prefix, Hash256},
+    errors::{Coded, ErrorCode},
+    http::{post_json, Limits},
+    platform::from_json_slice,
+};
+
+/// An L1 block by number and hash; `<number>:<hash>` on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct L1Reference {
+    pub number: u64,
+    pub hash: Hash256,
+}
+
+impl fmt::Display for L1Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.number, self.hash)
+    }
+}
+
+impl FromStr for L1Reference {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let (number, hash) = raw
+            .split_once(':')
+            .with_context(|| format!("L1 reference {raw:?} is not <number>:<hash>"))?;
+        Ok(Self {
+            number: number
+                .parse()
+                .with_context(|| format!("L1 block number {number:?}"))?,
+            hash: Hash256::from_hex(hash)?,
+        })
+    }
+}
+
+/// What the tracker needs from an L1 node.
+pub trait L1Client {
+    /// The latest finalized block.
+    fn finalized(&self) -> Result<L1Reference>;
+    /// Hash of the canonical block at `number`, or `None` past the head.
+    fn block_hash(&self, number: u64) -> Result<Option<Hash256>>;
+}
+
+/// [`L1Client`] over an Ethereum JSON-RPC endpoint (`eth_getBlockByNumber`).
+#[derive(Debug, Clone)]
+pub struct EthRpc {
+    pub url: String,
+    pub limits: Limits,
+}
+
+impl EthRpc {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            limits: Limits::default(),
+        }
+    }
+
+    fn block(&self, tag: &str) -> Result<Option<L1Reference>> {
+        #[derive(Deserialize)]
+        struct Block {
+            number: String,
+            hash: Hash256,
+        }
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(default)]
+            result: Option<Block>,
+            #[serde(default)]
+            error: Option<serde_json::Value>,
+        }
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_getBlockByNumber",
+            "params": [tag, false],
+        });
+        let body = post_json(&self.url, &serde_json::to_vec(&request)?, &self.limits)?;
+        let response: Response = from_json_slice(&body)
+            .with_context(|| format!("parsing eth_getBlockByNumber({tag}) response"))?;
+        if let Some(error) = response.error {
+            anyhow::bail!("eth_getBlockByNumber({tag}) failed: {error}");
+        }
+        response
+            .result
+            .map(|block| {
+                let number = u64::from_str_radix(strip_hex_prefix(&block.number), 16)
+                    .with_context(|| format!("L1 block number {:?}", block.number))?;
+                Ok(L1Reference {
+                    number,
+                    hash: block.hash,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl L1Client for EthRpc {
+    fn finalized(&self) -> Result<L1Reference> {
+        self.block("finalized")?
+            .context("L1 node reported no finalized block")
+    }
+
+    fn block_hash(&self, number: u64) -> Result<Option<Hash256>> {
+        Ok(self
+            .block(&format!("0x{number:x}"))?
+            .map(|block| block.hash))
+    }
+}
+
+pub struct ChainHeadTracker<'a> {
+    client: &'a dyn L1Client,
+}
+
+impl<'a> ChainHeadTracker<'a> {
+    pub fn new(client: &'a dyn L1Client) -> Self {
+        Self { client }
+    }
+
+    /// The L1 block to bind a proof to: `observed`, the block the public
+    /// inputs were read at, else the current finalized head. `observed` must
+    /// be finalized and still canonical.
+    pub fn observe(&self, observed: Option<L1Reference>) -> Result<L1Reference> {
+        let finalized = self.client.finalized()?;
+        let Some(observed) = observed else {
+            return Ok(finalized);
+        };
+        if observed.number > finalized.number {
+            anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!(
+                    "L1 block {} is not finalized yet (finalized head is {})",
+                    observed.number, finalized.number
+                )
+            ));
+        }
+        self.ensure_canonical(&observed)?;
+        Ok(observed)
+    }
+
+    /// Fails if `reference` is no longer on the canonical chain.
+    pub fn ensure_canonical(&self, reference: &L1Reference) -> Result<()> {
+        match self.client.block_hash(reference.number)? {
+            Some(hash) if hash == reference.hash => Ok(()),
+            Some(hash) => anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!(
+                    "L1 block {} was reorged: expected {}, chain has {hash}",
+                    reference.number, reference.hash
+                )
+            )),
+            None => anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!("L1 block {} is no longer on the chain", reference.number)
+            )),
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Experiment harness for advice-column layouts of the residual check.
+//!
+//! The folded circuit lays every `folded, pq, diff` triple out in a single
+//! advice column. [`LayoutCircuit`] proves the same statement, the sum of
+//! squared residuals over a witness, with its components split across
+//! `columns` lanes that run in parallel, each using either:
+//!
+//! * simple gates, five rows per component (`folded, pq, diff, diff^2, acc`)
+//!   with three degree-2 constraints, or
+//! * a fused gate, three rows per component (`folded, pq, acc`) with a single
+//!   `acc' = acc + (folded - pq)^2` constraint.
+//!
+//! More lanes lower `k` and prover FFT sizes but add commitments and
+//! evaluations to every proof. [`compare`] keys and proves each variant with
+//! throwaway params and reports `k`, proof size and timings.
+//!
+//! Lane layout, one lane per advice column:
+//!
+//! ```text
+//! row 0                 0                  s_zero
+//! row 1 + c * rows      folded_c           s_step (fused or simple)
+//! ...                   acc_c              running sum of squared residuals
+//! ```
+//!
+//! A final region in the first column adds up the lane sums and constrains
+//! the total to the single instance.
+
+use std::{fmt, str::FromStr, time::Instant};
+
+use anyhow::Result;
+use halo2_proofs::{
+    circuit::{Layouter, Region, SimpleFloorPlanner, Value},
+    plonk::{
+        keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error, Instance, Selector,
+        VerifyingKey,
+    },
+    poly::{kzg::commitment::ParamsKZG, Rotation},
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::Serialize;
+
+use crate::{prove::create_circuit_proof, verify::verify_with_keys};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutVariant {
+    pub columns: usize,
+    pub fused: bool,
+}
+
+impl Default for LayoutVariant {
+    /// The folded circuit's own layout.
+    fn default() -> Self {
+        Self {
+            columns: 1,
+            fused: false,
+        }
+    }
+}
+
+impl LayoutVariant {
+    fn rows_per_component(self) -> usize {
+        if self.fused {
+            3
+        } else {
+            5
+        }
+    }
+
+    /// Rows used by `components` residuals, before halo2's blinding rows.
+    pub fn rows(self, components: usize) -> usize {
+        let per_lane = components.div_ceil(self.columns.max(1));
+        1 + per_lane * self.rows_per_component() + 2 * self.columns.max(1)
+    }
+}
+
+impl fmt::Display for LayoutVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gates = if self.fused { "fused" } else { "simple" };
+        write!(f, "{}-column {gates}", self.columns)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LayoutConfig {
+    lanes: Vec<Column<Advice>>,
+    instance: Column<Instance>,
+    s_zero: Selector,
+    s_step: Selector,
+    s_combine: Selector,
+}
+
+/// Sum of squared residuals over flattened components in a chosen layout.
+#[derive(Clone, Debug, Default)]
+pub struct LayoutCircuit {
+    pub variant: LayoutVariant,
+    pub folded: Vec<Fr>,
+    pub pq: Vec<Fr>,
+}
+
+impl LayoutCircuit {
+    pub fn new(variant: LayoutVariant, folded: Vec<Fr>, pq: Vec<Fr>) -> Self {
+        Self {
+            variant,
+            folded,
+            pq,
+        }
+    }
+
+    /// The single instance: `sum((folded - pq)^2)`.
+    pub fn residual_sum(&self) -> Fr {
+        self.folded
+            .iter()
+            .zip(self.pq.iter())
+            .map(|(folded, pq)| (*folded - *pq).square())
+            .fold(Fr::zero(), |acc, square| acc + square)
+    }
+}
+
+impl Circuit<Fr> for LayoutCircuit {
+    type Config = LayoutConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = LayoutVariant;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            variant: self.variant,
+            folded: vec![Fr::zero(); self.folded.len()],
+            pq: vec![Fr::zero(); self.pq.len()],
+        }
+    }
+
+    fn params(&self) -> Self::Params {
+        self.variant
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        Self::configure_with_params(meta, LayoutVariant::default())
+    }
+
+    fn configure_with_params(
+        meta: &mut ConstraintSystem<Fr>,
+        variant: Self::Params,
+    ) -> Self::Config {
+        let lanes: Vec<_> = (0..variant.columns.max(1))
+            .map(|_| meta.advice_column())
+            .collect();
+        let instance = meta.instance_column();
+        for lane in &lanes {
+            meta.enable_equality(*lane);
+        }
+        meta.enable_equality(instance);
+        let s_zero = meta.selector();
+        let s_step = meta.selector();
+        let s_combine = meta.selector();
+
+        for lane in lanes.iter().copied() {
+            meta.create_gate("layout_zero", |meta| {
+                let s = meta.query_selector(s_zero);
+                vec![s * meta.query_advice(lane, Rotation::cur())]
+            });
+            meta.create_gate("layout_step", |meta| {
+                let s = meta.query_selector(s_step);
+                let folded = meta.query_advice(lane, Rotation::cur());
+                let pq = meta.query_advice(lane, Rotation::next());
+                let previous = meta.query_advice(lane, Rotation::prev());
+                if variant.fused {
+                    let acc = meta.query_advice(lane, Rotation(2));
+                    let diff = folded - pq;
+                    vec![s * (acc - previous - diff.clone() * diff)]
+                } else {
+                    let diff = meta.query_advice(lane, Rotation(2));
+                    let square = meta.query_advice(lane, Rotation(3));
+                    let acc = meta.query_advice(lane, Rotation(4));
+                    vec![
+                        s.clone() * (folded - pq - diff.clone()),
+                        s.clone() * (diff.clone() * diff - square.clone()),
+                        s * (acc - previous - square),
+                    ]
+                }
+            });
+        }
+
+        meta.create_gate("layout_combine", |meta| {
+            let s = meta.query_selector(s_combine);
+            let total = meta.query_advice(lanes[0], Rotation::cur());
+            let lane_sum = meta.query_advice(lanes[0], Rotation::next());
+            let next = meta.query_advice(lanes[0], Rotation(2));
+            vec![s * (next - total - lane_sum)]
+        });
+
+        LayoutConfig {
+            lanes,
+            instance,
+            s_zero,
+            s_step,
+            s_combine,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        if self.folded.len() != self.pq.len() {
+            return Err(Error::Synthesis);
+        }
+        let lanes = config.lanes.len();
+        let rows = self.variant.rows_per_component();
+        let per_lane = self.folded.len().div_ceil(lanes);
+
+        let lane_sums = layouter.assign_region(
+            || "layout_lanes",
+            |mut region: Region<'_, Fr>| {
+                config.s_zero.enable(&mut region, 0)?;
+                let mut sums = Vec::with_capacity(lanes);
+                for (lane_idx, column) in config.lanes.iter().enumerate() {
+                    let mut acc = Fr::zero();
+                    let mut last = region.assign_advice(*column, 0, Value::known(acc)).cell();
+                    for slot in 0..per_lane {
+                        let idx = lane_idx * per_lane + slot;
+                        let folded = self.folded.get(idx).copied().unwrap_or(Fr::zero());
+                        let pq = self.pq.get(idx).copied().unwrap_or(Fr::zero());
+                        let diff = folded - pq;
+                        acc += diff.square();
+                        let row = 1 + slot * rows;
+                        if lane_idx == 0 {
+                            config.s_step.enable(&mut region, row)?;
+                        }
+                        region.assign_advice(*column, row, Value::known(folded));
+                        region.assign_advice(*column, row + 1, Value::known(pq));
+                        if !self.variant.fused {
+                            region.assign_advice(*column, row + 2, Value::known(diff));
+                            region.assign_advice(*column, row + 3, Value::known(diff.square()));
+                        }
+                        last = region
+                            .assign_advice(*column, row + rows - 1, Value::known(acc))
+                            .cell();
+                    }
+                    sums.push((last, acc));
+                }
+                Ok(sums)
+            },
+        )?;
+
+        let column = config.lanes[0];
+        layouter.assign_region(
+            || "layout_combine",
+            |mut region: Region<'_, Fr>| {
+                let (cell, mut total) = lane_sums[0];
+                let mut total_cell = region.assign_advice(column, 0, Value::known(total));
+                region.constrain_equal(total_cell.cell(), cell);
+                for (idx, (cell, sum)) in lane_sums.iter().enumerate().skip(1) {
+                    let row = 2 * idx - 1;
+                    config.s_combine.enable(&mut region, row - 1)?;
+                    let assigned = region.assign_advice(column, row, Value::known(*sum));
+                    region.constrain_equal(assigned.cell(), *cell);
+                    total += sum;
+                    total_cell = region.assign_advice(column, row + 1, Value::known(total));
+                }
+                let public = region.assign_advice_from_instance(
+                    || "layout_total",
+                    config.instance,
+                    0,
+                    column,
+                    2 * lanes - 1,
+                )?;
+                region.constrain_equal(total_cell.cell(), public.cell());
+                Ok(())
+            },
+        )
+    }
+}
+
+/// What [`compare`] optimizes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Objective {
+    ProveTime,
+    ProofSize,
+}
+
+impl FromStr for Objective {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "prove-time" => Ok(Objective::ProveTime),
+            "proof-size" => Ok(Objective::ProofSize),
+            other => anyhow::bail!("unknown objective {other:?} (prove-time or proof-size)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutMeasurement {
+    pub variant: LayoutVariant,
+    pub circuit_k: u32,
+    pub rows: usize,
+    pub proof_bytes: usize,
+    pub keygen_ms: u64,
+    pub prove_ms: u64,
+    pub verify_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutReport {
+    pub components: usize,
+    pub objective: Objective,
+    pub measurements: Vec<LayoutMeasurement>,
+    pub recommendation: LayoutVariant,
+    pub reason: String,
+}
+
+/// Smallest `k` that fits `variant` for `components` residuals.
+pub fn layout_k(variant: LayoutVariant, components: usize) -> u32 {
+    let mut meta = ConstraintSystem::<Fr>::default();
+    LayoutCircuit::configure_with_params(&mut meta, variant);
+    let needed = variant.rows(components) + meta.minimum_rows();
+    needed.next_power_of_two().trailing_zeros().max(4)
+}
+
+/// Verifying key for `variant` over `components` residuals, with throwaway params.
+pub fn layout_vk(variant: LayoutVariant, components: usize) -> Result<VerifyingKey<G1Affine>> {
+    let circuit = LayoutCircuit::new(
+        variant,
+        vec![Fr::zero(); components],
+        vec![Fr::zero(); components],
+    );
+    let mut rng = ChaCha20Rng::from_seed([7u8; 32]);
+    let params = ParamsKZG::<Bn256>::setup(layout_k(variant, components), &mut rng);
+    Ok(keygen_vk(&params, &circuit)?)
+}
+
+/// Keys, proves and verifies one variant with throwaway params.
+pub fn measure(variant: LayoutVariant, folded: &[Fr], pq: &[Fr]) -> Result<LayoutMeasurement> {
+    let circuit = LayoutCircuit::new(variant, folded.to_vec(), pq.to_vec());
+    let circuit_k = layout_k(variant, folded.len());
+    let instances = [circuit.residual_sum()];
+
+    let started = Instant::now();
+    let mut rng = ChaCha20Rng::from_seed([7u8; 32]);
+    let params = ParamsKZG::<Bn256>::setup(circuit_k, &mut rng);
+    let blank = circuit.without_witnesses();
+    let vk = keygen_vk(&params, &blank)?;
+    let pk = keygen_pk(&params, vk, &blank)?;
+    let keygen_ms = started.elapsed().as_millis() as u64;
+
+    let started = Instant::now();
+    let proof = create_circuit_proof(&params, &pk, &circuit, &instances)?;
+    let prove_ms = started.elapsed().as_millis() as u64;
+
+    let started = Instant::now();
+    verify_with_keys(&params, pk.get_vk(), &instances, &proof)?;
+    let verify_ms = started.elapsed().as_millis() as u64;
+
+    Ok(LayoutMeasurement {
+        variant,
+        circuit_k,
+        rows: variant.rows(folded.len()),
+        proof_bytes: proof.len(),
+        keygen_ms,
+        prove_ms,
+        verify_ms,
+    })
+}
+
+/// Measures every variant on the same components and recommends one.
+pub fn compare(
+    variants: &[LayoutVariant],
+    folded: &[Fr],
+    pq: &[Fr],
+    objective: Objective,
+    mut on_measured: impl FnMut(&LayoutMeasurement),
+) -> Result<LayoutReport> {
+    if variants.is_empty() {
+        anyhow::bail!("no layouts to compare");
+    }
+    if folded.len() != pq.len() {
+        anyhow::bail!("folded and pq components differ in length");
+    }
+    let mut measurements = Vec::with_capacity(variants.len());
+    for variant in variants {
+        let measurement = measure(*variant, folded, pq)?;
+        on_measured(&measurement);
+        measurements.push(measurement);
+    }
+    let best = measurements
+        .iter()
+        .min_by_key(|m| match objective {
+            Objective::ProveTime => (m.prove_ms, m.proof_bytes as u64),
+            Objective::ProofSize => (m.proof_bytes as u64, m.prove_ms),
+        })
+        .expect("at least one measurement");
+    let baseline = &measurements[0];
+    let reason = format!(
+        "{} proves in {} ms with a {}-byte proof at k={} (first variant {}: {} ms, {} bytes, k={})",
+        best.variant,
+        best.prove_ms,
+        best.proof_bytes,
+        best.circuit_k,
+        baseline.variant,
+        baseline.prove_ms,
+        baseline.proof_bytes,
+        baseline.circuit_k
+    );
+    Ok(LayoutReport {
+        components: folded.len(),
+        objective,
+        recommendation: best.variant,
+        reason,
+        measurements,
+    })
+}
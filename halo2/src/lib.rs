@@ -1,8 +1,85 @@
+pub mod ann;
+pub mod audit;
+pub mod backfill;
+pub mod build_info;
+pub mod bytes;
+pub mod cancel;
+pub mod capabilities;
+#[cfg(feature = "chaos")]
+pub mod chaos;
 pub mod circuit;
+pub mod codebook;
+pub mod compat;
+pub mod container;
+pub mod crosscheck;
+pub mod delta;
+pub mod encryption;
+pub mod epsilon;
+pub mod errors;
+pub mod export;
+pub mod fixed_point;
+pub mod gadgets;
+pub mod hashing;
+pub mod hiding;
+pub mod http;
+#[cfg(feature = "index")]
+pub mod index;
+pub mod inspect;
 pub mod io;
+pub mod jobs;
+pub mod keygen;
 pub mod keys;
+pub mod l1;
+pub mod layout;
+pub mod merkle;
+pub mod metadata;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod opening;
+pub mod opq;
+pub mod permutation;
+pub mod platform;
+pub mod policy;
+pub mod poseidon;
+pub mod proof_size;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod prove;
+pub mod prover;
 pub mod public_inputs;
+pub mod quantization;
+pub mod quote;
+pub mod reconstruct;
+pub mod reference;
+pub mod replay;
+pub mod replication;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod scalar;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod search;
+pub mod selftest;
+pub mod settlement;
+pub mod shape;
+pub mod shard;
+pub mod signing;
+pub mod slo;
+pub mod sparse;
+pub mod srs;
+pub mod storage;
+pub mod summary;
+pub mod synthetic;
+pub mod transcript;
+pub mod upload;
+pub mod vector_commitment;
+pub mod verify;
+pub mod watch;
+pub mod witness_commitment;
 
-pub use circuit::FoldedCircuit;
+pub use circuit::{FoldedCircuit, FoldedParams};
 pub use io::{load_witness, WitnessData};
+pub use metadata::{ProofMetadata, ProofMetadataV1};
+pub use prover::Prover;
 pub use public_inputs::{load_public_inputs, ParsedPublicInputs};
+pub use storage::{LocalStorage, MemoryStorage, Storage};
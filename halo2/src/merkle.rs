@@ -0,0 +1,67 @@
+//! Poseidon Merkle trees over folded vector rows.
+//!
+//! Leaves are `poseidon::hash_leaf(row)`. Each level pairs adjacent nodes with
+//! `poseidon::hash_node`; an unpaired last node is promoted unchanged, so no
+//! padding leaves are needed. This mirrors `PoseidonChip::merkle_root`.
+
+use halo2curves::bn256::Fr;
+
+use crate::poseidon::{hash_leaf, hash_node};
+
+pub fn leaves(rows: &[Vec<Fr>]) -> Vec<Fr> {
+    rows.iter().map(|row| hash_leaf(row)).collect()
+}
+
+/// All tree levels, leaves first and the root level last.
+pub fn levels(leaves: Vec<Fr>) -> Vec<Vec<Fr>> {
+    let mut levels = vec![leaves];
+    while levels.last().map(|level| level.len() > 1).unwrap_or(false) {
+        let current = levels.last().expect("level exists");
+        let next = current
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(*left, *right),
+                [single] => *single,
+                _ => unreachable!("chunks(2) yields one or two nodes"),
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// Root of the tree over `leaves`; zero for an empty tree.
+pub fn root(leaves: Vec<Fr>) -> Fr {
+    levels(leaves)
+        .last()
+        .and_then(|level| level.first().copied())
+        .unwrap_or(Fr::zero())
+}
+
+pub fn vector_root(rows: &[Vec<Fr>]) -> Fr {
+    root(leaves(rows))
+}
+
+/// Authentication path for leaf `index` as `(sibling, node_is_right)` steps.
+/// `sibling` is `None` where the node was promoted without hashing.
+pub fn path(levels: &[Vec<Fr>], mut index: usize) -> Vec<(Option<Fr>, bool)> {
+    let mut steps = Vec::new();
+    for level in levels.iter().take(levels.len().saturating_sub(1)) {
+        let is_right = index % 2 == 1;
+        let sibling_index = if is_right { index - 1 } else { index + 1 };
+        steps.push((level.get(sibling_index).copied(), is_right));
+        index /= 2;
+    }
+    steps
+}
+
+/// Recomputes the root from a leaf and its path.
+pub fn root_from_path(leaf: Fr, path: &[(Option<Fr>, bool)]) -> Fr {
+    path.iter().fold(leaf, |node, (sibling, is_right)| {
+        match (sibling, is_right) {
+            (None, _) => node,
+            (Some(sibling), true) => hash_node(*sibling, node),
+            (Some(sibling), false) => hash_node(node, *sibling),
+        }
+    })
+}
@@ -0,0 +1,294 @@
+//! Versioned proof metadata shared by the prover, daemons and bindings.
+//!
+//! Metadata is serialized as a flat JSON object carrying a numeric `version`
+//! field. Unknown fields are ignored and documents without a version are read
+//! as V1, so older and newer producers interoperate as long as the version is
+//! understood.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    build_info::BuildInfo,
+    compat::{self, Incompatibility, CIRCUIT_VERSION, PROOF_FORMAT_VERSION},
+    errors::{Coded, ErrorCode},
+    fixed_point::DEFAULT_SCALE,
+    hashing::{digest_hex, HashAlgorithm},
+    io::Provenance,
+    l1::L1Reference,
+    platform::from_json_slice,
+    public_inputs::field_to_hex,
+    storage::write_atomic,
+    transcript::TranscriptKind,
+};
+
+pub const CURRENT_METADATA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofMetadataV1 {
+    pub circuit_k: u32,
+    #[serde(default)]
+    pub block_height: Option<u64>,
+    pub instances: Vec<String>,
+    pub proof_bytes: usize,
+    pub proof_digest: String,
+    #[serde(default)]
+    pub witness_digest: Option<String>,
+    pub prover_version: String,
+    pub created_at: u64,
+    /// See [`compat`]; absent in metadata written before versions were recorded.
+    #[serde(default = "legacy_version")]
+    pub proof_format_version: u32,
+    #[serde(default = "legacy_version")]
+    pub circuit_version: u32,
+    /// `proofDigest` of the previous block's proof, linking proofs into a
+    /// chain that [`check_lineage`] walks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_proof_digest: Option<String>,
+    /// The witness's `provenance` block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// The proof is a stand-in from a simulating prover; every verifier
+    /// rejects it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub simulated: bool,
+    /// Fiat-Shamir transcript the proof was made under; absent means blake2b.
+    #[serde(default, skip_serializing_if = "TranscriptKind::is_default")]
+    pub transcript: TranscriptKind,
+    /// Finalized L1 block the public inputs were observed at, see [`crate::l1`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_reference: Option<L1Reference>,
+    /// Hash behind `proofDigest` and `previousProofDigest`; absent means blake3.
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_default")]
+    pub digest_algorithm: HashAlgorithm,
+    /// The prover build that made the proof, see [`crate::build_info`];
+    /// absent in metadata written before builds were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildInfo>,
+    /// Fixed-point scale the witness was proven at; absent means
+    /// [`DEFAULT_SCALE`]. See [`ProofMetadataV1::check_scale`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u64>,
+}
+
+impl ProofMetadataV1 {
+    pub fn new(circuit_k: u32, block_height: Option<u64>, instances: &[Fr], proof: &[u8]) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        Self {
+            circuit_k,
+            block_height,
+            instances: instances.iter().map(field_to_hex).collect(),
+            proof_bytes: proof.len(),
+            proof_digest: digest_hex(proof),
+            witness_digest: None,
+            prover_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at,
+            proof_format_version: PROOF_FORMAT_VERSION,
+            circuit_version: CIRCUIT_VERSION,
+            previous_proof_digest: None,
+            provenance: None,
+            simulated: false,
+            transcript: TranscriptKind::default(),
+            l1_reference: None,
+            digest_algorithm: HashAlgorithm::default(),
+            build: Some(BuildInfo::current()),
+            scale: None,
+        }
+    }
+
+    /// Links this proof to the previous block's proof by its `proofDigest`.
+    pub fn with_previous_digest(mut self, digest: Option<&str>) -> Self {
+        self.previous_proof_digest = digest.map(|digest| {
+            digest
+                .trim_start_matches("0x")
+                .trim_start_matches("0X")
+                .to_ascii_lowercase()
+        });
+        self
+    }
+
+    /// Records where the proven witness came from.
+    pub fn with_provenance(mut self, provenance: Option<&Provenance>) -> Self {
+        self.provenance = provenance.cloned();
+        self
+    }
+
+    /// Marks the proof as simulated, see [`crate::prove::simulate_circuit_proof`].
+    pub fn with_simulated(mut self, simulated: bool) -> Self {
+        self.simulated = simulated;
+        self
+    }
+
+    pub fn with_transcript(mut self, transcript: TranscriptKind) -> Self {
+        self.transcript = transcript;
+        self
+    }
+
+    pub fn with_l1_reference(mut self, l1_reference: Option<L1Reference>) -> Self {
+        self.l1_reference = l1_reference;
+        self
+    }
+
+    /// Re-digests `proof` under `algorithm`, e.g. sha256 for a contract that
+    /// checks the lineage on-chain.
+    pub fn with_digest_algorithm(mut self, algorithm: HashAlgorithm, proof: &[u8]) -> Self {
+        self.proof_digest = hex::encode(algorithm.digest(proof).as_bytes());
+        self.digest_algorithm = algorithm;
+        self
+    }
+
+    /// Records the fixed-point scale of the keys; the default is left out.
+    pub fn with_scale(mut self, scale: u64) -> Self {
+        self.scale = (scale != DEFAULT_SCALE).then_some(scale);
+        self
+    }
+
+    /// Rejects a proof made at another fixed-point scale than `expected`,
+    /// the scale of the keys it is about to be verified with. Values, SLA
+    /// bounds and residuals mean different things at different scales.
+    pub fn check_scale(&self, expected: u64) -> Result<()> {
+        let recorded = self.scale.unwrap_or(DEFAULT_SCALE);
+        if recorded != expected {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Incompatible,
+                format!(
+                    "proof was made at fixed-point scale {recorded}, these keys use {expected}"
+                )
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether this build can verify the proof the metadata describes.
+    pub fn check_compatibility(&self) -> Result<(), Incompatibility> {
+        compat::check(self.proof_format_version, self.circuit_version)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProofMetadata {
+    V1(ProofMetadataV1),
+}
+
+impl ProofMetadata {
+    pub fn version(&self) -> u32 {
+        match self {
+            ProofMetadata::V1(_) => 1,
+        }
+    }
+
+    /// Returns the metadata upgraded to the latest layout.
+    pub fn into_latest(self) -> ProofMetadataV1 {
+        match self {
+            ProofMetadata::V1(inner) => inner,
+        }
+    }
+}
+
+impl From<ProofMetadataV1> for ProofMetadata {
+    fn from(inner: ProofMetadataV1) -> Self {
+        ProofMetadata::V1(inner)
+    }
+}
+
+#[derive(Serialize)]
+struct Tagged<'a, T> {
+    version: u32,
+    #[serde(flatten)]
+    inner: &'a T,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    #[serde(flatten)]
+    rest: serde_json::Map<String, serde_json::Value>,
+}
+
+fn legacy_version() -> u32 {
+    compat::LEGACY_VERSION
+}
+
+impl Serialize for ProofMetadata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ProofMetadata::V1(inner) => Tagged { version: 1, inner }.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ProofMetadata {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let envelope = Envelope::deserialize(deserializer)?;
+        match envelope.version {
+            1 => serde_json::from_value(serde_json::Value::Object(envelope.rest))
+                .map(ProofMetadata::V1)
+                .map_err(D::Error::custom),
+            other => Err(D::Error::custom(format!(
+                "unsupported proof metadata version {other} (latest known is {CURRENT_METADATA_VERSION})"
+            ))),
+        }
+    }
+}
+
+/// Checks one link of a proof lineage: `current` names `previous_proof` as
+/// its predecessor, and recorded block heights are consecutive. Each digest
+/// is checked under the algorithm its metadata records.
+pub fn check_lineage(
+    previous_proof: &[u8],
+    previous: &ProofMetadataV1,
+    current: &ProofMetadataV1,
+) -> Result<()> {
+    let hex_digest =
+        |algorithm: HashAlgorithm| hex::encode(algorithm.digest(previous_proof).as_bytes());
+    if previous.proof_digest != hex_digest(previous.digest_algorithm) {
+        anyhow::bail!(
+            "previous proof does not match its metadata proofDigest {}",
+            previous.proof_digest
+        );
+    }
+    let digest = hex_digest(current.digest_algorithm);
+    match &current.previous_proof_digest {
+        None => anyhow::bail!("metadata has no previousProofDigest"),
+        Some(linked) if *linked != digest => anyhow::bail!(
+            "previousProofDigest {linked} does not match the previous proof ({digest})"
+        ),
+        Some(_) => {}
+    }
+    if let (Some(previous_height), Some(height)) = (previous.block_height, current.block_height) {
+        if height != previous_height + 1 {
+            anyhow::bail!("block {height} does not follow block {previous_height}");
+        }
+    }
+    Ok(())
+}
+
+/// Location of the metadata document written next to a proof file.
+pub fn sidecar_path(proof_path: &Path) -> PathBuf {
+    let mut name = proof_path.as_os_str().to_owned();
+    name.push(".meta.json");
+    PathBuf::from(name)
+}
+
+pub fn write_sidecar(proof_path: &Path, metadata: &ProofMetadata) -> Result<()> {
+    let path = sidecar_path(proof_path);
+    let bytes = serde_json::to_vec_pretty(metadata)?;
+    write_atomic(&path, &bytes)
+}
+
+pub fn read_sidecar(proof_path: &Path) -> Result<ProofMetadata> {
+    let path = sidecar_path(proof_path);
+    let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+    from_json_slice(&bytes).with_context(|| format!("parsing {:?}", path))
+}
@@ -0,0 +1,120 @@
+//! Async entry points for services running on a tokio runtime.
+//!
+//! Fetching witnesses is I/O; decoding, key loading, proving and verifying
+//! are CPU-bound and run on tokio's blocking pool via `spawn_blocking`, so
+//! runtime worker threads are never held by a proof. Backends that are async
+//! themselves (object stores, RPC endpoints) implement [`AsyncStorage`];
+//! any blocking [`Storage`] can be adapted with [`Blocking`].
+//!
+//! Dropping a proving future cancels its [`CancellationToken`]. The blocking
+//! task stops at its next check; a `create_proof` call already underway runs
+//! to completion and its result is discarded.
+
+use std::{future::Future, path::PathBuf, pin::Pin, sync::Arc};
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+
+use crate::{
+    cancel::CancellationToken,
+    encryption::KeyProvider,
+    io::{decode_witness, WitnessData},
+    platform::from_json_slice,
+    prover::{Prover, ProverOutput},
+    public_inputs::ParsedPublicInputs,
+    storage::Storage,
+    verify::VerifierKeys,
+};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Read side of [`Storage`] for backends with an async client.
+pub trait AsyncStorage: Send + Sync {
+    fn read<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>>;
+}
+
+/// Runs a blocking [`Storage`] on the blocking pool.
+pub struct Blocking<S>(pub Arc<S>);
+
+impl<S: Storage + 'static> AsyncStorage for Blocking<S> {
+    fn read<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Vec<u8>>> {
+        let storage = Arc::clone(&self.0);
+        let key = key.to_string();
+        Box::pin(blocking(move || storage.read(&key)))
+    }
+}
+
+/// Runs `f` on the blocking pool.
+pub async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .context("blocking task panicked or was aborted")?
+}
+
+/// Async [`crate::io::load_witness_with`].
+pub async fn load_witness(
+    storage: &dyn AsyncStorage,
+    key: &str,
+    provider: Option<Arc<dyn KeyProvider>>,
+) -> Result<WitnessData> {
+    let bytes = storage.read(key).await?;
+    let source = key.to_string();
+    blocking(move || decode_witness(&bytes, provider.as_deref(), &source)).await
+}
+
+pub async fn load_public_inputs(
+    storage: &dyn AsyncStorage,
+    key: &str,
+) -> Result<ParsedPublicInputs> {
+    let bytes = storage.read(key).await?;
+    from_json_slice(&bytes).with_context(|| format!("parsing public inputs {key}"))
+}
+
+/// Async [`Prover::load`]; keygen runs on the blocking pool.
+pub async fn load_prover(
+    proving_key: PathBuf,
+    verification_key: PathBuf,
+    circuit_k: u32,
+) -> Result<Prover> {
+    blocking(move || Prover::load(&proving_key, &verification_key, circuit_k)).await
+}
+
+/// Cancels the token when the owning future is dropped before completing.
+struct CancelOnDrop(Option<CancellationToken>);
+
+impl CancelOnDrop {
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = &self.0 {
+            token.cancel();
+        }
+    }
+}
+
+impl Prover {
+    /// [`Prover::prove_with`] on the blocking pool.
+    pub async fn prove_async(
+        &self,
+        witness: WitnessData,
+        public_inputs: ParsedPublicInputs,
+        cancel: CancellationToken,
+    ) -> Result<ProverOutput> {
+        let prover = self.clone();
+        let guard = CancelOnDrop(Some(cancel.clone()));
+        let output = blocking(move || prover.prove_with(&witness, &public_inputs, &cancel)).await;
+        guard.disarm();
+        output
+    }
+}
+
+/// Verifies on the blocking pool.
+pub async fn verify(keys: Arc<VerifierKeys>, instances: Vec<Fr>, proof: Vec<u8>) -> Result<()> {
+    blocking(move || keys.verify(&instances, &proof)).await
+}
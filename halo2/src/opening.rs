@@ -0,0 +1,142 @@
+//! Merkle openings of individual folded vectors against `foldedVectorRoot`.
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256, fixed_point::resolve_scale, io::WitnessData, merkle,
+    permutation::committed_rows, poseidon::hash_leaf, public_inputs::field_to_hex,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathStep {
+    /// Sibling node, or `None` where the node was promoted without hashing.
+    pub sibling: Option<Hash256>,
+    pub is_right: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorOpening {
+    pub root: Hash256,
+    /// Leaf position in the committed tree; under a row permutation this is
+    /// not the vector's index in the witness.
+    pub index: usize,
+    pub vector_count: usize,
+    pub vector: Vec<f64>,
+    /// Fixed-point scale `vector` is hashed at; absent means the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u64>,
+    pub leaf: Hash256,
+    pub path: Vec<PathStep>,
+}
+
+/// Builds the opening for folded vector `index` of `witness`, in the tree
+/// `foldedVectorRoot` commits: the rows in their committed order when the
+/// witness carries a `rowPermutation`.
+pub fn open_vector(witness: &WitnessData, index: usize) -> Result<VectorOpening> {
+    let rows = witness
+        .fixed_point()?
+        .to_field_matrix(&witness.folded_vectors)
+        .context("foldedVectors")?;
+    if index >= rows.len() {
+        anyhow::bail!(
+            "vector index {index} out of range (block has {} vectors)",
+            rows.len()
+        );
+    }
+    let leaves = merkle::leaves(&rows);
+    let leaf = leaves[index];
+    let position = committed_rows(witness, &(0..rows.len()).collect::<Vec<_>>())
+        .iter()
+        .position(|&row| row == index)
+        .expect("committed order is a permutation of the rows");
+    let levels = merkle::levels(committed_rows(witness, &leaves));
+    let root = levels.last().expect("non-empty tree has a root level")[0];
+    let path = merkle::path(&levels, position)
+        .into_iter()
+        .map(|(sibling, is_right)| PathStep {
+            sibling: sibling.as_ref().map(Hash256::from_field),
+            is_right,
+        })
+        .collect();
+    Ok(VectorOpening {
+        root: Hash256::from_field(&root),
+        index: position,
+        vector_count: rows.len(),
+        vector: witness.folded_vectors[index].clone(),
+        scale: witness.scale,
+        leaf: Hash256::from_field(&leaf),
+        path,
+    })
+}
+
+/// Checks that the opened vector hashes to its leaf and that the path leads to
+/// `expected_root`, the block's public `foldedVectorRoot`. The root embedded
+/// in the opening is the opener's claim and proves nothing on its own.
+pub fn verify_opening(opening: &VectorOpening, expected_root: Hash256) -> Result<()> {
+    let fixed_point = resolve_scale(None, opening.scale)?;
+    let values: Vec<_> = opening
+        .vector
+        .iter()
+        .map(|v| fixed_point.saturating_field(*v))
+        .collect();
+    let leaf = hash_leaf(&values);
+    if Hash256::from_field(&leaf) != opening.leaf {
+        anyhow::bail!("opened vector does not hash to the claimed leaf");
+    }
+    let computed = resolve_path(leaf, opening.index, opening.vector_count, &opening.path)?;
+
+    let root = expected_root.to_canonical_field()?;
+    if computed != root {
+        anyhow::bail!(
+            "opening resolves to root {} but expected {}",
+            field_to_hex(&computed),
+            field_to_hex(&root)
+        );
+    }
+    Ok(())
+}
+
+/// Root reached from `leaf` at `index` of a tree over `count` leaves along
+/// `path`, checking every step's direction against the index, that nodes
+/// are promoted exactly where [`merkle::levels`] promotes them, and that the
+/// path reaches the root level.
+pub(crate) fn resolve_path(leaf: Fr, index: usize, count: usize, path: &[PathStep]) -> Result<Fr> {
+    if index >= count {
+        anyhow::bail!("leaf index {index} out of range for a tree of {count} leaves");
+    }
+    let mut steps = Vec::with_capacity(path.len());
+    let mut position = index;
+    let mut width = count;
+    for (level, step) in path.iter().enumerate() {
+        if width <= 1 {
+            anyhow::bail!("path is longer than a tree of {count} leaves");
+        }
+        if step.is_right != (position % 2 == 1) {
+            anyhow::bail!("path direction does not match index {index}");
+        }
+        let promoted = position % 2 == 0 && position + 1 == width;
+        if promoted != step.sibling.is_none() {
+            anyhow::bail!(
+                "path level {level}: a tree of {count} leaves {} node {position}",
+                if promoted { "promotes" } else { "hashes" }
+            );
+        }
+        let sibling = step
+            .sibling
+            .as_ref()
+            .map(Hash256::to_canonical_field)
+            .transpose()
+            .context("invalid sibling in path")?;
+        steps.push((sibling, step.is_right));
+        position /= 2;
+        width = (width + 1) / 2;
+    }
+    if width > 1 {
+        anyhow::bail!("path is shorter than a tree of {count} leaves");
+    }
+    Ok(merkle::root_from_path(leaf, &steps))
+}
@@ -0,0 +1,67 @@
+//! Optimized product quantization: PQ after a learned orthogonal rotation.
+//!
+//! OPQ quantizes `R x` instead of `x`, where `R` is a `dim x dim` rotation
+//! learned with the codebook. An OPQ witness carries `R` as `rotation` and
+//! already-rotated `foldedVectors`, so the residual checks and codebook
+//! lookups run unchanged in the rotated space; `R` is orthogonal, so
+//! residuals and distances equal those of the original vectors. A circuit
+//! keyed with `rotation` commits to `R` with Poseidon under
+//! [`ROTATION_DOMAIN`] at [`crate::circuit::FoldedParams::rotation_slot`].
+//! Off-circuit, queries are rotated by `R` before they meet the codebook.
+
+use anyhow::Result;
+use halo2curves::bn256::Fr;
+
+use crate::{
+    errors::{Coded, ErrorCode},
+    poseidon::{domain_capacity, hash_with_capacity, ROTATION_DOMAIN},
+    prove::float_to_field,
+};
+
+/// Largest deviation of `R Rᵀ` from the identity accepted as orthogonal.
+pub const ORTHOGONALITY_TOLERANCE: f64 = 1e-6;
+
+/// Checks that `rotation` is an orthogonal `dim x dim` matrix.
+pub fn validate_rotation(rotation: &[Vec<f64>], dim: usize) -> Result<()> {
+    if rotation.len() != dim || rotation.iter().any(|row| row.len() != dim) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!("rotation must be {dim} x {dim} to match the vectors")
+        ));
+    }
+    for (i, a) in rotation.iter().enumerate() {
+        for (j, b) in rotation.iter().enumerate() {
+            let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+            let expected = if i == j { 1.0 } else { 0.0 };
+            if (dot - expected).abs() > ORTHOGONALITY_TOLERANCE {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::InvalidInput,
+                    format!("rotation is not orthogonal: rows {i} and {j} have dot product {dot}")
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `rotation * vector`.
+pub fn rotate(rotation: &[Vec<f64>], vector: &[f64]) -> Vec<f64> {
+    rotation
+        .iter()
+        .map(|row| row.iter().zip(vector).map(|(r, x)| r * x).sum())
+        .collect()
+}
+
+/// Row-major rotation entries as circuit values.
+pub fn rotation_fields(rotation: &[Vec<f64>]) -> Vec<Fr> {
+    rotation
+        .iter()
+        .flat_map(|row| row.iter().map(|value| float_to_field(*value)))
+        .collect()
+}
+
+/// The public commitment to `rotation`, matching the in-circuit hash.
+pub fn rotation_commitment(rotation: &[Vec<f64>]) -> Fr {
+    let fields = rotation_fields(rotation);
+    hash_with_capacity(domain_capacity(ROTATION_DOMAIN, fields.len()), &fields)
+}
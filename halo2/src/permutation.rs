@@ -0,0 +1,82 @@
+//! Deterministic row shuffling under a committed permutation.
+//!
+//! Vector roots and witness commitments are Merkle trees over the rows in
+//! witness order, so a published root fixes which row was inserted where. A
+//! witness carrying a secret `rowPermutation` salt instead commits its rows
+//! in the order a ChaCha20 Fisher-Yates shuffle seeded from the salt picks.
+//! A circuit keyed with `rowPermutation` lays out the rows in witness order
+//! for the residual checks, proves the committed rows are a permutation of
+//! them (see [`crate::gadgets::shuffle`]) and exposes a Poseidon commitment
+//! to the salt and the order under [`PERMUTATION_DOMAIN`] at
+//! [`crate::circuit::FoldedParams::permutation_slot`]. The proof stays bound
+//! to one order while the roots reveal nothing about insertion order.
+
+use anyhow::Result;
+use halo2curves::bn256::Fr;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    errors::{Coded, ErrorCode},
+    hashing::{Blake3Hasher, Hasher},
+    io::WitnessData,
+    poseidon::{domain_capacity, hash_with_capacity, PERMUTATION_DOMAIN},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct RowPermutation {
+    /// Secret the order derives from; never published.
+    pub salt: Hash256,
+}
+
+impl RowPermutation {
+    /// `order[j]` is the witness row committed at position `j`.
+    pub fn order(&self, rows: usize) -> Vec<usize> {
+        let mut hasher = Blake3Hasher::derive_key("yysfold row permutation v1");
+        hasher.update(self.salt.as_bytes());
+        let mut rng = ChaCha20Rng::from_seed(hasher.finalize().0);
+        let mut order: Vec<usize> = (0..rows).collect();
+        for i in (1..rows).rev() {
+            order.swap(i, rng.gen_range(0..=i));
+        }
+        order
+    }
+}
+
+/// The witness's `rowPermutation`, which permuted circuits need.
+pub fn row_permutation(witness: &WitnessData) -> Result<&RowPermutation> {
+    witness.row_permutation.as_ref().ok_or_else(|| {
+        Coded::new(
+            ErrorCode::InvalidInput,
+            "row permutation mode needs a secret rowPermutation salt in the witness",
+        )
+        .into()
+    })
+}
+
+/// `rows` in committed order.
+pub fn permute<T: Clone>(order: &[usize], rows: &[T]) -> Vec<T> {
+    order.iter().map(|&index| rows[index].clone()).collect()
+}
+
+/// Rows of `witness` in the order its roots commit them: shuffled when it
+/// carries a `rowPermutation`, as is otherwise.
+pub fn committed_rows<T: Clone>(witness: &WitnessData, rows: &[T]) -> Vec<T> {
+    match &witness.row_permutation {
+        Some(permutation) => permute(&permutation.order(rows.len()), rows),
+        None => rows.to_vec(),
+    }
+}
+
+/// The public commitment to a permutation, matching the in-circuit hash over
+/// the salt and then the order.
+pub fn permutation_commitment(permutation: &RowPermutation, order: &[usize]) -> Fr {
+    let mut inputs = Vec::with_capacity(1 + order.len());
+    inputs.push(permutation.salt.to_field_reduced());
+    inputs.extend(order.iter().map(|&index| Fr::from(index as u64)));
+    hash_with_capacity(domain_capacity(PERMUTATION_DOMAIN, inputs.len()), &inputs)
+}
@@ -0,0 +1,127 @@
+//! Platform differences in file handling.
+//!
+//! Witnesses are often generated on Windows workstations and proved on Linux,
+//! so the tools accept what either side produces:
+//!
+//! * [`normalize`] makes Windows paths absolute with the `\\?\` prefix (and UNC
+//!   shares `\\?\UNC\`), which lifts the 260-character `MAX_PATH` limit on
+//!   deeply nested key and proof directories. Elsewhere paths pass through.
+//! * [`from_json_slice`] skips the UTF-8 byte order mark Windows editors
+//!   prepend. CRLF line endings are already JSON whitespace.
+//!
+//! Temporary files are created next to their destination (see
+//! [`crate::storage::AtomicFile`]) rather than under a hard-coded `/tmp`.
+
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// `path` in the form the platform's file APIs handle best.
+#[cfg(windows)]
+pub fn normalize(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let raw = absolute.as_os_str().to_string_lossy();
+    if raw.starts_with(r"\\?\") || !absolute.is_absolute() {
+        return absolute;
+    }
+    // Verbatim paths skip separator normalization, so convert them first.
+    let raw = raw.replace('/', r"\");
+    match raw.strip_prefix(r"\\") {
+        Some(share) => PathBuf::from(format!(r"\\?\UNC\{share}")),
+        None => PathBuf::from(format!(r"\\?\{raw}")),
+    }
+}
+
+/// `path` in the form the platform's file APIs handle best.
+#[cfg(not(windows))]
+pub fn normalize(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// `bytes` without a leading UTF-8 byte order mark.
+pub fn strip_bom(bytes: &[u8]) -> &[u8] {
+    bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)
+}
+
+/// `serde_json::from_slice`, tolerating a byte order mark.
+pub fn from_json_slice<T: DeserializeOwned>(bytes: &[u8]) -> serde_json::Result<T> {
+    serde_json::from_slice(strip_bom(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write, sync::Barrier, thread};
+
+    use super::*;
+    use crate::storage::AtomicFile;
+
+    #[test]
+    fn json_with_bom_and_crlf_parses() {
+        let bytes = b"\xEF\xBB\xBF{\r\n  \"k\": 4,\r\n  \"rows\": [1, 2]\r\n}\r\n";
+        assert_eq!(strip_bom(bytes), &bytes[3..]);
+        assert_eq!(strip_bom(&bytes[3..]), &bytes[3..]);
+        let value: serde_json::Value = from_json_slice(bytes).unwrap();
+        assert_eq!(value, serde_json::json!({"k": 4, "rows": [1, 2]}));
+    }
+
+    #[test]
+    fn racing_atomic_files_leave_one_complete_file() {
+        let dir = std::env::temp_dir().join(format!("yysfold-platform-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("proof.bin");
+        let writers = 8;
+        let barrier = Barrier::new(writers);
+        let created: Vec<bool> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..writers)
+                .map(|writer| {
+                    let (path, barrier) = (&path, &barrier);
+                    scope.spawn(move || {
+                        let mut file = AtomicFile::create(path).unwrap();
+                        file.write_all(&[writer as u8; 4096]).unwrap();
+                        barrier.wait();
+                        file.commit_new().unwrap()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        let winner = created.iter().position(|&created| created).unwrap();
+        assert_eq!(created.iter().filter(|&&created| created).count(), 1);
+        assert_eq!(fs::read(&path).unwrap(), [winner as u8; 4096]);
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"replaced").unwrap();
+        file.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"replaced");
+        assert_eq!(
+            fs::read_dir(&dir).unwrap().count(),
+            1,
+            "temporary files left"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn normalize_prefixes_drive_and_unc_paths() {
+        assert_eq!(
+            normalize(Path::new(r"C:\keys\folded.vk")),
+            PathBuf::from(r"\\?\C:\keys\folded.vk")
+        );
+        assert_eq!(
+            normalize(Path::new("C:/keys/nested/../folded.vk")),
+            PathBuf::from(r"\\?\C:\keys\folded.vk")
+        );
+        assert_eq!(
+            normalize(Path::new(r"\\prover\share\keys\folded.vk")),
+            PathBuf::from(r"\\?\UNC\prover\share\keys\folded.vk")
+        );
+        let verbatim = Path::new(r"\\?\C:\keys\folded.vk");
+        assert_eq!(normalize(verbatim), verbatim);
+    }
+}
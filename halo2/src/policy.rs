@@ -0,0 +1,199 @@
+//! Key usage policy: which circuits an operator lets the prover key and prove.
+//!
+//! The on-chain verifier is deployed for particular circuits, so a proof for
+//! any other `k`, shape or instance encoding is wasted work. A policy file
+//! names what is allowed and the prover and prover-server reject everything
+//! else before keygen:
+//!
+//! ```json
+//! {
+//!   "maxK": 18,
+//!   "allowedShapes": [
+//!     { "dim": 768, "subvectors": 96, "centroids": 256, "modes": ["pqCodes", "vectorRoot"] }
+//!   ],
+//!   "allowedEncodings": ["instanceHash"]
+//! }
+//! ```
+//!
+//! Absent fields allow anything. A shape rule matches when every field it
+//! sets equals the circuit's; `modes` lists the modes the circuit may enable.
+//! `vectors` and `dim` are checked against the witness when it is known, and
+//! against the keyed shape otherwise.
+
+use std::{fmt, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit::FoldedParams,
+    errors::{Coded, ErrorCode},
+    platform::{from_json_slice, normalize},
+    shape::WitnessShape,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_shapes: Option<Vec<ShapeRule>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_encodings: Option<Vec<InstanceEncoding>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ShapeRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vectors: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dim: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subvectors: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub centroids: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonzeros: Option<usize>,
+    /// Modes the circuit may enable, as named by [`circuit_modes`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modes: Option<Vec<String>>,
+}
+
+/// How the public values reach the verifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InstanceEncoding {
+    /// Every public value is its own instance.
+    Values,
+    /// `sha256(public values) mod p` is the only instance.
+    InstanceHash,
+}
+
+impl InstanceEncoding {
+    pub fn of(params: &FoldedParams) -> Self {
+        if params.instance_hash {
+            InstanceEncoding::InstanceHash
+        } else {
+            InstanceEncoding::Values
+        }
+    }
+}
+
+impl fmt::Display for InstanceEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InstanceEncoding::Values => "values",
+            InstanceEncoding::InstanceHash => "instanceHash",
+        })
+    }
+}
+
+/// Modes `params` enables, named after the prover flags in camelCase.
+pub fn circuit_modes(params: &FoldedParams) -> Vec<&'static str> {
+    [
+        ("vectorRoot", params.vector_root),
+        ("pqCodes", params.pq_codes),
+        ("codebookCommitment", params.codebook_commitment.is_some()),
+        ("lineage", params.lineage),
+        ("beacon", params.beacon),
+        ("sparse", params.nonzeros.is_some()),
+        ("compressionStats", params.compression_stats),
+        ("subvectorEpsilons", params.subvector_epsilons),
+        ("delta", params.delta.is_some()),
+        ("witnessCommitment", params.witness_commitment),
+        ("rotation", params.rotation),
+        ("residualStage", params.residual_centroids.is_some()),
+        ("scalar", params.scalar),
+        ("rowPermutation", params.row_permutation),
+        ("hiding", params.hiding),
+        ("vectorCommitments", params.vector_commitments),
+        ("slaBound", params.sla_bound),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = normalize(path);
+        let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+        from_json_slice(&bytes).with_context(|| format!("parsing policy {:?}", path))
+    }
+
+    /// Loads the policy at `path`, or allows everything when there is none.
+    pub fn load_optional(path: Option<&Path>) -> Result<Self> {
+        path.map_or_else(|| Ok(Self::default()), Self::load)
+    }
+
+    /// Checks keys for `params` at `circuit_k`, against the witness shape
+    /// when it is known and the keyed one otherwise.
+    pub fn check(
+        &self,
+        params: &FoldedParams,
+        circuit_k: u32,
+        witness: Option<WitnessShape>,
+    ) -> Result<()> {
+        if let Some(max_k) = self.max_k {
+            if circuit_k > max_k {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::PolicyViolation,
+                    format!("k={circuit_k} exceeds the policy's maxK {max_k}")
+                ));
+            }
+        }
+        let encoding = InstanceEncoding::of(params);
+        if let Some(allowed) = &self.allowed_encodings {
+            if !allowed.contains(&encoding) {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::PolicyViolation,
+                    format!("instance encoding {encoding} is not in the policy's allowedEncodings")
+                ));
+            }
+        }
+        if let Some(rules) = &self.allowed_shapes {
+            let shape = witness.unwrap_or(WitnessShape {
+                vectors: params.vectors,
+                dim: params.dim,
+            });
+            if !rules.iter().any(|rule| rule.matches(params, shape)) {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::PolicyViolation,
+                    format!(
+                        "circuit ({}-dim × {} vectors, {} subvectors × {} centroids, modes [{}]) matches none \
+                         of the policy's allowedShapes",
+                        shape.dim,
+                        shape.vectors,
+                        params.subvectors,
+                        params.centroids,
+                        circuit_modes(params).join(", ")
+                    )
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ShapeRule {
+    /// Whether the rule admits `params` proving vectors of `shape`.
+    pub fn matches(&self, params: &FoldedParams, shape: WitnessShape) -> bool {
+        let modes_allowed = self.modes.as_ref().map_or(true, |modes| {
+            circuit_modes(params)
+                .iter()
+                .all(|mode| modes.iter().any(|allowed| allowed == mode))
+        });
+        self.vectors.map_or(true, |rule| rule == shape.vectors)
+            && self.dim.map_or(true, |rule| rule == shape.dim)
+            && self
+                .subvectors
+                .map_or(true, |rule| rule == params.subvectors)
+            && self.centroids.map_or(true, |rule| rule == params.centroids)
+            && self
+                .nonzeros
+                .map_or(true, |rule| Some(rule) == params.nonzeros)
+            && modes_allowed
+    }
+}
@@ -0,0 +1,200 @@
+//! Off-circuit Poseidon over BN254 `Fr` (width 3, rate 2, x^5 S-box).
+//!
+//! Round constants and the Cauchy MDS matrix are derived with the Grain LFSR
+//! from the Poseidon reference. This module is the single source of truth for
+//! the parameters used by the in-circuit gadget in `gadgets::poseidon`, so
+//! hashes computed here always match what the circuit constrains.
+
+use std::{collections::VecDeque, sync::OnceLock};
+
+use halo2curves::{bn256::Fr, ff::PrimeField};
+
+pub const WIDTH: usize = 3;
+pub const RATE: usize = 2;
+pub const FULL_ROUNDS: usize = 8;
+pub const PARTIAL_ROUNDS: usize = 57;
+pub const TOTAL_ROUNDS: usize = FULL_ROUNDS + PARTIAL_ROUNDS;
+
+/// Capacity element used for 2-to-1 (Merkle node) hashes.
+pub const NODE_CAPACITY: u64 = 2;
+
+/// Domain tags for variable-length sponge inputs (see [`domain_capacity`]).
+pub const LEAF_DOMAIN: u64 = 1;
+pub const CODES_DOMAIN: u64 = 2;
+pub const QUERY_DOMAIN: u64 = 3;
+pub const RESULTS_DOMAIN: u64 = 4;
+pub const CANDIDATES_DOMAIN: u64 = 5;
+pub const CHALLENGE_DOMAIN: u64 = 6;
+pub const CODEBOOK_DOMAIN: u64 = 7;
+pub const SPARSITY_DOMAIN: u64 = 8;
+pub const EPSILONS_DOMAIN: u64 = 9;
+pub const TRANSCRIPT_DOMAIN: u64 = 10;
+pub const ROTATION_DOMAIN: u64 = 11;
+pub const SCALAR_DOMAIN: u64 = 12;
+pub const PERMUTATION_DOMAIN: u64 = 13;
+pub const HIDING_DOMAIN: u64 = 14;
+pub const VECTOR_COMMITMENT_DOMAIN: u64 = 15;
+pub const RESIDUALS_DOMAIN: u64 = 16;
+
+#[derive(Debug, Clone)]
+pub struct PoseidonSpec {
+    pub round_constants: Vec<[Fr; WIDTH]>,
+    pub mds: [[Fr; WIDTH]; WIDTH],
+}
+
+pub fn spec() -> &'static PoseidonSpec {
+    static SPEC: OnceLock<PoseidonSpec> = OnceLock::new();
+    SPEC.get_or_init(|| {
+        let mut grain = Grain::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS);
+        let round_constants = (0..TOTAL_ROUNDS)
+            .map(|_| [grain.next_field(), grain.next_field(), grain.next_field()])
+            .collect();
+        let xs = [grain.next_field(), grain.next_field(), grain.next_field()];
+        let ys = [grain.next_field(), grain.next_field(), grain.next_field()];
+        let mut mds = [[Fr::zero(); WIDTH]; WIDTH];
+        for (i, x) in xs.iter().enumerate() {
+            for (j, y) in ys.iter().enumerate() {
+                mds[i][j] = (*x + *y)
+                    .invert()
+                    .expect("cauchy matrix entries must be invertible");
+            }
+        }
+        PoseidonSpec {
+            round_constants,
+            mds,
+        }
+    })
+}
+
+/// Whether round `round` applies the S-box to the whole state.
+pub fn is_full_round(round: usize) -> bool {
+    round < FULL_ROUNDS / 2 || round >= FULL_ROUNDS / 2 + PARTIAL_ROUNDS
+}
+
+pub fn sbox(value: Fr) -> Fr {
+    let squared = value.square();
+    squared.square() * value
+}
+
+/// Applies a single round in place; shared by `permute` and the gadget's witness generation.
+pub fn apply_round(state: &mut [Fr; WIDTH], round: usize) {
+    let spec = spec();
+    let constants = &spec.round_constants[round];
+    let mut sboxed = [Fr::zero(); WIDTH];
+    for idx in 0..WIDTH {
+        let value = state[idx] + constants[idx];
+        sboxed[idx] = if idx == 0 || is_full_round(round) {
+            sbox(value)
+        } else {
+            value
+        };
+    }
+    for (row, out) in spec.mds.iter().zip(state.iter_mut()) {
+        *out = row
+            .iter()
+            .zip(sboxed.iter())
+            .fold(Fr::zero(), |acc, (m, v)| acc + *m * *v);
+    }
+}
+
+pub fn permute(state: &mut [Fr; WIDTH]) {
+    for round in 0..TOTAL_ROUNDS {
+        apply_round(state, round);
+    }
+}
+
+/// Sponge hash: absorbs `inputs` two at a time (zero-padding the last chunk)
+/// into a state whose capacity element is initialised to `capacity`.
+pub fn hash_with_capacity(capacity: Fr, inputs: &[Fr]) -> Fr {
+    let mut state = [capacity, Fr::zero(), Fr::zero()];
+    for chunk in inputs.chunks(RATE) {
+        state[1] += chunk[0];
+        if let Some(second) = chunk.get(1) {
+            state[2] += *second;
+        }
+        permute(&mut state);
+    }
+    state[1]
+}
+
+/// Capacity element `domain * 2^64 + len` for a variable-length input; encodes
+/// the length so zero padding is unambiguous, and never collides with
+/// [`NODE_CAPACITY`] for non-zero domains.
+pub fn domain_capacity(domain: u64, len: usize) -> Fr {
+    Fr::from_u128(((domain as u128) << 64) + len as u128)
+}
+
+pub fn leaf_capacity(len: usize) -> Fr {
+    domain_capacity(LEAF_DOMAIN, len)
+}
+
+pub fn hash_leaf(values: &[Fr]) -> Fr {
+    hash_with_capacity(leaf_capacity(values.len()), values)
+}
+
+pub fn hash_node(left: Fr, right: Fr) -> Fr {
+    hash_with_capacity(Fr::from(NODE_CAPACITY), &[left, right])
+}
+
+struct Grain {
+    bits: VecDeque<bool>,
+}
+
+impl Grain {
+    fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut bits = VecDeque::with_capacity(80);
+        push_bits(&mut bits, 1, 2); // prime field
+        push_bits(&mut bits, 0, 4); // x^alpha S-box
+        push_bits(&mut bits, Fr::NUM_BITS as usize, 12);
+        push_bits(&mut bits, width, 12);
+        push_bits(&mut bits, full_rounds, 10);
+        push_bits(&mut bits, partial_rounds, 10);
+        push_bits(&mut bits, (1 << 30) - 1, 30);
+        let mut grain = Self { bits };
+        for _ in 0..160 {
+            grain.next_bit();
+        }
+        grain
+    }
+
+    fn next_bit(&mut self) -> bool {
+        let b = &self.bits;
+        let bit = b[62] ^ b[51] ^ b[38] ^ b[23] ^ b[13] ^ b[0];
+        self.bits.pop_front();
+        self.bits.push_back(bit);
+        bit
+    }
+
+    fn next_filtered_bit(&mut self) -> bool {
+        loop {
+            let keep = self.next_bit();
+            let bit = self.next_bit();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Samples `NUM_BITS` bits big-endian, rejecting values outside the field.
+    fn next_field(&mut self) -> Fr {
+        let num_bits = Fr::NUM_BITS as usize;
+        loop {
+            let mut repr = <Fr as PrimeField>::Repr::default();
+            for idx in 0..num_bits {
+                if self.next_filtered_bit() {
+                    let position = num_bits - 1 - idx;
+                    repr.as_mut()[position / 8] |= 1 << (position % 8);
+                }
+            }
+            if let Some(value) = Option::<Fr>::from(Fr::from_repr(repr)) {
+                return value;
+            }
+        }
+    }
+}
+
+fn push_bits(bits: &mut VecDeque<bool>, value: usize, count: usize) {
+    for shift in (0..count).rev() {
+        bits.push_back((value >> shift) & 1 == 1);
+    }
+}
@@ -0,0 +1,156 @@
+//! Proof byte breakdown for a keyed constraint system.
+//!
+//! A halo2 KZG proof over BN254 is a sequence of compressed G1 points (32
+//! bytes) and scalars (32 bytes):
+//!
+//! * commitments: one per advice column, two per lookup for the permuted
+//!   columns, one per permutation chunk and lookup for the grand products,
+//!   the vanishing argument's random polynomial and `degree - 1` quotient
+//!   pieces;
+//! * evaluations: one per advice and fixed query, one per permutation sigma
+//!   column, `3 * chunks - 1` for the permutation products, five per lookup
+//!   and the random polynomial's evaluation. Instance columns are evaluated
+//!   by the verifier and are not sent;
+//! * opening proof: GWC sends one witness commitment per distinct query
+//!   rotation, SHPLONK always sends two.
+//!
+//! The counts follow from the verifying key alone, so a configuration can be
+//! sized without proving. This build proves with GWC; SHPLONK figures are
+//! what the same circuit would cost after switching multiopen schemes (a
+//! proof format change, see [`crate::compat`]).
+
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use anyhow::Result;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2curves::bn256::Fr;
+use serde::Serialize;
+
+/// Compressed BN254 G1 point.
+pub const COMMITMENT_BYTES: usize = 32;
+/// BN254 scalar.
+pub const SCALAR_BYTES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpeningScheme {
+    Gwc,
+    Shplonk,
+}
+
+impl fmt::Display for OpeningScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpeningScheme::Gwc => f.write_str("gwc"),
+            OpeningScheme::Shplonk => f.write_str("shplonk"),
+        }
+    }
+}
+
+impl FromStr for OpeningScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "gwc" => Ok(OpeningScheme::Gwc),
+            "shplonk" => Ok(OpeningScheme::Shplonk),
+            other => anyhow::bail!("unknown opening scheme {other:?} (gwc or shplonk)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Section {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+impl Section {
+    fn points(count: usize) -> Self {
+        Self {
+            count,
+            bytes: count * COMMITMENT_BYTES,
+        }
+    }
+
+    fn scalars(count: usize) -> Self {
+        Self {
+            count,
+            bytes: count * SCALAR_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofSizeBreakdown {
+    pub scheme: OpeningScheme,
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    pub permutation_columns: usize,
+    pub lookups: usize,
+    pub degree: usize,
+    /// Distinct rotations opened by the multiopen argument.
+    pub query_points: usize,
+    pub commitments: Section,
+    pub evaluations: Section,
+    pub opening: Section,
+    pub total_bytes: usize,
+}
+
+/// Sizes a proof for `cs`, which should come from a verifying key so that
+/// selectors are already compressed into fixed columns.
+pub fn breakdown(cs: &ConstraintSystem<Fr>, scheme: OpeningScheme) -> ProofSizeBreakdown {
+    let degree = cs.degree();
+    let permutation_columns = cs.permutation().get_columns().len();
+    let chunk_len = degree.saturating_sub(2).max(1);
+    let permutation_chunks = permutation_columns.div_ceil(chunk_len);
+    let lookups = cs.lookups().len();
+
+    let commitments =
+        cs.num_advice_columns() + 3 * lookups + permutation_chunks + 1 + degree.saturating_sub(1);
+
+    let permutation_evals = if permutation_chunks == 0 {
+        0
+    } else {
+        permutation_columns + 3 * permutation_chunks - 1
+    };
+    let evaluations =
+        cs.advice_queries().len() + cs.fixed_queries().len() + permutation_evals + 5 * lookups + 1;
+
+    let mut rotations: BTreeSet<i32> = BTreeSet::from([0]);
+    rotations.extend(cs.advice_queries().iter().map(|(_, rotation)| rotation.0));
+    rotations.extend(cs.fixed_queries().iter().map(|(_, rotation)| rotation.0));
+    if permutation_chunks > 0 {
+        rotations.insert(1);
+        if permutation_chunks > 1 {
+            rotations.insert(-((cs.blinding_factors() + 1) as i32));
+        }
+    }
+    if lookups > 0 {
+        rotations.extend([-1, 1]);
+    }
+    let opening = match scheme {
+        OpeningScheme::Gwc => rotations.len(),
+        OpeningScheme::Shplonk => 2,
+    };
+
+    let commitments = Section::points(commitments);
+    let evaluations = Section::scalars(evaluations);
+    let opening = Section::points(opening);
+    ProofSizeBreakdown {
+        scheme,
+        advice_columns: cs.num_advice_columns(),
+        fixed_columns: cs.num_fixed_columns(),
+        instance_columns: cs.num_instance_columns(),
+        permutation_columns,
+        lookups,
+        degree,
+        query_points: rotations.len(),
+        commitments,
+        evaluations,
+        opening,
+        total_bytes: commitments.bytes + evaluations.bytes + opening.bytes,
+    }
+}
@@ -0,0 +1,255 @@
+//! Rust types for `proto/yysfold/v1/service.proto`.
+//!
+//! These are the prost message definitions for the schema, checked in so that
+//! building the crate does not require `protoc`. Keep field tags in sync with
+//! the `.proto` file when either side changes.
+
+use crate::{
+    build_info::BuildInfo as LibBuildInfo,
+    io::Provenance as LibProvenance,
+    l1::L1Reference as LibL1Reference,
+    metadata::{ProofMetadata as LibProofMetadata, ProofMetadataV1},
+};
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WitnessRef {
+    #[prost(oneof = "witness_ref::Source", tags = "1, 2")]
+    pub source: Option<witness_ref::Source>,
+    #[prost(string, tag = "3")]
+    pub digest: String,
+}
+
+pub mod witness_ref {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Source {
+        #[prost(string, tag = "1")]
+        Uri(String),
+        #[prost(bytes = "vec", tag = "2")]
+        InlineJson(Vec<u8>),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum JobStatus {
+    Unspecified = 0,
+    Queued = 1,
+    Running = 2,
+    Succeeded = 3,
+    Failed = 4,
+    Cancelled = 5,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProofMetadata {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(uint32, tag = "2")]
+    pub circuit_k: u32,
+    #[prost(uint64, optional, tag = "3")]
+    pub block_height: Option<u64>,
+    #[prost(string, repeated, tag = "4")]
+    pub instances: Vec<String>,
+    #[prost(uint64, tag = "5")]
+    pub proof_bytes: u64,
+    #[prost(string, tag = "6")]
+    pub proof_digest: String,
+    #[prost(string, optional, tag = "7")]
+    pub witness_digest: Option<String>,
+    #[prost(string, tag = "8")]
+    pub prover_version: String,
+    #[prost(uint64, tag = "9")]
+    pub created_at: u64,
+    #[prost(uint32, tag = "10")]
+    pub proof_format_version: u32,
+    #[prost(uint32, tag = "11")]
+    pub circuit_version: u32,
+    #[prost(string, optional, tag = "12")]
+    pub previous_proof_digest: Option<String>,
+    #[prost(message, optional, tag = "13")]
+    pub provenance: Option<Provenance>,
+    #[prost(bool, tag = "14")]
+    pub simulated: bool,
+    #[prost(string, tag = "15")]
+    pub transcript: String,
+    #[prost(string, tag = "16")]
+    pub digest_algorithm: String,
+    #[prost(message, optional, tag = "17")]
+    pub l1_reference: Option<L1Reference>,
+    #[prost(message, optional, tag = "18")]
+    pub build: Option<BuildInfo>,
+    #[prost(uint64, optional, tag = "19")]
+    pub scale: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct L1Reference {
+    #[prost(uint64, tag = "1")]
+    pub number: u64,
+    #[prost(string, tag = "2")]
+    pub hash: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BuildInfo {
+    #[prost(string, tag = "1")]
+    pub crate_version: String,
+    #[prost(string, optional, tag = "2")]
+    pub git_commit: Option<String>,
+    #[prost(bool, tag = "3")]
+    pub git_dirty: bool,
+    #[prost(string, repeated, tag = "4")]
+    pub features: Vec<String>,
+    #[prost(uint32, tag = "5")]
+    pub circuit_version: u32,
+    #[prost(uint32, tag = "6")]
+    pub proof_format_version: u32,
+    #[prost(string, tag = "7")]
+    pub circuit_hash: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Provenance {
+    #[prost(string, optional, tag = "1")]
+    pub generator_version: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub model_id: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub codebook_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProofContainer {
+    #[prost(bytes = "vec", tag = "1")]
+    pub proof: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub metadata: Option<ProofMetadata>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubmitProofRequest {
+    #[prost(message, optional, tag = "1")]
+    pub witness: Option<WitnessRef>,
+    #[prost(message, optional, tag = "2")]
+    pub public_inputs: Option<WitnessRef>,
+    #[prost(string, tag = "3")]
+    pub idempotency_key: String,
+    #[prost(uint32, tag = "4")]
+    pub circuit_k: u32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubmitProofResponse {
+    #[prost(string, tag = "1")]
+    pub job_id: String,
+    #[prost(enumeration = "JobStatus", tag = "2")]
+    pub status: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetJobRequest {
+    #[prost(string, tag = "1")]
+    pub job_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Job {
+    #[prost(string, tag = "1")]
+    pub job_id: String,
+    #[prost(enumeration = "JobStatus", tag = "2")]
+    pub status: i32,
+    #[prost(string, tag = "3")]
+    pub error: String,
+    #[prost(message, optional, tag = "4")]
+    pub proof: Option<ProofContainer>,
+    #[prost(string, tag = "5")]
+    pub error_code: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub proof: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub public_inputs: Option<WitnessRef>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyResponse {
+    #[prost(bool, tag = "1")]
+    pub valid: bool,
+    #[prost(string, tag = "2")]
+    pub error: String,
+    #[prost(string, tag = "3")]
+    pub error_code: String,
+}
+
+impl From<&LibProofMetadata> for ProofMetadata {
+    fn from(metadata: &LibProofMetadata) -> Self {
+        let version = metadata.version();
+        let latest: ProofMetadataV1 = metadata.clone().into_latest();
+        Self {
+            version,
+            circuit_k: latest.circuit_k,
+            block_height: latest.block_height,
+            instances: latest.instances,
+            proof_bytes: latest.proof_bytes as u64,
+            proof_digest: latest.proof_digest,
+            witness_digest: latest.witness_digest,
+            prover_version: latest.prover_version,
+            created_at: latest.created_at,
+            proof_format_version: latest.proof_format_version,
+            circuit_version: latest.circuit_version,
+            previous_proof_digest: latest.previous_proof_digest,
+            provenance: latest.provenance.map(Provenance::from),
+            simulated: latest.simulated,
+            transcript: latest.transcript.to_string(),
+            digest_algorithm: latest.digest_algorithm.to_string(),
+            l1_reference: latest.l1_reference.map(L1Reference::from),
+            build: latest.build.map(BuildInfo::from),
+            scale: latest.scale,
+        }
+    }
+}
+
+impl From<LibProvenance> for Provenance {
+    fn from(provenance: LibProvenance) -> Self {
+        Self {
+            generator_version: provenance.generator_version,
+            model_id: provenance.model_id,
+            codebook_id: provenance.codebook_id,
+        }
+    }
+}
+
+impl From<LibBuildInfo> for BuildInfo {
+    fn from(build: LibBuildInfo) -> Self {
+        Self {
+            crate_version: build.crate_version,
+            git_commit: build.git_commit,
+            git_dirty: build.git_dirty,
+            features: build.features,
+            circuit_version: build.circuit_version,
+            proof_format_version: build.proof_format_version,
+            circuit_hash: build.circuit_hash,
+        }
+    }
+}
+
+impl From<LibL1Reference> for L1Reference {
+    fn from(reference: LibL1Reference) -> Self {
+        Self {
+            number: reference.number,
+            hash: reference.hash.to_string(),
+        }
+    }
+}
+
+impl ProofContainer {
+    pub fn new(proof: Vec<u8>, metadata: &LibProofMetadata) -> Self {
+        Self {
+            proof,
+            metadata: Some(metadata.into()),
+        }
+    }
+}
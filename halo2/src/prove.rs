@@ -0,0 +1,984 @@
+use anyhow::{Context, Result};
+use halo2_proofs::{
+    plonk::{create_proof, Circuit, ProvingKey},
+    poly::commitment::Params,
+    poly::kzg::{
+        commitment::{KZGCommitmentScheme, ParamsKZG},
+        multiopen::ProverGWC,
+    },
+    transcript::{Blake2bWrite, Challenge255, Keccak256Write, TranscriptWriterBuffer},
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    cancel::CancellationToken,
+    circuit::{FoldedCircuit, FoldedParams, VECTOR_ROOT_SLOT},
+    codebook::{commit_fields, CommitMode, CODEBOOK_ROOT_SLOT},
+    delta, epsilon,
+    errors::{Coded, ErrorCode},
+    fixed_point::{from_i64, resolve_scale, FixedPoint, DEFAULT_SCALE},
+    hiding::{self, check_hiding_commitments, FOLDED_COMMITMENT_SLOT, PQ_COMMITMENT_SLOT},
+    io::{FieldWitness, WitnessData},
+    merkle, opq, permutation,
+    proof_size::{breakdown, OpeningScheme},
+    public_inputs::{field_to_hex, ParsedPublicInputs},
+    quantization::{
+        codes_commitment, codes_to_fields, stage_codes, validate_pq_witness,
+        validate_residual_stage,
+    },
+    scalar::{scalar_commitment, scalar_fields, validate_scalar_witness},
+    shape::{ensure_blank_parity, negotiate, negotiate_shape},
+    sparse::sparsity_commitment,
+    transcript::{PoseidonWrite, TranscriptKind},
+    vector_commitment::check_vector_commitments,
+    witness_commitment,
+};
+
+/// Optional circuit features, chosen when the keys are generated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CircuitModes {
+    pub vector_root: bool,
+    pub pq_codes: bool,
+    /// Requires `pq_codes`.
+    pub codebook_commitment: Option<CommitMode>,
+    pub instance_hash: bool,
+    pub lineage: bool,
+    pub beacon: bool,
+    /// Lay out only the witness's `sparseVectors` entries.
+    pub sparse: bool,
+    /// Requires `pq_codes`.
+    pub compression_stats: bool,
+    /// Requires `pq_codes`.
+    pub subvector_epsilons: bool,
+    /// Changed vectors per delta witness; requires `vector_root`.
+    pub delta: Option<usize>,
+    /// Salted root over the full witness rows; excludes `sparse` and `delta`.
+    pub witness_commitment: bool,
+    /// Commit to the witness's OPQ rotation; requires `pq_codes`.
+    pub rotation: bool,
+    /// Check the witness's two-stage residual quantization; requires
+    /// `codebook_commitment`.
+    pub residual_stage: bool,
+    /// Dequantize the witness's int8 `scalarQuantization`; excludes
+    /// `pq_codes`, `sparse` and `delta`.
+    pub scalar: bool,
+    /// Commit rows in the order of the witness's `rowPermutation`; requires
+    /// `vector_root` or `witness_commitment` and excludes `delta`.
+    pub row_permutation: bool,
+    /// Recompute `foldedCommitment` and `pqCommitment` as hiding commitments
+    /// under the witness's `blinding`; excludes `sparse` and `delta`.
+    pub hiding: bool,
+    /// Recompute `foldedCommitment` and `pqCommitment` as Poseidon hashes of
+    /// the rows; excludes `hiding`, `sparse` and `delta`.
+    pub vector_commitments: bool,
+    /// Bound every vector's residual by the public `slaBound`.
+    pub sla_bound: bool,
+    /// Fixed-point scale; defaults to the witness's `scale`, or
+    /// [`DEFAULT_SCALE`]. A non-default scale excludes `pq_codes` and
+    /// `scalar`, whose tables are encoded at the default scale.
+    pub scale: Option<u64>,
+}
+
+/// Circuit params keyed to a witness's shape.
+pub fn circuit_params(witness: &WitnessData, modes: CircuitModes) -> Result<FoldedParams> {
+    let rows = &witness.folded_vectors;
+    shaped_params(
+        witness,
+        rows.len(),
+        rows.first().map(Vec::len).unwrap_or(0),
+        modes,
+    )
+}
+
+/// [`circuit_params`] for a witness streamed into the field.
+pub fn field_circuit_params(witness: &FieldWitness, modes: CircuitModes) -> Result<FoldedParams> {
+    if modes.pq_codes || modes.scalar {
+        anyhow::bail!(Coded::new(ErrorCode::Usage, NEEDS_FLOAT_VECTORS));
+    }
+    let rows = &witness.folded;
+    shaped_params(
+        &witness.witness,
+        rows.len(),
+        rows.first().map(Vec::len).unwrap_or(0),
+        modes,
+    )
+}
+
+/// Why a [`FieldWitness`] cannot be proved in some modes.
+const NEEDS_FLOAT_VECTORS: &str =
+    "pqCodes and scalar mode check their codes against the f64 vectors; load the witness whole";
+
+/// [`circuit_params`] for a witness of `vectors` rows of `dim` components.
+fn shaped_params(
+    witness: &WitnessData,
+    vectors: usize,
+    dim: usize,
+    modes: CircuitModes,
+) -> Result<FoldedParams> {
+    let scale = resolve_scale(modes.scale, witness.scale)?.scale();
+    let scale = (scale != DEFAULT_SCALE).then_some(scale);
+    if scale.is_some() && (modes.pq_codes || modes.scalar) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "a non-default fixed-point scale cannot be combined with pqCodes or scalar mode"
+        ));
+    }
+    if modes.delta.is_some() && (!modes.vector_root || modes.pq_codes || modes.sparse) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "delta mode needs vectorRoot and cannot be combined with pqCodes or sparse"
+        ));
+    }
+    if modes.witness_commitment && (modes.sparse || modes.delta.is_some()) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "witness commitments cannot be combined with sparse or delta mode"
+        ));
+    }
+    if modes.scalar && (modes.pq_codes || modes.sparse || modes.delta.is_some()) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "scalar mode cannot be combined with pqCodes, sparse or delta mode"
+        ));
+    }
+    if modes.row_permutation
+        && (!(modes.vector_root || modes.witness_commitment) || modes.delta.is_some())
+    {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "row permutation needs vectorRoot or witnessCommitment and cannot be combined with delta mode"
+        ));
+    }
+    if modes.hiding && (modes.sparse || modes.delta.is_some()) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "hiding commitments cannot be combined with sparse or delta mode"
+        ));
+    }
+    if modes.vector_commitments && (modes.hiding || modes.sparse || modes.delta.is_some()) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "vector commitments cannot be combined with hiding, sparse or delta mode"
+        ));
+    }
+    let mut params = FoldedParams {
+        vectors,
+        dim,
+        vector_root: modes.vector_root,
+        instance_hash: modes.instance_hash,
+        lineage: modes.lineage,
+        beacon: modes.beacon,
+        witness_commitment: modes.witness_commitment,
+        vector_commitments: modes.vector_commitments,
+        sla_bound: modes.sla_bound,
+        scale,
+        ..FoldedParams::default()
+    };
+    if modes.row_permutation {
+        permutation::row_permutation(witness)?;
+        params.row_permutation = true;
+    }
+    if modes.hiding {
+        hiding::blinding(witness)?;
+        params.hiding = true;
+    }
+    if modes.scalar {
+        validate_scalar_witness(witness)?;
+        params.scalar = true;
+    }
+    if modes.codebook_commitment.is_some() && !modes.pq_codes {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "codebook commitment needs the pqCodes circuit mode"
+        ));
+    }
+    if modes.compression_stats && !modes.pq_codes {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "compression stats need the pqCodes circuit mode"
+        ));
+    }
+    if modes.rotation && !modes.pq_codes {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "an OPQ rotation needs the pqCodes circuit mode"
+        ));
+    }
+    if modes.residual_stage && modes.codebook_commitment.is_none() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "a residual stage needs the codebookCommitment circuit mode"
+        ));
+    }
+    if modes.subvector_epsilons && !modes.pq_codes {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "subvector epsilons need the pqCodes circuit mode"
+        ));
+    }
+    if modes.sparse {
+        if modes.vector_root || modes.pq_codes {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Usage,
+                "sparse mode cannot be combined with vectorRoot or pqCodes"
+            ));
+        }
+        let sparse = witness
+            .sparse_vectors
+            .as_ref()
+            .context("sparse mode needs a witness with sparseVectors")?;
+        params.nonzeros = Some(sparse.max_nonzeros().max(1));
+    }
+    if let Some(capacity) = modes.delta {
+        let delta = witness
+            .delta
+            .as_ref()
+            .context("delta mode needs a delta witness, see `yysfold delta`")?;
+        if !delta.vector_count.is_power_of_two() {
+            anyhow::bail!(Coded::new(
+                ErrorCode::ShapeMismatch,
+                format!(
+                    "delta blocks need a power-of-two vector count, block has {}",
+                    delta.vector_count
+                )
+            ));
+        }
+        params.vectors = delta.vector_count;
+        params.delta = Some(capacity.max(1));
+    }
+    if modes.pq_codes {
+        let shape = validate_pq_witness(witness)?;
+        params.codebook_commitment = modes.codebook_commitment;
+        params.pq_codes = true;
+        params.subvectors = shape.subvectors;
+        params.centroids = shape.centroids;
+        params.compression_stats = modes.compression_stats;
+        params.subvector_epsilons = modes.subvector_epsilons;
+        params.rotation = modes.rotation;
+        if modes.residual_stage {
+            let stage = witness
+                .residual_stage
+                .as_ref()
+                .context("residual stage mode needs a witness with residualStage")?;
+            params.residual_centroids = Some(validate_residual_stage(stage, shape, vectors)?);
+        }
+    }
+    Ok(params)
+}
+
+/// Builds the full witness circuit for a block, deriving residuals from the vectors.
+pub fn build_circuit(
+    witness: &WitnessData,
+    public_inputs: &ParsedPublicInputs,
+    params: &FoldedParams,
+) -> Result<FoldedCircuit> {
+    build_circuit_with(witness, public_inputs, params, &CancellationToken::new())
+}
+
+/// [`build_circuit`], checking `cancel` between conversion steps.
+pub fn build_circuit_with(
+    witness: &WitnessData,
+    public_inputs: &ParsedPublicInputs,
+    params: &FoldedParams,
+    cancel: &CancellationToken,
+) -> Result<FoldedCircuit> {
+    cancel.check("witness conversion")?;
+    let shape = negotiate(witness, params, None)?;
+    let fixed_point = params.fixed_point()?;
+    check_keyed_scale(witness.scale, fixed_point)?;
+    let (folded, pq) = if params.nonzeros.is_some() {
+        (vec![], vec![])
+    } else {
+        let folded = fixed_point
+            .to_field_matrix(&witness.folded_vectors)
+            .context("foldedVectors")?;
+        cancel.check("pq vector conversion")?;
+        let pq = fixed_point
+            .to_field_matrix(&witness.pq_vectors)
+            .context("pqVectors")?;
+        (folded, pq)
+    };
+    let rows = VectorRows {
+        vectors: shape.vectors,
+        residuals: fixed_point.residuals(&witness.folded_vectors, &witness.pq_vectors),
+        folded,
+        pq,
+    };
+    build_rows(witness, rows, public_inputs, params, cancel)
+}
+
+/// [`build_circuit_with`] for a witness streamed into the field, see
+/// [`crate::io::load_field_witness`]. Residuals and the SLA bound are
+/// checked against the field rows; pqCodes and scalar circuits check codes
+/// against the `f64` vectors and are rejected.
+pub fn build_field_circuit(
+    witness: FieldWitness,
+    public_inputs: &ParsedPublicInputs,
+    params: &FoldedParams,
+    cancel: &CancellationToken,
+) -> Result<FoldedCircuit> {
+    cancel.check("witness conversion")?;
+    if params.pq_codes || params.scalar {
+        anyhow::bail!(Coded::new(ErrorCode::Usage, NEEDS_FLOAT_VECTORS));
+    }
+    let shape = negotiate_shape(&witness.witness, witness.shape()?, params, None)?;
+    let fixed_point = params.fixed_point()?;
+    if witness.fixed_point != fixed_point {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Incompatible,
+            format!(
+                "witness was converted at fixed-point scale {}, the keys are at {}",
+                witness.fixed_point.scale(),
+                fixed_point.scale()
+            )
+        ));
+    }
+    check_keyed_scale(witness.witness.scale, fixed_point)?;
+    let rows = VectorRows {
+        vectors: shape.vectors,
+        residuals: fixed_point.field_residuals(&witness.folded, &witness.pq),
+        folded: witness.folded,
+        pq: witness.pq,
+    };
+    build_rows(&witness.witness, rows, public_inputs, params, cancel)
+}
+
+/// Rejects a witness whose declared `scale` is not the keys'.
+fn check_keyed_scale(scale: Option<u64>, fixed_point: FixedPoint) -> Result<()> {
+    if let Some(scale) = scale.filter(|scale| *scale != fixed_point.scale()) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Incompatible,
+            format!(
+                "witness is at fixed-point scale {scale}, the keys at {}",
+                fixed_point.scale()
+            )
+        ));
+    }
+    Ok(())
+}
+
+/// A witness's dense vectors in the field, left empty for sparse circuits,
+/// with the squared residuals recomputed from them.
+struct VectorRows {
+    vectors: usize,
+    folded: Vec<Vec<Fr>>,
+    pq: Vec<Vec<Fr>>,
+    residuals: Vec<u128>,
+}
+
+/// Builds the circuit from `rows`, reading everything else from `witness`.
+fn build_rows(
+    witness: &WitnessData,
+    rows: VectorRows,
+    public_inputs: &ParsedPublicInputs,
+    params: &FoldedParams,
+    cancel: &CancellationToken,
+) -> Result<FoldedCircuit> {
+    let fixed_point = params.fixed_point()?;
+    let values = public_inputs.public_values(params)?;
+    let mut commitments = public_inputs.commitment_fields()?;
+    if params.codebook_commitment.is_some() {
+        commitments[CODEBOOK_ROOT_SLOT] = values[CODEBOOK_ROOT_SLOT];
+    }
+    if params.hiding || params.vector_commitments {
+        commitments[FOLDED_COMMITMENT_SLOT] = values[FOLDED_COMMITMENT_SLOT];
+        commitments[PQ_COMMITMENT_SLOT] = values[PQ_COMMITMENT_SLOT];
+    }
+
+    let (folded_vectors, pq_vectors, sparse_indices) = match params.nonzeros {
+        Some(nonzeros) => {
+            let sparse = witness
+                .sparse_vectors
+                .as_ref()
+                .context("circuit is keyed for sparse vectors; witness has no sparseVectors")?;
+            let (indices, folded, pq) = sparse.padded(nonzeros)?;
+            let commitment = sparsity_commitment(&indices);
+            if params.sparsity_slot().map(|slot| values[slot]) != Some(commitment) {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "sparsityCommitment does not match witness (expected {})",
+                        field_to_hex(&commitment)
+                    )
+                ));
+            }
+            let indices = indices
+                .iter()
+                .map(|row| row.iter().map(|index| Fr::from(*index as u64)).collect())
+                .collect();
+            (
+                fixed_point
+                    .to_field_matrix(&folded)
+                    .context("sparseVectors")?,
+                fixed_point.to_field_matrix(&pq).context("sparseVectors")?,
+                indices,
+            )
+        }
+        None => {
+            let (mut folded_vectors, mut pq_vectors) = (rows.folded, rows.pq);
+            if let Some(capacity) = params.delta {
+                let padding = vec![Fr::zero(); params.dim];
+                folded_vectors.resize(capacity, padding.clone());
+                pq_vectors.resize(capacity, padding);
+            }
+            (folded_vectors, pq_vectors, vec![])
+        }
+    };
+    let (previous_vector_root, delta_updates, updated_root) = match params.delta {
+        Some(capacity) => {
+            cancel.check("delta updates")?;
+            let section = witness
+                .delta
+                .as_ref()
+                .context("circuit is keyed for deltas; witness has no delta section")?;
+            let changed = &folded_vectors[..section.indices.len().min(capacity)];
+            let (updates, root) = delta::updates(section, changed, capacity)?;
+            let previous = section.previous_vector_root.to_canonical_field()?;
+            if params.delta_slot().map(|slot| values[slot]) != Some(previous) {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "previousVectorRoot does not match the delta witness (expected {})",
+                        section.previous_vector_root
+                    )
+                ));
+            }
+            (previous, updates, Some(root))
+        }
+        None => (Fr::zero(), vec![], None),
+    };
+    let blinding = if params.hiding {
+        cancel.check("hiding commitments")?;
+        let blinding = hiding::blinding(witness)?;
+        check_hiding_commitments(blinding, &folded_vectors, &pq_vectors, &values)?;
+        blinding.fields().to_vec()
+    } else {
+        vec![]
+    };
+    if params.vector_commitments {
+        cancel.check("vector commitments")?;
+        check_vector_commitments(&folded_vectors, &pq_vectors, &values)?;
+    }
+    let (row_order, permutation_salt) = match params.permutation_slot() {
+        Some(slot) => {
+            cancel.check("row permutation")?;
+            let permutation = permutation::row_permutation(witness)?;
+            let order = permutation.order(folded_vectors.len());
+            let commitment = permutation::permutation_commitment(permutation, &order);
+            if values[slot] != commitment {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "permutationCommitment does not match the witness rowPermutation (expected {})",
+                        field_to_hex(&commitment)
+                    )
+                ));
+            }
+            (order, permutation.salt.to_field_reduced())
+        }
+        None if witness.row_permutation.is_some() => anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            "witness carries a rowPermutation but the circuit is not keyed for rowPermutation"
+        )),
+        None => (vec![], Fr::zero()),
+    };
+    let committed = |rows: &[Fr]| -> Vec<Fr> {
+        if row_order.is_empty() {
+            rows.to_vec()
+        } else {
+            permutation::permute(&row_order, rows)
+        }
+    };
+    if params.vector_root {
+        cancel.check("vector root")?;
+        let root = updated_root
+            .unwrap_or_else(|| merkle::root(committed(&merkle::leaves(&folded_vectors))));
+        if values[VECTOR_ROOT_SLOT] != root {
+            anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!(
+                    "foldedVectorRoot does not match witness (expected {})",
+                    field_to_hex(&root)
+                )
+            ));
+        }
+    }
+    let row_salts = match params.witness_commitment_slot() {
+        Some(slot) => {
+            cancel.check("witness commitment")?;
+            let salts = witness_commitment::row_salts(
+                witness_commitment::audit_salt(witness)?,
+                folded_vectors.len(),
+            );
+            let root = merkle::root(committed(&witness_commitment::leaves(
+                &salts,
+                &folded_vectors,
+                &pq_vectors,
+            )));
+            if values[slot] != root {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "witnessCommitment does not match witness (expected {})",
+                        field_to_hex(&root)
+                    )
+                ));
+            }
+            salts
+        }
+        None => vec![],
+    };
+    let (pq_codes, codebook) = match params.pq_codes_slot() {
+        Some(slot) => {
+            cancel.check("pq codes")?;
+            let shape = validate_pq_witness(witness)?;
+            if shape.subvectors != params.subvectors || shape.centroids != params.centroids {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::ShapeMismatch,
+                    format!(
+                        "codebook shape does not match keyed circuit ({} subspaces x {} centroids)",
+                        params.subvectors, params.centroids
+                    )
+                ));
+            }
+            let codes = witness.pq_codes.as_deref().unwrap_or_default();
+            let commitment = codes_commitment(&stage_codes(witness));
+            if values[slot] != commitment {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "pqCodesCommitment does not match witness (expected {})",
+                        field_to_hex(&commitment)
+                    )
+                ));
+            }
+            let codebook: Vec<Vec<Vec<Fr>>> = witness
+                .codebook
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .map(|subspace| to_field_matrix(subspace))
+                .collect();
+            if let Some(mode) = params.codebook_commitment {
+                cancel.check("codebook commitment")?;
+                let root = commit_fields(&codebook, mode);
+                if values[CODEBOOK_ROOT_SLOT] != root {
+                    anyhow::bail!(Coded::new(ErrorCode::CommitmentMismatch, format!("codebookRoot does not match the witness codebook under {mode} (expected {})",
+                        field_to_hex(&root))));
+                }
+            }
+            let code_rows = codes
+                .iter()
+                .map(|row| codes_to_fields(std::slice::from_ref(row)))
+                .collect();
+            (code_rows, codebook)
+        }
+        None => (vec![], vec![]),
+    };
+    let (residual_codes, residual_codebook, primary_vectors) = match params.residual_codebook_slot()
+    {
+        Some(slot) => {
+            cancel.check("residual stage")?;
+            let stage = witness
+                .residual_stage
+                .as_ref()
+                .context("circuit is keyed for a residual stage; witness has no residualStage")?;
+            let centroids =
+                validate_residual_stage(stage, validate_pq_witness(witness)?, rows.vectors)?;
+            if Some(centroids) != params.residual_centroids {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::ShapeMismatch,
+                    format!(
+                        "residual codebook has {centroids} centroids per subspace, circuit is keyed for {:?}",
+                        params.residual_centroids
+                    )
+                ));
+            }
+            let residual_codebook: Vec<Vec<Vec<Fr>>> = stage
+                .codebook
+                .iter()
+                .map(|subspace| to_field_matrix(subspace))
+                .collect();
+            let mode = params
+                .codebook_commitment
+                .context("a residual stage needs a codebook commitment mode")?;
+            let root = commit_fields(&residual_codebook, mode);
+            if values[slot] != root {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "residualCodebookRoot does not match the residual codebook under {mode} (expected {})",
+                        field_to_hex(&root)
+                    )
+                ));
+            }
+            let primary_codebook = witness.codebook.as_deref().unwrap_or_default();
+            let primary_vectors = witness
+                .pq_codes
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .zip(primary_codebook)
+                        .flat_map(|(code, centroids)| &centroids[*code as usize])
+                        .map(|value| float_to_field(*value))
+                        .collect()
+                })
+                .collect();
+            let code_rows = stage
+                .codes
+                .iter()
+                .map(|row| codes_to_fields(std::slice::from_ref(row)))
+                .collect();
+            (code_rows, residual_codebook, primary_vectors)
+        }
+        None => (vec![], vec![], vec![]),
+    };
+    let (scalar_codes, scales, zero_points) = match params.scalar_slot() {
+        Some(slot) => {
+            cancel.check("scalar quantization")?;
+            let scalar = validate_scalar_witness(witness)?;
+            let commitment = scalar_commitment(scalar);
+            if values[slot] != commitment {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "scalarCommitment does not match the witness codes (expected {})",
+                        field_to_hex(&commitment)
+                    )
+                ));
+            }
+            let (scales, zero_points, codes) = scalar_fields(scalar);
+            (codes, scales, zero_points)
+        }
+        None => (vec![], vec![], vec![]),
+    };
+    let rotation = match params.rotation_slot() {
+        Some(slot) => {
+            cancel.check("rotation")?;
+            let rotation = witness
+                .rotation
+                .as_deref()
+                .context("circuit is keyed for an OPQ rotation; witness has no rotation")?;
+            opq::validate_rotation(rotation, params.dim)?;
+            let commitment = opq::rotation_commitment(rotation);
+            if values[slot] != commitment {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::CommitmentMismatch,
+                    format!(
+                        "rotationCommitment does not match the witness rotation (expected {})",
+                        field_to_hex(&commitment)
+                    )
+                ));
+            }
+            opq::rotation_fields(rotation)
+        }
+        None => vec![],
+    };
+    let subvector_bounds = if params.subvector_epsilons {
+        cancel.check("subvector epsilons")?;
+        let epsilons = public_inputs
+            .subvector_epsilons
+            .as_deref()
+            .unwrap_or_default();
+        let bounds = epsilon::subvector_bounds(epsilons)?;
+        epsilon::check_segments(
+            &witness.folded_vectors,
+            &witness.pq_vectors,
+            params.sub_dim(),
+            &bounds,
+        )?;
+        epsilon::bounds_to_fields(&bounds)
+    } else {
+        vec![]
+    };
+    let sla_bound = match params.sla_slot() {
+        Some(slot) => {
+            cancel.check("SLA bound")?;
+            let sla = public_inputs.sla_bound.unwrap_or_default();
+            epsilon::check_sla_bound(
+                fixed_point,
+                &rows.residuals,
+                fixed_point.squared_threshold(sla)?,
+            )?;
+            values[slot]
+        }
+        None => Fr::zero(),
+    };
+    cancel.check("residuals")?;
+    let epsilon_squared =
+        residual_fields(witness, &rows.residuals, folded_vectors.len(), fixed_point)?;
+    let commitment = epsilon::residual_commitment(&epsilon_squared);
+    if values[params.residual_slot()] != commitment {
+        anyhow::bail!(Coded::new(
+            ErrorCode::CommitmentMismatch,
+            format!(
+                "residualCommitment does not match the witness residuals (expected {})",
+                field_to_hex(&commitment)
+            )
+        ));
+    }
+    let hashed_inputs = if params.instance_hash {
+        values.clone()
+    } else {
+        vec![]
+    };
+    let circuit = FoldedCircuit {
+        public_inputs: params.instances(values),
+        folded_vectors,
+        pq_vectors,
+        epsilon_squared,
+        commitments,
+        params: params.clone(),
+        pq_codes,
+        codebook,
+        hashed_inputs,
+        sparse_indices,
+        subvector_bounds,
+        previous_vector_root,
+        delta_updates,
+        row_salts,
+        rotation,
+        residual_codes,
+        residual_codebook,
+        primary_vectors,
+        scalar_codes,
+        scales,
+        zero_points,
+        row_order,
+        permutation_salt,
+        blinding,
+        sla_bound,
+    };
+    ensure_blank_parity(&circuit)?;
+    Ok(circuit)
+}
+
+/// Runs `create_proof` for a single circuit, returning the transcript bytes.
+pub fn create_folded_proof(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &FoldedCircuit,
+) -> Result<Vec<u8>> {
+    ensure_blank_parity(circuit)?;
+    create_circuit_proof(params, pk, circuit, &circuit.public_inputs)
+}
+
+/// [`create_folded_proof`] for any circuit with a single instance column.
+pub fn create_circuit_proof<C: Circuit<Fr> + Clone>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &C,
+    instances: &[Fr],
+) -> Result<Vec<u8>> {
+    create_circuit_proof_with(params, pk, circuit, instances, &CancellationToken::new())
+}
+
+/// [`create_circuit_proof`], checking `cancel` before proving starts and
+/// discarding the proof if the job was cancelled while it ran.
+pub fn create_circuit_proof_with<C: Circuit<Fr> + Clone>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &C,
+    instances: &[Fr],
+    cancel: &CancellationToken,
+) -> Result<Vec<u8>> {
+    create_circuit_proof_in(
+        params,
+        pk,
+        circuit,
+        instances,
+        TranscriptKind::default(),
+        cancel,
+    )
+}
+
+/// [`create_circuit_proof_with`] under the `transcript` of choice.
+pub fn create_circuit_proof_in<C: Circuit<Fr> + Clone>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &C,
+    instances: &[Fr],
+    transcript: TranscriptKind,
+    cancel: &CancellationToken,
+) -> Result<Vec<u8>> {
+    cancel.check("proving")?;
+    let proof = match transcript {
+        TranscriptKind::Blake2b => {
+            prove_into::<C, Blake2bWrite<_, _, _>>(params, pk, circuit, instances)?
+        }
+        TranscriptKind::Keccak256 => {
+            prove_into::<C, Keccak256Write<_, _, _>>(params, pk, circuit, instances)?
+        }
+        TranscriptKind::Poseidon => {
+            prove_into::<C, PoseidonWrite<_>>(params, pk, circuit, instances)?
+        }
+    };
+    cancel.check("proof output")?;
+    Ok(proof)
+}
+
+fn prove_into<C, T>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &C,
+    instances: &[Fr],
+) -> Result<Vec<u8>>
+where
+    C: Circuit<Fr> + Clone,
+    T: TranscriptWriterBuffer<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+{
+    let instance_refs: Vec<&[Fr]> = vec![instances];
+    let circuit_instances: Vec<&[&[Fr]]> = vec![&instance_refs[..]];
+    let circuits = vec![circuit.clone()];
+
+    let mut transcript = T::init(vec![]);
+
+    let rng = ChaCha20Rng::from_entropy();
+
+    create_proof::<
+        KZGCommitmentScheme<Bn256>,
+        ProverGWC<'_, Bn256>,
+        Challenge255<G1Affine>,
+        ChaCha20Rng,
+        T,
+        C,
+    >(
+        params,
+        pk,
+        &circuits,
+        &circuit_instances,
+        rng,
+        &mut transcript,
+    )?;
+
+    Ok(transcript.finalize())
+}
+
+/// Leading bytes of every simulated proof. No valid transcript starts with
+/// them, since they do not decode as a curve point.
+pub const SIMULATED_PROOF_MAGIC: &[u8; 16] = b"yysfold-simulate";
+
+/// Stands in for [`create_circuit_proof_with`] when integrating against the
+/// prover: returns bytes the size of a real proof for `pk`, derived from
+/// `instances`, without proving. Verifiers reject them, see
+/// [`is_simulated_proof`].
+pub fn simulate_circuit_proof(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    instances: &[Fr],
+    cancel: &CancellationToken,
+) -> Result<Vec<u8>> {
+    cancel.check("proving")?;
+    let size = breakdown(pk.get_vk().cs(), OpeningScheme::Gwc).total_bytes;
+    let mut hasher = blake3::Hasher::new_derive_key("yysfold simulated proof v1");
+    hasher.update(&params.k().to_le_bytes());
+    for instance in instances {
+        hasher.update(field_to_hex(instance).as_bytes());
+    }
+    let mut proof = vec![0; size.max(SIMULATED_PROOF_MAGIC.len())];
+    let (magic, body) = proof.split_at_mut(SIMULATED_PROOF_MAGIC.len());
+    magic.copy_from_slice(SIMULATED_PROOF_MAGIC);
+    hasher.finalize_xof().fill(body);
+    Ok(proof)
+}
+
+pub fn is_simulated_proof(proof: &[u8]) -> bool {
+    proof.starts_with(SIMULATED_PROOF_MAGIC)
+}
+
+pub fn to_field_matrix(input: &[Vec<f64>]) -> Vec<Vec<Fr>> {
+    input
+        .iter()
+        .map(|row| row.iter().map(|value| float_to_field(*value)).collect())
+        .collect()
+}
+
+pub fn compute_field_residuals(folded: &[Vec<Fr>], pq: &[Vec<Fr>]) -> Vec<Fr> {
+    folded
+        .iter()
+        .zip(pq.iter())
+        .map(|(f_row, pq_row)| {
+            f_row
+                .iter()
+                .zip(pq_row.iter())
+                .fold(Fr::zero(), |acc, (a, b)| {
+                    let diff = *a - *b;
+                    acc + diff.square()
+                })
+        })
+        .collect()
+}
+
+/// Squared fixed-point residual of every row at the default scale, as a
+/// witness generator reports them in [`WitnessData::residuals`].
+pub fn fixed_residuals(folded: &[Vec<f64>], pq: &[Vec<f64>]) -> Vec<u128> {
+    FixedPoint::default().residuals(folded, pq)
+}
+
+/// The witness's reported residuals as circuit values, zero-padded to
+/// `rows`, once each matches its vectors at `fixed_point`. The circuit's residual gate then confirms them row by row.
+pub fn claimed_residuals(
+    witness: &WitnessData,
+    rows: usize,
+    fixed_point: FixedPoint,
+) -> Result<Vec<Fr>> {
+    let actual = fixed_point.residuals(&witness.folded_vectors, &witness.pq_vectors);
+    residual_fields(witness, &actual, rows, fixed_point)
+}
+
+/// [`claimed_residuals`] against `actual`, the residuals recomputed from
+/// the witness's vectors.
+fn residual_fields(
+    witness: &WitnessData,
+    actual: &[u128],
+    rows: usize,
+    fixed_point: FixedPoint,
+) -> Result<Vec<Fr>> {
+    let claimed = witness.residuals.as_deref().ok_or_else(|| {
+        Coded::new(
+            ErrorCode::InvalidInput,
+            "witness has no residuals; the witness generator must report them",
+        )
+    })?;
+    if claimed.len() != actual.len() || claimed.len() > rows {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "witness reports {} residuals for {} vectors",
+                claimed.len(),
+                actual.len()
+            )
+        ));
+    }
+    if let Some(row) = (0..claimed.len()).find(|&row| claimed[row] != actual[row]) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "vector {row} has residual {} but the witness reports {}",
+                actual[row], claimed[row]
+            )
+        ));
+    }
+    Ok(epsilon::residual_cells(claimed, fixed_point, rows))
+}
+
+/// Default fixed-point scale of f64 -> field conversions; keys may set
+/// another, see [`FoldedParams::scale`].
+pub const FIXED_POINT_SCALE: f64 = DEFAULT_SCALE as f64;
+
+/// `value` at the default scale, saturating out-of-range values; the
+/// circuit is built with [`FixedPoint::to_field_matrix`], which rejects them.
+pub fn float_to_field(value: f64) -> Fr {
+    FixedPoint::default().saturating_field(value)
+}
+
+/// `floor(value * FIXED_POINT_SCALE)` as a signed integer in the field, without
+/// dividing the scale back out; squared differences stay integers and can be
+/// range checked.
+pub fn float_to_fixed(value: f64) -> Fr {
+    from_i64(FixedPoint::default().saturating_fixed(value))
+}
@@ -0,0 +1,263 @@
+//! Long-lived proving handle.
+//!
+//! [`Prover`] owns the params, proving key and circuit layout behind an
+//! `Arc`, so one handle (or cheap clones of it) can serve proofs from any
+//! number of threads. halo2's `create_proof` only borrows the keys, so
+//! concurrent proofs share them without locking.
+//!
+//! It is also the library entry point for embedding the prover: the
+//! `prover` binary, the proving server and the watcher all prove through
+//! [`Prover::prove_with`] (or [`Prover::prove_field_with`] for a witness
+//! streamed into the field), so a service holding a handle gets the same
+//! circuit construction, transcript and metadata without shelling out.
+
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use halo2_proofs::{
+    plonk::ProvingKey,
+    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+
+use crate::{
+    cancel::CancellationToken,
+    circuit::{FoldedCircuit, FoldedParams},
+    errors::{Coded, ErrorCode},
+    io::{FieldWitness, Provenance, WitnessData},
+    keys::{load_or_init_keys_with_setup, read_circuit_k, read_circuit_params},
+    metadata::ProofMetadataV1,
+    prove::{
+        build_circuit_with, build_field_circuit, create_circuit_proof_in, simulate_circuit_proof,
+    },
+    public_inputs::ParsedPublicInputs,
+    selftest::{run_self_test, SelfTestReport},
+    shape::{negotiate, negotiate_shape},
+    srs::load_srs,
+    transcript::TranscriptKind,
+};
+
+/// A proof with the instances it was created for.
+#[derive(Debug, Clone)]
+pub struct ProverOutput {
+    pub proof: Vec<u8>,
+    pub instances: Vec<Fr>,
+    pub metadata: ProofMetadataV1,
+    pub timings: ProveTimings,
+}
+
+/// Wall time of the steps of one [`Prover::prove_with`] call.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProveTimings {
+    /// Shape negotiation and circuit construction.
+    pub witness: Duration,
+    pub prove: Duration,
+}
+
+struct Inner {
+    params: ParamsKZG<Bn256>,
+    pk: ProvingKey<G1Affine>,
+    layout: FoldedParams,
+}
+
+#[derive(Clone)]
+pub struct Prover {
+    inner: Arc<Inner>,
+    simulate: bool,
+    transcript: TranscriptKind,
+}
+
+impl Prover {
+    pub fn new(pk: ProvingKey<G1Affine>, params: ParamsKZG<Bn256>, layout: FoldedParams) -> Self {
+        Self {
+            inner: Arc::new(Inner { params, pk, layout }),
+            simulate: false,
+            transcript: TranscriptKind::default(),
+        }
+    }
+
+    /// Loads (or generates) the keys for the layout recorded in the
+    /// verifying key config, which must exist: every layout is keyed to a
+    /// witness shape, so there is no default circuit to fall back to.
+    pub fn load(proving_key: &Path, verification_key: &Path, circuit_k: u32) -> Result<Self> {
+        Self::load_with_setup(proving_key, verification_key, circuit_k, None)
+    }
+
+    /// [`Prover::load`] on the trusted setup at `setup` (see [`crate::srs`]).
+    pub fn load_with_setup(
+        proving_key: &Path,
+        verification_key: &Path,
+        circuit_k: u32,
+        setup: Option<&Path>,
+    ) -> Result<Self> {
+        if !verification_key.exists() {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Usage,
+                format!(
+                    "no key config at {:?}; generate k={circuit_k} keys for a witness with \
+                     `prover` or `keygen --layout`",
+                    verification_key
+                )
+            ));
+        }
+        let layout = read_circuit_params(verification_key)?;
+        let circuit_k = read_circuit_k(verification_key)?;
+        let setup = setup.map(|path| load_srs(path, circuit_k)).transpose()?;
+        let blank = FoldedCircuit::blank_with(&layout);
+        let (params, pk) = load_or_init_keys_with_setup(
+            proving_key,
+            verification_key,
+            circuit_k,
+            &blank,
+            setup.as_ref(),
+        )?;
+        Ok(Self::new(pk, params, layout))
+    }
+
+    /// Skips `create_proof` and returns simulated proofs (see
+    /// [`simulate_circuit_proof`]); everything around it runs as usual.
+    pub fn with_simulation(self, simulate: bool) -> Self {
+        Self { simulate, ..self }
+    }
+
+    /// Proves under `transcript` instead of blake2b.
+    pub fn with_transcript(self, transcript: TranscriptKind) -> Self {
+        Self { transcript, ..self }
+    }
+
+    pub fn transcript(&self) -> TranscriptKind {
+        self.transcript
+    }
+
+    pub fn simulates(&self) -> bool {
+        self.simulate
+    }
+
+    pub fn circuit_k(&self) -> u32 {
+        self.inner.params.k()
+    }
+
+    pub fn layout(&self) -> &FoldedParams {
+        &self.inner.layout
+    }
+
+    pub fn params(&self) -> &ParamsKZG<Bn256> {
+        &self.inner.params
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<G1Affine> {
+        &self.inner.pk
+    }
+
+    pub fn prove(
+        &self,
+        witness: &WitnessData,
+        public_inputs: &ParsedPublicInputs,
+    ) -> Result<ProverOutput> {
+        self.prove_with(witness, public_inputs, &CancellationToken::new())
+    }
+
+    /// [`Self::prove`], checking `cancel` between steps.
+    pub fn prove_with(
+        &self,
+        witness: &WitnessData,
+        public_inputs: &ParsedPublicInputs,
+        cancel: &CancellationToken,
+    ) -> Result<ProverOutput> {
+        let started = Instant::now();
+        negotiate(witness, &self.inner.layout, Some(self.circuit_k()))?;
+        let circuit = build_circuit_with(witness, public_inputs, &self.inner.layout, cancel)?;
+        self.prove_circuit(
+            circuit,
+            witness.provenance.as_ref(),
+            public_inputs,
+            started,
+            cancel,
+        )
+    }
+
+    /// [`Self::prove_with`] for a witness streamed into the field, see
+    /// [`build_field_circuit`].
+    pub fn prove_field_with(
+        &self,
+        witness: FieldWitness,
+        public_inputs: &ParsedPublicInputs,
+        cancel: &CancellationToken,
+    ) -> Result<ProverOutput> {
+        let started = Instant::now();
+        negotiate_shape(
+            &witness.witness,
+            witness.shape()?,
+            &self.inner.layout,
+            Some(self.circuit_k()),
+        )?;
+        let provenance = witness.witness.provenance.clone();
+        let circuit = build_field_circuit(witness, public_inputs, &self.inner.layout, cancel)?;
+        self.prove_circuit(circuit, provenance.as_ref(), public_inputs, started, cancel)
+    }
+
+    /// Proves a built `circuit`, timing the witness step from `started`.
+    fn prove_circuit(
+        &self,
+        circuit: FoldedCircuit,
+        provenance: Option<&Provenance>,
+        public_inputs: &ParsedPublicInputs,
+        started: Instant,
+        cancel: &CancellationToken,
+    ) -> Result<ProverOutput> {
+        let inner = &self.inner;
+        let witness_time = started.elapsed();
+        let proof = if self.simulate {
+            simulate_circuit_proof(&inner.params, &inner.pk, &circuit.public_inputs, cancel)?
+        } else {
+            create_circuit_proof_in(
+                &inner.params,
+                &inner.pk,
+                &circuit,
+                &circuit.public_inputs,
+                self.transcript,
+                cancel,
+            )?
+        };
+        let timings = ProveTimings {
+            witness: witness_time,
+            prove: started.elapsed() - witness_time,
+        };
+        let metadata = ProofMetadataV1::new(
+            self.circuit_k(),
+            Some(public_inputs.block_height),
+            &circuit.public_inputs,
+            &proof,
+        )
+        .with_previous_digest(
+            public_inputs
+                .previous_proof_digest
+                .map(|digest| digest.to_hex())
+                .as_deref(),
+        )
+        .with_provenance(provenance)
+        .with_simulated(self.simulate)
+        .with_transcript(self.transcript)
+        .with_scale(self.layout().scale());
+        Ok(ProverOutput {
+            proof,
+            instances: circuit.public_inputs,
+            metadata,
+            timings,
+        })
+    }
+
+    /// Runs the canary proof against this handle's keys.
+    pub fn self_test(&self) -> Result<SelfTestReport> {
+        run_self_test(
+            &self.inner.params,
+            &self.inner.pk,
+            self.circuit_k(),
+            &self.inner.layout,
+        )
+    }
+}
@@ -1,71 +1,350 @@
-use anyhow::Result;
-use blake3::Hasher;
-use halo2curves::{
-    bn256::Fr,
-    ff::Field,
-};
-use hex::FromHex;
+use anyhow::{Context, Result};
+use halo2curves::{bn256::Fr, ff::Field};
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    circuit::FoldedParams,
+    codebook::CODEBOOK_ROOT_SLOT,
+    epsilon::{epsilon_commitment, subvector_bounds},
+    hashing::{digest, HashAlgorithm},
+    hiding::{FOLDED_COMMITMENT_SLOT, PQ_COMMITMENT_SLOT},
+    platform::{from_json_slice, normalize, strip_bom},
+    quantization::CompressionStats,
+    storage::Storage,
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ParsedPublicInputs {
     #[serde(rename = "prevStateRoot")]
-    pub prev_state_root: String,
+    pub prev_state_root: Hash256,
     #[serde(rename = "newStateRoot")]
-    pub new_state_root: String,
+    pub new_state_root: Hash256,
     #[serde(rename = "blockHeight")]
     pub block_height: u64,
     #[serde(rename = "txMerkleRoot")]
-    pub tx_merkle_root: String,
+    pub tx_merkle_root: Hash256,
     #[serde(rename = "foldedCommitment")]
-    pub folded_commitment: String,
+    pub folded_commitment: Hash256,
     #[serde(rename = "pqCommitment")]
-    pub pq_commitment: String,
+    pub pq_commitment: Hash256,
     #[serde(rename = "codebookRoot")]
-    pub codebook_root: String,
+    pub codebook_root: Hash256,
+    /// Poseidon Merkle root of the folded vectors as a canonical field
+    /// element; required when the circuit is keyed with `vectorRoot`.
+    #[serde(
+        rename = "foldedVectorRoot",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub folded_vector_root: Option<Hash256>,
+    /// Poseidon commitment to the flattened PQ code list; required when the
+    /// circuit is keyed with `pqCodes`.
+    #[serde(
+        rename = "pqCodesCommitment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pq_codes_commitment: Option<Hash256>,
+    /// blake3 digest of the previous block's proof; required when the
+    /// circuit is keyed with `lineage`.
+    #[serde(
+        rename = "previousProofDigest",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub previous_proof_digest: Option<Hash256>,
+    /// 32-byte randomness beacon output, e.g. a drand round's
+    /// `randomness`; required when the circuit is keyed with `beacon`.
+    #[serde(
+        rename = "beaconValue",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub beacon_value: Option<Hash256>,
+    /// Poseidon commitment to the padded sparsity pattern; required when the circuit is keyed with `nonzeros`.
+    #[serde(
+        rename = "sparsityCommitment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sparsity_commitment: Option<Hash256>,
+    /// `foldedVectorRoot` of the previous block, which a delta proof updates
+    /// into this block's; required when the circuit is keyed with `delta`.
+    #[serde(
+        rename = "previousVectorRoot",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub previous_vector_root: Option<Hash256>,
+    /// Salted Poseidon Merkle root over the full witness rows; required when
+    /// the circuit is keyed with `witnessCommitment`.
+    #[serde(
+        rename = "witnessCommitment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub witness_commitment: Option<Hash256>,
+    /// Poseidon commitment to the witness's OPQ rotation; required when the
+    /// circuit is keyed with `rotation`.
+    #[serde(
+        rename = "rotationCommitment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rotation_commitment: Option<Hash256>,
+    /// Root of the residual codebook of a two-stage witness, under the same
+    /// commit mode as `codebookRoot`; required when the circuit is keyed with
+    /// `residualCentroids`.
+    #[serde(
+        rename = "residualCodebookRoot",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub residual_codebook_root: Option<Hash256>,
+    /// Poseidon commitment to the witness's int8 scales, zero points and
+    /// codes; required when the circuit is keyed with `scalar`.
+    #[serde(
+        rename = "scalarCommitment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scalar_commitment: Option<Hash256>,
+    /// Poseidon commitment to the salt and order rows are committed in;
+    /// required when the circuit is keyed with `rowPermutation`.
+    #[serde(
+        rename = "permutationCommitment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub permutation_commitment: Option<Hash256>,
+    /// Required when the circuit is keyed with `compressionStats`; must equal
+    /// the stats of the keyed shape.
+    #[serde(
+        rename = "compressionStats",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub compression_stats: Option<CompressionStats>,
+    /// Residual bound per PQ subspace; required when the circuit is keyed
+    /// with `subvectorEpsilons`.
+    #[serde(
+        rename = "subvectorEpsilons",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub subvector_epsilons: Option<Vec<f64>>,
+    /// Maximum reconstruction error of any vector; required when the circuit
+    /// is keyed with `slaBound`.
+    #[serde(rename = "slaBound", default, skip_serializing_if = "Option::is_none")]
+    pub sla_bound: Option<f64>,
+    /// Poseidon commitment to the residual of every laid-out vector (see
+    /// [`crate::epsilon::residual_commitment`]); required by every circuit.
+    #[serde(
+        rename = "residualCommitment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub residual_commitment: Option<Hash256>,
 }
 
 pub fn load_public_inputs(path: impl AsRef<std::path::Path>) -> Result<ParsedPublicInputs> {
-    let file = std::fs::File::open(path)?;
-    let reader = std::io::BufReader::new(file);
-    let public_inputs = serde_json::from_reader(reader)?;
-    Ok(public_inputs)
+    let path = normalize(path.as_ref());
+    let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+    from_json_slice(&bytes).with_context(|| format!("parsing public inputs {:?}", path))
+}
+
+/// Loads a public-inputs file holding one block or an array of blocks.
+pub fn load_public_inputs_batch(
+    path: impl AsRef<std::path::Path>,
+) -> Result<Vec<ParsedPublicInputs>> {
+    let path = normalize(path.as_ref());
+    let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+    let is_array = strip_bom(&bytes)
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        == Some(&b'[');
+    if is_array {
+        from_json_slice(&bytes).with_context(|| format!("parsing public inputs array {:?}", path))
+    } else {
+        from_json_slice(&bytes)
+            .map(|block| vec![block])
+            .with_context(|| format!("parsing public inputs {:?}", path))
+    }
+}
+
+pub fn load_public_inputs_from(storage: &dyn Storage, key: &str) -> Result<ParsedPublicInputs> {
+    let bytes = storage.read(key)?;
+    from_json_slice(&bytes).with_context(|| format!("parsing public inputs {key}"))
 }
 
 impl ParsedPublicInputs {
     pub fn to_field_elements(&self) -> Result<Vec<Fr>> {
         Ok(vec![
-            hex_to_field(&self.folded_commitment)?,
-            hex_to_field(&self.pq_commitment)?,
-            hex_to_field(&self.codebook_root)?,
+            root_to_field(&self.folded_commitment),
+            root_to_field(&self.pq_commitment),
+            root_to_field(&self.codebook_root),
         ])
     }
 
+    /// Instance column values for a circuit keyed with `params`.
+    pub fn to_instances(&self, params: &FoldedParams) -> Result<Vec<Fr>> {
+        Ok(params.instances(self.public_values(params)?))
+    }
+
+    /// Every public value the circuit constrains, in slot order; the same as
+    /// the instances unless `params.instance_hash` is set.
+    pub fn public_values(&self, params: &FoldedParams) -> Result<Vec<Fr>> {
+        let mut instances = self.to_field_elements()?;
+        if params.codebook_commitment.is_some() {
+            instances[CODEBOOK_ROOT_SLOT] = self
+                .codebook_root
+                .to_canonical_field()
+                .context("codebookRoot must be a canonical field element")?;
+        }
+        if params.hiding || params.vector_commitments {
+            instances[FOLDED_COMMITMENT_SLOT] = self
+                .folded_commitment
+                .to_canonical_field()
+                .context("foldedCommitment must be a canonical field element")?;
+            instances[PQ_COMMITMENT_SLOT] = self
+                .pq_commitment
+                .to_canonical_field()
+                .context("pqCommitment must be a canonical field element")?;
+        }
+        if params.vector_root {
+            let root = self
+                .folded_vector_root
+                .context("public inputs missing foldedVectorRoot")?;
+            instances.push(root.to_canonical_field()?);
+        }
+        if params.pq_codes {
+            let commitment = self
+                .pq_codes_commitment
+                .context("public inputs missing pqCodesCommitment")?;
+            instances.push(commitment.to_canonical_field()?);
+        }
+        if params.lineage {
+            let digest = self
+                .previous_proof_digest
+                .context("public inputs missing previousProofDigest")?;
+            instances.push(digest.to_field_reduced());
+        }
+        if params.beacon {
+            let beacon = self
+                .beacon_value
+                .context("public inputs missing beaconValue")?;
+            instances.push(beacon.to_field_reduced());
+        }
+        if params.nonzeros.is_some() {
+            let commitment = self
+                .sparsity_commitment
+                .context("public inputs missing sparsityCommitment")?;
+            instances.push(commitment.to_canonical_field()?);
+        }
+        if params.compression_stats {
+            let stats = self
+                .compression_stats
+                .context("public inputs missing compressionStats")?;
+            instances.push(Fr::from(stats.original_bytes));
+            instances.push(Fr::from(stats.pq_bytes));
+        }
+        if params.delta.is_some() {
+            let root = self
+                .previous_vector_root
+                .context("public inputs missing previousVectorRoot")?;
+            instances.push(root.to_canonical_field()?);
+        }
+        if params.witness_commitment {
+            let commitment = self
+                .witness_commitment
+                .context("public inputs missing witnessCommitment")?;
+            instances.push(commitment.to_canonical_field()?);
+        }
+        if params.rotation {
+            let commitment = self
+                .rotation_commitment
+                .context("public inputs missing rotationCommitment")?;
+            instances.push(commitment.to_canonical_field()?);
+        }
+        if params.residual_centroids.is_some() {
+            let root = self
+                .residual_codebook_root
+                .context("public inputs missing residualCodebookRoot")?;
+            instances.push(root.to_canonical_field()?);
+        }
+        if params.scalar {
+            let commitment = self
+                .scalar_commitment
+                .context("public inputs missing scalarCommitment")?;
+            instances.push(commitment.to_canonical_field()?);
+        }
+        if params.row_permutation {
+            let commitment = self
+                .permutation_commitment
+                .context("public inputs missing permutationCommitment")?;
+            instances.push(commitment.to_canonical_field()?);
+        }
+        if params.subvector_epsilons {
+            let epsilons = self
+                .subvector_epsilons
+                .as_deref()
+                .context("public inputs missing subvectorEpsilons")?;
+            if epsilons.len() != params.subvectors {
+                anyhow::bail!(
+                    "subvectorEpsilons has {} entries, keyed for {} subspaces",
+                    epsilons.len(),
+                    params.subvectors
+                );
+            }
+            let bounds = subvector_bounds(epsilons).context("subvectorEpsilons")?;
+            instances.push(epsilon_commitment(&bounds));
+        }
+        if params.sla_bound {
+            let sla = self.sla_bound.context("public inputs missing slaBound")?;
+            let bound = params.fixed_point()?.squared_threshold(sla);
+            instances.push(Fr::from_u128(bound.context("slaBound")?));
+        }
+        let commitment = self
+            .residual_commitment
+            .context("public inputs missing residualCommitment")?;
+        instances.push(commitment.to_canonical_field()?);
+        debug_assert_eq!(instances.len(), params.public_len());
+        Ok(instances)
+    }
+
     pub fn commitment_fields(&self) -> Result<[Fr; 3]> {
         Ok([
-            hex_to_field(&self.folded_commitment)?,
-            hex_to_field(&self.pq_commitment)?,
-            hex_to_field(&self.codebook_root)?,
+            root_to_field(&self.folded_commitment),
+            root_to_field(&self.pq_commitment),
+            root_to_field(&self.codebook_root),
         ])
     }
 }
 
-fn hex_to_field(hex_str: &str) -> Result<Fr> {
-    let normalized = hex_str.trim_start_matches("0x").trim_start_matches("0X");
-    let bytes = Vec::from_hex(normalized)?;
-    let seed = if bytes.is_empty() {
-        [0u8; 32]
-    } else {
-        let mut hasher = Hasher::new();
-        hasher.update(&bytes);
-        let hash = hasher.finalize();
-        let mut out = [0u8; 32];
-        out.copy_from_slice(hash.as_bytes());
-        out
-    };
+/// Maps a 32-byte root onto a field element through a blake3-seeded
+/// ChaCha20 draw, so roots wider than the field still land uniformly.
+fn root_to_field(root: &Hash256) -> Fr {
+    let seed = digest(root.as_bytes()).0;
     let mut rng = ChaCha20Rng::from_seed(seed);
-    Ok(Fr::random(&mut rng))
+    Fr::random(&mut rng)
 }
 
+/// Formats a field element as a 0x-prefixed, 32-byte big-endian hex string (EVM `uint256` order).
+pub fn field_to_hex(value: &Fr) -> String {
+    Hash256::from_field(value).to_hex()
+}
+
+/// `sha256(be32(values[0]) || be32(values[1]) || ..) mod p`, the single
+/// instance in `instanceHash` mode. On the EVM this is
+/// `uint256(sha256(abi.encodePacked(values))) % p` with every value `< p`.
+pub fn instance_hash(values: &[Fr]) -> Fr {
+    let mut hasher = HashAlgorithm::Sha256.hasher();
+    for value in values {
+        hasher.update(Hash256::from_field(value).as_bytes());
+    }
+    hasher.finalize().to_field_reduced()
+}
@@ -0,0 +1,409 @@
+//! Product-quantization helpers: code commitments, witness shape checks and
+//! partition presets for common embedding sizes.
+//!
+//! A witness may add a second, residual quantization stage
+//! ([`ResidualStage`]): a codebook of the same partition quantizing what the
+//! primary centroids leave over. Every pq component is then the fixed-point
+//! sum of its primary and residual centroid components (see [`stage_sum`]).
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    io::WitnessData,
+    poseidon::{domain_capacity, hash_with_capacity, CODES_DOMAIN},
+    prove::{float_to_field, FIXED_POINT_SCALE},
+};
+
+/// Second stage of two-stage residual quantization.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ResidualStage {
+    /// Residual centroid index per vector and subspace.
+    pub codes: Vec<Vec<u32>>,
+    /// Residual codebook as `codebook[subspace][centroid][component]`, with
+    /// the primary codebook's subspaces and widths.
+    pub codebook: Vec<Vec<Vec<f64>>>,
+}
+
+/// The pq component of a two-stage code: a value whose fixed-point form is
+/// the sum of the fixed-point `primary` and `residual` components, as the
+/// circuit adds them.
+pub fn stage_sum(primary: f64, residual: f64) -> f64 {
+    let fixed = (primary * FIXED_POINT_SCALE).floor() + (residual * FIXED_POINT_SCALE).floor();
+    (fixed + 0.5) / FIXED_POINT_SCALE
+}
+
+/// Every code row `pqCodesCommitment` covers: the primary codes, then the
+/// residual codes of a two-stage witness.
+pub fn stage_codes(witness: &WitnessData) -> Vec<Vec<u32>> {
+    let mut codes = witness.pq_codes.clone().unwrap_or_default();
+    if let Some(stage) = &witness.residual_stage {
+        codes.extend(stage.codes.iter().cloned());
+    }
+    codes
+}
+
+/// Shape of a product quantizer: `dim` components split into `subvectors`
+/// segments, each quantized against `centroids` entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PqShape {
+    pub dim: usize,
+    pub subvectors: usize,
+    pub centroids: usize,
+}
+
+impl PqShape {
+    pub fn sub_dim(&self) -> usize {
+        self.dim / self.subvectors
+    }
+}
+
+/// Storage of a block before and after product quantization: `f32`
+/// components against bit-packed codes plus the `f32` codebook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CompressionStats {
+    pub original_bytes: u64,
+    pub pq_bytes: u64,
+}
+
+impl CompressionStats {
+    pub fn new(vectors: usize, shape: PqShape) -> Self {
+        let code_bits = (shape.centroids.max(2) - 1).ilog2() as u64 + 1;
+        let codes = (vectors * shape.subvectors) as u64 * code_bits;
+        let codebook = (shape.centroids * shape.dim) as u64 * 4;
+        Self {
+            original_bytes: (vectors * shape.dim) as u64 * 4,
+            pq_bytes: codes.div_ceil(8) + codebook,
+        }
+    }
+
+    pub fn ratio(&self) -> f64 {
+        self.original_bytes as f64 / self.pq_bytes.max(1) as f64
+    }
+}
+
+/// Flattens codes row-major into field elements.
+pub fn codes_to_fields(codes: &[Vec<u32>]) -> Vec<Fr> {
+    codes
+        .iter()
+        .flat_map(|row| row.iter().map(|code| Fr::from(*code as u64)))
+        .collect()
+}
+
+/// Poseidon commitment to the code list, matching the in-circuit computation.
+pub fn codes_commitment(codes: &[Vec<u32>]) -> Fr {
+    let flat = codes_to_fields(codes);
+    hash_with_capacity(domain_capacity(CODES_DOMAIN, flat.len()), &flat)
+}
+
+/// Validates that codes, codebook and pq vectors in `witness` are mutually
+/// consistent, returning the quantizer shape.
+pub fn validate_pq_witness(witness: &WitnessData) -> Result<PqShape> {
+    let codes = witness
+        .pq_codes
+        .as_ref()
+        .context("witness missing pqCodes")?;
+    let codebook = witness
+        .codebook
+        .as_ref()
+        .context("witness missing codebook")?;
+    let dim = witness.pq_vectors.first().map(Vec::len).unwrap_or(0);
+    let subvectors = codebook.len();
+    let centroids = codebook.first().map(Vec::len).unwrap_or(0);
+    if subvectors == 0 || centroids == 0 || dim % subvectors != 0 {
+        anyhow::bail!(
+            "codebook with {subvectors} subspaces x {centroids} centroids does not fit {dim} dims"
+        );
+    }
+    let shape = PqShape {
+        dim,
+        subvectors,
+        centroids,
+    };
+    let sub_dim = shape.sub_dim();
+    for (subspace, entries) in codebook.iter().enumerate() {
+        if entries.len() != centroids || entries.iter().any(|entry| entry.len() != sub_dim) {
+            anyhow::bail!(
+                "codebook subspace {subspace} must hold {centroids} centroids of {sub_dim} components"
+            );
+        }
+    }
+    if codes.len() != witness.pq_vectors.len() {
+        anyhow::bail!(
+            "pqCodes has {} rows but witness has {} pq vectors",
+            codes.len(),
+            witness.pq_vectors.len()
+        );
+    }
+    let stage = witness.residual_stage.as_ref();
+    if let Some(stage) = stage {
+        validate_residual_stage(stage, shape, codes.len())?;
+    }
+    for (row_idx, (row_codes, pq_row)) in codes.iter().zip(witness.pq_vectors.iter()).enumerate() {
+        if row_codes.len() != subvectors || pq_row.len() != dim {
+            anyhow::bail!("row {row_idx}: expected {subvectors} codes and {dim} pq components");
+        }
+        for (subspace, code) in row_codes.iter().enumerate() {
+            let centroid = codebook[subspace].get(*code as usize).with_context(|| {
+                format!("row {row_idx}: code {code} out of range in subspace {subspace}")
+            })?;
+            let mut expected: Vec<Fr> = centroid.iter().map(|v| float_to_field(*v)).collect();
+            if let Some(stage) = stage {
+                let code = stage.codes[row_idx][subspace] as usize;
+                for (sum, v) in expected.iter_mut().zip(&stage.codebook[subspace][code]) {
+                    *sum += float_to_field(*v);
+                }
+            }
+            let segment = &pq_row[subspace * sub_dim..(subspace + 1) * sub_dim];
+            let matches = segment
+                .iter()
+                .zip(expected.iter())
+                .all(|(a, b)| float_to_field(*a) == *b);
+            if !matches {
+                anyhow::bail!(
+                    "row {row_idx}: pq vector segment {subspace} does not equal centroid {code}{}",
+                    if stage.is_some() {
+                        " plus its residual centroid"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+    }
+    Ok(shape)
+}
+
+/// Checks that `stage` partitions like `shape` and codes `rows` vectors with
+/// in-range codes, returning its centroids per subspace.
+pub fn validate_residual_stage(
+    stage: &ResidualStage,
+    shape: PqShape,
+    rows: usize,
+) -> Result<usize> {
+    let centroids = stage.codebook.first().map(Vec::len).unwrap_or(0);
+    let sub_dim = shape.sub_dim();
+    if stage.codebook.len() != shape.subvectors
+        || centroids == 0
+        || stage
+            .codebook
+            .iter()
+            .any(|entries| entries.len() != centroids || entries.iter().any(|e| e.len() != sub_dim))
+    {
+        anyhow::bail!(
+            "residual codebook must hold {} subspaces of equally many {sub_dim}-component centroids",
+            shape.subvectors
+        );
+    }
+    if stage.codes.len() != rows {
+        anyhow::bail!(
+            "residualStage has {} code rows for {rows} vectors",
+            stage.codes.len()
+        );
+    }
+    for (row_idx, row_codes) in stage.codes.iter().enumerate() {
+        if row_codes.len() != shape.subvectors {
+            anyhow::bail!(
+                "row {row_idx}: expected {} residual codes",
+                shape.subvectors
+            );
+        }
+        if let Some(code) = row_codes.iter().find(|code| **code as usize >= centroids) {
+            anyhow::bail!("row {row_idx}: residual code {code} out of range");
+        }
+    }
+    Ok(centroids)
+}
+
+/// Components per subvector used when a preset or plan does not say otherwise.
+pub const DEFAULT_SUB_DIM: usize = 8;
+pub const DEFAULT_CENTROIDS: usize = 256;
+
+/// Output sizes of common embedding models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingPreset {
+    /// all-MiniLM-L6-v2 and other small SentenceTransformers models.
+    MiniLm384,
+    /// all-mpnet-base-v2, BERT-base sized SentenceTransformers models.
+    Mpnet768,
+    /// OpenAI text-embedding-3-small and text-embedding-ada-002.
+    OpenAiSmall1536,
+    /// OpenAI text-embedding-3-large.
+    OpenAiLarge3072,
+}
+
+impl EmbeddingPreset {
+    pub const ALL: [EmbeddingPreset; 4] = [
+        EmbeddingPreset::MiniLm384,
+        EmbeddingPreset::Mpnet768,
+        EmbeddingPreset::OpenAiSmall1536,
+        EmbeddingPreset::OpenAiLarge3072,
+    ];
+
+    pub fn dim(self) -> usize {
+        match self {
+            EmbeddingPreset::MiniLm384 => 384,
+            EmbeddingPreset::Mpnet768 => 768,
+            EmbeddingPreset::OpenAiSmall1536 => 1536,
+            EmbeddingPreset::OpenAiLarge3072 => 3072,
+        }
+    }
+
+    /// Default quantizer: 8-component subvectors against 256 centroids.
+    pub fn shape(self) -> PqShape {
+        PqShape {
+            dim: self.dim(),
+            subvectors: self.dim() / DEFAULT_SUB_DIM,
+            centroids: DEFAULT_CENTROIDS,
+        }
+    }
+
+    pub fn for_dim(dim: usize) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.dim() == dim)
+    }
+}
+
+impl fmt::Display for EmbeddingPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EmbeddingPreset::MiniLm384 => "minilm-384",
+            EmbeddingPreset::Mpnet768 => "mpnet-768",
+            EmbeddingPreset::OpenAiSmall1536 => "openai-small-1536",
+            EmbeddingPreset::OpenAiLarge3072 => "openai-large-3072",
+        })
+    }
+}
+
+impl FromStr for EmbeddingPreset {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "384" | "minilm" | "minilm-384" | "all-minilm-l6-v2" => Ok(EmbeddingPreset::MiniLm384),
+            "768" | "mpnet" | "mpnet-768" | "all-mpnet-base-v2" => Ok(EmbeddingPreset::Mpnet768),
+            "1536"
+            | "openai-small"
+            | "openai-small-1536"
+            | "text-embedding-3-small"
+            | "text-embedding-ada-002" => Ok(EmbeddingPreset::OpenAiSmall1536),
+            "3072" | "openai-large" | "openai-large-3072" | "text-embedding-3-large" => {
+                Ok(EmbeddingPreset::OpenAiLarge3072)
+            }
+            other => anyhow::bail!("unknown embedding preset {other}"),
+        }
+    }
+}
+
+/// How a dimensionality is split into PQ subspaces, plus anything the caller
+/// should know before generating codebooks with it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionPlan {
+    pub dim: usize,
+    /// Dimension after zero padding; equal to `dim` unless padding was needed.
+    pub padded_dim: usize,
+    pub subvectors: usize,
+    pub sub_dim: usize,
+    pub centroids: usize,
+    /// Rows the in-circuit codebook lookup table occupies.
+    pub table_rows: usize,
+    pub warnings: Vec<String>,
+}
+
+impl PartitionPlan {
+    pub fn shape(&self) -> PqShape {
+        PqShape {
+            dim: self.padded_dim,
+            subvectors: self.subvectors,
+            centroids: self.centroids,
+        }
+    }
+}
+
+/// Picks a subvector partition for `dim` close to `target_sub_dim`.
+///
+/// An exact split is used when `target_sub_dim` divides `dim`; otherwise the
+/// nearest divisor within a factor of two, and failing that the vectors are
+/// zero-padded up to the next multiple. Both fallbacks are reported in
+/// `warnings`.
+pub fn plan_partition(
+    dim: usize,
+    target_sub_dim: usize,
+    centroids: usize,
+) -> Result<PartitionPlan> {
+    if dim == 0 || target_sub_dim == 0 {
+        anyhow::bail!("dim and subvector size must be positive");
+    }
+    if centroids == 0 || centroids > u32::MAX as usize {
+        anyhow::bail!("centroid count {centroids} out of range");
+    }
+    let mut warnings = Vec::new();
+    if EmbeddingPreset::for_dim(dim).is_none() {
+        let known: Vec<String> = EmbeddingPreset::ALL
+            .iter()
+            .map(|preset| preset.dim().to_string())
+            .collect();
+        warnings.push(format!(
+            "dim {dim} matches no embedding preset ({})",
+            known.join("/")
+        ));
+    }
+
+    let (sub_dim, padded_dim) = if dim % target_sub_dim == 0 {
+        (target_sub_dim, dim)
+    } else if let Some(sub_dim) = nearest_divisor(dim, target_sub_dim) {
+        warnings.push(format!(
+            "dim {dim} does not divide into {target_sub_dim}-component subvectors; using {sub_dim}"
+        ));
+        (sub_dim, dim)
+    } else {
+        let padded = dim.div_ceil(target_sub_dim) * target_sub_dim;
+        warnings.push(format!(
+            "dim {dim} has no divisor near {target_sub_dim}; pad vectors with {} zero components to {padded}",
+            padded - dim
+        ));
+        (target_sub_dim, padded)
+    };
+    if sub_dim == 1 && target_sub_dim > 1 {
+        warnings.push("subvectors of one component make PQ a scalar quantizer".to_string());
+    }
+
+    let table_rows = padded_dim * centroids;
+    let min_k = usize::BITS - table_rows.saturating_sub(1).leading_zeros();
+    if min_k > 20 {
+        warnings.push(format!(
+            "codebook lookup needs {table_rows} rows; pqCodes proofs need circuit k >= {min_k}"
+        ));
+    }
+    Ok(PartitionPlan {
+        dim,
+        padded_dim,
+        subvectors: padded_dim / sub_dim,
+        sub_dim,
+        centroids,
+        table_rows,
+        warnings,
+    })
+}
+
+/// Zero-pads `values` to `padded_dim` components.
+pub fn pad_vector(values: &[f64], padded_dim: usize) -> Vec<f64> {
+    let mut padded = values.to_vec();
+    padded.resize(padded_dim.max(values.len()), 0.0);
+    padded
+}
+
+fn nearest_divisor(dim: usize, target: usize) -> Option<usize> {
+    let low = (target / 2).max(2);
+    let high = target * 2;
+    (low..=high)
+        .filter(|candidate| dim % candidate == 0)
+        .min_by_key(|candidate| candidate.abs_diff(target))
+}
@@ -0,0 +1,147 @@
+//! Cost quotes for a proving job from its witness shape alone.
+//!
+//! A served circuit is keyed at a fixed `k`, so proving cost depends on the
+//! keys rather than on the witness: the witness only has to fit. A
+//! [`Quote`] therefore reports whether a `vectors × dim` witness fits, the
+//! proof size from the verifying key's constraint system, an estimate of
+//! the per-job memory, and the proving time observed for recent jobs, so a
+//! submitter can budget before uploading the witness.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{Coded, ErrorCode},
+    proof_size::{breakdown, OpeningScheme, ProofSizeBreakdown},
+    prover::Prover,
+    shape::{max_vectors, required_k, WitnessShape},
+    slo::{LatencyReport, Stage},
+};
+
+/// Body of a quote request.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct QuoteRequest {
+    pub vectors: usize,
+    pub dim: usize,
+}
+
+/// Price of a job as `base + perSecond * proving seconds`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Pricing {
+    #[serde(default)]
+    pub base: f64,
+    #[serde(default)]
+    pub per_second: f64,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Price {
+    pub amount: f64,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    pub circuit_k: u32,
+    /// Smallest `k` the witness would need on its own.
+    pub required_k: u32,
+    pub proof_bytes: usize,
+    /// Polynomials a job holds while proving, on top of the shared keys.
+    pub memory_bytes: u64,
+    /// Median `create_proof` time of recent jobs; unset before any finished.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prove_ms: Option<u64>,
+    /// Median submission-to-proof time of recent jobs, queueing included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<Price>,
+}
+
+/// Quotes a `request`-shaped witness against `prover`'s keys. Fails with a
+/// shape mismatch when the witness would be rejected.
+pub fn quote(
+    prover: &Prover,
+    request: QuoteRequest,
+    latency: Option<&LatencyReport>,
+    pricing: Option<&Pricing>,
+) -> Result<Quote> {
+    let params = prover.layout();
+    let circuit_k = prover.circuit_k();
+    let shape = WitnessShape {
+        vectors: request.vectors,
+        dim: request.dim,
+    };
+    if shape.vectors == 0 || shape.dim == 0 {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            "a quote needs at least one vector of at least one dimension"
+        ));
+    }
+    if params.delta.is_none() && (shape.vectors != params.vectors || shape.dim != params.dim) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "the keys take {}-dim × {} vectors, not {} × {}",
+                params.dim, params.vectors, shape.dim, shape.vectors
+            )
+        ));
+    }
+    let row_len = params.nonzeros.unwrap_or(shape.dim);
+    let laid_out = WitnessShape {
+        vectors: params.delta.unwrap_or(shape.vectors),
+        dim: row_len,
+    };
+    if laid_out.vectors > max_vectors(params, circuit_k, row_len) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "witness needs k≥{}, the keys are at k={circuit_k}",
+                required_k(params, laid_out)
+            )
+        ));
+    }
+
+    let cs = prover.proving_key().get_vk().cs();
+    let sizes = breakdown(cs, OpeningScheme::Gwc);
+    let observed = |stage: Stage| {
+        latency
+            .and_then(|report| report.stages.get(&stage))
+            .map(|stage| stage.p50_ms)
+    };
+    let prove_ms = observed(Stage::Prove);
+    let price = pricing.and_then(|pricing| {
+        let seconds = match prove_ms {
+            Some(ms) => ms as f64 / 1000.0,
+            None if pricing.per_second == 0.0 => 0.0,
+            None => return None,
+        };
+        Some(Price {
+            amount: pricing.base + pricing.per_second * seconds,
+            currency: pricing.currency.clone(),
+        })
+    });
+    Ok(Quote {
+        circuit_k,
+        required_k: required_k(params, laid_out),
+        proof_bytes: sizes.total_bytes,
+        memory_bytes: memory_bytes(circuit_k, &sizes),
+        prove_ms,
+        total_ms: observed(Stage::Total),
+        price,
+    })
+}
+
+/// Every committed polynomial in coefficient form plus on the extended
+/// domain the quotient is computed over, at 32 bytes a value.
+fn memory_bytes(circuit_k: u32, sizes: &ProofSizeBreakdown) -> u64 {
+    let n = 1u64 << circuit_k;
+    let extended = n * (sizes.degree.saturating_sub(1).max(1) as u64).next_power_of_two();
+    let polys = sizes.commitments.count + sizes.fixed_columns + sizes.instance_columns;
+    polys as u64 * (n + extended) * 32
+}
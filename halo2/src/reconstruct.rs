@@ -0,0 +1,145 @@
+//! Reconstruction of approximate vectors from compressed codes.
+//!
+//! The proof pipeline only sees `pqVectors` (the reconstructions), their
+//! codes and, for PQ, the codebook. A [`Reconstructor`] is the one piece a
+//! compression scheme supplies; [`fill_witness`] turns its output into those
+//! witness fields, plus the residuals the prover confirms. [`ProductQuantizer`]
+//! is the scheme the circuit's codebook lookup is built for.
+
+use anyhow::Result;
+
+use crate::{
+    errors::{Coded, ErrorCode},
+    io::WitnessData,
+};
+
+pub trait Reconstructor {
+    /// Short scheme name, e.g. `pq`.
+    fn scheme(&self) -> &'static str;
+
+    /// Components of every reconstructed vector.
+    fn dim(&self) -> usize;
+
+    /// The vector encoded by one row of codes.
+    fn reconstruct(&self, codes: &[u32]) -> Result<Vec<f64>>;
+
+    /// Per-subspace codebook for the witness's `codebook`, when the scheme
+    /// has one the circuit can look codes up in.
+    fn codebook(&self) -> Option<&[Vec<Vec<f64>>]> {
+        None
+    }
+
+    fn reconstruct_rows(&self, codes: &[Vec<u32>]) -> Result<Vec<Vec<f64>>> {
+        codes.iter().map(|row| self.reconstruct(row)).collect()
+    }
+}
+
+/// Product quantization: one code per subspace, each selecting a centroid
+/// segment of `codebook[subspace]`.
+#[derive(Debug, Clone, Copy)]
+pub struct ProductQuantizer<'a> {
+    codebook: &'a [Vec<Vec<f64>>],
+    sub_dim: usize,
+}
+
+impl<'a> ProductQuantizer<'a> {
+    /// Fails unless every subspace holds centroids of one common width.
+    pub fn new(codebook: &'a [Vec<Vec<f64>>]) -> Result<Self> {
+        let sub_dim = codebook
+            .first()
+            .and_then(|centroids| centroids.first())
+            .map(Vec::len)
+            .unwrap_or(0);
+        let ragged = codebook.iter().any(|centroids| {
+            centroids.is_empty() || centroids.iter().any(|centroid| centroid.len() != sub_dim)
+        });
+        if sub_dim == 0 || ragged {
+            anyhow::bail!(Coded::new(
+                ErrorCode::ShapeMismatch,
+                "codebook needs at least one centroid per subspace, all of one width"
+            ));
+        }
+        Ok(Self { codebook, sub_dim })
+    }
+}
+
+impl Reconstructor for ProductQuantizer<'_> {
+    fn scheme(&self) -> &'static str {
+        "pq"
+    }
+
+    fn dim(&self) -> usize {
+        self.codebook.len() * self.sub_dim
+    }
+
+    fn reconstruct(&self, codes: &[u32]) -> Result<Vec<f64>> {
+        if codes.len() != self.codebook.len() {
+            anyhow::bail!(Coded::new(
+                ErrorCode::ShapeMismatch,
+                format!(
+                    "{} codes for a {}-subspace codebook",
+                    codes.len(),
+                    self.codebook.len()
+                )
+            ));
+        }
+        let mut vector = Vec::with_capacity(self.dim());
+        for (subspace, (code, centroids)) in codes.iter().zip(self.codebook).enumerate() {
+            let Some(centroid) = centroids.get(*code as usize) else {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::InvalidInput,
+                    format!("code {code} out of range in subspace {subspace}")
+                ));
+            };
+            vector.extend_from_slice(centroid);
+        }
+        Ok(vector)
+    }
+
+    fn codebook(&self) -> Option<&[Vec<Vec<f64>>]> {
+        Some(self.codebook)
+    }
+}
+
+/// Sets `witness`'s `pqVectors`, `pqCodes`, `codebook` and `residuals` from
+/// `codes` under `reconstructor`, against its folded vectors.
+pub fn fill_witness(
+    witness: &mut WitnessData,
+    codes: Vec<Vec<u32>>,
+    reconstructor: &dyn Reconstructor,
+) -> Result<()> {
+    if codes.len() != witness.folded_vectors.len() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "{} code rows for {} folded vectors",
+                codes.len(),
+                witness.folded_vectors.len()
+            )
+        ));
+    }
+    let pq_vectors = reconstructor.reconstruct_rows(&codes)?;
+    let dim = reconstructor.dim();
+    if let Some(row) = witness
+        .folded_vectors
+        .iter()
+        .position(|row| row.len() != dim)
+    {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "folded vector {row} has {} components, {} reconstructions have {dim}",
+                witness.folded_vectors[row].len(),
+                reconstructor.scheme()
+            )
+        ));
+    }
+    let residuals = witness
+        .fixed_point()?
+        .residuals(&witness.folded_vectors, &pq_vectors);
+    witness.residuals = Some(residuals);
+    witness.pq_vectors = pq_vectors;
+    witness.pq_codes = Some(codes);
+    witness.codebook = reconstructor.codebook().map(<[_]>::to_vec);
+    Ok(())
+}
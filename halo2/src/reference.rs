@@ -0,0 +1,297 @@
+//! Slow reference implementation of fixed-point conversion and residuals,
+//! and a differential check of the optimized field code against it.
+//!
+//! The optimized path ([`FixedPoint`]) multiplies by the scale `S` in f64,
+//! settling only products within rounding error of an integer exactly, and
+//! accumulates residuals in the field. The reference works on the witness
+//! value as written (the shortest decimal that round-trips the f64) in exact
+//! integer and rational arithmetic throughout:
+//!
+//! ```text
+//! fixed(v)        = floor(digits(v) * S / 10^fraction_digits(v))   u128 division
+//! residual(a, b)  = sum_j (fixed(a_j) - fixed(b_j))^2              checked u128
+//! epsilon^2(a, b) = residual(a, b) / S^2                           a rational, mapped into Fr
+//! ```
+//!
+//! [`compare_rows`] reports every value or row where the two disagree;
+//! [`differential`] runs it over random witnesses built to hit the edges of
+//! the conversion.
+
+use anyhow::{Context, Result};
+use halo2curves::{bn256::Fr, ff::Field};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::Serialize;
+
+use crate::{
+    fixed_point::{from_i64, FixedPoint},
+    prove::compute_field_residuals,
+    public_inputs::field_to_hex,
+};
+
+/// An exact fraction; `denom` is never zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ratio {
+    pub numer: i128,
+    pub denom: u128,
+}
+
+impl Ratio {
+    /// The fraction as `numer * denom^-1` in the field.
+    pub fn to_field(self) -> Fr {
+        let denom = Fr::from_u128(self.denom)
+            .invert()
+            .expect("reference denominators are nonzero and below the modulus");
+        signed_field(self.numer) * denom
+    }
+}
+
+/// `floor(value * S)` of the decimal `value` prints as.
+pub fn reference_fixed(value: f64, fixed_point: FixedPoint) -> Result<i128> {
+    if !value.is_finite() {
+        anyhow::bail!("witness value {value} is not finite");
+    }
+    let printed = format!("{}", value.abs());
+    let (whole, fraction) = printed.split_once('.').unwrap_or((&printed, ""));
+    let too_large = || format!("witness value {value} is too large for the reference");
+    // |value| * S = digits * S / 10^fraction.len()
+    let digits: u128 = format!("{whole}{fraction}")
+        .parse()
+        .with_context(too_large)?;
+    let numer = digits
+        .checked_mul(u128::from(fixed_point.scale()))
+        .with_context(too_large)?;
+    let Some(denom) = 10u128.checked_pow(fraction.len() as u32) else {
+        // Over 38 fraction digits but at most 17 significant ones: the
+        // value is below 10^-21, far under one step.
+        return Ok(-i128::from(value < 0.0));
+    };
+    let magnitude = i128::try_from(numer / denom).with_context(too_large)?;
+    let truncated = numer % denom != 0;
+    Ok(if value.is_sign_negative() {
+        -magnitude - i128::from(truncated)
+    } else {
+        magnitude
+    })
+}
+
+/// Sum of squared fixed-point differences, failing instead of overflowing.
+pub fn squared_residual(a: &[f64], b: &[f64], fixed_point: FixedPoint) -> Result<u128> {
+    if a.len() != b.len() {
+        anyhow::bail!("rows have {} and {} components", a.len(), b.len());
+    }
+    let mut sum = 0u128;
+    for (x, y) in a.iter().zip(b) {
+        let diff = reference_fixed(*x, fixed_point)?.abs_diff(reference_fixed(*y, fixed_point)?);
+        sum = diff
+            .checked_mul(diff)
+            .and_then(|square| sum.checked_add(square))
+            .context("reference residual overflows u128")?;
+    }
+    Ok(sum)
+}
+
+/// The residual the circuit assigns for a row, `epsilon^2`.
+pub fn field_residual(a: &[f64], b: &[f64], fixed_point: FixedPoint) -> Result<Ratio> {
+    let scale = u128::from(fixed_point.scale());
+    Ok(Ratio {
+        numer: i128::try_from(squared_residual(a, b, fixed_point)?)?,
+        denom: scale * scale,
+    })
+}
+
+/// One value or row where the optimized code disagrees with the reference.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Discrepancy {
+    /// `fixedPoint`, `fixedSquaredDistance` or `fieldResidual`.
+    pub check: &'static str,
+    pub row: usize,
+    /// `folded` or `pq`, for `fixedPoint`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<usize>,
+    /// The input value, for `fixedPoint`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    pub reference: String,
+    pub optimized: String,
+}
+
+/// Compares the optimized conversion, squared distance and field residuals
+/// of `folded` against `pq` at `fixed_point` with the reference.
+pub fn compare_rows(
+    folded: &[Vec<f64>],
+    pq: &[Vec<f64>],
+    fixed_point: FixedPoint,
+) -> Result<Vec<Discrepancy>> {
+    let mut found = Vec::new();
+    for (row, (a, b)) in folded.iter().zip(pq).enumerate() {
+        for (vector, values) in [("folded", a), ("pq", b)] {
+            for (component, value) in values.iter().enumerate() {
+                let reference = signed_field(reference_fixed(*value, fixed_point)?);
+                let optimized = from_i64(fixed_point.saturating_fixed(*value));
+                if optimized != reference {
+                    found.push(Discrepancy {
+                        check: "fixedPoint",
+                        row,
+                        vector: Some(vector),
+                        component: Some(component),
+                        value: Some(*value),
+                        reference: field_to_hex(&reference),
+                        optimized: field_to_hex(&optimized),
+                    });
+                }
+            }
+        }
+        let reference = squared_residual(a, b, fixed_point)?;
+        let optimized = fixed_point.squared_distance(a, b);
+        if optimized != reference {
+            found.push(Discrepancy {
+                check: "fixedSquaredDistance",
+                row,
+                vector: None,
+                component: None,
+                value: None,
+                reference: reference.to_string(),
+                optimized: optimized.to_string(),
+            });
+        }
+    }
+    let to_field = |rows: &[Vec<f64>]| -> Vec<Vec<Fr>> {
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| fixed_point.saturating_field(*value))
+                    .collect()
+            })
+            .collect()
+    };
+    let optimized = compute_field_residuals(&to_field(folded), &to_field(pq));
+    for (row, ((a, b), optimized)) in folded.iter().zip(pq).zip(optimized).enumerate() {
+        let reference = field_residual(a, b, fixed_point)?.to_field();
+        if optimized != reference {
+            found.push(Discrepancy {
+                check: "fieldResidual",
+                row,
+                vector: None,
+                component: None,
+                value: None,
+                reference: field_to_hex(&reference),
+                optimized: field_to_hex(&optimized),
+            });
+        }
+    }
+    Ok(found)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DifferentialReport {
+    pub seed: u64,
+    pub rounds: usize,
+    pub values_checked: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+/// Runs [`compare_rows`] over `rounds` random `vectors × dim` witnesses.
+pub fn differential(
+    seed: u64,
+    rounds: usize,
+    vectors: usize,
+    dim: usize,
+    fixed_point: FixedPoint,
+) -> Result<DifferentialReport> {
+    let scale = fixed_point.scale() as f64;
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    let mut discrepancies = Vec::new();
+    for _ in 0..rounds {
+        let mut matrix = || -> Vec<Vec<f64>> {
+            (0..vectors)
+                .map(|_| (0..dim).map(|_| random_value(&mut rng, scale)).collect())
+                .collect()
+        };
+        let folded = matrix();
+        let pq = matrix();
+        discrepancies.extend(compare_rows(&folded, &pq, fixed_point)?);
+    }
+    Ok(DifferentialReport {
+        seed,
+        rounds,
+        values_checked: 2 * rounds * vectors * dim,
+        discrepancies,
+    })
+}
+
+/// A witness component drawn from a mix of typical embeddings and the
+/// inputs most likely to round differently in f64 at `scale`.
+fn random_value(rng: &mut ChaCha20Rng, scale: f64) -> f64 {
+    let sign = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+    match rng.gen_range(0..6) {
+        // Embedding components.
+        0 => rng.gen_range(-1.0..1.0),
+        // Whole fixed-point steps, as exporters write them.
+        1 => sign * f64::from(rng.gen_range(0u32..2_000_000)) / scale,
+        // More digits than the scale keeps.
+        2 => sign * f64::from(rng.gen_range(0u32..2_000_000_000)) / 1e9,
+        // Within a few ulps of a fixed-point step.
+        3 => {
+            let step = f64::from(rng.gen_range(0u32..2_000_000)) / scale;
+            let ulps = rng.gen_range(-4i64..=4);
+            sign * f64::from_bits((step.to_bits() as i64 + ulps).max(0) as u64)
+        }
+        // Below one fixed-point step.
+        4 => sign * rng.gen_range(0.0..1.0) / scale,
+        // Large magnitudes and exact zeros.
+        _ => match rng.gen_range(0..3) {
+            0 => sign * rng.gen_range(1.0..1e6),
+            1 => 0.0,
+            _ => -0.0,
+        },
+    }
+}
+
+fn signed_field(value: i128) -> Fr {
+    let magnitude = Fr::from_u128(value.unsigned_abs());
+    if value < 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn optimized_conversion_matches_the_reference() {
+        // The nearest f64 to 0.000249 is below it; a plain f64 floor gives 248.
+        let fixed_point = FixedPoint::default();
+        assert_eq!(reference_fixed(0.000249, fixed_point).unwrap(), 249);
+        assert_eq!(fixed_point.saturating_fixed(0.000249), 249);
+        let report = differential(7, 200, 4, 16, fixed_point).unwrap();
+        assert_eq!(report.values_checked, 2 * 200 * 4 * 16);
+        assert!(
+            report.discrepancies.is_empty(),
+            "{}",
+            serde_json::to_string_pretty(&report.discrepancies).unwrap()
+        );
+    }
+
+    #[test]
+    fn scales_other_than_powers_of_ten_match_the_reference() {
+        for scale in [1 << 20, 3, 1_000_003] {
+            let fixed_point = FixedPoint::new(scale).unwrap();
+            let ratio = field_residual(&[1.5], &[0.0], fixed_point).unwrap();
+            assert_eq!(ratio.denom, u128::from(scale) * u128::from(scale));
+            let report = differential(11, 100, 4, 16, fixed_point).unwrap();
+            assert!(
+                report.discrepancies.is_empty(),
+                "scale {scale}: {}",
+                serde_json::to_string_pretty(&report.discrepancies).unwrap()
+            );
+        }
+    }
+}
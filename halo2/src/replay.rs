@@ -0,0 +1,366 @@
+//! Replay archives: everything a prover run consumed, in one file.
+//!
+//! `prover --record replay.tar` captures the witness bytes, the public inputs
+//! after `--previous-proof` filled them in, both key configs and the versions
+//! and settings of the run, so a failing proof can be attached to a bug
+//! report and re-run with `yysfold replay`.
+//!
+//! The archive is a plain ustar file that `tar -xf` unpacks:
+//!
+//! ```text
+//! manifest.json             ReplayManifest
+//! witness.json              the witness file as given, encrypted or not
+//! public_inputs.json
+//! keys/proving_key.json     key configs with the seed, without artifacts
+//! keys/verification_key.json
+//! ```
+//!
+//! Key configs carry the seed, so the replay regenerates the exact keys; the
+//! references to serialized key artifacts are dropped because the archive
+//! does not carry them. Anyone holding an archive can prove with those keys,
+//! so share it as you would the keys themselves.
+
+use std::{
+    fs,
+    path::Path,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cancel::CancellationToken,
+    circuit::{FoldedCircuit, FoldedParams},
+    compat::{CIRCUIT_VERSION, PROOF_FORMAT_VERSION},
+    errors::{Coded, ErrorCode},
+    hashing::digest_hex,
+    io::decode_witness,
+    keys::{key_fingerprint, load_or_init_keys_in},
+    platform::{from_json_slice, normalize},
+    prove::{build_circuit_with, create_circuit_proof_with},
+    public_inputs::{field_to_hex, ParsedPublicInputs},
+    shape::negotiate,
+    storage::{write_atomic, MemoryStorage, Storage},
+    verify::verify_with_keys,
+};
+
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const WITNESS: &str = "witness.json";
+const PUBLIC_INPUTS: &str = "public_inputs.json";
+const PROVING_KEY: &str = "keys/proving_key.json";
+const VERIFICATION_KEY: &str = "keys/verification_key.json";
+
+const BLOCK: usize = 512;
+
+/// Versions and settings of the recorded run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayManifest {
+    pub format_version: u32,
+    pub prover_version: String,
+    pub proof_format_version: u32,
+    pub circuit_version: u32,
+    pub circuit_k: u32,
+    pub circuit: FoldedParams,
+    /// [`key_fingerprint`] of the proving key config as it was on disk.
+    pub key_fingerprint: String,
+    pub recorded_at: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub manifest: ReplayManifest,
+    pub witness: Vec<u8>,
+    pub public_inputs: ParsedPublicInputs,
+    pub proving_config: Vec<u8>,
+    pub verifying_config: Vec<u8>,
+}
+
+/// Outcome of [`Replay::run`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    pub key_fingerprint: String,
+    /// Recorded versions that differ from this build, as `name: recorded -> current`.
+    pub version_drift: Vec<String>,
+    pub keygen_ms: u128,
+    pub prove_ms: u128,
+    pub proof_bytes: usize,
+    pub proof_digest: String,
+    pub instances: Vec<String>,
+    pub verified: bool,
+}
+
+impl Replay {
+    /// Captures a prover run about to prove `witness_path` with the keys at
+    /// `proving_key` and `verification_key`, which must already exist.
+    pub fn capture(
+        witness_path: &Path,
+        public_inputs: &ParsedPublicInputs,
+        proving_key: &Path,
+        verification_key: &Path,
+        circuit_k: u32,
+        circuit: &FoldedParams,
+    ) -> Result<Self> {
+        let read = |path: &Path| {
+            let path = normalize(path);
+            fs::read(&path).with_context(|| format!("opening {:?}", path))
+        };
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        Ok(Self {
+            manifest: ReplayManifest {
+                format_version: REPLAY_FORMAT_VERSION,
+                prover_version: env!("CARGO_PKG_VERSION").to_string(),
+                proof_format_version: PROOF_FORMAT_VERSION,
+                circuit_version: CIRCUIT_VERSION,
+                circuit_k,
+                circuit: circuit.clone(),
+                key_fingerprint: key_fingerprint(proving_key)?,
+                recorded_at,
+            },
+            witness: read(witness_path)?,
+            public_inputs: public_inputs.clone(),
+            proving_config: seed_only(&read(proving_key)?)?,
+            verifying_config: seed_only(&read(verification_key)?)?,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut tar = TarWriter::default();
+        tar.append(MANIFEST, &serde_json::to_vec_pretty(&self.manifest)?);
+        tar.append(WITNESS, &self.witness);
+        tar.append(
+            PUBLIC_INPUTS,
+            &serde_json::to_vec_pretty(&self.public_inputs)?,
+        );
+        tar.append(PROVING_KEY, &self.proving_config);
+        tar.append(VERIFICATION_KEY, &self.verifying_config);
+        write_atomic(path, &tar.finish(self.manifest.recorded_at))
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let path = normalize(path);
+        let bytes = fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+        let mut entries = read_tar(&bytes).with_context(|| format!("reading {:?}", path))?;
+        let mut take = |name: &str| {
+            entries
+                .iter()
+                .position(|(entry, _)| entry == name)
+                .map(|idx| entries.swap_remove(idx).1)
+                .ok_or_else(|| {
+                    Coded::new(
+                        ErrorCode::InvalidInput,
+                        format!("replay archive {:?} has no {name}", path),
+                    )
+                })
+        };
+        let manifest: ReplayManifest =
+            from_json_slice(&take(MANIFEST)?).context("parsing replay manifest")?;
+        if manifest.format_version > REPLAY_FORMAT_VERSION {
+            anyhow::bail!(Coded::new(
+                ErrorCode::Incompatible,
+                format!(
+                    "replay format {} is newer than this build reads ({REPLAY_FORMAT_VERSION})",
+                    manifest.format_version
+                )
+            ));
+        }
+        let public_inputs =
+            from_json_slice(&take(PUBLIC_INPUTS)?).context("parsing replay public inputs")?;
+        Ok(Self {
+            manifest,
+            witness: take(WITNESS)?,
+            public_inputs,
+            proving_config: take(PROVING_KEY)?,
+            verifying_config: take(VERIFICATION_KEY)?,
+        })
+    }
+
+    /// Recorded versions that differ from this build.
+    pub fn version_drift(&self) -> Vec<String> {
+        let manifest = &self.manifest;
+        let mut drift = Vec::new();
+        let current = env!("CARGO_PKG_VERSION");
+        if manifest.prover_version != current {
+            drift.push(format!(
+                "proverVersion: {} -> {current}",
+                manifest.prover_version
+            ));
+        }
+        if manifest.proof_format_version != PROOF_FORMAT_VERSION {
+            drift.push(format!(
+                "proofFormatVersion: {} -> {PROOF_FORMAT_VERSION}",
+                manifest.proof_format_version
+            ));
+        }
+        if manifest.circuit_version != CIRCUIT_VERSION {
+            drift.push(format!(
+                "circuitVersion: {} -> {CIRCUIT_VERSION}",
+                manifest.circuit_version
+            ));
+        }
+        drift
+    }
+
+    /// Re-runs the recorded prove with keys regenerated from the recorded
+    /// seed, then verifies the proof. Fails where the recorded run failed.
+    pub fn run(&self) -> Result<ReplayReport> {
+        let manifest = &self.manifest;
+        let witness = decode_witness(&self.witness, None, "replay witness")?;
+        negotiate(&witness, &manifest.circuit, Some(manifest.circuit_k))?;
+
+        let storage = MemoryStorage::new();
+        storage.write(PROVING_KEY, &self.proving_config)?;
+        storage.write(VERIFICATION_KEY, &self.verifying_config)?;
+        let blank = FoldedCircuit::blank_with(&manifest.circuit);
+        let started = Instant::now();
+        let (params, pk) = load_or_init_keys_in(
+            &storage,
+            PROVING_KEY,
+            VERIFICATION_KEY,
+            manifest.circuit_k,
+            &blank,
+            None,
+        )?;
+        let keygen_ms = started.elapsed().as_millis();
+
+        let cancel = CancellationToken::new();
+        let started = Instant::now();
+        let circuit =
+            build_circuit_with(&witness, &self.public_inputs, &manifest.circuit, &cancel)?;
+        let proof =
+            create_circuit_proof_with(&params, &pk, &circuit, &circuit.public_inputs, &cancel)?;
+        let prove_ms = started.elapsed().as_millis();
+        let verified =
+            verify_with_keys(&params, pk.get_vk(), &circuit.public_inputs, &proof).is_ok();
+
+        Ok(ReplayReport {
+            key_fingerprint: manifest.key_fingerprint.clone(),
+            version_drift: self.version_drift(),
+            keygen_ms,
+            prove_ms,
+            proof_bytes: proof.len(),
+            proof_digest: digest_hex(&proof),
+            instances: circuit.public_inputs.iter().map(field_to_hex).collect(),
+            verified,
+        })
+    }
+}
+
+/// `config` without its `artifacts` references.
+fn seed_only(config: &[u8]) -> Result<Vec<u8>> {
+    let mut config: serde_json::Value =
+        from_json_slice(config).context("parsing key config for replay")?;
+    if let Some(fields) = config.as_object_mut() {
+        fields.remove("artifacts");
+    }
+    Ok(serde_json::to_vec_pretty(&config)?)
+}
+
+/// Minimal ustar writer for regular files with names under 100 bytes.
+#[derive(Default)]
+struct TarWriter {
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+impl TarWriter {
+    fn append(&mut self, name: &str, data: &[u8]) {
+        self.entries.push((name.to_string(), data.to_vec()));
+    }
+
+    fn finish(self, mtime: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (name, data) in self.entries {
+            let mut header = [0u8; BLOCK];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            write_octal(&mut header[100..108], 0o644);
+            write_octal(&mut header[108..116], 0);
+            write_octal(&mut header[116..124], 0);
+            write_octal(&mut header[124..136], data.len() as u64);
+            write_octal(&mut header[136..148], mtime);
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[263..265].copy_from_slice(b"00");
+            let checksum = header_checksum(&header);
+            write_octal(&mut header[148..155], checksum);
+            header[155] = b' ';
+            out.extend_from_slice(&header);
+            out.extend_from_slice(&data);
+            out.resize(out.len().next_multiple_of(BLOCK), 0);
+        }
+        out.resize(out.len() + 2 * BLOCK, 0);
+        out
+    }
+}
+
+/// Zero-padded octal digits followed by a NUL, filling `field`.
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+fn read_octal(field: &[u8]) -> Result<u64> {
+    let digits = std::str::from_utf8(field)?.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    Ok(u64::from_str_radix(digits, 8)?)
+}
+
+/// Sum of the header bytes with the checksum field read as spaces.
+fn header_checksum(header: &[u8]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(idx, byte)| match idx {
+            148..=155 => u64::from(b' '),
+            _ => u64::from(*byte),
+        })
+        .sum()
+}
+
+/// Regular-file entries of a ustar archive, in order.
+fn read_tar(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset + BLOCK <= bytes.len() {
+        let header = &bytes[offset..offset + BLOCK];
+        if header.iter().all(|byte| *byte == 0) {
+            return Ok(entries);
+        }
+        if read_octal(&header[148..156])? != header_checksum(header) {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!("tar header at byte {offset} has a bad checksum")
+            ));
+        }
+        let name_len = header[..100]
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(100);
+        let name = String::from_utf8_lossy(&header[..name_len]).into_owned();
+        let size = read_octal(&header[124..136])? as usize;
+        let start = offset + BLOCK;
+        let Some(data) = bytes.get(start..start + size) else {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!("tar entry {name} is truncated")
+            ));
+        };
+        if matches!(header[156], b'0' | 0) {
+            entries.push((name, data.to_vec()));
+        }
+        offset = start + size.next_multiple_of(BLOCK);
+    }
+    anyhow::bail!(Coded::new(
+        ErrorCode::InvalidInput,
+        "tar archive ends without its end-of-archive blocks"
+    ))
+}
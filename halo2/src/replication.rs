@@ -0,0 +1,223 @@
+//! Post-prove replication of proof artifacts to secondary stores.
+//!
+//! A [`ReplicationConfig`] lists targets, each either a command or a
+//! webhook, and [`Replicator::replicate`] hands every artifact of a proved
+//! block to every target:
+//!
+//! * `command`: argv run once per artifact with `{key}` and `{height}`
+//!   substituted and the artifact on stdin, e.g.
+//!   `["aws", "s3", "cp", "-", "s3://proofs-eu/{key}"]`. A non-empty first
+//!   line of stdout is taken as the replica's location.
+//! * `webhook`: an `http://` URL POSTed `{target, key, height, digest,
+//!   content}` with the content hex-encoded. A JSON `location` in the
+//!   response is taken as the replica's location.
+//!
+//! A target that fails is reported and the others still run, so one
+//! unreachable region never blocks the rest. The resulting [`Replica`]s are
+//! what `ProofIndex::record_replicas` stores for disaster recovery.
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{Coded, ErrorCode},
+    hashing::digest,
+    http::{post_json, Limits},
+    platform::{from_json_slice, normalize},
+    storage::Storage,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReplicationConfig {
+    pub targets: Vec<ReplicaTarget>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReplicaTarget {
+    /// Recorded with every replica, e.g. the region.
+    pub name: String,
+    /// Exactly one of `command` and `webhook` is set.
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
+    #[serde(default)]
+    pub webhook: Option<String>,
+}
+
+/// One artifact stored at one target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Replica {
+    pub target: String,
+    pub artifact_key: String,
+    pub location: String,
+    /// Unix seconds the copy finished.
+    pub replicated_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationFailure {
+    pub target: String,
+    pub artifact_key: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationReport {
+    pub replicas: Vec<Replica>,
+    pub failures: Vec<ReplicationFailure>,
+}
+
+impl ReplicationConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = normalize(path);
+        let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+        let config: Self = from_json_slice(&bytes)
+            .with_context(|| format!("parsing replication config {:?}", path))?;
+        for target in &config.targets {
+            match (&target.command, &target.webhook) {
+                (Some(argv), None) if !argv.is_empty() => {}
+                (None, Some(_)) => {}
+                _ => anyhow::bail!(Coded::new(
+                    ErrorCode::InvalidInput,
+                    format!(
+                        "replication target {:?} needs exactly one of a non-empty command or a webhook",
+                        target.name
+                    )
+                )),
+            }
+        }
+        Ok(config)
+    }
+}
+
+pub struct Replicator {
+    pub config: ReplicationConfig,
+    pub limits: Limits,
+}
+
+impl Replicator {
+    pub fn new(config: ReplicationConfig) -> Self {
+        Self {
+            config,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Copies the artifacts at `keys` of the block at `height` from
+    /// `storage` to every target.
+    pub fn replicate(
+        &self,
+        storage: &dyn Storage,
+        height: u64,
+        keys: &[String],
+    ) -> Result<ReplicationReport> {
+        let mut report = ReplicationReport::default();
+        for key in keys {
+            let content = storage.read(key)?;
+            for target in &self.config.targets {
+                match self.copy(target, height, key, &content) {
+                    Ok(location) => report.replicas.push(Replica {
+                        target: target.name.clone(),
+                        artifact_key: key.clone(),
+                        location,
+                        replicated_at: now(),
+                    }),
+                    Err(err) => report.failures.push(ReplicationFailure {
+                        target: target.name.clone(),
+                        artifact_key: key.clone(),
+                        error: format!("{err:#}"),
+                    }),
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    fn copy(
+        &self,
+        target: &ReplicaTarget,
+        height: u64,
+        key: &str,
+        content: &[u8],
+    ) -> Result<String> {
+        let default_location = format!("{}:{key}", target.name);
+        match (&target.command, &target.webhook) {
+            (Some(argv), _) => {
+                let location = run_command(argv, height, key, content)?;
+                Ok(location.unwrap_or(default_location))
+            }
+            (None, Some(url)) => {
+                let body = serde_json::to_vec(&serde_json::json!({
+                    "target": target.name,
+                    "key": key,
+                    "height": height,
+                    "digest": digest(content),
+                    "content": hex::encode(content),
+                }))?;
+                let response = post_json(url, &body, &self.limits)?;
+                #[derive(Deserialize)]
+                struct Ack {
+                    location: Option<String>,
+                }
+                let location = from_json_slice::<Ack>(&response)
+                    .ok()
+                    .and_then(|ack| ack.location);
+                Ok(location.unwrap_or(default_location))
+            }
+            (None, None) => anyhow::bail!("replication target {:?} has no hook", target.name),
+        }
+    }
+}
+
+fn run_command(argv: &[String], height: u64, key: &str, content: &[u8]) -> Result<Option<String>> {
+    let argv: Vec<String> = argv
+        .iter()
+        .map(|arg| {
+            arg.replace("{key}", key)
+                .replace("{height}", &height.to_string())
+        })
+        .collect();
+    let (program, args) = argv.split_first().context("empty replication command")?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running {program:?}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(content)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{program:?} exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,260 @@
+//! [`Storage`] in an S3 bucket, driven through the `aws` CLI.
+//!
+//! Every operation runs one `aws s3` or `aws s3api` command against
+//! `s3://{bucket}/{prefix}{key}`, so credentials, regions and endpoints come
+//! from the usual AWS configuration and no SDK is linked. `write_new` relies
+//! on conditional writes (`put-object --if-none-match '*'`), which needs an
+//! AWS CLI from late 2024 or newer.
+
+use std::{
+    fs,
+    io::Write,
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::storage::{join_key, Storage};
+
+#[derive(Debug, Clone)]
+pub struct S3Storage {
+    bucket: String,
+    /// Prepended to every key, empty or ending in `/`.
+    prefix: String,
+    /// The CLI invocation, `["aws"]` unless overridden, e.g. to add
+    /// `--endpoint-url` or `--profile`.
+    command: Vec<String>,
+}
+
+/// Distinguishes request bodies of concurrent conditional writes.
+static BODY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl S3Storage {
+    pub fn new(bucket: impl Into<String>) -> Self {
+        Self {
+            bucket: bucket.into(),
+            prefix: String::new(),
+            command: vec!["aws".to_string()],
+        }
+    }
+
+    /// Keeps every key under `prefix` within the bucket.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        let prefix = prefix.trim_matches('/');
+        self.prefix = match prefix {
+            "" => String::new(),
+            prefix => format!("{prefix}/"),
+        };
+        self
+    }
+
+    pub fn with_command(mut self, command: Vec<String>) -> Self {
+        self.command = command;
+        self
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key.trim_start_matches('/'))
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket, self.object_key(key))
+    }
+
+    /// Runs the CLI with `args`, feeding it `stdin`, and returns its output
+    /// whatever the exit status.
+    fn run(&self, args: &[&str], stdin: Option<&[u8]>) -> Result<Output> {
+        let (program, base) = self.command.split_first().context("empty aws command")?;
+        let mut child = Command::new(program)
+            .args(base)
+            .args(args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("running {program:?}"))?;
+        if let (Some(mut pipe), Some(bytes)) = (child.stdin.take(), stdin) {
+            pipe.write_all(bytes)?;
+        }
+        Ok(child.wait_with_output()?)
+    }
+
+    /// Like [`S3Storage::run`], failing unless the command succeeds.
+    fn run_ok(&self, args: &[&str], stdin: Option<&[u8]>) -> Result<Vec<u8>> {
+        let output = self.run(args, stdin)?;
+        if !output.status.success() {
+            anyhow::bail!(failure(args, &output));
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl Storage for S3Storage {
+    fn read(&self, key: &str) -> Result<Vec<u8>> {
+        self.run_ok(
+            &["s3", "cp", "--only-show-errors", &self.url(key), "-"],
+            None,
+        )
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        self.run_ok(
+            &["s3", "cp", "--only-show-errors", "-", &self.url(key)],
+            Some(bytes),
+        )?;
+        Ok(())
+    }
+
+    fn write_new(&self, key: &str, bytes: &[u8]) -> Result<bool> {
+        let body = std::env::temp_dir().join(format!(
+            "yysfold-s3-{}-{}.body",
+            std::process::id(),
+            BODY_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&body, bytes).with_context(|| format!("writing {:?}", body))?;
+        let object_key = self.object_key(key);
+        let body_arg = body.to_string_lossy().into_owned();
+        let args = [
+            "s3api",
+            "put-object",
+            "--bucket",
+            &self.bucket,
+            "--key",
+            &object_key,
+            "--body",
+            &body_arg,
+            "--if-none-match",
+            "*",
+        ];
+        let output = self.run(&args, None);
+        let _ = fs::remove_file(&body);
+        let output = output?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        if stderr(&output).contains("PreconditionFailed") {
+            return Ok(false);
+        }
+        anyhow::bail!(failure(&args, &output))
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        let object_key = self.object_key(key);
+        let args = [
+            "s3api",
+            "head-object",
+            "--bucket",
+            &self.bucket,
+            "--key",
+            &object_key,
+        ];
+        let output = self.run(&args, None)?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        if stderr(&output).contains("(404)") {
+            return Ok(false);
+        }
+        anyhow::bail!(failure(&args, &output))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.run_ok(&["s3", "rm", "--only-show-errors", &self.url(key)], None)?;
+        Ok(())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let dir = match prefix.trim_end_matches('/') {
+            "" => self.prefix.clone(),
+            dir => format!("{}/", self.object_key(dir)),
+        };
+        let stdout = self.run_ok(
+            &[
+                "s3api",
+                "list-objects-v2",
+                "--bucket",
+                &self.bucket,
+                "--prefix",
+                &dir,
+                "--delimiter",
+                "/",
+                "--output",
+                "json",
+            ],
+            None,
+        )?;
+        listed_keys(prefix, &dir, &stdout)
+    }
+}
+
+/// Keys of a `list-objects-v2` response directly under the object prefix
+/// `dir`, as `prefix/name`, sorted.
+fn listed_keys(prefix: &str, dir: &str, stdout: &[u8]) -> Result<Vec<String>> {
+    #[derive(Default, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Listing {
+        #[serde(default)]
+        contents: Vec<Object>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Object {
+        key: String,
+    }
+    // An empty listing prints nothing rather than an empty object.
+    let listing: Listing = if stdout.iter().all(u8::is_ascii_whitespace) {
+        Listing::default()
+    } else {
+        serde_json::from_slice(stdout).context("parsing list-objects-v2 output")?
+    };
+    let mut keys: Vec<String> = listing
+        .contents
+        .iter()
+        .filter_map(|object| object.key.strip_prefix(dir))
+        .filter(|name| !name.is_empty() && !name.contains('/'))
+        .map(|name| join_key(prefix, name))
+        .collect();
+    keys.sort();
+    Ok(keys)
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn failure(args: &[&str], output: &Output) -> String {
+    format!(
+        "aws {} exited with {}: {}",
+        args.join(" "),
+        output.status,
+        stderr(output).trim()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_matches_memory_storage_keys() {
+        let storage = S3Storage::new("proofs").with_prefix("/eu/");
+        assert_eq!(storage.url("dir/a.json"), "s3://proofs/eu/dir/a.json");
+        let stdout = br#"{"Contents": [
+            {"Key": "eu/dir/c.json", "Size": 2},
+            {"Key": "eu/dir/b.json", "Size": 2}
+        ], "CommonPrefixes": [{"Prefix": "eu/dir/sub/"}]}"#;
+        assert_eq!(
+            listed_keys("dir/", "eu/dir/", stdout).unwrap(),
+            ["dir/b.json", "dir/c.json"]
+        );
+        assert!(listed_keys("missing", "eu/missing/", b"\n")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+//! Int8 scalar quantization, a cheaper alternative to PQ.
+//!
+//! Each component is stored as an int8 code under a per-dimension scale and
+//! zero point and dequantizes to `scale * (code - zero_point)`. A circuit
+//! keyed with `scalar` checks that relation for every pq component, with one
+//! row and no codebook table per component, and commits to the scales, zero
+//! points and codes with Poseidon under [`SCALAR_DOMAIN`] at
+//! [`crate::circuit::FoldedParams::scalar_slot`]. The residual checks then
+//! bound the dequantization error like any other reconstruction.
+
+use anyhow::Result;
+use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{Coded, ErrorCode},
+    gadgets::scalar::int8_to_field,
+    io::WitnessData,
+    poseidon::{domain_capacity, hash_with_capacity, SCALAR_DOMAIN},
+    prove::{float_to_field, FIXED_POINT_SCALE},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ScalarQuantization {
+    /// One scale per dimension.
+    pub scales: Vec<f64>,
+    /// One zero point per dimension.
+    pub zero_points: Vec<i8>,
+    /// `codes[vector][dimension]`.
+    pub codes: Vec<Vec<i8>>,
+}
+
+impl ScalarQuantization {
+    /// Quantizes `vectors` with a per-dimension affine map spanning each
+    /// dimension's range.
+    pub fn fit(vectors: &[Vec<f64>]) -> Self {
+        let dim = vectors.first().map(Vec::len).unwrap_or(0);
+        let mut scales = Vec::with_capacity(dim);
+        let mut zero_points = Vec::with_capacity(dim);
+        for d in 0..dim {
+            let (lo, hi) = vectors.iter().fold((0.0f64, 0.0f64), |(lo, hi), row| {
+                (lo.min(row[d]), hi.max(row[d]))
+            });
+            let scale = if hi > lo { (hi - lo) / 255.0 } else { 1.0 };
+            scales.push(scale);
+            zero_points.push(clamp_int8(-128.0 - lo / scale));
+        }
+        let codes = vectors
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(d, value)| clamp_int8(value / scales[d] + f64::from(zero_points[d])))
+                    .collect()
+            })
+            .collect();
+        ScalarQuantization {
+            scales,
+            zero_points,
+            codes,
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.scales.len()
+    }
+
+    /// The reconstructed vectors, for the witness's `pqVectors`.
+    pub fn dequantize_rows(&self) -> Vec<Vec<f64>> {
+        self.codes
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(d, code)| dequantize(self.scales[d], self.zero_points[d], *code))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// `scale * (code - zero_point)`, chosen so its fixed-point form is exactly
+/// the product the circuit computes from the fixed-point scale.
+pub fn dequantize(scale: f64, zero_point: i8, code: i8) -> f64 {
+    let steps = f64::from(code) - f64::from(zero_point);
+    let fixed = (scale * FIXED_POINT_SCALE).floor() * steps;
+    (fixed + 0.5) / FIXED_POINT_SCALE
+}
+
+fn clamp_int8(value: f64) -> i8 {
+    value.round().clamp(f64::from(i8::MIN), f64::from(i8::MAX)) as i8
+}
+
+/// Checks that the witness's `scalarQuantization` covers every vector and
+/// that every pq component is its dequantized code.
+pub fn validate_scalar_witness(witness: &WitnessData) -> Result<&ScalarQuantization> {
+    let Some(scalar) = witness.scalar_quantization.as_ref() else {
+        anyhow::bail!(Coded::new(
+            ErrorCode::InvalidInput,
+            "scalar mode needs a witness with scalarQuantization"
+        ));
+    };
+    let dim = witness.pq_vectors.first().map(Vec::len).unwrap_or(0);
+    if scalar.scales.len() != dim || scalar.zero_points.len() != dim {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "scalarQuantization has {} scales and {} zero points for {dim} dimensions",
+                scalar.scales.len(),
+                scalar.zero_points.len()
+            )
+        ));
+    }
+    if scalar.codes.len() != witness.pq_vectors.len() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "scalarQuantization has {} code rows for {} pq vectors",
+                scalar.codes.len(),
+                witness.pq_vectors.len()
+            )
+        ));
+    }
+    let (scales, zeros, codes) = scalar_fields(scalar);
+    for (row_idx, (row_codes, pq_row)) in codes.iter().zip(&witness.pq_vectors).enumerate() {
+        if row_codes.len() != dim || pq_row.len() != dim {
+            anyhow::bail!(Coded::new(
+                ErrorCode::ShapeMismatch,
+                format!("row {row_idx}: expected {dim} codes and {dim} pq components")
+            ));
+        }
+        for (d, value) in pq_row.iter().enumerate() {
+            if float_to_field(*value) != scales[d] * (row_codes[d] - zeros[d]) {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::InvalidInput,
+                    format!("row {row_idx}: pq component {d} does not dequantize from its code")
+                ));
+            }
+        }
+    }
+    Ok(scalar)
+}
+
+/// Scales, zero points and code rows as circuit values.
+pub fn scalar_fields(scalar: &ScalarQuantization) -> (Vec<Fr>, Vec<Fr>, Vec<Vec<Fr>>) {
+    let scales = scalar.scales.iter().map(|s| float_to_field(*s)).collect();
+    let zeros = scalar
+        .zero_points
+        .iter()
+        .map(|z| int8_to_field(*z))
+        .collect();
+    let codes = scalar
+        .codes
+        .iter()
+        .map(|row| row.iter().map(|c| int8_to_field(*c)).collect())
+        .collect();
+    (scales, zeros, codes)
+}
+
+/// The public commitment to `scalar`, matching the in-circuit hash over the
+/// scales, then the zero points, then the codes row by row.
+pub fn scalar_commitment(scalar: &ScalarQuantization) -> Fr {
+    let (scales, zeros, codes) = scalar_fields(scalar);
+    let mut inputs = scales;
+    inputs.extend(zeros);
+    inputs.extend(codes.into_iter().flatten());
+    hash_with_capacity(domain_capacity(SCALAR_DOMAIN, inputs.len()), &inputs)
+}
@@ -0,0 +1,50 @@
+//! JSON Schemas of the prover's inputs, generated from the Rust types.
+//!
+//! External pipelines validate the witnesses and public inputs they produce
+//! against these before handing them to the prover, so a renamed or
+//! mistyped field is caught at the producer. The schemas follow the serde
+//! attributes, so they cannot drift from what the prover parses.
+
+use std::{fmt, str::FromStr};
+
+use schemars::{schema::RootSchema, schema_for};
+
+use crate::{io::WitnessData, public_inputs::ParsedPublicInputs};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    Witness,
+    PublicInputs,
+}
+
+impl SchemaKind {
+    pub const ALL: [SchemaKind; 2] = [SchemaKind::Witness, SchemaKind::PublicInputs];
+
+    pub fn schema(self) -> RootSchema {
+        match self {
+            SchemaKind::Witness => schema_for!(WitnessData),
+            SchemaKind::PublicInputs => schema_for!(ParsedPublicInputs),
+        }
+    }
+}
+
+impl fmt::Display for SchemaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SchemaKind::Witness => "witness",
+            SchemaKind::PublicInputs => "public-inputs",
+        })
+    }
+}
+
+impl FromStr for SchemaKind {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> anyhow::Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "witness" => Ok(SchemaKind::Witness),
+            "public-inputs" | "publicinputs" => Ok(SchemaKind::PublicInputs),
+            other => anyhow::bail!("unknown schema {other:?} (witness or public-inputs)"),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Off-circuit approximate-nearest-neighbour search over a block's vectors.
+//!
+//! A [`SearchIndex`] serves queries from the folded vectors, the pq
+//! reconstructions, or the codebook and codes. The codes index uses PQ
+//! asymmetric distance computation: per query it builds one table of
+//! `|q_m - centroid|^2` per subspace and scores each vector as a sum of `M`
+//! table lookups, without reconstructing it.
+//!
+//! Witnesses with an OPQ `rotation` store rotated vectors, so every index
+//! over them rotates queries by the same matrix first (see [`crate::opq`]).
+//!
+//! [`SearchIndex::attest`] turns a result set into an [`AnnWitness`] for the
+//! distance-threshold circuit in `ann`.
+
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ann::{fixed_squared_distance, AnnWitness},
+    io::WitnessData,
+    opq::{rotate, validate_rotation},
+    prove::FIXED_POINT_SCALE,
+    quantization::validate_pq_witness,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IndexSource {
+    /// Exact distances over the folded vectors.
+    Folded,
+    /// Exact distances over the pq-reconstructed vectors.
+    Pq,
+    /// Asymmetric distances over the codebook and codes.
+    Codes,
+}
+
+impl fmt::Display for IndexSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IndexSource::Folded => "folded",
+            IndexSource::Pq => "pq",
+            IndexSource::Codes => "codes",
+        })
+    }
+}
+
+impl FromStr for IndexSource {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "folded" => Ok(IndexSource::Folded),
+            "pq" => Ok(IndexSource::Pq),
+            "codes" | "adc" => Ok(IndexSource::Codes),
+            other => anyhow::bail!("unknown index source {other}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hit {
+    pub index: usize,
+    pub distance: f64,
+}
+
+#[derive(Debug, Clone)]
+enum Vectors {
+    Dense(Vec<Vec<f64>>),
+    Codes {
+        codebook: Vec<Vec<Vec<f64>>>,
+        codes: Vec<Vec<u32>>,
+        sub_dim: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchIndex {
+    source: IndexSource,
+    dim: usize,
+    vectors: Vectors,
+    rotation: Option<Vec<Vec<f64>>>,
+}
+
+impl SearchIndex {
+    pub fn from_witness(witness: &WitnessData, source: IndexSource) -> Result<Self> {
+        let vectors = match source {
+            IndexSource::Folded => Vectors::Dense(witness.folded_vectors.clone()),
+            IndexSource::Pq => Vectors::Dense(witness.pq_vectors.clone()),
+            IndexSource::Codes => {
+                let shape = validate_pq_witness(witness)?;
+                Vectors::Codes {
+                    codebook: witness.codebook.clone().unwrap_or_default(),
+                    codes: witness.pq_codes.clone().unwrap_or_default(),
+                    sub_dim: shape.sub_dim(),
+                }
+            }
+        };
+        let dim = match &vectors {
+            Vectors::Dense(rows) => rows.first().map(Vec::len).unwrap_or(0),
+            Vectors::Codes {
+                codebook, sub_dim, ..
+            } => codebook.len() * sub_dim,
+        };
+        if let Vectors::Dense(rows) = &vectors {
+            if let Some(idx) = rows.iter().position(|row| row.len() != dim) {
+                anyhow::bail!(
+                    "{source} vector {idx} has {} components, expected {dim}",
+                    rows[idx].len()
+                );
+            }
+        }
+        if let Some(rotation) = &witness.rotation {
+            validate_rotation(rotation, dim)?;
+        }
+        Ok(Self {
+            source,
+            dim,
+            vectors,
+            rotation: witness.rotation.clone(),
+        })
+    }
+
+    pub fn source(&self) -> IndexSource {
+        self.source
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.vectors {
+            Vectors::Dense(rows) => rows.len(),
+            Vectors::Codes { codes, .. } => codes.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The stored vector, reconstructed from its codes for a codes index.
+    pub fn vector(&self, index: usize) -> Result<Vec<f64>> {
+        match &self.vectors {
+            Vectors::Dense(rows) => rows
+                .get(index)
+                .cloned()
+                .with_context(|| format!("vector index {index} out of range")),
+            Vectors::Codes {
+                codebook, codes, ..
+            } => {
+                let row = codes
+                    .get(index)
+                    .with_context(|| format!("vector index {index} out of range"))?;
+                Ok(row
+                    .iter()
+                    .zip(codebook.iter())
+                    .flat_map(|(code, centroids)| centroids[*code as usize].iter().copied())
+                    .collect())
+            }
+        }
+    }
+
+    /// `query` in the space the vectors are stored in: rotated for an OPQ
+    /// index, unchanged otherwise.
+    fn index_space<'a>(&self, query: &'a [f64]) -> Result<Cow<'a, [f64]>> {
+        if query.len() != self.dim {
+            anyhow::bail!(
+                "query has {} components, index holds {}-dimensional vectors",
+                query.len(),
+                self.dim
+            );
+        }
+        Ok(match &self.rotation {
+            Some(rotation) => Cow::Owned(rotate(rotation, query)),
+            None => Cow::Borrowed(query),
+        })
+    }
+
+    /// The `k` nearest vectors by Euclidean distance, closest first.
+    pub fn search(&self, query: &[f64], k: usize) -> Result<Vec<Hit>> {
+        let query = self.index_space(query)?;
+        Ok(self.search_index_space(&query, k))
+    }
+
+    fn search_index_space(&self, query: &[f64], k: usize) -> Vec<Hit> {
+        let squared: Vec<f64> = match &self.vectors {
+            Vectors::Dense(rows) => rows
+                .iter()
+                .map(|row| squared_distance(query, row))
+                .collect(),
+            Vectors::Codes {
+                codebook,
+                codes,
+                sub_dim,
+            } => {
+                let tables = distance_tables(query, codebook, *sub_dim);
+                codes
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .zip(tables.iter())
+                            .map(|(code, table)| table[*code as usize])
+                            .sum()
+                    })
+                    .collect()
+            }
+        };
+        let mut hits: Vec<Hit> = squared
+            .into_iter()
+            .enumerate()
+            .map(|(index, distance)| Hit {
+                index,
+                distance: distance.sqrt(),
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then(a.index.cmp(&b.index))
+        });
+        hits.truncate(k);
+        hits
+    }
+
+    /// Builds the witness attesting the `k` nearest vectors to `query`.
+    ///
+    /// `threshold` defaults to the smallest bound that covers every hit in
+    /// the circuit's fixed-point arithmetic. With `candidates`, the `n`
+    /// nearest vectors are disclosed as the candidate set and the witness
+    /// carries a top-k claim over them. For an OPQ index the witness holds
+    /// the rotated query, as the results are rotated vectors.
+    pub fn attest(
+        &self,
+        query: &[f64],
+        k: usize,
+        threshold: Option<f64>,
+        candidates: Option<usize>,
+    ) -> Result<AnnWitness> {
+        let rotated = self.index_space(query)?;
+        let query: &[f64] = &rotated;
+        let pool = candidates.unwrap_or(k).max(k);
+        let hits = self.search_index_space(query, pool);
+        if hits.len() < k || k == 0 {
+            anyhow::bail!(
+                "index holds {} vectors, cannot attest {k} results",
+                self.len()
+            );
+        }
+        // Order by the distance the circuit computes, so float rounding in
+        // the search cannot produce an unsorted result list.
+        let mut pool: Vec<(u128, Vec<f64>)> = hits
+            .iter()
+            .map(|hit| {
+                let vector = self.vector(hit.index)?;
+                Ok((fixed_squared_distance(query, &vector), vector))
+            })
+            .collect::<Result<_>>()?;
+        pool.sort_by_key(|(distance, _)| *distance);
+
+        let results: Vec<Vec<f64>> = pool[..k].iter().map(|(_, vector)| vector.clone()).collect();
+        let furthest = pool[k - 1].0;
+        let threshold = threshold.unwrap_or_else(|| covering_threshold(furthest));
+        let witness = AnnWitness {
+            query: query.to_vec(),
+            results,
+            threshold,
+            candidates: candidates.map(|_| pool.into_iter().map(|(_, vector)| vector).collect()),
+        };
+        witness.validate()?;
+        Ok(witness)
+    }
+}
+
+/// Per-subspace tables of squared distances from the query segment to each centroid.
+fn distance_tables(query: &[f64], codebook: &[Vec<Vec<f64>>], sub_dim: usize) -> Vec<Vec<f64>> {
+    codebook
+        .iter()
+        .enumerate()
+        .map(|(subspace, centroids)| {
+            let segment = &query[subspace * sub_dim..(subspace + 1) * sub_dim];
+            centroids
+                .iter()
+                .map(|centroid| squared_distance(segment, centroid))
+                .collect()
+        })
+        .collect()
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Smallest threshold whose fixed-point square is at least `squared`.
+fn covering_threshold(squared: u128) -> f64 {
+    let mut root = (squared as f64).sqrt().ceil() as u128;
+    while root * root < squared {
+        root += 1;
+    }
+    // One extra unit absorbs the floor in `ann::squared_threshold`.
+    (root + 1) as f64 / FIXED_POINT_SCALE
+}
@@ -0,0 +1,155 @@
+//! Canary proof run against loaded keys before a process accepts real work.
+//!
+//! A deterministic synthetic block shaped for the keyed circuit is proved and
+//! verified with the same keys, and a proof against altered instances must be
+//! rejected. Mismatched keys, encoding changes or a broken build surface here
+//! instead of on the first customer job.
+
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use halo2_proofs::{
+    plonk::{keygen_vk, ProvingKey, VerifyingKey},
+    poly::kzg::commitment::ParamsKZG,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use serde::Serialize;
+
+use crate::{
+    bytes::Hash256,
+    circuit::FoldedParams,
+    codebook::CommitMode,
+    metadata::CURRENT_METADATA_VERSION,
+    prove::{build_circuit, create_folded_proof, FIXED_POINT_SCALE},
+    sparse::{sparsity_commitment, SparseVectors},
+    synthetic::{generate, SyntheticBlock, SyntheticConfig},
+    verify::verify_with_keys,
+};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+    pub circuit_k: u32,
+    pub circuit: FoldedParams,
+    pub proof_bytes: usize,
+    pub prove_ms: u64,
+    pub verify_ms: u64,
+    pub metadata_version: u32,
+    pub fixed_point_scale: f64,
+    pub features: Vec<&'static str>,
+}
+
+/// Cargo features compiled into this build.
+pub fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "encryption") {
+        features.push("encryption");
+    }
+    if cfg!(feature = "proto") {
+        features.push("proto");
+    }
+    features
+}
+
+/// The default-mode circuit keyed to the shape of the default canary block,
+/// for self-tests without a key config.
+pub fn canary_params() -> FoldedParams {
+    let config = SyntheticConfig::default();
+    FoldedParams {
+        vectors: config.vectors,
+        dim: config.dim,
+        ..FoldedParams::default()
+    }
+}
+
+/// The canary block for a keyed circuit shape.
+pub fn canary_block(circuit: &FoldedParams) -> Result<SyntheticBlock> {
+    let mut config = SyntheticConfig {
+        seed: 0xca7a_4b1d,
+        codebook_mode: circuit.codebook_commitment.unwrap_or(CommitMode::Merkle),
+        ..SyntheticConfig::default()
+    };
+    config.vectors = circuit.vectors;
+    config.dim = circuit.dim;
+    if circuit.pq_codes {
+        config.subvectors = circuit.subvectors;
+        config.centroids = circuit.centroids;
+    } else {
+        config.subvectors = 1;
+        config.centroids = 1;
+    }
+    let mut block = generate(&config).context("generating canary block")?;
+    if let Some(nonzeros) = circuit.nonzeros {
+        let witness = &mut block.witness;
+        for row in witness
+            .folded_vectors
+            .iter_mut()
+            .chain(witness.pq_vectors.iter_mut())
+        {
+            row.iter_mut().skip(nonzeros).for_each(|value| *value = 0.0);
+        }
+        let sparse = SparseVectors::from_dense(&witness.folded_vectors, &witness.pq_vectors)?;
+        let (indices, _, _) = sparse.padded(nonzeros)?;
+        block.public_inputs.sparsity_commitment =
+            Some(Hash256::from_field(&sparsity_commitment(&indices)));
+        witness.sparse_vectors = Some(sparse);
+    }
+    Ok(block)
+}
+
+/// Proves and verifies the canary block with `pk`, and checks that a proof
+/// does not verify against altered instances.
+pub fn run_self_test(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit_k: u32,
+    circuit: &FoldedParams,
+) -> Result<SelfTestReport> {
+    run_self_test_with(params, pk, pk.get_vk(), circuit_k, circuit)
+}
+
+/// [`run_self_test`] verifying with a separately loaded `vk`.
+pub fn run_self_test_with(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    vk: &VerifyingKey<G1Affine>,
+    circuit_k: u32,
+    circuit: &FoldedParams,
+) -> Result<SelfTestReport> {
+    let block = canary_block(circuit)?;
+    let canary = build_circuit(&block.witness, &block.public_inputs, circuit)
+        .context("self-test: building canary circuit")?;
+
+    let started = Instant::now();
+    let proof = create_folded_proof(params, pk, &canary).context("self-test: proving canary")?;
+    let prove_ms = started.elapsed().as_millis() as u64;
+
+    let started = Instant::now();
+    verify_with_keys(params, vk, &canary.public_inputs, &proof)
+        .context("self-test: canary proof does not verify with the loaded keys")?;
+    let verify_ms = started.elapsed().as_millis() as u64;
+
+    let keyed = keygen_vk(params, &canary).context("self-test: keying the canary circuit")?;
+    if keyed.transcript_repr() != vk.transcript_repr() {
+        anyhow::bail!(
+            "self-test: canary circuit keys to a different verifying key than the loaded keys"
+        );
+    }
+
+    let mut altered = canary.public_inputs.clone();
+    altered[0] += Fr::one();
+    if verify_with_keys(params, vk, &altered, &proof).is_ok() {
+        anyhow::bail!("self-test: canary proof verified against altered instances");
+    }
+
+    Ok(SelfTestReport {
+        circuit_k,
+        circuit: circuit.clone(),
+        proof_bytes: proof.len(),
+        prove_ms,
+        verify_ms,
+        metadata_version: CURRENT_METADATA_VERSION,
+        fixed_point_scale: FIXED_POINT_SCALE,
+        features: enabled_features(),
+    })
+}
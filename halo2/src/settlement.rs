@@ -0,0 +1,177 @@
+//! Aggregate instance commitments for posting a batch of blocks on-chain.
+//!
+//! A settlement contract accepts a contiguous run of blocks with one value,
+//! a digest it recomputes from the blocks' instances. No circuit proves this
+//! digest; each block is still verified on its own.
+//!
+//! ```text
+//! block_i   = instance_hash(public values of block i)
+//! aggregate = sha256(be32(firstHeight) || be32(n) || block_1 || .. || block_n) mod p
+//! ```
+//!
+//! `block_i` is the single instance of an `instanceHash` circuit (see
+//! [`instance_hash`]), so the contract can recompute it from the public
+//! inputs alone. In Solidity, with `blocks` a `uint256[]` of values below `p`:
+//!
+//! ```solidity
+//! uint256 aggregate = uint256(
+//!     sha256(abi.encodePacked(firstHeight, blocks.length, blocks))
+//! ) % P;
+//! ```
+//!
+//! [`SettlementBatch::calldata`] is `abi.encode(firstHeight, aggregate, blocks)`.
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use serde::Serialize;
+
+use crate::{
+    bytes::Hash256,
+    circuit::FoldedParams,
+    errors::{Coded, ErrorCode},
+    hashing::HashAlgorithm,
+    public_inputs::{instance_hash, ParsedPublicInputs},
+};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementBatch {
+    pub first_height: u64,
+    pub last_height: u64,
+    /// `instance_hash` of every block's public values, in height order.
+    pub block_instances: Vec<Hash256>,
+    pub aggregate_commitment: Hash256,
+    /// ABI-encoded `(uint256 firstHeight, uint256 aggregate, uint256[] blocks)`,
+    /// 0x-prefixed.
+    pub calldata: String,
+}
+
+/// Commits to `blocks`, which must have consecutive heights, under the
+/// circuit they were all proved with.
+pub fn settlement_batch(
+    blocks: &[ParsedPublicInputs],
+    params: &FoldedParams,
+) -> Result<SettlementBatch> {
+    let mut blocks: Vec<&ParsedPublicInputs> = blocks.iter().collect();
+    blocks.sort_by_key(|block| block.block_height);
+    let first = blocks.first().context("a settlement batch needs blocks")?;
+    let first_height = first.block_height;
+    for (offset, block) in blocks.iter().enumerate() {
+        if block.block_height != first_height + offset as u64 {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!(
+                    "settlement batch from height {first_height} is missing or repeats height {}",
+                    first_height + offset as u64
+                )
+            ));
+        }
+    }
+    let block_instances = blocks
+        .iter()
+        .map(|block| {
+            let values = block
+                .public_values(params)
+                .with_context(|| format!("block {}", block.block_height))?;
+            Ok(instance_hash(&values))
+        })
+        .collect::<Result<Vec<Fr>>>()?;
+    let aggregate = aggregate_commitment(first_height, &block_instances);
+    let block_instances: Vec<Hash256> = block_instances.iter().map(Hash256::from_field).collect();
+    Ok(SettlementBatch {
+        first_height,
+        last_height: first_height + blocks.len() as u64 - 1,
+        calldata: format!(
+            "0x{}",
+            hex::encode(abi_encode(
+                first_height,
+                &Hash256::from_field(&aggregate),
+                &block_instances
+            ))
+        ),
+        block_instances,
+        aggregate_commitment: Hash256::from_field(&aggregate),
+    })
+}
+
+/// `sha256(be32(first_height) || be32(n) || be32(block_1) || ..) mod p`.
+pub fn aggregate_commitment(first_height: u64, block_instances: &[Fr]) -> Fr {
+    let mut hasher = HashAlgorithm::Sha256.hasher();
+    hasher.update(&uint256(first_height));
+    hasher.update(&uint256(block_instances.len() as u64));
+    for value in block_instances {
+        hasher.update(Hash256::from_field(value).as_bytes());
+    }
+    hasher.finalize().to_field_reduced()
+}
+
+fn uint256(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn abi_encode(first_height: u64, aggregate: &Hash256, blocks: &[Hash256]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(32 * (4 + blocks.len()));
+    out.extend_from_slice(&uint256(first_height));
+    out.extend_from_slice(aggregate.as_bytes());
+    // Offset of the dynamic array, after the three head words.
+    out.extend_from_slice(&uint256(3 * 32));
+    out.extend_from_slice(&uint256(blocks.len() as u64));
+    for block in blocks {
+        out.extend_from_slice(block.as_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::{generate, SyntheticConfig};
+
+    // Known answers computed outside Rust as the contract does:
+    // uint256(sha256(abi.encodePacked(uint256(7), uint256(3), [1, 2, p - 1]))) % p
+    // and abi.encode(uint256(7), aggregate, [1, 2, p - 1]).
+    const AGGREGATE: &str = "0x2e3a49d7e995f436e0306da24e602a6509f4db5a2470dfbcbce5069acdfcaaf9";
+    const CALLDATA: &str = concat!(
+        "0000000000000000000000000000000000000000000000000000000000000007",
+        "2e3a49d7e995f436e0306da24e602a6509f4db5a2470dfbcbce5069acdfcaaf9",
+        "0000000000000000000000000000000000000000000000000000000000000060",
+        "0000000000000000000000000000000000000000000000000000000000000003",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000",
+    );
+
+    fn blocks() -> Vec<Fr> {
+        vec![Fr::from(1), Fr::from(2), -Fr::one()]
+    }
+
+    #[test]
+    fn aggregate_commitment_matches_the_contract() {
+        let aggregate = aggregate_commitment(7, &blocks());
+        assert_eq!(Hash256::from_field(&aggregate).to_hex(), AGGREGATE);
+    }
+
+    #[test]
+    fn calldata_matches_abi_encode() {
+        let aggregate = Hash256::from_hex(AGGREGATE).unwrap();
+        let blocks: Vec<Hash256> = blocks().iter().map(Hash256::from_field).collect();
+        assert_eq!(hex::encode(abi_encode(7, &aggregate, &blocks)), CALLDATA);
+    }
+
+    #[test]
+    fn batches_need_consecutive_heights() {
+        let block = |block_height| {
+            generate(&SyntheticConfig {
+                block_height,
+                ..SyntheticConfig::default()
+            })
+            .unwrap()
+            .public_inputs
+        };
+        let params = FoldedParams::default();
+        assert!(settlement_batch(&[block(1), block(3)], &params).is_err());
+        assert!(settlement_batch(&[], &params).is_err());
+    }
+}
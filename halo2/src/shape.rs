@@ -0,0 +1,408 @@
+//! Checks a witness against the circuit shape recorded with the keys.
+//!
+//! A witness that does not fit the keyed circuit otherwise fails deep inside
+//! synthesis as a bare `Error::Synthesis` or `NotEnoughRowsAvailable`.
+//! [`negotiate`] runs before the circuit is built and names what the keys
+//! expect, what the witness has, and how to re-key for it.
+
+use std::fmt;
+
+use anyhow::Result;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem};
+use halo2curves::bn256::Fr;
+
+use crate::{
+    circuit::{FoldedCircuit, FoldedParams},
+    errors::{Coded, ErrorCode},
+    fixed_point::TABLE_ROWS,
+    gadgets::{merkle::MerkleUpdate, sha256::Sha256Chip},
+    io::WitnessData,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WitnessShape {
+    pub vectors: usize,
+    pub dim: usize,
+}
+
+impl WitnessShape {
+    /// Shape of `witness`, rejecting ragged rows and folded/pq disagreements.
+    pub fn of(witness: &WitnessData) -> Result<Self> {
+        Self::of_rows(&witness.folded_vectors, &witness.pq_vectors)
+    }
+
+    /// [`Self::of`] for vectors in any representation.
+    pub fn of_rows<T>(folded: &[Vec<T>], pq: &[Vec<T>]) -> Result<Self> {
+        let vectors = folded.len();
+        if vectors == 0 {
+            anyhow::bail!("witness must contain foldedVectors");
+        }
+        if pq.len() != vectors {
+            anyhow::bail!(
+                "witness has {vectors} folded vectors but {} pq vectors",
+                pq.len()
+            );
+        }
+        let dim = folded[0].len();
+        if dim == 0 {
+            anyhow::bail!("witness vectors must have at least one dimension");
+        }
+        let rows = folded.iter().zip(pq);
+        for (row, (folded, pq)) in rows.enumerate() {
+            if folded.len() != dim || pq.len() != dim {
+                anyhow::bail!(
+                    "vector {row} has {} folded and {} pq components, expected {dim} like vector 0",
+                    folded.len(),
+                    pq.len()
+                );
+            }
+        }
+        Ok(Self { vectors, dim })
+    }
+
+    /// Rows of the residual column: `folded, pq, diff` per component and
+    /// one epsilon check per vector.
+    pub fn residual_rows(&self) -> usize {
+        self.vectors * (3 * self.dim + 1)
+    }
+}
+
+/// A witness that does not fit the keyed circuit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    pub expected: String,
+    pub got: String,
+    pub hint: String,
+}
+
+impl fmt::Display for ShapeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "witness does not fit the keyed circuit: expected {}, got {}; {}",
+            self.expected, self.got, self.hint
+        )
+    }
+}
+
+impl std::error::Error for ShapeMismatch {}
+
+/// Rows halo2 reserves for blinding at the end of every column.
+fn reserved_rows(params: &FoldedParams) -> usize {
+    let mut meta = ConstraintSystem::<Fr>::default();
+    FoldedCircuit::configure_with_params(&mut meta, params.clone());
+    meta.minimum_rows()
+}
+
+/// Most vectors of `dim` components whose residuals fit in `2^circuit_k` rows.
+pub fn max_vectors(params: &FoldedParams, circuit_k: u32, dim: usize) -> usize {
+    let usable = (1usize << circuit_k).saturating_sub(reserved_rows(params));
+    if usable < TABLE_ROWS {
+        return 0;
+    }
+    usable / (3 * dim + 1)
+}
+
+/// Smallest `k` whose residual column, component range table and
+/// `instanceHash` SHA-256 hold `shape`.
+pub fn required_k(params: &FoldedParams, shape: WitnessShape) -> u32 {
+    let hashed = if params.instance_hash {
+        Sha256Chip::rows(params.public_len())
+    } else {
+        0
+    };
+    let needed = shape.residual_rows().max(TABLE_ROWS).max(hashed) + reserved_rows(params);
+    needed.next_power_of_two().trailing_zeros()
+}
+
+/// Fails with the `k` that `shape` needs when `circuit_k` is too small,
+/// for paths such as `MockProver::run` that would otherwise report a bare
+/// `NotEnoughRowsAvailable`.
+pub fn ensure_rows(params: &FoldedParams, shape: WitnessShape, circuit_k: u32) -> Result<()> {
+    let needed = required_k(params, shape);
+    if needed > circuit_k {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!("witness needs k≥{needed}, you passed {circuit_k}")
+        ));
+    }
+    Ok(())
+}
+
+/// Checks `witness` against the keyed `params`, and against the row budget
+/// of `circuit_k` when it is known. Every circuit fixes `vectors` and `dim`
+/// at keygen.
+pub fn negotiate(
+    witness: &WitnessData,
+    params: &FoldedParams,
+    circuit_k: Option<u32>,
+) -> Result<WitnessShape> {
+    negotiate_shape(witness, WitnessShape::of(witness)?, params, circuit_k)
+}
+
+/// [`negotiate`] for a witness whose vectors are held elsewhere, such as a
+/// [`crate::io::FieldWitness`], with `shape` taken from them.
+pub fn negotiate_shape(
+    witness: &WitnessData,
+    shape: WitnessShape,
+    params: &FoldedParams,
+    circuit_k: Option<u32>,
+) -> Result<WitnessShape> {
+    if let Some(capacity) = params.delta {
+        let vectors = witness.delta.as_ref().map(|delta| delta.vector_count);
+        if vectors != Some(params.vectors) || shape.dim != params.dim || shape.vectors > capacity {
+            return Err(ShapeMismatch {
+                expected: format!(
+                    "a delta of ≤{capacity} changed {}-dim vectors in a block of {}",
+                    params.dim, params.vectors
+                ),
+                got: match vectors {
+                    Some(vectors) => format!(
+                        "{} changed {}-dim vectors in a block of {vectors}",
+                        shape.vectors, shape.dim
+                    ),
+                    None => "a full witness".to_string(),
+                },
+                hint: "build the witness with `yysfold delta`, or re-keygen with a larger --delta"
+                    .to_string(),
+            }
+            .into());
+        }
+    } else if witness.delta.is_some() {
+        return Err(ShapeMismatch {
+            expected: "a full witness".to_string(),
+            got: format!("a delta of {} changed vectors", shape.vectors),
+            hint: "prove deltas with keys generated with --delta".to_string(),
+        }
+        .into());
+    } else if shape.vectors != params.vectors || shape.dim != params.dim {
+        return Err(ShapeMismatch {
+            expected: format!("{}-dim × {} vectors", params.dim, params.vectors),
+            got: format!("{} × {}", shape.dim, shape.vectors),
+            hint: "the vector layout is fixed at keygen, re-keygen from this witness \
+                   by running the prover with new --proving-key and --verification-key paths"
+                .to_string(),
+        }
+        .into());
+    }
+    if let Some(nonzeros) = params.nonzeros {
+        let found = witness
+            .sparse_vectors
+            .as_ref()
+            .map(|sparse| sparse.max_nonzeros());
+        if found.map_or(true, |found| found > nonzeros) {
+            return Err(ShapeMismatch {
+                expected: format!("sparseVectors with ≤{nonzeros} nonzeros per vector"),
+                got: match found {
+                    Some(found) => format!("{found} nonzeros"),
+                    None => "a dense witness".to_string(),
+                },
+                hint: "re-keygen with --sparse from this witness".to_string(),
+            }
+            .into());
+        }
+    }
+    if let Some(circuit_k) = circuit_k {
+        let row_len = params.nonzeros.unwrap_or(shape.dim);
+        let capacity = max_vectors(params, circuit_k, row_len);
+        if params.delta.unwrap_or(shape.vectors) > capacity {
+            let laid_out = WitnessShape {
+                vectors: params.delta.unwrap_or(shape.vectors),
+                dim: row_len,
+            };
+            return Err(ShapeMismatch {
+                expected: format!("{}-dim × ≤{capacity} vectors at k={circuit_k}", shape.dim),
+                got: format!("{} × {}", shape.dim, shape.vectors),
+                hint: format!(
+                    "re-keygen with --circuit-k {}",
+                    required_k(params, laid_out).max(circuit_k + 1)
+                ),
+            }
+            .into());
+        }
+    }
+    Ok(shape)
+}
+
+fn row_lens<T>(rows: &[Vec<T>]) -> Vec<usize> {
+    rows.iter().map(Vec::len).collect()
+}
+
+/// Checks that `circuit` has the shape of the blank circuit its keys were
+/// generated from, so a proof never silently runs against a different
+/// layout than the one keyed.
+pub fn ensure_blank_parity(circuit: &FoldedCircuit) -> Result<()> {
+    let params = &circuit.params;
+    let blank = FoldedCircuit::blank_with(params);
+    if circuit.public_inputs.len() != blank.public_inputs.len() {
+        anyhow::bail!(
+            "circuit has {} instances, keys expect {}",
+            circuit.public_inputs.len(),
+            blank.public_inputs.len()
+        );
+    }
+    if circuit.hashed_inputs.len() != blank.hashed_inputs.len() {
+        anyhow::bail!(
+            "circuit hashes {} public values, keys expect {}",
+            circuit.hashed_inputs.len(),
+            blank.hashed_inputs.len()
+        );
+    }
+    let keyed = row_lens(&blank.folded_vectors);
+    if row_lens(&circuit.folded_vectors) != keyed
+        || row_lens(&circuit.pq_vectors) != keyed
+        || circuit.epsilon_squared.len() != blank.epsilon_squared.len()
+    {
+        anyhow::bail!(
+            "circuit vectors do not match the keyed {} entries × {} vectors",
+            params.row_len(),
+            params.laid_out_vectors()
+        );
+    }
+    let paths = |updates: &[MerkleUpdate]| -> Vec<usize> {
+        updates.iter().map(|update| update.path.len()).collect()
+    };
+    if paths(&circuit.delta_updates) != paths(&blank.delta_updates) {
+        anyhow::bail!(
+            "circuit delta updates do not match the keyed {} updates of depth {}",
+            params.delta.unwrap_or_default(),
+            params.tree_depth()
+        );
+    }
+    if row_lens(&circuit.sparse_indices) != row_lens(&blank.sparse_indices) {
+        anyhow::bail!(
+            "circuit sparsity pattern does not match the keyed {} nonzeros per vector",
+            params.row_len()
+        );
+    }
+    let codebook = |codebook: &[Vec<Vec<Fr>>]| -> Vec<Vec<usize>> {
+        codebook.iter().map(|subspace| row_lens(subspace)).collect()
+    };
+    if row_lens(&circuit.pq_codes) != row_lens(&blank.pq_codes)
+        || codebook(&circuit.codebook) != codebook(&blank.codebook)
+    {
+        anyhow::bail!(
+            "circuit codebook does not match the keyed {} subspaces × {} centroids",
+            params.subvectors,
+            params.centroids
+        );
+    }
+    if circuit.row_salts.len() != blank.row_salts.len() {
+        anyhow::bail!(
+            "circuit has {} row salts, keys expect {}",
+            circuit.row_salts.len(),
+            blank.row_salts.len()
+        );
+    }
+    if circuit.rotation.len() != blank.rotation.len() {
+        anyhow::bail!(
+            "circuit has {} rotation entries, keys expect {}",
+            circuit.rotation.len(),
+            blank.rotation.len()
+        );
+    }
+    if row_lens(&circuit.residual_codes) != row_lens(&blank.residual_codes)
+        || codebook(&circuit.residual_codebook) != codebook(&blank.residual_codebook)
+        || row_lens(&circuit.primary_vectors) != row_lens(&blank.primary_vectors)
+    {
+        anyhow::bail!(
+            "circuit residual stage does not match the keyed {} subspaces × {:?} centroids",
+            params.subvectors,
+            params.residual_centroids
+        );
+    }
+    if row_lens(&circuit.scalar_codes) != row_lens(&blank.scalar_codes)
+        || circuit.scales.len() != blank.scales.len()
+        || circuit.zero_points.len() != blank.zero_points.len()
+    {
+        anyhow::bail!(
+            "circuit int8 codes do not match the keyed {} vectors × {} dimensions",
+            params.vectors,
+            params.dim
+        );
+    }
+    if circuit.blinding.len() != blank.blinding.len() {
+        anyhow::bail!(
+            "circuit has {} blinding factors, keys expect {}",
+            circuit.blinding.len(),
+            blank.blinding.len()
+        );
+    }
+    if circuit.row_order.len() != blank.row_order.len() {
+        anyhow::bail!(
+            "circuit permutes {} rows, keys expect {}",
+            circuit.row_order.len(),
+            blank.row_order.len()
+        );
+    }
+    if circuit.subvector_bounds.len() != blank.subvector_bounds.len() {
+        anyhow::bail!(
+            "circuit has {} subvector epsilons, keys expect {}",
+            circuit.subvector_bounds.len(),
+            blank.subvector_bounds.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{plonk::keygen_vk, poly::kzg::commitment::ParamsKZG};
+    use halo2curves::bn256::Bn256;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+    use crate::{
+        prove::build_circuit,
+        selftest::{canary_block, canary_params},
+    };
+
+    /// The canary circuit for `params` and the blank circuit its keys come from.
+    fn circuits(params: &FoldedParams) -> (FoldedCircuit, FoldedCircuit, u32) {
+        let block = canary_block(params).unwrap();
+        let circuit = build_circuit(&block.witness, &block.public_inputs, params).unwrap();
+        let k = required_k(params, WitnessShape::of(&block.witness).unwrap());
+        (circuit, FoldedCircuit::blank_with(params), k)
+    }
+
+    fn assert_keys_match(params: FoldedParams) {
+        let (circuit, blank, k) = circuits(&params);
+        ensure_blank_parity(&circuit).unwrap();
+        let setup = ParamsKZG::<Bn256>::setup(k, ChaCha20Rng::seed_from_u64(0));
+        let full = keygen_vk(&setup, &circuit).unwrap();
+        let keyed = keygen_vk(&setup, &blank).unwrap();
+        assert_eq!(full.transcript_repr(), keyed.transcript_repr());
+    }
+
+    #[test]
+    fn default_blank_circuit_keys_like_a_full_one() {
+        assert_keys_match(canary_params());
+    }
+
+    #[test]
+    fn vector_root_blank_circuit_keys_like_a_full_one() {
+        assert_keys_match(FoldedParams {
+            vector_root: true,
+            ..canary_params()
+        });
+    }
+
+    #[test]
+    fn sla_bound_blank_circuit_keys_like_a_full_one() {
+        assert_keys_match(FoldedParams {
+            sla_bound: true,
+            instance_hash: true,
+            ..canary_params()
+        });
+    }
+
+    #[test]
+    fn default_parity_rejects_a_reshaped_circuit() {
+        let (mut circuit, _, _) = circuits(&canary_params());
+        circuit.folded_vectors.pop();
+        circuit.pq_vectors.pop();
+        circuit.epsilon_squared.pop();
+        assert!(ensure_blank_parity(&circuit).is_err());
+    }
+}
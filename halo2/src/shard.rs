@@ -0,0 +1,391 @@
+//! Sharded blocks: one block proven as several equal-sized circuits.
+//!
+//! A block too large for one circuit at an acceptable `k` is split into `S`
+//! contiguous row ranges ([`split`]). Every shard is proven on its own with
+//! the same keys, against public inputs derived from the block's
+//! ([`shard_public_inputs`]):
+//!
+//! - block-level values (state roots, height, `txMerkleRoot`, `codebookRoot`,
+//!   lineage, beacon and `subvectorEpsilons`) are copied unchanged;
+//! - `foldedCommitment` and `pqCommitment` become [`shard_commitment`]s of the
+//!   block's, binding each shard to its block and position;
+//! - row-dependent values (`foldedVectorRoot`, `pqCodesCommitment`,
+//!   `compressionStats`, `residualCommitment`) are recomputed over the
+//!   shard's rows.
+//!
+//! [`combine`] checks each shard's public inputs against that derivation,
+//! verifies every shard proof and writes a [`ShardManifest`] whose `root`
+//! commits to the block's public inputs and every shard's instances and proof.
+
+use anyhow::{Context, Result};
+use halo2_proofs::poly::commitment::Params;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    compat::CIRCUIT_VERSION,
+    epsilon::{residual_cells, residual_commitment},
+    errors::{Coded, ErrorCode},
+    hashing::{digest, Blake3Hasher, Hasher},
+    io::WitnessData,
+    merkle,
+    permutation::{committed_rows, permutation_commitment, row_permutation},
+    public_inputs::ParsedPublicInputs,
+    quantization::{
+        codes_commitment, stage_codes, validate_pq_witness, CompressionStats, ResidualStage,
+    },
+    scalar::{scalar_commitment, ScalarQuantization},
+    sparse::SparseVectors,
+    verify::VerifierKeys,
+};
+
+pub const SHARD_MANIFEST_VERSION: u32 = 1;
+
+/// blake3 `derive_key` context of shard commitments and manifest roots.
+const SHARD_CONTEXT: &str = "yysfold shard v1";
+
+/// Splits `witness` into `shards` witnesses of equal row counts, so every
+/// shard fits one set of keys. The codebook, header and provenance are
+/// shared.
+pub fn split(witness: &WitnessData, shards: usize) -> Result<Vec<WitnessData>> {
+    if witness.delta.is_some() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "delta witnesses cannot be sharded; shard the full block"
+        ));
+    }
+    let rows = witness.folded_vectors.len();
+    if shards == 0 || rows % shards != 0 {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            format!("{shards} shards do not divide the block's {rows} vectors evenly")
+        ));
+    }
+    if witness.pq_vectors.len() != rows {
+        anyhow::bail!(Coded::new(
+            ErrorCode::ShapeMismatch,
+            format!(
+                "{rows} folded vectors but {} pq vectors",
+                witness.pq_vectors.len()
+            )
+        ));
+    }
+    let per_shard = rows / shards;
+    let range = |index: usize| index * per_shard..(index + 1) * per_shard;
+    Ok((0..shards)
+        .map(|index| WitnessData {
+            folded_vectors: witness.folded_vectors[range(index)].to_vec(),
+            pq_vectors: witness.pq_vectors[range(index)].to_vec(),
+            header_rlp: witness.header_rlp.clone(),
+            pq_codes: witness
+                .pq_codes
+                .as_ref()
+                .map(|codes| codes.get(range(index)).unwrap_or_default().to_vec()),
+            codebook: witness.codebook.clone(),
+            sparse_vectors: witness.sparse_vectors.as_ref().map(|sparse| SparseVectors {
+                dim: sparse.dim,
+                rows: sparse.rows.get(range(index)).unwrap_or_default().to_vec(),
+            }),
+            provenance: witness.provenance.clone(),
+            delta: None,
+            audit_salt: None,
+            residuals: witness
+                .residuals
+                .as_ref()
+                .map(|residuals| residuals.get(range(index)).unwrap_or_default().to_vec()),
+            scale: witness.scale,
+            rotation: witness.rotation.clone(),
+            residual_stage: witness.residual_stage.as_ref().map(|stage| ResidualStage {
+                codes: stage.codes.get(range(index)).unwrap_or_default().to_vec(),
+                codebook: stage.codebook.clone(),
+            }),
+            scalar_quantization: witness.scalar_quantization.as_ref().map(|scalar| {
+                ScalarQuantization {
+                    scales: scalar.scales.clone(),
+                    zero_points: scalar.zero_points.clone(),
+                    codes: scalar.codes.get(range(index)).unwrap_or_default().to_vec(),
+                }
+            }),
+            row_permutation: witness.row_permutation.clone(),
+            blinding: None,
+        })
+        .collect())
+}
+
+/// The commitment shard `index` of `count` exposes in place of the block's
+/// `commitment`.
+pub fn shard_commitment(commitment: &Hash256, index: usize, count: usize) -> Hash256 {
+    let mut hasher = Blake3Hasher::derive_key(SHARD_CONTEXT);
+    hasher.update(b"commitment");
+    hasher.update(commitment.as_bytes());
+    hasher.update(&(index as u64).to_le_bytes());
+    hasher.update(&(count as u64).to_le_bytes());
+    hasher.finalize()
+}
+
+/// Public inputs for shard `index` of `count`, holding the rows of `shard`.
+pub fn shard_public_inputs(
+    block: &ParsedPublicInputs,
+    shard: &WitnessData,
+    index: usize,
+    count: usize,
+) -> Result<ParsedPublicInputs> {
+    if block.sparsity_commitment.is_some() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "sparse circuits cannot be sharded; their padding is keyed per block"
+        ));
+    }
+    if shard.blinding.is_some() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "hiding commitments cannot be sharded; shard commitments derive from the block's"
+        ));
+    }
+    if block.witness_commitment.is_some() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            "witness commitments cannot be sharded; they are rooted over the whole block"
+        ));
+    }
+    let mut public_inputs = block.clone();
+    public_inputs.folded_commitment = shard_commitment(&block.folded_commitment, index, count);
+    public_inputs.pq_commitment = shard_commitment(&block.pq_commitment, index, count);
+    if block.folded_vector_root.is_some() {
+        let folded = shard
+            .fixed_point()?
+            .to_field_matrix(&shard.folded_vectors)
+            .context("foldedVectors")?;
+        let leaves = merkle::leaves(&folded);
+        let root = merkle::root(committed_rows(shard, &leaves));
+        public_inputs.folded_vector_root = Some(Hash256::from_field(&root));
+    }
+    if block.pq_codes_commitment.is_some() {
+        if shard.pq_codes.is_none() {
+            anyhow::bail!("block commits to pqCodes; witness has none");
+        }
+        let codes = stage_codes(shard);
+        public_inputs.pq_codes_commitment = Some(Hash256::from_field(&codes_commitment(&codes)));
+    }
+    if block.scalar_commitment.is_some() {
+        let scalar = shard
+            .scalar_quantization
+            .as_ref()
+            .context("block commits to scalarQuantization; witness has none")?;
+        public_inputs.scalar_commitment = Some(Hash256::from_field(&scalar_commitment(scalar)));
+    }
+    if block.permutation_commitment.is_some() {
+        let permutation = row_permutation(shard)?;
+        let order = permutation.order(shard.folded_vectors.len());
+        public_inputs.permutation_commitment = Some(Hash256::from_field(&permutation_commitment(
+            permutation,
+            &order,
+        )));
+    }
+    if block.compression_stats.is_some() {
+        let shape = validate_pq_witness(shard)?;
+        public_inputs.compression_stats =
+            Some(CompressionStats::new(shard.folded_vectors.len(), shape));
+    }
+    let fixed_point = shard.fixed_point()?;
+    let residuals = fixed_point.residuals(&shard.folded_vectors, &shard.pq_vectors);
+    let cells = residual_cells(&residuals, fixed_point, shard.folded_vectors.len());
+    public_inputs.residual_commitment = Some(Hash256::from_field(&residual_commitment(&cells)));
+    Ok(public_inputs)
+}
+
+/// Checks that `shard` is shard `index` of `count` of `block`: block-level
+/// values unchanged and both commitments derived from the block's.
+pub fn check_shard(
+    block: &ParsedPublicInputs,
+    shard: &ParsedPublicInputs,
+    index: usize,
+    count: usize,
+) -> Result<()> {
+    let mismatch = |field: &str| -> Result<()> {
+        Err(Coded::new(
+            ErrorCode::CommitmentMismatch,
+            format!("shard {index}: {field} differs from the block's"),
+        )
+        .into())
+    };
+    if shard.prev_state_root != block.prev_state_root {
+        return mismatch("prevStateRoot");
+    }
+    if shard.new_state_root != block.new_state_root {
+        return mismatch("newStateRoot");
+    }
+    if shard.block_height != block.block_height {
+        return mismatch("blockHeight");
+    }
+    if shard.tx_merkle_root != block.tx_merkle_root {
+        return mismatch("txMerkleRoot");
+    }
+    if shard.codebook_root != block.codebook_root {
+        return mismatch("codebookRoot");
+    }
+    if shard.previous_proof_digest != block.previous_proof_digest {
+        return mismatch("previousProofDigest");
+    }
+    if shard.beacon_value != block.beacon_value {
+        return mismatch("beaconValue");
+    }
+    if shard.subvector_epsilons != block.subvector_epsilons {
+        return mismatch("subvectorEpsilons");
+    }
+    if shard.sla_bound != block.sla_bound {
+        return mismatch("slaBound");
+    }
+    for (field, block_value, shard_value) in [
+        (
+            "foldedCommitment",
+            &block.folded_commitment,
+            &shard.folded_commitment,
+        ),
+        ("pqCommitment", &block.pq_commitment, &shard.pq_commitment),
+    ] {
+        let expected = shard_commitment(block_value, index, count);
+        if *shard_value != expected {
+            anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!("shard {index}: {field} is not shard {index} of {count} of the block's (expected {expected})")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// One proven shard, as handed to [`combine`].
+#[derive(Debug, Clone)]
+pub struct ShardProof {
+    pub public_inputs: ParsedPublicInputs,
+    pub proof: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardEntry {
+    pub index: usize,
+    pub public_inputs: ParsedPublicInputs,
+    /// blake3 over the shard's instances, each as 32 big-endian bytes.
+    pub instances_digest: Hash256,
+    /// blake3 of the shard's proof bytes.
+    pub proof_digest: Hash256,
+}
+
+/// Binds a block's verified shard proofs to its public inputs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardManifest {
+    pub format_version: u32,
+    pub circuit_version: u32,
+    pub circuit_k: u32,
+    pub block_height: u64,
+    /// blake3 of the block's public inputs as JSON.
+    pub block_digest: Hash256,
+    pub shards: Vec<ShardEntry>,
+    /// Commitment to `blockDigest` and every shard's entry, in order.
+    pub root: Hash256,
+}
+
+impl ShardManifest {
+    /// Recomputes `root` from the other fields.
+    pub fn compute_root(&self) -> Result<Hash256> {
+        let mut hasher = Blake3Hasher::derive_key(SHARD_CONTEXT);
+        hasher.update(b"manifest");
+        hasher.update(&self.format_version.to_le_bytes());
+        hasher.update(&self.circuit_version.to_le_bytes());
+        hasher.update(&self.circuit_k.to_le_bytes());
+        hasher.update(self.block_digest.as_bytes());
+        hasher.update(&(self.shards.len() as u64).to_le_bytes());
+        for shard in &self.shards {
+            hasher.update(&(shard.index as u64).to_le_bytes());
+            hasher.update(json_digest(&shard.public_inputs)?.as_bytes());
+            hasher.update(shard.instances_digest.as_bytes());
+            hasher.update(shard.proof_digest.as_bytes());
+        }
+        Ok(hasher.finalize())
+    }
+}
+
+/// Verifies every shard of `block` against `keys` and returns the manifest
+/// binding them; `shards` are in index order.
+pub fn combine(
+    block: &ParsedPublicInputs,
+    keys: &VerifierKeys,
+    shards: &[ShardProof],
+) -> Result<ShardManifest> {
+    if shards.is_empty() {
+        anyhow::bail!(Coded::new(ErrorCode::Usage, "no shards to combine"));
+    }
+    let mut entries = Vec::with_capacity(shards.len());
+    for (index, shard) in shards.iter().enumerate() {
+        check_shard(block, &shard.public_inputs, index, shards.len())?;
+        let instances = shard
+            .public_inputs
+            .to_instances(&keys.circuit)
+            .with_context(|| format!("shard {index}"))?;
+        keys.verify(&instances, &shard.proof)
+            .with_context(|| format!("shard {index} of {} rejected", shards.len()))?;
+        let mut hasher = Blake3Hasher::new();
+        for instance in &instances {
+            hasher.update(Hash256::from_field(instance).as_bytes());
+        }
+        entries.push(ShardEntry {
+            index,
+            public_inputs: shard.public_inputs.clone(),
+            instances_digest: hasher.finalize(),
+            proof_digest: digest(&shard.proof),
+        });
+    }
+    let mut manifest = ShardManifest {
+        format_version: SHARD_MANIFEST_VERSION,
+        circuit_version: CIRCUIT_VERSION,
+        circuit_k: keys.params.k(),
+        block_height: block.block_height,
+        block_digest: json_digest(block)?,
+        shards: entries,
+        root: Hash256::ZERO,
+    };
+    manifest.root = manifest.compute_root()?;
+    Ok(manifest)
+}
+
+/// Checks `manifest` was combined from `block` and has not been edited since.
+pub fn check_manifest(manifest: &ShardManifest, block: &ParsedPublicInputs) -> Result<()> {
+    let block_digest = json_digest(block)?;
+    if manifest.block_digest != block_digest {
+        anyhow::bail!(Coded::new(
+            ErrorCode::CommitmentMismatch,
+            format!(
+                "manifest is for block digest {}, public inputs digest to {block_digest}",
+                manifest.block_digest
+            )
+        ));
+    }
+    let count = manifest.shards.len();
+    for (index, shard) in manifest.shards.iter().enumerate() {
+        if shard.index != index {
+            anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!("manifest entry {index} claims shard index {}", shard.index)
+            ));
+        }
+        check_shard(block, &shard.public_inputs, index, count)?;
+    }
+    let root = manifest.compute_root()?;
+    if manifest.root != root {
+        anyhow::bail!(Coded::new(
+            ErrorCode::CommitmentMismatch,
+            format!(
+                "manifest root {} does not match its entries ({root})",
+                manifest.root
+            )
+        ));
+    }
+    Ok(())
+}
+
+fn json_digest(public_inputs: &ParsedPublicInputs) -> Result<Hash256> {
+    Ok(digest(&serde_json::to_vec(public_inputs)?))
+}
@@ -0,0 +1,230 @@
+//! Witness manifests signed by the witness generator.
+//!
+//! The generator signs a witness's [`witness_digest`] with its ed25519 key
+//! and ships the detached [`WitnessManifest`] next to the witness, as
+//! `<witness>.sig.json` by default. A prover given a [`Signers`] list
+//! refuses witnesses that are unsigned, signed by a key not on the list, or
+//! changed since signing, so a prover fleet only proves data an authorized
+//! generator produced.
+//!
+//! The digest is taken over the witness as this crate re-serializes it
+//! (compact JSON after loading), so whitespace, key order and encryption of
+//! the file do not change it. Signing and verifying need the `signing`
+//! feature.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{
+    bytes::Hash256,
+    errors::{Coded, ErrorCode},
+    hashing::{Blake3Hasher, Hasher},
+    io::WitnessData,
+    platform::{from_json_slice, normalize},
+};
+
+pub const ALGORITHM: &str = "ed25519";
+/// Appended to a witness path for its default manifest path.
+pub const MANIFEST_SUFFIX: &str = ".sig.json";
+const DIGEST_CONTEXT: &str = "yysfold witness manifest v1";
+
+/// Detached signature over a witness digest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WitnessManifest {
+    pub algorithm: String,
+    pub public_key: Hash256,
+    pub digest: Hash256,
+    /// 64-byte ed25519 signature over `digest`, hex.
+    pub signature: String,
+}
+
+/// Generator keys a prover accepts witnesses from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Signers {
+    pub signers: Vec<Signer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Signer {
+    pub name: String,
+    pub public_key: Hash256,
+}
+
+/// blake3 of the re-serialized witness under the manifest context.
+pub fn witness_digest(witness: &WitnessData) -> Result<Hash256> {
+    let bytes = Zeroizing::new(serde_json::to_vec(witness)?);
+    let mut hasher = Blake3Hasher::derive_key(DIGEST_CONTEXT);
+    hasher.update(&bytes);
+    Ok(hasher.finalize())
+}
+
+/// `<witness>.sig.json`.
+pub fn manifest_path(witness: &Path) -> PathBuf {
+    let mut path = witness.as_os_str().to_owned();
+    path.push(MANIFEST_SUFFIX);
+    PathBuf::from(path)
+}
+
+impl WitnessManifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = normalize(path);
+        let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+        from_json_slice(&bytes).with_context(|| format!("parsing witness manifest {:?}", path))
+    }
+
+    /// The manifest at `path`, or at [`manifest_path`] of `witness` when
+    /// that exists.
+    pub fn load_for(witness: &Path, path: Option<&Path>) -> Result<Option<Self>> {
+        match path {
+            Some(path) => Self::load(path).map(Some),
+            None => {
+                let path = manifest_path(witness);
+                if path.exists() {
+                    Self::load(&path).map(Some)
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+/// Signs `witness` with the 32-byte ed25519 secret key `secret`.
+pub fn sign_witness(witness: &WitnessData, secret: &[u8; 32]) -> Result<WitnessManifest> {
+    let digest = witness_digest(witness)?;
+    Ok(WitnessManifest {
+        algorithm: ALGORITHM.to_string(),
+        public_key: public_key(secret)?,
+        digest,
+        signature: hex::encode(ed25519::sign(secret, digest.as_bytes())?),
+    })
+}
+
+/// Public key of the ed25519 secret key `secret`.
+pub fn public_key(secret: &[u8; 32]) -> Result<Hash256> {
+    ed25519::public_key(secret).map(Hash256)
+}
+
+/// A fresh ed25519 secret key from the OS RNG.
+pub fn generate_secret_key() -> Zeroizing<[u8; 32]> {
+    let mut secret = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(secret.as_mut());
+    secret
+}
+
+/// Parses a hex ed25519 secret key, as `yysfold sign-witness --key` reads.
+pub fn parse_secret_key(hex_key: &str) -> Result<Zeroizing<[u8; 32]>> {
+    let bytes = Zeroizing::new(
+        hex::decode(hex_key.trim().trim_start_matches("0x")).context("signing key must be hex")?,
+    );
+    <[u8; 32]>::try_from(bytes.as_slice())
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow::anyhow!("signing key must be 32 bytes"))
+}
+
+impl Signers {
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = normalize(path);
+        let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+        from_json_slice(&bytes).with_context(|| format!("parsing witness signers {:?}", path))
+    }
+
+    /// The signer whose valid signature over `witness` is `manifest`.
+    pub fn check(
+        &self,
+        witness: &WitnessData,
+        manifest: Option<&WitnessManifest>,
+    ) -> Result<&Signer> {
+        let unauthorized = |message: String| Coded::new(ErrorCode::UnauthorizedWitness, message);
+        let Some(manifest) = manifest else {
+            anyhow::bail!(unauthorized(
+                "witness is unsigned; this prover only proves witnesses with a signed manifest"
+                    .to_string()
+            ));
+        };
+        if manifest.algorithm != ALGORITHM {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!(
+                    "witness manifest uses {:?}; only {ALGORITHM} is supported",
+                    manifest.algorithm
+                )
+            ));
+        }
+        let signer = self
+            .signers
+            .iter()
+            .find(|signer| signer.public_key == manifest.public_key)
+            .ok_or_else(|| {
+                unauthorized(format!(
+                    "witness is signed by {}, which is not an authorized generator",
+                    manifest.public_key
+                ))
+            })?;
+        let digest = witness_digest(witness)?;
+        if digest != manifest.digest {
+            anyhow::bail!(unauthorized(format!(
+                "witness digest {digest} differs from the signed {}; the witness changed after signing",
+                manifest.digest
+            )));
+        }
+        let signature = hex::decode(manifest.signature.trim_start_matches("0x"))
+            .context("witness manifest signature must be hex")?;
+        ed25519::verify(
+            manifest.public_key.as_bytes(),
+            digest.as_bytes(),
+            &signature,
+        )
+        .map_err(|err| unauthorized(format!("{err:#} (signer {:?})", signer.name)))?;
+        Ok(signer)
+    }
+}
+
+#[cfg(feature = "signing")]
+mod ed25519 {
+    use anyhow::Result;
+    use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+    pub fn public_key(secret: &[u8; 32]) -> Result<[u8; 32]> {
+        Ok(SigningKey::from_bytes(secret).verifying_key().to_bytes())
+    }
+
+    pub fn sign(secret: &[u8; 32], msg: &[u8]) -> Result<[u8; 64]> {
+        Ok(SigningKey::from_bytes(secret).sign(msg).to_bytes())
+    }
+
+    pub fn verify(public: &[u8; 32], msg: &[u8], signature: &[u8]) -> Result<()> {
+        let key = VerifyingKey::from_bytes(public)
+            .map_err(|_| anyhow::anyhow!("witness manifest public key is not an ed25519 key"))?;
+        let signature = Signature::from_slice(signature)
+            .map_err(|_| anyhow::anyhow!("witness manifest signature must be 64 bytes"))?;
+        key.verify_strict(msg, &signature)
+            .map_err(|_| anyhow::anyhow!("witness manifest signature is invalid"))
+    }
+}
+
+#[cfg(not(feature = "signing"))]
+mod ed25519 {
+    use anyhow::Result;
+
+    const DISABLED: &str = "witness manifests need the `signing` feature";
+
+    pub fn public_key(_secret: &[u8; 32]) -> Result<[u8; 32]> {
+        anyhow::bail!(DISABLED)
+    }
+
+    pub fn sign(_secret: &[u8; 32], _msg: &[u8]) -> Result<[u8; 64]> {
+        anyhow::bail!(DISABLED)
+    }
+
+    pub fn verify(_public: &[u8; 32], _msg: &[u8], _signature: &[u8]) -> Result<()> {
+        anyhow::bail!(DISABLED)
+    }
+}
@@ -0,0 +1,312 @@
+//! Per-stage latency percentiles and SLO checks for the proving service.
+//!
+//! [`LatencyTracker`] keeps the latest `window` samples of every [`Stage`]
+//! and reports nearest-rank percentiles over them. Operators set thresholds
+//! such as "p99 of `total` under 5m"; once a stage has `minSamples` samples,
+//! a percentile over its threshold is a [`Breach`]. [`LatencyTracker::record`]
+//! returns the breaches that started or cleared with each job, for alerting,
+//! and with `flipReadiness` set the service reports itself not ready while
+//! any breach is open.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::parse_timeout;
+
+pub const DEFAULT_WINDOW: usize = 256;
+pub const DEFAULT_MIN_SAMPLES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// Submission to a worker picking the job up.
+    Queue,
+    /// Shape negotiation and circuit construction from the witness.
+    Witness,
+    /// `create_proof`.
+    Prove,
+    /// Submission to the finished proof.
+    Total,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Queue => "queue",
+            Stage::Witness => "witness",
+            Stage::Prove => "prove",
+            Stage::Total => "total",
+        })
+    }
+}
+
+/// SLO settings as written in the service config.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SloConfig {
+    /// Samples kept per stage; defaults to [`DEFAULT_WINDOW`].
+    #[serde(default)]
+    pub window: Option<usize>,
+    /// Samples a stage needs before its thresholds apply; defaults to
+    /// [`DEFAULT_MIN_SAMPLES`].
+    #[serde(default)]
+    pub min_samples: Option<usize>,
+    #[serde(default)]
+    pub thresholds: Vec<SloThreshold>,
+    /// Report not ready while any threshold is breached.
+    #[serde(default)]
+    pub flip_readiness: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SloThreshold {
+    pub stage: Stage,
+    /// Percentile in `(0, 100]`, e.g. `99`.
+    pub percentile: f64,
+    /// Upper bound (`90`, `500ms`, `5m`, `1h`).
+    pub max: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Threshold {
+    stage: Stage,
+    percentile: f64,
+    max: Duration,
+}
+
+/// A stage percentile over its threshold.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Breach {
+    pub stage: Stage,
+    pub percentile: f64,
+    pub observed_ms: u64,
+    pub max_ms: u64,
+}
+
+impl fmt::Display for Breach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} p{} is {} ms, over the {} ms SLO",
+            self.stage, self.percentile, self.observed_ms, self.max_ms
+        )
+    }
+}
+
+/// A breach starting or clearing, returned by [`LatencyTracker::record`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SloAlert {
+    pub breach: Breach,
+    pub resolved: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageLatency {
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyReport {
+    pub window: usize,
+    pub stages: BTreeMap<Stage, StageLatency>,
+    pub breaches: Vec<Breach>,
+    pub ready: bool,
+}
+
+struct State {
+    window: usize,
+    min_samples: usize,
+    thresholds: Vec<Threshold>,
+    flip_readiness: bool,
+    samples: HashMap<Stage, VecDeque<Duration>>,
+    breaches: Vec<Breach>,
+}
+
+pub struct LatencyTracker {
+    state: Mutex<State>,
+}
+
+impl LatencyTracker {
+    pub fn new(config: &SloConfig) -> Result<Self> {
+        let tracker = Self {
+            state: Mutex::new(State {
+                window: DEFAULT_WINDOW,
+                min_samples: DEFAULT_MIN_SAMPLES,
+                thresholds: vec![],
+                flip_readiness: false,
+                samples: HashMap::new(),
+                breaches: vec![],
+            }),
+        };
+        tracker.configure(config)?;
+        Ok(tracker)
+    }
+
+    /// Applies new settings, keeping the samples recorded so far.
+    pub fn configure(&self, config: &SloConfig) -> Result<()> {
+        let thresholds = config
+            .thresholds
+            .iter()
+            .map(|threshold| {
+                if !(threshold.percentile > 0.0 && threshold.percentile <= 100.0) {
+                    anyhow::bail!(
+                        "SLO percentile {} for {} is not in (0, 100]",
+                        threshold.percentile,
+                        threshold.stage
+                    );
+                }
+                Ok(Threshold {
+                    stage: threshold.stage,
+                    percentile: threshold.percentile,
+                    max: parse_timeout(&threshold.max)
+                        .with_context(|| format!("SLO bound for {}", threshold.stage))?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut state = self.lock();
+        state.window = config.window.unwrap_or(DEFAULT_WINDOW).max(1);
+        state.min_samples = config.min_samples.unwrap_or(DEFAULT_MIN_SAMPLES);
+        state.thresholds = thresholds;
+        state.flip_readiness = config.flip_readiness;
+        let window = state.window;
+        for samples in state.samples.values_mut() {
+            while samples.len() > window {
+                samples.pop_front();
+            }
+        }
+        state.breaches = evaluate(&state);
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Records one job's stage latencies and returns the breaches this
+    /// started or cleared.
+    pub fn record(&self, samples: &[(Stage, Duration)]) -> Vec<SloAlert> {
+        let mut state = self.lock();
+        let window = state.window;
+        for (stage, latency) in samples {
+            let stage_samples = state.samples.entry(*stage).or_default();
+            stage_samples.push_back(*latency);
+            while stage_samples.len() > window {
+                stage_samples.pop_front();
+            }
+        }
+        let current = evaluate(&state);
+        let same = |a: &Breach, b: &Breach| a.stage == b.stage && a.percentile == b.percentile;
+        let mut alerts: Vec<SloAlert> = current
+            .iter()
+            .filter(|breach| !state.breaches.iter().any(|open| same(open, breach)))
+            .map(|breach| SloAlert {
+                breach: breach.clone(),
+                resolved: false,
+            })
+            .collect();
+        alerts.extend(
+            state
+                .breaches
+                .iter()
+                .filter(|open| !current.iter().any(|breach| same(open, breach)))
+                .map(|open| SloAlert {
+                    breach: open.clone(),
+                    resolved: true,
+                }),
+        );
+        state.breaches = current;
+        alerts
+    }
+
+    /// Whether the service should report ready: always, unless readiness
+    /// follows the SLOs and one is breached.
+    pub fn ready(&self) -> bool {
+        let state = self.lock();
+        !state.flip_readiness || state.breaches.is_empty()
+    }
+
+    pub fn report(&self) -> LatencyReport {
+        let state = self.lock();
+        let stages = state
+            .samples
+            .iter()
+            .map(|(stage, samples)| {
+                let sorted = sorted(samples);
+                let ms = |percentile: f64| millis(nearest_rank(&sorted, percentile));
+                (
+                    *stage,
+                    StageLatency {
+                        samples: sorted.len(),
+                        p50_ms: ms(50.0),
+                        p90_ms: ms(90.0),
+                        p99_ms: ms(99.0),
+                        max_ms: ms(100.0),
+                    },
+                )
+            })
+            .collect();
+        LatencyReport {
+            window: state.window,
+            stages,
+            breaches: state.breaches.clone(),
+            ready: !state.flip_readiness || state.breaches.is_empty(),
+        }
+    }
+}
+
+fn evaluate(state: &State) -> Vec<Breach> {
+    state
+        .thresholds
+        .iter()
+        .filter_map(|threshold| {
+            let samples = state.samples.get(&threshold.stage)?;
+            if samples.is_empty() || samples.len() < state.min_samples {
+                return None;
+            }
+            let observed = nearest_rank(&sorted(samples), threshold.percentile);
+            (observed > threshold.max).then(|| Breach {
+                stage: threshold.stage,
+                percentile: threshold.percentile,
+                observed_ms: millis(observed),
+                max_ms: millis(threshold.max),
+            })
+        })
+        .collect()
+}
+
+fn sorted(samples: &VecDeque<Duration>) -> Vec<Duration> {
+    let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+    sorted.sort_unstable();
+    sorted
+}
+
+/// Nearest-rank percentile of ascending `sorted`; zero when empty.
+fn nearest_rank(sorted: &[Duration], percentile: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
@@ -0,0 +1,148 @@
+//! Sparse witness encoding and the committed sparsity pattern.
+//!
+//! A sparse witness lists, per vector, the component indices where the
+//! folded or pq value is nonzero together with both values. Every other
+//! component is zero in both vectors and contributes nothing to the
+//! residual, so a circuit keyed with `nonzeros` lays out only those entries.
+//!
+//! Rows are padded to the keyed `nonzeros` with index `dim` and zero values.
+//! The padded index list is committed with Poseidon under
+//! [`SPARSITY_DOMAIN`] and exposed as `sparsityCommitment`, so the proof
+//! states which components the residuals were taken over.
+
+use anyhow::Result;
+use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::poseidon::{domain_capacity, hash_with_capacity, SPARSITY_DOMAIN};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SparseRow {
+    /// Strictly increasing component indices below `dim`.
+    pub indices: Vec<u32>,
+    pub folded: Vec<f64>,
+    pub pq: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SparseVectors {
+    pub dim: usize,
+    pub rows: Vec<SparseRow>,
+}
+
+impl SparseVectors {
+    /// Keeps the entries where either vector is nonzero.
+    pub fn from_dense(folded: &[Vec<f64>], pq: &[Vec<f64>]) -> Result<Self> {
+        if folded.len() != pq.len() {
+            anyhow::bail!(
+                "{} folded vectors but {} pq vectors",
+                folded.len(),
+                pq.len()
+            );
+        }
+        let dim = folded.first().map(Vec::len).unwrap_or(0);
+        let mut rows = Vec::with_capacity(folded.len());
+        for (row_idx, (folded_row, pq_row)) in folded.iter().zip(pq).enumerate() {
+            if folded_row.len() != dim || pq_row.len() != dim {
+                anyhow::bail!("row {row_idx}: expected {dim} folded and pq components");
+            }
+            let mut row = SparseRow::default();
+            for (idx, (a, b)) in folded_row.iter().zip(pq_row).enumerate() {
+                if *a != 0.0 || *b != 0.0 {
+                    row.indices.push(idx as u32);
+                    row.folded.push(*a);
+                    row.pq.push(*b);
+                }
+            }
+            rows.push(row);
+        }
+        Ok(Self { dim, rows })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (row_idx, row) in self.rows.iter().enumerate() {
+            if row.folded.len() != row.indices.len() || row.pq.len() != row.indices.len() {
+                anyhow::bail!(
+                    "sparse row {row_idx}: {} indices but {} folded and {} pq values",
+                    row.indices.len(),
+                    row.folded.len(),
+                    row.pq.len()
+                );
+            }
+            let mut previous = None;
+            for index in &row.indices {
+                if *index as usize >= self.dim || previous.is_some_and(|prev| prev >= *index) {
+                    anyhow::bail!(
+                        "sparse row {row_idx}: indices must increase strictly and stay below {}",
+                        self.dim
+                    );
+                }
+                previous = Some(*index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Most nonzero entries in any row; the smallest `nonzeros` to key for.
+    pub fn max_nonzeros(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|row| row.indices.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn to_dense(&self) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let mut folded = vec![vec![0.0; self.dim]; self.rows.len()];
+        let mut pq = vec![vec![0.0; self.dim]; self.rows.len()];
+        for (row_idx, row) in self.rows.iter().enumerate() {
+            for (entry, index) in row.indices.iter().enumerate() {
+                folded[row_idx][*index as usize] = row.folded[entry];
+                pq[row_idx][*index as usize] = row.pq[entry];
+            }
+        }
+        (folded, pq)
+    }
+
+    /// Rows padded to `nonzeros` entries: `(indices, folded, pq)`.
+    pub fn padded(&self, nonzeros: usize) -> Result<(Vec<Vec<u32>>, Vec<Vec<f64>>, Vec<Vec<f64>>)> {
+        let mut indices = Vec::with_capacity(self.rows.len());
+        let mut folded = Vec::with_capacity(self.rows.len());
+        let mut pq = Vec::with_capacity(self.rows.len());
+        for (row_idx, row) in self.rows.iter().enumerate() {
+            if row.indices.len() > nonzeros {
+                anyhow::bail!(
+                    "sparse row {row_idx} has {} nonzeros, keyed for at most {nonzeros}",
+                    row.indices.len()
+                );
+            }
+            let padding = nonzeros - row.indices.len();
+            let mut row_indices = row.indices.clone();
+            row_indices.extend(std::iter::repeat(self.dim as u32).take(padding));
+            let mut row_folded = row.folded.clone();
+            row_folded.extend(std::iter::repeat(0.0).take(padding));
+            let mut row_pq = row.pq.clone();
+            row_pq.extend(std::iter::repeat(0.0).take(padding));
+            indices.push(row_indices);
+            folded.push(row_folded);
+            pq.push(row_pq);
+        }
+        Ok((indices, folded, pq))
+    }
+}
+
+/// Flattens padded indices row-major into field elements.
+pub fn indices_to_fields(indices: &[Vec<u32>]) -> Vec<Fr> {
+    indices
+        .iter()
+        .flat_map(|row| row.iter().map(|index| Fr::from(*index as u64)))
+        .collect()
+}
+
+/// `sparsityCommitment` over padded indices, matching the in-circuit hash.
+pub fn sparsity_commitment(indices: &[Vec<u32>]) -> Fr {
+    let flat = indices_to_fields(indices);
+    hash_with_capacity(domain_capacity(SPARSITY_DOMAIN, flat.len()), &flat)
+}
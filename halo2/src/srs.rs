@@ -0,0 +1,155 @@
+//! Trusted-setup files for keygen.
+//!
+//! Keys built from a config's seed rerun `ParamsKZG::setup` on a
+//! reproducible toxic waste, which is slow and lets anyone holding the
+//! config forge proofs. A trusted setup is read instead from either:
+//!
+//! * halo2 params (`ParamsKZG::write`, as the `.srs` files converted from
+//!   the Perpetual Powers of Tau are distributed), or
+//! * a snarkjs `.ptau` file, of which the first `2^k` `tau·G1` powers and
+//!   the first two `tau·G2` powers are taken.
+//!
+//! Either is reduced to the requested `k`. [`setup_fingerprint`] identifies
+//! the ceremony independently of `k`, and is what key configs record.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use halo2_proofs::poly::{commitment::Params, kzg::commitment::ParamsKZG};
+use halo2curves::{
+    bn256::{Bn256, G1Affine, G2Affine},
+    group::GroupEncoding,
+    serde::SerdeObject,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    errors::{Coded, ErrorCode},
+    hashing::digest_hex,
+    platform::normalize,
+};
+
+/// Leading bytes of a snarkjs powers-of-tau file.
+pub const PTAU_MAGIC: &[u8; 4] = b"ptau";
+
+const PTAU_HEADER: u32 = 1;
+const PTAU_TAU_G1: u32 = 2;
+const PTAU_TAU_G2: u32 = 3;
+/// Bytes of a bn254 base field element in a `.ptau` file.
+const PTAU_N8: usize = 32;
+
+/// Reads the trusted setup at `path`, reduced to `k`.
+pub fn load_srs(path: &Path, k: u32) -> Result<ParamsKZG<Bn256>> {
+    let path = normalize(path);
+    let bytes = std::fs::read(&path).with_context(|| format!("opening {:?}", path))?;
+    read_srs(&bytes, k).with_context(|| format!("reading trusted setup {:?}", path))
+}
+
+/// Reads halo2 params or a `.ptau` file, reduced to `k`.
+pub fn read_srs(bytes: &[u8], k: u32) -> Result<ParamsKZG<Bn256>> {
+    if bytes.starts_with(PTAU_MAGIC) {
+        return read_ptau(bytes, k);
+    }
+    let mut params = ParamsKZG::<Bn256>::read(&mut &bytes[..])?;
+    if params.k() < k {
+        anyhow::bail!(too_small(params.k(), k));
+    }
+    if params.k() > k {
+        params.downsize(k);
+    }
+    Ok(params)
+}
+
+/// Serializes `params` in the halo2 format [`read_srs`] reads.
+pub fn write_srs(params: &ParamsKZG<Bn256>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    params.write(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// blake3 of `g2 || s_g2`: the same for every `k` of one ceremony.
+pub fn setup_fingerprint(params: &ParamsKZG<Bn256>) -> String {
+    let mut bytes = params.g2().to_bytes().as_ref().to_vec();
+    bytes.extend_from_slice(params.s_g2().to_bytes().as_ref());
+    digest_hex(&bytes)
+}
+
+fn read_ptau(bytes: &[u8], k: u32) -> Result<ParamsKZG<Bn256>> {
+    let mut rest = &bytes[PTAU_MAGIC.len()..];
+    let _version = take_u32(&mut rest)?;
+    let sections = take_u32(&mut rest)?;
+    let (mut header, mut tau_g1, mut tau_g2) = (None, None, None);
+    for _ in 0..sections {
+        let kind = take_u32(&mut rest)?;
+        let size = usize::try_from(take_u64(&mut rest)?)?;
+        let body = take(&mut rest, size)?;
+        match kind {
+            PTAU_HEADER => header = Some(body),
+            PTAU_TAU_G1 => tau_g1 = Some(body),
+            PTAU_TAU_G2 => tau_g2 = Some(body),
+            _ => {}
+        }
+    }
+    let mut header = header.context("ptau file has no header section")?;
+    let n8 = take_u32(&mut header)? as usize;
+    if n8 != PTAU_N8 {
+        anyhow::bail!("ptau field elements are {n8} bytes, bn254 needs {PTAU_N8}");
+    }
+    take(&mut header, n8)?;
+    let power = take_u32(&mut header)?;
+    if power < k {
+        anyhow::bail!(too_small(power, k));
+    }
+
+    // Points are stored uncompressed in Montgomery form, little-endian,
+    // which is the raw layout of the halo2curves types.
+    let n = 1usize << k;
+    let g1_len = 2 * PTAU_N8;
+    let tau_g1 = tau_g1.context("ptau file has no tauG1 section")?;
+    let g = (0..n)
+        .map(|i| {
+            let raw = tau_g1
+                .get(i * g1_len..(i + 1) * g1_len)
+                .context("ptau tauG1 section is truncated")?;
+            G1Affine::from_raw_bytes(raw).with_context(|| format!("tauG1[{i}] is not on bn254"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let g2_len = 4 * PTAU_N8;
+    let tau_g2 = tau_g2.context("ptau file has no tauG2 section")?;
+    let g2_at = |i: usize| {
+        let raw = tau_g2
+            .get(i * g2_len..(i + 1) * g2_len)
+            .context("ptau tauG2 section is truncated")?;
+        G2Affine::from_raw_bytes(raw).with_context(|| format!("tauG2[{i}] is not on bn254"))
+    };
+    let (g2, s_g2) = (g2_at(0)?, g2_at(1)?);
+    // `from_parts` only borrows an instance for its type and computes the
+    // Lagrange basis from `g`.
+    let template = ParamsKZG::<Bn256>::setup(1, ChaCha20Rng::from_seed([0; 32]));
+    Ok(template.from_parts(k, g, None, g2, s_g2))
+}
+
+fn too_small(setup_k: u32, k: u32) -> Coded {
+    Coded::new(
+        ErrorCode::KeyMismatch,
+        format!("trusted setup only supports k≤{setup_k}, keys need k={k}"),
+    )
+}
+
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if rest.len() < len {
+        anyhow::bail!("ptau file is truncated");
+    }
+    let (head, tail) = rest.split_at(len);
+    *rest = tail;
+    Ok(head)
+}
+
+fn take_u32(rest: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(take(rest, 4)?.try_into()?))
+}
+
+fn take_u64(rest: &mut &[u8]) -> Result<u64> {
+    Ok(u64::from_le_bytes(take(rest, 8)?.try_into()?))
+}
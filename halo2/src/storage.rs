@@ -0,0 +1,393 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    errors::{Coded, ErrorCode},
+    platform::normalize,
+};
+
+/// Byte-oriented artifact store used for keys, witnesses, public inputs and proofs.
+///
+/// Keys are `/`-separated strings; each backend decides how they map onto its namespace.
+pub trait Storage: Send + Sync {
+    fn read(&self, key: &str) -> Result<Vec<u8>>;
+    fn write(&self, key: &str, bytes: &[u8]) -> Result<()>;
+    /// Writes `key` only if it does not exist yet and reports whether it did.
+    /// Of several callers racing on one key exactly one sees `true`; the
+    /// default falls back to check-then-write for backends without an
+    /// exclusive create.
+    fn write_new(&self, key: &str, bytes: &[u8]) -> Result<bool> {
+        if self.exists(key)? {
+            return Ok(false);
+        }
+        self.write(key, bytes)?;
+        Ok(true)
+    }
+    fn exists(&self, key: &str) -> Result<bool>;
+    fn delete(&self, key: &str) -> Result<()>;
+    /// Keys directly under the directory `prefix`, as `prefix/name`, sorted;
+    /// keys nested deeper are not listed.
+    fn list(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+/// Storage rooted at a local directory. Keys of a `LocalStorage::new` store
+/// must stay under its root; `LocalStorage::default()` has no root and takes
+/// keys as plain paths, absolute ones included.
+#[derive(Debug, Clone, Default)]
+pub struct LocalStorage {
+    root: Option<PathBuf>,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(root.into()),
+        }
+    }
+
+    /// The file behind `key`, rejecting absolute keys and `..` components
+    /// when the store has a root.
+    pub fn path_for(&self, key: &str) -> Result<PathBuf> {
+        let Some(root) = &self.root else {
+            return Ok(normalize(Path::new(key)));
+        };
+        let confined = Path::new(key)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !confined {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!("storage key {key:?} escapes {root:?}")
+            ));
+        }
+        Ok(normalize(&root.join(key)))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.path_for(key)?;
+        fs::read(&path).with_context(|| format!("opening {:?}", path))
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("creating {:?}", parent))?;
+        }
+        write_atomic(&path, bytes)
+    }
+
+    fn write_new(&self, key: &str, bytes: &[u8]) -> Result<bool> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("creating {:?}", parent))?;
+        }
+        let mut file = AtomicFile::create(&path)?;
+        file.write_all(bytes)
+            .with_context(|| format!("writing {:?}", path))?;
+        file.commit_new()
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.path_for(key)?.exists())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path_for(key)?;
+        fs::remove_file(&path).with_context(|| format!("removing {:?}", path))
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut dir = self.path_for(prefix)?;
+        if dir.as_os_str().is_empty() {
+            dir = PathBuf::from(".");
+        }
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&dir).with_context(|| format!("listing {:?}", dir))? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                keys.push(join_key(prefix, &name));
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// Process-local storage, mainly for tests and embedding.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    entries: RwLock<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> Result<Vec<u8>> {
+        let entries = self.entries.read().expect("storage lock poisoned");
+        entries
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no entry for key {key}"))
+    }
+
+    fn write(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let mut entries = self.entries.write().expect("storage lock poisoned");
+        entries.insert(key.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn write_new(&self, key: &str, bytes: &[u8]) -> Result<bool> {
+        let mut entries = self.entries.write().expect("storage lock poisoned");
+        if entries.contains_key(key) {
+            return Ok(false);
+        }
+        entries.insert(key.to_string(), bytes.to_vec());
+        Ok(true)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        let entries = self.entries.read().expect("storage lock poisoned");
+        Ok(entries.contains_key(key))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let mut entries = self.entries.write().expect("storage lock poisoned");
+        entries
+            .remove(key)
+            .map(|_| ())
+            .ok_or_else(|| anyhow::anyhow!("no entry for key {key}"))
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let entries = self.entries.read().expect("storage lock poisoned");
+        let dir = prefix.trim_end_matches('/');
+        Ok(entries
+            .keys()
+            .filter_map(|key| {
+                let name = match dir {
+                    "" => key.as_str(),
+                    dir => key.strip_prefix(dir)?.strip_prefix('/')?,
+                };
+                (!name.is_empty() && !name.contains('/')).then(|| join_key(prefix, name))
+            })
+            .collect())
+    }
+}
+
+/// Converts a filesystem path into a key understood by [`LocalStorage::default`].
+pub fn path_key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+pub(crate) fn join_key(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", prefix.trim_end_matches('/'), name)
+    }
+}
+
+/// A file written under a temporary name next to `path` and renamed over it
+/// on [`AtomicFile::commit`], so readers see either the previous contents or
+/// the complete new ones, never a truncated file. Dropping it uncommitted
+/// removes the temporary file.
+#[derive(Debug)]
+pub struct AtomicFile {
+    path: PathBuf,
+    temp: PathBuf,
+    file: Option<File>,
+    committed: bool,
+}
+
+/// Distinguishes temporary files of concurrent writers within one process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl AtomicFile {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = normalize(path.as_ref());
+        let name = path
+            .file_name()
+            .with_context(|| format!("{:?} has no file name", path))?;
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(
+            ".{}-{}.tmp",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp = path.with_file_name(temp_name);
+        let file = File::create(&temp).with_context(|| format!("creating {:?}", temp))?;
+        Ok(Self {
+            path,
+            temp,
+            file: Some(file),
+            committed: false,
+        })
+    }
+
+    /// Flushes the contents to disk, renames the file into place and syncs
+    /// the directory so the rename itself survives a crash.
+    pub fn commit(mut self) -> Result<()> {
+        self.close()?;
+        fs::rename(&self.temp, &self.path)
+            .with_context(|| format!("renaming {:?} to {:?}", self.temp, self.path))?;
+        self.committed = true;
+        sync_parent(&self.path)
+    }
+
+    /// Like [`AtomicFile::commit`], but leaves an existing file in place and
+    /// returns `false` instead. The file is hard-linked into place, which
+    /// fails atomically when the name is taken.
+    pub fn commit_new(mut self) -> Result<bool> {
+        self.close()?;
+        match fs::hard_link(&self.temp, &self.path) {
+            Ok(()) => {
+                sync_parent(&self.path)?;
+                Ok(true)
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Ok(false),
+            Err(err) => {
+                Err(err).with_context(|| format!("linking {:?} to {:?}", self.temp, self.path))
+            }
+        }
+    }
+
+    /// Syncs and closes the temporary file; Windows cannot rename open files.
+    fn close(&mut self) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            file.sync_all()
+                .with_context(|| format!("syncing {:?}", self.temp))?;
+        }
+        Ok(())
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("atomic file already closed"))
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file()?.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        self.file.take();
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Writes `bytes` to `path` through an [`AtomicFile`].
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(bytes)
+        .with_context(|| format!("writing {:?}", path))?;
+    file.commit()
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> Result<()> {
+    let parent = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(parent) => parent,
+        None => Path::new("."),
+    };
+    File::open(parent)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("syncing {:?}", parent))
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(storage: &dyn Storage) {
+        for key in [
+            "a.json",
+            "dir/b.json",
+            "dir/c.json",
+            "dir/sub/d.json",
+            "dirx/e.json",
+        ] {
+            storage.write(key, b"{}").unwrap();
+        }
+    }
+
+    #[test]
+    fn local_and_memory_list_alike() {
+        let root = std::env::temp_dir().join(format!("yysfold-storage-{}", std::process::id()));
+        let local = LocalStorage::new(&root);
+        let memory = MemoryStorage::new();
+        fill(&local);
+        fill(&memory);
+        for prefix in ["", "dir", "dir/", "dir/sub", "missing"] {
+            assert_eq!(
+                local.list(prefix).unwrap(),
+                memory.list(prefix).unwrap(),
+                "{prefix:?}"
+            );
+        }
+        assert_eq!(memory.list("dir").unwrap(), ["dir/b.json", "dir/c.json"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn rooted_storage_rejects_escaping_keys() {
+        let root = std::env::temp_dir().join(format!("yysfold-rooted-{}", std::process::id()));
+        let rooted = LocalStorage::new(&root);
+        let outside = std::env::temp_dir().join("outside.json");
+        let outside = path_key(&outside);
+        for key in [
+            "../outside.json",
+            "dir/../../outside.json",
+            outside.as_str(),
+        ] {
+            assert!(rooted.path_for(key).is_err(), "{key:?}");
+            assert!(rooted.write(key, b"{}").is_err(), "{key:?}");
+            assert!(rooted.read(key).is_err(), "{key:?}");
+        }
+        assert!(rooted.list("..").is_err());
+        assert_eq!(
+            rooted.path_for("./dir/a.json").unwrap(),
+            normalize(&root.join("./dir/a.json"))
+        );
+        assert_eq!(
+            LocalStorage::default().path_for(&outside).unwrap(),
+            normalize(Path::new(&outside))
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Explorer-friendly summaries of verified block proofs.
+//!
+//! A summary flattens what a chain dashboard shows for a block (height,
+//! state roots, commitments, proof size and when the proof was checked) into
+//! one JSON object, so explorers need not parse public inputs, metadata
+//! sidecars and shard manifests themselves. Blocks are only summarized after
+//! their proof verifies.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    bytes::Hash256, compat::CIRCUIT_VERSION, hashing::digest, metadata::ProofMetadataV1,
+    public_inputs::ParsedPublicInputs, shard::ShardManifest, transcript::TranscriptKind,
+    verify::VerifierKeys,
+};
+
+pub const SUMMARY_FORMAT_VERSION: u32 = 1;
+
+/// A block's optional commitments, present when its circuit exposes them.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryCommitments {
+    pub folded: Hash256,
+    pub pq: Hash256,
+    pub codebook_root: Hash256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folded_vector_root: Option<Hash256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pq_codes: Option<Hash256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub witness: Option<Hash256>,
+}
+
+/// The shard manifest a proof was combined into.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateMembership {
+    pub manifest_root: Hash256,
+    pub block_height: u64,
+    pub shard_index: usize,
+    pub shard_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSummary {
+    pub height: u64,
+    pub prev_state_root: Hash256,
+    pub new_state_root: Hash256,
+    pub tx_merkle_root: Hash256,
+    pub commitments: SummaryCommitments,
+    pub proof_bytes: usize,
+    /// blake3 of the proof bytes.
+    pub proof_digest: Hash256,
+    pub circuit_version: u32,
+    #[serde(skip_serializing_if = "TranscriptKind::is_default")]
+    pub transcript: TranscriptKind,
+    /// Unix seconds the proof was created, from its metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proved_at: Option<u64>,
+    /// Unix seconds the proof verified for this summary.
+    pub verified_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_proof_digest: Option<Hash256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<AggregateMembership>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryExport {
+    pub format_version: u32,
+    /// Blocks in height order.
+    pub blocks: Vec<BlockSummary>,
+}
+
+/// Verifies `proof` for `public_inputs` and summarizes the block. The proof
+/// is verified under the transcript `metadata` records, and `manifests` are
+/// searched for the shard entry holding it.
+pub fn summarize(
+    public_inputs: &ParsedPublicInputs,
+    proof: &[u8],
+    metadata: Option<&ProofMetadataV1>,
+    keys: &VerifierKeys,
+    manifests: &[ShardManifest],
+) -> Result<BlockSummary> {
+    let transcript = match metadata {
+        Some(metadata) => {
+            metadata.check_compatibility()?;
+            metadata.check_scale(keys.circuit.scale())?;
+            metadata.transcript
+        }
+        None => TranscriptKind::default(),
+    };
+    let instances = public_inputs.to_instances(&keys.circuit)?;
+    keys.verify_in(&instances, proof, transcript)
+        .with_context(|| format!("block {} rejected", public_inputs.block_height))?;
+    let verified_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let proof_digest = digest(proof);
+    let aggregate = manifests.iter().find_map(|manifest| {
+        let entry = manifest
+            .shards
+            .iter()
+            .find(|entry| entry.proof_digest == proof_digest)?;
+        Some(AggregateMembership {
+            manifest_root: manifest.root,
+            block_height: manifest.block_height,
+            shard_index: entry.index,
+            shard_count: manifest.shards.len(),
+        })
+    });
+    Ok(BlockSummary {
+        height: public_inputs.block_height,
+        prev_state_root: public_inputs.prev_state_root,
+        new_state_root: public_inputs.new_state_root,
+        tx_merkle_root: public_inputs.tx_merkle_root,
+        commitments: SummaryCommitments {
+            folded: public_inputs.folded_commitment,
+            pq: public_inputs.pq_commitment,
+            codebook_root: public_inputs.codebook_root,
+            folded_vector_root: public_inputs.folded_vector_root,
+            pq_codes: public_inputs.pq_codes_commitment,
+            witness: public_inputs.witness_commitment,
+        },
+        proof_bytes: proof.len(),
+        proof_digest,
+        circuit_version: metadata
+            .map(|metadata| metadata.circuit_version)
+            .unwrap_or(CIRCUIT_VERSION),
+        transcript,
+        proved_at: metadata.map(|metadata| metadata.created_at),
+        verified_at,
+        previous_proof_digest: public_inputs.previous_proof_digest,
+        aggregate,
+    })
+}
+
+/// Wraps `blocks` for export, sorted by height.
+pub fn export(mut blocks: Vec<BlockSummary>) -> SummaryExport {
+    blocks.sort_by_key(|block| block.height);
+    SummaryExport {
+        format_version: SUMMARY_FORMAT_VERSION,
+        blocks,
+    }
+}
@@ -0,0 +1,375 @@
+//! Synthetic block generation for examples, smoke tests and onboarding, and
+//! public inputs templated from a witness for devnets.
+
+use anyhow::{Context, Result};
+use blake3::Hasher;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    bytes::Hash256,
+    codebook::{self, CommitMode},
+    delta,
+    epsilon::{covering_epsilons, covering_sla_bound, residual_cells, residual_commitment},
+    fixed_point::FixedPoint,
+    hiding::hiding_commitments,
+    io::{Provenance, WitnessData},
+    merkle,
+    opq::rotation_commitment,
+    permutation::{committed_rows, permutation_commitment},
+    prove::{fixed_residuals, to_field_matrix},
+    public_inputs::ParsedPublicInputs,
+    quantization::{codes_commitment, stage_codes, validate_pq_witness, CompressionStats, PqShape},
+    reconstruct::{ProductQuantizer, Reconstructor},
+    scalar::scalar_commitment,
+    sparse::sparsity_commitment,
+    vector_commitment::vector_commitments,
+    witness_commitment::witness_commitment,
+};
+
+#[derive(Debug, Clone)]
+pub struct SyntheticConfig {
+    pub vectors: usize,
+    pub dim: usize,
+    pub subvectors: usize,
+    pub centroids: usize,
+    pub block_height: u64,
+    pub seed: u64,
+    /// How `codebookRoot` is derived from the generated codebook.
+    pub codebook_mode: CommitMode,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            vectors: 8,
+            dim: 16,
+            subvectors: 4,
+            centroids: 4,
+            block_height: 1,
+            seed: 0,
+            codebook_mode: CommitMode::Merkle,
+        }
+    }
+}
+
+/// A generated block; the codebook and codes travel inside `witness`.
+#[derive(Debug, Clone)]
+pub struct SyntheticBlock {
+    pub witness: WitnessData,
+    pub public_inputs: ParsedPublicInputs,
+}
+
+/// Generates random embeddings, product-quantizes them against a codebook sampled
+/// from the data, and derives matching public inputs.
+pub fn generate(config: &SyntheticConfig) -> Result<SyntheticBlock> {
+    if config.vectors == 0 || config.dim == 0 {
+        anyhow::bail!("synthetic block needs at least one vector and one dimension");
+    }
+    if config.subvectors == 0 || config.dim % config.subvectors != 0 {
+        anyhow::bail!(
+            "dim {} is not divisible into {} subvectors",
+            config.dim,
+            config.subvectors
+        );
+    }
+    if config.centroids == 0 {
+        anyhow::bail!("synthetic block needs at least one centroid");
+    }
+    let mut rng = ChaCha20Rng::seed_from_u64(config.seed);
+    let sub_dim = config.dim / config.subvectors;
+
+    let folded_vectors: Vec<Vec<f64>> = (0..config.vectors)
+        .map(|_| (0..config.dim).map(|_| rng.gen_range(-1.0..1.0)).collect())
+        .collect();
+
+    let codebook: Vec<Vec<Vec<f64>>> = (0..config.subvectors)
+        .map(|subspace| {
+            (0..config.centroids)
+                .map(|centroid| {
+                    let row = &folded_vectors[centroid % folded_vectors.len()];
+                    row[subspace * sub_dim..(subspace + 1) * sub_dim].to_vec()
+                })
+                .collect()
+        })
+        .collect();
+
+    let codes: Vec<Vec<u32>> = folded_vectors
+        .iter()
+        .map(|row| {
+            codebook
+                .iter()
+                .enumerate()
+                .map(|(subspace, centroids)| {
+                    let segment = &row[subspace * sub_dim..(subspace + 1) * sub_dim];
+                    nearest_centroid(segment, centroids) as u32
+                })
+                .collect()
+        })
+        .collect();
+    let pq_vectors = ProductQuantizer::new(&codebook)?.reconstruct_rows(&codes)?;
+
+    let mut public_inputs = ParsedPublicInputs {
+        prev_state_root: random_hash(&mut rng),
+        new_state_root: random_hash(&mut rng),
+        block_height: config.block_height,
+        tx_merkle_root: random_hash(&mut rng),
+        folded_commitment: digest_rows(&folded_vectors),
+        pq_commitment: digest_rows(&pq_vectors),
+        codebook_root: codebook::commit(&codebook, config.codebook_mode).hash(),
+        folded_vector_root: Some(Hash256::from_field(&merkle::vector_root(&to_field_matrix(
+            &folded_vectors,
+        )))),
+        pq_codes_commitment: Some(Hash256::from_field(&codes_commitment(&codes))),
+        previous_proof_digest: Some(random_hash(&mut rng)),
+        beacon_value: Some(random_hash(&mut rng)),
+        sparsity_commitment: None,
+        previous_vector_root: None,
+        witness_commitment: None,
+        rotation_commitment: None,
+        residual_codebook_root: None,
+        scalar_commitment: None,
+        permutation_commitment: None,
+        compression_stats: Some(CompressionStats::new(
+            config.vectors,
+            PqShape {
+                dim: config.dim,
+                subvectors: config.subvectors,
+                centroids: config.centroids,
+            },
+        )),
+        subvector_epsilons: Some(covering_epsilons(
+            &folded_vectors,
+            &pq_vectors,
+            config.subvectors,
+        )),
+        sla_bound: Some(covering_sla_bound(
+            FixedPoint::default(),
+            &folded_vectors,
+            &pq_vectors,
+        )),
+        residual_commitment: None,
+    };
+
+    let residuals = fixed_residuals(&folded_vectors, &pq_vectors);
+    public_inputs.residual_commitment = Some(Hash256::from_field(&residual_commitment(
+        &residual_cells(&residuals, FixedPoint::default(), config.vectors),
+    )));
+    let witness = WitnessData {
+        folded_vectors,
+        pq_vectors,
+        header_rlp: None,
+        pq_codes: Some(codes),
+        codebook: Some(codebook),
+        sparse_vectors: None,
+        provenance: Some(Provenance {
+            generator_version: Some(format!("yysfold-synthetic/{}", env!("CARGO_PKG_VERSION"))),
+            model_id: None,
+            codebook_id: Some(public_inputs.codebook_root.to_hex()),
+        }),
+        delta: None,
+        audit_salt: Some(random_hash(&mut rng)),
+        residuals: Some(residuals),
+        scale: None,
+        rotation: None,
+        residual_stage: None,
+        scalar_quantization: None,
+        row_permutation: None,
+        blinding: None,
+    };
+    public_inputs.witness_commitment = Some(Hash256::from_field(&witness_commitment(&witness)?));
+    Ok(SyntheticBlock {
+        witness,
+        public_inputs,
+    })
+}
+
+/// Choices [`template_public_inputs`] cannot read off the witness.
+#[derive(Debug, Clone)]
+pub struct TemplateOptions {
+    pub block_height: u64,
+    /// How `codebookRoot` is derived from the witness codebook.
+    pub codebook_mode: CommitMode,
+    /// Padding for `sparsityCommitment`; defaults to the witness's widest row,
+    /// as `prover --sparse` keys it.
+    pub nonzeros: Option<usize>,
+    /// Derive `foldedCommitment` and `pqCommitment` as the Poseidon
+    /// commitments `prover --vector-commitments` checks.
+    pub vector_commitments: bool,
+    /// `slaBound` to commit to; defaults to the smallest bound the witness
+    /// satisfies.
+    pub sla_bound: Option<f64>,
+    /// Changed-row capacity the circuit is keyed for, which pads
+    /// `residualCommitment`; defaults to the witness's rows.
+    pub delta: Option<usize>,
+}
+
+impl Default for TemplateOptions {
+    fn default() -> Self {
+        Self {
+            block_height: 1,
+            codebook_mode: CommitMode::Merkle,
+            nonzeros: None,
+            vector_commitments: false,
+            sla_bound: None,
+            delta: None,
+        }
+    }
+}
+
+/// Public inputs consistent with `witness`, for devnets without a chain.
+///
+/// Every commitment the prover checks is computed from the witness; values
+/// it cannot check are fabricated from a fixed pattern, a tag byte repeated
+/// and the block height in the last 8 bytes, so consecutive heights chain
+/// (`newStateRoot` of block `h` is `prevStateRoot` of `h + 1`):
+///
+/// ```text
+/// prevStateRoot        0xaaaa..aa || be64(h - 1)
+/// newStateRoot         0xaaaa..aa || be64(h)
+/// txMerkleRoot         0xbbbb..bb || be64(h)
+/// previousProofDigest  0xcccc..cc || be64(h - 1)
+/// beaconValue          0xdddd..dd || be64(h)
+/// codebookRoot         0xeeee..ee || be64(0)   without a witness codebook
+/// ```
+pub fn template_public_inputs(
+    witness: &WitnessData,
+    options: &TemplateOptions,
+) -> Result<ParsedPublicInputs> {
+    let height = options.block_height;
+    let fixed_point = witness.fixed_point()?;
+    let folded = fixed_point
+        .to_field_matrix(&witness.folded_vectors)
+        .context("foldedVectors")?;
+    let (previous_vector_root, folded_vector_root) = match &witness.delta {
+        Some(section) => {
+            let (_, root) = delta::updates(section, &folded, folded.len())?;
+            (Some(section.previous_vector_root), root)
+        }
+        None => (
+            None,
+            merkle::root(committed_rows(witness, &merkle::leaves(&folded))),
+        ),
+    };
+    let pq_shape = match (&witness.pq_codes, &witness.codebook) {
+        (Some(_), Some(_)) => Some(validate_pq_witness(witness)?),
+        _ => None,
+    };
+    let [folded_commitment, pq_commitment] = match &witness.blinding {
+        Some(_) => hiding_commitments(witness)?,
+        None if options.vector_commitments => vector_commitments(witness)?,
+        None => [
+            digest_rows(&witness.folded_vectors),
+            digest_rows(&witness.pq_vectors),
+        ],
+    };
+    let sparsity_commitment = match &witness.sparse_vectors {
+        Some(sparse) => {
+            let nonzeros = options
+                .nonzeros
+                .unwrap_or_else(|| sparse.max_nonzeros().max(1));
+            let (indices, _, _) = sparse.padded(nonzeros)?;
+            Some(Hash256::from_field(&sparsity_commitment(&indices)))
+        }
+        None => None,
+    };
+    let residuals = residual_cells(
+        &fixed_point.residuals(&witness.folded_vectors, &witness.pq_vectors),
+        fixed_point,
+        options.delta.unwrap_or(witness.folded_vectors.len()),
+    );
+    Ok(ParsedPublicInputs {
+        prev_state_root: devnet_root(0xaa, height.saturating_sub(1)),
+        new_state_root: devnet_root(0xaa, height),
+        block_height: height,
+        tx_merkle_root: devnet_root(0xbb, height),
+        folded_commitment,
+        pq_commitment,
+        codebook_root: match &witness.codebook {
+            Some(codebook) => codebook::commit(codebook, options.codebook_mode).hash(),
+            None => devnet_root(0xee, 0),
+        },
+        folded_vector_root: Some(Hash256::from_field(&folded_vector_root)),
+        pq_codes_commitment: witness
+            .pq_codes
+            .as_ref()
+            .map(|_| Hash256::from_field(&codes_commitment(&stage_codes(witness)))),
+        previous_proof_digest: Some(devnet_root(0xcc, height.saturating_sub(1))),
+        beacon_value: Some(devnet_root(0xdd, height)),
+        sparsity_commitment,
+        previous_vector_root,
+        witness_commitment: match witness.audit_salt {
+            Some(_) => Some(Hash256::from_field(&witness_commitment(witness)?)),
+            None => None,
+        },
+        rotation_commitment: witness
+            .rotation
+            .as_deref()
+            .map(|rotation| Hash256::from_field(&rotation_commitment(rotation))),
+        residual_codebook_root: witness
+            .residual_stage
+            .as_ref()
+            .map(|stage| codebook::commit(&stage.codebook, options.codebook_mode).hash()),
+        scalar_commitment: witness
+            .scalar_quantization
+            .as_ref()
+            .map(|scalar| Hash256::from_field(&scalar_commitment(scalar))),
+        permutation_commitment: witness.row_permutation.as_ref().map(|permutation| {
+            let order = permutation.order(witness.folded_vectors.len());
+            Hash256::from_field(&permutation_commitment(permutation, &order))
+        }),
+        compression_stats: pq_shape
+            .map(|shape| CompressionStats::new(witness.folded_vectors.len(), shape)),
+        subvector_epsilons: pq_shape.map(|shape| {
+            covering_epsilons(
+                &witness.folded_vectors,
+                &witness.pq_vectors,
+                shape.subvectors,
+            )
+        }),
+        sla_bound: Some(options.sla_bound.unwrap_or_else(|| {
+            covering_sla_bound(fixed_point, &witness.folded_vectors, &witness.pq_vectors)
+        })),
+        residual_commitment: Some(Hash256::from_field(&residual_commitment(&residuals))),
+    })
+}
+
+fn devnet_root(tag: u8, value: u64) -> Hash256 {
+    let mut bytes = [tag; 32];
+    bytes[24..].copy_from_slice(&value.to_be_bytes());
+    Hash256(bytes)
+}
+
+fn nearest_centroid(segment: &[f64], centroids: &[Vec<f64>]) -> usize {
+    let mut best = 0;
+    let mut best_distance = f64::INFINITY;
+    for (idx, centroid) in centroids.iter().enumerate() {
+        let distance: f64 = segment
+            .iter()
+            .zip(centroid.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        if distance < best_distance {
+            best = idx;
+            best_distance = distance;
+        }
+    }
+    best
+}
+
+fn digest_rows(rows: &[Vec<f64>]) -> Hash256 {
+    let mut hasher = Hasher::new();
+    for row in rows {
+        hasher.update(&(row.len() as u64).to_le_bytes());
+        for value in row {
+            hasher.update(&value.to_le_bytes());
+        }
+    }
+    hasher.finalize().into()
+}
+
+fn random_hash(rng: &mut ChaCha20Rng) -> Hash256 {
+    let mut bytes = [0u8; 32];
+    rng.fill_bytes(&mut bytes);
+    Hash256(bytes)
+}
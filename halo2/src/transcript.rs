@@ -0,0 +1,244 @@
+//! Fiat-Shamir transcript choice for proving and verifying.
+//!
+//! * `blake2b`: the default, and what every proof without a recorded
+//!   transcript was made with.
+//! * `keccak256`: cheaper to replay in an EVM verifier.
+//! * `poseidon`: cheaper to replay inside another circuit, such as one that
+//!   aggregates inner proofs. Challenges come from the same Poseidon sponge
+//!   as [`crate::poseidon`], and G1 coordinates are absorbed as 88-bit limbs
+//!   so no absorbed value needs a non-native reduction.
+//!
+//! The kind is recorded in proof metadata; a proof only verifies under the
+//! transcript it was made with.
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    str::FromStr,
+};
+
+use anyhow::Result;
+use halo2_proofs::transcript::{
+    Challenge255, EncodedChallenge, Transcript, TranscriptRead, TranscriptReadBuffer,
+    TranscriptWrite, TranscriptWriterBuffer,
+};
+use halo2curves::{
+    bn256::{Fq, Fr, G1Affine},
+    ff::PrimeField,
+    group::GroupEncoding,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::poseidon::{domain_capacity, permute, RATE, TRANSCRIPT_DOMAIN, WIDTH};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptKind {
+    #[default]
+    Blake2b,
+    Keccak256,
+    Poseidon,
+}
+
+impl TranscriptKind {
+    pub fn is_default(&self) -> bool {
+        *self == TranscriptKind::Blake2b
+    }
+}
+
+impl fmt::Display for TranscriptKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TranscriptKind::Blake2b => "blake2b",
+            TranscriptKind::Keccak256 => "keccak256",
+            TranscriptKind::Poseidon => "poseidon",
+        })
+    }
+}
+
+impl FromStr for TranscriptKind {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw.to_ascii_lowercase().as_str() {
+            "blake2b" => Ok(TranscriptKind::Blake2b),
+            "keccak256" | "keccak" => Ok(TranscriptKind::Keccak256),
+            "poseidon" => Ok(TranscriptKind::Poseidon),
+            other => anyhow::bail!("unknown transcript {other:?} (blake2b, keccak256 or poseidon)"),
+        }
+    }
+}
+
+/// Bytes per limb of an absorbed base-field coordinate.
+const LIMB_BYTES: usize = 11;
+/// Limbs per coordinate: 88 + 88 + 80 bits covers the 254-bit base field.
+const LIMBS: usize = 3;
+
+/// Duplex Poseidon sponge: inputs queue until the next squeeze, which absorbs
+/// them two at a time and returns the first rate element.
+#[derive(Debug, Clone)]
+struct Sponge {
+    state: [Fr; WIDTH],
+    pending: Vec<Fr>,
+}
+
+impl Sponge {
+    fn new() -> Self {
+        Sponge {
+            state: [
+                domain_capacity(TRANSCRIPT_DOMAIN, 0),
+                Fr::zero(),
+                Fr::zero(),
+            ],
+            pending: Vec::new(),
+        }
+    }
+
+    fn absorb(&mut self, value: Fr) {
+        self.pending.push(value);
+    }
+
+    /// Absorbs both coordinates of `point`; the identity absorbs as zeros.
+    fn absorb_point(&mut self, point: &G1Affine) {
+        for coordinate in [&point.x, &point.y] {
+            for limb in limbs(coordinate) {
+                self.absorb(limb);
+            }
+        }
+    }
+
+    fn squeeze(&mut self) -> Fr {
+        if self.pending.is_empty() {
+            permute(&mut self.state);
+        }
+        for chunk in self.pending.chunks(RATE) {
+            self.state[1] += chunk[0];
+            if let Some(second) = chunk.get(1) {
+                self.state[2] += *second;
+            }
+            permute(&mut self.state);
+        }
+        self.pending.clear();
+        self.state[1]
+    }
+
+    /// A challenge whose scalar is exactly the squeezed element.
+    fn challenge(&mut self) -> Challenge255<G1Affine> {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.squeeze().to_repr().as_ref());
+        Challenge255::new(&bytes)
+    }
+}
+
+/// `value` as little-endian limbs of `LIMB_BYTES` bytes.
+fn limbs(value: &Fq) -> [Fr; LIMBS] {
+    let repr = value.to_repr();
+    let mut limbs = [Fr::zero(); LIMBS];
+    for (limb, chunk) in limbs.iter_mut().zip(repr.as_ref().chunks(LIMB_BYTES)) {
+        let mut bytes = [0u8; 16];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        *limb = Fr::from_u128(u128::from_le_bytes(bytes));
+    }
+    limbs
+}
+
+/// Poseidon transcript writer. Proof bytes are encoded as under blake2b, so
+/// proofs are the same size whichever transcript made them.
+#[derive(Debug, Clone)]
+pub struct PoseidonWrite<W> {
+    writer: W,
+    sponge: Sponge,
+}
+
+impl<W: Write> Transcript<G1Affine, Challenge255<G1Affine>> for PoseidonWrite<W> {
+    fn squeeze_challenge(&mut self) -> Challenge255<G1Affine> {
+        self.sponge.challenge()
+    }
+
+    fn common_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.sponge.absorb_point(&point);
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, scalar: Fr) -> io::Result<()> {
+        self.sponge.absorb(scalar);
+        Ok(())
+    }
+}
+
+impl<W: Write> TranscriptWrite<G1Affine, Challenge255<G1Affine>> for PoseidonWrite<W> {
+    fn write_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.common_point(point)?;
+        self.writer.write_all(point.to_bytes().as_ref())
+    }
+
+    fn write_scalar(&mut self, scalar: Fr) -> io::Result<()> {
+        self.common_scalar(scalar)?;
+        self.writer.write_all(scalar.to_repr().as_ref())
+    }
+}
+
+impl<W: Write> TranscriptWriterBuffer<W, G1Affine, Challenge255<G1Affine>> for PoseidonWrite<W> {
+    fn init(writer: W) -> Self {
+        PoseidonWrite {
+            writer,
+            sponge: Sponge::new(),
+        }
+    }
+
+    fn finalize(self) -> W {
+        self.writer
+    }
+}
+
+/// Poseidon transcript reader, the counterpart of [`PoseidonWrite`].
+#[derive(Debug, Clone)]
+pub struct PoseidonRead<R> {
+    reader: R,
+    sponge: Sponge,
+}
+
+impl<R: Read> Transcript<G1Affine, Challenge255<G1Affine>> for PoseidonRead<R> {
+    fn squeeze_challenge(&mut self) -> Challenge255<G1Affine> {
+        self.sponge.challenge()
+    }
+
+    fn common_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.sponge.absorb_point(&point);
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, scalar: Fr) -> io::Result<()> {
+        self.sponge.absorb(scalar);
+        Ok(())
+    }
+}
+
+impl<R: Read> TranscriptRead<G1Affine, Challenge255<G1Affine>> for PoseidonRead<R> {
+    fn read_point(&mut self) -> io::Result<G1Affine> {
+        let mut compressed = <G1Affine as GroupEncoding>::Repr::default();
+        self.reader.read_exact(compressed.as_mut())?;
+        let point: G1Affine = Option::from(G1Affine::from_bytes(&compressed))
+            .ok_or_else(|| io::Error::other("invalid point encoding in proof"))?;
+        self.common_point(point)?;
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<Fr> {
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        self.reader.read_exact(repr.as_mut())?;
+        let scalar: Fr = Option::from(Fr::from_repr(repr))
+            .ok_or_else(|| io::Error::other("invalid field element encoding in proof"))?;
+        self.common_scalar(scalar)?;
+        Ok(scalar)
+    }
+}
+
+impl<R: Read> TranscriptReadBuffer<R, G1Affine, Challenge255<G1Affine>> for PoseidonRead<R> {
+    fn init(reader: R) -> Self {
+        PoseidonRead {
+            reader,
+            sponge: Sponge::new(),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Resumable witness uploads for the proving service.
+//!
+//! A client declares the size and blake3 digest of a witness, then sends it
+//! in chunks, each at the offset the server reports having stored. A
+//! dropped connection only loses the chunk in flight: the client asks for
+//! the offset and continues from there. Uploads live in a [`Storage`] as
+//! an `{id}.json` declaration and the chunks stored so far under `{id}/`,
+//! each keyed by the offset it ends at, so they also survive a server
+//! restart. The last chunk triggers a digest check, and a mismatching upload
+//! is discarded.
+
+use std::{
+    fmt,
+    sync::{Mutex, MutexGuard},
+};
+
+use anyhow::{Context, Result};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    errors::{Coded, ErrorCode},
+    hashing::digest,
+    platform::from_json_slice,
+    storage::Storage,
+};
+
+/// What the client declares when starting an upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UploadDeclaration {
+    pub size: u64,
+    /// blake3 of the whole witness file.
+    pub digest: Hash256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadStatus {
+    pub id: String,
+    pub size: u64,
+    /// Bytes stored so far; the next chunk must start here.
+    pub offset: u64,
+    pub complete: bool,
+}
+
+/// Upload failures the service answers with their own status codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadError {
+    Unknown {
+        id: String,
+    },
+    /// The chunk does not start where the stored bytes end.
+    OffsetMismatch {
+        id: String,
+        stored: u64,
+        sent: u64,
+    },
+    Incomplete {
+        id: String,
+    },
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::Unknown { id } => write!(f, "unknown upload {id}"),
+            UploadError::OffsetMismatch { id, stored, sent } => write!(
+                f,
+                "upload {id} is at offset {stored}, chunk starts at {sent}"
+            ),
+            UploadError::Incomplete { id } => write!(f, "upload {id} is not complete"),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+pub struct UploadStore {
+    storage: Box<dyn Storage>,
+    max_size: u64,
+    lock: Mutex<()>,
+}
+
+impl UploadStore {
+    /// Keeps uploads in `storage`, refusing declarations over `max_size` bytes.
+    pub fn open(storage: Box<dyn Storage>, max_size: u64) -> Self {
+        Self {
+            storage,
+            max_size,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn create(&self, declaration: UploadDeclaration) -> Result<UploadStatus> {
+        if declaration.size == 0 || declaration.size > self.max_size {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!(
+                    "upload size must be between 1 and {} bytes, got {}",
+                    self.max_size, declaration.size
+                )
+            ));
+        }
+        let mut id = [0u8; 16];
+        OsRng.fill_bytes(&mut id);
+        let id = hex::encode(id);
+        let _guard = self.lock();
+        self.storage
+            .write(&declaration_key(&id), &serde_json::to_vec(&declaration)?)?;
+        Ok(UploadStatus {
+            id,
+            size: declaration.size,
+            offset: 0,
+            complete: false,
+        })
+    }
+
+    pub fn status(&self, id: &str) -> Result<UploadStatus> {
+        let _guard = self.lock();
+        let declaration = self.declaration(id)?;
+        self.status_of(id, &declaration)
+    }
+
+    /// Appends `chunk` at `offset`, which must be the stored length. The
+    /// chunk that completes the upload is checked against the declared
+    /// digest; on a mismatch the upload is deleted.
+    pub fn append(&self, id: &str, offset: u64, chunk: &[u8]) -> Result<UploadStatus> {
+        let _guard = self.lock();
+        let declaration = self.declaration(id)?;
+        let stored = self.status_of(id, &declaration)?;
+        if offset != stored.offset {
+            anyhow::bail!(UploadError::OffsetMismatch {
+                id: id.to_string(),
+                stored: stored.offset,
+                sent: offset,
+            });
+        }
+        if offset + chunk.len() as u64 > declaration.size {
+            anyhow::bail!(Coded::new(
+                ErrorCode::InvalidInput,
+                format!(
+                    "chunk ends at {}, past the declared size {}",
+                    offset + chunk.len() as u64,
+                    declaration.size
+                )
+            ));
+        }
+        if chunk.is_empty() {
+            return Ok(stored);
+        }
+        let end = offset + chunk.len() as u64;
+        self.storage
+            .write(&chunk_key(id, end), chunk)
+            .with_context(|| format!("storing upload {id}"))?;
+        let status = self.status_of(id, &declaration)?;
+        if status.complete {
+            if let Err(err) = self.verify(id, &declaration) {
+                self.remove(id);
+                return Err(err);
+            }
+        }
+        Ok(status)
+    }
+
+    /// Reads a complete upload, re-checking its digest.
+    pub fn read(&self, id: &str) -> Result<Vec<u8>> {
+        let _guard = self.lock();
+        let declaration = self.declaration(id)?;
+        if !self.status_of(id, &declaration)?.complete {
+            anyhow::bail!(UploadError::Incomplete { id: id.to_string() });
+        }
+        self.verify(id, &declaration)
+    }
+
+    /// Deletes an upload once its witness is no longer needed.
+    pub fn discard(&self, id: &str) {
+        let _guard = self.lock();
+        self.remove(id);
+    }
+
+    fn verify(&self, id: &str, declaration: &UploadDeclaration) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(declaration.size as usize);
+        for (key, _) in self.chunks(id)? {
+            let chunk = self
+                .storage
+                .read(&key)
+                .with_context(|| format!("reading upload {id}"))?;
+            bytes.extend_from_slice(&chunk);
+        }
+        let digest = digest(&bytes);
+        if digest != declaration.digest {
+            anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!(
+                    "upload {id} has digest {digest}, declared {}",
+                    declaration.digest
+                )
+            ));
+        }
+        Ok(bytes)
+    }
+
+    fn status_of(&self, id: &str, declaration: &UploadDeclaration) -> Result<UploadStatus> {
+        let offset = self.chunks(id)?.last().map_or(0, |(_, end)| *end);
+        Ok(UploadStatus {
+            id: id.to_string(),
+            size: declaration.size,
+            offset,
+            complete: offset == declaration.size,
+        })
+    }
+
+    fn declaration(&self, id: &str) -> Result<UploadDeclaration> {
+        let unknown = || UploadError::Unknown { id: id.to_string() };
+        if id.len() != 32 || !id.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            anyhow::bail!(unknown());
+        }
+        let bytes = self
+            .storage
+            .read(&declaration_key(id))
+            .map_err(|_| unknown())?;
+        from_json_slice(&bytes).with_context(|| format!("parsing upload {id}"))
+    }
+
+    /// Stored chunks of `id` with the offset each ends at, in upload order.
+    fn chunks(&self, id: &str) -> Result<Vec<(String, u64)>> {
+        let mut chunks = Vec::new();
+        for key in self.storage.list(id)? {
+            let end = key
+                .rsplit('/')
+                .next()
+                .and_then(|name| name.strip_suffix(".part"))
+                .and_then(|end| end.parse().ok());
+            if let Some(end) = end {
+                chunks.push((key, end));
+            }
+        }
+        chunks.sort_by_key(|(_, end)| *end);
+        Ok(chunks)
+    }
+
+    fn remove(&self, id: &str) {
+        for (key, _) in self.chunks(id).unwrap_or_default() {
+            let _ = self.storage.delete(&key);
+        }
+        let _ = self.storage.delete(&declaration_key(id));
+    }
+}
+
+fn declaration_key(id: &str) -> String {
+    format!("{id}.json")
+}
+
+/// Key of the chunk of `id` ending at offset `end`.
+fn chunk_key(id: &str, end: u64) -> String {
+    format!("{id}/{end:020}.part")
+}
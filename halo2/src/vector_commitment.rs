@@ -0,0 +1,68 @@
+//! Poseidon commitments to the vector matrices.
+//!
+//! By default `foldedCommitment` and `pqCommitment` are blake3 digests that
+//! the circuit maps onto field elements and copies through, so a proof says
+//! nothing about which vectors they commit to. A circuit keyed with
+//! `vectorCommitments` instead hashes the laid-out folded and pq rows
+//! in-circuit, `Poseidon(rows..)` under [`VECTOR_COMMITMENT_DOMAIN`], and
+//! constrains the digests to equal the two public values, which are then
+//! canonical field elements.
+//!
+//! The commitments are binding but not hiding; see [`crate::hiding`] for
+//! blinded ones.
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+
+use crate::{
+    bytes::Hash256,
+    errors::{Coded, ErrorCode},
+    hiding::{FOLDED_COMMITMENT_SLOT, PQ_COMMITMENT_SLOT},
+    io::WitnessData,
+    poseidon::{domain_capacity, hash_with_capacity, VECTOR_COMMITMENT_DOMAIN},
+};
+
+/// Commitment to `rows`, matching the in-circuit hash over the rows
+/// flattened row by row.
+pub fn vector_commitment(rows: &[Vec<Fr>]) -> Fr {
+    let inputs: Vec<Fr> = rows.iter().flatten().copied().collect();
+    hash_with_capacity(
+        domain_capacity(VECTOR_COMMITMENT_DOMAIN, inputs.len()),
+        &inputs,
+    )
+}
+
+/// `foldedCommitment` and `pqCommitment` of a witness.
+pub fn vector_commitments(witness: &WitnessData) -> Result<[Hash256; 2]> {
+    let (folded, pq) = witness.field_vectors()?;
+    Ok([
+        Hash256::from_field(&vector_commitment(&folded)),
+        Hash256::from_field(&vector_commitment(&pq)),
+    ])
+}
+
+/// Checks that `values` hold the commitments of `folded` and `pq`.
+pub fn check_vector_commitments(folded: &[Vec<Fr>], pq: &[Vec<Fr>], values: &[Fr]) -> Result<()> {
+    for (name, slot, commitment) in [
+        (
+            "foldedCommitment",
+            FOLDED_COMMITMENT_SLOT,
+            vector_commitment(folded),
+        ),
+        ("pqCommitment", PQ_COMMITMENT_SLOT, vector_commitment(pq)),
+    ] {
+        let value = values
+            .get(slot)
+            .with_context(|| format!("public values missing {name}"))?;
+        if *value != commitment {
+            anyhow::bail!(Coded::new(
+                ErrorCode::CommitmentMismatch,
+                format!(
+                    "{name} does not match the witness vectors (expected {})",
+                    Hash256::from_field(&commitment)
+                )
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,319 @@
+//! Proof verification.
+//!
+//! [`verify_proof_bytes`] is the in-process counterpart of the `verifier`
+//! binary: it loads a key config and returns a [`VerificationReport`] with
+//! the instances, transcript and timings, so an integrator can verify
+//! without shelling out. [`VerifierKeys`] keeps loaded keys for verifying
+//! many proofs.
+
+use std::{
+    io::Read,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use halo2_proofs::{
+    plonk::{verify_proof, Error, VerifyingKey},
+    poly::commitment::ParamsProver,
+    poly::kzg::{
+        commitment::{KZGCommitmentScheme, ParamsKZG},
+        multiopen::VerifierGWC,
+        strategy::SingleStrategy,
+    },
+    transcript::{Blake2bRead, Challenge255, Keccak256Read, TranscriptReadBuffer},
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+
+use crate::{
+    circuit::{FoldedCircuit, FoldedParams},
+    container::{is_container, ProofContainer},
+    errors::{Coded, ErrorCode},
+    keys::{
+        load_params_and_vk_with_setup, read_any_params, read_circuit_k, read_circuit_params,
+        read_verifier_bundle, KEY_FORMAT,
+    },
+    proof_size::{breakdown, OpeningScheme},
+    prove::is_simulated_proof,
+    public_inputs::ParsedPublicInputs,
+    srs::load_srs,
+    transcript::{PoseidonRead, TranscriptKind},
+};
+
+/// Outcome of a successful [`verify_proof_bytes`] or
+/// [`VerifierKeys::verify_bytes`].
+#[derive(Debug, Clone)]
+pub struct VerificationReport {
+    /// Instance column values the proof verified against.
+    pub instances: Vec<Fr>,
+    pub transcript: TranscriptKind,
+    /// The proof came in a [`ProofContainer`].
+    pub container: bool,
+    /// Maximum reconstruction error the proof bounds every vector by, for
+    /// keys with `slaBound`.
+    pub sla_bound: Option<f64>,
+    pub timings: VerifyTimings,
+}
+
+/// Wall time of the steps of one verification.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerifyTimings {
+    /// Loading or regenerating the keys; zero with already loaded keys.
+    pub keys: Duration,
+    pub verify: Duration,
+}
+
+/// Verifies `proof` for `public_inputs` under the key config at `vk_path`,
+/// built on the trusted setup at `setup` (see [`crate::srs`]); only keys
+/// derived from a seed load without one.
+///
+/// A [`ProofContainer`] is checked against the keys and verified under its
+/// embedded transcript; a bare proof is verified under `transcript`, by
+/// default blake2b. See [`VerifierKeys::verify_bytes`].
+pub fn verify_proof_bytes(
+    vk_path: &Path,
+    setup: Option<&Path>,
+    proof: &[u8],
+    public_inputs: &ParsedPublicInputs,
+    transcript: Option<TranscriptKind>,
+) -> Result<VerificationReport> {
+    let started = Instant::now();
+    let keys = VerifierKeys::from_config_with_setup(vk_path, setup)?;
+    let loaded = started.elapsed();
+    let mut report = keys.verify_bytes(proof, public_inputs, transcript)?;
+    report.timings.keys = loaded;
+    Ok(report)
+}
+
+/// Folded-circuit verifier keys, from a key config or a verifier bundle.
+pub struct VerifierKeys {
+    pub circuit: FoldedParams,
+    pub params: ParamsKZG<Bn256>,
+    pub vk: VerifyingKey<G1Affine>,
+}
+
+impl VerifierKeys {
+    /// Regenerates the keys from a key config (or reads its serialized artifacts).
+    pub fn from_config(verifying_path: &Path) -> Result<Self> {
+        Self::from_config_with_setup(verifying_path, None)
+    }
+
+    /// [`VerifierKeys::from_config`] for keys built on the trusted setup at
+    /// `setup` (see [`crate::srs`]).
+    pub fn from_config_with_setup(verifying_path: &Path, setup: Option<&Path>) -> Result<Self> {
+        let circuit = read_circuit_params(verifying_path)?;
+        let setup = setup
+            .map(|path| load_srs(path, read_circuit_k(verifying_path)?))
+            .transpose()?;
+        let (params, vk) = load_params_and_vk_with_setup(
+            verifying_path,
+            &FoldedCircuit::blank_with(&circuit),
+            setup.as_ref(),
+        )?;
+        Ok(Self {
+            circuit,
+            params,
+            vk,
+        })
+    }
+
+    /// Reads a bundle written by `yysfold keys export-verifier`; no setup runs.
+    pub fn from_bundle(bundle_path: &Path) -> Result<Self> {
+        let bundle = read_verifier_bundle::<FoldedParams>(bundle_path)?;
+        let (params, vk) = bundle.load(&FoldedCircuit::blank_with(&bundle.circuit))?;
+        Ok(Self {
+            circuit: bundle.circuit,
+            params,
+            vk,
+        })
+    }
+
+    /// Reads serialized params (full, or the verifier params written by
+    /// `yysfold keys export-verifier --params-output`) and a verifying key
+    /// (the `.vk` artifact of `yysfold keys migrate`) for `circuit`,
+    /// typically the layout embedded in a [`crate::container::ProofContainer`].
+    pub fn from_raw(params: &[u8], vk: &[u8], circuit: FoldedParams) -> Result<Self> {
+        let params = read_any_params(params).context("reading params")?;
+        let vk = VerifyingKey::read::<_, FoldedCircuit>(&mut &vk[..], KEY_FORMAT, circuit.clone())
+            .context("reading verifying key")?;
+        Ok(Self {
+            circuit,
+            params,
+            vk,
+        })
+    }
+
+    /// Length of every proof made with these keys.
+    pub fn proof_bytes(&self) -> usize {
+        expected_proof_bytes(&self.vk)
+    }
+
+    pub fn verify(&self, instances: &[Fr], proof: &[u8]) -> Result<()> {
+        verify_with_keys(&self.params, &self.vk, instances, proof)
+    }
+
+    /// [`Self::verify`] for a proof made under `transcript`.
+    pub fn verify_in(
+        &self,
+        instances: &[Fr],
+        proof: &[u8],
+        transcript: TranscriptKind,
+    ) -> Result<()> {
+        verify_with_transcript(&self.params, &self.vk, instances, proof, transcript)
+    }
+
+    /// Verifies a bare proof or a [`ProofContainer`] for `public_inputs`.
+    /// `transcript` defaults to the container's, or blake2b for a bare
+    /// proof; a container made under another transcript is rejected.
+    pub fn verify_bytes(
+        &self,
+        proof: &[u8],
+        public_inputs: &ParsedPublicInputs,
+        transcript: Option<TranscriptKind>,
+    ) -> Result<VerificationReport> {
+        let started = Instant::now();
+        let (instances, transcript, container) = if is_container(proof) {
+            let container = ProofContainer::from_bytes(proof)?;
+            if let Some(transcript) = transcript.filter(|kind| *kind != container.header.transcript)
+            {
+                anyhow::bail!(Coded::new(
+                    ErrorCode::Incompatible,
+                    format!(
+                        "proof container was made under the {} transcript, not {transcript}",
+                        container.header.transcript
+                    )
+                ));
+            }
+            container.verify(public_inputs, self)?;
+            (
+                public_inputs.to_instances(&container.header.circuit)?,
+                container.header.transcript,
+                true,
+            )
+        } else {
+            let instances = public_inputs.to_instances(&self.circuit)?;
+            let transcript = transcript.unwrap_or_default();
+            self.verify_in(&instances, proof, transcript)?;
+            (instances, transcript, false)
+        };
+        Ok(VerificationReport {
+            instances,
+            transcript,
+            container,
+            sla_bound: public_inputs.sla_bound.filter(|_| self.circuit.sla_bound),
+            timings: VerifyTimings {
+                keys: Duration::ZERO,
+                verify: started.elapsed(),
+            },
+        })
+    }
+}
+
+/// Length of a proof for `vk`. The transcript is a fixed sequence of points
+/// and scalars, so every valid proof has exactly this many bytes.
+pub fn expected_proof_bytes(vk: &VerifyingKey<G1Affine>) -> usize {
+    breakdown(vk.cs(), OpeningScheme::Gwc).total_bytes
+}
+
+/// Verifies a single folded-circuit proof against already loaded keys.
+pub fn verify_with_keys(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    instances: &[Fr],
+    proof: &[u8],
+) -> Result<()> {
+    verify_with_transcript(params, vk, instances, proof, TranscriptKind::default())
+}
+
+/// [`verify_with_keys`] for a proof made under `transcript`.
+///
+/// The proof must be exactly [`expected_proof_bytes`] long and the transcript
+/// must consume all of it; anything else fails with
+/// [`ErrorCode::MalformedProof`] before or instead of a verification error.
+pub fn verify_with_transcript(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    instances: &[Fr],
+    proof: &[u8],
+    transcript: TranscriptKind,
+) -> Result<()> {
+    if is_simulated_proof(proof) {
+        anyhow::bail!(Coded::new(
+            ErrorCode::VerificationFailed,
+            "simulated proof: produced with --simulate, it proves nothing"
+        ));
+    }
+    let expected = expected_proof_bytes(vk);
+    if proof.len() != expected {
+        anyhow::bail!(Coded::new(
+            ErrorCode::MalformedProof,
+            format!(
+                "proof is {} bytes, these keys produce {expected}-byte proofs",
+                proof.len()
+            )
+        ));
+    }
+    let mut remaining = proof;
+    let verified = match transcript {
+        TranscriptKind::Blake2b => {
+            verify_from::<_, Blake2bRead<_, _, _>>(params, vk, instances, &mut remaining)
+        }
+        TranscriptKind::Keccak256 => {
+            verify_from::<_, Keccak256Read<_, _, _>>(params, vk, instances, &mut remaining)
+        }
+        TranscriptKind::Poseidon => {
+            verify_from::<_, PoseidonRead<_>>(params, vk, instances, &mut remaining)
+        }
+    };
+    if let Err(err) = verified {
+        let code = match err {
+            // Bytes that do not decode as the points and scalars expected.
+            Error::Transcript(_) => ErrorCode::MalformedProof,
+            _ => ErrorCode::VerificationFailed,
+        };
+        anyhow::bail!(Coded::new(code, err.to_string()));
+    }
+    if !remaining.is_empty() {
+        anyhow::bail!(Coded::new(
+            ErrorCode::MalformedProof,
+            format!(
+                "{} trailing bytes after the proof transcript",
+                remaining.len()
+            )
+        ));
+    }
+    Ok(())
+}
+
+/// Runs `verify_proof` over the transcript read from `reader`.
+fn verify_from<R, T>(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    instances: &[Fr],
+    reader: R,
+) -> Result<(), Error>
+where
+    R: Read,
+    T: TranscriptReadBuffer<R, G1Affine, Challenge255<G1Affine>>,
+{
+    let instance_refs: Vec<&[Fr]> = vec![instances];
+    let circuit_instances: Vec<&[&[Fr]]> = vec![&instance_refs[..]];
+
+    let params_verifier = params.verifier_params();
+    let strategy = SingleStrategy::new(params_verifier);
+    let mut transcript = T::init(reader);
+
+    verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierGWC<'_, Bn256>,
+        Challenge255<G1Affine>,
+        T,
+        SingleStrategy<'_, Bn256>,
+    >(
+        params_verifier,
+        vk,
+        strategy,
+        &circuit_instances,
+        &mut transcript,
+    )
+}
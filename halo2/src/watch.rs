@@ -0,0 +1,216 @@
+//! Proving witnesses as they land in a directory or storage prefix.
+//!
+//! A block is a pair `<name>.witness.json` + `<name>.public.json` directly
+//! under the watched prefix, and is proven to `<name>.proof` with its
+//! `.meta.json` sidecar next to it. A pair is picked up once both files
+//! exist and neither has changed for the debounce interval, so half-written
+//! uploads are not proven. Ready pairs are proven lowest block height first.
+//! A pair that fails to load or prove is moved under `quarantine/` with a
+//! `<name>.error.txt` explaining why, and the watcher moves on.
+//!
+//! The watcher only uses [`Storage`], so it works against any backend.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{
+    bytes::Hash256, cancel::CancellationToken, hashing::digest, io::decode_witness,
+    metadata::ProofMetadata, platform::from_json_slice, prover::Prover,
+    public_inputs::ParsedPublicInputs, storage::Storage,
+};
+
+const WITNESS_SUFFIX: &str = ".witness.json";
+const PUBLIC_SUFFIX: &str = ".public.json";
+const PROOF_SUFFIX: &str = ".proof";
+/// Prefix, under the watched one, that failed pairs are moved to.
+pub const QUARANTINE: &str = "quarantine";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    Proved {
+        name: String,
+        height: u64,
+        proof_key: String,
+        public_inputs_key: String,
+    },
+    Quarantined {
+        name: String,
+        error: String,
+    },
+}
+
+/// A pair seen but not yet proven, with the digests it had when first seen.
+#[derive(Debug, Clone)]
+struct Pending {
+    since: Instant,
+    digests: (Hash256, Hash256),
+}
+
+pub struct Watcher<'a> {
+    storage: &'a dyn Storage,
+    prefix: String,
+    debounce: Duration,
+    pending: BTreeMap<String, Pending>,
+}
+
+impl<'a> Watcher<'a> {
+    pub fn new(storage: &'a dyn Storage, prefix: &str, debounce: Duration) -> Self {
+        Self {
+            storage,
+            prefix: prefix.trim_end_matches('/').to_string(),
+            debounce,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Key of `name` + `suffix` under the watched prefix.
+    fn key(&self, name: &str, suffix: &str) -> String {
+        if self.prefix.is_empty() {
+            format!("{name}{suffix}")
+        } else {
+            format!("{}/{name}{suffix}", self.prefix)
+        }
+    }
+
+    /// Names of the unproven pairs directly under the prefix.
+    fn unproven(&self) -> Result<Vec<String>> {
+        let listed = self.storage.list(&self.prefix)?;
+        let mut names = Vec::new();
+        for key in &listed {
+            let relative = key
+                .strip_prefix(&self.prefix)
+                .unwrap_or(key)
+                .trim_start_matches('/');
+            let Some(name) = relative.strip_suffix(WITNESS_SUFFIX) else {
+                continue;
+            };
+            if name.contains('/') {
+                continue;
+            }
+            if listed.contains(&self.key(name, PUBLIC_SUFFIX))
+                && !listed.contains(&self.key(name, PROOF_SUFFIX))
+            {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Proves every pair that has been stable for the debounce interval and
+    /// returns what happened to each, in proving order.
+    pub fn poll(&mut self, prover: &Prover, cancel: &CancellationToken) -> Result<Vec<WatchEvent>> {
+        let now = Instant::now();
+        let names = self.unproven()?;
+        self.pending.retain(|name, _| names.contains(name));
+        let mut ready = Vec::new();
+        for name in names {
+            let witness_bytes = self.storage.read(&self.key(&name, WITNESS_SUFFIX))?;
+            let public_bytes = self.storage.read(&self.key(&name, PUBLIC_SUFFIX))?;
+            let digests = (digest(&witness_bytes), digest(&public_bytes));
+            let pending = self.pending.entry(name.clone()).or_insert(Pending {
+                since: now,
+                digests,
+            });
+            if pending.digests != digests {
+                *pending = Pending {
+                    since: now,
+                    digests,
+                };
+            }
+            if now.duration_since(pending.since) < self.debounce {
+                continue;
+            }
+            // Unparseable public inputs sort first and are quarantined.
+            let height = from_json_slice::<ParsedPublicInputs>(&public_bytes)
+                .map(|public_inputs| public_inputs.block_height)
+                .ok();
+            ready.push((height, name, witness_bytes, public_bytes));
+        }
+        ready.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+
+        let mut events = Vec::with_capacity(ready.len());
+        for (_, name, witness_bytes, public_bytes) in ready {
+            cancel.check("watch")?;
+            self.pending.remove(&name);
+            let event = match self.prove(prover, &name, &witness_bytes, &public_bytes, cancel) {
+                Ok(event) => event,
+                Err(err) if cancel.is_cancelled() => return Err(err),
+                Err(err) => {
+                    let error = format!("{err:#}");
+                    self.quarantine(&name, &error)?;
+                    WatchEvent::Quarantined { name, error }
+                }
+            };
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    fn prove(
+        &self,
+        prover: &Prover,
+        name: &str,
+        witness_bytes: &[u8],
+        public_bytes: &[u8],
+        cancel: &CancellationToken,
+    ) -> Result<WatchEvent> {
+        let witness_key = self.key(name, WITNESS_SUFFIX);
+        let witness = decode_witness(witness_bytes, None, &witness_key)?;
+        let public_inputs: ParsedPublicInputs = from_json_slice(public_bytes)?;
+        let output = prover.prove_with(&witness, &public_inputs, cancel)?;
+        let proof_key = self.key(name, PROOF_SUFFIX);
+        let metadata = ProofMetadata::from(output.metadata);
+        self.storage.write(
+            &format!("{proof_key}.meta.json"),
+            &serde_json::to_vec_pretty(&metadata)?,
+        )?;
+        // The proof goes last: its presence marks the pair as done.
+        self.storage.write(&proof_key, &output.proof)?;
+        Ok(WatchEvent::Proved {
+            name: name.to_string(),
+            height: public_inputs.block_height,
+            proof_key,
+            public_inputs_key: self.key(name, PUBLIC_SUFFIX),
+        })
+    }
+
+    /// Moves the pair under [`QUARANTINE`] next to an error report.
+    fn quarantine(&self, name: &str, error: &str) -> Result<()> {
+        let quarantined = |suffix: &str| {
+            if self.prefix.is_empty() {
+                format!("{QUARANTINE}/{name}{suffix}")
+            } else {
+                format!("{}/{QUARANTINE}/{name}{suffix}", self.prefix)
+            }
+        };
+        for suffix in [WITNESS_SUFFIX, PUBLIC_SUFFIX] {
+            let key = self.key(name, suffix);
+            let bytes = self.storage.read(&key)?;
+            self.storage.write(&quarantined(suffix), &bytes)?;
+            self.storage.delete(&key)?;
+        }
+        self.storage
+            .write(&quarantined(".error.txt"), format!("{error}\n").as_bytes())
+    }
+
+    /// Polls every `interval` until `cancel` fires, reporting each event.
+    pub fn run(
+        &mut self,
+        prover: &Prover,
+        interval: Duration,
+        cancel: &CancellationToken,
+        mut on_event: impl FnMut(&WatchEvent),
+    ) -> Result<()> {
+        while !cancel.is_cancelled() {
+            for event in self.poll(prover, cancel)? {
+                on_event(&event);
+            }
+            std::thread::sleep(interval);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+//! Salted commitment to a block's full witness, for audits that open rows.
+//!
+//! A circuit keyed with `witnessCommitment` exposes a Poseidon Merkle root
+//! over one leaf per vector, `hash_leaf([salt, folded.., pq..])`, as
+//! `witnessCommitment`. Row salts come from the witness's secret `auditSalt`
+//! as `hash_node(auditSalt, row)`, so the published root reveals nothing
+//! about the rows. Later, an auditor handed a [`RowOpening`] (the row, its
+//! salt and Merkle path) checks it against the already published
+//! `witnessCommitment` without a re-prove. Opening one row discloses that
+//! row's salt only.
+//!
+//! A witness carrying a `rowPermutation` commits its leaves in shuffled
+//! order, see [`crate::permutation`].
+
+use anyhow::{Context, Result};
+use halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bytes::Hash256,
+    errors::{Coded, ErrorCode},
+    fixed_point::resolve_scale,
+    io::WitnessData,
+    merkle,
+    opening::{resolve_path, PathStep},
+    permutation::committed_rows,
+    poseidon::{hash_leaf, hash_node},
+};
+
+/// Salt of every row of a block committed under `audit_salt`.
+pub fn row_salts(audit_salt: &Hash256, rows: usize) -> Vec<Fr> {
+    let seed = audit_salt.to_field_reduced();
+    (0..rows)
+        .map(|row| hash_node(seed, Fr::from(row as u64)))
+        .collect()
+}
+
+/// Leaf of one row: its salt, then the folded and pq components.
+pub fn row_leaf(salt: Fr, folded: &[Fr], pq: &[Fr]) -> Fr {
+    let mut values = Vec::with_capacity(1 + folded.len() + pq.len());
+    values.push(salt);
+    values.extend_from_slice(folded);
+    values.extend_from_slice(pq);
+    hash_leaf(&values)
+}
+
+/// Leaves for the field rows of a block, salted with `salts`.
+pub fn leaves(salts: &[Fr], folded: &[Vec<Fr>], pq: &[Vec<Fr>]) -> Vec<Fr> {
+    salts
+        .iter()
+        .zip(folded.iter().zip(pq))
+        .map(|(salt, (folded, pq))| row_leaf(*salt, folded, pq))
+        .collect()
+}
+
+/// The witness's `auditSalt`, which witness commitments need.
+pub fn audit_salt(witness: &WitnessData) -> Result<&Hash256> {
+    witness.audit_salt.as_ref().ok_or_else(|| {
+        Coded::new(
+            ErrorCode::InvalidInput,
+            "witness commitments need a secret auditSalt in the witness",
+        )
+        .into()
+    })
+}
+
+/// `witnessCommitment` of `witness`.
+pub fn witness_commitment(witness: &WitnessData) -> Result<Fr> {
+    let salts = row_salts(audit_salt(witness)?, witness.folded_vectors.len());
+    let (folded, pq) = witness.field_vectors()?;
+    let leaves = leaves(&salts, &folded, &pq);
+    Ok(merkle::root(committed_rows(witness, &leaves)))
+}
+
+/// One witness row opened against `witnessCommitment`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowOpening {
+    pub commitment: Hash256,
+    /// Leaf position in the committed tree; under a row permutation this is
+    /// not the row's index in the witness.
+    pub index: usize,
+    pub vector_count: usize,
+    pub folded_vector: Vec<f64>,
+    pub pq_vector: Vec<f64>,
+    /// Fixed-point scale of the row; absent means the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u64>,
+    /// This row's salt; other rows' salts stay secret.
+    pub salt: Hash256,
+    pub path: Vec<PathStep>,
+}
+
+/// Opens row `index` of `witness`.
+pub fn open_row(witness: &WitnessData, index: usize) -> Result<RowOpening> {
+    let count = witness.folded_vectors.len();
+    if index >= count {
+        anyhow::bail!(Coded::new(
+            ErrorCode::Usage,
+            format!("row index {index} out of range (block has {count} vectors)")
+        ));
+    }
+    let salts = row_salts(audit_salt(witness)?, count);
+    let positions: Vec<usize> = committed_rows(witness, &(0..count).collect::<Vec<_>>());
+    let position = positions
+        .iter()
+        .position(|&row| row == index)
+        .expect("committed order is a permutation of the rows");
+    let (folded, pq) = witness.field_vectors()?;
+    let levels = merkle::levels(committed_rows(witness, &leaves(&salts, &folded, &pq)));
+    let root = levels.last().expect("non-empty tree has a root level")[0];
+    let path = merkle::path(&levels, position)
+        .into_iter()
+        .map(|(sibling, is_right)| PathStep {
+            sibling: sibling.as_ref().map(Hash256::from_field),
+            is_right,
+        })
+        .collect();
+    Ok(RowOpening {
+        commitment: Hash256::from_field(&root),
+        index: position,
+        vector_count: count,
+        folded_vector: witness.folded_vectors[index].clone(),
+        pq_vector: witness.pq_vectors[index].clone(),
+        scale: witness.scale,
+        salt: Hash256::from_field(&salts[index]),
+        path,
+    })
+}
+
+/// Checks `opening` against `commitment`, the block's public
+/// `witnessCommitment`; the commitment embedded in the opening is only the
+/// opener's claim.
+pub fn verify_row_opening(opening: &RowOpening, commitment: Hash256) -> Result<()> {
+    let fixed_point = resolve_scale(None, opening.scale)?;
+    let row = |values: &[f64]| -> Vec<Fr> {
+        values
+            .iter()
+            .map(|v| fixed_point.saturating_field(*v))
+            .collect()
+    };
+    let salt = opening.salt.to_canonical_field().context("salt")?;
+    let leaf = row_leaf(salt, &row(&opening.folded_vector), &row(&opening.pq_vector));
+    let computed = resolve_path(leaf, opening.index, opening.vector_count, &opening.path)?;
+    if Hash256::from_field(&computed) != commitment {
+        anyhow::bail!(Coded::new(
+            ErrorCode::CommitmentMismatch,
+            format!(
+                "row {} opens to {} but the witness commitment is {commitment}",
+                opening.index,
+                Hash256::from_field(&computed)
+            )
+        ));
+    }
+    Ok(())
+}